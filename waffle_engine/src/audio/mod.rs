@@ -0,0 +1,400 @@
+// Waffle Engine Audio Module
+// Sound sources and listeners on entities. A `WaffleAudioSource` points at a
+// `.wav` or `.ogg` asset and plays when the game starts or when a
+// `PlayAudioEvent` asks it to. Spatial sources pan between the listener's ears
+// and fade out between their minimum and maximum distance from it.
+
+use bevy::audio::{DefaultSpatialScale, PlaybackMode, SpatialScale, Volume};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::core::components::EditorHidden;
+use crate::core::config::EngineConfig;
+use crate::core::play::{PlaySession, PlayState};
+use crate::core::random::WaffleRng;
+use crate::core::surface::{PhysicalSurface, SurfaceContactEvent, SurfaceContactKind};
+use crate::rendering::camera::CameraSettings;
+use crate::rendering::split_screen::LocalPlayers;
+
+/// Positions reach bevy's mixer scaled down by this much. Its own falloff
+/// only starts one scaled unit out, so within a kilometre the distances on
+/// `WaffleAudioSource` are the only attenuation.
+const AUDIO_SPATIAL_SCALE: f32 = 0.001;
+
+/// Play a sound from this entity
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaffleAudioSource {
+    /// Asset path, e.g. `sounds/door.ogg`
+    pub clip: String,
+    pub volume: f32,
+    pub looping: bool,
+    /// Pan and fade with the distance to the listener
+    pub spatial: bool,
+    /// Start as soon as the game starts playing
+    pub play_on_start: bool,
+    /// Full volume up to this distance
+    pub min_distance: f32,
+    /// Silent from this distance on
+    pub max_distance: f32,
+}
+
+impl Default for WaffleAudioSource {
+    fn default() -> Self {
+        Self {
+            clip: String::new(),
+            volume: 1.0,
+            looping: false,
+            spatial: true,
+            play_on_start: true,
+            min_distance: 1.0,
+            max_distance: 30.0,
+        }
+    }
+}
+
+impl WaffleAudioSource {
+    pub fn new(clip: impl Into<String>) -> Self {
+        Self {
+            clip: clip.into(),
+            ..default()
+        }
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Heard the same everywhere, e.g. music
+    pub fn non_spatial(mut self) -> Self {
+        self.spatial = false;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_distances(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Volume at `distance` from the listener, before the master volume
+    pub fn volume_at(&self, distance: f32) -> f32 {
+        if !self.spatial {
+            return self.volume;
+        }
+        let min = self.min_distance.max(0.0);
+        let range = (self.max_distance - min).max(f32::EPSILON);
+        let falloff = 1.0 - ((distance - min) / range).clamp(0.0, 1.0);
+        self.volume * falloff
+    }
+}
+
+/// Hear spatial sounds from this entity, usually a camera. Without one the
+/// main camera listens.
+#[derive(Component, Reflect, Clone, Debug)]
+pub struct WaffleAudioListener {
+    /// Distance between the ears
+    pub ear_gap: f32,
+}
+
+impl Default for WaffleAudioListener {
+    fn default() -> Self {
+        Self { ear_gap: 0.2 }
+    }
+}
+
+/// Start an entity's `WaffleAudioSource` from the beginning
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayAudioEvent {
+    pub entity: Entity,
+}
+
+impl PlayAudioEvent {
+    pub fn new(entity: Entity) -> Self {
+        Self { entity }
+    }
+}
+
+/// Stop an entity's `WaffleAudioSource`
+#[derive(Event, Clone, Copy, Debug)]
+pub struct StopAudioEvent {
+    pub entity: Entity,
+}
+
+impl StopAudioEvent {
+    pub fn new(entity: Entity) -> Self {
+        Self { entity }
+    }
+}
+
+/// Clips for footsteps and impacts on each surface, one picked at random per
+/// contact. Surfaces without clips of their own use the `Default` ones.
+#[derive(Resource, Clone, Debug)]
+pub struct SurfaceSounds {
+    pub footsteps: HashMap<PhysicalSurface, Vec<String>>,
+    pub impacts: HashMap<PhysicalSurface, Vec<String>>,
+    pub footstep_volume: f32,
+    pub impact_volume: f32,
+}
+
+impl Default for SurfaceSounds {
+    fn default() -> Self {
+        Self {
+            footsteps: HashMap::new(),
+            impacts: HashMap::new(),
+            footstep_volume: 0.6,
+            impact_volume: 0.9,
+        }
+    }
+}
+
+impl SurfaceSounds {
+    pub fn clips(&self, kind: SurfaceContactKind, surface: PhysicalSurface) -> &[String] {
+        let clips = match kind {
+            SurfaceContactKind::Footstep => &self.footsteps,
+            SurfaceContactKind::Impact => &self.impacts,
+        };
+        clips
+            .get(&surface)
+            .filter(|clips| !clips.is_empty())
+            .or_else(|| clips.get(&PhysicalSurface::Default))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// A sound entity that goes away once its clip has played
+#[derive(Component)]
+pub struct OneShotAudio;
+
+/// What bevy adds to an entity while it plays
+type PlayingAudio = (Handle<AudioSource>, PlaybackSettings, AudioSink, SpatialAudioSink);
+
+pub struct WaffleAudioPlugin;
+
+impl Plugin for WaffleAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DefaultSpatialScale(SpatialScale::new(AUDIO_SPATIAL_SCALE)))
+            .register_type::<WaffleAudioSource>()
+            .register_type::<WaffleAudioListener>()
+            .add_event::<PlayAudioEvent>()
+            .add_event::<StopAudioEvent>()
+            .init_resource::<SurfaceSounds>()
+            .add_systems(Update, (attach_default_audio_listener, sync_audio_listeners).chain())
+            .add_systems(
+                Update,
+                (play_surface_contact_sounds, stop_removed_audio_sources, handle_audio_events).chain(),
+            )
+            .add_systems(Update, pause_audio_with_session)
+            .add_systems(PostUpdate, attenuate_audio_sources.after(TransformSystem::TransformPropagate))
+            .add_systems(OnEnter(PlayState::Playing), play_audio_on_start)
+            .add_systems(OnExit(PlayState::Playing), stop_all_audio);
+    }
+}
+
+/// Let the main camera listen when nothing else does
+fn attach_default_audio_listener(
+    mut commands: Commands,
+    camera_settings: Res<CameraSettings>,
+    listeners: Query<(), With<WaffleAudioListener>>,
+) {
+    if !listeners.is_empty() {
+        return;
+    }
+    if let Some(mut camera) = camera_settings.main_camera_entity.and_then(|camera| commands.get_entity(camera)) {
+        camera.insert(WaffleAudioListener::default());
+    }
+}
+
+/// Give listeners the ears bevy mixes spatial sounds for
+fn sync_audio_listeners(
+    mut commands: Commands,
+    listeners: Query<(Entity, &WaffleAudioListener), Changed<WaffleAudioListener>>,
+    mut removed: RemovedComponents<WaffleAudioListener>,
+) {
+    for (entity, listener) in &listeners {
+        commands
+            .entity(entity)
+            .insert(SpatialListener::new(listener.ear_gap.max(0.01)));
+    }
+    for entity in removed.read() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<SpatialListener>();
+        }
+    }
+}
+
+fn handle_audio_events(
+    mut commands: Commands,
+    mut play_events: EventReader<PlayAudioEvent>,
+    mut stop_events: EventReader<StopAudioEvent>,
+    config: Res<EngineConfig>,
+    asset_server: Res<AssetServer>,
+    sources: Query<(&WaffleAudioSource, Has<OneShotAudio>)>,
+) {
+    for event in stop_events.read() {
+        if let Some(mut entity) = commands.get_entity(event.entity) {
+            entity.remove::<PlayingAudio>();
+        }
+    }
+    for event in play_events.read() {
+        let Ok((source, one_shot)) = sources.get(event.entity) else {
+            continue;
+        };
+        if !config.audio_enabled || source.clip.is_empty() {
+            if one_shot {
+                commands.entity(event.entity).despawn_recursive();
+            }
+            continue;
+        }
+        let mode = if one_shot {
+            PlaybackMode::Despawn
+        } else if source.looping {
+            PlaybackMode::Loop
+        } else {
+            PlaybackMode::Remove
+        };
+        // Dropping the old sink stops it; bevy starts the new one once loaded
+        commands.entity(event.entity).remove::<PlayingAudio>().insert((
+            asset_server.load::<AudioSource>(source.clip.clone()),
+            PlaybackSettings {
+                mode,
+                volume: Volume::new(0.0),
+                spatial: source.spatial,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn stop_removed_audio_sources(mut commands: Commands, mut removed: RemovedComponents<WaffleAudioSource>) {
+    for entity in removed.read() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<PlayingAudio>();
+        }
+    }
+}
+
+fn play_audio_on_start(sources: Query<(Entity, &WaffleAudioSource)>, mut play_events: EventWriter<PlayAudioEvent>) {
+    for (entity, source) in &sources {
+        if source.play_on_start {
+            play_events.send(PlayAudioEvent::new(entity));
+        }
+    }
+}
+
+fn stop_all_audio(mut commands: Commands, sources: Query<(Entity, Has<OneShotAudio>), With<WaffleAudioSource>>) {
+    for (entity, one_shot) in &sources {
+        if one_shot {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            commands.entity(entity).remove::<PlayingAudio>();
+        }
+    }
+}
+
+/// Play a clip for each footstep and impact from where it happened
+fn play_surface_contact_sounds(
+    mut commands: Commands,
+    sounds: Res<SurfaceSounds>,
+    mut rng: Local<Option<WaffleRng>>,
+    mut contacts: EventReader<SurfaceContactEvent>,
+    mut play_events: EventWriter<PlayAudioEvent>,
+) {
+    // Its own generator, so sounds don't shift the gameplay random sequence
+    let rng = rng.get_or_insert_with(|| WaffleRng::new(0x5EED));
+    for contact in contacts.read() {
+        let Some(clip) = rng.choose(sounds.clips(contact.kind, contact.surface)) else {
+            continue;
+        };
+        let volume = match contact.kind {
+            SurfaceContactKind::Footstep => sounds.footstep_volume,
+            SurfaceContactKind::Impact => sounds.impact_volume,
+        };
+        let mut source = WaffleAudioSource::new(clip.clone()).with_volume(volume).with_distances(1.0, 25.0);
+        source.play_on_start = false;
+        let entity = commands
+            .spawn((
+                source,
+                OneShotAudio,
+                TransformBundle::from_transform(Transform::from_translation(contact.point)),
+                EditorHidden,
+                Name::new("Surface Sound"),
+            ))
+            .id();
+        play_events.send(PlayAudioEvent::new(entity));
+    }
+}
+
+/// Hold game sounds while the game is paused
+fn pause_audio_with_session(
+    session: Res<PlaySession>,
+    sinks: Query<&AudioSink, With<WaffleAudioSource>>,
+    spatial_sinks: Query<&SpatialAudioSink, With<WaffleAudioSource>>,
+) {
+    if !session.is_changed() {
+        return;
+    }
+    let sinks = sinks
+        .iter()
+        .map(|sink| sink as &dyn AudioSinkPlayback)
+        .chain(spatial_sinks.iter().map(|sink| sink as &dyn AudioSinkPlayback));
+    for sink in sinks {
+        if session.paused {
+            sink.pause();
+        } else {
+            sink.play();
+        }
+    }
+}
+
+/// Fade every playing source by its distance to the nearest listener. With
+/// two or more split-screen players, their cameras are the listeners.
+fn attenuate_audio_sources(
+    global_volume: Res<GlobalVolume>,
+    players: Res<LocalPlayers>,
+    listeners: Query<&GlobalTransform, With<WaffleAudioListener>>,
+    transforms: Query<&GlobalTransform>,
+    sinks: Query<(&WaffleAudioSource, &GlobalTransform, &AudioSink)>,
+    spatial_sinks: Query<(&WaffleAudioSource, &GlobalTransform, &SpatialAudioSink)>,
+) {
+    let positions: Vec<Vec3> = if players.len() >= 2 {
+        players
+            .players()
+            .iter()
+            .filter_map(|player| transforms.get(player.camera).ok())
+            .map(GlobalTransform::translation)
+            .collect()
+    } else {
+        listeners.iter().map(GlobalTransform::translation).collect()
+    };
+    let master = global_volume.volume.get();
+
+    let sinks = sinks
+        .iter()
+        .map(|(source, transform, sink)| (source, transform, sink as &dyn AudioSinkPlayback))
+        .chain(
+            spatial_sinks
+                .iter()
+                .map(|(source, transform, sink)| (source, transform, sink as &dyn AudioSinkPlayback)),
+        );
+    for (source, transform, sink) in sinks {
+        let position = transform.translation();
+        let distance = positions
+            .iter()
+            .map(|listener| listener.distance(position))
+            .min_by(f32::total_cmp)
+            .unwrap_or(0.0);
+        let volume = source.volume_at(distance) * master;
+        if (sink.volume() - volume).abs() > 1e-4 {
+            sink.set_volume(volume);
+        }
+    }
+}
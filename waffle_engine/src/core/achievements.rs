@@ -0,0 +1,289 @@
+// Waffle Engine Stats and Achievements
+// Named stat counters and achievements that unlock when a stat reaches its
+// target. Both belong to the player's profile rather than a save game and are
+// stored under `profiles/<name>/stats.ron`. Platform services such as Steam
+// plug in as `AchievementBackend`s and are told about every change.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const ACHIEVEMENTS_FILE: &str = "assets/achievements.ron";
+pub const PROFILE_DIR: &str = "profiles";
+/// Seconds between saves of changed stats
+const STATS_SAVE_INTERVAL: f32 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AchievementDefinition {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Unlocks once this stat reaches `target`; achievements without a stat
+    /// are only unlocked by `Achievements::unlock`
+    #[serde(default)]
+    pub stat: Option<String>,
+    #[serde(default)]
+    pub target: f32,
+    /// Hide title and description until unlocked
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl AchievementDefinition {
+    pub fn load_all(path: impl AsRef<Path>) -> anyhow::Result<Vec<Self>> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+}
+
+/// Stats and unlocks of one profile, as stored on disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStats {
+    pub stats: HashMap<String, f32>,
+    pub unlocked: Vec<String>,
+}
+
+impl ProfileStats {
+    pub fn path(profile: &str) -> PathBuf {
+        PathBuf::from(PROFILE_DIR).join(profile).join("stats.ron")
+    }
+
+    pub fn load(profile: &str) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(Self::path(profile))?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, profile: &str) -> anyhow::Result<()> {
+        let path = Self::path(profile);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+/// Platform achievement service, e.g. Steam or a console SDK
+pub trait AchievementBackend: Send + Sync {
+    fn name(&self) -> &str;
+    fn set_stat(&mut self, stat: &str, value: f32);
+    fn unlock(&mut self, achievement: &str);
+    /// Push pending changes; called whenever the profile is saved
+    fn flush(&mut self) {}
+}
+
+#[derive(Resource)]
+pub struct Achievements {
+    pub profile: String,
+    pub definitions: Vec<AchievementDefinition>,
+    data: ProfileStats,
+    backends: Vec<Box<dyn AchievementBackend>>,
+    /// Stats changed since the last unlock check
+    changed_stats: Vec<String>,
+    /// Unlocked since the last update, still to be announced
+    pending_unlocks: Vec<String>,
+    dirty: bool,
+    since_save: f32,
+}
+
+impl Default for Achievements {
+    fn default() -> Self {
+        Self {
+            profile: "default".to_string(),
+            definitions: Vec::new(),
+            data: ProfileStats::default(),
+            backends: Vec::new(),
+            changed_stats: Vec::new(),
+            pending_unlocks: Vec::new(),
+            dirty: false,
+            since_save: 0.0,
+        }
+    }
+}
+
+impl Achievements {
+    pub fn stat(&self, name: &str) -> f32 {
+        self.data.stats.get(name).copied().unwrap_or(0.0)
+    }
+
+    pub fn stats(&self) -> &HashMap<String, f32> {
+        &self.data.stats
+    }
+
+    pub fn set_stat(&mut self, name: &str, value: f32) {
+        if self.data.stats.get(name) == Some(&value) {
+            return;
+        }
+        self.data.stats.insert(name.to_string(), value);
+        for backend in &mut self.backends {
+            backend.set_stat(name, value);
+        }
+        if !self.changed_stats.iter().any(|changed| changed == name) {
+            self.changed_stats.push(name.to_string());
+        }
+        self.dirty = true;
+    }
+
+    /// Add to a counter and return its new value
+    pub fn increment(&mut self, name: &str, amount: f32) -> f32 {
+        let value = self.stat(name) + amount;
+        self.set_stat(name, value);
+        value
+    }
+
+    /// Raise a stat to `value` if it is higher, e.g. for best scores
+    pub fn record_max(&mut self, name: &str, value: f32) {
+        if value > self.stat(name) {
+            self.set_stat(name, value);
+        }
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.data.unlocked.iter().any(|unlocked| unlocked == id)
+    }
+
+    pub fn unlock(&mut self, id: &str) {
+        if self.is_unlocked(id) {
+            return;
+        }
+        if !self.definitions.iter().any(|definition| definition.id == id) {
+            warn!("Unlocking undefined achievement \"{}\"", id);
+        }
+        self.data.unlocked.push(id.to_string());
+        self.pending_unlocks.push(id.to_string());
+        for backend in &mut self.backends {
+            backend.unlock(id);
+        }
+        self.dirty = true;
+    }
+
+    /// Progress towards a stat-based achievement, from 0 to 1
+    pub fn progress(&self, definition: &AchievementDefinition) -> f32 {
+        if self.is_unlocked(&definition.id) {
+            return 1.0;
+        }
+        match &definition.stat {
+            Some(stat) if definition.target > 0.0 => (self.stat(stat) / definition.target).clamp(0.0, 1.0),
+            _ => 0.0,
+        }
+    }
+
+    pub fn add_backend(&mut self, backend: impl AchievementBackend + 'static) {
+        let mut backend: Box<dyn AchievementBackend> = Box::new(backend);
+        sync_backend(backend.as_mut(), &self.data);
+        self.backends.push(backend);
+    }
+
+    pub fn backend_names(&self) -> Vec<&str> {
+        self.backends.iter().map(|backend| backend.name()).collect()
+    }
+
+    /// Switch to another profile, saving the current one first
+    pub fn load_profile(&mut self, profile: impl Into<String>) {
+        self.save();
+        self.profile = profile.into();
+        self.data = match ProfileStats::load(&self.profile) {
+            Ok(data) => data,
+            Err(_) if !ProfileStats::path(&self.profile).exists() => ProfileStats::default(),
+            Err(err) => {
+                error!("Failed to load stats for profile \"{}\": {}", self.profile, err);
+                ProfileStats::default()
+            }
+        };
+        self.changed_stats = self.data.stats.keys().cloned().collect();
+        self.pending_unlocks.clear();
+        for backend in &mut self.backends {
+            sync_backend(backend.as_mut(), &self.data);
+        }
+    }
+
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        match self.data.save(&self.profile) {
+            Ok(()) => self.dirty = false,
+            Err(err) => error!("Failed to save stats for profile \"{}\": {}", self.profile, err),
+        }
+        for backend in &mut self.backends {
+            backend.flush();
+        }
+        self.since_save = 0.0;
+    }
+}
+
+/// Bring a backend up to date with what a profile already has
+fn sync_backend(backend: &mut dyn AchievementBackend, data: &ProfileStats) {
+    for (stat, value) in &data.stats {
+        backend.set_stat(stat, *value);
+    }
+    for id in &data.unlocked {
+        backend.unlock(id);
+    }
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct AchievementUnlockedEvent {
+    pub id: String,
+    pub title: String,
+}
+
+pub fn load_achievements(mut achievements: ResMut<Achievements>) {
+    if Path::new(ACHIEVEMENTS_FILE).exists() {
+        match AchievementDefinition::load_all(ACHIEVEMENTS_FILE) {
+            Ok(definitions) => achievements.definitions = definitions,
+            Err(err) => warn!("Failed to load {}: {}", ACHIEVEMENTS_FILE, err),
+        }
+    }
+    let profile = achievements.profile.clone();
+    achievements.load_profile(profile);
+}
+
+/// Unlock achievements whose stat reached its target, announce unlocks and
+/// save changed stats now and then
+pub fn update_achievements(
+    time: Res<Time>,
+    mut achievements: ResMut<Achievements>,
+    mut events: EventWriter<AchievementUnlockedEvent>,
+) {
+    if !achievements.changed_stats.is_empty() {
+        let changed = std::mem::take(&mut achievements.changed_stats);
+        let reached: Vec<String> = achievements
+            .definitions
+            .iter()
+            .filter(|definition| {
+                definition.stat.as_ref().is_some_and(|stat| changed.contains(stat))
+                    && achievements.stat(definition.stat.as_deref().unwrap_or_default()) >= definition.target
+            })
+            .map(|definition| definition.id.clone())
+            .collect();
+        for id in reached {
+            achievements.unlock(&id);
+        }
+    }
+
+    let unlocked = std::mem::take(&mut achievements.pending_unlocks);
+    for id in &unlocked {
+        let title = achievements
+            .definitions
+            .iter()
+            .find(|definition| &definition.id == id)
+            .map_or_else(|| id.clone(), |definition| definition.title.clone());
+        info!("Achievement unlocked: {}", title);
+        events.send(AchievementUnlockedEvent { id: id.clone(), title });
+    }
+
+    achievements.since_save += time.delta_seconds();
+    if !unlocked.is_empty() || achievements.since_save >= STATS_SAVE_INTERVAL {
+        achievements.save();
+    }
+}
+
+pub fn save_achievements_on_exit(mut exit_events: EventReader<AppExit>, mut achievements: ResMut<Achievements>) {
+    if exit_events.read().next().is_some() {
+        achievements.save();
+    }
+}
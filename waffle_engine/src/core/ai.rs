@@ -0,0 +1,348 @@
+// Waffle Engine AI
+// Behavior trees: serializable node graphs ticked per agent, with leaf
+// actions and conditions registered by name from Rust or, through the
+// registry's script hook, from Lua
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Result of ticking a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeStatus {
+    Success,
+    Failure,
+    Running,
+}
+
+/// A node in a behavior tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BehaviorNode {
+    /// Runs children in order until one succeeds
+    Selector(Vec<BehaviorNode>),
+    /// Runs children in order until one fails
+    Sequence(Vec<BehaviorNode>),
+    /// Flips success and failure of its child
+    Inverter(Box<BehaviorNode>),
+    /// Registered condition; succeeds or fails immediately
+    Condition(String),
+    /// Registered action; may keep running across ticks
+    Action(String),
+    /// Runs for the given number of seconds, then succeeds
+    Wait(f32),
+}
+
+impl BehaviorNode {
+    pub fn label(&self) -> String {
+        match self {
+            BehaviorNode::Selector(_) => "Selector".to_string(),
+            BehaviorNode::Sequence(_) => "Sequence".to_string(),
+            BehaviorNode::Inverter(_) => "Inverter".to_string(),
+            BehaviorNode::Condition(name) => format!("? {name}"),
+            BehaviorNode::Action(name) => format!("! {name}"),
+            BehaviorNode::Wait(seconds) => format!("Wait {seconds:.1}s"),
+        }
+    }
+
+    pub fn children(&self) -> &[BehaviorNode] {
+        match self {
+            BehaviorNode::Selector(children) | BehaviorNode::Sequence(children) => children,
+            BehaviorNode::Inverter(child) => std::slice::from_ref(child.as_ref()),
+            _ => &[],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Option<&mut Vec<BehaviorNode>> {
+        match self {
+            BehaviorNode::Selector(children) | BehaviorNode::Sequence(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Number of nodes in this subtree, including itself
+    pub fn node_count(&self) -> usize {
+        1 + self.children().iter().map(BehaviorNode::node_count).sum::<usize>()
+    }
+}
+
+/// A named behavior tree, stored as RON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BehaviorTree {
+    pub name: String,
+    pub root: BehaviorNode,
+}
+
+impl Default for BehaviorTree {
+    fn default() -> Self {
+        Self {
+            name: "New Tree".to_string(),
+            root: BehaviorNode::Selector(Vec::new()),
+        }
+    }
+}
+
+impl BehaviorTree {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+/// Per-agent key/value memory shared by the tree's leaves
+#[derive(Debug, Clone, Default)]
+pub struct Blackboard {
+    pub values: HashMap<String, f32>,
+    pub flags: HashMap<String, bool>,
+}
+
+/// Runs a behavior tree on the entity it is attached to
+#[derive(Component, Clone)]
+pub struct BehaviorTreeAgent {
+    pub tree: Arc<BehaviorTree>,
+    pub tick_interval: f32,
+    pub blackboard: Blackboard,
+    pub last_status: Option<NodeStatus>,
+    since_tick: f32,
+    /// Per-node memory indexed in pre-order: running child index or wait time
+    memory: Vec<f32>,
+}
+
+impl BehaviorTreeAgent {
+    pub fn new(tree: BehaviorTree) -> Self {
+        Self {
+            tree: Arc::new(tree),
+            tick_interval: 0.0,
+            blackboard: Blackboard::default(),
+            last_status: None,
+            since_tick: 0.0,
+            memory: Vec::new(),
+        }
+    }
+
+    pub fn with_tick_interval(mut self, seconds: f32) -> Self {
+        self.tick_interval = seconds.max(0.0);
+        self
+    }
+}
+
+/// Context handed to leaf callbacks
+pub struct BehaviorContext<'a> {
+    pub entity: Entity,
+    pub world: &'a mut World,
+    pub blackboard: &'a mut Blackboard,
+    pub delta: f32,
+}
+
+pub type BehaviorLeaf = Arc<dyn Fn(&mut BehaviorContext) -> NodeStatus + Send + Sync>;
+
+/// Whether a leaf is an action or a condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeafKind {
+    Action,
+    Condition,
+}
+
+impl LeafKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            LeafKind::Action => "action",
+            LeafKind::Condition => "condition",
+        }
+    }
+}
+
+/// Runs a leaf no Rust callback is registered for; `None` if the script side
+/// doesn't know it either
+pub type ScriptLeafRunner = fn(LeafKind, &str, &mut BehaviorContext) -> Option<NodeStatus>;
+
+/// Named leaf actions and conditions available to behavior trees
+#[derive(Resource, Default)]
+pub struct BehaviorRegistry {
+    pub actions: HashMap<String, BehaviorLeaf>,
+    pub conditions: HashMap<String, BehaviorLeaf>,
+    /// Set by the Lua runtime so trees can use leaves scripts register
+    pub script_leaves: Option<ScriptLeafRunner>,
+    /// Missing leaves already reported, so each is only warned about once
+    warned: HashSet<(LeafKind, String)>,
+}
+
+impl BehaviorRegistry {
+    pub fn register_action(
+        &mut self,
+        name: impl Into<String>,
+        action: impl Fn(&mut BehaviorContext) -> NodeStatus + Send + Sync + 'static,
+    ) {
+        self.actions.insert(name.into(), Arc::new(action));
+    }
+
+    pub fn register_condition(
+        &mut self,
+        name: impl Into<String>,
+        condition: impl Fn(&mut BehaviorContext) -> bool + Send + Sync + 'static,
+    ) {
+        self.conditions.insert(
+            name.into(),
+            Arc::new(move |context: &mut BehaviorContext| {
+                if condition(context) {
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Failure
+                }
+            }),
+        );
+    }
+
+    /// Run the named leaf, falling back to scripts; unknown leaves fail
+    fn run_leaf(&mut self, kind: LeafKind, name: &str, context: &mut BehaviorContext) -> NodeStatus {
+        let leaves = match kind {
+            LeafKind::Action => &self.actions,
+            LeafKind::Condition => &self.conditions,
+        };
+        if let Some(leaf) = leaves.get(name) {
+            return leaf(context);
+        }
+        if let Some(status) = self.script_leaves.and_then(|run| run(kind, name, context)) {
+            return status;
+        }
+        if self.warned.insert((kind, name.to_string())) {
+            warn!("Behavior tree {} '{}' is not registered", kind.name(), name);
+        }
+        NodeStatus::Failure
+    }
+}
+
+pub fn setup_behavior_registry(mut registry: ResMut<BehaviorRegistry>) {
+    registry.register_condition("HasTarget", |context| {
+        context.blackboard.flags.get("has_target").copied().unwrap_or(false)
+    });
+    registry.register_action("Idle", |_| NodeStatus::Success);
+}
+
+/// Tick every agent's tree. Exclusive so leaves can access the whole world.
+pub fn tick_behavior_trees(world: &mut World, mut due: Local<Vec<Entity>>) {
+    let delta = world.resource::<Time>().delta_seconds();
+    world.resource_scope(|world, mut registry: Mut<BehaviorRegistry>| {
+        let registry = registry.bypass_change_detection();
+
+        due.clear();
+        let mut agents = world.query::<(Entity, &mut BehaviorTreeAgent)>();
+        for (entity, mut agent) in agents.iter_mut(world) {
+            agent.since_tick += delta;
+            if agent.since_tick >= agent.tick_interval {
+                due.push(entity);
+            }
+        }
+
+        for &entity in due.iter() {
+            // Leaves get the world, so the agent's state is moved out while
+            // its tree runs rather than borrowed
+            let Some(mut agent) = world.get_mut::<BehaviorTreeAgent>(entity) else {
+                continue;
+            };
+            let elapsed = std::mem::take(&mut agent.since_tick);
+            let tree = agent.tree.clone();
+            let mut blackboard = std::mem::take(&mut agent.blackboard);
+            let mut memory = std::mem::take(&mut agent.memory);
+            let node_count = tree.root.node_count();
+            if memory.len() != node_count {
+                memory = vec![0.0; node_count];
+            }
+
+            let mut context = BehaviorContext {
+                entity,
+                world: &mut *world,
+                blackboard: &mut blackboard,
+                delta: elapsed,
+            };
+            let mut index = 0;
+            let status = tick_node(&tree.root, &mut index, &mut memory, registry, &mut context);
+
+            if let Some(mut agent) = world.get_mut::<BehaviorTreeAgent>(entity) {
+                agent.blackboard = blackboard;
+                agent.memory = memory;
+                agent.last_status = Some(status);
+            }
+        }
+    });
+}
+
+fn tick_node(
+    node: &BehaviorNode,
+    index: &mut usize,
+    memory: &mut [f32],
+    registry: &mut BehaviorRegistry,
+    context: &mut BehaviorContext,
+) -> NodeStatus {
+    let node_index = *index;
+    *index += 1;
+
+    match node {
+        BehaviorNode::Selector(children) | BehaviorNode::Sequence(children) => {
+            let stop_on = if matches!(node, BehaviorNode::Selector(_)) {
+                NodeStatus::Success
+            } else {
+                NodeStatus::Failure
+            };
+            let resume = memory[node_index] as usize;
+            let mut result = if stop_on == NodeStatus::Success {
+                NodeStatus::Failure
+            } else {
+                NodeStatus::Success
+            };
+            for (child_index, child) in children.iter().enumerate() {
+                if child_index < resume {
+                    *index += child.node_count();
+                    continue;
+                }
+                let status = tick_node(child, index, memory, registry, context);
+                if status == NodeStatus::Running {
+                    memory[node_index] = child_index as f32;
+                    // Skip the remaining siblings so indices stay aligned.
+                    *index += children[child_index + 1..]
+                        .iter()
+                        .map(BehaviorNode::node_count)
+                        .sum::<usize>();
+                    return NodeStatus::Running;
+                }
+                if status == stop_on {
+                    *index += children[child_index + 1..]
+                        .iter()
+                        .map(BehaviorNode::node_count)
+                        .sum::<usize>();
+                    result = status;
+                    break;
+                }
+            }
+            memory[node_index] = 0.0;
+            result
+        }
+        BehaviorNode::Inverter(child) => match tick_node(child, index, memory, registry, context) {
+            NodeStatus::Success => NodeStatus::Failure,
+            NodeStatus::Failure => NodeStatus::Success,
+            NodeStatus::Running => NodeStatus::Running,
+        },
+        BehaviorNode::Condition(name) => registry.run_leaf(LeafKind::Condition, name, context),
+        BehaviorNode::Action(name) => registry.run_leaf(LeafKind::Action, name, context),
+        BehaviorNode::Wait(seconds) => {
+            memory[node_index] += context.delta;
+            if memory[node_index] >= *seconds {
+                memory[node_index] = 0.0;
+                NodeStatus::Success
+            } else {
+                NodeStatus::Running
+            }
+        }
+    }
+}
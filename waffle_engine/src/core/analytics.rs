@@ -0,0 +1,319 @@
+// Waffle Engine Analytics
+// Opt-in gameplay telemetry for shipped games. Nothing is recorded until the
+// player opts in. Events are batched and handed to a background thread that
+// sends them through an `AnalyticsBackend`; batches that cannot be sent are
+// queued on disk and retried with the next one.
+//
+// The default backend posts JSON to the project's `https://` analytics
+// endpoint through the system's `curl`. For a vendor SDK, install a backend
+// with `Analytics::set_backend`.
+
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::core::project::ProjectSettings;
+
+pub const ANALYTICS_DIR: &str = "analytics";
+const CONSENT_FILE: &str = "analytics/consent.ron";
+const QUEUE_FILE: &str = "analytics/queue.jsonl";
+/// Events are sent once this many are waiting, or after `FLUSH_INTERVAL`
+const BATCH_SIZE: usize = 20;
+const FLUSH_INTERVAL: f32 = 30.0;
+/// Oldest queued events are dropped beyond this many
+const MAX_QUEUED_EVENTS: usize = 5000;
+/// How long quitting waits for the analytics thread before leaving it behind
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsEvent {
+    pub name: String,
+    pub properties: serde_json::Value,
+    /// Unix milliseconds
+    pub timestamp: u64,
+    /// Random id shared by all events of one run of the game
+    pub session: String,
+}
+
+/// Where analytics events go
+pub trait AnalyticsBackend: Send + 'static {
+    fn send(&mut self, events: &[AnalyticsEvent]) -> anyhow::Result<()>;
+}
+
+/// Posts each batch as a JSON array to an `https://` URL. Uses the `curl`
+/// command line tool, which ships with Windows, macOS and most Linux distros.
+pub struct HttpsJsonBackend {
+    url: String,
+}
+
+impl HttpsJsonBackend {
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let Some(rest) = url.strip_prefix("https://") else {
+            anyhow::bail!("analytics endpoints must use https://, got \"{url}\"");
+        };
+        if rest.split(['/', ':']).next().is_none_or(str::is_empty) {
+            anyhow::bail!("no host in \"{url}\"");
+        }
+        Ok(Self { url: url.to_string() })
+    }
+}
+
+impl AnalyticsBackend for HttpsJsonBackend {
+    fn send(&mut self, events: &[AnalyticsEvent]) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(events)?;
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--connect-timeout", "3", "--max-time", "5"])
+            // Never fall back to plain HTTP, even through a redirect
+            .args(["--proto", "=https", "--proto-redir", "=https"])
+            .args(["--header", "Content-Type: application/json", "--data-binary", "@-"])
+            .arg(&self.url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow::anyhow!("could not run curl: {err}"))?;
+        let written = child.stdin.take().map_or(Ok(()), |mut stdin| stdin.write_all(&body));
+        let output = child.wait_with_output()?;
+        written?;
+        if !output.status.success() {
+            anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+}
+
+/// What the analytics thread is asked to do, in order
+enum WorkerMessage {
+    /// Send a batch, along with anything queued on disk
+    Send(Vec<AnalyticsEvent>),
+    /// Queue a batch on disk for the next run without trying to send it
+    Queue(Vec<AnalyticsEvent>),
+    SetBackend(Box<dyn AnalyticsBackend>),
+    /// The player opted out; delete the queue
+    OptOut,
+}
+
+/// The analytics thread. It is the only code that touches the queue file, so
+/// batches it is still retrying can't race an opt-out or the final flush.
+struct AnalyticsWorker {
+    sender: Sender<WorkerMessage>,
+    /// Disconnects when the thread ends
+    finished: Receiver<()>,
+}
+
+#[derive(Resource)]
+pub struct Analytics {
+    enabled: bool,
+    session: String,
+    pending: Vec<AnalyticsEvent>,
+    since_flush: f32,
+    worker: Option<AnalyticsWorker>,
+    has_backend: bool,
+}
+
+impl Default for Analytics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            session: uuid::Uuid::new_v4().to_string(),
+            pending: Vec::new(),
+            since_flush: 0.0,
+            worker: None,
+            has_backend: false,
+        }
+    }
+}
+
+impl Analytics {
+    /// Whether the player has opted in
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record the player's choice. Opting out also drops everything not yet sent.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if let Err(err) = write_consent(enabled) {
+            warn!("Failed to store analytics consent: {}", err);
+        }
+        if !enabled {
+            self.pending.clear();
+            self.send_to_worker(WorkerMessage::OptOut);
+        }
+    }
+
+    /// Record an event, e.g. `analytics.event("level_complete", json!({ "time": 123 }))`
+    pub fn event(&mut self, name: impl Into<String>, properties: serde_json::Value) {
+        if !self.enabled {
+            return;
+        }
+        self.pending.push(AnalyticsEvent {
+            name: name.into(),
+            properties,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            session: self.session.clone(),
+        });
+    }
+
+    /// Send events through `backend` from now on
+    pub fn set_backend(&mut self, backend: impl AnalyticsBackend) {
+        self.has_backend = true;
+        self.send_to_worker(WorkerMessage::SetBackend(Box::new(backend)));
+    }
+
+    pub fn has_backend(&self) -> bool {
+        self.has_backend
+    }
+
+    /// Hand waiting events to the backend now
+    pub fn flush(&mut self) {
+        self.since_flush = 0.0;
+        if self.pending.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut self.pending);
+        self.send_to_worker(WorkerMessage::Send(batch));
+    }
+
+    /// Queue waiting events for the next run and give the analytics thread a
+    /// moment to finish. A send still stuck on the network is abandoned rather
+    /// than holding up the exit; its batch is lost.
+    pub fn shut_down(&mut self) {
+        if !self.pending.is_empty() {
+            let batch = std::mem::take(&mut self.pending);
+            self.send_to_worker(WorkerMessage::Queue(batch));
+        }
+        if let Some(worker) = self.worker.take() {
+            // Closing the channel ends the thread once it has drained it
+            drop(worker.sender);
+            if let Err(RecvTimeoutError::Timeout) = worker.finished.recv_timeout(SHUTDOWN_WAIT) {
+                warn!("Analytics are still being sent; quitting without them");
+            }
+        }
+    }
+
+    fn send_to_worker(&mut self, message: WorkerMessage) {
+        if self.worker.is_none() {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let (finished_sender, finished) = crossbeam_channel::bounded(0);
+            let spawned = std::thread::Builder::new()
+                .name("analytics".to_string())
+                .spawn(move || {
+                    // Dropped however the thread ends, panics included
+                    let _finished: Sender<()> = finished_sender;
+                    run_analytics_worker(receiver);
+                });
+            match spawned {
+                Ok(_) => self.worker = Some(AnalyticsWorker { sender, finished }),
+                Err(err) => {
+                    error!("Failed to start the analytics thread: {}", err);
+                    return;
+                }
+            }
+        }
+        if self.worker.as_ref().is_some_and(|worker| worker.sender.send(message).is_err()) {
+            warn!("The analytics thread has stopped; events are dropped");
+        }
+    }
+}
+
+fn run_analytics_worker(messages: Receiver<WorkerMessage>) {
+    let mut backend: Option<Box<dyn AnalyticsBackend>> = None;
+    for message in messages.iter() {
+        match message {
+            WorkerMessage::Send(batch) => {
+                // Without a backend, keep events for the next run
+                let Some(backend) = backend.as_mut() else {
+                    queue_events(&batch);
+                    continue;
+                };
+                let mut events = take_queue();
+                events.extend(batch);
+                if let Err(err) = backend.send(&events) {
+                    warn!("Failed to send {} analytics events, queued for later: {}", events.len(), err);
+                    queue_events(&events);
+                }
+            }
+            WorkerMessage::Queue(batch) => queue_events(&batch),
+            WorkerMessage::SetBackend(new_backend) => backend = Some(new_backend),
+            WorkerMessage::OptOut => {
+                let _ = std::fs::remove_file(QUEUE_FILE);
+            }
+        }
+    }
+}
+
+fn queue_events(events: &[AnalyticsEvent]) {
+    if let Err(err) = append_queue(events) {
+        warn!("Failed to queue analytics events: {}", err);
+    }
+}
+
+fn write_consent(enabled: bool) -> anyhow::Result<()> {
+    std::fs::create_dir_all(ANALYTICS_DIR)?;
+    std::fs::write(CONSENT_FILE, ron::ser::to_string(&enabled)?)?;
+    Ok(())
+}
+
+fn read_consent() -> bool {
+    std::fs::read_to_string(CONSENT_FILE)
+        .ok()
+        .and_then(|data| ron::de::from_str(&data).ok())
+        .unwrap_or(false)
+}
+
+/// Read and remove the offline queue
+fn take_queue() -> Vec<AnalyticsEvent> {
+    let Ok(data) = std::fs::read_to_string(QUEUE_FILE) else {
+        return Vec::new();
+    };
+    let _ = std::fs::remove_file(QUEUE_FILE);
+    data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+fn append_queue(events: &[AnalyticsEvent]) -> anyhow::Result<()> {
+    std::fs::create_dir_all(ANALYTICS_DIR)?;
+    let mut queued = if Path::new(QUEUE_FILE).exists() { take_queue() } else { Vec::new() };
+    queued.extend_from_slice(events);
+    let overflow = queued.len().saturating_sub(MAX_QUEUED_EVENTS);
+    let mut data = String::new();
+    for event in &queued[overflow..] {
+        data.push_str(&serde_json::to_string(event)?);
+        data.push('\n');
+    }
+    std::fs::write(QUEUE_FILE, data)?;
+    Ok(())
+}
+
+/// Load the player's consent and connect the project's endpoint, unless a
+/// plugin already installed a backend
+pub fn start_analytics(project_settings: Res<ProjectSettings>, mut analytics: ResMut<Analytics>) {
+    analytics.enabled = read_consent();
+    let endpoint = project_settings.analytics_endpoint.trim();
+    if analytics.has_backend() || endpoint.is_empty() {
+        return;
+    }
+    match HttpsJsonBackend::new(endpoint) {
+        Ok(backend) => analytics.set_backend(backend),
+        Err(err) => warn!("Analytics endpoint not usable: {}", err),
+    }
+}
+
+pub fn flush_analytics(time: Res<Time>, mut analytics: ResMut<Analytics>) {
+    analytics.since_flush += time.delta_seconds();
+    if analytics.pending.len() >= BATCH_SIZE || analytics.since_flush >= FLUSH_INTERVAL {
+        analytics.flush();
+    }
+}
+
+pub fn flush_analytics_on_exit(mut exit_events: EventReader<AppExit>, mut analytics: ResMut<Analytics>) {
+    if exit_events.read().next().is_some() {
+        analytics.shut_down();
+    }
+}
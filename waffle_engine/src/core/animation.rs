@@ -0,0 +1,237 @@
+// Waffle Engine Skeletal Animation
+// Plays the animation clips of imported models. A `WaffleAnimator` on a
+// model's root picks which of the clips in its glTF file plays, how fast and
+// whether it loops. The clips are gathered into an animation graph once the
+// file loads, and that graph drives the `AnimationPlayer` Bevy spawns inside
+// the model's scene. An `AnimationStateMachine` on the same entity takes over
+// choosing the clips from the animator.
+
+use bevy::animation::RepeatAnimation;
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::state_machine::AnimationStateMachine;
+
+/// Plays one of a model's animation clips
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaffleAnimator {
+    /// Name of the clip to play; the model's first clip when unset
+    pub clip: Option<String>,
+    pub playing: bool,
+    pub speed: f32,
+    pub looping: bool,
+    /// Names of the model's clips, once it has loaded
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub clips: Vec<String>,
+    /// Seconds into the current clip
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub time: f32,
+    /// Length of the current clip in seconds
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub duration: f32,
+    /// Jump to this many seconds into the clip on the next frame
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub seek: Option<f32>,
+}
+
+impl Default for WaffleAnimator {
+    fn default() -> Self {
+        Self {
+            clip: None,
+            playing: true,
+            speed: 1.0,
+            looping: true,
+            clips: Vec::new(),
+            time: 0.0,
+            duration: 0.0,
+            seek: None,
+        }
+    }
+}
+
+/// What an animator is bound to: the glTF file of its model, the graph built
+/// from its clips and the player the graph drives
+#[derive(Component)]
+pub struct AnimatorBinding {
+    scene: AssetId<Scene>,
+    gltf: Handle<Gltf>,
+    clip_names: Vec<String>,
+    clips: Vec<Handle<AnimationClip>>,
+    graph: Option<Handle<AnimationGraph>>,
+    nodes: Vec<AnimationNodeIndex>,
+    player: Option<Entity>,
+    active: Option<AnimationNodeIndex>,
+    was_finished: bool,
+}
+
+impl AnimatorBinding {
+    /// The graph node of the clip called `name`
+    pub fn clip_node(&self, name: &str) -> Option<AnimationNodeIndex> {
+        let index = self.clip_names.iter().position(|clip| clip == name)?;
+        self.nodes.get(index).copied()
+    }
+
+    /// The model's animation player, once its scene has spawned
+    pub fn player(&self) -> Option<Entity> {
+        self.player
+    }
+}
+
+/// Load the glTF file behind each animated model, again when its model changes
+pub fn bind_animators(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    animators: Query<(Entity, &Handle<Scene>, Option<&AnimatorBinding>), With<WaffleAnimator>>,
+) {
+    for (entity, scene, binding) in animators.iter() {
+        if binding.is_some_and(|binding| binding.scene == scene.id()) {
+            continue;
+        }
+        // Only models loaded from a file have clips to find
+        let Some(path) = scene.path() else {
+            continue;
+        };
+        commands.entity(entity).insert(AnimatorBinding {
+            scene: scene.id(),
+            gltf: asset_server.load(path.without_label().into_owned()),
+            clip_names: Vec::new(),
+            clips: Vec::new(),
+            graph: None,
+            nodes: Vec::new(),
+            player: None,
+            active: None,
+            was_finished: false,
+        });
+    }
+}
+
+/// List the clips of each loaded model and build a graph of them
+pub fn load_animator_clips(
+    gltfs: Res<Assets<Gltf>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+    mut animators: Query<(&mut WaffleAnimator, &mut AnimatorBinding)>,
+) {
+    for (mut animator, mut binding) in animators.iter_mut() {
+        if binding.graph.is_none() {
+            let Some(gltf) = gltfs.get(&binding.gltf) else {
+                continue;
+            };
+            // Named clips keep their names, in the order the file lists them
+            binding.clip_names = gltf
+                .animations
+                .iter()
+                .enumerate()
+                .map(|(index, clip)| {
+                    gltf.named_animations
+                        .iter()
+                        .find(|(_, named)| *named == clip)
+                        .map(|(name, _)| name.to_string())
+                        .unwrap_or_else(|| format!("Animation{index}"))
+                })
+                .collect();
+            binding.clips = gltf.animations.clone();
+            let mut graph = AnimationGraph::new();
+            let root = graph.root;
+            let nodes = graph.add_clips(gltf.animations.iter().cloned(), 1.0, root).collect();
+            binding.graph = Some(graphs.add(graph));
+            binding.nodes = nodes;
+            binding.player = None;
+            binding.active = None;
+        }
+        // Animators put back from a scene file or snapshot start without them
+        if animator.clips != binding.clip_names {
+            animator.bypass_change_detection().clips = binding.clip_names.clone();
+        }
+    }
+}
+
+/// Play each animator's clip on the model's animation player
+pub fn drive_animators(
+    mut commands: Commands,
+    clips: Res<Assets<AnimationClip>>,
+    mut animators: Query<(Entity, &mut WaffleAnimator, &mut AnimatorBinding, Has<AnimationStateMachine>)>,
+    children: Query<&Children>,
+    mut players: Query<(&mut AnimationPlayer, Option<&Handle<AnimationGraph>>)>,
+) {
+    for (entity, mut animator, mut binding, has_state_machine) in animators.iter_mut() {
+        let Some(graph) = binding.graph.clone() else {
+            continue;
+        };
+        // The player appears once the model's scene has spawned
+        if binding.player.is_none_or(|player| !players.contains(player)) {
+            binding.player = children.iter_descendants(entity).find(|child| players.contains(*child));
+            binding.active = None;
+        }
+        let Some(player_entity) = binding.player else {
+            continue;
+        };
+        let Ok((mut player, player_graph)) = players.get_mut(player_entity) else {
+            continue;
+        };
+        if player_graph != Some(&graph) {
+            commands.entity(player_entity).insert(graph);
+        }
+        if has_state_machine {
+            binding.active = None;
+            continue;
+        }
+
+        let index = match &animator.clip {
+            Some(name) => binding.clip_names.iter().position(|clip| clip == name),
+            None => (!binding.nodes.is_empty()).then_some(0),
+        };
+        let node = index.map(|index| binding.nodes[index]);
+        if node != binding.active {
+            player.stop_all();
+            if let Some(node) = node {
+                player.start(node);
+            }
+            binding.active = node;
+            binding.was_finished = false;
+        }
+        let (Some(index), Some(node)) = (index, node) else {
+            continue;
+        };
+        let Some(active) = player.animation_mut(node) else {
+            continue;
+        };
+
+        active.set_speed(animator.speed).set_repeat(if animator.looping {
+            RepeatAnimation::Forever
+        } else {
+            RepeatAnimation::Never
+        });
+        if let Some(time) = animator.seek {
+            animator.seek = None;
+            active.replay();
+            active.seek_to(time);
+            binding.was_finished = false;
+        }
+        let finished = active.is_finished();
+        if finished && !binding.was_finished {
+            // A clip that doesn't loop stops at its end
+            animator.playing = false;
+        } else if finished && animator.playing {
+            // Play pressed again after the end starts over
+            active.replay();
+        }
+        if animator.playing {
+            active.resume();
+        } else {
+            active.pause();
+        }
+        binding.was_finished = active.is_finished();
+
+        let time = active.seek_time();
+        let duration = clips.get(&binding.clips[index]).map(AnimationClip::duration).unwrap_or(0.0);
+        let animator = animator.bypass_change_detection();
+        animator.time = time;
+        animator.duration = duration;
+    }
+}
@@ -0,0 +1,242 @@
+// Waffle Engine Asset Import Settings
+// Every asset can have a `.meta` sidecar next to it, written by the editor the
+// first time it sees the asset. The sidecar holds a UUID that stays with the
+// asset through moves and renames, and the options it is imported with:
+// whether an image holds sRGB color or raw data, how it is filtered, and how
+// much a model is scaled. Bevy's own meta files are turned off, so the options
+// are applied here as images, meshes and scenes finish loading. Sidecars are
+// read from the assets folder, not from mounted bundles.
+
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::primitives::Aabb;
+use bevy::render::texture::ImageSampler;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use crate::core::config::EngineConfig;
+
+pub const META_EXTENSION: &str = "meta";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureFiltering {
+    /// Smooth, for most textures
+    #[default]
+    Linear,
+    /// Blocky, for pixel art
+    Nearest,
+}
+
+impl TextureFiltering {
+    pub const ALL: [TextureFiltering; 2] = [TextureFiltering::Linear, TextureFiltering::Nearest];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextureFiltering::Linear => "Linear",
+            TextureFiltering::Nearest => "Nearest",
+        }
+    }
+
+    fn sampler(self) -> ImageSampler {
+        match self {
+            TextureFiltering::Linear => ImageSampler::linear(),
+            TextureFiltering::Nearest => ImageSampler::nearest(),
+        }
+    }
+}
+
+/// How an asset is imported. Options that don't apply to the asset's kind
+/// are ignored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    /// Images hold sRGB color; off for normal maps, masks and other data
+    pub srgb: bool,
+    pub filtering: TextureFiltering,
+    /// Models are scaled by this about their origin
+    pub mesh_scale: f32,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            filtering: TextureFiltering::Linear,
+            mesh_scale: 1.0,
+        }
+    }
+}
+
+/// The contents of a `.meta` sidecar
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetMeta {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub import: ImportSettings,
+}
+
+impl Default for AssetMeta {
+    /// A new UUID and default settings
+    fn default() -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            import: ImportSettings::default(),
+        }
+    }
+}
+
+/// The sidecar of the asset at `path` under `root`
+pub fn meta_path(root: &Path, path: &str) -> PathBuf {
+    root.join(format!("{path}.{META_EXTENSION}"))
+}
+
+pub fn is_meta_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(META_EXTENSION))
+}
+
+pub fn read_asset_meta(root: &Path, path: &str) -> anyhow::Result<AssetMeta> {
+    let text = std::fs::read_to_string(meta_path(root, path))?;
+    Ok(ron::from_str(&text)?)
+}
+
+pub fn write_asset_meta(root: &Path, path: &str, meta: &AssetMeta) -> anyhow::Result<()> {
+    let text = ron::ser::to_string_pretty(meta, ron::ser::PrettyConfig::default())?;
+    std::fs::write(meta_path(root, path), text)?;
+    Ok(())
+}
+
+/// UUID of the asset at `path` under `root`, from its sidecar
+pub fn asset_uuid(root: &Path, path: &str) -> Option<Uuid> {
+    read_asset_meta(root, path).ok().map(|meta| meta.uuid)
+}
+
+/// Path under `root` of the asset whose sidecar holds `uuid`
+pub fn find_asset_by_uuid(root: &Path, uuid: Uuid) -> Option<String> {
+    let suffix = format!(".{META_EXTENSION}");
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_meta_file(entry.path()))
+        .find_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+            let path = relative.strip_suffix(&suffix)?;
+            (asset_uuid(root, path)? == uuid).then(|| path.to_string())
+        })
+}
+
+/// Give an asset a sidecar with a new UUID and default settings, unless it
+/// has one already. Returns whether one was written.
+pub fn ensure_asset_meta(root: &Path, path: &str) -> anyhow::Result<bool> {
+    if meta_path(root, path).exists() {
+        return Ok(false);
+    }
+    write_asset_meta(root, path, &AssetMeta::default())?;
+    Ok(true)
+}
+
+/// Import settings of the file a loaded asset came from; `None` without a
+/// readable sidecar, so the asset is left as loaded
+fn import_settings(config: &EngineConfig, asset_path: &AssetPath) -> Option<ImportSettings> {
+    let path = asset_path.path().to_string_lossy().replace('\\', "/");
+    read_asset_meta(&config.asset_root, &path).ok().map(|meta| meta.import)
+}
+
+pub fn apply_image_import_settings(
+    config: Res<EngineConfig>,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        // Textures inside a model already know whether they hold color
+        let Some(path) = asset_server.get_path(*id).filter(|path| path.label().is_none()) else {
+            continue;
+        };
+        let Some(settings) = import_settings(&config, &path) else {
+            continue;
+        };
+        let Some(image) = images.get_mut(*id) else {
+            continue;
+        };
+        let format = image.texture_descriptor.format;
+        image.texture_descriptor.format = if settings.srgb { format.add_srgb_suffix() } else { format.remove_srgb_suffix() };
+        image.sampler = settings.filtering.sampler();
+    }
+}
+
+/// Scale meshes loaded on their own, like `.obj` files. Meshes inside a glTF
+/// are scaled through its scenes instead.
+pub fn apply_mesh_import_settings(
+    mut commands: Commands,
+    config: Res<EngineConfig>,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<AssetEvent<Mesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    bounded: Query<(Entity, &Handle<Mesh>), With<Aabb>>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        let Some(path) = asset_server.get_path(*id).filter(|path| path.label().is_none()) else {
+            continue;
+        };
+        let Some(settings) = import_settings(&config, &path) else {
+            continue;
+        };
+        if settings.mesh_scale == 1.0 {
+            continue;
+        }
+        let Some(mesh) = meshes.get_mut(*id) else {
+            continue;
+        };
+        if let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+            for position in positions {
+                *position = (Vec3::from(*position) * settings.mesh_scale).to_array();
+            }
+        }
+        // Bevy works bounds out again for entities without them
+        for (entity, handle) in &bounded {
+            if handle.id() == *id {
+                commands.entity(entity).remove::<Aabb>();
+            }
+        }
+    }
+}
+
+/// Scale the roots of a model's scenes, which moves everything in them
+pub fn apply_scene_import_settings(
+    config: Res<EngineConfig>,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<AssetEvent<Scene>>,
+    mut scenes: ResMut<Assets<Scene>>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        let Some(settings) = asset_server
+            .get_path(*id)
+            .and_then(|path| import_settings(&config, &path))
+        else {
+            continue;
+        };
+        if settings.mesh_scale == 1.0 {
+            continue;
+        }
+        let Some(scene) = scenes.get_mut(*id) else {
+            continue;
+        };
+        let mut roots = scene.world.query_filtered::<&mut Transform, Without<Parent>>();
+        for mut transform in roots.iter_mut(&mut scene.world) {
+            transform.translation *= settings.mesh_scale;
+            transform.scale *= settings.mesh_scale;
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// Waffle Engine Asset Bundles
+// Named groups of assets packed into a single `.wpak` file, for DLC and
+// patches. Bundles are defined in the project settings and built from the
+// editor. At runtime `Bundles::mount("bundles/dlc1.wpak")` makes a bundle's
+// files loadable by their usual asset paths, and files in later mounts
+// override earlier ones and the assets folder. Getting the file onto the
+// player's machine is left to the store or launcher.
+//
+// A `.wpak` file is the magic `WPAK`, a format version, the length of a JSON
+// index, the index, then the files back to back. Files are stored
+// uncompressed.
+
+use bevy::asset::io::{
+    AssetReader, AssetReaderError, AssetSource, AssetSourceBuilder, AssetSourceId, ErasedAssetReader, PathStream,
+    Reader, VecReader,
+};
+use bevy::prelude::*;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::core::project::BundleDefinition;
+
+pub const BUNDLE_DIR: &str = "bundles";
+pub const BUNDLE_EXTENSION: &str = "wpak";
+const BUNDLE_MAGIC: &[u8; 4] = b"WPAK";
+const BUNDLE_VERSION: u32 = 1;
+const ASSETS_DIR: &str = "assets";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BundleIndex {
+    name: String,
+    files: Vec<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BundleFile {
+    path: String,
+    /// From the start of the file data
+    offset: u64,
+    size: u64,
+}
+
+impl BundleDefinition {
+    pub fn output_path(&self) -> PathBuf {
+        PathBuf::from(BUNDLE_DIR).join(format!("{}.{BUNDLE_EXTENSION}", self.name))
+    }
+
+    /// Whether an asset path, relative to the assets folder, belongs here
+    pub fn includes(&self, path: &str) -> bool {
+        self.include.iter().any(|prefix| {
+            let prefix = prefix.trim().trim_matches('/');
+            !prefix.is_empty()
+                && (path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')))
+        })
+    }
+
+    /// Pack the included assets into `output_path`, returning the file count
+    pub fn build(&self) -> anyhow::Result<usize> {
+        let root = Path::new(ASSETS_DIR);
+        let mut paths: Vec<String> = WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(root).ok()?;
+                Some(relative.to_string_lossy().replace('\\', "/"))
+            })
+            .filter(|path| self.includes(path))
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            anyhow::bail!("bundle \"{}\" includes no files", self.name);
+        }
+
+        let mut files = Vec::with_capacity(paths.len());
+        let mut data = Vec::new();
+        for path in paths {
+            let bytes = std::fs::read(root.join(&path))?;
+            files.push(BundleFile {
+                path,
+                offset: data.len() as u64,
+                size: bytes.len() as u64,
+            });
+            data.extend_from_slice(&bytes);
+        }
+        let count = files.len();
+        let index = serde_json::to_vec(&BundleIndex {
+            name: self.name.clone(),
+            files,
+        })?;
+
+        std::fs::create_dir_all(BUNDLE_DIR)?;
+        let mut output = std::io::BufWriter::new(std::fs::File::create(self.output_path())?);
+        output.write_all(BUNDLE_MAGIC)?;
+        output.write_all(&BUNDLE_VERSION.to_le_bytes())?;
+        output.write_all(&(index.len() as u64).to_le_bytes())?;
+        output.write_all(&index)?;
+        output.write_all(&data)?;
+        output.flush()?;
+        Ok(count)
+    }
+}
+
+/// End of a file's data in the bundle, or `None` if it overflows
+fn data_end(data_start: u64, offset: u64, size: u64) -> Option<u64> {
+    data_start.checked_add(offset)?.checked_add(size)
+}
+
+struct MountedBundle {
+    name: String,
+    file: PathBuf,
+    /// Where file data starts in `file`
+    data_start: u64,
+    files: HashMap<PathBuf, (u64, u64)>,
+}
+
+impl MountedBundle {
+    fn open(file: &Path) -> anyhow::Result<Self> {
+        let mut reader = std::fs::File::open(file)?;
+        let mut header = [0u8; 16];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != BUNDLE_MAGIC {
+            anyhow::bail!("not a bundle file");
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into()?);
+        if version != BUNDLE_VERSION {
+            anyhow::bail!("unsupported bundle version {version}");
+        }
+        // Lengths come from the file, so a truncated or corrupt bundle must
+        // not get to size an allocation past what the file holds
+        let file_length = reader.metadata()?.len();
+        let index_length = u64::from_le_bytes(header[8..16].try_into()?);
+        let data_start = 16u64
+            .checked_add(index_length)
+            .filter(|end| *end <= file_length)
+            .ok_or_else(|| anyhow::anyhow!("bundle index runs past the end of the file"))?;
+        let mut index = vec![0; index_length as usize];
+        reader.read_exact(&mut index)?;
+        let index: BundleIndex = serde_json::from_slice(&index)?;
+        let mut files = HashMap::new();
+        for entry in index.files {
+            if data_end(data_start, entry.offset, entry.size).is_none_or(|end| end > file_length) {
+                anyhow::bail!("\"{}\" runs past the end of the bundle", entry.path);
+            }
+            files.insert(PathBuf::from(entry.path), (entry.offset, entry.size));
+        }
+        Ok(Self {
+            name: index.name,
+            file: file.to_path_buf(),
+            data_start,
+            files,
+        })
+    }
+
+    fn read(&self, path: &Path) -> Option<std::io::Result<Vec<u8>>> {
+        let (offset, size) = *self.files.get(path)?;
+        let read = || {
+            let mut reader = std::fs::File::open(&self.file)?;
+            // The file may have been replaced since it was mounted
+            let file_length = reader.metadata()?.len();
+            if data_end(self.data_start, offset, size).is_none_or(|end| end > file_length) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("{} runs past the end of the bundle", path.display()),
+                ));
+            }
+            reader.seek(SeekFrom::Start(self.data_start + offset))?;
+            let mut bytes = vec![0; size as usize];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        };
+        Some(read())
+    }
+}
+
+/// Mounted bundles, shared with the asset reader
+#[derive(Resource, Clone, Default)]
+pub struct Bundles {
+    mounted: Arc<RwLock<Vec<MountedBundle>>>,
+    /// Files of newly mounted bundles, reloaded if already in use
+    pending_reloads: Arc<RwLock<Vec<String>>>,
+}
+
+impl Bundles {
+    /// Make a bundle's files loadable, overriding earlier mounts. Returns the
+    /// bundle's name.
+    pub fn mount(&self, file: impl AsRef<Path>) -> anyhow::Result<String> {
+        let bundle = MountedBundle::open(file.as_ref())?;
+        let name = bundle.name.clone();
+        self.unmount(&name);
+        self.pending_reloads
+            .write()
+            .extend(bundle.files.keys().map(|path| path.to_string_lossy().replace('\\', "/")));
+        info!("Mounted bundle \"{}\" with {} files", name, bundle.files.len());
+        self.mounted.write().push(bundle);
+        Ok(name)
+    }
+
+    pub fn unmount(&self, name: &str) -> bool {
+        let mut mounted = self.mounted.write();
+        let Some(index) = mounted.iter().position(|bundle| bundle.name == name) else {
+            return false;
+        };
+        let bundle = mounted.remove(index);
+        self.pending_reloads
+            .write()
+            .extend(bundle.files.keys().map(|path| path.to_string_lossy().replace('\\', "/")));
+        true
+    }
+
+    /// Names of mounted bundles, in mount order
+    pub fn mounted(&self) -> Vec<String> {
+        self.mounted.read().iter().map(|bundle| bundle.name.clone()).collect()
+    }
+
+    fn read(&self, path: &Path) -> Option<std::io::Result<Vec<u8>>> {
+        self.mounted.read().iter().rev().find_map(|bundle| bundle.read(path))
+    }
+}
+
+/// Reads from mounted bundles first, then the assets folder
+struct BundleAssetReader {
+    bundles: Bundles,
+    fallback: Box<dyn ErasedAssetReader>,
+}
+
+impl BundleAssetReader {
+    fn read_bundled<'a>(&self, path: &Path) -> Option<Result<Box<Reader<'a>>, AssetReaderError>> {
+        let read = self.bundles.read(path)?;
+        Some(
+            read.map(|bytes| Box::new(VecReader::new(bytes)) as Box<Reader<'a>>)
+                .map_err(|err| AssetReaderError::Io(Arc::new(err))),
+        )
+    }
+}
+
+impl AssetReader for BundleAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<Box<Reader<'a>>, AssetReaderError> {
+        match self.read_bundled(path) {
+            Some(result) => result,
+            None => self.fallback.read(path).await,
+        }
+    }
+
+    async fn read_meta<'a>(&'a self, path: &'a Path) -> Result<Box<Reader<'a>>, AssetReaderError> {
+        let mut meta_path = path.as_os_str().to_owned();
+        meta_path.push(".meta");
+        match self.read_bundled(Path::new(&meta_path)) {
+            Some(result) => result,
+            None => self.fallback.read_meta(path).await,
+        }
+    }
+
+    async fn read_directory<'a>(&'a self, path: &'a Path) -> Result<Box<PathStream>, AssetReaderError> {
+        // Folder loads only see the assets folder
+        self.fallback.read_directory(path).await
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        self.fallback.is_directory(path).await
+    }
+}
+
+/// Replaces the default asset source so bundles can be mounted; add it
+/// before `DefaultPlugins`
+pub struct WaffleBundlesPlugin;
+
+impl Plugin for WaffleBundlesPlugin {
+    fn build(&self, app: &mut App) {
+        let bundles = Bundles::default();
+        let reader_bundles = bundles.clone();
+        let mut file_reader = AssetSource::get_default_reader(ASSETS_DIR.to_string());
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSourceBuilder::platform_default(ASSETS_DIR, None).with_reader(move || {
+                Box::new(BundleAssetReader {
+                    bundles: reader_bundles.clone(),
+                    fallback: file_reader(),
+                })
+            }),
+        )
+        .insert_resource(bundles)
+        .add_systems(Update, reload_bundled_assets);
+    }
+}
+
+/// Reload assets already in use whose files a mount or unmount changed
+pub fn reload_bundled_assets(bundles: Res<Bundles>, asset_server: Res<AssetServer>) {
+    let paths = std::mem::take(&mut *bundles.pending_reloads.write());
+    for path in paths {
+        if asset_server.get_path_id(path.clone()).is_some() {
+            asset_server.reload(path);
+        }
+    }
+}
@@ -12,6 +12,10 @@ pub struct EngineRoot;
 #[derive(Component, Reflect, Default)]
 pub struct EditorHidden;
 
+/// Spawned by gameplay while playing; Stop despawns it with the rest of the play session
+#[derive(Component, Reflect, Default)]
+pub struct PlaySpawned;
+
 /// Engine camera component
 #[derive(Component, Reflect)]
 pub struct EngineCamera {
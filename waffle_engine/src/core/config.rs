@@ -6,7 +6,8 @@ use serde::{Serialize, Deserialize};
 use std::path::PathBuf;
 
 /// Main engine configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+#[derive(Debug, Clone, Serialize, Deserialize, Resource, Reflect)]
+#[reflect(Resource)]
 pub struct EngineConfig {
     pub engine_name: String,
     pub version: String,
@@ -44,7 +45,7 @@ impl Default for EngineConfig {
 }
 
 /// Rendering quality settings
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Reflect)]
 pub enum RenderingQuality {
     Low,
     Medium,
@@ -53,7 +54,7 @@ pub enum RenderingQuality {
 }
 
 /// Logging level settings
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Reflect)]
 pub enum LogLevel {
     Error,
     Warn,
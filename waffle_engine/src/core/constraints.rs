@@ -0,0 +1,173 @@
+// Waffle Engine Constraints
+// Lightweight transform constraints evaluated after gameplay each frame
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::spatial::SpatialQuery;
+
+/// Rotate the entity so its forward axis points at the target
+#[derive(Component, Reflect, Clone)]
+pub struct LookAtConstraint {
+    pub target: Option<Entity>,
+    pub up: Vec3,
+}
+
+impl Default for LookAtConstraint {
+    fn default() -> Self {
+        Self {
+            target: None,
+            up: Vec3::Y,
+        }
+    }
+}
+
+/// Keep the entity at an offset from the target, optionally trailing behind it
+#[derive(Component, Reflect, Clone)]
+pub struct FollowConstraint {
+    pub target: Option<Entity>,
+    pub offset: Vec3,
+    /// Approximate seconds to catch up with the target; 0 snaps every frame
+    pub lag: f32,
+}
+
+impl Default for FollowConstraint {
+    fn default() -> Self {
+        Self {
+            target: None,
+            offset: Vec3::new(0.0, 2.0, -4.0),
+            lag: 0.0,
+        }
+    }
+}
+
+/// Drop the entity onto the surface below it
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StickToSurfaceConstraint {
+    pub height: f32,
+    pub max_distance: f32,
+    pub align_to_normal: bool,
+}
+
+impl Default for StickToSurfaceConstraint {
+    fn default() -> Self {
+        Self {
+            height: 0.0,
+            max_distance: 100.0,
+            align_to_normal: false,
+        }
+    }
+}
+
+pub fn apply_follow_constraints(
+    time: Res<Time>,
+    targets: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    mut followers: Query<(Entity, &FollowConstraint, &mut Transform)>,
+) {
+    for (entity, constraint, mut transform) in &mut followers {
+        let Some(target) = constraint.target.and_then(|target| targets.get(target).ok()) else {
+            continue;
+        };
+        let desired = target.transform_point(constraint.offset);
+        let desired = world_to_parent_space(entity, desired, &parents, &targets);
+        transform.translation = if constraint.lag > 0.0 {
+            let blend = 1.0 - (-time.delta_seconds() / constraint.lag).exp();
+            transform.translation.lerp(desired, blend)
+        } else {
+            desired
+        };
+    }
+}
+
+pub fn apply_stick_to_surface_constraints(
+    spatial: SpatialQuery,
+    globals: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    mut constrained: Query<(Entity, &StickToSurfaceConstraint, &mut Transform)>,
+) {
+    for (entity, constraint, mut transform) in &mut constrained {
+        let Ok(global) = globals.get(entity) else {
+            continue;
+        };
+        // Cast from slightly above so an entity resting on the surface still finds it.
+        let origin = global.translation() + Vec3::Y * constraint.max_distance * 0.5;
+        let Some(hit) = spatial.cast_ray_filtered(
+            origin,
+            Vec3::NEG_Y,
+            constraint.max_distance * 1.5,
+            |candidate| !is_self_or_descendant(candidate, entity, &children),
+        ) else {
+            continue;
+        };
+
+        let desired = hit.point + hit.normal * constraint.height;
+        transform.translation = world_to_parent_space(entity, desired, &parents, &globals);
+        if constraint.align_to_normal {
+            let forward = global.forward();
+            let tangent = forward.reject_from(hit.normal).normalize_or_zero();
+            if tangent != Vec3::ZERO {
+                let world_rotation = Transform::IDENTITY.looking_to(tangent, hit.normal).rotation;
+                transform.rotation = parent_rotation(entity, &parents, &globals).inverse() * world_rotation;
+            }
+        }
+    }
+}
+
+pub fn apply_look_at_constraints(
+    targets: Query<&GlobalTransform>,
+    mut lookers: Query<(Entity, &LookAtConstraint, &mut Transform)>,
+    parents: Query<&Parent>,
+) {
+    for (entity, constraint, mut transform) in &mut lookers {
+        let Some(target) = constraint.target.and_then(|target| targets.get(target).ok()) else {
+            continue;
+        };
+        let Ok(global) = targets.get(entity) else {
+            continue;
+        };
+        let direction = target.translation() - global.translation();
+        if direction.length_squared() < 1e-8 {
+            continue;
+        }
+        let world_rotation = Transform::IDENTITY.looking_to(direction, constraint.up).rotation;
+        transform.rotation = parent_rotation(entity, &parents, &targets).inverse() * world_rotation;
+    }
+}
+
+fn parent_rotation(entity: Entity, parents: &Query<&Parent>, globals: &Query<&GlobalTransform>) -> Quat {
+    parents
+        .get(entity)
+        .ok()
+        .and_then(|parent| globals.get(parent.get()).ok())
+        .map(|parent| parent.compute_transform().rotation)
+        .unwrap_or(Quat::IDENTITY)
+}
+
+fn world_to_parent_space(
+    entity: Entity,
+    point: Vec3,
+    parents: &Query<&Parent>,
+    globals: &Query<&GlobalTransform>,
+) -> Vec3 {
+    parents
+        .get(entity)
+        .ok()
+        .and_then(|parent| globals.get(parent.get()).ok())
+        .map(|parent| parent.affine().inverse().transform_point3(point))
+        .unwrap_or(point)
+}
+
+fn is_self_or_descendant(candidate: Entity, root: Entity, children: &Query<&Children>) -> bool {
+    let mut stack = vec![root];
+    while let Some(current) = stack.pop() {
+        if current == candidate {
+            return true;
+        }
+        if let Ok(list) = children.get(current) {
+            stack.extend(list.iter().copied());
+        }
+    }
+    false
+}
@@ -0,0 +1,229 @@
+// Waffle Engine Game Cursor
+// Gameplay control over the mouse cursor: lock, hide and custom images.
+// Only applied while playing; the editor owns the cursor otherwise.
+
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorCapture {
+    #[default]
+    Free,
+    /// Kept inside the window
+    Confined,
+    /// Pinned in place and hidden, for mouse-look
+    Locked,
+}
+
+/// Software cursor drawn with bevy_ui in place of the system cursor
+#[derive(Debug, Clone)]
+pub struct CursorImage {
+    pub image: Handle<Image>,
+    pub size: Vec2,
+    /// Pixel within the image that sits on the pointer position
+    pub hotspot: Vec2,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct GameCursor {
+    pub capture: CursorCapture,
+    pub visible: bool,
+    pub image: Option<CursorImage>,
+    /// Set when Escape hands the cursor back to the editor; cleared by clicking the game
+    pub suspended: bool,
+    /// Window region showing the game, in logical pixels; `None` is the whole window
+    pub viewport: Option<Rect>,
+    /// Camera the software cursor draws into; `None` uses the default UI camera
+    pub camera: Option<Entity>,
+    /// Pointer position relative to the game viewport, in logical window pixels
+    pub position: Option<Vec2>,
+}
+
+impl Default for GameCursor {
+    fn default() -> Self {
+        Self {
+            capture: CursorCapture::Free,
+            visible: true,
+            image: None,
+            suspended: false,
+            viewport: None,
+            camera: None,
+            position: None,
+        }
+    }
+}
+
+impl GameCursor {
+    pub fn lock(&mut self) {
+        self.capture = CursorCapture::Locked;
+    }
+
+    pub fn confine(&mut self) {
+        self.capture = CursorCapture::Confined;
+    }
+
+    pub fn release(&mut self) {
+        self.capture = CursorCapture::Free;
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn show(&mut self) {
+        self.visible = true;
+    }
+
+    pub fn set_image(&mut self, image: Handle<Image>, size: Vec2, hotspot: Vec2) {
+        self.image = Some(CursorImage { image, size, hotspot });
+    }
+
+    pub fn clear_image(&mut self) {
+        self.image = None;
+    }
+
+    /// Whether gameplay currently holds the cursor
+    pub fn is_captured(&self) -> bool {
+        !self.suspended && self.capture != CursorCapture::Free
+    }
+}
+
+/// UI node used to draw `GameCursor::image`
+#[derive(Component)]
+pub struct SoftwareCursor;
+
+/// Push the gameplay cursor state to the window while playing
+pub fn apply_game_cursor(
+    mut cursor: ResMut<GameCursor>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+
+    let pointer = window.cursor_position();
+    let over_game = pointer.is_some_and(|pointer| cursor.viewport.is_none_or(|rect| rect.contains(pointer)));
+    if keyboard_input.just_pressed(KeyCode::Escape) && cursor.capture != CursorCapture::Free {
+        cursor.suspended = true;
+    } else if cursor.suspended && over_game && mouse_input.just_pressed(MouseButton::Left) {
+        cursor.suspended = false;
+    }
+
+    let (grab_mode, visible) = if cursor.suspended {
+        (CursorGrabMode::None, true)
+    } else {
+        let grab_mode = match cursor.capture {
+            CursorCapture::Free => CursorGrabMode::None,
+            CursorCapture::Confined => CursorGrabMode::Confined,
+            CursorCapture::Locked => CursorGrabMode::Locked,
+        };
+        let visible = cursor.visible && cursor.image.is_none() && cursor.capture != CursorCapture::Locked;
+        (grab_mode, visible || !over_game)
+    };
+    if window.cursor.grab_mode != grab_mode {
+        window.cursor.grab_mode = grab_mode;
+    }
+    if window.cursor.visible != visible {
+        window.cursor.visible = visible;
+    }
+
+    cursor.position = pointer.filter(|_| over_game).map(|pointer| match cursor.viewport {
+        Some(rect) => pointer - rect.min,
+        None => pointer,
+    });
+}
+
+/// Draw the custom cursor image at the pointer, if one is set
+pub fn update_software_cursor(
+    mut commands: Commands,
+    cursor: Res<GameCursor>,
+    mut software_cursor: Query<
+        (Entity, &mut Style, &mut UiImage, &mut Visibility, Option<&TargetCamera>),
+        With<SoftwareCursor>,
+    >,
+    cameras: Query<&Camera>,
+    ui_scale: Res<UiScale>,
+) {
+    let Some(image) = cursor.image.as_ref() else {
+        for (entity, ..) in &software_cursor {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+
+    let Ok((entity, mut style, mut ui_image, mut visibility, target_camera)) = software_cursor.get_single_mut()
+    else {
+        commands.spawn((
+            SoftwareCursor,
+            Name::new("Software Cursor"),
+            ImageBundle {
+                image: UiImage::new(image.image.clone()),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(image.size.x),
+                    height: Val::Px(image.size.y),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ZIndex::Global(i32::MAX),
+        ));
+        return;
+    };
+
+    if let Some(camera) = cursor.camera {
+        if target_camera.map(|target| target.0) != Some(camera) {
+            commands.entity(entity).insert(TargetCamera(camera));
+        }
+    }
+    if ui_image.texture != image.image {
+        ui_image.texture = image.image.clone();
+    }
+    let shown = cursor.visible && !cursor.suspended && cursor.capture != CursorCapture::Locked;
+    match cursor.position.filter(|_| shown) {
+        Some(position) => {
+            // Render targets such as the editor viewport image can differ in
+            // size from the window region that displays them.
+            let scale = cursor
+                .camera
+                .and_then(|camera| cameras.get(camera).ok())
+                .and_then(Camera::logical_viewport_size)
+                .zip(cursor.viewport.map(|rect| rect.size()))
+                .filter(|(_, viewport)| viewport.x > 0.0 && viewport.y > 0.0)
+                .map(|(target, viewport)| target / viewport)
+                .unwrap_or(Vec2::ONE);
+            // The image is sized in UI pixels, which `UiScale` scales
+            let top_left = position * scale / ui_scale.0 - image.hotspot;
+            style.left = Val::Px(top_left.x);
+            style.top = Val::Px(top_left.y);
+            style.width = Val::Px(image.size.x);
+            style.height = Val::Px(image.size.y);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// Hand the cursor back to the editor when a play session stops
+pub fn release_game_cursor(
+    mut commands: Commands,
+    mut cursor: ResMut<GameCursor>,
+    software_cursor: Query<Entity, With<SoftwareCursor>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    *cursor = GameCursor {
+        viewport: cursor.viewport,
+        camera: cursor.camera,
+        ..default()
+    };
+    for entity in &software_cursor {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+}
@@ -0,0 +1,127 @@
+// Waffle Engine Debug Draw
+// Immediate-mode debug shapes and labels for gameplay code. Everything is a
+// no-op in release builds and can be switched off at runtime.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+#[derive(Resource, Debug, Clone)]
+pub struct DebugDrawSettings {
+    pub enabled: bool,
+}
+
+impl Default for DebugDrawSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DebugText {
+    pub position: Vec3,
+    pub text: String,
+    pub color: Color,
+}
+
+/// World-space labels; drawn by the editor viewport overlay
+#[derive(Resource, Debug, Default)]
+pub struct DebugTextQueue {
+    pending: Vec<DebugText>,
+    /// Labels submitted during the last completed frame
+    pub frame: Vec<DebugText>,
+}
+
+/// A shape queued through `DebugDrawQueue`
+#[derive(Debug, Clone)]
+pub enum DebugShape {
+    Line { start: Vec3, end: Vec3, color: Color },
+    Sphere { position: Vec3, radius: f32, color: Color },
+    Text(DebugText),
+}
+
+/// Debug shapes from code that only has the world, such as scripts; drawn
+/// through `DebugDraw` at the end of the frame
+#[derive(Resource, Debug, Default)]
+pub struct DebugDrawQueue {
+    pub shapes: Vec<DebugShape>,
+}
+
+/// Debug drawing from systems: `debug.line(a, b, color)`, `debug.sphere(pos, r)`,
+/// `debug.text(pos, "hp 34")`
+#[derive(SystemParam)]
+pub struct DebugDraw<'w, 's> {
+    gizmos: Gizmos<'w, 's>,
+    texts: ResMut<'w, DebugTextQueue>,
+    settings: Res<'w, DebugDrawSettings>,
+}
+
+impl<'w, 's> DebugDraw<'w, 's> {
+    pub fn enabled(&self) -> bool {
+        cfg!(debug_assertions) && self.settings.enabled
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        if self.enabled() {
+            self.gizmos.line(start, end, color);
+        }
+    }
+
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Color) {
+        if self.enabled() {
+            self.gizmos.ray(origin, direction, color);
+        }
+    }
+
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Color) {
+        if self.enabled() {
+            self.gizmos.arrow(start, end, color);
+        }
+    }
+
+    pub fn sphere(&mut self, position: Vec3, radius: f32) {
+        self.sphere_colored(position, radius, Color::srgb(1.0, 0.9, 0.2));
+    }
+
+    pub fn sphere_colored(&mut self, position: Vec3, radius: f32, color: Color) {
+        if self.enabled() {
+            self.gizmos.sphere(position, Quat::IDENTITY, radius, color);
+        }
+    }
+
+    pub fn cuboid(&mut self, center: Vec3, size: Vec3, color: Color) {
+        if self.enabled() {
+            self.gizmos
+                .cuboid(Transform::from_translation(center).with_scale(size), color);
+        }
+    }
+
+    pub fn text(&mut self, position: Vec3, text: impl Into<String>) {
+        self.text_colored(position, text, Color::WHITE);
+    }
+
+    pub fn text_colored(&mut self, position: Vec3, text: impl Into<String>, color: Color) {
+        if self.enabled() {
+            self.texts.pending.push(DebugText {
+                position,
+                text: text.into(),
+                color,
+            });
+        }
+    }
+}
+
+/// Publish this frame's labels and start collecting the next frame's
+pub fn flip_debug_text(mut texts: ResMut<DebugTextQueue>) {
+    let pending = std::mem::take(&mut texts.pending);
+    texts.frame = pending;
+}
+
+pub fn draw_queued_debug_shapes(mut queue: ResMut<DebugDrawQueue>, mut debug: DebugDraw) {
+    for shape in queue.shapes.drain(..) {
+        match shape {
+            DebugShape::Line { start, end, color } => debug.line(start, end, color),
+            DebugShape::Sphere { position, radius, color } => debug.sphere_colored(position, radius, color),
+            DebugShape::Text(text) => debug.text_colored(text.position, text.text, text.color),
+        }
+    }
+}
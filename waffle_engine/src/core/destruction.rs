@@ -0,0 +1,512 @@
+// Waffle Engine Destruction
+// Meshes that break into debris when a `DestroyEvent` hits them. Each mesh is
+// split into Voronoi chunks once, on a background task as soon as it has
+// loaded, and the chunks are kept in a `FracturePrefab` asset so destroying
+// only spawns entities.
+//
+// Chunks fill the mesh's convex shape; concave meshes break into chunks of
+// their hull. Rigid body physics is not enabled in the engine yet, so debris
+// flies ballistically and bounces off scene meshes found by the spatial query.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+use crate::core::components::PlaySpawned;
+use crate::core::health::DeathEvent;
+use crate::core::random::WaffleRng;
+use crate::core::spatial::SpatialQuery;
+
+const DEBRIS_GRAVITY: f32 = 9.81;
+const DEBRIS_RESTITUTION: f32 = 0.35;
+const DEBRIS_FRICTION: f32 = 0.6;
+/// Seconds debris takes to shrink away at the end of its lifetime
+const DEBRIS_FADE_TIME: f32 = 0.5;
+
+/// Breaks into `chunk_count` pieces when destroyed
+#[derive(Component, Debug, Clone)]
+pub struct Destructible {
+    pub chunk_count: usize,
+    /// Seed for the fracture pattern, so a mesh always breaks the same way
+    pub seed: u64,
+    /// Speed chunks fly away from the hit with, in units per second
+    pub burst_speed: f32,
+    /// Seconds chunks stay before shrinking away
+    pub debris_lifetime: f32,
+    /// Set once the mesh has been fractured
+    pub prefab: Option<Handle<FracturePrefab>>,
+}
+
+impl Default for Destructible {
+    fn default() -> Self {
+        Self {
+            chunk_count: 12,
+            seed: 0,
+            burst_speed: 3.0,
+            debris_lifetime: 6.0,
+            prefab: None,
+        }
+    }
+}
+
+impl Destructible {
+    pub fn new(chunk_count: usize) -> Self {
+        Self {
+            chunk_count,
+            ..default()
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_burst_speed(mut self, speed: f32) -> Self {
+        self.burst_speed = speed;
+        self
+    }
+
+    pub fn with_debris_lifetime(mut self, seconds: f32) -> Self {
+        self.debris_lifetime = seconds.max(0.0);
+        self
+    }
+}
+
+/// Pre-fractured pieces of a mesh
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct FracturePrefab {
+    pub chunks: Vec<FractureChunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FractureChunk {
+    /// Vertices are relative to `center`
+    pub mesh: Handle<Mesh>,
+    /// Chunk center in the source mesh's space
+    pub center: Vec3,
+    /// Distance from the center to the furthest vertex
+    pub radius: f32,
+}
+
+/// Break a `Destructible` entity apart; send it from gameplay code or scripts
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DestroyEvent {
+    pub entity: Entity,
+    /// Where the hit landed; chunks fly away from it. Defaults to the center
+    pub point: Option<Vec3>,
+    /// Extra speed on top of the entity's `burst_speed`
+    pub force: f32,
+}
+
+impl DestroyEvent {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            point: None,
+            force: 0.0,
+        }
+    }
+
+    pub fn at(entity: Entity, point: Vec3, force: f32) -> Self {
+        Self {
+            entity,
+            point: Some(point),
+            force,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct DebrisChunk {
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub radius: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    scale: Vec3,
+    resting: bool,
+}
+
+/// Fracture still running for a destructible's mesh
+#[derive(Component)]
+pub struct Fracturing(Task<Vec<(Mesh, Vec3, f32)>>);
+
+/// Start fracturing destructible meshes once they have loaded, off the main thread
+pub fn prefracture_destructibles(
+    mut commands: Commands,
+    query: Query<(Entity, &Destructible, &Handle<Mesh>), Without<Fracturing>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    for (entity, destructible, mesh_handle) in &query {
+        if destructible.prefab.is_some() {
+            continue;
+        }
+        let Some(mesh) = meshes.get(mesh_handle) else {
+            continue;
+        };
+        let mesh = mesh.clone();
+        let (chunk_count, seed) = (destructible.chunk_count, destructible.seed);
+        let task = AsyncComputeTaskPool::get().spawn(async move { fracture_mesh(&mesh, chunk_count, seed) });
+        commands.entity(entity).insert(Fracturing(task));
+    }
+}
+
+/// Turn finished fractures into prefabs
+pub fn finish_destructible_fractures(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Destructible, &mut Fracturing)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut prefabs: ResMut<Assets<FracturePrefab>>,
+) {
+    for (entity, mut destructible, mut fracturing) in &mut query {
+        let Some(pieces) = block_on(poll_once(&mut fracturing.0)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<Fracturing>();
+        if pieces.is_empty() {
+            warn!("Could not fracture the mesh of {:?}; it will not break", entity);
+        }
+        let chunks = pieces
+            .into_iter()
+            .map(|(mesh, center, radius)| FractureChunk {
+                mesh: meshes.add(mesh),
+                center,
+                radius,
+            })
+            .collect();
+        destructible.prefab = Some(prefabs.add(FracturePrefab { chunks }));
+    }
+}
+
+/// Destructibles with health break when they die
+pub fn destroy_on_death(
+    mut deaths: EventReader<DeathEvent>,
+    destructibles: Query<(), With<Destructible>>,
+    mut destroy: EventWriter<DestroyEvent>,
+) {
+    for death in deaths.read() {
+        if destructibles.contains(death.entity) {
+            destroy.send(DestroyEvent::new(death.entity));
+        }
+    }
+}
+
+/// Swap destroyed entities for their debris chunks
+pub fn apply_destroy_events(
+    mut commands: Commands,
+    mut events: EventReader<DestroyEvent>,
+    query: Query<(&Destructible, &GlobalTransform, Option<&Handle<StandardMaterial>>)>,
+    prefabs: Res<Assets<FracturePrefab>>,
+) {
+    for event in events.read() {
+        let Ok((destructible, global, material)) = query.get(event.entity) else {
+            continue;
+        };
+        let Some(prefab) = destructible.prefab.as_ref().and_then(|handle| prefabs.get(handle)) else {
+            warn!("{:?} was destroyed before its mesh was fractured", event.entity);
+            continue;
+        };
+
+        let transform = global.compute_transform();
+        let origin = event.point.unwrap_or(transform.translation);
+        let speed = destructible.burst_speed + event.force;
+        let max_scale = transform.scale.abs().max_element();
+        let mut rng = WaffleRng::new(destructible.seed ^ event.entity.to_bits());
+        for chunk in &prefab.chunks {
+            let position = global.transform_point(chunk.center);
+            let outward = (position - origin).try_normalize().unwrap_or(Vec3::Y);
+            let jitter = Vec3::new(
+                rng.range_f32(-0.3, 0.3),
+                rng.range_f32(0.0, 0.5),
+                rng.range_f32(-0.3, 0.3),
+            );
+            let mut entity = commands.spawn((
+                DebrisChunk {
+                    velocity: (outward + jitter) * speed * rng.range_f32(0.7, 1.3),
+                    angular_velocity: Vec3::new(
+                        rng.range_f32(-1.0, 1.0),
+                        rng.range_f32(-1.0, 1.0),
+                        rng.range_f32(-1.0, 1.0),
+                    ) * speed * 2.0,
+                    radius: chunk.radius * max_scale,
+                    age: 0.0,
+                    lifetime: destructible.debris_lifetime,
+                    scale: transform.scale,
+                    resting: false,
+                },
+                PlaySpawned,
+                Name::new("Debris"),
+                PbrBundle {
+                    mesh: chunk.mesh.clone(),
+                    transform: Transform {
+                        translation: position,
+                        rotation: transform.rotation,
+                        scale: transform.scale,
+                    },
+                    ..default()
+                },
+            ));
+            if let Some(material) = material {
+                entity.insert(material.clone());
+            }
+        }
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
+
+pub fn simulate_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    spatial: SpatialQuery,
+    mut debris: Query<(Entity, &mut DebrisChunk, &mut Transform)>,
+    debris_filter: Query<(), With<DebrisChunk>>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (entity, mut chunk, mut transform) in &mut debris {
+        chunk.age += delta;
+        let fade = (chunk.age - chunk.lifetime) / DEBRIS_FADE_TIME;
+        if fade >= 1.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if fade > 0.0 {
+            transform.scale = chunk.scale * (1.0 - fade);
+        }
+        if chunk.resting {
+            continue;
+        }
+
+        chunk.velocity.y -= DEBRIS_GRAVITY * delta;
+        let step = chunk.velocity * delta;
+        let distance = step.length();
+        let hit = if distance > f32::EPSILON {
+            spatial.cast_ray_filtered(transform.translation, step, distance + chunk.radius, |candidate| {
+                !debris_filter.contains(candidate)
+            })
+        } else {
+            None
+        };
+
+        match hit {
+            Some(hit) => {
+                transform.translation = hit.point + hit.normal * chunk.radius;
+                let normal_speed = chunk.velocity.dot(hit.normal);
+                if normal_speed < 0.0 {
+                    let tangent = chunk.velocity - hit.normal * normal_speed;
+                    chunk.velocity = tangent * (1.0 - DEBRIS_FRICTION) - hit.normal * normal_speed * DEBRIS_RESTITUTION;
+                }
+                chunk.angular_velocity *= 0.6;
+                if chunk.velocity.length_squared() < 0.05 {
+                    chunk.resting = true;
+                }
+            }
+            None => transform.translation += step,
+        }
+
+        let spin = chunk.angular_velocity * delta;
+        transform.rotation = (Quat::from_scaled_axis(spin) * transform.rotation).normalize();
+    }
+}
+
+/// Split a mesh into up to `chunk_count` convex Voronoi cells of its volume,
+/// returned as `(mesh, center, radius)` with vertices relative to the center
+pub fn fracture_mesh(mesh: &Mesh, chunk_count: usize, seed: u64) -> Vec<(Mesh, Vec3, f32)> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return Vec::new();
+    };
+    let positions: Vec<Vec3> = positions.iter().map(|p| Vec3::from(*p)).collect();
+    if positions.len() < 3 {
+        return Vec::new();
+    }
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+
+    let min = positions.iter().copied().fold(Vec3::MAX, Vec3::min);
+    let max = positions.iter().copied().fold(Vec3::MIN, Vec3::max);
+    let epsilon = (max - min).max_element().max(1e-3) * 1e-4;
+
+    // Outer faces of the mesh that every vertex lies behind bound its volume
+    let mut bounds: Vec<(Vec3, f32)> = Vec::new();
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [positions[triangle[0]], positions[triangle[1]], positions[triangle[2]]];
+        let Some(normal) = (b - a).cross(c - a).try_normalize() else {
+            continue;
+        };
+        let offset = normal.dot(a);
+        let duplicate = bounds
+            .iter()
+            .any(|(n, d)| n.dot(normal) > 0.9999 && (d - offset).abs() <= epsilon);
+        if duplicate {
+            continue;
+        }
+        if positions.iter().all(|p| normal.dot(*p) - offset <= epsilon) {
+            bounds.push((normal, offset));
+        }
+    }
+
+    let inside = |point: Vec3| bounds.iter().all(|(n, d)| n.dot(point) <= *d);
+    let mut rng = WaffleRng::new(seed);
+    let mut sites = Vec::new();
+    let mut attempts = 0;
+    while sites.len() < chunk_count.max(1) && attempts < chunk_count.max(1) * 50 {
+        attempts += 1;
+        let site = Vec3::new(
+            rng.range_f32(min.x, max.x),
+            rng.range_f32(min.y, max.y),
+            rng.range_f32(min.z, max.z),
+        );
+        if inside(site) {
+            sites.push(site);
+        }
+    }
+
+    let mut pieces = Vec::new();
+    for (index, site) in sites.iter().enumerate() {
+        let mut cell = box_polyhedron(min, max);
+        for (normal, offset) in &bounds {
+            cell = clip_polyhedron(cell, *normal, *offset, epsilon);
+        }
+        for (other_index, other) in sites.iter().enumerate() {
+            if other_index == index || cell.is_empty() {
+                continue;
+            }
+            let Some(normal) = (*other - *site).try_normalize() else {
+                continue;
+            };
+            cell = clip_polyhedron(cell, normal, normal.dot((*site + *other) * 0.5), epsilon);
+        }
+        if let Some(piece) = polyhedron_to_mesh(&cell) {
+            pieces.push(piece);
+        }
+    }
+    pieces
+}
+
+/// Convex polyhedron as faces wound counter-clockwise seen from outside
+type Polyhedron = Vec<Vec<Vec3>>;
+
+fn box_polyhedron(min: Vec3, max: Vec3) -> Polyhedron {
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        })
+        .collect();
+    let mut faces = Vec::new();
+    for axis in 0..3 {
+        for (bound, sign) in [(min[axis], -1.0), (max[axis], 1.0)] {
+            let mut normal = Vec3::ZERO;
+            normal[axis] = sign;
+            let face: Vec<Vec3> = corners.iter().copied().filter(|c| c[axis] == bound).collect();
+            faces.push(order_polygon(face, normal));
+        }
+    }
+    faces
+}
+
+/// Keep the part of `cell` where `normal.dot(p) <= offset`
+fn clip_polyhedron(cell: Polyhedron, normal: Vec3, offset: f32, epsilon: f32) -> Polyhedron {
+    let distance = |p: Vec3| normal.dot(p) - offset;
+    if cell.iter().flatten().all(|p| distance(*p) <= epsilon) {
+        return cell;
+    }
+
+    let mut faces = Vec::new();
+    let mut cap: Vec<Vec3> = Vec::new();
+    for face in &cell {
+        let mut clipped = Vec::new();
+        for (i, current) in face.iter().enumerate() {
+            let next = face[(i + 1) % face.len()];
+            let (d_current, d_next) = (distance(*current), distance(next));
+            if d_current <= 0.0 {
+                clipped.push(*current);
+                if d_current.abs() <= epsilon {
+                    cap.push(*current);
+                }
+            }
+            if (d_current <= 0.0) != (d_next <= 0.0) {
+                let point = *current + (next - *current) * (d_current / (d_current - d_next));
+                clipped.push(point);
+                cap.push(point);
+            }
+        }
+        if clipped.len() >= 3 {
+            faces.push(clipped);
+        }
+    }
+
+    let mut unique: Vec<Vec3> = Vec::new();
+    for point in cap {
+        if !unique.iter().any(|p| p.distance(point) <= epsilon) {
+            unique.push(point);
+        }
+    }
+    if unique.len() >= 3 {
+        faces.push(order_polygon(unique, normal));
+    }
+    faces
+}
+
+/// Sort coplanar points counter-clockwise around `normal`
+fn order_polygon(mut points: Vec<Vec3>, normal: Vec3) -> Vec<Vec3> {
+    let center = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+    let u = normal.any_orthonormal_vector();
+    let v = normal.cross(u);
+    let angle = |p: &Vec3| {
+        let offset = *p - center;
+        offset.dot(v).atan2(offset.dot(u))
+    };
+    points.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+    points
+}
+
+fn polyhedron_to_mesh(cell: &Polyhedron) -> Option<(Mesh, Vec3, f32)> {
+    let points: Vec<Vec3> = cell.iter().flatten().copied().collect();
+    if cell.len() < 4 || points.is_empty() {
+        return None;
+    }
+    let center = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+    let radius = points.iter().map(|p| p.distance(center)).fold(0.0, f32::max);
+    if radius <= f32::EPSILON {
+        return None;
+    }
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for face in cell {
+        let Some(normal) = (face[1] - face[0]).cross(face[2] - face[0]).try_normalize() else {
+            continue;
+        };
+        let u = normal.any_orthonormal_vector();
+        let v = normal.cross(u);
+        let base = positions.len() as u32;
+        for point in face {
+            positions.push((*point - center).to_array());
+            normals.push(normal.to_array());
+            uvs.push([point.dot(u), point.dot(v)]);
+        }
+        for i in 1..face.len() as u32 - 1 {
+            indices.extend([base, base + i, base + i + 1]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+    Some((mesh, center, radius))
+}
@@ -0,0 +1,294 @@
+// Waffle Engine Dialogue
+// Branching dialogue graphs stored as RON. The `DialoguePlayer` walks one
+// graph at a time; lines and choices are reported as `DialogueEvent`s for the
+// dialogue box, gameplay code and scripts.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::core::variables::{GameVariables, VariableCondition};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// Node the choice leads to; `None` ends the dialogue
+    pub next: Option<String>,
+    /// Hidden unless the condition holds
+    #[serde(default)]
+    pub condition: Option<VariableCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogueNode {
+    pub id: String,
+    pub speaker: String,
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    /// Node that follows a line without choices; `None` ends the dialogue
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Flags set when the line is shown
+    #[serde(default)]
+    pub set_flags: Vec<String>,
+    /// Position on the editor canvas
+    #[serde(default)]
+    pub position: (f32, f32),
+}
+
+impl DialogueNode {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            speaker: String::new(),
+            text: String::new(),
+            choices: Vec::new(),
+            next: None,
+            set_flags: Vec::new(),
+            position: (0.0, 0.0),
+        }
+    }
+
+    /// Ids of the nodes this one can lead to
+    pub fn targets(&self) -> Vec<&str> {
+        let mut targets: Vec<&str> = self.choices.iter().filter_map(|choice| choice.next.as_deref()).collect();
+        if self.choices.is_empty() {
+            targets.extend(self.next.as_deref());
+        }
+        targets
+    }
+}
+
+/// A named dialogue, stored as RON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogueGraph {
+    pub name: String,
+    pub start: String,
+    pub nodes: Vec<DialogueNode>,
+}
+
+impl Default for DialogueGraph {
+    fn default() -> Self {
+        Self {
+            name: "New Dialogue".to_string(),
+            start: "start".to_string(),
+            nodes: vec![DialogueNode::new("start")],
+        }
+    }
+}
+
+impl DialogueGraph {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn node(&self, id: &str) -> Option<&DialogueNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Node ids that are referenced but do not exist
+    pub fn missing_targets(&self) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+        let references = std::iter::once(self.start.as_str())
+            .chain(self.nodes.iter().flat_map(|node| node.targets()));
+        for id in references {
+            if self.node(id).is_none() && !missing.iter().any(|existing| existing == id) {
+                missing.push(id.to_string());
+            }
+        }
+        missing
+    }
+
+    /// An id not used by any node, starting from `base`
+    pub fn unique_id(&self, base: &str) -> String {
+        if self.node(base).is_none() {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| format!("{base}_{n}"))
+            .find(|id| self.node(id).is_none())
+            .unwrap_or_else(|| base.to_string())
+    }
+}
+
+/// The dialogue currently playing, if any
+#[derive(Resource, Debug, Default)]
+pub struct DialoguePlayer {
+    graph: Option<Arc<DialogueGraph>>,
+    current: Option<String>,
+    /// Entity the dialogue was started on, e.g. the NPC talked to
+    pub entity: Option<Entity>,
+}
+
+impl DialoguePlayer {
+    pub fn is_active(&self) -> bool {
+        self.current_node().is_some()
+    }
+
+    pub fn graph(&self) -> Option<&DialogueGraph> {
+        self.graph.as_deref()
+    }
+
+    pub fn current_node(&self) -> Option<&DialogueNode> {
+        self.graph.as_ref()?.node(self.current.as_deref()?)
+    }
+
+    /// Choices of the current line whose conditions hold, with their index
+    /// in the node's choice list
+    pub fn available_choices<'a>(&'a self, variables: &GameVariables) -> Vec<(usize, &'a DialogueChoice)> {
+        let Some(node) = self.current_node() else {
+            return Vec::new();
+        };
+        node.choices
+            .iter()
+            .enumerate()
+            .filter(|(_, choice)| choice.condition.as_ref().is_none_or(|condition| condition.evaluate(variables)))
+            .collect()
+    }
+}
+
+/// Start a dialogue, replacing any that is playing
+#[derive(Event, Debug, Clone)]
+pub struct StartDialogueEvent {
+    pub graph: Arc<DialogueGraph>,
+    pub entity: Option<Entity>,
+}
+
+impl StartDialogueEvent {
+    pub fn new(graph: DialogueGraph, entity: Option<Entity>) -> Self {
+        Self {
+            graph: Arc::new(graph),
+            entity,
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>, entity: Option<Entity>) -> anyhow::Result<Self> {
+        Ok(Self::new(DialogueGraph::load(path)?, entity))
+    }
+}
+
+/// Player input for the playing dialogue
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum DialogueInputEvent {
+    /// Continue past a line without choices
+    Advance,
+    /// Pick a choice by its index in the node's choice list
+    Choose(usize),
+    /// Leave the dialogue
+    Close,
+}
+
+/// What happened in the playing dialogue
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum DialogueEvent {
+    Started { graph: String, entity: Option<Entity> },
+    Line { node: String, speaker: String, text: String },
+    Chose { node: String, choice: usize },
+    Ended { graph: String, entity: Option<Entity> },
+}
+
+pub fn run_dialogues(
+    mut starts: EventReader<StartDialogueEvent>,
+    mut inputs: EventReader<DialogueInputEvent>,
+    mut player: ResMut<DialoguePlayer>,
+    mut variables: ResMut<GameVariables>,
+    mut events: EventWriter<DialogueEvent>,
+) {
+    if let Some(start) = starts.read().last() {
+        if player.is_active() {
+            end_dialogue(&mut player, &mut events);
+        }
+        player.graph = Some(start.graph.clone());
+        player.entity = start.entity;
+        events.send(DialogueEvent::Started {
+            graph: start.graph.name.clone(),
+            entity: start.entity,
+        });
+        let first = start.graph.start.clone();
+        enter_node(&mut player, Some(first), &mut variables, &mut events);
+        // Input sent alongside the start was meant for the previous dialogue
+        inputs.clear();
+        return;
+    }
+
+    for input in inputs.read() {
+        let Some(node) = player.current_node().cloned() else {
+            break;
+        };
+        let next = match *input {
+            DialogueInputEvent::Advance if node.choices.is_empty() => node.next.clone(),
+            DialogueInputEvent::Choose(index) => {
+                let available = player
+                    .available_choices(&variables)
+                    .into_iter()
+                    .any(|(available, _)| available == index);
+                if !available {
+                    continue;
+                }
+                events.send(DialogueEvent::Chose {
+                    node: node.id.clone(),
+                    choice: index,
+                });
+                node.choices[index].next.clone()
+            }
+            DialogueInputEvent::Close => None,
+            DialogueInputEvent::Advance => continue,
+        };
+        enter_node(&mut player, next, &mut variables, &mut events);
+    }
+}
+
+fn enter_node(
+    player: &mut DialoguePlayer,
+    id: Option<String>,
+    variables: &mut GameVariables,
+    events: &mut EventWriter<DialogueEvent>,
+) {
+    player.current = id;
+    let Some(node) = player.current_node() else {
+        if let Some(id) = player.current.as_deref() {
+            warn!("Dialogue node \"{}\" does not exist; ending the dialogue", id);
+        }
+        end_dialogue(player, events);
+        return;
+    };
+    for flag in &node.set_flags {
+        variables.set_flag(flag.clone(), true);
+    }
+    events.send(DialogueEvent::Line {
+        node: node.id.clone(),
+        speaker: node.speaker.clone(),
+        text: node.text.clone(),
+    });
+}
+
+fn end_dialogue(player: &mut DialoguePlayer, events: &mut EventWriter<DialogueEvent>) {
+    if let Some(graph) = player.graph.take() {
+        events.send(DialogueEvent::Ended {
+            graph: graph.name.clone(),
+            entity: player.entity,
+        });
+    }
+    player.current = None;
+    player.entity = None;
+}
+
+/// Leave any dialogue when the play session stops
+pub fn close_dialogue_on_stop(player: Res<DialoguePlayer>, mut inputs: EventWriter<DialogueInputEvent>) {
+    if player.is_active() {
+        inputs.send(DialogueInputEvent::Close);
+    }
+}
@@ -70,6 +70,20 @@ pub enum InputEvent {
     MouseButtonReleased(MouseButton),
     MouseMoved(Vec2),
     MouseWheel(f32),
+    /// A finger or pen touched, moved on or left the screen
+    Touch(TouchPoint),
+}
+
+/// One finger or pen on a touch screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub phase: bevy::input::touch::TouchPhase,
+    /// Window position in logical pixels
+    pub position: Vec2,
+    /// How hard it presses, 0 to 1, on hardware that reports it
+    pub pressure: Option<f32>,
+    pub pen: bool,
 }
 
 /// Engine error event
@@ -0,0 +1,141 @@
+// Waffle Engine Golden Images
+// Rendering regression checks: a test scene's frame is compared against a
+// stored golden image with a perceptual color difference, so small driver
+// noise passes while real changes to lighting, sky or post-processing fail.
+
+use bevy::prelude::*;
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const GOLDEN_DIR: &str = "tests/golden";
+
+/// Largest YIQ color delta between two RGB colors, used to normalize deltas to 0..1
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+/// Golden-image check attached to a test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GoldenSettings {
+    /// Golden file name inside `GOLDEN_DIR`; defaults to the test name
+    pub image: Option<String>,
+    /// Per-pixel perceptual difference (0..1) below which pixels count as equal
+    pub pixel_tolerance: f32,
+    /// Fraction of pixels allowed to differ before the test fails
+    pub max_diff_fraction: f32,
+}
+
+impl Default for GoldenSettings {
+    fn default() -> Self {
+        Self {
+            image: None,
+            pixel_tolerance: 0.02,
+            max_diff_fraction: 0.001,
+        }
+    }
+}
+
+impl GoldenSettings {
+    pub fn golden_path(&self, test_name: &str) -> PathBuf {
+        let file = self
+            .image
+            .clone()
+            .unwrap_or_else(|| format!("{}.png", crate::core::testing::sanitize_file_name(test_name)));
+        PathBuf::from(GOLDEN_DIR).join(file)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoldenComparison {
+    pub differing_pixels: u64,
+    pub total_pixels: u64,
+    pub max_delta: f32,
+    /// Differing pixels in red over a faded copy of the golden
+    pub diff_image: RgbImage,
+}
+
+impl GoldenComparison {
+    pub fn diff_fraction(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.differing_pixels as f32 / self.total_pixels as f32
+    }
+
+    pub fn passes(&self, settings: &GoldenSettings) -> bool {
+        self.diff_fraction() <= settings.max_diff_fraction
+    }
+}
+
+/// Convert a captured frame to 8-bit RGB, dropping alpha
+pub fn image_to_rgb(image: Image) -> anyhow::Result<RgbImage> {
+    let dynamic = image
+        .try_into_dynamic()
+        .map_err(|err| anyhow::anyhow!("Unsupported screenshot format: {err}"))?;
+    let rgb = dynamic.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    RgbImage::from_raw(width, height, rgb.into_raw()).ok_or_else(|| anyhow::anyhow!("Invalid screenshot size"))
+}
+
+pub fn load_golden(path: impl AsRef<Path>) -> anyhow::Result<RgbImage> {
+    Ok(image::open(path)?.to_rgb8())
+}
+
+pub fn save_png(image: &RgbImage, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save(path)?;
+    Ok(())
+}
+
+pub fn compare_images(actual: &RgbImage, golden: &RgbImage, pixel_tolerance: f32) -> anyhow::Result<GoldenComparison> {
+    if actual.dimensions() != golden.dimensions() {
+        anyhow::bail!(
+            "Image size {:?} does not match golden size {:?}",
+            actual.dimensions(),
+            golden.dimensions()
+        );
+    }
+
+    let (width, height) = golden.dimensions();
+    let mut diff_image = RgbImage::new(width, height);
+    let mut differing_pixels = 0;
+    let mut max_delta: f32 = 0.0;
+    for (x, y, expected) in golden.enumerate_pixels() {
+        let delta = perceptual_delta(*actual.get_pixel(x, y), *expected);
+        max_delta = max_delta.max(delta);
+        let out = if delta > pixel_tolerance {
+            differing_pixels += 1;
+            Rgb([255, 0, 0])
+        } else {
+            let gray = (luma(*expected) * 0.3 + 0.7 * 255.0) as u8;
+            Rgb([gray, gray, gray])
+        };
+        diff_image.put_pixel(x, y, out);
+    }
+
+    Ok(GoldenComparison {
+        differing_pixels,
+        total_pixels: width as u64 * height as u64,
+        max_delta,
+        diff_image,
+    })
+}
+
+fn luma(color: Rgb<u8>) -> f32 {
+    let [r, g, b] = color.0.map(f32::from);
+    r * 0.2988953 + g * 0.5866225 + b * 0.11448223
+}
+
+/// Color difference in YIQ space, weighted towards brightness as the eye is
+fn perceptual_delta(a: Rgb<u8>, b: Rgb<u8>) -> f32 {
+    let [r1, g1, b1] = a.0.map(f32::from);
+    let [r2, g2, b2] = b.0.map(f32::from);
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+    let y = dr * 0.2988953 + dg * 0.5866225 + db * 0.11448223;
+    let i = dr * 0.59597799 - dg * 0.2741761 - db * 0.3218019;
+    let q = dr * 0.21147017 - dg * 0.5226171 + db * 0.31114694;
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_YIQ_DELTA
+}
@@ -0,0 +1,79 @@
+// Waffle Engine Panic Guards
+// Keeps a panic in project code from taking the editor or game down with it.
+// The panic is caught and logged as an error, which also shows it in the
+// editor's Output panel. The code that panicked is then disabled until it is
+// enabled again.
+//
+// Plugin systems opt in by wrapping them:
+//     app.add_systems(Update, guarded("spawn_waves", spawn_waves));
+// Editor extension callbacks are always guarded. Catching panics needs the
+// default `panic = "unwind"` profile setting.
+
+use bevy::prelude::*;
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Run `f`, turning a panic into an error holding the panic message
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+/// Guarded systems that panicked, with their panic message
+#[derive(Resource, Debug, Default)]
+pub struct DisabledSystems {
+    pub systems: Vec<(String, String)>,
+}
+
+impl DisabledSystems {
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.systems.iter().any(|(disabled, _)| disabled == name)
+    }
+
+    pub fn enable(&mut self, name: &str) {
+        self.systems.retain(|(disabled, _)| disabled != name);
+    }
+
+    fn disable(&mut self, name: &str, message: String) {
+        if !self.is_disabled(name) {
+            self.systems.push((name.to_string(), message));
+        }
+    }
+}
+
+/// Run `system` exclusively, disabling it instead of crashing when it panics
+pub fn guarded<M>(
+    name: impl Into<String>,
+    system: impl IntoSystem<(), (), M>,
+) -> impl FnMut(&mut World) + Send + Sync + 'static {
+    let name = name.into();
+    let mut system = IntoSystem::into_system(system);
+    let mut initialized = false;
+    move |world: &mut World| {
+        if world
+            .get_resource::<DisabledSystems>()
+            .is_some_and(|disabled| disabled.is_disabled(&name))
+        {
+            return;
+        }
+        if !initialized {
+            system.initialize(world);
+            initialized = true;
+        }
+        if let Err(message) = catch_panic(|| system.run((), world)) {
+            error!("System \"{}\" panicked and was disabled: {}", name, message);
+            world
+                .get_resource_or_insert_with(DisabledSystems::default)
+                .disable(&name, message);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Waffle Engine Health and Damage
+// Optional gameplay scaffolding: health, teams, damage and death events
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Health restored per second while alive
+    pub regeneration: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            regeneration: 0.0,
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            0.0
+        } else {
+            (self.current / self.max).clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(100.0)
+    }
+}
+
+/// Lets an entity with `Health` receive damage events
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Damageable {
+    pub multiplier: f32,
+    /// Seconds of invulnerability after each hit
+    pub invulnerability: f32,
+    #[serde(skip)]
+    pub invulnerable_for: f32,
+}
+
+impl Default for Damageable {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            invulnerability: 0.0,
+            invulnerable_for: 0.0,
+        }
+    }
+}
+
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Team(pub u32);
+
+/// Added once an entity's health reaches zero
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Dead;
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GameplayRules {
+    pub friendly_fire: bool,
+}
+
+/// Request to damage an entity
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub source: Option<Entity>,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+/// Damage that actually landed, after teams, multipliers and invulnerability
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageAppliedEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub remaining: f32,
+    pub source: Option<Entity>,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+pub fn regenerate_health(
+    time: Res<Time>,
+    mut query: Query<(&mut Health, Option<&mut Damageable>), Without<Dead>>,
+) {
+    let delta = time.delta_seconds();
+    for (mut health, damageable) in &mut query {
+        if health.regeneration > 0.0 && health.current < health.max {
+            health.current = (health.current + health.regeneration * delta).min(health.max);
+        }
+        if let Some(mut damageable) = damageable {
+            if damageable.invulnerable_for > 0.0 {
+                damageable.invulnerable_for = (damageable.invulnerable_for - delta).max(0.0);
+            }
+        }
+    }
+}
+
+pub fn apply_damage_events(
+    mut commands: Commands,
+    rules: Res<GameplayRules>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<(&mut Health, &mut Damageable, Option<&Team>), Without<Dead>>,
+    teams: Query<&Team>,
+    mut applied: EventWriter<DamageAppliedEvent>,
+    mut deaths: EventWriter<DeathEvent>,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, mut damageable, team)) = targets.get_mut(event.target) else {
+            continue;
+        };
+        if health.is_dead() || damageable.invulnerable_for > 0.0 || event.amount <= 0.0 {
+            continue;
+        }
+        let source_team = event.source.and_then(|source| teams.get(source).ok());
+        if !rules.friendly_fire && team.is_some() && team == source_team && event.source != Some(event.target) {
+            continue;
+        }
+
+        let amount = event.amount * damageable.multiplier;
+        health.current = (health.current - amount).max(0.0);
+        damageable.invulnerable_for = damageable.invulnerability;
+        applied.send(DamageAppliedEvent {
+            target: event.target,
+            amount,
+            remaining: health.current,
+            source: event.source,
+        });
+
+        if health.is_dead() {
+            commands.entity(event.target).insert(Dead);
+            deaths.send(DeathEvent {
+                entity: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+pub fn apply_heal_events(mut heal_events: EventReader<HealEvent>, mut targets: Query<&mut Health, Without<Dead>>) {
+    for event in heal_events.read() {
+        if let Ok(mut health) = targets.get_mut(event.target) {
+            health.current = (health.current + event.amount.max(0.0)).min(health.max);
+        }
+    }
+}
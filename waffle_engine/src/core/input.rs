@@ -0,0 +1,370 @@
+// Waffle Engine Input Axes
+// How raw mouse and gamepad axes become the values controllers act on. Every
+// axis has a dead zone that swallows small movements, a sensitivity that
+// scales it and can invert it, and, for sticks and triggers, a response curve
+// that trades speed near the center for precision. The settings live in the
+// project's input config; gamepad dead zones are also handed to Bevy, so
+// everything reading gamepads directly sees the same ones.
+// Touch screens and pens arrive as touches. They are passed on as
+// `InputEvent::Touch`, and the fingers on the screen are read together as a
+// gesture: a drag of their center and a pinch of their spread.
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{AxisSettings, ButtonAxisSettings, GamepadSettings};
+use bevy::input::touch::{ForceTouch, TouchInput};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::core::events::{InputEvent, TouchPoint};
+use crate::core::project::ProjectSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAxis {
+    MouseX,
+    MouseY,
+    MouseWheel,
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl InputAxis {
+    pub const ALL: [InputAxis; 9] = [
+        InputAxis::MouseX,
+        InputAxis::MouseY,
+        InputAxis::MouseWheel,
+        InputAxis::LeftStickX,
+        InputAxis::LeftStickY,
+        InputAxis::RightStickX,
+        InputAxis::RightStickY,
+        InputAxis::LeftTrigger,
+        InputAxis::RightTrigger,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputAxis::MouseX => "Mouse X",
+            InputAxis::MouseY => "Mouse Y",
+            InputAxis::MouseWheel => "Mouse Wheel",
+            InputAxis::LeftStickX => "Left Stick X",
+            InputAxis::LeftStickY => "Left Stick Y",
+            InputAxis::RightStickX => "Right Stick X",
+            InputAxis::RightStickY => "Right Stick Y",
+            InputAxis::LeftTrigger => "Left Trigger",
+            InputAxis::RightTrigger => "Right Trigger",
+        }
+    }
+
+    /// Sticks and triggers run from -1 or 0 to 1; mouse axes are deltas in
+    /// pixels or lines with no limit
+    pub fn is_bounded(self) -> bool {
+        !matches!(self, InputAxis::MouseX | InputAxis::MouseY | InputAxis::MouseWheel)
+    }
+
+    fn gamepad_axis(self) -> Option<GamepadAxisType> {
+        match self {
+            InputAxis::LeftStickX => Some(GamepadAxisType::LeftStickX),
+            InputAxis::LeftStickY => Some(GamepadAxisType::LeftStickY),
+            InputAxis::RightStickX => Some(GamepadAxisType::RightStickX),
+            InputAxis::RightStickY => Some(GamepadAxisType::RightStickY),
+            _ => None,
+        }
+    }
+
+    fn gamepad_trigger(self) -> Option<GamepadButtonType> {
+        match self {
+            InputAxis::LeftTrigger => Some(GamepadButtonType::LeftTrigger2),
+            InputAxis::RightTrigger => Some(GamepadButtonType::RightTrigger2),
+            _ => None,
+        }
+    }
+}
+
+/// Shape of a stick or trigger's response between the dead zone and full tilt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Slow near the center, for aiming
+    Quadratic,
+    /// Slower still near the center
+    Cubic,
+    /// Gentle at both ends, quick in the middle
+    SCurve,
+}
+
+impl ResponseCurve {
+    pub const ALL: [ResponseCurve; 4] = [
+        ResponseCurve::Linear,
+        ResponseCurve::Quadratic,
+        ResponseCurve::Cubic,
+        ResponseCurve::SCurve,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ResponseCurve::Linear => "Linear",
+            ResponseCurve::Quadratic => "Quadratic",
+            ResponseCurve::Cubic => "Cubic",
+            ResponseCurve::SCurve => "S-Curve",
+        }
+    }
+
+    /// Map 0..1 onto 0..1
+    pub fn apply(self, amount: f32) -> f32 {
+        let amount = amount.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => amount,
+            ResponseCurve::Quadratic => amount * amount,
+            ResponseCurve::Cubic => amount * amount * amount,
+            ResponseCurve::SCurve => amount * amount * (3.0 - 2.0 * amount),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputAxisSettings {
+    /// Movement smaller than this is ignored: a fraction of full tilt for
+    /// sticks and triggers, pixels or lines for mouse axes
+    pub dead_zone: f32,
+    pub sensitivity: f32,
+    pub invert: bool,
+    /// Only used by sticks and triggers
+    pub curve: ResponseCurve,
+}
+
+impl Default for InputAxisSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.0,
+            sensitivity: 1.0,
+            invert: false,
+            curve: ResponseCurve::Linear,
+        }
+    }
+}
+
+impl InputAxisSettings {
+    /// Settings an axis has until the project changes them
+    pub fn default_for(axis: InputAxis) -> Self {
+        let dead_zone = match axis {
+            InputAxis::LeftStickX | InputAxis::LeftStickY | InputAxis::RightStickX | InputAxis::RightStickY => 0.15,
+            InputAxis::LeftTrigger | InputAxis::RightTrigger => 0.05,
+            InputAxis::MouseX | InputAxis::MouseY | InputAxis::MouseWheel => 0.0,
+        };
+        Self {
+            dead_zone,
+            ..default()
+        }
+    }
+
+    /// Turn a raw value of `axis` into the value controllers use. Sticks and
+    /// triggers are rescaled so they start from zero at the dead zone's edge
+    /// and still reach one at full tilt.
+    pub fn apply(&self, axis: InputAxis, raw: f32) -> f32 {
+        let magnitude = raw.abs();
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+        let shaped = if axis.is_bounded() {
+            let live = ((magnitude - self.dead_zone) / (1.0 - self.dead_zone).max(f32::EPSILON)).min(1.0);
+            self.curve.apply(live)
+        } else {
+            magnitude
+        };
+        let sign = if self.invert { -raw.signum() } else { raw.signum() };
+        shaped * sign * self.sensitivity
+    }
+}
+
+/// Per-axis input settings, stored in the project settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    /// Axes the project has changed; the rest use their defaults
+    pub axes: BTreeMap<InputAxis, InputAxisSettings>,
+}
+
+impl InputConfig {
+    pub fn axis(&self, axis: InputAxis) -> InputAxisSettings {
+        self.axes
+            .get(&axis)
+            .copied()
+            .unwrap_or_else(|| InputAxisSettings::default_for(axis))
+    }
+
+    pub fn axis_mut(&mut self, axis: InputAxis) -> &mut InputAxisSettings {
+        self.axes
+            .entry(axis)
+            .or_insert_with(|| InputAxisSettings::default_for(axis))
+    }
+
+    /// A raw value of `axis` with its settings applied
+    pub fn apply(&self, axis: InputAxis, raw: f32) -> f32 {
+        self.axis(axis).apply(axis, raw)
+    }
+}
+
+/// System parameter for gamepad sticks and triggers with the project's
+/// settings applied. With several gamepads, the one pushed furthest wins.
+#[derive(SystemParam)]
+pub struct InputAxes<'w> {
+    project_settings: Res<'w, ProjectSettings>,
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+}
+
+impl<'w> InputAxes<'w> {
+    pub fn config(&self) -> &InputConfig {
+        &self.project_settings.input
+    }
+
+    /// A gamepad axis; always zero for mouse axes, which arrive as events
+    pub fn value(&self, axis: InputAxis) -> f32 {
+        let raw = self
+            .gamepads
+            .iter()
+            .filter_map(|gamepad| {
+                if let Some(axis_type) = axis.gamepad_axis() {
+                    self.axes.get(GamepadAxis::new(gamepad, axis_type))
+                } else {
+                    let button_type = axis.gamepad_trigger()?;
+                    self.button_axes.get(GamepadButton::new(gamepad, button_type))
+                }
+            })
+            .fold(0.0_f32, |furthest, value| if value.abs() > furthest.abs() { value } else { furthest });
+        self.config().apply(axis, raw)
+    }
+
+    /// A stick's X and Y together
+    pub fn stick(&self, x: InputAxis, y: InputAxis) -> Vec2 {
+        Vec2::new(self.value(x), self.value(y))
+    }
+}
+
+/// Hand the project's gamepad dead zones to Bevy, for every connected gamepad
+pub fn apply_gamepad_dead_zones(
+    project_settings: Res<ProjectSettings>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_settings: ResMut<GamepadSettings>,
+) {
+    if !project_settings.is_changed() && !gamepads.is_changed() {
+        return;
+    }
+    let config = &project_settings.input;
+    let dead_zone = |axis| config.axis(axis).dead_zone.clamp(0.0, 0.95);
+
+    for gamepad in gamepads.iter() {
+        for axis in InputAxis::ALL {
+            if let Some(axis_type) = axis.gamepad_axis() {
+                let zone = dead_zone(axis);
+                if let Ok(settings) = AxisSettings::new(-1.0, -zone, zone, 1.0, 0.01) {
+                    gamepad_settings
+                        .axis_settings
+                        .insert(GamepadAxis::new(gamepad, axis_type), settings);
+                }
+            } else if let Some(button_type) = axis.gamepad_trigger() {
+                gamepad_settings.button_axis_settings.insert(
+                    GamepadButton::new(gamepad, button_type),
+                    ButtonAxisSettings {
+                        low: dead_zone(axis),
+                        ..default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// How hard a touch presses, 0 to 1
+pub fn touch_pressure(force: ForceTouch) -> f32 {
+    let pressure = match force {
+        ForceTouch::Calibrated {
+            force,
+            max_possible_force,
+            ..
+        } => force / max_possible_force.max(f64::EPSILON),
+        ForceTouch::Normalized(force) => force,
+    };
+    (pressure as f32).clamp(0.0, 1.0)
+}
+
+/// Touches that report a pressure are taken for pens, since few touch
+/// screens measure how hard a finger presses
+pub fn is_pen_touch(force: Option<ForceTouch>) -> bool {
+    force.is_some()
+}
+
+/// Pass window touches on as input events
+pub fn forward_touch_input(mut touches: EventReader<TouchInput>, mut events: EventWriter<InputEvent>) {
+    for touch in touches.read() {
+        events.send(InputEvent::Touch(TouchPoint {
+            id: touch.id,
+            phase: touch.phase,
+            position: touch.position,
+            pressure: touch.force.map(touch_pressure),
+            pen: is_pen_touch(touch.force),
+        }));
+    }
+}
+
+/// How the fingers on the screen moved since last frame. Pens are left out:
+/// they point and draw rather than steer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchGesture {
+    pub fingers: usize,
+    /// Center of the fingers, in logical window pixels
+    pub center: Vec2,
+    /// Movement of the center in logical pixels
+    pub drag: Vec2,
+    /// Spread of the fingers over last frame's; above one when they move apart
+    pub pinch: f32,
+}
+
+impl Default for TouchGesture {
+    fn default() -> Self {
+        Self {
+            fingers: 0,
+            center: Vec2::ZERO,
+            drag: Vec2::ZERO,
+            pinch: 1.0,
+        }
+    }
+}
+
+impl TouchGesture {
+    pub fn read(touches: &Touches) -> Self {
+        let fingers: Vec<_> = touches.iter().filter(|touch| !is_pen_touch(touch.force())).collect();
+        if fingers.is_empty() {
+            return Self::default();
+        }
+        // A finger that just landed has not moved, so it shifts both
+        // centers alike and the gesture doesn't jump
+        let count = fingers.len() as f32;
+        let center = fingers.iter().map(|touch| touch.position()).sum::<Vec2>() / count;
+        let previous_center = fingers.iter().map(|touch| touch.previous_position()).sum::<Vec2>() / count;
+        let spread = fingers.iter().map(|touch| touch.position().distance(center)).sum::<f32>() / count;
+        let previous_spread = fingers
+            .iter()
+            .map(|touch| touch.previous_position().distance(previous_center))
+            .sum::<f32>()
+            / count;
+        let pinch = if fingers.len() > 1 && previous_spread > 1.0 {
+            spread / previous_spread
+        } else {
+            1.0
+        };
+        Self {
+            fingers: fingers.len(),
+            center,
+            drag: center - previous_center,
+            pinch,
+        }
+    }
+}
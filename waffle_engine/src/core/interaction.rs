@@ -0,0 +1,164 @@
+// Waffle Engine Interaction
+// Use and pickup scaffolding: the `Interactable` the active camera looks at
+// becomes the interaction focus, and pressing the interact key sends an
+// `InteractEvent` for it that gameplay code and scripts react to.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::spatial::{RayHit, SpatialQuery};
+use crate::rendering::camera::CameraSettings;
+use crate::rendering::highlight::Highlight;
+
+/// Something the player can use, open or pick up
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Interactable {
+    /// Shown to the player while focused, e.g. "Open door"
+    pub prompt: String,
+    /// Furthest distance from the camera it can be used from
+    pub range: f32,
+    pub enabled: bool,
+    /// Show `prompt` over the entity in the world while focused
+    pub show_prompt: bool,
+    /// Where the prompt is drawn, relative to the entity's origin
+    pub prompt_offset: Vec3,
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Self {
+            prompt: "Use".to_string(),
+            range: 2.5,
+            enabled: true,
+            show_prompt: true,
+            prompt_offset: Vec3::Y * 0.5,
+        }
+    }
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..default()
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range.max(0.0);
+        self
+    }
+
+    pub fn with_prompt_offset(mut self, offset: Vec3) -> Self {
+        self.prompt_offset = offset;
+        self
+    }
+
+    pub fn without_prompt(mut self) -> Self {
+        self.show_prompt = false;
+        self
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct InteractionSettings {
+    pub key: KeyCode,
+    /// Longest ray cast for interactables, whatever their own range
+    pub max_distance: f32,
+    /// Drawn around the focused interactable, unless it has its own `Highlight`
+    pub focus_highlight: Option<Highlight>,
+}
+
+impl Default for InteractionSettings {
+    fn default() -> Self {
+        Self {
+            key: KeyCode::KeyF,
+            max_distance: 10.0,
+            focus_highlight: Some(Highlight::outline(Color::srgb(1.0, 0.85, 0.3))),
+        }
+    }
+}
+
+/// The interactable the active camera is looking at, if any is in range
+#[derive(Resource, Debug, Clone, Default)]
+pub struct InteractionFocus {
+    pub entity: Option<Entity>,
+    pub point: Option<Vec3>,
+}
+
+/// Sent when the player uses an interactable; the `on_interact` hook for
+/// gameplay code and scripts
+#[derive(Event, Debug, Clone, Copy)]
+pub struct InteractEvent {
+    pub entity: Entity,
+    pub point: Vec3,
+}
+
+/// Cast a ray and return the interactable it hits, with the hit. Meshes that
+/// are children of an interactable count as the interactable itself, and
+/// anything else in the way blocks the ray.
+pub fn interaction_raycast(
+    spatial: &SpatialQuery,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    interactables: &Query<&Interactable>,
+    parents: &Query<&Parent>,
+) -> Option<(Entity, RayHit)> {
+    let hit = spatial.cast_ray(origin, direction, max_distance)?;
+    let owner = std::iter::once(hit.entity)
+        .chain(parents.iter_ancestors(hit.entity))
+        .find(|entity| interactables.contains(*entity))?;
+    let interactable = interactables.get(owner).ok()?;
+    (interactable.enabled && hit.distance <= interactable.range).then_some((owner, hit))
+}
+
+pub fn update_interaction_focus(
+    settings: Res<InteractionSettings>,
+    camera_settings: Option<Res<CameraSettings>>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    spatial: SpatialQuery,
+    interactables: Query<&Interactable>,
+    parents: Query<&Parent>,
+    mut focus: ResMut<InteractionFocus>,
+) {
+    let camera = camera_settings
+        .and_then(|settings| settings.active_camera_entity)
+        .and_then(|entity| cameras.get(entity).ok());
+    let target = camera.and_then(|camera| {
+        interaction_raycast(
+            &spatial,
+            camera.translation(),
+            camera.forward().into(),
+            settings.max_distance,
+            &interactables,
+            &parents,
+        )
+    });
+
+    let entity = target.map(|(entity, _)| entity);
+    let point = target.map(|(_, hit)| hit.point);
+    if focus.entity != entity || focus.point != point {
+        focus.entity = entity;
+        focus.point = point;
+    }
+}
+
+pub fn trigger_interactions(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<InteractionSettings>,
+    focus: Res<InteractionFocus>,
+    mut events: EventWriter<InteractEvent>,
+) {
+    if !keyboard.just_pressed(settings.key) {
+        return;
+    }
+    if let (Some(entity), Some(point)) = (focus.entity, focus.point) {
+        events.send(InteractEvent { entity, point });
+    }
+}
+
+/// Drop the focus when play stops so no prompt lingers in the editor
+pub fn clear_interaction_focus(mut focus: ResMut<InteractionFocus>) {
+    *focus = InteractionFocus::default();
+}
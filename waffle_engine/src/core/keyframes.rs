@@ -0,0 +1,321 @@
+// Waffle Engine Property Keyframes
+// Animates properties of any entity from keyframes: its transform, the
+// intensity and color of its light and the base color of its material. Each
+// track holds the keys of one property, and between two keys the value moves
+// linearly, eased or not at all until the next key. Tracks play while the
+// game runs; stopping play puts every animated value back.
+
+use bevy::ecs::query::QueryData;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Keys closer together than this are the same key
+const KEY_TIME_EPSILON: f32 = 0.001;
+
+/// How a value moves from one key to the next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum KeyInterpolation {
+    #[default]
+    Linear,
+    /// Slow out of the key and into the next
+    Ease,
+    /// Hold the value until the next key
+    Step,
+}
+
+impl KeyInterpolation {
+    pub const ALL: [KeyInterpolation; 3] = [KeyInterpolation::Linear, KeyInterpolation::Ease, KeyInterpolation::Step];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyInterpolation::Linear => "Linear",
+            KeyInterpolation::Ease => "Ease",
+            KeyInterpolation::Step => "Step",
+        }
+    }
+
+    /// Map the progress between two keys, 0 to 1
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            KeyInterpolation::Linear => t,
+            KeyInterpolation::Ease => t * t * (3.0 - 2.0 * t),
+            KeyInterpolation::Step => 0.0,
+        }
+    }
+}
+
+/// A property keyframe tracks can animate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum KeyedProperty {
+    Translation,
+    Rotation,
+    Scale,
+    /// Intensity of a point or spot light, illuminance of a directional one
+    LightIntensity,
+    LightColor,
+    /// Base color of the entity's material
+    BaseColor,
+}
+
+impl KeyedProperty {
+    pub const TRANSFORM: [KeyedProperty; 3] = [KeyedProperty::Translation, KeyedProperty::Rotation, KeyedProperty::Scale];
+    pub const LIGHT: [KeyedProperty; 2] = [KeyedProperty::LightIntensity, KeyedProperty::LightColor];
+    pub const MATERIAL: [KeyedProperty; 1] = [KeyedProperty::BaseColor];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyedProperty::Translation => "Position",
+            KeyedProperty::Rotation => "Rotation",
+            KeyedProperty::Scale => "Scale",
+            KeyedProperty::LightIntensity => "Light Intensity",
+            KeyedProperty::LightColor => "Light Color",
+            KeyedProperty::BaseColor => "Base Color",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds from the start of the animation
+    pub time: f32,
+    /// The value packed into four floats: a vector in xyz, a quaternion, an
+    /// intensity in x or a linear RGBA color
+    pub value: Vec4,
+    pub interpolation: KeyInterpolation,
+}
+
+/// The keys of one property, in time order
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct KeyframeTrack {
+    pub property: KeyedProperty,
+    pub keys: Vec<Keyframe>,
+}
+
+impl KeyframeTrack {
+    /// Add a key, replacing one at the same time
+    pub fn insert(&mut self, key: Keyframe) {
+        match self
+            .keys
+            .iter()
+            .position(|existing| existing.time >= key.time - KEY_TIME_EPSILON)
+        {
+            Some(index) if (self.keys[index].time - key.time).abs() <= KEY_TIME_EPSILON => self.keys[index] = key,
+            Some(index) => self.keys.insert(index, key),
+            None => self.keys.push(key),
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |key| key.time)
+    }
+
+    /// The value at `time`, holding the first and last keys beyond the ends
+    pub fn sample(&self, time: f32) -> Option<Vec4> {
+        let first = self.keys.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        let next_index = self.keys.iter().position(|key| key.time > time);
+        let Some(next_index) = next_index else {
+            return self.keys.last().map(|key| key.value);
+        };
+        let from = &self.keys[next_index - 1];
+        let to = &self.keys[next_index];
+        let span = (to.time - from.time).max(f32::EPSILON);
+        let t = from.interpolation.apply(((time - from.time) / span).clamp(0.0, 1.0));
+        Some(match self.property {
+            KeyedProperty::Rotation => {
+                let from = Quat::from_vec4(from.value).normalize();
+                let to = Quat::from_vec4(to.value).normalize();
+                Vec4::from(from.slerp(to, t))
+            }
+            _ => from.value.lerp(to.value, t),
+        })
+    }
+}
+
+/// Animates properties of the entity it is on
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PropertyAnimation {
+    pub tracks: Vec<KeyframeTrack>,
+    pub looping: bool,
+    pub speed: f32,
+    /// Seconds into the animation while playing
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub time: f32,
+    /// Reached the end without looping
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub finished: bool,
+}
+
+impl Default for PropertyAnimation {
+    fn default() -> Self {
+        Self {
+            tracks: Vec::new(),
+            looping: false,
+            speed: 1.0,
+            time: 0.0,
+            finished: false,
+        }
+    }
+}
+
+impl PropertyAnimation {
+    /// Time of the last key of any track
+    pub fn duration(&self) -> f32 {
+        self.tracks.iter().map(KeyframeTrack::duration).fold(0.0, f32::max)
+    }
+
+    /// The track of `property`, added if there is none
+    pub fn track_mut(&mut self, property: KeyedProperty) -> &mut KeyframeTrack {
+        let index = match self.tracks.iter().position(|track| track.property == property) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(KeyframeTrack {
+                    property,
+                    keys: Vec::new(),
+                });
+                self.tracks.len() - 1
+            }
+        };
+        &mut self.tracks[index]
+    }
+}
+
+/// The components keyframe tracks read and write
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct KeyedTargetQuery {
+    transform: Option<&'static mut Transform>,
+    point_light: Option<&'static mut PointLight>,
+    spot_light: Option<&'static mut SpotLight>,
+    directional_light: Option<&'static mut DirectionalLight>,
+    material: Option<&'static Handle<StandardMaterial>>,
+}
+
+fn color_to_vec4(color: Color) -> Vec4 {
+    let color = color.to_linear();
+    Vec4::new(color.red, color.green, color.blue, color.alpha)
+}
+
+fn vec4_to_color(value: Vec4) -> Color {
+    Color::LinearRgba(LinearRgba::new(value.x, value.y, value.z, value.w))
+}
+
+impl KeyedTargetQueryReadOnlyItem<'_> {
+    /// The current value of `property`, if the entity has it
+    pub fn read(&self, property: KeyedProperty, materials: &Assets<StandardMaterial>) -> Option<Vec4> {
+        match property {
+            KeyedProperty::Translation => self.transform.map(|transform| transform.translation.extend(0.0)),
+            KeyedProperty::Rotation => self.transform.map(|transform| Vec4::from(transform.rotation)),
+            KeyedProperty::Scale => self.transform.map(|transform| transform.scale.extend(0.0)),
+            KeyedProperty::LightIntensity => self
+                .point_light
+                .map(|light| light.intensity)
+                .or(self.spot_light.map(|light| light.intensity))
+                .or(self.directional_light.map(|light| light.illuminance))
+                .map(|intensity| Vec4::new(intensity, 0.0, 0.0, 0.0)),
+            KeyedProperty::LightColor => self
+                .point_light
+                .map(|light| light.color)
+                .or(self.spot_light.map(|light| light.color))
+                .or(self.directional_light.map(|light| light.color))
+                .map(color_to_vec4),
+            KeyedProperty::BaseColor => self
+                .material
+                .and_then(|handle| materials.get(handle))
+                .map(|material| color_to_vec4(material.base_color)),
+        }
+    }
+}
+
+impl KeyedTargetQueryItem<'_> {
+    /// Set `property` to a sampled value. Entities sharing a material share
+    /// its animated base color.
+    pub fn write(&mut self, property: KeyedProperty, value: Vec4, materials: &mut Assets<StandardMaterial>) {
+        match property {
+            KeyedProperty::Translation => {
+                if let Some(transform) = self.transform.as_mut() {
+                    transform.translation = value.truncate();
+                }
+            }
+            KeyedProperty::Rotation => {
+                if let Some(transform) = self.transform.as_mut() {
+                    transform.rotation = Quat::from_vec4(value).normalize();
+                }
+            }
+            KeyedProperty::Scale => {
+                if let Some(transform) = self.transform.as_mut() {
+                    transform.scale = value.truncate();
+                }
+            }
+            KeyedProperty::LightIntensity => {
+                if let Some(light) = self.point_light.as_mut() {
+                    light.intensity = value.x;
+                }
+                if let Some(light) = self.spot_light.as_mut() {
+                    light.intensity = value.x;
+                }
+                if let Some(light) = self.directional_light.as_mut() {
+                    light.illuminance = value.x;
+                }
+            }
+            KeyedProperty::LightColor => {
+                let color = vec4_to_color(value);
+                if let Some(light) = self.point_light.as_mut() {
+                    light.color = color;
+                }
+                if let Some(light) = self.spot_light.as_mut() {
+                    light.color = color;
+                }
+                if let Some(light) = self.directional_light.as_mut() {
+                    light.color = color;
+                }
+            }
+            KeyedProperty::BaseColor => {
+                if let Some(material) = self.material.and_then(|handle| materials.get_mut(handle)) {
+                    material.base_color = vec4_to_color(value);
+                }
+            }
+        }
+    }
+}
+
+/// Every animation starts from its first key when play starts
+pub fn reset_property_animations(mut animations: Query<&mut PropertyAnimation>) {
+    for mut animation in &mut animations {
+        animation.time = 0.0;
+        animation.finished = false;
+    }
+}
+
+/// Advance each animation and write its tracks' values
+pub fn play_property_animations(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut animations: Query<(&mut PropertyAnimation, KeyedTargetQuery)>,
+) {
+    for (mut animation, mut target) in &mut animations {
+        // A finished animation leaves its values to whatever changes them next
+        if animation.finished {
+            continue;
+        }
+        let duration = animation.duration();
+        let mut now = animation.time + time.delta_seconds() * animation.speed;
+        if animation.looping && duration > 0.0 {
+            now = now.rem_euclid(duration);
+        } else {
+            now = now.clamp(0.0, duration);
+            animation.finished = now >= duration;
+        }
+        animation.time = now;
+        for track in &animation.tracks {
+            if let Some(value) = track.sample(now) {
+                target.write(track.property, value, &mut materials);
+            }
+        }
+    }
+}
@@ -6,14 +6,83 @@ pub mod systems;
 pub mod components;
 pub mod resources;
 pub mod events;
+pub mod spatial;
+pub mod surface;
+pub mod asset_meta;
+pub mod input;
+pub mod animation;
+pub mod keyframes;
+pub mod state_machine;
+pub mod constraints;
+pub mod tween;
+pub mod random;
+pub mod ai;
+pub mod pool;
+pub mod health;
+pub mod projectile;
+pub mod destruction;
+pub mod vehicle;
+pub mod interaction;
+pub mod variables;
+pub mod dialogue;
+pub mod quest;
+pub mod savegame;
+pub mod achievements;
+pub mod presence;
+pub mod analytics;
+pub mod guard;
+pub mod system_toggles;
+pub mod resource_config;
+pub mod bundles;
+#[cfg(feature = "steam")]
+pub mod steam;
+pub mod project;
+pub mod play;
+pub mod cursor;
+pub mod debug_draw;
+pub mod replay;
+pub mod testing;
+pub mod golden;
 
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
 
 use config::*;
 use systems::*;
 use components::*;
 use resources::*;
 use events::*;
+use constraints::*;
+use tween::*;
+use random::*;
+use ai::*;
+use pool::*;
+use health::*;
+use projectile::*;
+use surface::*;
+use asset_meta::*;
+use input::*;
+use animation::*;
+use keyframes::*;
+use state_machine::*;
+use destruction::*;
+use vehicle::*;
+use interaction::*;
+use variables::*;
+use dialogue::*;
+use quest::*;
+use savegame::*;
+use achievements::*;
+use presence::*;
+use analytics::*;
+use guard::*;
+use system_toggles::*;
+use resource_config::*;
+use project::*;
+use play::*;
+use cursor::*;
+use debug_draw::*;
+use replay::*;
 
 // Core plugin group
 pub struct WaffleCorePlugin;
@@ -22,13 +91,165 @@ impl Plugin for WaffleCorePlugin {
     fn build(&self, app: &mut App) {
         // Add core systems
         app.add_systems(Startup, setup_core_systems)
+            .add_systems(Startup, load_project_settings)
             .add_systems(Update, update_core_systems)
             .add_systems(PostUpdate, post_update_core_systems)
 
+            // Gameplay tweens and timers advance while playing
+            .add_systems(Update, (update_tweens, update_game_timers).run_if(is_playing))
+
+            // Behavior trees
+            .add_systems(Startup, setup_behavior_registry)
+            .add_systems(Update, tick_behavior_trees.run_if(is_playing))
+
+            // Entity pools; despawned instances are forgotten before gameplay asks for one
+            .add_systems(First, prune_entity_pools)
+            .add_systems(Update, prewarm_entity_pools)
+
+            // Health and damage only change while playing
+            .add_systems(
+                Update,
+                (regenerate_health, apply_heal_events, apply_damage_events).chain().run_if(is_playing),
+            )
+
+            // Projectiles feed their hits into the damage pipeline
+            .add_systems(Update, update_projectiles.before(apply_damage_events).run_if(is_playing))
+
+            // Surfaces tell footsteps and impacts what they touched
+            .init_resource::<MaterialSurfaces>()
+            .add_systems(Update, emit_footsteps.run_if(is_playing))
+            .add_systems(Update, report_projectile_impacts.after(update_projectiles).run_if(is_playing))
+            .add_systems(OnEnter(PlayState::Playing), reset_footsteps)
+
+            // Gamepad dead zones from the project's input config
+            .add_systems(PreUpdate, apply_gamepad_dead_zones)
+            .add_systems(PreUpdate, forward_touch_input.after(bevy::input::InputSystem))
+
+            // Model animation clips played by their animators
+            .add_systems(Update, (bind_animators, load_animator_clips, drive_animators).chain())
+
+            // State machines pick the clips of animators they are on
+            .init_asset::<AnimationStateGraph>()
+            .init_asset_loader::<AnimationStateGraphLoader>()
+            .add_systems(Update, (bind_state_machines, drive_state_machines).chain().after(drive_animators))
+            .add_systems(OnEnter(PlayState::Playing), reset_state_machines)
+
+            // Keyframed properties play while the game runs
+            .add_systems(OnEnter(PlayState::Playing), reset_property_animations)
+            .add_systems(Update, play_property_animations.run_if(is_playing))
+
+            // Import settings from `.meta` sidecars, applied as assets load
+            .add_systems(
+                Update,
+                (apply_image_import_settings, apply_mesh_import_settings, apply_scene_import_settings),
+            )
+
+            // Destructible meshes break into pre-fractured debris while playing
+            .init_asset::<FracturePrefab>()
+            .add_systems(Update, (
+                prefracture_destructibles,
+                finish_destructible_fractures,
+                (destroy_on_death, apply_destroy_events, simulate_debris).chain().run_if(is_playing),
+            ).chain().after(apply_damage_events))
+
+            // Raycast vehicles only drive while playing
+            .add_systems(Update, (
+                (read_vehicle_key_bindings, update_raycast_vehicles).chain().run_if(is_playing),
+                pose_vehicle_wheels,
+            ).chain())
+
+            // Use and pickup interactions while playing
+            .init_resource::<InteractionSettings>()
+            .init_resource::<InteractionFocus>()
+            .add_systems(Update, (update_interaction_focus, trigger_interactions).chain().run_if(is_playing))
+            .add_systems(OnExit(PlayState::Playing), clear_interaction_focus)
+
+            // Shared game variables and branching dialogue
+            .init_resource::<GameVariables>()
+            .init_resource::<DialoguePlayer>()
+            .add_systems(Update, run_dialogues)
+            .add_systems(OnExit(PlayState::Playing), close_dialogue_on_stop)
+
+            // Quests advance from game variables and persist in save games
+            .init_resource::<QuestLog>()
+            .add_systems(Startup, load_quest_definitions)
+            .add_systems(Update, (handle_save_game_events, update_quests).chain().after(run_dialogues))
+
+            // Profile stats and achievements
+            .init_resource::<Achievements>()
+            .add_systems(Startup, load_achievements)
+            .add_systems(Update, update_achievements)
+            .add_systems(Last, save_achievements_on_exit)
+
+            // What the user is doing, for Discord and similar services
+            .init_resource::<RichPresence>()
+            .add_systems(Update, update_rich_presence)
+
+            // Opt-in telemetry, batched and sent off the main thread
+            .init_resource::<Analytics>()
+            .add_systems(Startup, start_analytics)
+            .add_systems(Update, flush_analytics)
+            .add_systems(Last, flush_analytics_on_exit)
+
+            // Systems wrapped in `guarded` that panicked
+            .init_resource::<DisabledSystems>()
+
+            // Systems wrapped in `toggleable` that were paused from the editor
+            .init_resource::<SystemToggles>()
+
+            // Resources saved from the editor's Resources panel
+            .add_systems(PostStartup, load_resource_configs)
+
+            // Play sessions can be paused without leaving play
+            .init_state::<PlayState>()
+            .init_resource::<PlaySession>()
+            .add_systems(PreUpdate, sync_play_pause)
+            .add_systems(OnExit(PlayState::Playing), reset_play_pause)
+
+            // Gameplay cursor, owned by the game only while playing
+            .add_systems(Update, (apply_game_cursor, update_software_cursor).chain().run_if(is_playing))
+            .add_systems(OnExit(PlayState::Playing), release_game_cursor)
+
+            // Replays record or drive input for the whole play session
+            .add_systems(OnEnter(PlayState::Playing), begin_replay_session)
+            .add_systems(OnExit(PlayState::Playing), end_replay_session)
+            .add_systems(
+                PreUpdate,
+                (record_replay_frame, apply_replay_frame).after(bevy::input::InputSystem),
+            )
+
+            // Debug draw labels are double buffered for the editor overlay
+            .add_systems(PostUpdate, draw_queued_debug_shapes)
+            .add_systems(Last, flip_debug_text)
+
+            // Constraints run after gameplay, before transforms propagate
+            .add_systems(
+                PostUpdate,
+                (
+                    apply_follow_constraints,
+                    apply_stick_to_surface_constraints,
+                    apply_look_at_constraints,
+                )
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            )
+
             // Add core resources
             .init_resource::<EngineConfig>()
             .init_resource::<EngineState>()
             .init_resource::<PerformanceMetrics>()
+            .init_resource::<Tweens>()
+            .init_resource::<GameTimers>()
+            .init_resource::<GlobalRng>()
+            .init_resource::<BehaviorRegistry>()
+            .init_resource::<EntityPools>()
+            .init_resource::<GameplayRules>()
+            .init_resource::<ProjectSettings>()
+            .init_resource::<GameCursor>()
+            .init_resource::<DebugDrawSettings>()
+            .init_resource::<DebugTextQueue>()
+            .init_resource::<DebugDrawQueue>()
+            .init_resource::<ReplaySession>()
 
             // Add core events
             .add_event::<EngineInitializedEvent>()
@@ -37,13 +258,56 @@ impl Plugin for WaffleCorePlugin {
             .add_event::<SceneEvent>()
             .add_event::<InputEvent>()
             .add_event::<EngineErrorEvent>()
-            .add_event::<PerformanceEvent>();
+            .add_event::<PerformanceEvent>()
+            .add_event::<TweenCompletedEvent>()
+            .add_event::<TimerFinishedEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_event::<DamageAppliedEvent>()
+            .add_event::<DeathEvent>()
+            .add_event::<ProjectileHitEvent>()
+            .add_event::<SurfaceContactEvent>()
+            .add_event::<DestroyEvent>()
+            .add_event::<InteractEvent>()
+            .add_event::<StartDialogueEvent>()
+            .add_event::<DialogueInputEvent>()
+            .add_event::<DialogueEvent>()
+            .add_event::<QuestEvent>()
+            .add_event::<SaveGameEvent>()
+            .add_event::<AchievementUnlockedEvent>();
 
         // Register core components
-        app.register_type::<EngineRoot>()
+        app.register_type::<EngineConfig>()
+            .register_type::<EngineRoot>()
             .register_type::<EngineCamera>()
             .register_type::<EngineLight>()
-            .register_type::<EngineTransform>();
+            .register_type::<EngineTransform>()
+            .register_type::<LookAtConstraint>()
+            .register_type::<FollowConstraint>()
+            .register_type::<StickToSurfaceConstraint>()
+            .register_type::<Health>()
+            .register_type::<Damageable>()
+            .register_type::<Team>()
+            .register_type::<PhysicalSurface>()
+            .register_type::<Footsteps>()
+            .register_type::<WaffleAnimator>()
+            .register_type::<PropertyAnimation>()
+            .register_type::<AnimationStateMachine>();
+
+        #[cfg(feature = "discord")]
+        app.add_systems(Update, publish_discord_presence.after(update_rich_presence));
+
+        // Steam client, stats and cloud saves
+        #[cfg(feature = "steam")]
+        app.add_systems(Startup, steam::init_steam.before(load_achievements))
+            .add_systems(PreUpdate, steam::run_steam_callbacks)
+            .add_systems(
+                Update,
+                (
+                    steam::download_cloud_saves.before(handle_save_game_events),
+                    steam::upload_cloud_saves.after(handle_save_game_events),
+                ),
+            );
     }
 }
 
@@ -0,0 +1,37 @@
+// Waffle Engine Play Sessions
+// Distinguishes editing the scene from running it as a game. A paused
+// session stays in `PlayState::Playing` but gameplay systems stop and
+// virtual time stands still until it resumes.
+
+use bevy::prelude::*;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlayState {
+    #[default]
+    Editing,
+    Playing,
+}
+
+#[derive(Resource, Default)]
+pub struct PlaySession {
+    pub paused: bool,
+}
+
+pub fn is_playing(state: Res<State<PlayState>>, session: Res<PlaySession>) -> bool {
+    *state.get() == PlayState::Playing && !session.paused
+}
+
+pub fn sync_play_pause(session: Res<PlaySession>, mut time: ResMut<Time<Virtual>>) {
+    if !session.is_changed() {
+        return;
+    }
+    if session.paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+}
+
+pub fn reset_play_pause(mut session: ResMut<PlaySession>) {
+    session.paused = false;
+}
@@ -0,0 +1,256 @@
+// Waffle Engine Entity Pools
+// Pre-instantiated, recycled entities for spawn-heavy gameplay
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// How a pool creates new instances
+#[derive(Clone)]
+pub enum PoolPrefab {
+    /// glTF/scene asset path, spawned as a `SceneBundle`
+    Scene(String),
+    /// Custom spawner; should return the root entity it created
+    Custom(Arc<dyn Fn(&mut Commands) -> Entity + Send + Sync>),
+}
+
+/// Marks an entity owned by a pool
+#[derive(Component, Debug, Clone)]
+pub struct Pooled {
+    pub pool: String,
+}
+
+/// Present on pooled entities that are parked and waiting to be reused
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PoolInactive;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolStats {
+    pub total: usize,
+    pub active: usize,
+    pub peak_active: usize,
+    /// `get` calls that had to spawn because no parked instance was free
+    pub misses: usize,
+}
+
+pub struct EntityPool {
+    pub prefab: PoolPrefab,
+    pub prewarm: usize,
+    /// Upper bound on instances; `None` grows without limit
+    pub max_size: Option<usize>,
+    pub free: Vec<Entity>,
+    pub active: HashSet<Entity>,
+    pub stats: PoolStats,
+}
+
+impl EntityPool {
+    fn refresh_stats(&mut self) {
+        self.stats.active = self.active.len();
+        self.stats.total = self.active.len() + self.free.len();
+        self.stats.peak_active = self.stats.peak_active.max(self.stats.active);
+    }
+}
+
+/// All registered pools, keyed by name
+#[derive(Resource, Default)]
+pub struct EntityPools {
+    pub pools: HashMap<String, EntityPool>,
+}
+
+impl EntityPools {
+    pub fn register(&mut self, name: impl Into<String>, prefab: PoolPrefab, prewarm: usize, max_size: Option<usize>) {
+        self.pools.insert(
+            name.into(),
+            EntityPool {
+                prefab,
+                prewarm,
+                max_size,
+                free: Vec::new(),
+                active: HashSet::new(),
+                stats: PoolStats::default(),
+            },
+        );
+    }
+}
+
+/// Gameplay access to pools: `pool.get("bullet")` / `pool.release(entity)`
+#[derive(SystemParam)]
+pub struct Pool<'w, 's> {
+    pools: ResMut<'w, EntityPools>,
+    pooled: Query<'w, 's, &'static Pooled>,
+    commands: Commands<'w, 's>,
+}
+
+impl<'w, 's> Pool<'w, 's> {
+    /// Take an instance from the pool, spawning one if none are parked.
+    /// Returns `None` for unknown pools or when `max_size` is reached.
+    pub fn get(&mut self, name: &str) -> Option<Entity> {
+        let pool = self.pools.pools.get_mut(name)?;
+        // Skip parked instances despawned since `prune_entity_pools` last ran
+        let mut parked = None;
+        while let Some(entity) = pool.free.pop() {
+            if self.commands.get_entity(entity).is_some() {
+                parked = Some(entity);
+                break;
+            }
+        }
+        let entity = if let Some(entity) = parked {
+            entity
+        } else {
+            if pool.max_size.is_some_and(|max| pool.stats.total >= max) {
+                return None;
+            }
+            pool.stats.misses += 1;
+            spawn_pooled(&mut self.commands, name, &pool.prefab)
+        };
+        pool.active.insert(entity);
+        pool.refresh_stats();
+        self.commands
+            .entity(entity)
+            .remove::<PoolInactive>()
+            .insert(Visibility::Inherited);
+        Some(entity)
+    }
+
+    /// Park an instance for reuse. Entities that are not pooled are despawned.
+    pub fn release(&mut self, entity: Entity) {
+        let Ok(pooled) = self.pooled.get(entity) else {
+            if let Some(entity) = self.commands.get_entity(entity) {
+                entity.despawn_recursive();
+            }
+            return;
+        };
+        let Some(pool) = self.pools.pools.get_mut(&pooled.pool) else {
+            return;
+        };
+        if !pool.active.remove(&entity) {
+            return;
+        }
+        pool.free.push(entity);
+        pool.refresh_stats();
+        park(&mut self.commands, entity);
+    }
+
+    pub fn stats(&self, name: &str) -> Option<PoolStats> {
+        self.pools.pools.get(name).map(|pool| pool.stats)
+    }
+}
+
+fn spawn_pooled(commands: &mut Commands, name: &str, prefab: &PoolPrefab) -> Entity {
+    let entity = match prefab {
+        PoolPrefab::Scene(path) => {
+            let path = path.clone();
+            let name = name.to_string();
+            let entity = commands.spawn_empty().id();
+            commands.add(move |world: &mut World| {
+                let scene = world.resource::<AssetServer>().load(format!("{path}#Scene0"));
+                world.entity_mut(entity).insert((SceneBundle { scene, ..default() }, Name::new(name)));
+            });
+            entity
+        }
+        PoolPrefab::Custom(spawner) => spawner(commands),
+    };
+    commands.entity(entity).insert(Pooled {
+        pool: name.to_string(),
+    });
+    entity
+}
+
+fn park(commands: &mut Commands, entity: Entity) {
+    commands
+        .entity(entity)
+        .insert((PoolInactive, Visibility::Hidden));
+}
+
+/// Spawn parked instances until each pool reaches its prewarm count
+pub fn prewarm_entity_pools(mut commands: Commands, mut pools: ResMut<EntityPools>) {
+    for (name, pool) in pools.pools.iter_mut() {
+        while pool.stats.total < pool.prewarm {
+            let entity = spawn_pooled(&mut commands, name, &pool.prefab);
+            park(&mut commands, entity);
+            pool.free.push(entity);
+            pool.refresh_stats();
+        }
+    }
+}
+
+/// Forget pooled entities that were despawned directly instead of released
+pub fn prune_entity_pools(mut pools: ResMut<EntityPools>, mut removed: RemovedComponents<Pooled>) {
+    let removed: Vec<Entity> = removed.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+    for pool in pools.pools.values_mut() {
+        pool.free.retain(|entity| !removed.contains(entity));
+        pool.active.retain(|entity| !removed.contains(entity));
+        pool.refresh_stats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::{RunSystemOnce, SystemState};
+
+    fn world_with_pool(max_size: Option<usize>) -> World {
+        let mut pools = EntityPools::default();
+        let prefab = PoolPrefab::Custom(Arc::new(|commands: &mut Commands| {
+            commands.spawn(SpatialBundle::default()).id()
+        }));
+        pools.register("crate", prefab, 0, max_size);
+        let mut world = World::new();
+        world.insert_resource(pools);
+        world
+    }
+
+    fn with_pool<R>(world: &mut World, use_pool: impl FnOnce(&mut Pool) -> R) -> R {
+        let mut state = SystemState::<Pool>::new(world);
+        let result = use_pool(&mut state.get_mut(world));
+        state.apply(world);
+        result
+    }
+
+    #[test]
+    fn released_instances_are_reused() {
+        let mut world = world_with_pool(None);
+        let first = with_pool(&mut world, |pool| pool.get("crate")).expect("pool spawns an instance");
+        assert!(world.get::<Pooled>(first).is_some());
+
+        with_pool(&mut world, |pool| pool.release(first));
+        assert!(world.get::<PoolInactive>(first).is_some());
+        assert_eq!(world.get::<Visibility>(first), Some(&Visibility::Hidden));
+
+        let second = with_pool(&mut world, |pool| pool.get("crate"));
+        assert_eq!(second, Some(first));
+        assert!(world.get::<PoolInactive>(first).is_none());
+        assert_eq!(world.get::<Visibility>(first), Some(&Visibility::Inherited));
+
+        let stats = with_pool(&mut world, |pool| pool.stats("crate")).expect("pool exists");
+        assert_eq!((stats.total, stats.active, stats.peak_active, stats.misses), (1, 1, 1, 1));
+    }
+
+    #[test]
+    fn max_size_caps_instances() {
+        let mut world = world_with_pool(Some(1));
+        assert!(with_pool(&mut world, |pool| pool.get("crate")).is_some());
+        assert_eq!(with_pool(&mut world, |pool| pool.get("crate")), None);
+        assert_eq!(with_pool(&mut world, |pool| pool.get("missing")), None);
+    }
+
+    #[test]
+    fn despawned_instances_are_not_handed_out() {
+        let mut world = world_with_pool(None);
+        let first = with_pool(&mut world, |pool| pool.get("crate")).expect("pool spawns an instance");
+        with_pool(&mut world, |pool| pool.release(first));
+        world.despawn(first);
+
+        let second = with_pool(&mut world, |pool| pool.get("crate")).expect("pool spawns a new instance");
+        assert_ne!(second, first);
+        assert!(world.get::<PoolInactive>(second).is_none());
+
+        world.run_system_once(prune_entity_pools);
+        let stats = with_pool(&mut world, |pool| pool.stats("crate")).expect("pool exists");
+        assert_eq!((stats.total, stats.active), (1, 1));
+    }
+}
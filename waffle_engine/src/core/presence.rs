@@ -0,0 +1,146 @@
+// Waffle Engine Rich Presence
+// Builds a one-line description of what the user is doing, e.g. "Editing
+// Level_03" or "Playing - Wave 7", and publishes it to Discord when the
+// project enables it. The Discord client is only built with the `discord`
+// cargo feature.
+
+use bevy::prelude::*;
+
+use crate::core::play::PlayState;
+use crate::core::resources::EngineState;
+
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct RichPresence {
+    /// Set by the game to describe play sessions, e.g. "Wave 7"; the scene
+    /// name is shown when unset
+    pub game_status: Option<String>,
+    /// Current text, e.g. "Playing - Wave 7"
+    pub state: String,
+    pub details: Option<String>,
+}
+
+impl RichPresence {
+    pub fn set_game_status(&mut self, status: impl Into<String>) {
+        self.game_status = Some(status.into());
+    }
+
+    pub fn clear_game_status(&mut self) {
+        self.game_status = None;
+    }
+}
+
+pub fn update_rich_presence(
+    engine_state: Res<EngineState>,
+    play_state: Res<State<PlayState>>,
+    mut presence: ResMut<RichPresence>,
+) {
+    let scene = engine_state.current_scene.as_deref().unwrap_or("Untitled Scene");
+    let (state, details) = match play_state.get() {
+        PlayState::Editing => (format!("Editing {scene}"), None),
+        PlayState::Playing => match presence.game_status.as_deref() {
+            Some(status) => (format!("Playing - {status}"), Some(scene.to_string())),
+            None => (format!("Playing {scene}"), None),
+        },
+    };
+    if presence.state != state || presence.details != details {
+        presence.state = state;
+        presence.details = details;
+    }
+}
+
+#[cfg(feature = "discord")]
+pub use discord::publish_discord_presence;
+
+#[cfg(feature = "discord")]
+mod discord {
+    use bevy::prelude::*;
+    use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+
+    use super::RichPresence;
+    use crate::core::project::ProjectSettings;
+
+    #[derive(Default)]
+    pub struct DiscordConnection {
+        client: Option<DiscordIpcClient>,
+        application_id: String,
+        /// Unix seconds the session started, shown as elapsed time
+        started: i64,
+        published: Option<RichPresence>,
+        /// Connecting failed; retried when the settings change
+        failed: bool,
+    }
+
+    impl DiscordConnection {
+        fn close(&mut self) {
+            if let Some(mut client) = self.client.take() {
+                let _ = client.close();
+            }
+            self.published = None;
+            self.failed = false;
+        }
+    }
+
+    /// Keep Discord showing the current presence while the project enables it
+    pub fn publish_discord_presence(
+        project_settings: Res<ProjectSettings>,
+        presence: Res<RichPresence>,
+        mut connection: Local<DiscordConnection>,
+    ) {
+        let settings = &project_settings.discord_presence;
+        let wanted = settings.enabled && !settings.application_id.is_empty();
+        if !wanted || connection.application_id != settings.application_id {
+            connection.close();
+            connection.application_id.clear();
+        }
+        if !wanted {
+            return;
+        }
+
+        if connection.failed {
+            return;
+        }
+        if connection.client.is_none() {
+            let client = DiscordIpcClient::new(&settings.application_id).and_then(|mut client| {
+                client.connect()?;
+                Ok(client)
+            });
+            match client {
+                Ok(client) => {
+                    connection.client = Some(client);
+                    connection.application_id = settings.application_id.clone();
+                    connection.started = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+                }
+                Err(err) => {
+                    // Discord is probably not running
+                    warn!("Could not connect to Discord: {}", err);
+                    connection.application_id = settings.application_id.clone();
+                    connection.failed = true;
+                    return;
+                }
+            }
+        }
+
+        if connection.published.as_ref() == Some(&*presence) {
+            return;
+        }
+        let started = connection.started;
+        let Some(client) = connection.client.as_mut() else {
+            return;
+        };
+        let mut activity = activity::Activity::new()
+            .state(&presence.state)
+            .timestamps(activity::Timestamps::new().start(started));
+        if let Some(details) = presence.details.as_deref() {
+            activity = activity.details(details);
+        }
+        match client.set_activity(activity) {
+            Ok(()) => connection.published = Some(presence.clone()),
+            Err(err) => {
+                warn!("Lost connection to Discord: {}", err);
+                connection.close();
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+// Waffle Engine Project Settings
+// Settings shared by everyone working on a project, stored in project.ron
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::input::InputConfig;
+
+pub const PROJECT_SETTINGS_PATH: &str = "project.ron";
+
+/// Number of render layers exposed in the editor
+pub const RENDER_LAYER_COUNT: usize = 8;
+
+/// Unit lengths are shown in; the world itself is always in meters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LengthUnit {
+    #[default]
+    Meters,
+    Centimeters,
+}
+
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 2] = [LengthUnit::Meters, LengthUnit::Centimeters];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LengthUnit::Meters => "Meters",
+            LengthUnit::Centimeters => "Centimeters",
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            LengthUnit::Meters => "m",
+            LengthUnit::Centimeters => "cm",
+        }
+    }
+
+    pub fn from_meters(self, meters: f32) -> f32 {
+        match self {
+            LengthUnit::Meters => meters,
+            LengthUnit::Centimeters => meters * 100.0,
+        }
+    }
+
+    pub fn to_meters(self, value: f32) -> f32 {
+        match self {
+            LengthUnit::Meters => value,
+            LengthUnit::Centimeters => value / 100.0,
+        }
+    }
+
+    pub fn format(self, meters: f32) -> String {
+        match self {
+            LengthUnit::Meters => format!("{:.3} m", meters),
+            LengthUnit::Centimeters => format!("{:.1} cm", self.from_meters(meters)),
+        }
+    }
+}
+
+/// Up axis of imported model files; Z-up models are turned to the engine's Y-up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// Rotation that turns a model authored with this up axis to Y-up
+    pub fn import_rotation(self) -> Quat {
+        match self {
+            UpAxis::Y => Quat::IDENTITY,
+            UpAxis::Z => Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+        }
+    }
+}
+
+/// Increments used when dragging gizmos with snapping on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapPreset {
+    pub name: String,
+    /// Meters
+    pub translate: f32,
+    pub rotate_degrees: f32,
+    pub scale: f32,
+}
+
+impl SnapPreset {
+    fn new(name: &str, translate: f32, rotate_degrees: f32, scale: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            translate,
+            rotate_degrees,
+            scale,
+        }
+    }
+}
+
+/// Publishing what the user is doing to Discord; needs the `discord` feature
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscordPresenceSettings {
+    pub enabled: bool,
+    /// Application id from the Discord developer portal
+    pub application_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HdrOutputMode {
+    #[default]
+    Sdr,
+    /// 10-bit PQ (ST 2084) with Rec. 2020 primaries
+    Hdr10,
+    /// Linear 16-bit float with sRGB primaries
+    ScRgb,
+}
+
+impl HdrOutputMode {
+    pub const ALL: [HdrOutputMode; 3] = [HdrOutputMode::Sdr, HdrOutputMode::Hdr10, HdrOutputMode::ScRgb];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HdrOutputMode::Sdr => "SDR",
+            HdrOutputMode::Hdr10 => "HDR10",
+            HdrOutputMode::ScRgb => "scRGB",
+        }
+    }
+}
+
+/// Display output for HDR monitors; SDR is used when the display or
+/// renderer cannot present HDR
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HdrOutputSettings {
+    pub mode: HdrOutputMode,
+    /// Brightness of SDR white, such as UI, in nits
+    pub paper_white_nits: f32,
+    /// Brightest highlight the tonemapper aims for, in nits
+    pub peak_brightness_nits: f32,
+}
+
+impl Default for HdrOutputSettings {
+    fn default() -> Self {
+        Self {
+            mode: HdrOutputMode::Sdr,
+            paper_white_nits: 200.0,
+            peak_brightness_nits: 1000.0,
+        }
+    }
+}
+
+/// Space color values are edited and blended in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkingColorSpace {
+    /// Linear with sRGB (Rec. 709) primaries, the space the renderer uses
+    #[default]
+    LinearSrgb,
+    /// Linear with ACES AP1 primaries
+    AcesCg,
+}
+
+/// View transform from scene light to display values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputTransform {
+    /// Each environment's tonemapping setting
+    #[default]
+    PerEnvironment,
+    /// Clip to the display range without tonemapping
+    Standard,
+    AcesFitted,
+    AgX,
+    TonyMcMapface,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorManagementSettings {
+    pub working_space: WorkingColorSpace,
+    pub output_transform: OutputTransform,
+}
+
+/// How game UI grows with the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UiScaleMode {
+    /// The same size on screen at any resolution, following the display's DPI
+    #[default]
+    ConstantSize,
+    /// The same share of the screen's height at any resolution
+    ScaleWithHeight,
+}
+
+impl UiScaleMode {
+    pub const ALL: [UiScaleMode; 2] = [UiScaleMode::ConstantSize, UiScaleMode::ScaleWithHeight];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UiScaleMode::ConstantSize => "Constant Size",
+            UiScaleMode::ScaleWithHeight => "Scale With Height",
+        }
+    }
+}
+
+/// Edges of the screen game UI keeps clear of, for TVs that crop the picture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafeAreaPreset {
+    #[default]
+    None,
+    /// 3.5% on each side; what every TV still shows
+    ActionSafe,
+    /// 5% on each side; where text is kept
+    TitleSafe,
+    Custom,
+}
+
+impl SafeAreaPreset {
+    pub const ALL: [SafeAreaPreset; 4] = [
+        SafeAreaPreset::None,
+        SafeAreaPreset::ActionSafe,
+        SafeAreaPreset::TitleSafe,
+        SafeAreaPreset::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SafeAreaPreset::None => "None",
+            SafeAreaPreset::ActionSafe => "Action Safe (3.5%)",
+            SafeAreaPreset::TitleSafe => "Title Safe (5%)",
+            SafeAreaPreset::Custom => "Custom",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameUiSettings {
+    pub scale_mode: UiScaleMode,
+    /// Screen height in logical pixels the UI is designed at, for
+    /// `ScaleWithHeight`
+    pub reference_height: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub safe_area: SafeAreaPreset,
+    /// Fraction of the width and of the height kept clear on each side, for
+    /// a custom safe area
+    pub custom_safe_margin: Vec2,
+}
+
+impl Default for GameUiSettings {
+    fn default() -> Self {
+        Self {
+            scale_mode: UiScaleMode::ConstantSize,
+            reference_height: 1080.0,
+            min_scale: 0.5,
+            max_scale: 4.0,
+            safe_area: SafeAreaPreset::None,
+            custom_safe_margin: Vec2::splat(0.05),
+        }
+    }
+}
+
+impl GameUiSettings {
+    /// Fraction of the width and of the height kept clear on each side
+    pub fn safe_margin(&self) -> Vec2 {
+        match self.safe_area {
+            SafeAreaPreset::None => Vec2::ZERO,
+            SafeAreaPreset::ActionSafe => Vec2::splat(0.035),
+            SafeAreaPreset::TitleSafe => Vec2::splat(0.05),
+            SafeAreaPreset::Custom => self.custom_safe_margin.clamp(Vec2::ZERO, Vec2::splat(0.25)),
+        }
+    }
+}
+
+/// Assets packed into `bundles/<name>.wpak` for DLC and patches
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleDefinition {
+    pub name: String,
+    /// Folders or files, relative to the assets folder
+    pub include: Vec<String>,
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Display names for render layers, indexed by layer
+    pub render_layers: Vec<String>,
+    /// Grid spacing in meters new editor sessions start with
+    pub default_grid_size: f32,
+    pub units: LengthUnit,
+    pub import_up_axis: UpAxis,
+    pub snap_presets: Vec<SnapPreset>,
+    pub discord_presence: DiscordPresenceSettings,
+    /// `https://` URL analytics events are posted to; empty disables the
+    /// default backend
+    pub analytics_endpoint: String,
+    pub bundles: Vec<BundleDefinition>,
+    /// Compile the pipelines of materials earlier warm-ups saw on start
+    pub warm_up_shaders_on_start: bool,
+    pub hdr_output: HdrOutputSettings,
+    pub color_management: ColorManagementSettings,
+    pub game_ui: GameUiSettings,
+    pub input: InputConfig,
+    /// Scene the editor opens on start, by name; empty starts with a blank
+    /// scene
+    pub startup_scene: String,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        let mut render_layers = vec!["Default".to_string(), "Minimap".to_string()];
+        render_layers.extend((render_layers.len()..RENDER_LAYER_COUNT).map(|layer| format!("Layer {layer}")));
+        Self {
+            render_layers,
+            default_grid_size: 1.0,
+            units: LengthUnit::Meters,
+            import_up_axis: UpAxis::Y,
+            snap_presets: vec![
+                SnapPreset::new("Coarse", 1.0, 45.0, 0.5),
+                SnapPreset::new("Fine", 0.25, 15.0, 0.1),
+                SnapPreset::new("Detail", 0.05, 5.0, 0.05),
+            ],
+            discord_presence: DiscordPresenceSettings::default(),
+            analytics_endpoint: String::new(),
+            bundles: Vec::new(),
+            warm_up_shaders_on_start: false,
+            hdr_output: HdrOutputSettings::default(),
+            color_management: ColorManagementSettings::default(),
+            game_ui: GameUiSettings::default(),
+            input: InputConfig::default(),
+            startup_scene: String::new(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    pub fn load() -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(PROJECT_SETTINGS_PATH)?;
+        let mut settings: Self = ron::de::from_str(&data)?;
+        settings.render_layers.resize_with(RENDER_LAYER_COUNT, String::new);
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(PROJECT_SETTINGS_PATH, data)?;
+        Ok(())
+    }
+
+    pub fn snap_preset(&self, index: usize) -> Option<&SnapPreset> {
+        self.snap_presets.get(index).or_else(|| self.snap_presets.first())
+    }
+
+    pub fn render_layer_name(&self, layer: usize) -> String {
+        match self.render_layers.get(layer) {
+            Some(name) if !name.is_empty() => name.clone(),
+            _ => format!("Layer {layer}"),
+        }
+    }
+}
+
+pub fn load_project_settings(mut commands: Commands) {
+    let settings = if std::path::Path::new(PROJECT_SETTINGS_PATH).exists() {
+        ProjectSettings::load().unwrap_or_else(|error| {
+            warn!("Failed to load {}: {}", PROJECT_SETTINGS_PATH, error);
+            ProjectSettings::default()
+        })
+    } else {
+        ProjectSettings::default()
+    };
+    commands.insert_resource(settings);
+}
@@ -0,0 +1,162 @@
+// Waffle Engine Projectiles
+// Ballistic movers that sweep the spatial query each frame and report hits
+
+use bevy::prelude::*;
+
+use crate::core::components::PlaySpawned;
+use crate::core::health::{DamageEvent, Health};
+use crate::core::spatial::SpatialQuery;
+
+/// Moves under its own velocity and gravity until it hits something or expires
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    pub velocity: Vec3,
+    /// Downward acceleration in units per second squared
+    pub gravity: f32,
+    /// Seconds before the projectile despawns on its own
+    pub lifetime: f32,
+    pub age: f32,
+    /// Damage sent as a `DamageEvent` on impact
+    pub damage: f32,
+    /// Entity that fired the projectile; it and its children are never hit
+    pub source: Option<Entity>,
+    /// Draws a line along the last frame's path when set
+    pub tracer: Option<Color>,
+}
+
+impl Projectile {
+    pub fn new(direction: Vec3, speed: f32) -> Self {
+        Self {
+            velocity: direction.normalize_or_zero() * speed,
+            gravity: 0.0,
+            lifetime: 5.0,
+            age: 0.0,
+            damage: 0.0,
+            source: None,
+            tracer: None,
+        }
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_lifetime(mut self, seconds: f32) -> Self {
+        self.lifetime = seconds.max(0.0);
+        self
+    }
+
+    pub fn with_damage(mut self, damage: f32, source: Option<Entity>) -> Self {
+        self.damage = damage;
+        self.source = source;
+        self
+    }
+
+    pub fn with_tracer(mut self, color: Color) -> Self {
+        self.tracer = Some(color);
+        self
+    }
+}
+
+/// Sent when a projectile's path crosses mesh geometry
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ProjectileHitEvent {
+    pub projectile: Entity,
+    pub target: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub source: Option<Entity>,
+}
+
+/// Spawn a bare projectile at `origin`. Attach a mesh or scene to the returned
+/// entity for a visible bullet.
+pub fn spawn_projectile(commands: &mut Commands, origin: Vec3, projectile: Projectile) -> Entity {
+    commands.spawn(projectile_bundle(origin, projectile)).id()
+}
+
+/// What `spawn_projectile` spawns, for code holding the world instead of
+/// `Commands`
+pub fn projectile_bundle(origin: Vec3, projectile: Projectile) -> impl Bundle {
+    (
+        projectile,
+        PlaySpawned,
+        Name::new("Projectile"),
+        SpatialBundle::from_transform(Transform::from_translation(origin)),
+    )
+}
+
+pub fn update_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    spatial: SpatialQuery,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    mut hits: EventWriter<ProjectileHitEvent>,
+    mut damage: EventWriter<DamageEvent>,
+    mut gizmos: Gizmos,
+    parents: Query<&Parent>,
+    health: Query<(), With<Health>>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (entity, mut projectile, mut transform) in &mut projectiles {
+        projectile.age += delta;
+        if projectile.age >= projectile.lifetime {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        projectile.velocity.y -= projectile.gravity * delta;
+        let start = transform.translation;
+        let step = projectile.velocity * delta;
+        let distance = step.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+
+        let source = projectile.source;
+        // Skip the projectile's own meshes and anything belonging to the shooter.
+        let hit = spatial.cast_ray_filtered(start, step, distance, |candidate| {
+            !std::iter::once(candidate)
+                .chain(parents.iter_ancestors(candidate))
+                .any(|owner| owner == entity || Some(owner) == source)
+        });
+
+        let end = hit.map(|hit| hit.point).unwrap_or(start + step);
+        if let Some(color) = projectile.tracer {
+            gizmos.line(start, end, color);
+        }
+
+        let Some(hit) = hit else {
+            transform.translation = end;
+            if projectile.velocity.length_squared() > 0.0 {
+                transform.look_to(projectile.velocity, Vec3::Y);
+            }
+            continue;
+        };
+
+        hits.send(ProjectileHitEvent {
+            projectile: entity,
+            target: hit.entity,
+            point: hit.point,
+            normal: hit.normal,
+            source,
+        });
+        if projectile.damage > 0.0 {
+            // Meshes usually sit below the entity that owns the health.
+            let target = std::iter::once(hit.entity)
+                .chain(parents.iter_ancestors(hit.entity))
+                .find(|candidate| health.contains(*candidate))
+                .unwrap_or(hit.entity);
+            damage.send(DamageEvent {
+                target,
+                amount: projectile.damage,
+                source,
+            });
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
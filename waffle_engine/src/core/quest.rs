@@ -0,0 +1,221 @@
+// Waffle Engine Quests
+// Quest definitions are RON files under `assets/quests`. Objectives complete
+// when their condition on the game variables holds, so scripts and gameplay
+// code drive quests by setting flags and values. A completed quest sets the
+// flag `quest.<id>.completed`, which other quests can start on.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::core::variables::{GameVariables, VariableCondition};
+
+pub const QUEST_DIR: &str = "assets/quests";
+pub const QUEST_EXTENSION: &str = "quest.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestObjective {
+    pub id: String,
+    pub description: String,
+    pub condition: VariableCondition,
+    /// Not needed to complete the quest
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestDefinition {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub objectives: Vec<QuestObjective>,
+    /// Starts the quest automatically once it holds; without one the quest
+    /// waits for `QuestLog::start`
+    #[serde(default)]
+    pub start_condition: Option<VariableCondition>,
+    #[serde(default)]
+    pub fail_condition: Option<VariableCondition>,
+}
+
+impl QuestDefinition {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn completion_flag(&self) -> String {
+        format!("quest.{}.completed", self.id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuestStatus {
+    #[default]
+    Inactive,
+    Active,
+    Completed,
+    Failed,
+}
+
+impl QuestStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuestStatus::Inactive => "Inactive",
+            QuestStatus::Active => "Active",
+            QuestStatus::Completed => "Completed",
+            QuestStatus::Failed => "Failed",
+        }
+    }
+}
+
+/// Saved state of one quest
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct QuestProgress {
+    pub status: QuestStatus,
+    pub completed_objectives: Vec<String>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct QuestLog {
+    pub definitions: Vec<QuestDefinition>,
+    /// By quest id; quests without an entry are inactive
+    pub progress: HashMap<String, QuestProgress>,
+    /// Quests started with `start`, picked up by `update_quests`
+    pending_starts: Vec<String>,
+}
+
+impl QuestLog {
+    pub fn add_definition(&mut self, definition: QuestDefinition) {
+        match self.definitions.iter_mut().find(|existing| existing.id == definition.id) {
+            Some(existing) => *existing = definition,
+            None => self.definitions.push(definition),
+        }
+    }
+
+    pub fn definition(&self, id: &str) -> Option<&QuestDefinition> {
+        self.definitions.iter().find(|definition| definition.id == id)
+    }
+
+    pub fn status(&self, id: &str) -> QuestStatus {
+        self.progress.get(id).map_or(QuestStatus::Inactive, |progress| progress.status)
+    }
+
+    pub fn is_objective_complete(&self, quest: &str, objective: &str) -> bool {
+        self.progress
+            .get(quest)
+            .is_some_and(|progress| progress.completed_objectives.iter().any(|done| done == objective))
+    }
+
+    /// Start an inactive quest on the next update
+    pub fn start(&mut self, id: impl Into<String>) {
+        self.pending_starts.push(id.into());
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &QuestDefinition> {
+        self.definitions
+            .iter()
+            .filter(|definition| self.status(&definition.id) == QuestStatus::Active)
+    }
+
+    /// Forget all progress, e.g. for a new game
+    pub fn reset(&mut self) {
+        self.progress.clear();
+        self.pending_starts.clear();
+    }
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum QuestEvent {
+    Started { quest: String },
+    ObjectiveCompleted { quest: String, objective: String },
+    Completed { quest: String },
+    Failed { quest: String },
+}
+
+pub fn load_quest_definitions(mut quest_log: ResMut<QuestLog>) {
+    let quest_dir = Path::new(QUEST_DIR);
+    if !quest_dir.exists() {
+        return;
+    }
+    let suffix = format!(".{QUEST_EXTENSION}");
+    for entry in WalkDir::new(quest_dir).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.to_string_lossy().ends_with(&suffix) {
+            continue;
+        }
+        match QuestDefinition::load(path) {
+            Ok(definition) => quest_log.add_definition(definition),
+            Err(err) => warn!("Failed to load quest {}: {}", path.display(), err),
+        }
+    }
+}
+
+/// Start, advance, complete and fail quests from the game variables
+pub fn update_quests(
+    mut quest_log: ResMut<QuestLog>,
+    mut variables: ResMut<GameVariables>,
+    mut events: EventWriter<QuestEvent>,
+) {
+    if !quest_log.is_changed() && !variables.is_changed() {
+        return;
+    }
+
+    let log = quest_log.bypass_change_detection();
+    let pending = std::mem::take(&mut log.pending_starts);
+    for index in 0..log.definitions.len() {
+        let definition = &log.definitions[index];
+        let id = definition.id.clone();
+        let progress = log.progress.entry(id.clone()).or_default();
+
+        if progress.status == QuestStatus::Inactive {
+            let started = pending.contains(&id)
+                || definition
+                    .start_condition
+                    .as_ref()
+                    .is_some_and(|condition| condition.evaluate(&variables));
+            if !started {
+                continue;
+            }
+            progress.status = QuestStatus::Active;
+            events.send(QuestEvent::Started { quest: id.clone() });
+        }
+        if progress.status != QuestStatus::Active {
+            continue;
+        }
+
+        if definition.fail_condition.as_ref().is_some_and(|condition| condition.evaluate(&variables)) {
+            progress.status = QuestStatus::Failed;
+            events.send(QuestEvent::Failed { quest: id });
+            continue;
+        }
+        for objective in &definition.objectives {
+            let done = progress.completed_objectives.contains(&objective.id);
+            if !done && objective.condition.evaluate(&variables) {
+                progress.completed_objectives.push(objective.id.clone());
+                events.send(QuestEvent::ObjectiveCompleted {
+                    quest: id.clone(),
+                    objective: objective.id.clone(),
+                });
+            }
+        }
+        let complete = definition
+            .objectives
+            .iter()
+            .filter(|objective| !objective.optional)
+            .all(|objective| progress.completed_objectives.contains(&objective.id));
+        if complete {
+            progress.status = QuestStatus::Completed;
+            variables.set_flag(definition.completion_flag(), true);
+            events.send(QuestEvent::Completed { quest: id });
+        }
+    }
+
+    for id in pending {
+        if log.definition(&id).is_none() {
+            warn!("Cannot start unknown quest \"{}\"", id);
+        }
+    }
+}
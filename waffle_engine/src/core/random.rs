@@ -0,0 +1,349 @@
+// Waffle Engine Random and Noise
+// Seeded RNG, gradient noise and weighted choice helpers with identical
+// results on every platform
+
+use bevy::prelude::*;
+
+/// Deterministic xoshiro256** generator
+#[derive(Debug, Clone)]
+pub struct WaffleRng {
+    state: [u64; 4],
+}
+
+impl WaffleRng {
+    pub fn new(seed: u64) -> Self {
+        let mut splitmix = seed;
+        let mut next = || {
+            splitmix = splitmix.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = splitmix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        Self {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform value in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u64 << 24) as f32)
+    }
+
+    /// Uniform value in `[min, max)`
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform integer in `[min, max]`
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i64 - min as i64 + 1) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        let index = self.range_i32(0, items.len() as i32 - 1) as usize;
+        items.get(index)
+    }
+
+    /// Pick an index with probability proportional to its weight.
+    /// Negative weights count as zero; returns `None` if every weight is zero.
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|weight| weight.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = self.next_f32() * total;
+        for (index, weight) in weights.iter().enumerate() {
+            let weight = weight.max(0.0);
+            if roll < weight {
+                return Some(index);
+            }
+            roll -= weight;
+        }
+        weights.iter().rposition(|weight| *weight > 0.0)
+    }
+
+    pub fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let weights: Vec<f32> = items.iter().map(|(_, weight)| *weight).collect();
+        self.weighted_index(&weights).map(|index| &items[index].0)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range_i32(0, i as i32) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    pub fn point_in_sphere(&mut self, radius: f32) -> Vec3 {
+        loop {
+            let point = Vec3::new(
+                self.range_f32(-1.0, 1.0),
+                self.range_f32(-1.0, 1.0),
+                self.range_f32(-1.0, 1.0),
+            );
+            if point.length_squared() <= 1.0 {
+                return point * radius;
+            }
+        }
+    }
+}
+
+/// Engine-wide random source; set `seed` and call `reseed` for reproducible runs
+#[derive(Resource, Debug, Clone)]
+pub struct GlobalRng {
+    pub seed: u64,
+    pub rng: WaffleRng,
+}
+
+impl GlobalRng {
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = WaffleRng::new(seed);
+    }
+
+    /// Independent generator for a subsystem, derived from the global seed and a name
+    pub fn fork(&self, name: &str) -> WaffleRng {
+        let hash = name
+            .bytes()
+            .fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+            });
+        WaffleRng::new(self.seed ^ hash)
+    }
+}
+
+impl Default for GlobalRng {
+    fn default() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0x5EED);
+        Self {
+            seed,
+            rng: WaffleRng::new(seed),
+        }
+    }
+}
+
+/// Seeded gradient noise (Perlin 2D/3D, simplex 2D, fBm)
+#[derive(Debug, Clone)]
+pub struct Noise {
+    permutation: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        WaffleRng::new(seed).shuffle(&mut table);
+        let mut permutation = [0u8; 512];
+        for (i, slot) in permutation.iter_mut().enumerate() {
+            *slot = table[i & 255];
+        }
+        Self { permutation }
+    }
+
+    fn hash(&self, i: i32) -> u8 {
+        self.permutation[(i & 255) as usize]
+    }
+
+    /// Perlin noise in roughly `[-1, 1]`
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let xi = x.floor() as i32;
+        let yi = y.floor() as i32;
+        let xf = x - x.floor();
+        let yf = y - y.floor();
+        let u = fade(xf);
+        let v = fade(yf);
+
+        let aa = self.hash(self.hash(xi) as i32 + yi);
+        let ab = self.hash(self.hash(xi) as i32 + yi + 1);
+        let ba = self.hash(self.hash(xi + 1) as i32 + yi);
+        let bb = self.hash(self.hash(xi + 1) as i32 + yi + 1);
+
+        let x1 = lerp(grad2(aa, xf, yf), grad2(ba, xf - 1.0, yf), u);
+        let x2 = lerp(grad2(ab, xf, yf - 1.0), grad2(bb, xf - 1.0, yf - 1.0), u);
+        lerp(x1, x2, v)
+    }
+
+    /// Perlin noise in roughly `[-1, 1]`
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let xi = x.floor() as i32;
+        let yi = y.floor() as i32;
+        let zi = z.floor() as i32;
+        let xf = x - x.floor();
+        let yf = y - y.floor();
+        let zf = z - z.floor();
+        let u = fade(xf);
+        let v = fade(yf);
+        let w = fade(zf);
+
+        let a = self.hash(xi) as i32 + yi;
+        let aa = self.hash(a) as i32 + zi;
+        let ab = self.hash(a + 1) as i32 + zi;
+        let b = self.hash(xi + 1) as i32 + yi;
+        let ba = self.hash(b) as i32 + zi;
+        let bb = self.hash(b + 1) as i32 + zi;
+
+        lerp(
+            lerp(
+                lerp(grad3(self.hash(aa), xf, yf, zf), grad3(self.hash(ba), xf - 1.0, yf, zf), u),
+                lerp(grad3(self.hash(ab), xf, yf - 1.0, zf), grad3(self.hash(bb), xf - 1.0, yf - 1.0, zf), u),
+                v,
+            ),
+            lerp(
+                lerp(grad3(self.hash(aa + 1), xf, yf, zf - 1.0), grad3(self.hash(ba + 1), xf - 1.0, yf, zf - 1.0), u),
+                lerp(
+                    grad3(self.hash(ab + 1), xf, yf - 1.0, zf - 1.0),
+                    grad3(self.hash(bb + 1), xf - 1.0, yf - 1.0, zf - 1.0),
+                    u,
+                ),
+                v,
+            ),
+            w,
+        )
+    }
+
+    /// Simplex noise in roughly `[-1, 1]`
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        const F2: f32 = 0.366_025_4;
+        const G2: f32 = 0.211_324_87;
+
+        let s = (x + y) * F2;
+        let i = (x + s).floor() as i32;
+        let j = (y + s).floor() as i32;
+        let t = (i + j) as f32 * G2;
+        let x0 = x - (i as f32 - t);
+        let y0 = y - (j as f32 - t);
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let x1 = x0 - i1 as f32 + G2;
+        let y1 = y0 - j1 as f32 + G2;
+        let x2 = x0 - 1.0 + 2.0 * G2;
+        let y2 = y0 - 1.0 + 2.0 * G2;
+
+        let corner = |gi: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * grad2(gi, x, y)
+            }
+        };
+        let n0 = corner(self.hash(i + self.hash(j) as i32), x0, y0);
+        let n1 = corner(self.hash(i + i1 + self.hash(j + j1) as i32), x1, y1);
+        let n2 = corner(self.hash(i + 1 + self.hash(j + 1) as i32), x2, y2);
+        70.0 * (n0 + n1 + n2)
+    }
+
+    /// Fractal Brownian motion over `perlin2`, normalized to roughly `[-1, 1]`
+    pub fn fbm2(&self, x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut norm = 0.0;
+        for _ in 0..octaves.max(1) {
+            sum += self.perlin2(x * frequency, y * frequency) * amplitude;
+            norm += amplitude;
+            amplitude *= gain;
+            frequency *= lacunarity;
+        }
+        sum / norm
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn grad2(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+fn grad3(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = WaffleRng::new(42);
+        let mut b = WaffleRng::new(42);
+        let sequence: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        assert_eq!(sequence, (0..8).map(|_| b.next_u32()).collect::<Vec<_>>());
+        assert_ne!(sequence, (0..8).map(|_| WaffleRng::new(43).next_u32()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn forks_follow_the_global_seed() {
+        let mut global = GlobalRng::default();
+        global.reseed(7);
+        let first = global.fork("loot").next_u64();
+        assert_eq!(first, global.fork("loot").next_u64());
+        assert_ne!(first, global.fork("weather").next_u64());
+        global.reseed(8);
+        assert_ne!(first, global.fork("loot").next_u64());
+    }
+
+    #[test]
+    fn weighted_choice_skips_zero_weights() {
+        let mut rng = WaffleRng::new(1);
+        let items = [("never", 0.0), ("always", 2.0), ("negative", -1.0)];
+        for _ in 0..100 {
+            assert_eq!(rng.weighted_choice(&items), Some(&"always"));
+        }
+        assert_eq!(rng.weighted_choice(&[("none", 0.0)]), None);
+    }
+}
@@ -0,0 +1,313 @@
+// Waffle Engine Replays
+// Records input, frame timing and the RNG seed during play sessions so a
+// session can be played back frame for frame to reproduce bugs. Replays
+// assume the session starts from the same scene state it was recorded from.
+
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum};
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::core::play::PlayState;
+use crate::core::random::GlobalRng;
+
+pub const REPLAY_DIR: &str = "replays";
+pub const REPLAY_EXTENSION: &str = "replay.ron";
+
+/// Input state for a single frame
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub delta: f32,
+    pub keys: Vec<String>,
+    pub mouse_buttons: Vec<String>,
+    pub mouse_motion: Vec2,
+    pub mouse_wheel: f32,
+    pub cursor: Option<Vec2>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub scene: Option<String>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.delta).sum()
+    }
+}
+
+/// Replay files saved in `REPLAY_DIR`, newest first
+pub fn list_replays() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(REPLAY_DIR) else {
+        return Vec::new();
+    };
+    let mut replays: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(REPLAY_EXTENSION))
+        })
+        .collect();
+    replays.sort();
+    replays.reverse();
+    replays
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayMode {
+    #[default]
+    Idle,
+    Recording,
+    Playing,
+}
+
+#[derive(Resource, Default)]
+pub struct ReplaySession {
+    pub mode: ReplayMode,
+    /// Recording in progress, or the most recently finished one
+    pub recording: Option<Replay>,
+    /// Replay to run at the start of the next play session
+    pub queued: Option<Replay>,
+    playback: Option<Replay>,
+    pub playback_frame: usize,
+    last_keys: HashSet<KeyCode>,
+    last_mouse_buttons: HashSet<MouseButton>,
+}
+
+impl ReplaySession {
+    pub fn playback_progress(&self) -> Option<(usize, usize)> {
+        self.playback
+            .as_ref()
+            .map(|replay| (self.playback_frame, replay.frames.len()))
+    }
+
+    /// Write the last recording to `REPLAY_DIR` and return its path
+    pub fn save_recording(&self) -> anyhow::Result<PathBuf> {
+        let Some(recording) = self.recording.as_ref() else {
+            anyhow::bail!("No replay has been recorded yet");
+        };
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let path = PathBuf::from(REPLAY_DIR).join(format!("session_{stamp}.{REPLAY_EXTENSION}"));
+        recording.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Start recording, or start playback of a queued replay, when play begins
+pub fn begin_replay_session(
+    mut session: ResMut<ReplaySession>,
+    mut rng: ResMut<GlobalRng>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    session.last_keys.clear();
+    session.last_mouse_buttons.clear();
+    session.playback_frame = 0;
+
+    if let Some(replay) = session.queued.take() {
+        info!("Playing back replay ({} frames)", replay.frames.len());
+        rng.reseed(replay.seed);
+        if let Some(frame) = replay.frames.first() {
+            *time_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(frame.delta));
+        }
+        session.playback = Some(replay);
+        session.mode = ReplayMode::Playing;
+        return;
+    }
+
+    let seed = GlobalRng::default().seed;
+    rng.reseed(seed);
+    session.recording = Some(Replay {
+        seed,
+        scene: None,
+        frames: Vec::new(),
+    });
+    session.mode = ReplayMode::Recording;
+}
+
+pub fn end_replay_session(mut session: ResMut<ReplaySession>, mut time_strategy: ResMut<TimeUpdateStrategy>) {
+    if session.mode == ReplayMode::Playing {
+        *time_strategy = TimeUpdateStrategy::Automatic;
+        session.playback = None;
+    }
+    session.mode = ReplayMode::Idle;
+}
+
+/// Capture this frame's input into the active recording
+pub fn record_replay_frame(
+    mut session: ResMut<ReplaySession>,
+    time: Res<Time<Real>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let motion: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    let wheel: f32 = mouse_wheel.read().map(|wheel| wheel.y).sum();
+    if session.mode != ReplayMode::Recording {
+        return;
+    }
+    let Some(recording) = session.recording.as_mut() else {
+        return;
+    };
+
+    let mut key_names: Vec<String> = keys.get_pressed().filter_map(key_name).collect();
+    key_names.sort();
+    recording.frames.push(ReplayFrame {
+        delta: time.delta_seconds(),
+        keys: key_names,
+        mouse_buttons: mouse_buttons.get_pressed().map(|button| mouse_button_name(*button)).collect(),
+        mouse_motion: motion,
+        mouse_wheel: wheel,
+        cursor: windows.get_single().ok().and_then(Window::cursor_position),
+    });
+}
+
+/// Replace live input with the recorded frame
+pub fn apply_replay_frame(
+    mut session: ResMut<ReplaySession>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+    mut mouse_wheel: ResMut<Events<MouseWheel>>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut windows: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+) {
+    if session.mode != ReplayMode::Playing {
+        return;
+    }
+    let index = session.playback_frame;
+    let Some(frame) = session
+        .playback
+        .as_ref()
+        .and_then(|replay| replay.frames.get(index))
+        .cloned()
+    else {
+        info!("Replay finished after {} frames", index);
+        keys.release_all();
+        mouse_buttons.release_all();
+        *time_strategy = TimeUpdateStrategy::Automatic;
+        session.playback = None;
+        session.mode = ReplayMode::Idle;
+        return;
+    };
+    session.playback_frame += 1;
+
+    let desired_keys: HashSet<KeyCode> = frame.keys.iter().filter_map(|name| key_from_name(name)).collect();
+    let desired_buttons: HashSet<MouseButton> = frame
+        .mouse_buttons
+        .iter()
+        .filter_map(|name| mouse_button_from_name(name))
+        .collect();
+    apply_buttons(&mut keys, &session.last_keys, &desired_keys);
+    apply_buttons(&mut mouse_buttons, &session.last_mouse_buttons, &desired_buttons);
+    session.last_keys = desired_keys;
+    session.last_mouse_buttons = desired_buttons;
+
+    mouse_motion.clear();
+    if frame.mouse_motion != Vec2::ZERO {
+        mouse_motion.send(MouseMotion {
+            delta: frame.mouse_motion,
+        });
+    }
+    mouse_wheel.clear();
+    if let Ok((window_entity, mut window)) = windows.get_single_mut() {
+        if frame.mouse_wheel != 0.0 {
+            mouse_wheel.send(MouseWheel {
+                unit: bevy::input::mouse::MouseScrollUnit::Line,
+                x: 0.0,
+                y: frame.mouse_wheel,
+                window: window_entity,
+            });
+        }
+        if frame.cursor.is_some() && window.cursor_position() != frame.cursor {
+            window.set_cursor_position(frame.cursor);
+        }
+    }
+
+    // Time for the next frame comes from the recording as well.
+    let next_delta = session
+        .playback
+        .as_ref()
+        .and_then(|replay| replay.frames.get(index + 1))
+        .map(|frame| frame.delta);
+    if let Some(delta) = next_delta {
+        *time_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(delta));
+    }
+}
+
+/// Make `input` report exactly `desired` as held, with press and release edges
+/// relative to the previously applied frame
+fn apply_buttons<T>(input: &mut ButtonInput<T>, previous: &HashSet<T>, desired: &HashSet<T>)
+where
+    T: Copy + Eq + std::hash::Hash + Send + Sync + 'static,
+{
+    input.reset_all();
+    for button in previous {
+        input.press(*button);
+        input.clear_just_pressed(*button);
+        if !desired.contains(button) {
+            input.release(*button);
+        }
+    }
+    for button in desired.difference(previous) {
+        input.press(*button);
+    }
+}
+
+fn key_name(key: &KeyCode) -> Option<String> {
+    matches!(key.variant_type(), bevy::reflect::VariantType::Unit).then(|| key.variant_name().to_string())
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    KeyCode::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+fn mouse_button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Other(index) => format!("Other{index}"),
+        other => format!("{other:?}"),
+    }
+}
+
+fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        "Back" => Some(MouseButton::Back),
+        "Forward" => Some(MouseButton::Forward),
+        other => other.strip_prefix("Other")?.parse().ok().map(MouseButton::Other),
+    }
+}
+
+pub fn queue_replay(session: &mut ReplaySession, next_state: &mut NextState<PlayState>, replay: Replay) {
+    session.queued = Some(replay);
+    next_state.set(PlayState::Playing);
+}
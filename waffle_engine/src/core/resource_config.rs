@@ -0,0 +1,68 @@
+// Waffle Engine Resource Config Files
+// Resources that reflect `Resource` can be saved from the editor's Resources
+// panel to `config/<Name>.ron`. At startup every file in that folder is read
+// back and applied over the resource it names, in the editor and in builds
+// alike, so tuned settings stay tuned without a code change.
+
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::TypeRegistry;
+use serde::de::DeserializeSeed;
+use std::path::{Path, PathBuf};
+
+pub const RESOURCE_CONFIG_DIR: &str = "config";
+
+/// Config file of a resource, by its short type name
+pub fn resource_config_path(short_name: &str) -> PathBuf {
+    Path::new(RESOURCE_CONFIG_DIR).join(format!("{short_name}.ron"))
+}
+
+pub fn save_resource_config(value: &dyn Reflect, short_name: &str, registry: &TypeRegistry) -> anyhow::Result<PathBuf> {
+    let serializer = ReflectSerializer::new(value, registry);
+    let data = ron::ser::to_string_pretty(&serializer, ron::ser::PrettyConfig::default())?;
+    std::fs::create_dir_all(RESOURCE_CONFIG_DIR)?;
+    let path = resource_config_path(short_name);
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+pub fn read_resource_config(path: &Path, registry: &TypeRegistry) -> anyhow::Result<Box<dyn Reflect>> {
+    let data = std::fs::read_to_string(path)?;
+    let mut deserializer = ron::de::Deserializer::from_str(&data)?;
+    Ok(ReflectDeserializer::new(registry).deserialize(&mut deserializer)?)
+}
+
+/// Apply the saved config files over the resources they name. Runs after
+/// startup, once the plugins have inserted their resources.
+pub fn load_resource_configs(world: &mut World) {
+    let Ok(entries) = std::fs::read_dir(RESOURCE_CONFIG_DIR) else {
+        return;
+    };
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        if path.extension().is_none_or(|extension| extension != "ron") {
+            continue;
+        }
+        let value = match read_resource_config(&path, &registry) {
+            Ok(value) => value,
+            Err(error) => {
+                warn!("Failed to load {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        // Loaded values are dynamic; the type they stand for is what's registered
+        let type_path = value.get_represented_type_info().map(|info| info.type_path()).unwrap_or_default();
+        let Some(reflect_resource) = registry.get_with_type_path(type_path).and_then(|r| r.data::<ReflectResource>())
+        else {
+            warn!("{} is not for a reflected resource", path.display());
+            continue;
+        };
+        if reflect_resource.reflect(world).is_none() {
+            warn!("{} is for {}, which isn't in the world", path.display(), type_path);
+            continue;
+        }
+        reflect_resource.apply(world, &*value);
+        info!("Applied resource config {}", path.display());
+    }
+}
@@ -0,0 +1,80 @@
+// Waffle Engine Save Games
+// Named save slots holding game progress: game variables and quest state.
+// Scene contents are not saved; games restore them from the variables.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::core::quest::{QuestLog, QuestProgress};
+use crate::core::variables::GameVariables;
+
+pub const SAVE_DIR: &str = "saves";
+pub const SAVE_EXTENSION: &str = "save.ron";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub variables: GameVariables,
+    #[serde(default)]
+    pub quests: HashMap<String, QuestProgress>,
+}
+
+impl SaveGame {
+    pub fn path(slot: &str) -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(format!("{slot}.{SAVE_EXTENSION}"))
+    }
+
+    pub fn exists(slot: &str) -> bool {
+        Self::path(slot).exists()
+    }
+
+    pub fn load(slot: &str) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(Self::path(slot))?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, slot: &str) -> anyhow::Result<()> {
+        std::fs::create_dir_all(SAVE_DIR)?;
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(Self::path(slot), data)?;
+        Ok(())
+    }
+}
+
+/// Save to or load from a slot by name
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum SaveGameEvent {
+    Save(String),
+    Load(String),
+}
+
+pub fn handle_save_game_events(
+    mut events: EventReader<SaveGameEvent>,
+    mut variables: ResMut<GameVariables>,
+    mut quest_log: ResMut<QuestLog>,
+) {
+    for event in events.read() {
+        match event {
+            SaveGameEvent::Save(slot) => {
+                let save = SaveGame {
+                    variables: variables.clone(),
+                    quests: quest_log.progress.clone(),
+                };
+                match save.save(slot) {
+                    Ok(()) => info!("Saved game to slot \"{}\"", slot),
+                    Err(err) => error!("Failed to save slot \"{}\": {}", slot, err),
+                }
+            }
+            SaveGameEvent::Load(slot) => match SaveGame::load(slot) {
+                Ok(save) => {
+                    *variables = save.variables;
+                    quest_log.reset();
+                    quest_log.progress = save.quests;
+                    info!("Loaded game from slot \"{}\"", slot);
+                }
+                Err(err) => error!("Failed to load slot \"{}\": {}", slot, err),
+            },
+        }
+    }
+}
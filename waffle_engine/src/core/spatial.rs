@@ -0,0 +1,181 @@
+// Waffle Engine Spatial Queries
+// Ray casts against rendered mesh geometry, usable from gameplay systems
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::primitives::Aabb;
+
+use crate::core::components::EditorHidden;
+use crate::terrain::TerrainChunk;
+
+/// Result of a successful ray cast
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+/// System parameter for casting rays against mesh entities.
+///
+/// Candidates are culled by their `Aabb` before triangles are tested, so
+/// entities whose bounds have not been computed yet are skipped.
+/// Terrain chunks are hidden from the editor but still hit.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's> {
+    meshes: Res<'w, Assets<Mesh>>,
+    mesh_query: Query<
+        'w,
+        's,
+        (Entity, &'static GlobalTransform, &'static Handle<Mesh>, &'static Aabb),
+        Or<(Without<EditorHidden>, With<TerrainChunk>)>,
+    >,
+}
+
+impl<'w, 's> SpatialQuery<'w, 's> {
+    /// Cast a ray and return the closest hit within `max_distance`.
+    pub fn cast_ray(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit> {
+        self.cast_ray_filtered(origin, direction, max_distance, |_| true)
+    }
+
+    /// Cast a ray, only considering entities accepted by `filter`.
+    pub fn cast_ray_filtered(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+        let mut closest: Option<RayHit> = None;
+
+        for (entity, transform, mesh_handle, aabb) in self.mesh_query.iter() {
+            if !filter(entity) {
+                continue;
+            }
+            let world_to_local = transform.affine().inverse();
+            let local_origin = world_to_local.transform_point3(origin);
+            let local_direction = world_to_local.transform_vector3(direction);
+            let best = closest.map(|hit| hit.distance).unwrap_or(max_distance);
+
+            if ray_aabb_distance(local_origin, local_direction, aabb).is_none() {
+                continue;
+            }
+            let Some(mesh) = self.meshes.get(mesh_handle) else {
+                continue;
+            };
+            let Some((local_t, local_normal)) = ray_mesh_intersection(mesh, local_origin, local_direction)
+            else {
+                continue;
+            };
+
+            let point = transform.transform_point(local_origin + local_direction * local_t);
+            let distance = origin.distance(point);
+            if distance > best {
+                continue;
+            }
+            let normal_matrix = Mat3::from(transform.affine().matrix3).inverse().transpose();
+            let mut normal = (normal_matrix * local_normal).normalize_or_zero();
+            // Triangles are tested double sided, so face the normal back at the ray.
+            if normal.dot(direction) > 0.0 {
+                normal = -normal;
+            }
+            closest = Some(RayHit {
+                entity,
+                point,
+                normal,
+                distance,
+            });
+        }
+
+        closest
+    }
+}
+
+fn ray_aabb_distance(origin: Vec3, direction: Vec3, aabb: &Aabb) -> Option<f32> {
+    let min = Vec3::from(aabb.min());
+    let max = Vec3::from(aabb.max());
+    let inv = direction.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let t_min = t1.min(t2).max_element();
+    let t_max = t1.max(t2).min_element();
+    if t_max < t_min.max(0.0) {
+        return None;
+    }
+    Some(t_min.max(0.0))
+}
+
+/// Closest triangle hit in mesh space, returned as (ray parameter, face normal).
+fn ray_mesh_intersection(mesh: &Mesh, origin: Vec3, direction: Vec3) -> Option<(f32, Vec3)> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+
+    let triangle = |a: usize, b: usize, c: usize| {
+        Some([
+            Vec3::from(*positions.get(a)?),
+            Vec3::from(*positions.get(b)?),
+            Vec3::from(*positions.get(c)?),
+        ])
+    };
+
+    let mut best: Option<(f32, Vec3)> = None;
+    let mut test = |vertices: Option<[Vec3; 3]>| {
+        let Some([a, b, c]) = vertices else {
+            return;
+        };
+        if let Some(t) = ray_triangle_intersection(origin, direction, a, b, c) {
+            if best.is_none_or(|(best_t, _)| t < best_t) {
+                best = Some((t, (b - a).cross(c - a).normalize_or_zero()));
+            }
+        }
+    };
+
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                test(triangle(tri[0] as usize, tri[1] as usize, tri[2] as usize));
+            }
+        }
+        Some(Indices::U32(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                test(triangle(tri[0] as usize, tri[1] as usize, tri[2] as usize));
+            }
+        }
+        None => {
+            for start in (0..positions.len().saturating_sub(2)).step_by(3) {
+                test(triangle(start, start + 1, start + 2));
+            }
+        }
+    }
+
+    best
+}
+
+/// Möller–Trumbore, double sided.
+fn ray_triangle_intersection(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t > 1e-5).then_some(t)
+}
@@ -0,0 +1,367 @@
+// Waffle Engine Animation State Machines
+// Chooses which of a model's clips play from a `.wanim` graph. The graph
+// lists states, each naming a clip, and transitions between them that fire
+// when their conditions on the machine's parameters hold, cross-fading the
+// two clips over the transition's blend time. Parameters are floats, bools
+// and triggers set from Lua, so locomotion like idle, walk and run can be
+// authored in the graph file and driven by a speed from the script.
+//
+// A machine takes over the model's `WaffleAnimator`: the animator still
+// finds the clips and the animation player, the machine picks what plays.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::animation::RepeatAnimation;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::animation::AnimatorBinding;
+
+/// Extension of animation state machine files
+pub const STATE_MACHINE_EXTENSION: &str = "wanim";
+
+/// Kind and starting value of a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ParameterDefault {
+    Float(f32),
+    Bool(bool),
+    /// Set by a script, cleared again by the transition it fires
+    Trigger,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateParameter {
+    pub name: String,
+    pub default: ParameterDefault,
+}
+
+/// A test on one parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransitionCondition {
+    Greater(String, f32),
+    Less(String, f32),
+    True(String),
+    False(String),
+    Triggered(String),
+}
+
+impl TransitionCondition {
+    pub fn evaluate(&self, parameters: &AnimationParameters) -> bool {
+        match self {
+            TransitionCondition::Greater(name, value) => parameters.float(name) > *value,
+            TransitionCondition::Less(name, value) => parameters.float(name) < *value,
+            TransitionCondition::True(name) => parameters.bool(name),
+            TransitionCondition::False(name) => !parameters.bool(name),
+            TransitionCondition::Triggered(name) => parameters.triggers.contains(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationState {
+    pub name: String,
+    /// Name of the model's clip this state plays
+    pub clip: String,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateTransition {
+    /// State the transition leaves; from any state when unset
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: String,
+    /// All of these must hold for the transition to fire
+    #[serde(default)]
+    pub conditions: Vec<TransitionCondition>,
+    /// Seconds the two clips cross-fade for
+    #[serde(default)]
+    pub blend: f32,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+/// A `.wanim` file
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationStateGraph {
+    #[serde(default)]
+    pub parameters: Vec<StateParameter>,
+    /// The first state is the one the machine starts in
+    pub states: Vec<AnimationState>,
+    #[serde(default)]
+    pub transitions: Vec<StateTransition>,
+}
+
+impl AnimationStateGraph {
+    pub fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+
+    /// The first transition out of `current` whose conditions hold
+    fn next_transition(&self, current: usize, parameters: &AnimationParameters) -> Option<(usize, &StateTransition)> {
+        let current_name = &self.states[current].name;
+        self.transitions.iter().find_map(|transition| {
+            let leaves_current = match &transition.from {
+                Some(from) => from == current_name,
+                // Any-state transitions don't restart the state they lead to
+                None => &transition.to != current_name,
+            };
+            if !leaves_current || !transition.conditions.iter().all(|condition| condition.evaluate(parameters)) {
+                return None;
+            }
+            self.state_index(&transition.to).map(|to| (to, transition))
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationStateGraphLoader;
+
+impl AssetLoader for AnimationStateGraphLoader {
+    type Asset = AnimationStateGraph;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<AnimationStateGraph, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[STATE_MACHINE_EXTENSION]
+    }
+}
+
+/// Current values of a machine's parameters
+#[derive(Debug, Clone, Default)]
+pub struct AnimationParameters {
+    pub floats: HashMap<String, f32>,
+    pub bools: HashMap<String, bool>,
+    /// Triggers set and not yet used by a transition
+    pub triggers: HashSet<String>,
+}
+
+impl AnimationParameters {
+    pub fn float(&self, name: &str) -> f32 {
+        self.floats.get(name).copied().unwrap_or(0.0)
+    }
+
+    pub fn bool(&self, name: &str) -> bool {
+        self.bools.get(name).copied().unwrap_or(false)
+    }
+
+    /// Give parameters the graph declares their starting value, keeping any
+    /// a script already set
+    fn apply_defaults(&mut self, graph: &AnimationStateGraph) {
+        for parameter in &graph.parameters {
+            match parameter.default {
+                ParameterDefault::Float(value) => {
+                    self.floats.entry(parameter.name.clone()).or_insert(value);
+                }
+                ParameterDefault::Bool(value) => {
+                    self.bools.entry(parameter.name.clone()).or_insert(value);
+                }
+                ParameterDefault::Trigger => {}
+            }
+        }
+    }
+}
+
+/// Plays a model's clips from an animation state machine file
+#[derive(Component, Reflect, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationStateMachine {
+    /// Asset path of the `.wanim` file
+    pub graph: String,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub parameters: AnimationParameters,
+    /// Name of the state playing, once the graph has loaded
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: Option<String>,
+}
+
+impl AnimationStateMachine {
+    pub fn new(graph: impl Into<String>) -> Self {
+        Self {
+            graph: graph.into(),
+            ..default()
+        }
+    }
+
+    pub fn set_float(&mut self, name: impl Into<String>, value: f32) {
+        self.parameters.floats.insert(name.into(), value);
+    }
+
+    pub fn set_bool(&mut self, name: impl Into<String>, value: bool) {
+        self.parameters.bools.insert(name.into(), value);
+    }
+
+    pub fn trigger(&mut self, name: impl Into<String>) {
+        self.parameters.triggers.insert(name.into());
+    }
+}
+
+/// A cross-fade out of the previous state
+struct StateBlend {
+    from: AnimationNodeIndex,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Where a machine is in its graph
+#[derive(Component)]
+pub struct StateMachinePlayback {
+    path: String,
+    graph: Handle<AnimationStateGraph>,
+    player: Option<Entity>,
+    /// Index of the current state and its clip's node, once both are known
+    current: Option<(usize, AnimationNodeIndex)>,
+    blend: Option<StateBlend>,
+}
+
+/// Load the graph of each machine, again when its path changes
+pub fn bind_state_machines(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    machines: Query<(Entity, &AnimationStateMachine, Option<&StateMachinePlayback>)>,
+) {
+    for (entity, machine, playback) in machines.iter() {
+        if playback.is_some_and(|playback| playback.path == machine.graph) {
+            continue;
+        }
+        if machine.graph.is_empty() {
+            commands.entity(entity).remove::<StateMachinePlayback>();
+            continue;
+        }
+        commands.entity(entity).insert(StateMachinePlayback {
+            path: machine.graph.clone(),
+            graph: asset_server.load(machine.graph.clone()),
+            player: None,
+            current: None,
+            blend: None,
+        });
+    }
+}
+
+/// Fire transitions and weight the clips of the current and previous states
+pub fn drive_state_machines(
+    time: Res<Time>,
+    graphs: Res<Assets<AnimationStateGraph>>,
+    mut machines: Query<(&mut AnimationStateMachine, &mut StateMachinePlayback, &AnimatorBinding)>,
+    mut players: Query<&mut AnimationPlayer>,
+) {
+    for (mut machine, mut playback, binding) in machines.iter_mut() {
+        let Some(graph) = graphs.get(&playback.graph) else {
+            continue;
+        };
+        let Some(player_entity) = binding.player() else {
+            continue;
+        };
+        let Ok(mut player) = players.get_mut(player_entity) else {
+            continue;
+        };
+        if graph.states.is_empty() {
+            continue;
+        }
+
+        // A new player, or a graph that was just loaded, starts from the top
+        if playback.player != Some(player_entity) || playback.current.is_none() {
+            let Some(node) = binding.clip_node(&graph.states[0].clip) else {
+                continue;
+            };
+            machine.parameters.apply_defaults(graph);
+            player.stop_all();
+            player.start(node);
+            playback.player = Some(player_entity);
+            playback.current = Some((0, node));
+            playback.blend = None;
+        }
+        let Some((mut current, mut node)) = playback.current else {
+            continue;
+        };
+
+        // One transition per frame, so chains of them play out in order
+        if let Some((to, transition)) = graph.next_transition(current, &machine.parameters) {
+            if let Some(to_node) = binding.clip_node(&graph.states[to].clip) {
+                for condition in &transition.conditions {
+                    if let TransitionCondition::Triggered(name) = condition {
+                        machine.parameters.triggers.remove(name);
+                    }
+                }
+                // A fade cut short drops the clip it was fading out
+                if let Some(blend) = playback.blend.take() {
+                    if blend.from != node && blend.from != to_node {
+                        player.stop(blend.from);
+                    }
+                }
+                if to_node != node {
+                    playback.blend = (transition.blend > 0.0).then_some(StateBlend {
+                        from: node,
+                        elapsed: 0.0,
+                        duration: transition.blend,
+                    });
+                    if playback.blend.is_none() {
+                        player.stop(node);
+                    }
+                }
+                player.start(to_node);
+                current = to;
+                node = to_node;
+                playback.current = Some((current, node));
+            }
+        }
+
+        let mut weight = 1.0;
+        if let Some(blend) = playback.blend.as_mut() {
+            blend.elapsed += time.delta_seconds();
+            weight = (blend.elapsed / blend.duration).clamp(0.0, 1.0);
+            if weight >= 1.0 {
+                player.stop(blend.from);
+                playback.blend = None;
+            } else if let Some(previous) = player.animation_mut(blend.from) {
+                previous.set_weight(1.0 - weight);
+            }
+        }
+
+        let state = &graph.states[current];
+        if let Some(active) = player.animation_mut(node) {
+            active.set_weight(weight);
+            active.set_speed(state.speed).set_repeat(if state.looping {
+                RepeatAnimation::Forever
+            } else {
+                RepeatAnimation::Never
+            });
+        }
+        if machine.state.as_ref() != Some(&state.name) {
+            machine.bypass_change_detection().state = Some(state.name.clone());
+        }
+    }
+}
+
+/// Machines start over from their first state when play starts
+pub fn reset_state_machines(mut machines: Query<(&mut AnimationStateMachine, &mut StateMachinePlayback)>) {
+    for (mut machine, mut playback) in machines.iter_mut() {
+        machine.parameters = AnimationParameters::default();
+        playback.current = None;
+        playback.blend = None;
+    }
+}
@@ -0,0 +1,149 @@
+// Waffle Engine Steam Integration
+// Only built with the `steam` cargo feature. Starts the Steam client, uses the
+// Steam user's id as the stats profile, reports stats and achievements to
+// Steam and mirrors save game slots to Steam Cloud. The app id comes from
+// `steam_appid.txt` next to the executable during development.
+//
+// Without Steam running the game carries on with local saves and stats only.
+
+use bevy::prelude::*;
+use std::io::{Read, Write};
+use steamworks::{Client, ClientManager, SingleClient};
+
+use crate::core::achievements::{AchievementBackend, Achievements};
+use crate::core::savegame::{SaveGame, SaveGameEvent, SAVE_EXTENSION};
+
+#[derive(Resource, Clone)]
+pub struct SteamClient(pub Client<ClientManager>);
+
+impl SteamClient {
+    /// Persona name of the signed in user
+    pub fn user_name(&self) -> String {
+        self.0.friends().name()
+    }
+
+    pub fn steam_id(&self) -> u64 {
+        self.0.user().steam_id().raw()
+    }
+
+    pub fn cloud_enabled(&self) -> bool {
+        let storage = self.0.remote_storage();
+        storage.is_cloud_enabled_for_account() && storage.is_cloud_enabled_for_app()
+    }
+}
+
+/// Runs Steam callbacks; it must stay on the main thread
+pub struct SteamCallbacks(SingleClient<ClientManager>);
+
+pub struct SteamAchievementBackend {
+    client: Client<ClientManager>,
+}
+
+impl AchievementBackend for SteamAchievementBackend {
+    fn name(&self) -> &str {
+        "Steam"
+    }
+
+    fn set_stat(&mut self, stat: &str, value: f32) {
+        if self.client.user_stats().set_stat_f32(stat, value).is_err() {
+            warn!("Steam rejected stat \"{}\"; is it defined for the app?", stat);
+        }
+    }
+
+    fn unlock(&mut self, achievement: &str) {
+        if self.client.user_stats().achievement(achievement).set().is_err() {
+            warn!("Steam rejected achievement \"{}\"; is it defined for the app?", achievement);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.client.user_stats().store_stats().is_err() {
+            warn!("Failed to store stats on Steam");
+        }
+    }
+}
+
+/// Start Steam and switch the stats profile to the Steam user
+pub fn init_steam(world: &mut World) {
+    let (client, single) = match Client::init() {
+        Ok(client) => client,
+        Err(err) => {
+            warn!("Steam is not available: {}", err);
+            return;
+        }
+    };
+    client.user_stats().request_current_stats();
+
+    let steam = SteamClient(client.clone());
+    info!("Signed in to Steam as {}", steam.user_name());
+    if let Some(mut achievements) = world.get_resource_mut::<Achievements>() {
+        achievements.profile = format!("steam_{}", steam.steam_id());
+        achievements.add_backend(SteamAchievementBackend { client });
+    }
+    world.insert_resource(steam);
+    world.insert_non_send_resource(SteamCallbacks(single));
+}
+
+pub fn run_steam_callbacks(callbacks: Option<NonSend<SteamCallbacks>>) {
+    if let Some(callbacks) = callbacks {
+        callbacks.0.run_callbacks();
+    }
+}
+
+fn cloud_file_name(slot: &str) -> String {
+    format!("{slot}.{SAVE_EXTENSION}")
+}
+
+/// Fetch slots from Steam Cloud before they are loaded, when this machine
+/// does not have them
+pub fn download_cloud_saves(steam: Option<Res<SteamClient>>, mut events: EventReader<SaveGameEvent>) {
+    let Some(steam) = steam.filter(|steam| steam.cloud_enabled()) else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        let SaveGameEvent::Load(slot) = event else {
+            continue;
+        };
+        if SaveGame::exists(slot) {
+            continue;
+        }
+        let file = steam.0.remote_storage().file(&cloud_file_name(slot));
+        if !file.exists() {
+            continue;
+        }
+        let mut data = Vec::new();
+        if let Err(err) = file.read().read_to_end(&mut data) {
+            error!("Failed to download save \"{}\" from Steam Cloud: {}", slot, err);
+            continue;
+        }
+        let path = SaveGame::path(slot);
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, data));
+        if let Err(err) = written {
+            error!("Failed to store save \"{}\" from Steam Cloud: {}", slot, err);
+        }
+    }
+}
+
+/// Copy slots to Steam Cloud once they have been saved
+pub fn upload_cloud_saves(steam: Option<Res<SteamClient>>, mut events: EventReader<SaveGameEvent>) {
+    let Some(steam) = steam.filter(|steam| steam.cloud_enabled()) else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        let SaveGameEvent::Save(slot) = event else {
+            continue;
+        };
+        let Ok(data) = std::fs::read(SaveGame::path(slot)) else {
+            continue;
+        };
+        let mut writer = steam.0.remote_storage().file(&cloud_file_name(slot)).write();
+        if let Err(err) = writer.write_all(&data) {
+            error!("Failed to upload save \"{}\" to Steam Cloud: {}", slot, err);
+        }
+    }
+}
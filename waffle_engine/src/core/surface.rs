@@ -0,0 +1,277 @@
+// Waffle Engine Physical Surfaces
+// What things are made of, so footsteps and impacts can sound and look
+// different on grass, wood or metal. A `PhysicalSurface` on an entity tags it
+// and everything under it; a mesh without one of its own takes the surface of
+// its material, e.g. the one its `.wmat` file names. Ray casts through
+// `SurfaceQuery` report the surface they hit, and every footstep or impact is
+// announced as a `SurfaceContactEvent` for sounds and effects to pick up.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::core::projectile::ProjectileHitEvent;
+use crate::core::spatial::{RayHit, SpatialQuery};
+
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PhysicalSurface {
+    #[default]
+    Default,
+    Grass,
+    Dirt,
+    Gravel,
+    Sand,
+    Snow,
+    Water,
+    Wood,
+    Metal,
+    Stone,
+    Concrete,
+    Glass,
+    Fabric,
+}
+
+impl PhysicalSurface {
+    pub const ALL: [PhysicalSurface; 13] = [
+        PhysicalSurface::Default,
+        PhysicalSurface::Grass,
+        PhysicalSurface::Dirt,
+        PhysicalSurface::Gravel,
+        PhysicalSurface::Sand,
+        PhysicalSurface::Snow,
+        PhysicalSurface::Water,
+        PhysicalSurface::Wood,
+        PhysicalSurface::Metal,
+        PhysicalSurface::Stone,
+        PhysicalSurface::Concrete,
+        PhysicalSurface::Glass,
+        PhysicalSurface::Fabric,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PhysicalSurface::Default => "Default",
+            PhysicalSurface::Grass => "Grass",
+            PhysicalSurface::Dirt => "Dirt",
+            PhysicalSurface::Gravel => "Gravel",
+            PhysicalSurface::Sand => "Sand",
+            PhysicalSurface::Snow => "Snow",
+            PhysicalSurface::Water => "Water",
+            PhysicalSurface::Wood => "Wood",
+            PhysicalSurface::Metal => "Metal",
+            PhysicalSurface::Stone => "Stone",
+            PhysicalSurface::Concrete => "Concrete",
+            PhysicalSurface::Glass => "Glass",
+            PhysicalSurface::Fabric => "Fabric",
+        }
+    }
+}
+
+/// Surfaces of materials, for meshes that aren't tagged themselves
+#[derive(Resource, Clone, Default)]
+pub struct MaterialSurfaces {
+    by_material: HashMap<AssetId<StandardMaterial>, PhysicalSurface>,
+    /// Surfaces named by `.wmat` files, by asset path; the loader fills it in
+    by_path: Arc<RwLock<HashMap<String, PhysicalSurface>>>,
+}
+
+impl MaterialSurfaces {
+    pub fn get(&self, material: &Handle<StandardMaterial>) -> Option<PhysicalSurface> {
+        self.by_material.get(&material.id()).copied().or_else(|| {
+            let path = material.path()?.to_string();
+            self.by_path.read().get(&path).copied()
+        })
+    }
+
+    /// Tag a material, or clear its tag to fall back on its file's
+    pub fn set(&mut self, material: &Handle<StandardMaterial>, surface: Option<PhysicalSurface>) {
+        match surface {
+            Some(surface) => {
+                self.by_material.insert(material.id(), surface);
+            }
+            None => {
+                self.by_material.remove(&material.id());
+            }
+        }
+    }
+
+    /// Record the surface a material file names
+    pub fn set_for_path(&self, path: impl Into<String>, surface: Option<PhysicalSurface>) {
+        let path = path.into();
+        let mut by_path = self.by_path.write();
+        match surface {
+            Some(surface) => {
+                by_path.insert(path, surface);
+            }
+            None => {
+                by_path.remove(&path);
+            }
+        }
+    }
+}
+
+/// A ray cast hit and what it hit is made of
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceHit {
+    pub hit: RayHit,
+    pub surface: PhysicalSurface,
+}
+
+/// System parameter for ray casts that report surfaces
+#[derive(SystemParam)]
+pub struct SurfaceQuery<'w, 's> {
+    spatial: SpatialQuery<'w, 's>,
+    material_surfaces: Res<'w, MaterialSurfaces>,
+    surface_query: Query<'w, 's, &'static PhysicalSurface>,
+    material_query: Query<'w, 's, &'static Handle<StandardMaterial>>,
+    parent_query: Query<'w, 's, &'static Parent>,
+}
+
+impl<'w, 's> SurfaceQuery<'w, 's> {
+    /// The entity's own tag, then its material's, then the nearest tagged
+    /// ancestor's
+    pub fn surface_of(&self, entity: Entity) -> PhysicalSurface {
+        if let Ok(surface) = self.surface_query.get(entity) {
+            return *surface;
+        }
+        if let Some(surface) = self
+            .material_query
+            .get(entity)
+            .ok()
+            .and_then(|material| self.material_surfaces.get(material))
+        {
+            return surface;
+        }
+        self.parent_query
+            .iter_ancestors(entity)
+            .find_map(|ancestor| self.surface_query.get(ancestor).ok().copied())
+            .unwrap_or_default()
+    }
+
+    pub fn cast_ray(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<SurfaceHit> {
+        self.cast_ray_filtered(origin, direction, max_distance, |_| true)
+    }
+
+    pub fn cast_ray_filtered(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<SurfaceHit> {
+        let hit = self.spatial.cast_ray_filtered(origin, direction, max_distance, filter)?;
+        Some(SurfaceHit {
+            hit,
+            surface: self.surface_of(hit.entity),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceContactKind {
+    Footstep,
+    Impact,
+}
+
+/// Something stepped on or hit a surface. Sounds, decals and particles
+/// listen for these to vary by surface.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SurfaceContactEvent {
+    /// The walker or projectile
+    pub source: Entity,
+    /// What was stepped on or hit
+    pub target: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub surface: PhysicalSurface,
+    pub kind: SurfaceContactKind,
+}
+
+/// Step on the ground every `stride` of distance covered
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Footsteps {
+    pub stride: f32,
+    /// How far below the entity's origin the ground can be
+    pub reach: f32,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    travelled: f32,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    last_position: Option<Vec3>,
+}
+
+impl Default for Footsteps {
+    fn default() -> Self {
+        Self {
+            stride: 0.8,
+            reach: 1.2,
+            travelled: 0.0,
+            last_position: None,
+        }
+    }
+}
+
+pub fn emit_footsteps(
+    surfaces: SurfaceQuery,
+    parents: Query<&Parent>,
+    mut walkers: Query<(Entity, &mut Footsteps, &GlobalTransform)>,
+    mut contacts: EventWriter<SurfaceContactEvent>,
+) {
+    for (entity, mut footsteps, transform) in &mut walkers {
+        let position = transform.translation();
+        let Some(last) = footsteps.last_position.replace(position) else {
+            continue;
+        };
+        footsteps.travelled += (position - last).with_y(0.0).length();
+        if footsteps.travelled < footsteps.stride.max(0.05) {
+            continue;
+        }
+        footsteps.travelled = 0.0;
+
+        // The walker's own meshes are not ground
+        let ground = surfaces.cast_ray_filtered(position + Vec3::Y * 0.1, Vec3::NEG_Y, footsteps.reach + 0.1, |candidate| {
+            candidate != entity && !parents.iter_ancestors(candidate).any(|ancestor| ancestor == entity)
+        });
+        if let Some(ground) = ground {
+            contacts.send(SurfaceContactEvent {
+                source: entity,
+                target: ground.hit.entity,
+                point: ground.hit.point,
+                normal: ground.hit.normal,
+                surface: ground.surface,
+                kind: SurfaceContactKind::Footstep,
+            });
+        }
+    }
+}
+
+/// Forget where walkers were, so the first step after play starts is a
+/// full stride away
+pub fn reset_footsteps(mut walkers: Query<&mut Footsteps>) {
+    for mut footsteps in &mut walkers {
+        footsteps.travelled = 0.0;
+        footsteps.last_position = None;
+    }
+}
+
+pub fn report_projectile_impacts(
+    surfaces: SurfaceQuery,
+    mut hits: EventReader<ProjectileHitEvent>,
+    mut contacts: EventWriter<SurfaceContactEvent>,
+) {
+    for hit in hits.read() {
+        contacts.send(SurfaceContactEvent {
+            source: hit.projectile,
+            target: hit.target,
+            point: hit.point,
+            normal: hit.normal,
+            surface: surfaces.surface_of(hit.target),
+            kind: SurfaceContactKind::Impact,
+        });
+    }
+}
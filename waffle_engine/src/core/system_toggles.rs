@@ -0,0 +1,54 @@
+// Waffle Engine System Toggles
+// Lets a system be paused at runtime from the editor's Systems panel, to see
+// whether it is the one misbehaving. Pausing skips the system through a run
+// condition; the rest of the schedule keeps its order.
+//
+// Plugin systems opt in by wrapping them:
+//     app.add_systems(Update, toggleable(sync_sun_from_environment));
+// Ordering against the wrapped system by name still works, as in
+// `.after(sync_sun_from_environment)`.
+
+use bevy::ecs::schedule::SystemConfigs;
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+/// Toggleable systems paused from the Systems panel, by system name
+#[derive(Resource, Debug, Default)]
+pub struct SystemToggles {
+    paused: HashSet<String>,
+}
+
+impl SystemToggles {
+    pub fn is_paused(&self, name: &str) -> bool {
+        self.paused.contains(name)
+    }
+
+    pub fn set_paused(&mut self, name: &str, paused: bool) {
+        if paused {
+            self.paused.insert(name.to_string());
+        } else {
+            self.paused.remove(name);
+        }
+    }
+
+    pub fn paused_count(&self) -> usize {
+        self.paused.len()
+    }
+
+    pub fn resume_all(&mut self) {
+        self.paused.clear();
+    }
+}
+
+/// `system`, skipped while it is paused in `SystemToggles`
+pub fn toggleable<M>(system: impl IntoSystem<(), (), M>) -> SystemConfigs {
+    let system = IntoSystem::into_system(system);
+    let name = system.name().to_string();
+    system.run_if(move |toggles: Option<Res<SystemToggles>>| toggles.is_none_or(|toggles| !toggles.is_paused(&name)))
+}
+
+/// Whether a run condition is the one `toggleable` adds, going by the name
+/// of its closure
+pub fn is_toggle_condition(condition_name: &str) -> bool {
+    condition_name.contains("system_toggles::toggleable")
+}
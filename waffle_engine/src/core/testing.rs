@@ -0,0 +1,497 @@
+// Waffle Engine Test Harness
+// `waffle_engine test [--update-goldens] [filter]` loads each test scene, runs
+// the game for a number of frames, checks assertions and golden images and
+// reports pass/fail. Failing tests leave a screenshot in test_results/.
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::scene::SceneInstance;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::{PrimaryWindow, WindowRef};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::core::golden::{GoldenSettings, compare_images, image_to_rgb, load_golden, save_png};
+
+use crate::core::play::PlayState;
+use crate::rendering::camera::{ViewportRenderTarget, WaffleMainCamera};
+use crate::rendering::scene::SceneRootEntity;
+
+pub const TEST_DIR: &str = "tests";
+pub const TEST_EXTENSION: &str = "test.ron";
+pub const TEST_RESULTS_DIR: &str = "test_results";
+
+/// Frames to wait for a test scene to finish loading before failing
+const SCENE_LOAD_TIMEOUT_FRAMES: u32 = 600;
+/// Frames to wait for a requested screenshot to come back from the renderer
+const SCREENSHOT_TIMEOUT_FRAMES: u32 = 30;
+/// Tests advance time in fixed steps so frame counts and renders are repeatable
+const TEST_FRAME_TIME: f64 = 1.0 / 60.0;
+
+/// Declarative check evaluated once a test has run its frames
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TestAssertion {
+    /// An entity with this `Name` exists
+    EntityExists(String),
+    /// No entity with this `Name` exists
+    EntityMissing(String),
+    /// The named entity's world position is within `tolerance` of `position`
+    PositionNear {
+        entity: String,
+        position: Vec3,
+        tolerance: f32,
+    },
+}
+
+/// Scene test stored as `tests/<name>.test.ron`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSpec {
+    pub name: String,
+    /// glTF scene to load, relative to the assets folder
+    pub scene: Option<String>,
+    pub frames: u32,
+    #[serde(default)]
+    pub assertions: Vec<TestAssertion>,
+    /// Compare the final frame against a stored golden image
+    #[serde(default)]
+    pub golden: Option<GoldenSettings>,
+}
+
+impl TestSpec {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+}
+
+pub type TestCheck = Arc<dyn Fn(&mut World) -> Result<(), String> + Send + Sync>;
+
+/// A test case: a spec plus optional Rust checks registered by the project
+#[derive(Clone)]
+pub struct TestCase {
+    pub spec: TestSpec,
+    pub checks: Vec<TestCheck>,
+}
+
+/// Tests registered from Rust, run alongside the ones found in `TEST_DIR`
+#[derive(Resource, Default, Clone)]
+pub struct ProjectTests {
+    pub cases: Vec<TestCase>,
+}
+
+impl ProjectTests {
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        scene: Option<&str>,
+        frames: u32,
+        check: impl Fn(&mut World) -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        self.cases.push(TestCase {
+            spec: TestSpec {
+                name: name.into(),
+                scene: scene.map(str::to_string),
+                frames,
+                assertions: Vec::new(),
+                golden: None,
+            },
+            checks: vec![Arc::new(check)],
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    pub screenshot: Option<PathBuf>,
+    pub golden_diff: Option<PathBuf>,
+}
+
+type CapturedFrame = Arc<Mutex<Option<Image>>>;
+
+enum TestPhase {
+    Loading { waited: u32 },
+    Running { frame: u32 },
+    Capturing { remaining: u32, frame: CapturedFrame },
+}
+
+struct ActiveTest {
+    case: TestCase,
+    scene: Option<Entity>,
+    phase: TestPhase,
+    failures: Vec<String>,
+    screenshot: Option<PathBuf>,
+    golden_diff: Option<PathBuf>,
+}
+
+#[derive(Resource, Default)]
+pub struct TestRunner {
+    queue: Vec<TestCase>,
+    active: Option<ActiveTest>,
+    /// Overwrite golden images with this run's frames instead of comparing
+    pub update_goldens: bool,
+    pub results: Vec<TestResult>,
+}
+
+/// Runs project tests instead of the editor
+pub struct WaffleTestPlugin {
+    /// Only run tests whose name contains this text
+    pub filter: Option<String>,
+    pub update_goldens: bool,
+}
+
+impl Plugin for WaffleTestPlugin {
+    fn build(&self, app: &mut App) {
+        let filter = self.filter.clone();
+        app.init_resource::<ProjectTests>()
+            .insert_resource(TestRunner {
+                update_goldens: self.update_goldens,
+                ..default()
+            })
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                TEST_FRAME_TIME,
+            )))
+            .add_systems(
+                Startup,
+                (
+                    move |mut runner: ResMut<TestRunner>, project_tests: Res<ProjectTests>| {
+                        runner.queue = collect_test_cases(&project_tests, filter.as_deref());
+                        runner.queue.reverse();
+                        info!("Running {} test(s)", runner.queue.len());
+                    },
+                    start_playing,
+                ),
+            )
+            .add_systems(PostStartup, render_main_camera_to_window)
+            .add_systems(Last, run_tests);
+    }
+}
+
+fn collect_test_cases(project_tests: &ProjectTests, filter: Option<&str>) -> Vec<TestCase> {
+    let mut cases = project_tests.cases.clone();
+    if let Ok(entries) = std::fs::read_dir(TEST_DIR) {
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with(TEST_EXTENSION))
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            match TestSpec::load(&path) {
+                Ok(spec) => cases.push(TestCase {
+                    spec,
+                    checks: Vec::new(),
+                }),
+                Err(err) => error!("Failed to load test {}: {err}", path.display()),
+            }
+        }
+    }
+    cases.retain(|case| filter.is_none_or(|filter| case.spec.name.contains(filter)));
+    cases
+}
+
+fn start_playing(mut next_state: ResMut<NextState<PlayState>>) {
+    next_state.set(PlayState::Playing);
+}
+
+/// The editor renders into a viewport texture; tests screenshot the window instead.
+fn render_main_camera_to_window(mut commands: Commands, mut cameras: Query<&mut Camera, With<WaffleMainCamera>>) {
+    commands.remove_resource::<ViewportRenderTarget>();
+    for mut camera in &mut cameras {
+        camera.target = RenderTarget::Window(WindowRef::Primary);
+    }
+}
+
+/// Advance the active test by one frame, starting the next one when it finishes
+pub fn run_tests(world: &mut World) {
+    let mut runner = world.remove_resource::<TestRunner>().unwrap_or_default();
+    step_tests(world, &mut runner);
+    world.insert_resource(runner);
+}
+
+fn step_tests(world: &mut World, runner: &mut TestRunner) {
+    let Some(mut active) = runner.active.take() else {
+        match runner.queue.pop() {
+            Some(case) => runner.active = Some(start_test(world, case)),
+            None => finish_tests(world, runner),
+        }
+        return;
+    };
+
+    match active.phase {
+        TestPhase::Loading { waited } => {
+            let loaded = active.scene.is_none_or(|scene| {
+                world
+                    .get::<SceneInstance>(scene)
+                    .is_some_and(|instance| world.resource::<SceneSpawner>().instance_is_ready(**instance))
+            });
+            if loaded {
+                active.phase = TestPhase::Running { frame: 0 };
+            } else if waited >= SCENE_LOAD_TIMEOUT_FRAMES {
+                active.failures.push("Scene did not finish loading".to_string());
+                complete_test(world, runner, active);
+                return;
+            } else {
+                active.phase = TestPhase::Loading { waited: waited + 1 };
+            }
+        }
+        TestPhase::Running { frame } => {
+            if frame + 1 < active.case.spec.frames {
+                active.phase = TestPhase::Running { frame: frame + 1 };
+            } else {
+                active.failures = evaluate_test(world, &active.case);
+                if active.failures.is_empty() && active.case.spec.golden.is_none() {
+                    complete_test(world, runner, active);
+                    return;
+                }
+                match request_screenshot(world) {
+                    Some(frame) => {
+                        active.phase = TestPhase::Capturing {
+                            remaining: SCREENSHOT_TIMEOUT_FRAMES,
+                            frame,
+                        }
+                    }
+                    None => {
+                        active.failures.push("Could not capture a screenshot".to_string());
+                        complete_test(world, runner, active);
+                        return;
+                    }
+                }
+            }
+        }
+        TestPhase::Capturing { remaining, ref frame } => {
+            let captured = frame.lock().ok().and_then(|mut frame| frame.take());
+            if let Some(image) = captured {
+                check_captured_frame(&mut active, image, runner.update_goldens);
+                complete_test(world, runner, active);
+                return;
+            }
+            if remaining == 0 {
+                active.failures.push("Screenshot was not captured in time".to_string());
+                complete_test(world, runner, active);
+                return;
+            }
+            active.phase = TestPhase::Capturing {
+                remaining: remaining - 1,
+                frame: frame.clone(),
+            };
+        }
+    }
+    runner.active = Some(active);
+}
+
+fn start_test(world: &mut World, case: TestCase) -> ActiveTest {
+    info!("Test '{}' started", case.spec.name);
+    let scene = case.spec.scene.as_ref().map(|path| {
+        let handle = world
+            .resource::<AssetServer>()
+            .load(format!("{path}#Scene0"));
+        let entity = world
+            .spawn((
+                Name::new(format!("Test Scene: {}", case.spec.name)),
+                SceneBundle {
+                    scene: handle,
+                    ..default()
+                },
+            ))
+            .id();
+        if let Some(root) = world.get_resource::<SceneRootEntity>().map(|root| root.0) {
+            world.entity_mut(root).add_child(entity);
+        }
+        entity
+    });
+    ActiveTest {
+        case,
+        scene,
+        phase: TestPhase::Loading { waited: 0 },
+        failures: Vec::new(),
+        screenshot: None,
+        golden_diff: None,
+    }
+}
+
+fn evaluate_test(world: &mut World, case: &TestCase) -> Vec<String> {
+    let mut failures = Vec::new();
+    for assertion in &case.spec.assertions {
+        if let Err(message) = evaluate_assertion(world, assertion) {
+            failures.push(message);
+        }
+    }
+    for check in &case.checks {
+        if let Err(message) = check(world) {
+            failures.push(message);
+        }
+    }
+    failures
+}
+
+fn evaluate_assertion(world: &mut World, assertion: &TestAssertion) -> Result<(), String> {
+    let mut named = world.query::<(&Name, &GlobalTransform)>();
+    let mut find = |world: &World, name: &str| {
+        named
+            .iter(world)
+            .find(|(entity_name, _)| entity_name.as_str() == name)
+            .map(|(_, transform)| transform.translation())
+    };
+    match assertion {
+        TestAssertion::EntityExists(name) => find(world, name)
+            .map(|_| ())
+            .ok_or_else(|| format!("Expected entity '{name}' to exist")),
+        TestAssertion::EntityMissing(name) => match find(world, name) {
+            Some(_) => Err(format!("Expected entity '{name}' to be gone")),
+            None => Ok(()),
+        },
+        TestAssertion::PositionNear {
+            entity,
+            position,
+            tolerance,
+        } => {
+            let actual = find(world, entity).ok_or_else(|| format!("Entity '{entity}' not found"))?;
+            let distance = actual.distance(*position);
+            if distance <= *tolerance {
+                Ok(())
+            } else {
+                Err(format!(
+                    "'{entity}' is at {actual:.2}, {distance:.2} away from {position:.2} (tolerance {tolerance})"
+                ))
+            }
+        }
+    }
+}
+
+fn request_screenshot(world: &mut World) -> Option<CapturedFrame> {
+    let mut windows = world.query_filtered::<Entity, With<PrimaryWindow>>();
+    let window = windows.iter(world).next()?;
+    let frame = CapturedFrame::default();
+    let slot = frame.clone();
+    world
+        .resource_mut::<ScreenshotManager>()
+        .take_screenshot(window, move |image| {
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(image);
+            }
+        })
+        .ok()?;
+    Some(frame)
+}
+
+/// Compare the captured frame with the test's golden image, and keep it on
+/// disk if the test failed
+fn check_captured_frame(active: &mut ActiveTest, image: Image, update_goldens: bool) {
+    let name = active.case.spec.name.clone();
+    let frame = match image_to_rgb(image) {
+        Ok(frame) => frame,
+        Err(err) => {
+            active.failures.push(err.to_string());
+            return;
+        }
+    };
+
+    if let Some(golden) = active.case.spec.golden.as_ref() {
+        let golden_path = golden.golden_path(&name);
+        if update_goldens {
+            match save_png(&frame, &golden_path) {
+                Ok(()) => info!("Updated golden image {}", golden_path.display()),
+                Err(err) => active
+                    .failures
+                    .push(format!("Failed to write golden image {}: {err}", golden_path.display())),
+            }
+        } else {
+            match load_golden(&golden_path)
+                .and_then(|expected| compare_images(&frame, &expected, golden.pixel_tolerance))
+            {
+                Ok(comparison) if comparison.passes(golden) => {}
+                Ok(comparison) => {
+                    active.failures.push(format!(
+                        "{:.3}% of pixels differ from {} (allowed {:.3}%, max delta {:.3})",
+                        comparison.diff_fraction() * 100.0,
+                        golden_path.display(),
+                        golden.max_diff_fraction * 100.0,
+                        comparison.max_delta
+                    ));
+                    let diff_path = result_path(&name, "diff.png");
+                    match save_png(&comparison.diff_image, &diff_path) {
+                        Ok(()) => active.golden_diff = Some(diff_path),
+                        Err(err) => error!("Failed to write {}: {err}", diff_path.display()),
+                    }
+                }
+                Err(err) => active.failures.push(format!(
+                    "Golden image check against {} failed: {err} (run with --update-goldens to create it)",
+                    golden_path.display()
+                )),
+            }
+        }
+    }
+
+    if !active.failures.is_empty() {
+        let path = result_path(&name, "png");
+        match save_png(&frame, &path) {
+            Ok(()) => active.screenshot = Some(path),
+            Err(err) => error!("Failed to write {}: {err}", path.display()),
+        }
+    }
+}
+
+fn result_path(test_name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(TEST_RESULTS_DIR).join(format!("{}.{extension}", sanitize_file_name(test_name)))
+}
+
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+        .collect()
+}
+
+fn complete_test(world: &mut World, runner: &mut TestRunner, active: ActiveTest) {
+    if let Some(entity) = active.scene.and_then(|scene| world.get_entity_mut(scene)) {
+        entity.despawn_recursive();
+    }
+    let passed = active.failures.is_empty();
+    if passed {
+        info!("Test '{}' passed", active.case.spec.name);
+    } else {
+        error!("Test '{}' failed", active.case.spec.name);
+        for failure in &active.failures {
+            error!("  {}", failure);
+        }
+    }
+    runner.results.push(TestResult {
+        name: active.case.spec.name,
+        passed,
+        failures: active.failures,
+        screenshot: active.screenshot,
+        golden_diff: active.golden_diff,
+    });
+}
+
+fn finish_tests(world: &mut World, runner: &TestRunner) {
+    let failed = runner.results.iter().filter(|result| !result.passed).count();
+    let mut report = String::new();
+    for result in &runner.results {
+        report.push_str(&format!("{} {}\n", if result.passed { "PASS" } else { "FAIL" }, result.name));
+        for failure in &result.failures {
+            report.push_str(&format!("    {failure}\n"));
+        }
+        if let Some(screenshot) = &result.screenshot {
+            report.push_str(&format!("    screenshot: {}\n", screenshot.display()));
+        }
+        if let Some(diff) = &result.golden_diff {
+            report.push_str(&format!("    golden diff: {}\n", diff.display()));
+        }
+    }
+    report.push_str(&format!("\n{} passed, {} failed\n", runner.results.len() - failed, failed));
+    println!("{report}");
+    if std::fs::create_dir_all(TEST_RESULTS_DIR).is_ok() {
+        let _ = std::fs::write(Path::new(TEST_RESULTS_DIR).join("report.txt"), &report);
+    }
+
+    world.send_event(if failed == 0 { AppExit::Success } else { AppExit::error() });
+}
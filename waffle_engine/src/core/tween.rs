@@ -0,0 +1,400 @@
+// Waffle Engine Tweens and Timers
+// Small gameplay helpers for animating transforms and scheduling callbacks
+
+use bevy::prelude::*;
+
+/// Easing curves applied to tween progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    EaseOutBack,
+    EaseOutBounce,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 9] = [
+        Easing::Linear,
+        Easing::EaseInQuad,
+        Easing::EaseOutQuad,
+        Easing::EaseInOutQuad,
+        Easing::EaseInCubic,
+        Easing::EaseOutCubic,
+        Easing::EaseInOutCubic,
+        Easing::EaseOutBack,
+        Easing::EaseOutBounce,
+    ];
+
+    /// Easing named by its label, ignoring case and underscores, so scripts
+    /// can write "EaseOutQuad" or "ease_out_quad"
+    pub fn from_name(name: &str) -> Option<Easing> {
+        let name: String = name.chars().filter(|c| *c != '_').collect();
+        Easing::ALL.into_iter().find(|easing| easing.label().eq_ignore_ascii_case(&name))
+    }
+
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::EaseOutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+            Easing::EaseOutBounce => {
+                let n1 = 7.5625;
+                let d1 = 2.75;
+                if t < 1.0 / d1 {
+                    n1 * t * t
+                } else if t < 2.0 / d1 {
+                    let t = t - 1.5 / d1;
+                    n1 * t * t + 0.75
+                } else if t < 2.5 / d1 {
+                    let t = t - 2.25 / d1;
+                    n1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d1;
+                    n1 * t * t + 0.984375
+                }
+            }
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::EaseInQuad => "EaseInQuad",
+            Easing::EaseOutQuad => "EaseOutQuad",
+            Easing::EaseInOutQuad => "EaseInOutQuad",
+            Easing::EaseInCubic => "EaseInCubic",
+            Easing::EaseOutCubic => "EaseOutCubic",
+            Easing::EaseInOutCubic => "EaseInOutCubic",
+            Easing::EaseOutBack => "EaseOutBack",
+            Easing::EaseOutBounce => "EaseOutBounce",
+        }
+    }
+}
+
+/// Transform property driven by a tween
+#[derive(Debug, Clone, Copy)]
+pub enum TweenProperty {
+    Position(Vec3),
+    Rotation(Quat),
+    Scale(Vec3),
+    /// Decaying positional jitter around the starting translation
+    Shake { amplitude: f32, frequency: f32 },
+}
+
+impl TweenProperty {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TweenProperty::Position(_) => "Position",
+            TweenProperty::Rotation(_) => "Rotation",
+            TweenProperty::Scale(_) => "Scale",
+            TweenProperty::Shake { .. } => "Shake",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TweenRepeat {
+    Once,
+    Loop,
+    PingPong,
+}
+
+impl TweenRepeat {
+    pub const ALL: [TweenRepeat; 3] = [TweenRepeat::Once, TweenRepeat::Loop, TweenRepeat::PingPong];
+
+    /// Repeat named by its label, ignoring case and underscores, as easings are
+    pub fn from_name(name: &str) -> Option<TweenRepeat> {
+        let name: String = name.chars().filter(|c| *c != '_').collect();
+        TweenRepeat::ALL.into_iter().find(|repeat| repeat.label().eq_ignore_ascii_case(&name))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TweenRepeat::Once => "Once",
+            TweenRepeat::Loop => "Loop",
+            TweenRepeat::PingPong => "PingPong",
+        }
+    }
+}
+
+/// Description of a tween, e.g. `Tween::position(entity, target, 0.5, Easing::EaseOutQuad)`
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+    pub entity: Entity,
+    pub property: TweenProperty,
+    pub duration: f32,
+    pub easing: Easing,
+    pub delay: f32,
+    pub repeat: TweenRepeat,
+}
+
+impl Tween {
+    pub fn position(entity: Entity, target: Vec3, duration: f32, easing: Easing) -> Self {
+        Self::new(entity, TweenProperty::Position(target), duration, easing)
+    }
+
+    pub fn rotation(entity: Entity, target: Quat, duration: f32, easing: Easing) -> Self {
+        Self::new(entity, TweenProperty::Rotation(target), duration, easing)
+    }
+
+    pub fn scale(entity: Entity, target: Vec3, duration: f32, easing: Easing) -> Self {
+        Self::new(entity, TweenProperty::Scale(target), duration, easing)
+    }
+
+    pub fn shake(entity: Entity, amplitude: f32, duration: f32) -> Self {
+        Self::new(
+            entity,
+            TweenProperty::Shake {
+                amplitude,
+                frequency: 25.0,
+            },
+            duration,
+            Easing::Linear,
+        )
+    }
+
+    fn new(entity: Entity, property: TweenProperty, duration: f32, easing: Easing) -> Self {
+        Self {
+            entity,
+            property,
+            duration: duration.max(0.0),
+            easing,
+            delay: 0.0,
+            repeat: TweenRepeat::Once,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: TweenRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TweenId(pub u64);
+
+/// A running tween with the values captured when it started
+#[derive(Debug, Clone)]
+pub struct ActiveTween {
+    pub id: TweenId,
+    pub tween: Tween,
+    pub elapsed: f32,
+    start: Option<Transform>,
+}
+
+impl ActiveTween {
+    pub fn progress(&self) -> f32 {
+        if self.tween.duration <= 0.0 {
+            return 1.0;
+        }
+        ((self.elapsed - self.tween.delay) / self.tween.duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Active tweens, driven by `update_tweens`
+#[derive(Resource, Default)]
+pub struct Tweens {
+    pub active: Vec<ActiveTween>,
+    next_id: u64,
+}
+
+impl Tweens {
+    pub fn add(&mut self, tween: Tween) -> TweenId {
+        self.next_id += 1;
+        let id = TweenId(self.next_id);
+        self.active.push(ActiveTween {
+            id,
+            tween,
+            elapsed: 0.0,
+            start: None,
+        });
+        id
+    }
+
+    pub fn cancel(&mut self, id: TweenId) {
+        self.active.retain(|active| active.id != id);
+    }
+
+    pub fn cancel_entity(&mut self, entity: Entity) {
+        self.active.retain(|active| active.tween.entity != entity);
+    }
+
+    pub fn is_active(&self, id: TweenId) -> bool {
+        self.active.iter().any(|active| active.id == id)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TweenCompletedEvent {
+    pub id: TweenId,
+    pub entity: Entity,
+}
+
+/// Named countdown timers, e.g. `timers.start("respawn", 3.0, false)`
+#[derive(Resource, Default)]
+pub struct GameTimers {
+    pub timers: Vec<GameTimer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameTimer {
+    pub name: String,
+    pub timer: Timer,
+}
+
+impl GameTimers {
+    pub fn start(&mut self, name: impl Into<String>, seconds: f32, repeating: bool) {
+        let name = name.into();
+        let mode = if repeating {
+            TimerMode::Repeating
+        } else {
+            TimerMode::Once
+        };
+        self.timers.retain(|timer| timer.name != name);
+        self.timers.push(GameTimer {
+            name,
+            timer: Timer::from_seconds(seconds.max(0.0), mode),
+        });
+    }
+
+    pub fn cancel(&mut self, name: &str) {
+        self.timers.retain(|timer| timer.name != name);
+    }
+
+    pub fn remaining(&self, name: &str) -> Option<f32> {
+        self.timers
+            .iter()
+            .find(|timer| timer.name == name)
+            .map(|timer| timer.timer.remaining_secs())
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TimerFinishedEvent {
+    pub name: String,
+}
+
+pub fn update_tweens(
+    time: Res<Time>,
+    mut tweens: ResMut<Tweens>,
+    mut transforms: Query<&mut Transform>,
+    mut completed: EventWriter<TweenCompletedEvent>,
+) {
+    let delta = time.delta_seconds();
+    let mut finished = Vec::new();
+
+    for active in tweens.active.iter_mut() {
+        let Ok(mut transform) = transforms.get_mut(active.tween.entity) else {
+            finished.push(active.id);
+            continue;
+        };
+
+        active.elapsed += delta;
+        if active.elapsed < active.tween.delay {
+            continue;
+        }
+        let start = *active.start.get_or_insert(*transform);
+
+        let raw = active.progress();
+        let t = active.tween.easing.apply(raw);
+        match active.tween.property {
+            TweenProperty::Position(target) => transform.translation = start.translation.lerp(target, t),
+            TweenProperty::Rotation(target) => transform.rotation = start.rotation.slerp(target, t),
+            TweenProperty::Scale(target) => transform.scale = start.scale.lerp(target, t),
+            TweenProperty::Shake { amplitude, frequency } => {
+                let phase = (active.elapsed - active.tween.delay) * frequency;
+                let falloff = 1.0 - raw;
+                let jitter = Vec3::new(
+                    (phase * 1.0).sin(),
+                    (phase * 1.3 + 1.7).sin(),
+                    (phase * 0.7 + 4.1).sin(),
+                );
+                transform.translation = start.translation + jitter * amplitude * falloff;
+            }
+        }
+
+        if raw < 1.0 {
+            continue;
+        }
+        match active.tween.repeat {
+            TweenRepeat::Once => {
+                if let TweenProperty::Shake { .. } = active.tween.property {
+                    transform.translation = start.translation;
+                }
+                finished.push(active.id);
+                completed.send(TweenCompletedEvent {
+                    id: active.id,
+                    entity: active.tween.entity,
+                });
+            }
+            TweenRepeat::Loop => {
+                active.elapsed = active.tween.delay;
+                *transform = start;
+            }
+            TweenRepeat::PingPong => {
+                active.elapsed = active.tween.delay;
+                active.start = Some(*transform);
+                active.tween.property = match active.tween.property {
+                    TweenProperty::Position(_) => TweenProperty::Position(start.translation),
+                    TweenProperty::Rotation(_) => TweenProperty::Rotation(start.rotation),
+                    TweenProperty::Scale(_) => TweenProperty::Scale(start.scale),
+                    shake => shake,
+                };
+            }
+        }
+    }
+
+    if !finished.is_empty() {
+        tweens.active.retain(|active| !finished.contains(&active.id));
+    }
+}
+
+pub fn update_game_timers(
+    time: Res<Time>,
+    mut timers: ResMut<GameTimers>,
+    mut finished: EventWriter<TimerFinishedEvent>,
+) {
+    for timer in timers.timers.iter_mut() {
+        timer.timer.tick(time.delta());
+        for _ in 0..timer.timer.times_finished_this_tick() {
+            finished.send(TimerFinishedEvent {
+                name: timer.name.clone(),
+            });
+        }
+    }
+    timers
+        .timers
+        .retain(|timer| timer.timer.mode() == TimerMode::Repeating || !timer.timer.finished());
+}
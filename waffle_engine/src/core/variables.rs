@@ -0,0 +1,77 @@
+// Waffle Engine Game Variables
+// Named flags and numbers shared by gameplay code, scripts and data-driven
+// systems such as dialogue conditions
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameVariables {
+    pub flags: HashMap<String, bool>,
+    pub values: HashMap<String, f32>,
+}
+
+impl GameVariables {
+    /// Unset flags are false
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.get(name).copied().unwrap_or(false)
+    }
+
+    pub fn set_flag(&mut self, name: impl Into<String>, value: bool) {
+        self.flags.insert(name.into(), value);
+    }
+
+    /// Unset values are zero
+    pub fn value(&self, name: &str) -> f32 {
+        self.values.get(name).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_value(&mut self, name: impl Into<String>, value: f32) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn add_value(&mut self, name: impl Into<String>, amount: f32) -> f32 {
+        let value = self.values.entry(name.into()).or_insert(0.0);
+        *value += amount;
+        *value
+    }
+}
+
+/// Test against game variables, used by data assets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VariableCondition {
+    Flag(String),
+    NotFlag(String),
+    AtLeast(String, f32),
+    Below(String, f32),
+}
+
+impl VariableCondition {
+    pub fn evaluate(&self, variables: &GameVariables) -> bool {
+        match self {
+            VariableCondition::Flag(name) => variables.flag(name),
+            VariableCondition::NotFlag(name) => !variables.flag(name),
+            VariableCondition::AtLeast(name, value) => variables.value(name) >= *value,
+            VariableCondition::Below(name, value) => variables.value(name) < *value,
+        }
+    }
+
+    pub fn variable(&self) -> &str {
+        match self {
+            VariableCondition::Flag(name)
+            | VariableCondition::NotFlag(name)
+            | VariableCondition::AtLeast(name, _)
+            | VariableCondition::Below(name, _) => name,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            VariableCondition::Flag(name) => name.clone(),
+            VariableCondition::NotFlag(name) => format!("!{name}"),
+            VariableCondition::AtLeast(name, value) => format!("{name} >= {value}"),
+            VariableCondition::Below(name, value) => format!("{name} < {value}"),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// Waffle Engine Vehicles
+// Raycast vehicles: each wheel casts a ray down from its suspension anchor and
+// pushes the chassis with spring, drive, brake and grip forces. Rigid body
+// physics is not enabled in the engine yet, so the chassis integrates its own
+// velocity and collides with scene meshes only through its wheels.
+//
+// Gameplay code and scripts drive a vehicle by writing its `VehicleInput`;
+// `VehicleKeyBindings` does that from the keyboard while playing.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::spatial::SpatialQuery;
+
+const VEHICLE_GRAVITY: f32 = 9.81;
+/// Longest step integrated at once; longer frames are split up
+const MAX_VEHICLE_STEP: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VehicleWheel {
+    /// Suspension anchor relative to the chassis, with the spring at rest
+    /// length below it
+    pub position: Vec3,
+    pub radius: f32,
+    pub steered: bool,
+    pub driven: bool,
+}
+
+/// Runtime state of a wheel, updated by the vehicle simulation
+#[derive(Debug, Clone, Default)]
+pub struct WheelState {
+    /// Ground point under the wheel, when it touches anything
+    pub contact: Option<Vec3>,
+    /// How far the spring is pushed in from its rest length
+    pub compression: f32,
+    /// Radians around the chassis' up axis
+    pub steer_angle: f32,
+    /// Radians the wheel has rolled, for posing wheel meshes
+    pub spin: f32,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct RaycastVehicle {
+    pub wheels: Vec<VehicleWheel>,
+    /// Kilograms
+    pub mass: f32,
+    /// Suspension travel at rest
+    pub suspension_length: f32,
+    /// Newtons per meter of compression
+    pub suspension_stiffness: f32,
+    pub suspension_damping: f32,
+    /// Newtons at full throttle, shared by the driven wheels
+    pub engine_force: f32,
+    /// Newtons at full brake, shared by all wheels
+    pub brake_force: f32,
+    /// Degrees at full steering input
+    pub max_steer_angle: f32,
+    /// Tire friction coefficient; limits drive, brake and cornering forces
+    pub grip: f32,
+    pub drag: f32,
+    #[serde(skip)]
+    pub velocity: Vec3,
+    #[serde(skip)]
+    pub angular_velocity: Vec3,
+    #[serde(skip)]
+    wheel_states: Vec<WheelState>,
+}
+
+impl Default for RaycastVehicle {
+    fn default() -> Self {
+        let wheel = |x: f32, z: f32, front: bool| VehicleWheel {
+            position: Vec3::new(x, -0.2, z),
+            radius: 0.35,
+            steered: front,
+            driven: !front,
+        };
+        Self {
+            wheels: vec![
+                wheel(-0.8, -1.3, true),
+                wheel(0.8, -1.3, true),
+                wheel(-0.8, 1.3, false),
+                wheel(0.8, 1.3, false),
+            ],
+            mass: 1200.0,
+            suspension_length: 0.3,
+            suspension_stiffness: 35000.0,
+            suspension_damping: 4000.0,
+            engine_force: 6000.0,
+            brake_force: 9000.0,
+            max_steer_angle: 30.0,
+            grip: 1.2,
+            drag: 0.4,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            wheel_states: Vec::new(),
+        }
+    }
+}
+
+impl RaycastVehicle {
+    pub fn wheel_states(&self) -> &[WheelState] {
+        &self.wheel_states
+    }
+
+    /// Speed along the chassis' forward axis, in meters per second
+    pub fn forward_speed(&self, rotation: Quat) -> f32 {
+        self.velocity.dot(rotation * Vec3::NEG_Z)
+    }
+
+    /// Wheel hub relative to the chassis for the current suspension travel
+    pub fn wheel_center(&self, index: usize) -> Option<Vec3> {
+        let wheel = self.wheels.get(index)?;
+        let compression = self.wheel_states.get(index).map_or(0.0, |state| state.compression);
+        Some(wheel.position - Vec3::Y * (self.suspension_length - compression))
+    }
+
+    /// Diagonal inertia of a box spanning the wheels
+    fn inertia(&self) -> Vec3 {
+        let half = self
+            .wheels
+            .iter()
+            .fold(Vec3::splat(0.25), |half, wheel| half.max(wheel.position.abs()))
+            .with_y(0.5);
+        let squared = half * half;
+        Vec3::new(squared.y + squared.z, squared.x + squared.z, squared.x + squared.y) * (self.mass / 3.0)
+    }
+}
+
+/// Driver controls, written by gameplay code, scripts or `VehicleKeyBindings`
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct VehicleInput {
+    /// -1 for full reverse to 1 for full throttle
+    pub throttle: f32,
+    /// 0 to 1
+    pub brake: f32,
+    /// -1 for full left to 1 for full right
+    pub steer: f32,
+}
+
+/// Keyboard controls for a vehicle while playing
+#[derive(Component, Debug, Clone)]
+pub struct VehicleKeyBindings {
+    pub throttle: KeyCode,
+    pub reverse: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub brake: KeyCode,
+}
+
+impl Default for VehicleKeyBindings {
+    fn default() -> Self {
+        Self {
+            throttle: KeyCode::KeyW,
+            reverse: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            brake: KeyCode::Space,
+        }
+    }
+}
+
+/// Posed as wheel `index` of the vehicle it is a child of
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VehicleWheelMesh {
+    pub index: usize,
+}
+
+pub fn read_vehicle_key_bindings(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&VehicleKeyBindings, &mut VehicleInput)>,
+) {
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        f32::from(u8::from(keyboard.pressed(positive))) - f32::from(u8::from(keyboard.pressed(negative)))
+    };
+    for (bindings, mut input) in &mut query {
+        input.throttle = axis(bindings.reverse, bindings.throttle);
+        input.steer = axis(bindings.left, bindings.right);
+        input.brake = if keyboard.pressed(bindings.brake) { 1.0 } else { 0.0 };
+    }
+}
+
+pub fn update_raycast_vehicles(
+    time: Res<Time>,
+    spatial: SpatialQuery,
+    mut vehicles: Query<(Entity, &mut RaycastVehicle, &mut Transform, Option<&VehicleInput>)>,
+    parent_query: Query<&Parent>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+    let steps = (delta / MAX_VEHICLE_STEP).ceil().max(1.0);
+    let dt = delta / steps;
+
+    for (entity, mut vehicle, mut transform, input) in &mut vehicles {
+        let input = input.copied().unwrap_or_default();
+        let not_self = |candidate: Entity| {
+            candidate != entity && !parent_query.iter_ancestors(candidate).any(|ancestor| ancestor == entity)
+        };
+        for _ in 0..steps as u32 {
+            step_vehicle(&mut vehicle, &mut transform, &input, dt, &spatial, &not_self);
+        }
+    }
+}
+
+fn step_vehicle(
+    vehicle: &mut RaycastVehicle,
+    transform: &mut Transform,
+    input: &VehicleInput,
+    dt: f32,
+    spatial: &SpatialQuery,
+    filter: &impl Fn(Entity) -> bool,
+) {
+    let wheel_count = vehicle.wheels.len();
+    vehicle.wheel_states.resize(wheel_count, WheelState::default());
+    let mass = vehicle.mass.max(1.0);
+    let rotation = transform.rotation;
+    let up = rotation * Vec3::Y;
+    let driven_count = vehicle.wheels.iter().filter(|wheel| wheel.driven).count().max(1) as f32;
+    let wheel_mass = mass / wheel_count.max(1) as f32;
+
+    let mut force = Vec3::NEG_Y * VEHICLE_GRAVITY * mass;
+    let mut torque = Vec3::ZERO;
+    for index in 0..wheel_count {
+        let wheel = vehicle.wheels[index].clone();
+        let steer_angle = if wheel.steered {
+            -input.steer.clamp(-1.0, 1.0) * vehicle.max_steer_angle.to_radians()
+        } else {
+            0.0
+        };
+        let anchor = transform.translation + rotation * wheel.position;
+        let reach = vehicle.suspension_length + wheel.radius;
+        let hit = spatial.cast_ray_filtered(anchor, -up, reach, filter);
+
+        let state = &mut vehicle.wheel_states[index];
+        state.steer_angle = steer_angle;
+        let Some(hit) = hit else {
+            state.contact = None;
+            state.compression = 0.0;
+            continue;
+        };
+
+        let arm = hit.point - transform.translation;
+        let point_velocity = vehicle.velocity + vehicle.angular_velocity.cross(arm);
+        let compression = (reach - hit.distance).min(vehicle.suspension_length);
+        let load = (vehicle.suspension_stiffness * compression - vehicle.suspension_damping * point_velocity.dot(up)).max(0.0);
+
+        let heading = rotation * Quat::from_rotation_y(steer_angle) * Vec3::NEG_Z;
+        let forward = (heading - hit.normal * heading.dot(hit.normal)).normalize_or_zero();
+        let right = forward.cross(hit.normal);
+        let forward_speed = point_velocity.dot(forward);
+        let lateral_speed = point_velocity.dot(right);
+
+        // Tires can push at most grip times their load; braking and cornering
+        // never push harder than it takes to stop the wheel's share of mass
+        let max_friction = vehicle.grip * load;
+        let drive = if wheel.driven {
+            input.throttle.clamp(-1.0, 1.0) * vehicle.engine_force / driven_count
+        } else {
+            0.0
+        };
+        let stop_force = wheel_mass / dt;
+        let brake = (input.brake.clamp(0.0, 1.0) * vehicle.brake_force / wheel_count as f32)
+            .min(forward_speed.abs() * stop_force);
+        let longitudinal = (drive - brake * forward_speed.signum()).clamp(-max_friction, max_friction);
+        let lateral = (-lateral_speed * stop_force).clamp(-max_friction, max_friction);
+
+        let wheel_force = hit.normal * load + forward * longitudinal + right * lateral;
+        force += wheel_force;
+        torque += arm.cross(wheel_force);
+
+        state.contact = Some(hit.point);
+        state.compression = compression;
+        state.spin += forward_speed / wheel.radius.max(0.01) * dt;
+    }
+
+    force -= vehicle.velocity * vehicle.velocity.length() * vehicle.drag;
+    vehicle.velocity += force / mass * dt;
+    let local_torque = rotation.inverse() * torque;
+    let angular_acceleration = rotation * (local_torque / vehicle.inertia());
+    vehicle.angular_velocity = (vehicle.angular_velocity + angular_acceleration * dt) * (1.0 - 0.5 * dt);
+
+    transform.translation += vehicle.velocity * dt;
+    transform.rotation = (Quat::from_scaled_axis(vehicle.angular_velocity * dt) * rotation).normalize();
+
+    // Wheels that lost the ground spin down
+    for state in &mut vehicle.wheel_states {
+        if state.contact.is_none() {
+            state.spin *= 1.0 - dt;
+        }
+    }
+}
+
+/// Move wheel meshes to their suspension travel, steering and spin
+pub fn pose_vehicle_wheels(
+    vehicles: Query<&RaycastVehicle>,
+    mut wheels: Query<(&VehicleWheelMesh, &Parent, &mut Transform)>,
+) {
+    for (wheel, parent, mut transform) in &mut wheels {
+        let Ok(vehicle) = vehicles.get(parent.get()) else {
+            continue;
+        };
+        let (Some(center), Some(state)) = (vehicle.wheel_center(wheel.index), vehicle.wheel_states.get(wheel.index)) else {
+            continue;
+        };
+        transform.translation = center;
+        transform.rotation = Quat::from_rotation_y(state.steer_angle) * Quat::from_rotation_x(-state.spin);
+    }
+}
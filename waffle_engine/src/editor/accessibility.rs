@@ -0,0 +1,262 @@
+//! Waffle Engine Editor Accessibility
+//! Mirrors the editor UI into Bevy's accessibility tree for screen readers
+
+use bevy::a11y::accesskit as bevy_accesskit;
+use bevy::a11y::{AccessibilityNode, AccessibilityRequested, ActionRequest, Focus};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::egui::accesskit as egui_accesskit;
+use bevy_egui::{egui, EguiContext, EguiInput, EguiOutput};
+use std::collections::HashMap;
+
+use crate::core::components::EditorHidden;
+
+/// Name a widget for screen readers when its text doesn't describe it
+pub trait AccessibleName {
+    fn accessible_name(self, name: impl Into<String>) -> Self;
+}
+
+impl AccessibleName for egui::Response {
+    fn accessible_name(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.ctx.accesskit_node_builder(self.id, |builder| builder.set_name(name));
+        self
+    }
+}
+
+/// Describe a hierarchy row as an item of a tree, with its child count
+pub fn describe_hierarchy_row(response: &egui::Response, name: &str, selected: bool, children: usize) {
+    let label = match children {
+        0 => name.to_string(),
+        1 => format!("{name}, 1 child"),
+        count => format!("{name}, {count} children"),
+    };
+    response.ctx.accesskit_node_builder(response.id, |builder| {
+        builder.set_role(egui_accesskit::Role::TreeItem);
+        builder.set_name(label);
+        builder.set_selected(selected);
+    });
+}
+
+/// The Bevy entities standing in for egui's widgets, by egui node
+#[derive(Resource, Default)]
+pub struct EditorAccessTree {
+    nodes: HashMap<egui_accesskit::NodeId, Entity>,
+    parents: HashMap<Entity, Option<Entity>>,
+    /// The egui node behind each entity, for actions coming back
+    targets: HashMap<Entity, egui_accesskit::NodeId>,
+}
+
+/// Have egui describe its widgets once a screen reader is listening
+pub fn enable_editor_accesskit(
+    requested: Res<AccessibilityRequested>,
+    mut contexts: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut enabled: Local<bool>,
+) {
+    if *enabled || !requested.get() {
+        return;
+    }
+    for mut context in contexts.iter_mut() {
+        context.get_mut().enable_accesskit();
+        *enabled = true;
+    }
+}
+
+/// Copy egui's tree into Bevy's, reusing the entities of widgets still shown
+pub fn mirror_editor_access_tree(
+    mut commands: Commands,
+    mut tree: ResMut<EditorAccessTree>,
+    mut focus: ResMut<Focus>,
+    mut windows: Query<(&EguiOutput, &mut EguiContext), With<PrimaryWindow>>,
+) {
+    let Ok((output, mut context)) = windows.get_single_mut() else {
+        return;
+    };
+    let Some(update) = output.platform_output.accesskit_update.as_ref() else {
+        return;
+    };
+    let Some(root) = update.tree.as_ref().map(|tree| tree.root) else {
+        return;
+    };
+    // egui lays out in points; the platform expects physical pixels
+    let scale = context.get_mut().pixels_per_point() as f64;
+
+    let EditorAccessTree { nodes, parents, targets } = &mut *tree;
+    let mut seen = HashMap::new();
+    for (id, _) in update.nodes.iter().filter(|(id, _)| *id != root) {
+        let entity = nodes
+            .get(id)
+            .copied()
+            .unwrap_or_else(|| commands.spawn((EditorHidden, Name::new("Editor Widget"))).id());
+        seen.insert(*id, entity);
+    }
+
+    let mut parent_of = HashMap::new();
+    for (id, node) in &update.nodes {
+        let parent = seen.get(id).copied();
+        for child in node.children() {
+            if let Some(child) = seen.get(child) {
+                parent_of.insert(*child, parent);
+            }
+        }
+    }
+
+    for (id, node) in update.nodes.iter().filter(|(id, _)| *id != root) {
+        let entity = seen[id];
+        let mut builder = convert_node(node, scale);
+        let labels: Vec<_> = node
+            .labelled_by()
+            .iter()
+            .filter_map(|label| seen.get(label))
+            .map(|label| bevy_accesskit::NodeId(label.to_bits()))
+            .collect();
+        if !labels.is_empty() {
+            builder.set_labelled_by(labels);
+        }
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(AccessibilityNode(builder));
+        let parent = parent_of.get(&entity).copied().flatten();
+        if parents.get(&entity) != Some(&parent) {
+            match parent {
+                Some(parent) => entity_commands.set_parent(parent),
+                None => entity_commands.remove_parent(),
+            };
+            parents.insert(entity, parent);
+        }
+        targets.insert(entity, *id);
+    }
+
+    // Widgets egui no longer shows leave the tree
+    for (id, entity) in nodes.iter() {
+        if !seen.contains_key(id) {
+            commands.entity(*entity).despawn();
+            parents.remove(entity);
+            targets.remove(entity);
+        }
+    }
+    *nodes = seen;
+
+    let focused = nodes.get(&update.focus).copied();
+    if focused.is_some() || focus.0.is_some_and(|entity| targets.contains_key(&entity)) {
+        focus.0 = focused;
+    }
+}
+
+/// Hand screen reader actions on editor widgets to egui as input
+pub fn forward_editor_access_actions(
+    tree: Res<EditorAccessTree>,
+    mut requests: EventReader<ActionRequest>,
+    mut inputs: Query<&mut EguiInput, With<PrimaryWindow>>,
+) {
+    let Ok(mut input) = inputs.get_single_mut() else {
+        requests.clear();
+        return;
+    };
+    for request in requests.read() {
+        let Some(target) = Entity::try_from_bits(request.target.0)
+            .ok()
+            .and_then(|entity| tree.targets.get(&entity))
+        else {
+            continue;
+        };
+        let Some(action) = convert_action(request.action) else {
+            continue;
+        };
+        let data = match &request.data {
+            Some(bevy_accesskit::ActionData::NumericValue(value)) => {
+                Some(egui_accesskit::ActionData::NumericValue(*value))
+            }
+            Some(bevy_accesskit::ActionData::Value(value)) => Some(egui_accesskit::ActionData::Value(value.clone())),
+            _ => None,
+        };
+        input.events.push(egui::Event::AccessKitActionRequest(egui_accesskit::ActionRequest {
+            action,
+            target: *target,
+            data,
+        }));
+    }
+}
+
+/// egui and Bevy are built against different AccessKit releases, so nodes are
+/// copied field by field
+fn convert_node(node: &egui_accesskit::Node, scale: f64) -> bevy_accesskit::NodeBuilder {
+    let mut builder = bevy_accesskit::NodeBuilder::new(convert_role(node.role()));
+    if let Some(name) = node.name() {
+        builder.set_name(name);
+    }
+    if let Some(value) = node.value() {
+        builder.set_value(value);
+    }
+    if let Some(value) = node.numeric_value() {
+        builder.set_numeric_value(value);
+    }
+    if let Some(bounds) = node.bounds() {
+        builder.set_bounds(bevy_accesskit::Rect {
+            x0: bounds.x0 * scale,
+            y0: bounds.y0 * scale,
+            x1: bounds.x1 * scale,
+            y1: bounds.y1 * scale,
+        });
+    }
+    if node.is_disabled() {
+        builder.set_disabled();
+    }
+    if let Some(selected) = node.is_selected() {
+        builder.set_selected(selected);
+    }
+    if let Some(checked) = node.checked() {
+        builder.set_toggled(match checked {
+            egui_accesskit::Checked::True => bevy_accesskit::Toggled::True,
+            egui_accesskit::Checked::False => bevy_accesskit::Toggled::False,
+            egui_accesskit::Checked::Mixed => bevy_accesskit::Toggled::Mixed,
+        });
+    }
+    for (egui_action, bevy_action) in ACTIONS {
+        if node.supports_action(egui_action) {
+            builder.add_action(bevy_action);
+        }
+    }
+    builder
+}
+
+/// The roles egui gives its widgets
+fn convert_role(role: egui_accesskit::Role) -> bevy_accesskit::Role {
+    use bevy_accesskit::Role as Bevy;
+    use egui_accesskit::Role as Egui;
+    match role {
+        Egui::Window => Bevy::Window,
+        Egui::StaticText => Bevy::StaticText,
+        Egui::InlineTextBox => Bevy::InlineTextBox,
+        Egui::Link => Bevy::Link,
+        Egui::TextInput => Bevy::TextInput,
+        Egui::PasswordInput => Bevy::PasswordInput,
+        Egui::MultilineTextInput => Bevy::MultilineTextInput,
+        Egui::Button | Egui::ToggleButton => Bevy::Button,
+        Egui::CheckBox => Bevy::CheckBox,
+        Egui::RadioButton => Bevy::RadioButton,
+        Egui::ComboBox => Bevy::ComboBox,
+        Egui::Slider => Bevy::Slider,
+        Egui::SpinButton => Bevy::SpinButton,
+        Egui::ColorWell => Bevy::ColorWell,
+        Egui::ProgressIndicator => Bevy::ProgressIndicator,
+        Egui::TreeItem => Bevy::TreeItem,
+        _ => Bevy::Unknown,
+    }
+}
+
+/// The actions egui handles, in both releases
+const ACTIONS: [(egui_accesskit::Action, bevy_accesskit::Action); 6] = [
+    (egui_accesskit::Action::Default, bevy_accesskit::Action::Default),
+    (egui_accesskit::Action::Focus, bevy_accesskit::Action::Focus),
+    (egui_accesskit::Action::Increment, bevy_accesskit::Action::Increment),
+    (egui_accesskit::Action::Decrement, bevy_accesskit::Action::Decrement),
+    (egui_accesskit::Action::SetValue, bevy_accesskit::Action::SetValue),
+    (egui_accesskit::Action::ScrollIntoView, bevy_accesskit::Action::ScrollIntoView),
+];
+
+fn convert_action(action: bevy_accesskit::Action) -> Option<egui_accesskit::Action> {
+    ACTIONS
+        .iter()
+        .find(|(_, bevy_action)| *bevy_action == action)
+        .map(|(egui_action, _)| *egui_action)
+}
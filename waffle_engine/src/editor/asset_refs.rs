@@ -0,0 +1,706 @@
+//! Waffle Engine Asset References
+//! Finds where an asset is used before it is moved or deleted, so the editor
+//! can fix the references instead of leaving broken handles behind. Text
+//! assets refer to other assets by path and have those paths rewritten in
+//! place; handles loaded in the open scene are switched to the new path.
+//! Scene files also keep the UUIDs of the assets they name, so assets moved
+//! outside the editor are found again by their sidecars.
+//! Deleted images and models can leave a magenta placeholder at their path so
+//! everything that used them still loads.
+
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use super::{AssetBrowserCache, EditorState};
+use crate::core::asset_meta::{asset_uuid, find_asset_by_uuid, meta_path};
+use crate::rendering::materials::{material_textures, material_textures_mut};
+use crate::rendering::placeholders::LocateMissingAssetEvent;
+
+/// Text assets searched for references
+const REFERENCE_EXTENSIONS: [&str; 7] = ["ron", "json", "gltf", "wgsl", "wmat", "mtl", "obj"];
+const PLACEHOLDER_COLOR: [u8; 3] = [255, 0, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetFileAction {
+    Move,
+    Delete,
+}
+
+/// A text asset that refers to the asset being moved or deleted
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetReference {
+    pub file: String,
+    pub count: usize,
+}
+
+/// State of the move/delete window opened from the Assets panel
+#[derive(Clone, Debug)]
+pub struct AssetFileDialog {
+    pub path: String,
+    pub action: AssetFileAction,
+    /// Destination while moving, relative to the assets folder
+    pub target: String,
+    pub references: Vec<AssetReference>,
+}
+
+impl AssetFileDialog {
+    pub fn new(root: &Path, path: &str, action: AssetFileAction) -> Self {
+        Self {
+            path: path.to_string(),
+            action,
+            target: path.to_string(),
+            references: find_asset_references(root, path),
+        }
+    }
+}
+
+#[derive(Event, Clone, Debug, PartialEq)]
+pub enum AssetFileEvent {
+    Move {
+        from: String,
+        to: String,
+        fix_references: bool,
+    },
+    Delete {
+        path: String,
+        placeholder: bool,
+    },
+}
+
+/// Ways `file` can spell `path`. glTF uris, MTL texture maps and OBJ
+/// material libraries are relative to their file; everything else uses paths
+/// relative to the assets folder or the project.
+fn reference_forms(file: &str, path: &str) -> Vec<String> {
+    if file.ends_with(".gltf") || file.ends_with(".mtl") || file.ends_with(".obj") {
+        vec![relative_path(parent_dir(file), path)]
+    } else {
+        vec![path.to_string(), format!("assets/{path}")]
+    }
+}
+
+fn relative_path(dir: &str, path: &str) -> String {
+    let dir: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').collect();
+    let common = dir
+        .iter()
+        .zip(&path[..path.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; dir.len() - common];
+    parts.extend(&path[common..]);
+    parts.join("/")
+}
+
+/// Join `path`, relative to the folder `dir`, into a path relative to the
+/// assets folder
+fn resolve_relative(dir: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Statements of OBJ and MTL files that end in a path: texture maps and
+/// material libraries
+fn names_path(keyword: &str) -> bool {
+    keyword.starts_with("map_") || matches!(keyword, "bump" | "disp" | "decal" | "norm" | "refl" | "mtllib")
+}
+
+/// Paths named at the end of OBJ or MTL statements
+fn statement_paths(text: &str) -> Vec<&str> {
+    text.lines()
+        .filter(|line| line.split_whitespace().next().is_some_and(names_path))
+        .filter_map(|line| line.split_whitespace().last())
+        .collect()
+}
+
+/// Replace `from` where it ends an OBJ or MTL statement, and count them
+fn replace_statement_paths(text: &str, from: &str, to: &str) -> (String, usize) {
+    let mut count = 0;
+    let mut result = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end();
+        let keyword = content.split_whitespace().next().unwrap_or("");
+        match content.strip_suffix(from) {
+            Some(before) if names_path(keyword) && before.ends_with(char::is_whitespace) => {
+                result.push_str(before);
+                result.push_str(to);
+                result.push_str(&line[content.len()..]);
+                count += 1;
+            }
+            _ => result.push_str(line),
+        }
+    }
+    (result, count)
+}
+
+/// Replace references to `from` in the text of `file`, and count them
+fn replace_references(file: &str, text: &str, from: &str, to: &str) -> (String, usize) {
+    if file.ends_with(".mtl") || file.ends_with(".obj") {
+        replace_statement_paths(text, from, to)
+    } else {
+        replace_quoted(text, from, to)
+    }
+}
+
+/// Relative paths the file moved from `from` to `to` holds itself, such as a
+/// glTF's buffer and image uris, as their old and new spellings
+fn own_relative_references(text: &str, from: &str, to: &str) -> Vec<(String, String)> {
+    let paths: Vec<String> = if to.ends_with(".gltf") {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+            return Vec::new();
+        };
+        ["images", "buffers"]
+            .iter()
+            .filter_map(|key| json.get(key)?.as_array())
+            .flatten()
+            .filter_map(|item| item.get("uri")?.as_str())
+            .filter(|uri| !uri.starts_with("data:") && !uri.contains("://"))
+            .map(str::to_string)
+            .collect()
+    } else if to.ends_with(".mtl") || to.ends_with(".obj") {
+        statement_paths(text)
+            .into_iter()
+            .filter(|path| !Path::new(path).is_absolute())
+            .map(str::to_string)
+            .collect()
+    } else {
+        return Vec::new();
+    };
+    let mut references: Vec<(String, String)> = Vec::new();
+    for uri in paths {
+        let moved = relative_path(parent_dir(to), &resolve_relative(parent_dir(from), &uri));
+        if moved != uri && !references.iter().any(|(old, _)| *old == uri) {
+            references.push((uri, moved));
+        }
+    }
+    references
+}
+
+/// Keep the relative references inside a file moved from `from` to `to`
+/// pointing at what they named from its old folder, returning how many were
+/// changed
+pub fn rewrite_own_references(root: &Path, from: &str, to: &str) -> anyhow::Result<usize> {
+    if parent_dir(from) == parent_dir(to) {
+        return Ok(0);
+    }
+    let file_path = root.join(to);
+    let Ok(mut text) = std::fs::read_to_string(&file_path) else {
+        return Ok(0);
+    };
+    let mut total = 0;
+    for (old, new) in own_relative_references(&text, from, to) {
+        let (rewritten, count) = replace_references(to, &text, &old, &new);
+        text = rewritten;
+        total += count;
+    }
+    if total > 0 {
+        std::fs::write(&file_path, text)?;
+    }
+    Ok(total)
+}
+
+/// Quoted strings in a text asset, such as the paths a scene file names
+fn quoted_strings(text: &str) -> Vec<&str> {
+    let mut strings = Vec::new();
+    let mut start = None;
+    let mut escaped = false;
+    for (index, character) in text.char_indices() {
+        match (start, character) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), '"') => {
+                strings.push(&text[open..index]);
+                start = None;
+            }
+            (None, '"') => start = Some(index + 1),
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// UUIDs of the assets under `root` that `text` names by path, labels
+/// dropped, read from their sidecars
+pub fn referenced_asset_uuids(root: &Path, text: &str) -> BTreeMap<String, Uuid> {
+    let mut uuids = BTreeMap::new();
+    for string in quoted_strings(text) {
+        let path = string.split_once('#').map_or(string, |(path, _)| path);
+        if path.is_empty() || uuids.contains_key(path) || !root.join(path).is_file() {
+            continue;
+        }
+        if let Some(uuid) = asset_uuid(root, path) {
+            uuids.insert(path.to_string(), uuid);
+        }
+    }
+    uuids
+}
+
+/// Point references in `text` to assets that are gone from their path at
+/// wherever the sidecar with the same UUID now is, for assets moved outside
+/// the editor. Returns the moves that were followed.
+pub fn follow_moved_assets(root: &Path, text: &mut String, uuids: &BTreeMap<String, Uuid>) -> Vec<(String, String)> {
+    let mut moves = Vec::new();
+    for (path, uuid) in uuids {
+        if root.join(path).exists() {
+            continue;
+        }
+        let Some(moved) = find_asset_by_uuid(root, *uuid) else {
+            continue;
+        };
+        let (rewritten, _) = replace_quoted(text, path, &moved);
+        *text = rewritten;
+        moves.push((path.clone(), moved));
+    }
+    moves
+}
+
+/// Replace quoted occurrences of `from`, including ones followed by a label
+/// such as `#Scene0`, and count them
+fn replace_quoted(text: &str, from: &str, to: &str) -> (String, usize) {
+    let needle = format!("\"{from}");
+    let mut result = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = text;
+    while let Some(index) = rest.find(&needle) {
+        let after = &rest[index + needle.len()..];
+        result.push_str(&rest[..=index]);
+        if after.starts_with('"') || after.starts_with('#') {
+            result.push_str(to);
+            count += 1;
+        } else {
+            result.push_str(from);
+        }
+        rest = after;
+    }
+    result.push_str(rest);
+    (result, count)
+}
+
+/// Text assets under `root`, relative to it
+fn text_assets(root: &Path) -> impl Iterator<Item = String> + '_ {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| REFERENCE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .filter_map(move |entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+}
+
+pub fn find_asset_references(root: &Path, path: &str) -> Vec<AssetReference> {
+    let mut references = Vec::new();
+    for file in text_assets(root) {
+        if file == path {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(root.join(&file)) else {
+            continue;
+        };
+        let count: usize = reference_forms(&file, path)
+            .iter()
+            .map(|form| replace_references(&file, &text, form, form).1)
+            .sum();
+        if count > 0 {
+            references.push(AssetReference { file, count });
+        }
+    }
+    references
+}
+
+/// Point every reference to `from` at `to`, returning how many were changed
+pub fn rewrite_asset_references(root: &Path, from: &str, to: &str) -> anyhow::Result<usize> {
+    let mut total = 0;
+    for file in text_assets(root) {
+        if file == to {
+            continue;
+        }
+        let file_path = root.join(&file);
+        let Ok(mut text) = std::fs::read_to_string(&file_path) else {
+            continue;
+        };
+        let mut changed = 0;
+        for (old, new) in reference_forms(&file, from).iter().zip(reference_forms(&file, to)) {
+            let (rewritten, count) = replace_references(&file, &text, old, &new);
+            text = rewritten;
+            changed += count;
+        }
+        if changed > 0 {
+            std::fs::write(&file_path, text)?;
+            total += changed;
+        }
+    }
+    Ok(total)
+}
+
+pub fn can_write_placeholder(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "tga" | "obj" | "gltf" | "glb")
+}
+
+/// Write a magenta checker texture or cube in the format `file` names
+pub fn write_placeholder_asset(file: &Path) -> anyhow::Result<()> {
+    let extension = file
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "tga" => {
+            let texture = image::RgbImage::from_fn(8, 8, |x, y| {
+                if (x + y) % 2 == 0 {
+                    image::Rgb(PLACEHOLDER_COLOR)
+                } else {
+                    image::Rgb([0, 0, 0])
+                }
+            });
+            texture.save(file)?;
+        }
+        "obj" => std::fs::write(file, placeholder_obj())?,
+        "gltf" => std::fs::write(file, placeholder_gltf_json(None).to_string())?,
+        "glb" => std::fs::write(file, placeholder_glb())?,
+        _ => anyhow::bail!("no placeholder for .{extension} files"),
+    }
+    Ok(())
+}
+
+const CUBE_POSITIONS: [[f32; 3]; 8] = [
+    [-0.5, -0.5, -0.5],
+    [0.5, -0.5, -0.5],
+    [0.5, 0.5, -0.5],
+    [-0.5, 0.5, -0.5],
+    [-0.5, -0.5, 0.5],
+    [0.5, -0.5, 0.5],
+    [0.5, 0.5, 0.5],
+    [-0.5, 0.5, 0.5],
+];
+
+/// Counter-clockwise from outside
+const CUBE_TRIANGLES: [[u16; 3]; 12] = [
+    [0, 2, 1], [0, 3, 2],
+    [4, 5, 6], [4, 6, 7],
+    [0, 1, 5], [0, 5, 4],
+    [3, 6, 2], [3, 7, 6],
+    [0, 4, 7], [0, 7, 3],
+    [1, 2, 6], [1, 6, 5],
+];
+
+fn placeholder_obj() -> String {
+    let mut obj = String::from("# Placeholder for a deleted model\n");
+    for [x, y, z] in CUBE_POSITIONS {
+        obj.push_str(&format!("v {x} {y} {z}\n"));
+    }
+    for [a, b, c] in CUBE_TRIANGLES {
+        obj.push_str(&format!("f {} {} {}\n", a + 1, b + 1, c + 1));
+    }
+    obj
+}
+
+fn cube_buffer() -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for position in CUBE_POSITIONS {
+        for value in position {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for index in CUBE_TRIANGLES.iter().flatten() {
+        buffer.extend_from_slice(&index.to_le_bytes());
+    }
+    buffer
+}
+
+/// glTF document for the cube; the buffer is embedded unless `glb` holds it
+fn placeholder_gltf_json(glb_buffer_length: Option<usize>) -> serde_json::Value {
+    let buffer = cube_buffer();
+    let positions_length = CUBE_POSITIONS.len() * 12;
+    let buffer_json = match glb_buffer_length {
+        Some(length) => serde_json::json!({ "byteLength": length }),
+        None => serde_json::json!({
+            "byteLength": buffer.len(),
+            "uri": format!("data:application/octet-stream;base64,{}", base64_encode(&buffer)),
+        }),
+    };
+    serde_json::json!({
+        "asset": { "version": "2.0", "generator": "Waffle Engine placeholder" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "Missing Asset" }],
+        "meshes": [{
+            "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }],
+        }],
+        "materials": [{
+            "name": "Missing Asset",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 0.0, 1.0, 1.0],
+                "metallicFactor": 0.0,
+            },
+        }],
+        "buffers": [buffer_json],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": positions_length, "target": 34962 },
+            { "buffer": 0, "byteOffset": positions_length, "byteLength": buffer.len() - positions_length, "target": 34963 },
+        ],
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": CUBE_POSITIONS.len(),
+                "type": "VEC3",
+                "min": [-0.5, -0.5, -0.5],
+                "max": [0.5, 0.5, 0.5],
+            },
+            {
+                "bufferView": 1,
+                "componentType": 5123,
+                "count": CUBE_TRIANGLES.len() * 3,
+                "type": "SCALAR",
+            },
+        ],
+    })
+}
+
+fn placeholder_glb() -> Vec<u8> {
+    let mut buffer = cube_buffer();
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+    let mut json = placeholder_gltf_json(Some(buffer.len())).to_string().into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let length = 12 + 8 + json.len() + 8 + buffer.len();
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&buffer);
+    glb
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - index * 6)) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Where a handle to `from` should point after the move, keeping its label
+fn moved_asset_path(path: Option<&AssetPath>, from: &str, to: &str) -> Option<AssetPath<'static>> {
+    let path = path?;
+    if path.path() != Path::new(from) {
+        return None;
+    }
+    let moved = AssetPath::from(to.to_string());
+    Some(match path.label() {
+        Some(label) => moved.with_label(label.to_string()),
+        None => moved,
+    })
+}
+
+fn move_asset_file(root: &Path, from: &str, to: &str) -> anyhow::Result<()> {
+    let source = root.join(from);
+    let destination = root.join(to);
+    if destination.exists() {
+        anyhow::bail!("\"{to}\" already exists");
+    }
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(&source, &destination)?;
+    // Keep the asset's UUID and import settings with it
+    let meta = meta_path(root, from);
+    if meta.exists() {
+        std::fs::rename(meta, meta_path(root, to))?;
+    }
+    Ok(())
+}
+
+pub fn apply_asset_file_events(
+    mut events: EventReader<AssetFileEvent>,
+    mut cache: ResMut<AssetBrowserCache>,
+    mut editor_state: ResMut<EditorState>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut scene_query: Query<&mut Handle<Scene>>,
+    mut mesh_query: Query<&mut Handle<Mesh>>,
+) {
+    for event in events.read() {
+        let root = cache.root.clone();
+        match event {
+            AssetFileEvent::Move { from, to, fix_references } => {
+                if let Err(err) = move_asset_file(&root, from, to) {
+                    error!("Failed to move {} to {}: {}", from, to, err);
+                    continue;
+                }
+                info!("Moved {} to {}", from, to);
+                match rewrite_own_references(&root, from, to) {
+                    Ok(0) => {}
+                    Ok(count) => info!("Updated {} relative references inside {}", count, to),
+                    Err(err) => error!("Failed to update the relative references inside {}: {}", to, err),
+                }
+                if editor_state.selected_asset.as_deref() == Some(from.as_str()) {
+                    editor_state.selected_asset = Some(to.clone());
+                }
+                cache.last_scan = None;
+                if !fix_references {
+                    continue;
+                }
+
+                match rewrite_asset_references(&root, from, to) {
+                    Ok(0) => {}
+                    Ok(count) => info!("Updated {} references to {}", count, from),
+                    Err(err) => error!("Failed to update references to {}: {}", from, err),
+                }
+
+                // Loaded assets in the open scene
+                for mut handle in &mut scene_query {
+                    if let Some(path) = moved_asset_path(handle.path(), from, to) {
+                        *handle = asset_server.load(path);
+                    }
+                }
+                for mut handle in &mut mesh_query {
+                    if let Some(path) = moved_asset_path(handle.path(), from, to) {
+                        *handle = asset_server.load(path);
+                    }
+                }
+                let material_ids: Vec<AssetId<StandardMaterial>> = materials
+                    .iter()
+                    .filter(|(_, material)| {
+                        material_textures(material)
+                            .into_iter()
+                            .flatten()
+                            .any(|texture| moved_asset_path(texture.path(), from, to).is_some())
+                    })
+                    .map(|(id, _)| id)
+                    .collect();
+                for id in material_ids {
+                    let Some(material) = materials.get_mut(id) else {
+                        continue;
+                    };
+                    for texture in material_textures_mut(material).into_iter().flatten() {
+                        if let Some(path) = moved_asset_path(texture.path(), from, to) {
+                            *texture = asset_server.load(path);
+                        }
+                    }
+                }
+            }
+            AssetFileEvent::Delete { path, placeholder } => {
+                let file = root.join(path);
+                let result = if *placeholder {
+                    write_placeholder_asset(&file)
+                } else {
+                    std::fs::remove_file(&file).map_err(anyhow::Error::from).map(|()| {
+                        let _ = std::fs::remove_file(meta_path(&root, path));
+                    })
+                };
+                match result {
+                    Ok(()) if *placeholder => {
+                        info!("Replaced {} with a placeholder", path);
+                        asset_server.reload(path.clone());
+                    }
+                    Ok(()) => info!("Deleted {}", path),
+                    Err(err) => {
+                        error!("Failed to delete {}: {}", path, err);
+                        continue;
+                    }
+                }
+                if !placeholder && editor_state.selected_asset.as_deref() == Some(path.as_str()) {
+                    editor_state.selected_asset = None;
+                }
+                cache.last_scan = None;
+            }
+        }
+    }
+}
+
+/// Point text assets that still name a missing file at the file picked for it
+pub fn rewrite_located_references(
+    mut events: EventReader<LocateMissingAssetEvent>,
+    cache: Res<AssetBrowserCache>,
+) {
+    for event in events.read() {
+        match rewrite_asset_references(&cache.root, &event.missing, &event.replacement) {
+            Ok(0) => {}
+            Ok(count) => info!("Updated {} references to {}", count, event.missing),
+            Err(err) => error!("Failed to update references to {}: {}", event.missing, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moved_gltf_keeps_its_uris() {
+        let gltf = r#"{"buffers":[{"uri":"crate.bin"},{"uri":"data:application/octet-stream;base64,AA=="}],"images":[{"uri":"../textures/wood.png"}]}"#;
+        let references = own_relative_references(gltf, "models/crate.gltf", "models/props/crate.gltf");
+        assert_eq!(
+            references,
+            vec![
+                ("../textures/wood.png".to_string(), "../../textures/wood.png".to_string()),
+                ("crate.bin".to_string(), "../crate.bin".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_mtl_keeps_its_texture_maps() {
+        let mtl = "newmtl wood\nKd 1 1 1\nmap_Kd -s 2 2 1 wood.png\nbump ../normals/wood_n.png\n";
+        let (rewritten, count) = replace_statement_paths(mtl, "wood.png", "../wood.png");
+        assert_eq!(count, 1);
+        assert_eq!(rewritten, "newmtl wood\nKd 1 1 1\nmap_Kd -s 2 2 1 ../wood.png\nbump ../normals/wood_n.png\n");
+        assert_eq!(
+            own_relative_references(mtl, "models/crate.mtl", "models/props/crate.mtl"),
+            vec![
+                ("wood.png".to_string(), "../wood.png".to_string()),
+                ("../normals/wood_n.png".to_string(), "../../normals/wood_n.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_strings_skip_escapes() {
+        let text = r#"(name: Some("say \"hi\""), model: Some("models/crate.glb#Scene0"))"#;
+        assert_eq!(quoted_strings(text), vec![r#"say \"hi\""#, "models/crate.glb#Scene0"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Waffle Engine Editor Camera Bookmarks
+//! Numbered viewport bookmarks stored per scene in the editor metadata
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::core::resources::EngineState;
+use crate::rendering::camera::WaffleMainCamera;
+use super::EditorState;
+
+pub const BOOKMARK_SLOTS: usize = 9;
+
+const EDITOR_METADATA_DIR: &str = "editor_metadata";
+const UNTITLED_SCENE: &str = "untitled";
+
+const DIGIT_KEYS: [KeyCode; BOOKMARK_SLOTS] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+/// Editor-only data saved alongside a scene
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SceneEditorMetadata {
+    pub camera_bookmarks: [Option<CameraBookmark>; BOOKMARK_SLOTS],
+}
+
+/// Camera bookmarks of the scene currently open in the editor
+#[derive(Resource, Default)]
+pub struct CameraBookmarks {
+    pub scene: Option<String>,
+    pub metadata: SceneEditorMetadata,
+}
+
+impl CameraBookmarks {
+    pub fn get(&self, slot: usize) -> Option<CameraBookmark> {
+        self.metadata.camera_bookmarks.get(slot).copied().flatten()
+    }
+}
+
+pub fn load_scene_bookmarks(
+    engine_state: Res<EngineState>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    let scene = engine_state
+        .current_scene
+        .clone()
+        .unwrap_or_else(|| UNTITLED_SCENE.to_string());
+    if bookmarks.scene.as_deref() == Some(scene.as_str()) {
+        return;
+    }
+
+    bookmarks.metadata = load_metadata(&scene).unwrap_or_default();
+    bookmarks.scene = Some(scene);
+}
+
+/// Digits jump to a bookmark and Ctrl+digit stores one; only while editing,
+/// since in play the digits belong to the game
+pub fn handle_camera_bookmarks(
+    mut contexts: EguiContexts,
+    editor_state: Res<EditorState>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut camera_query: Query<&mut Transform, With<WaffleMainCamera>>,
+) {
+    if !editor_state.viewport_focused {
+        return;
+    }
+    if contexts.try_ctx_mut().is_none_or(|ctx| ctx.wants_keyboard_input()) {
+        return;
+    }
+
+    let Some(slot) = DIGIT_KEYS
+        .iter()
+        .position(|key| keyboard_input.just_pressed(*key))
+    else {
+        return;
+    };
+
+    let Ok(mut camera) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let ctrl = keyboard_input.pressed(KeyCode::ControlLeft)
+        || keyboard_input.pressed(KeyCode::ControlRight);
+    if ctrl {
+        bookmarks.metadata.camera_bookmarks[slot] = Some(CameraBookmark {
+            translation: camera.translation,
+            rotation: camera.rotation,
+        });
+        if let Some(scene) = bookmarks.scene.as_deref() {
+            match save_metadata(scene, &bookmarks.metadata) {
+                Ok(()) => info!("Stored camera bookmark {}", slot + 1),
+                Err(err) => warn!("Failed to save editor metadata for {}: {}", scene, err),
+            }
+        }
+    } else if let Some(bookmark) = bookmarks.get(slot) {
+        camera.translation = bookmark.translation;
+        camera.rotation = bookmark.rotation;
+    }
+}
+
+/// Under the project folder, which `open_project` passes as the asset base
+/// path, so it does not depend on where the editor was started
+fn metadata_path(scene: &str) -> PathBuf {
+    let file_name: String = scene
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    FileAssetReader::get_base_path()
+        .join(EDITOR_METADATA_DIR)
+        .join(format!("{file_name}.ron"))
+}
+
+fn load_metadata(scene: &str) -> Option<SceneEditorMetadata> {
+    let data = std::fs::read_to_string(metadata_path(scene)).ok()?;
+    ron::de::from_str(&data).ok()
+}
+
+fn save_metadata(scene: &str, metadata: &SceneEditorMetadata) -> anyhow::Result<()> {
+    let path = metadata_path(scene);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let data = ron::ser::to_string_pretty(metadata, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
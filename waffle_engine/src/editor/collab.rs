@@ -0,0 +1,863 @@
+//! Waffle Engine Editor Collaboration
+//! Opt-in shared editing sessions: one editor hosts, others join over TCP.
+//! Scene edits are broadcast as operations and merged last-writer-wins per
+//! entity field; each peer's selection shows up in the hierarchy.
+//! The host listens on the loopback address unless LAN hosting is turned on,
+//! and only takes peers whose Hello carries the session token.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::{DeleteEntityEvent, EditorState, SpawnAssetEvent, SpawnPrimitiveEvent, SpawnSource};
+use crate::core::components::EditorHidden;
+use crate::rendering::scene::WaffleSceneObject;
+
+pub const DEFAULT_COLLAB_PORT: u16 = 7878;
+
+const PRESENCE_INTERVAL: Duration = Duration::from_secs(3);
+/// Peers that have not been heard from for this long are dropped
+const PRESENCE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest message line accepted; a peer sending more is disconnected
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Peers that connect but send no Hello for this long are turned away
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Peers that stop reading are dropped once a write stalls this long
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Stable identity of a scene entity across all editors in a session
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CollabId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CollabTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl From<Transform> for CollabTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+impl From<CollabTransform> for Transform {
+    fn from(transform: CollabTransform) -> Self {
+        Transform {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CollabOp {
+    Spawn {
+        id: CollabId,
+        parent: Option<CollabId>,
+        source: SpawnSource,
+    },
+    Transform {
+        id: CollabId,
+        transform: CollabTransform,
+    },
+    Rename {
+        id: CollabId,
+        name: String,
+    },
+    SetVisible {
+        id: CollabId,
+        visible: bool,
+    },
+    Delete {
+        id: CollabId,
+    },
+}
+
+impl CollabOp {
+    fn target(&self) -> CollabId {
+        match self {
+            CollabOp::Spawn { id, .. }
+            | CollabOp::Transform { id, .. }
+            | CollabOp::Rename { id, .. }
+            | CollabOp::SetVisible { id, .. }
+            | CollabOp::Delete { id } => *id,
+        }
+    }
+
+    fn field(&self) -> CollabField {
+        match self {
+            CollabOp::Spawn { .. } | CollabOp::Delete { .. } => CollabField::Existence,
+            CollabOp::Transform { .. } => CollabField::Transform,
+            CollabOp::Rename { .. } => CollabField::Name,
+            CollabOp::SetVisible { .. } => CollabField::Visibility,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum CollabField {
+    Existence,
+    Transform,
+    Name,
+    Visibility,
+}
+
+/// Ordering for last-writer-wins: wall clock time, ties broken by peer id
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CollabStamp {
+    pub millis: u64,
+    pub peer: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CollabMessage {
+    /// Sent by a peer when it joins; the host answers with the scene if the
+    /// token matches its own
+    Hello {
+        peer: u64,
+        name: String,
+        color: [u8; 3],
+        token: String,
+    },
+    Presence {
+        peer: u64,
+        name: String,
+        color: [u8; 3],
+        selected: Option<CollabId>,
+    },
+    Goodbye { peer: u64 },
+    Op { stamp: CollabStamp, op: CollabOp },
+}
+
+#[derive(Clone, Debug)]
+pub struct PeerPresence {
+    pub name: String,
+    pub color: [u8; 3],
+    pub selected: Option<CollabId>,
+    last_seen: Instant,
+}
+
+/// Another editor's selection, shown next to the entity in the hierarchy
+#[derive(Clone, Debug)]
+pub struct PresenceTag {
+    pub name: String,
+    pub color: egui::Color32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollabRole {
+    Host,
+    Client,
+}
+
+/// Open TCP connections; the host relays every message it receives to the
+/// other clients
+struct CollabLink {
+    /// Handed to a writer thread, so the editor never waits on the network
+    outgoing: Sender<Outgoing>,
+    incoming: Receiver<CollabMessage>,
+    running: Arc<AtomicBool>,
+}
+
+enum Outgoing {
+    Line(String),
+    /// Shut every connection down once the lines before it are written
+    Close,
+}
+
+impl CollabLink {
+    fn host(port: u16, allow_lan: bool, token: String) -> std::io::Result<Self> {
+        let address = if allow_lan { "0.0.0.0" } else { "127.0.0.1" };
+        let listener = TcpListener::bind((address, port))?;
+        listener.set_nonblocking(true)?;
+        let streams = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let (sender, incoming) = unbounded();
+
+        let accept_streams = streams.clone();
+        let accept_running = running.clone();
+        std::thread::spawn(move || {
+            while accept_running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, address)) => {
+                        info!("Collaborator connected from {}", address);
+                        let configured = stream
+                            .set_nonblocking(false)
+                            .and_then(|_| stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)))
+                            .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)));
+                        if let Err(err) = configured {
+                            warn!("Dropping collaborator: {err}");
+                            continue;
+                        }
+                        let Ok(reader) = stream.try_clone() else {
+                            continue;
+                        };
+                        // Only added to the broadcast once its Hello checks out
+                        let admission = Admission {
+                            streams: accept_streams.clone(),
+                            writer: stream,
+                            token: token.clone(),
+                        };
+                        spawn_reader(reader, sender.clone(), Some(admission), accept_running.clone());
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(err) => {
+                        error!("Collaboration host stopped accepting: {err}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            outgoing: spawn_writer(streams),
+            incoming,
+            running,
+        })
+    }
+
+    fn join(address: &str) -> std::io::Result<Self> {
+        let stream = connect(address)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let reader = stream.try_clone()?;
+        let streams = Arc::new(Mutex::new(vec![stream]));
+        let running = Arc::new(AtomicBool::new(true));
+        let (sender, incoming) = unbounded();
+        spawn_reader(reader, sender, None, running.clone());
+        Ok(Self {
+            outgoing: spawn_writer(streams),
+            incoming,
+            running,
+        })
+    }
+
+    fn send(&self, message: &CollabMessage) {
+        let Ok(line) = serde_json::to_string(message) else {
+            return;
+        };
+        let _ = self.outgoing.send(Outgoing::Line(line));
+    }
+
+    fn close(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        let _ = self.outgoing.send(Outgoing::Close);
+    }
+}
+
+/// Connect to the first address `address` resolves to that answers in time
+fn connect(address: &str) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+    for candidate in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&candidate, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "address did not resolve")
+    }))
+}
+
+/// Write queued lines on a thread of their own; stalled peers hold it up for
+/// at most `WRITE_TIMEOUT`
+fn spawn_writer(streams: Arc<Mutex<Vec<TcpStream>>>) -> Sender<Outgoing> {
+    let (sender, receiver) = unbounded();
+    std::thread::spawn(move || {
+        for outgoing in receiver {
+            let Ok(mut streams) = streams.lock() else {
+                break;
+            };
+            match outgoing {
+                Outgoing::Line(line) => write_line(&mut streams, &line, None),
+                Outgoing::Close => {
+                    for stream in streams.iter() {
+                        let _ = stream.shutdown(std::net::Shutdown::Both);
+                    }
+                    break;
+                }
+            }
+        }
+    });
+    sender
+}
+
+/// Write a message line to every stream except `skip`, dropping dead connections
+fn write_line(streams: &mut Vec<TcpStream>, line: &str, skip: Option<std::net::SocketAddr>) {
+    streams.retain_mut(|stream| {
+        if skip.is_some() && stream.peer_addr().ok() == skip {
+            return true;
+        }
+        stream
+            .write_all(line.as_bytes())
+            .and_then(|_| stream.write_all(b"\n"))
+            .is_ok()
+    });
+}
+
+/// A connection to the host that joins the session once it sends a Hello
+/// with the session token
+struct Admission {
+    streams: Arc<Mutex<Vec<TcpStream>>>,
+    writer: TcpStream,
+    token: String,
+}
+
+/// Read one message line of at most `MAX_MESSAGE_BYTES`; `None` at the end
+/// of the stream
+fn read_message_line(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut line = Vec::new();
+    reader.by_ref().take(MAX_MESSAGE_BYTES + 1).read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') && line.len() as u64 > MAX_MESSAGE_BYTES {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "collaboration message too long"));
+    }
+    while line.last().is_some_and(|byte| *byte == b'\n' || *byte == b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+}
+
+fn spawn_reader(
+    stream: TcpStream,
+    sender: Sender<CollabMessage>,
+    mut admission: Option<Admission>,
+    running: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        let from = stream.peer_addr().ok();
+        let relay = admission.as_ref().map(|admission| admission.streams.clone());
+        let mut reader = BufReader::new(stream);
+        loop {
+            if !running.load(Ordering::Relaxed) {
+                break;
+            }
+            let line = match read_message_line(&mut reader) {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(error) => {
+                    warn!("Dropping collaborator: {error}");
+                    break;
+                }
+            };
+            let Ok(message) = serde_json::from_str::<CollabMessage>(&line) else {
+                warn!("Ignoring malformed collaboration message");
+                continue;
+            };
+            if let Some(pending) = admission.take() {
+                let admitted = matches!(&message, CollabMessage::Hello { token, .. } if *token == pending.token);
+                if !admitted {
+                    warn!("Rejected collaborator without the session token");
+                    let _ = pending.writer.shutdown(std::net::Shutdown::Both);
+                    break;
+                }
+                // Admitted peers may stay quiet for as long as they like
+                let _ = reader.get_ref().set_read_timeout(None);
+                if let Ok(mut streams) = pending.streams.lock() {
+                    streams.push(pending.writer);
+                }
+            }
+            if let Some(relay) = relay.as_ref() {
+                if let Ok(mut streams) = relay.lock() {
+                    write_line(&mut streams, &line, from);
+                }
+            }
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[derive(Resource)]
+pub struct CollabSession {
+    pub peer_id: u64,
+    pub user_name: String,
+    pub color: [u8; 3],
+    /// Address typed into the collaboration window, `host:port`
+    pub address: String,
+    /// Shared secret peers must send to join; the host's is handed out to
+    /// collaborators
+    pub token: String,
+    /// Host on every network interface rather than only this machine
+    pub allow_lan: bool,
+    pub role: Option<CollabRole>,
+    pub peers: HashMap<u64, PeerPresence>,
+    link: Option<CollabLink>,
+    stamps: HashMap<(CollabId, CollabField), CollabStamp>,
+    /// Last value seen for each entity, so only real local edits are broadcast
+    synced: HashMap<CollabId, SyncedState>,
+    /// Remote spawns waiting for the spawn systems to create their entity
+    pending_spawns: VecDeque<(CollabId, SpawnSource)>,
+    /// Edits to entities that are still waiting to be spawned
+    deferred: Vec<CollabMessage>,
+    /// Deletes that came from the network and must not be echoed back
+    remote_deletes: HashSet<Entity>,
+    last_selected: Option<CollabId>,
+    last_presence: Option<Instant>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SyncedState {
+    transform: CollabTransform,
+    name: String,
+    visible: bool,
+}
+
+impl Default for CollabSession {
+    fn default() -> Self {
+        let peer_id = Uuid::new_v4().as_u64_pair().0;
+        let hue = (peer_id % 360) as f32;
+        let color = Color::hsl(hue, 0.7, 0.6).to_srgba();
+        Self {
+            peer_id,
+            user_name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "Editor".to_string()),
+            color: [
+                (color.red * 255.0) as u8,
+                (color.green * 255.0) as u8,
+                (color.blue * 255.0) as u8,
+            ],
+            address: format!("127.0.0.1:{DEFAULT_COLLAB_PORT}"),
+            token: Uuid::new_v4().simple().to_string(),
+            allow_lan: false,
+            role: None,
+            peers: HashMap::new(),
+            link: None,
+            stamps: HashMap::new(),
+            synced: HashMap::new(),
+            pending_spawns: VecDeque::new(),
+            deferred: Vec::new(),
+            remote_deletes: HashSet::new(),
+            last_selected: None,
+            last_presence: None,
+        }
+    }
+}
+
+impl CollabSession {
+    pub fn is_active(&self) -> bool {
+        self.link.is_some()
+    }
+
+    pub fn host(&mut self, port: u16) -> std::io::Result<()> {
+        self.disconnect();
+        self.link = Some(CollabLink::host(port, self.allow_lan, self.token.clone())?);
+        self.role = Some(CollabRole::Host);
+        let scope = if self.allow_lan { "the network" } else { "this machine" };
+        info!("Hosting collaboration session on port {} for {}", port, scope);
+        Ok(())
+    }
+
+    pub fn join(&mut self) -> std::io::Result<()> {
+        self.disconnect();
+        self.link = Some(CollabLink::join(&self.address)?);
+        self.role = Some(CollabRole::Client);
+        self.send(CollabMessage::Hello {
+            peer: self.peer_id,
+            name: self.user_name.clone(),
+            color: self.color,
+            token: self.token.clone(),
+        });
+        info!("Joined collaboration session at {}", self.address);
+        Ok(())
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(link) = self.link.take() {
+            link.send(&CollabMessage::Goodbye { peer: self.peer_id });
+            link.close();
+            info!("Left collaboration session");
+        }
+        self.role = None;
+        self.peers.clear();
+        self.stamps.clear();
+        self.synced.clear();
+        self.pending_spawns.clear();
+        self.deferred.clear();
+        self.remote_deletes.clear();
+        self.last_presence = None;
+    }
+
+    /// Peers' selections keyed by the entity they have selected
+    pub fn presence_tags(&self, entities: &HashMap<CollabId, Entity>) -> HashMap<Entity, Vec<PresenceTag>> {
+        let mut tags: HashMap<Entity, Vec<PresenceTag>> = HashMap::new();
+        for peer in self.peers.values() {
+            let Some(entity) = peer.selected.and_then(|id| entities.get(&id)) else {
+                continue;
+            };
+            let [r, g, b] = peer.color;
+            tags.entry(*entity).or_default().push(PresenceTag {
+                name: peer.name.clone(),
+                color: egui::Color32::from_rgb(r, g, b),
+            });
+        }
+        tags
+    }
+
+    fn send(&self, message: CollabMessage) {
+        if let Some(link) = self.link.as_ref() {
+            link.send(&message);
+        }
+    }
+
+    fn next_stamp(&self) -> CollabStamp {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        CollabStamp {
+            millis,
+            peer: self.peer_id,
+        }
+    }
+
+    /// Stamp and broadcast a local edit
+    fn publish(&mut self, op: CollabOp) {
+        let stamp = self.next_stamp();
+        self.stamps.insert((op.target(), op.field()), stamp);
+        self.send(CollabMessage::Op { stamp, op });
+    }
+
+    /// Whether a remote edit is newer than what this editor has for the field
+    fn accept(&mut self, stamp: CollabStamp, op: &CollabOp) -> bool {
+        let key = (op.target(), op.field());
+        if self.stamps.get(&key).is_some_and(|current| *current >= stamp) {
+            return false;
+        }
+        self.stamps.insert(key, stamp);
+        true
+    }
+}
+
+/// Give new scene entities a session id and announce them to the other editors
+pub fn assign_collab_ids(
+    mut commands: Commands,
+    mut session: ResMut<CollabSession>,
+    new_entities: Query<(Entity, &SpawnSource, Option<&Parent>), (With<WaffleSceneObject>, Without<CollabId>)>,
+    ids: Query<&CollabId>,
+) {
+    if !session.is_active() {
+        return;
+    }
+    for (entity, source, parent) in &new_entities {
+        let pending = session
+            .pending_spawns
+            .iter()
+            .position(|(_, pending_source)| pending_source == source);
+        if let Some(index) = pending {
+            if let Some((id, _)) = session.pending_spawns.remove(index) {
+                commands.entity(entity).insert(id);
+            }
+            continue;
+        }
+
+        let id = CollabId(Uuid::new_v4());
+        commands.entity(entity).insert(id);
+        session.publish(CollabOp::Spawn {
+            id,
+            parent: parent.and_then(|parent| ids.get(parent.get()).ok().copied()),
+            source: source.clone(),
+        });
+    }
+}
+
+/// Broadcast local transform, name and visibility edits
+pub fn publish_local_edits(
+    mut session: ResMut<CollabSession>,
+    editor_state: Res<EditorState>,
+    edited: Query<
+        (&CollabId, &Transform, Option<&Name>, &Visibility),
+        (Without<EditorHidden>, Or<(Changed<Transform>, Changed<Name>, Changed<Visibility>, Added<CollabId>)>),
+    >,
+    ids: Query<&CollabId>,
+) {
+    if !session.is_active() {
+        return;
+    }
+    for (id, transform, name, visibility) in &edited {
+        let current = SyncedState {
+            transform: (*transform).into(),
+            name: name.map(|name| name.as_str().to_string()).unwrap_or_default(),
+            visible: *visibility != Visibility::Hidden,
+        };
+        let previous = session.synced.insert(*id, current.clone());
+        let Some(previous) = previous else {
+            continue;
+        };
+        if previous.transform != current.transform {
+            session.publish(CollabOp::Transform {
+                id: *id,
+                transform: current.transform,
+            });
+        }
+        if previous.name != current.name {
+            session.publish(CollabOp::Rename {
+                id: *id,
+                name: current.name.clone(),
+            });
+        }
+        if previous.visible != current.visible {
+            session.publish(CollabOp::SetVisible {
+                id: *id,
+                visible: current.visible,
+            });
+        }
+    }
+
+    let selected = editor_state
+        .selection
+        .primary()
+        .and_then(|entity| ids.get(entity).ok().copied());
+    let presence_due = session
+        .last_presence
+        .is_none_or(|last| last.elapsed() >= PRESENCE_INTERVAL);
+    if selected != session.last_selected || presence_due {
+        session.last_selected = selected;
+        session.last_presence = Some(Instant::now());
+        session.send(CollabMessage::Presence {
+            peer: session.peer_id,
+            name: session.user_name.clone(),
+            color: session.color,
+            selected,
+        });
+    }
+}
+
+pub fn publish_local_deletes(
+    mut session: ResMut<CollabSession>,
+    mut events: EventReader<DeleteEntityEvent>,
+    ids: Query<&CollabId>,
+) {
+    for event in events.read() {
+        if session.remote_deletes.remove(&event.entity) || !session.is_active() {
+            continue;
+        }
+        if let Ok(id) = ids.get(event.entity) {
+            session.publish(CollabOp::Delete { id: *id });
+        }
+    }
+}
+
+/// Apply operations and presence received from the other editors
+pub fn apply_remote_collab_messages(
+    mut commands: Commands,
+    mut session: ResMut<CollabSession>,
+    mut entities: Query<(Entity, &CollabId, &mut Transform, Option<&mut Name>, &mut Visibility)>,
+    snapshot_query: Query<(&CollabId, &SpawnSource, Option<&Parent>), Without<EditorHidden>>,
+    parent_ids: Query<&CollabId>,
+    mut spawn_primitive_events: EventWriter<SpawnPrimitiveEvent>,
+    mut spawn_asset_events: EventWriter<SpawnAssetEvent>,
+    mut delete_events: EventWriter<DeleteEntityEvent>,
+) {
+    let Some(link) = session.link.as_ref() else {
+        return;
+    };
+    let incoming: Vec<CollabMessage> = link.incoming.try_iter().collect();
+    let mut messages = std::mem::take(&mut session.deferred);
+    messages.extend(incoming);
+    if messages.is_empty() && session.peers.is_empty() {
+        return;
+    }
+
+    let id_map: HashMap<CollabId, Entity> = entities.iter().map(|(entity, id, ..)| (*id, entity)).collect();
+    for message in messages {
+        match message {
+            CollabMessage::Hello { peer, name, color, .. } => {
+                info!("{} joined the session", name);
+                session.peers.insert(
+                    peer,
+                    PeerPresence {
+                        name,
+                        color,
+                        selected: None,
+                        last_seen: Instant::now(),
+                    },
+                );
+                if session.role == Some(CollabRole::Host) {
+                    send_scene_snapshot(&mut session, &snapshot_query, &parent_ids, &entities);
+                }
+                session.last_presence = None;
+            }
+            CollabMessage::Presence {
+                peer,
+                name,
+                color,
+                selected,
+            } => {
+                session.peers.insert(
+                    peer,
+                    PeerPresence {
+                        name,
+                        color,
+                        selected,
+                        last_seen: Instant::now(),
+                    },
+                );
+            }
+            CollabMessage::Goodbye { peer } => {
+                if let Some(presence) = session.peers.remove(&peer) {
+                    info!("{} left the session", presence.name);
+                }
+            }
+            CollabMessage::Op { stamp, op } => {
+                let target = id_map.get(&op.target()).copied();
+                let waiting_for_spawn = session.pending_spawns.iter().any(|(pending, _)| *pending == op.target());
+                if target.is_none() && waiting_for_spawn && !matches!(op, CollabOp::Spawn { .. }) {
+                    session.deferred.push(CollabMessage::Op { stamp, op });
+                    continue;
+                }
+                if stamp.peer == session.peer_id || !session.accept(stamp, &op) {
+                    continue;
+                }
+                match op {
+                    CollabOp::Spawn { id, parent, source } => {
+                        if target.is_some() || waiting_for_spawn {
+                            continue;
+                        }
+                        let parent = parent.and_then(|parent| id_map.get(&parent).copied());
+                        match &source {
+                            SpawnSource::Primitive(kind) => {
+                                spawn_primitive_events.send(SpawnPrimitiveEvent { kind: *kind, parent });
+                            }
+                            SpawnSource::Asset(path) => {
+                                spawn_asset_events.send(SpawnAssetEvent {
+                                    path: path.clone(),
+                                    parent,
+                                });
+                            }
+                        }
+                        session.pending_spawns.push_back((id, source));
+                    }
+                    CollabOp::Delete { .. } => {
+                        if let Some(entity) = target {
+                            session.remote_deletes.insert(entity);
+                            session.synced.remove(&op.target());
+                            delete_events.send(DeleteEntityEvent { entity });
+                        }
+                    }
+                    CollabOp::Transform { id, transform } => {
+                        let Some(entity) = target else { continue };
+                        if let Ok((_, _, mut current, _, _)) = entities.get_mut(entity) {
+                            *current = transform.into();
+                            if let Some(synced) = session.synced.get_mut(&id) {
+                                synced.transform = transform;
+                            }
+                        }
+                    }
+                    CollabOp::Rename { id, name } => {
+                        let Some(entity) = target else { continue };
+                        if let Ok((_, _, _, current, _)) = entities.get_mut(entity) {
+                            match current {
+                                Some(mut current) => current.set(name.clone()),
+                                None => {
+                                    commands.entity(entity).insert(Name::new(name.clone()));
+                                }
+                            }
+                            if let Some(synced) = session.synced.get_mut(&id) {
+                                synced.name = name;
+                            }
+                        }
+                    }
+                    CollabOp::SetVisible { id, visible } => {
+                        let Some(entity) = target else { continue };
+                        if let Ok((_, _, _, _, mut current)) = entities.get_mut(entity) {
+                            *current = if visible { Visibility::Inherited } else { Visibility::Hidden };
+                            if let Some(synced) = session.synced.get_mut(&id) {
+                                synced.visible = visible;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    session
+        .peers
+        .retain(|_, peer| peer.last_seen.elapsed() < PRESENCE_TIMEOUT);
+}
+
+/// Bring a newly joined editor up to date with every shared entity
+fn send_scene_snapshot(
+    session: &mut CollabSession,
+    snapshot_query: &Query<(&CollabId, &SpawnSource, Option<&Parent>), Without<EditorHidden>>,
+    parent_ids: &Query<&CollabId>,
+    entities: &Query<(Entity, &CollabId, &mut Transform, Option<&mut Name>, &mut Visibility)>,
+) {
+    // Parents first, so children can be attached when they arrive.
+    let mut pending: Vec<(CollabId, SpawnSource, Option<CollabId>)> = snapshot_query
+        .iter()
+        .map(|(id, source, parent)| {
+            let parent = parent.and_then(|parent| parent_ids.get(parent.get()).ok().copied());
+            (*id, source.clone(), parent)
+        })
+        .collect();
+    let mut sent: HashSet<CollabId> = HashSet::new();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|(id, source, parent)| {
+            let ready = parent.is_none_or(|parent| sent.contains(&parent) || !snapshot_query.iter().any(|(other, ..)| *other == parent));
+            if ready {
+                session.publish(CollabOp::Spawn {
+                    id: *id,
+                    parent: *parent,
+                    source: source.clone(),
+                });
+                sent.insert(*id);
+            }
+            !ready
+        });
+        if pending.len() == before {
+            break;
+        }
+    }
+
+    for (_, id, transform, name, visibility) in entities.iter() {
+        if !sent.contains(id) {
+            continue;
+        }
+        session.publish(CollabOp::Transform {
+            id: *id,
+            transform: (*transform).into(),
+        });
+        if let Some(name) = name {
+            session.publish(CollabOp::Rename {
+                id: *id,
+                name: name.as_str().to_string(),
+            });
+        }
+        session.publish(CollabOp::SetVisible {
+            id: *id,
+            visible: *visibility != Visibility::Hidden,
+        });
+    }
+}
+
+pub fn disconnect_collab_on_exit(mut exit_events: EventReader<AppExit>, mut session: ResMut<CollabSession>) {
+    if exit_events.read().next().is_some() {
+        session.disconnect();
+    }
+}
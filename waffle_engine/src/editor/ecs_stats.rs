@@ -0,0 +1,257 @@
+//! Waffle Engine ECS Stats Panel
+//! Lists the archetypes of the world with how many entities each holds, and
+//! every component with its storage and the memory its values take. Taken on
+//! demand, since walking the world every frame costs more than it tells. Many
+//! empty archetypes, or counts that swing between refreshes, point at
+//! components being inserted and removed more than they need to be.
+
+use bevy::ecs::component::StorageType;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct ArchetypeStats {
+    pub id: u32,
+    pub entities: usize,
+    /// Short names of the components, sorted
+    pub components: Vec<String>,
+    /// Entities in the table backing the archetype, shared with other
+    /// archetypes that differ only in sparse set components
+    pub table_rows: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComponentStats {
+    pub name: String,
+    pub sparse: bool,
+    /// Bytes per value
+    pub size: usize,
+    pub entities: usize,
+    /// Archetypes the component is in
+    pub archetypes: usize,
+}
+
+impl ComponentStats {
+    pub fn bytes(&self) -> usize {
+        self.size * self.entities
+    }
+}
+
+/// The world's layout at one refresh
+#[derive(Clone, Debug, Default)]
+pub struct EcsSnapshot {
+    /// Seconds since startup
+    pub time: f32,
+    pub entities: u32,
+    pub tables: usize,
+    pub archetypes: Vec<ArchetypeStats>,
+    pub components: Vec<ComponentStats>,
+}
+
+impl EcsSnapshot {
+    pub fn capture(world: &World) -> Self {
+        let components = world.components();
+        let mut component_stats: HashMap<bevy::ecs::component::ComponentId, ComponentStats> = HashMap::new();
+        let mut archetypes = Vec::new();
+        for archetype in world.archetypes().iter() {
+            let mut names = Vec::new();
+            for id in archetype.components() {
+                let Some(info) = components.get_info(id) else {
+                    continue;
+                };
+                let name = short_type_name(info.name());
+                let stats = component_stats.entry(id).or_insert_with(|| ComponentStats {
+                    name: name.clone(),
+                    sparse: info.storage_type() == StorageType::SparseSet,
+                    size: info.layout().size(),
+                    entities: 0,
+                    archetypes: 0,
+                });
+                stats.entities += archetype.len();
+                stats.archetypes += 1;
+                names.push(name);
+            }
+            names.sort();
+            archetypes.push(ArchetypeStats {
+                id: archetype.id().index() as u32,
+                entities: archetype.len(),
+                components: names,
+                table_rows: world
+                    .storages()
+                    .tables
+                    .get(archetype.table_id())
+                    .map_or(0, |table| table.entity_count()),
+            });
+        }
+        let mut components: Vec<ComponentStats> = component_stats.into_values().collect();
+        components.sort_by(|a, b| b.bytes().cmp(&a.bytes()).then_with(|| a.name.cmp(&b.name)));
+        Self {
+            time: world.get_resource::<Time>().map_or(0.0, |time| time.elapsed_seconds()),
+            entities: world.entities().len(),
+            tables: world.storages().tables.len(),
+            archetypes,
+            components,
+        }
+    }
+
+    pub fn empty_archetypes(&self) -> usize {
+        self.archetypes.iter().filter(|archetype| archetype.entities == 0).count()
+    }
+}
+
+/// `bevy_asset::handle::Handle<bevy_render::mesh::mesh::Mesh>` as `Handle<Mesh>`
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if matches!(c, '<' | '>' | ',' | '(' | ')' | '[' | ']' | ';' | '&' | ' ') {
+            short.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            short.push(c);
+        } else {
+            segment.push(c);
+        }
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    short
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EcsStatsView {
+    #[default]
+    Archetypes,
+    Components,
+}
+
+#[derive(Resource, Default)]
+pub struct EcsStats {
+    pub snapshot: Option<EcsSnapshot>,
+    /// Entity count of each archetype at the refresh before, to show churn
+    previous: HashMap<u32, usize>,
+    refresh_requested: bool,
+    pub view: EcsStatsView,
+    /// Only archetypes or components whose names contain this
+    pub filter: String,
+    pub hide_empty: bool,
+}
+
+impl EcsStats {
+    pub fn request_refresh(&mut self) {
+        self.refresh_requested = true;
+    }
+}
+
+/// Take a snapshot of the world when the panel asks for one
+pub fn collect_ecs_stats(world: &mut World) {
+    if !world.get_resource::<EcsStats>().is_some_and(|stats| stats.refresh_requested) {
+        return;
+    }
+    let snapshot = EcsSnapshot::capture(world);
+    let mut stats = world.resource_mut::<EcsStats>();
+    stats.refresh_requested = false;
+    stats.previous = stats
+        .snapshot
+        .as_ref()
+        .map(|old| old.archetypes.iter().map(|archetype| (archetype.id, archetype.entities)).collect())
+        .unwrap_or_default();
+    stats.snapshot = Some(snapshot);
+}
+
+pub fn draw_ecs_stats_panel(ui: &mut egui::Ui, stats: &mut EcsStats) {
+    ui.horizontal(|ui| {
+        ui.heading("ECS Stats");
+        if ui.button("Refresh").clicked() {
+            stats.request_refresh();
+        }
+    });
+    let Some(snapshot) = stats.snapshot.as_ref() else {
+        ui.label("Refresh to take a snapshot of the world.");
+        return;
+    };
+    ui.label(format!(
+        "{} entities, {} archetypes ({} empty), {} tables, {} components at {:.1}s",
+        snapshot.entities,
+        snapshot.archetypes.len(),
+        snapshot.empty_archetypes(),
+        snapshot.tables,
+        snapshot.components.len(),
+        snapshot.time
+    ));
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut stats.view, EcsStatsView::Archetypes, "Archetypes");
+        ui.selectable_value(&mut stats.view, EcsStatsView::Components, "Components");
+        ui.add(egui::TextEdit::singleline(&mut stats.filter).hint_text("Filter components").desired_width(160.0));
+        if stats.view == EcsStatsView::Archetypes {
+            ui.checkbox(&mut stats.hide_empty, "Hide empty");
+        }
+    });
+    ui.separator();
+
+    let filter = stats.filter.trim().to_lowercase();
+    let matches = |name: &str| filter.is_empty() || name.to_lowercase().contains(&filter);
+    match stats.view {
+        EcsStatsView::Archetypes => {
+            let mut archetypes: Vec<&ArchetypeStats> = snapshot
+                .archetypes
+                .iter()
+                .filter(|archetype| !stats.hide_empty || archetype.entities > 0)
+                .filter(|archetype| archetype.components.iter().any(|name| matches(name)))
+                .collect();
+            archetypes.sort_by(|a, b| b.entities.cmp(&a.entities).then(a.id.cmp(&b.id)));
+            egui::ScrollArea::vertical().id_source("ecs_stats_archetypes").show(ui, |ui| {
+                for archetype in archetypes {
+                    let change = match stats.previous.get(&archetype.id) {
+                        Some(before) if *before != archetype.entities => {
+                            format!(" ({:+})", archetype.entities as i64 - *before as i64)
+                        }
+                        None if !stats.previous.is_empty() => " (new)".to_string(),
+                        _ => String::new(),
+                    };
+                    let header = format!(
+                        "#{}: {} entities{}, {} components",
+                        archetype.id,
+                        archetype.entities,
+                        change,
+                        archetype.components.len()
+                    );
+                    egui::CollapsingHeader::new(header).id_source(("ecs_archetype", archetype.id)).show(ui, |ui| {
+                        ui.weak(format!("Table holds {} entities", archetype.table_rows));
+                        for name in &archetype.components {
+                            ui.label(name);
+                        }
+                    });
+                }
+            });
+        }
+        EcsStatsView::Components => {
+            egui::ScrollArea::vertical().id_source("ecs_stats_components").show(ui, |ui| {
+                egui::Grid::new("ecs_stats_component_grid").num_columns(5).striped(true).show(ui, |ui| {
+                    ui.strong("Component");
+                    ui.strong("Storage");
+                    ui.strong("Entities");
+                    ui.strong("Archetypes");
+                    ui.strong("Memory");
+                    ui.end_row();
+                    for component in snapshot.components.iter().filter(|component| matches(&component.name)) {
+                        ui.label(&component.name);
+                        ui.label(if component.sparse { "Sparse set" } else { "Table" });
+                        ui.label(component.entities.to_string());
+                        ui.label(component.archetypes.to_string());
+                        ui.label(format_bytes(component.bytes()))
+                            .on_hover_text(format!("{} bytes each", component.size));
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
@@ -0,0 +1,273 @@
+//! Waffle Engine Editor Extensions
+//! Lets project plugins add panels, menu entries, settings pages and viewport tools
+
+use bevy::ecs::world::CommandQueue;
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use super::tools::{CustomEditorTool, ViewportToolClickEvent};
+use crate::core::guard::catch_panic;
+
+/// What extension callbacks can see and do. World changes are queued and
+/// applied after the editor UI has been drawn.
+pub struct EditorExtensionContext<'a> {
+    pub selected_entity: Option<Entity>,
+    commands: &'a mut CommandQueue,
+}
+
+impl<'a> EditorExtensionContext<'a> {
+    pub fn new(selected_entity: Option<Entity>, commands: &'a mut CommandQueue) -> Self {
+        Self {
+            selected_entity,
+            commands,
+        }
+    }
+
+    /// Queue a change to the world
+    pub fn run(&mut self, command: impl FnOnce(&mut World) + Send + 'static) {
+        self.commands.push(command);
+    }
+
+    /// Queue an event for the project's systems
+    pub fn send_event<E: Event>(&mut self, event: E) {
+        self.commands.push(move |world: &mut World| {
+            world.send_event(event);
+        });
+    }
+}
+
+pub type PanelDrawFn = Box<dyn FnMut(&mut egui::Ui, &mut EditorExtensionContext) + Send + Sync>;
+pub type MenuActionFn = Box<dyn FnMut(&mut EditorExtensionContext) + Send + Sync>;
+pub type SettingsDrawFn = Box<dyn FnMut(&mut egui::Ui) + Send + Sync>;
+
+pub struct EditorPanel {
+    /// Stored in the dock layout, so keep it stable between versions
+    pub id: String,
+    pub title: String,
+    pub draw: PanelDrawFn,
+    /// Panic message once the panel has been disabled
+    pub error: Option<String>,
+}
+
+pub struct EditorMenuItem {
+    /// Top-level menu, e.g. "Tools"; unknown names get their own menu
+    pub menu: String,
+    pub label: String,
+    pub action: MenuActionFn,
+    pub error: Option<String>,
+}
+
+pub struct EditorSettingsPage {
+    pub title: String,
+    pub draw: SettingsDrawFn,
+    pub error: Option<String>,
+}
+
+/// Everything project plugins have added to the editor
+#[derive(Resource, Default)]
+pub struct EditorExtensions {
+    pub panels: Vec<EditorPanel>,
+    pub menu_items: Vec<EditorMenuItem>,
+    pub settings_pages: Vec<EditorSettingsPage>,
+    pub tools: Vec<CustomEditorTool>,
+    /// Settings page shown in the Plugin Settings window
+    pub selected_settings_page: usize,
+}
+
+impl EditorExtensions {
+    pub fn panel_title(&self, id: &str) -> Option<&str> {
+        self.panels
+            .iter()
+            .find(|panel| panel.id == id)
+            .map(|panel| panel.title.as_str())
+    }
+
+    pub fn draw_panel(&mut self, id: &str, ui: &mut egui::Ui, ctx: &mut EditorExtensionContext) {
+        match self.panels.iter_mut().find(|panel| panel.id == id) {
+            Some(panel) if panel.error.is_some() => draw_disabled_extension(ui, &mut panel.error),
+            Some(panel) => run_guarded("Editor panel", &panel.title, &mut panel.error, || (panel.draw)(ui, ctx)),
+            None => {
+                ui.label(format!("Panel \"{}\" is not available; is its plugin loaded?", id));
+            }
+        }
+    }
+
+    pub fn tool_clicked(&mut self, id: &str, click: &ViewportToolClickEvent, ctx: &mut EditorExtensionContext) {
+        if let Some(tool) = self.tools.iter_mut().find(|tool| tool.id == id && tool.error.is_none()) {
+            run_guarded("Editor tool", &tool.label, &mut tool.error, || (tool.on_click)(click, ctx));
+        }
+    }
+
+    /// Menus added by extensions that are not one of the built-in ones
+    pub fn extra_menus(&self) -> Vec<String> {
+        let mut menus: Vec<String> = Vec::new();
+        for item in &self.menu_items {
+            if !BUILT_IN_MENUS.contains(&item.menu.as_str()) && !menus.contains(&item.menu) {
+                menus.push(item.menu.clone());
+            }
+        }
+        menus
+    }
+
+    /// Draw the extension entries for `menu`, with a separator before them
+    pub fn draw_menu_items(&mut self, menu: &str, ui: &mut egui::Ui, ctx: &mut EditorExtensionContext) {
+        let mut items = self.menu_items.iter_mut().filter(|item| item.menu == menu).peekable();
+        if items.peek().is_none() {
+            return;
+        }
+        if BUILT_IN_MENUS.contains(&menu) {
+            ui.separator();
+        }
+        for item in items {
+            let response = ui.add_enabled(item.error.is_none(), egui::Button::new(&item.label));
+            if let Some(error) = &item.error {
+                response.on_disabled_hover_text(format!("Disabled after a panic: {error}"));
+            } else if response.clicked() {
+                run_guarded("Menu item", &item.label, &mut item.error, || (item.action)(ctx));
+                ui.close_menu();
+            }
+        }
+    }
+}
+
+/// Run an extension callback, disabling it if it panics
+pub fn run_guarded(kind: &str, name: &str, error: &mut Option<String>, callback: impl FnOnce()) {
+    if let Err(message) = catch_panic(callback) {
+        error!("{} \"{}\" panicked and was disabled: {}", kind, name, message);
+        *error = Some(message);
+    }
+}
+
+/// Shown in place of a disabled extension's UI
+pub fn draw_disabled_extension(ui: &mut egui::Ui, error: &mut Option<String>) {
+    let Some(message) = error.as_deref() else {
+        return;
+    };
+    ui.colored_label(egui::Color32::from_rgb(230, 90, 80), "Disabled after a panic:");
+    ui.label(message);
+    if ui.button("Enable Again").clicked() {
+        *error = None;
+    }
+}
+
+const BUILT_IN_MENUS: [&str; 5] = ["File", "Edit", "View", "Tools", "Help"];
+
+/// Editor registration on `App` for project plugins. Callbacks that panic
+/// are disabled instead of taking the editor down.
+///
+/// ```ignore
+/// app.add_editor_panel("spawn_waves", "Spawn Waves", |ui, ctx| {
+///     ui.label("Waves");
+///     if ui.button("Clear Enemies").clicked() {
+///         ctx.run(|world| clear_enemies(world));
+///     }
+/// });
+/// ```
+pub trait EditorAppExt {
+    fn add_editor_panel(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        draw: impl FnMut(&mut egui::Ui, &mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn add_editor_menu_item(
+        &mut self,
+        menu: impl Into<String>,
+        label: impl Into<String>,
+        action: impl FnMut(&mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn add_editor_settings_page(
+        &mut self,
+        title: impl Into<String>,
+        draw: impl FnMut(&mut egui::Ui) + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    /// Add a tool to the viewport tool palette; `on_click` runs for each
+    /// viewport click while it is active
+    fn add_editor_tool(
+        &mut self,
+        id: impl Into<String>,
+        label: impl Into<String>,
+        on_click: impl FnMut(&ViewportToolClickEvent, &mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl EditorAppExt for App {
+    fn add_editor_panel(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        draw: impl FnMut(&mut egui::Ui, &mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let panel = EditorPanel {
+            id: id.into(),
+            title: title.into(),
+            draw: Box::new(draw),
+            error: None,
+        };
+        let mut extensions = self.world_mut().get_resource_or_insert_with(EditorExtensions::default);
+        if extensions.panels.iter().any(|existing| existing.id == panel.id) {
+            warn!("Editor panel \"{}\" is already registered", panel.id);
+        } else {
+            extensions.panels.push(panel);
+        }
+        self
+    }
+
+    fn add_editor_menu_item(
+        &mut self,
+        menu: impl Into<String>,
+        label: impl Into<String>,
+        action: impl FnMut(&mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(EditorExtensions::default)
+            .menu_items
+            .push(EditorMenuItem {
+                menu: menu.into(),
+                label: label.into(),
+                action: Box::new(action),
+                error: None,
+            });
+        self
+    }
+
+    fn add_editor_settings_page(
+        &mut self,
+        title: impl Into<String>,
+        draw: impl FnMut(&mut egui::Ui) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(EditorExtensions::default)
+            .settings_pages
+            .push(EditorSettingsPage {
+                title: title.into(),
+                draw: Box::new(draw),
+                error: None,
+            });
+        self
+    }
+
+    fn add_editor_tool(
+        &mut self,
+        id: impl Into<String>,
+        label: impl Into<String>,
+        on_click: impl FnMut(&ViewportToolClickEvent, &mut EditorExtensionContext) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let tool = CustomEditorTool {
+            id: id.into(),
+            label: label.into(),
+            on_click: Box::new(on_click),
+            error: None,
+        };
+        let mut extensions = self.world_mut().get_resource_or_insert_with(EditorExtensions::default);
+        if extensions.tools.iter().any(|existing| existing.id == tool.id) {
+            warn!("Editor tool \"{}\" is already registered", tool.id);
+        } else {
+            extensions.tools.push(tool);
+        }
+        self
+    }
+}
@@ -0,0 +1,172 @@
+//! Waffle Engine External Editing
+//! Opens assets in outside applications and reimports them when they are saved
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{AssetBrowserCache, AssetKind};
+
+const EXTERNAL_TOOLS_PATH: &str = "editor_tools.ron";
+
+/// Command used to open each kind of asset; empty means the OS default
+/// application. `{file}` is replaced with the asset path, otherwise the path
+/// is appended, e.g. `code --wait` or `gimp`.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalToolSettings {
+    pub image: String,
+    pub model: String,
+    pub audio: String,
+    pub script: String,
+    pub material: String,
+    pub other: String,
+}
+
+impl ExternalToolSettings {
+    pub fn load() -> Option<Self> {
+        let data = std::fs::read_to_string(EXTERNAL_TOOLS_PATH).ok()?;
+        ron::de::from_str(&data).ok()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(EXTERNAL_TOOLS_PATH, data)?;
+        Ok(())
+    }
+
+    pub fn tool(&self, kind: AssetKind) -> &str {
+        match kind {
+            AssetKind::Image => &self.image,
+            AssetKind::Model => &self.model,
+            AssetKind::Audio => &self.audio,
+            AssetKind::Script => &self.script,
+            AssetKind::Material => &self.material,
+            AssetKind::Other => &self.other,
+        }
+    }
+
+    pub fn tool_mut(&mut self, kind: AssetKind) -> &mut String {
+        match kind {
+            AssetKind::Image => &mut self.image,
+            AssetKind::Model => &mut self.model,
+            AssetKind::Audio => &mut self.audio,
+            AssetKind::Script => &mut self.script,
+            AssetKind::Material => &mut self.material,
+            AssetKind::Other => &mut self.other,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct OpenExternalEvent {
+    /// Path relative to the assets folder
+    pub path: String,
+}
+
+/// Assets opened externally and their last seen modification time
+#[derive(Resource, Default)]
+pub struct ExternallyEditedAssets {
+    files: HashMap<String, Option<SystemTime>>,
+    /// Launched tools, waited on once they exit so they don't linger as
+    /// zombie processes
+    children: Vec<Child>,
+    last_poll: Option<Instant>,
+}
+
+pub fn load_external_tools(mut tools: ResMut<ExternalToolSettings>) {
+    if let Some(loaded) = ExternalToolSettings::load() {
+        *tools = loaded;
+    }
+}
+
+pub fn apply_open_external_events(
+    mut events: EventReader<OpenExternalEvent>,
+    asset_cache: Res<AssetBrowserCache>,
+    tools: Res<ExternalToolSettings>,
+    mut edited: ResMut<ExternallyEditedAssets>,
+) {
+    for event in events.read() {
+        let full_path = asset_cache.root.join(&event.path);
+        let kind = super::classify_asset(full_path.extension().and_then(|ext| ext.to_str()));
+        match launch_tool(tools.tool(kind), &full_path) {
+            Ok(child) => {
+                info!("Opened {} externally", event.path);
+                edited.files.insert(event.path.clone(), modified_time(&full_path));
+                edited.children.push(child);
+            }
+            Err(err) => error!("Failed to open {}: {err}", event.path),
+        }
+    }
+}
+
+/// Reload assets whose files changed since they were opened externally
+pub fn reimport_externally_edited_assets(
+    asset_server: Res<AssetServer>,
+    mut edited: ResMut<ExternallyEditedAssets>,
+    mut asset_cache: ResMut<AssetBrowserCache>,
+) {
+    if edited.files.is_empty() && edited.children.is_empty() {
+        return;
+    }
+    let needs_poll = edited
+        .last_poll
+        .map(|last| last.elapsed() >= Duration::from_secs(1))
+        .unwrap_or(true);
+    if !needs_poll {
+        return;
+    }
+    edited.last_poll = Some(Instant::now());
+    edited.children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+    let root = asset_cache.root.clone();
+    let mut changed = false;
+    for (path, last_modified) in edited.files.iter_mut() {
+        let modified = modified_time(&root.join(path));
+        if modified.is_some() && modified != *last_modified {
+            *last_modified = modified;
+            info!("Reimporting {}", path);
+            asset_server.reload(path.clone());
+            changed = true;
+        }
+    }
+    if changed {
+        asset_cache.last_scan = None;
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn launch_tool(tool: &str, path: &Path) -> std::io::Result<Child> {
+    let mut parts = tool.split_whitespace();
+    let Some(program) = parts.next() else {
+        return open_with_system(path);
+    };
+
+    let file = path.to_string_lossy();
+    let mut args: Vec<String> = parts.map(|arg| arg.replace("{file}", &file)).collect();
+    if !tool.contains("{file}") {
+        args.push(file.into_owned());
+    }
+    Command::new(program).args(args).spawn()
+}
+
+fn open_with_system(path: &Path) -> std::io::Result<Child> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(path).spawn()
+}
@@ -0,0 +1,178 @@
+//! Waffle Engine Foliage Painting
+//! The Foliage tool scatters copies of the selected foliage layer's mesh over
+//! whatever surface is under the cursor while the left mouse button is held,
+//! each turned and sized a little differently. Copies are kept apart by the
+//! brush density, so painting over the same spot fills it in rather than
+//! piling up. Erase, or painting with Shift held, removes them instead.
+
+use bevy::prelude::*;
+
+use super::tools::{viewport_pointer_ray, ActiveTool, EditorTool};
+use super::EditorState;
+use crate::core::random::WaffleRng;
+use crate::core::spatial::SpatialQuery;
+use crate::rendering::camera::{WaffleMainCamera, WaffleOrthoCamera};
+use crate::rendering::foliage::{FoliageInstance, FoliageLayer};
+
+/// Copies tried per second for each copy the brush area holds at full density
+const FOLIAGE_FILL_RATE: f32 = 4.0;
+/// At most this many copies are tried in one frame
+const MAX_FOLIAGE_TRIES_PER_FRAME: usize = 64;
+
+/// Options of the Foliage tool
+#[derive(Clone, Debug)]
+pub struct FoliageBrush {
+    pub erase: bool,
+    /// In world units
+    pub radius: f32,
+    /// Copies per square unit once an area is filled
+    pub density: f32,
+    pub scale_min: f32,
+    pub scale_max: f32,
+    /// Turn each copy to a random heading around its up axis
+    pub random_yaw: bool,
+    /// Lean copies with the slope they stand on instead of straight up
+    pub align_to_surface: bool,
+}
+
+impl Default for FoliageBrush {
+    fn default() -> Self {
+        Self {
+            erase: false,
+            radius: 3.0,
+            density: 2.0,
+            scale_min: 0.8,
+            scale_max: 1.2,
+            random_yaw: true,
+            align_to_surface: false,
+        }
+    }
+}
+
+/// Where the Foliage brush is, for drawing it
+#[derive(Resource)]
+pub struct FoliagePainter {
+    /// Position and normal of the surface under the cursor, and the radius
+    pub brush: Option<(Vec3, Vec3, f32)>,
+    rng: WaffleRng,
+}
+
+impl Default for FoliagePainter {
+    fn default() -> Self {
+        Self {
+            brush: None,
+            rng: WaffleRng::new(0x5eed_f011a6e),
+        }
+    }
+}
+
+/// Paint the selected foliage layer with the Foliage tool
+#[allow(clippy::too_many_arguments)]
+pub fn paint_foliage(
+    editor_state: Res<EditorState>,
+    active_tool: Res<ActiveTool>,
+    mut painter: ResMut<FoliagePainter>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+    ortho_camera_query: Query<(&Camera, &GlobalTransform, &WaffleOrthoCamera)>,
+    spatial: SpatialQuery,
+    mut layers: Query<(&mut FoliageLayer, &GlobalTransform)>,
+) {
+    painter.brush = None;
+    if active_tool.tool != EditorTool::Foliage {
+        return;
+    }
+    let Some(selected) = editor_state.selection.primary() else {
+        return;
+    };
+    let Ok((mut layer, transform)) = layers.get_mut(selected) else {
+        return;
+    };
+    let Some(ray) = viewport_pointer_ray(&editor_state, &camera_query, &ortho_camera_query) else {
+        return;
+    };
+    let brush = &active_tool.foliage_brush;
+    let Some(hit) = spatial.cast_ray(ray.origin, *ray.direction, 10_000.0) else {
+        return;
+    };
+    painter.brush = Some((hit.point, hit.normal, brush.radius));
+
+    let painting = mouse_input.pressed(MouseButton::Left)
+        && !mouse_input.pressed(MouseButton::Right)
+        && !mouse_input.pressed(MouseButton::Middle);
+    if !painting {
+        return;
+    }
+    let shift = keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+    let radius = brush.radius.max(0.01);
+
+    if brush.erase || shift {
+        let inside = |instance: &FoliageInstance| {
+            transform.transform_point(instance.position).distance_squared(hit.point) <= radius * radius
+        };
+        // Only touch the layer when something goes, so its cells aren't rebuilt
+        if layer.instances.iter().any(inside) {
+            layer.instances.retain(|instance| !inside(instance));
+        }
+        return;
+    }
+
+    let spacing = 1.0 / brush.density.max(0.001).sqrt();
+    let reach = radius + spacing;
+    let mut nearby: Vec<Vec3> = layer
+        .instances
+        .iter()
+        .map(|instance| transform.transform_point(instance.position))
+        .filter(|position| position.distance_squared(hit.point) <= reach * reach)
+        .collect();
+
+    let area = std::f32::consts::PI * radius * radius;
+    let tries = (area * brush.density * FOLIAGE_FILL_RATE * time.delta_seconds()).ceil() as usize;
+    let local_from_world = transform.affine().inverse();
+    let layer_scale = transform.compute_transform().scale.max_element().max(0.001);
+    let mut added = Vec::new();
+    for _ in 0..tries.min(MAX_FOLIAGE_TRIES_PER_FRAME) {
+        // A point in the brush disc, dropped onto the surface below it
+        let angle = painter.rng.range_f32(0.0, std::f32::consts::TAU);
+        let distance = radius * painter.rng.next_f32().sqrt();
+        let above = hit.point + Vec3::new(angle.cos() * distance, radius, angle.sin() * distance);
+        let Some(ground) = spatial.cast_ray(above, Vec3::NEG_Y, radius * 2.0) else {
+            continue;
+        };
+        if nearby.iter().any(|other| other.distance_squared(ground.point) < spacing * spacing) {
+            continue;
+        }
+        nearby.push(ground.point);
+
+        let up = if brush.align_to_surface {
+            local_from_world.transform_vector3(ground.normal).try_normalize().unwrap_or(Vec3::Y)
+        } else {
+            Vec3::Y
+        };
+        let yaw = if brush.random_yaw {
+            painter.rng.range_f32(0.0, std::f32::consts::TAU)
+        } else {
+            0.0
+        };
+        let scale_max = brush.scale_max.max(brush.scale_min);
+        added.push(FoliageInstance {
+            position: local_from_world.transform_point3(ground.point),
+            rotation: Quat::from_rotation_arc(Vec3::Y, up) * Quat::from_rotation_y(yaw),
+            scale: painter.rng.range_f32(brush.scale_min, scale_max) / layer_scale,
+        });
+    }
+    if !added.is_empty() {
+        layer.instances.extend(added);
+    }
+}
+
+pub fn draw_foliage_brush(painter: Res<FoliagePainter>, mut gizmos: Gizmos) {
+    let Some((center, normal, radius)) = painter.brush else {
+        return;
+    };
+    let normal = Dir3::new(normal).unwrap_or(Dir3::Y);
+    let color = Color::srgb(0.45, 0.9, 0.35);
+    gizmos.circle(center + *normal * 0.05, normal, radius, color);
+}
@@ -0,0 +1,288 @@
+//! Waffle Engine Input Debug Panel
+//! Shows what the engine receives from input devices, to check bindings and
+//! dead zones without starting the game: every connected gamepad with its
+//! sticks, triggers and buttons as they move, and a log of recent key, mouse,
+//! touch and gamepad events. Stick values are shown after Bevy's dead zones are
+//! applied, with the dead zone drawn around them.
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadSettings};
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
+use bevy::input::touch::{TouchInput, TouchPhase};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use std::collections::{HashSet, VecDeque};
+
+use crate::core::input::{is_pen_touch, touch_pressure};
+
+/// How many events the log keeps
+const LOG_LENGTH: usize = 64;
+
+const STICK_SIZE: f32 = 72.0;
+
+const GAMEPAD_BUTTONS: [(GamepadButtonType, &str); 17] = [
+    (GamepadButtonType::South, "A"),
+    (GamepadButtonType::East, "B"),
+    (GamepadButtonType::West, "X"),
+    (GamepadButtonType::North, "Y"),
+    (GamepadButtonType::LeftTrigger, "LB"),
+    (GamepadButtonType::RightTrigger, "RB"),
+    (GamepadButtonType::LeftThumb, "LS"),
+    (GamepadButtonType::RightThumb, "RS"),
+    (GamepadButtonType::Select, "Select"),
+    (GamepadButtonType::Start, "Start"),
+    (GamepadButtonType::Mode, "Mode"),
+    (GamepadButtonType::DPadUp, "Up"),
+    (GamepadButtonType::DPadDown, "Down"),
+    (GamepadButtonType::DPadLeft, "Left"),
+    (GamepadButtonType::DPadRight, "Right"),
+    (GamepadButtonType::C, "C"),
+    (GamepadButtonType::Z, "Z"),
+];
+
+#[derive(Clone, Debug)]
+pub struct InputLogEntry {
+    /// Seconds since startup
+    pub time: f32,
+    pub text: String,
+}
+
+/// Recent input events, newest last
+#[derive(Resource, Default)]
+pub struct InputDebugLog {
+    pub entries: VecDeque<InputLogEntry>,
+    /// Stop recording, to read the log
+    pub paused: bool,
+    pub log_mouse_motion: bool,
+}
+
+impl InputDebugLog {
+    fn push(&mut self, time: f32, text: String) {
+        if self.paused {
+            return;
+        }
+        if self.entries.len() == LOG_LENGTH {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(InputLogEntry { time, text });
+    }
+}
+
+/// Gamepad state the panel reads
+#[derive(SystemParam)]
+pub struct GamepadInputs<'w> {
+    pub gamepads: Res<'w, Gamepads>,
+    pub axes: Res<'w, Axis<GamepadAxis>>,
+    /// Analog values of buttons, e.g. how far a trigger is pulled
+    pub button_axes: Res<'w, Axis<GamepadButton>>,
+    pub buttons: Res<'w, ButtonInput<GamepadButton>>,
+    pub settings: Res<'w, GamepadSettings>,
+}
+
+pub fn record_input_events(
+    time: Res<Time>,
+    mut log: ResMut<InputDebugLog>,
+    mut keys: EventReader<KeyboardInput>,
+    mut mouse_buttons: EventReader<MouseButtonInput>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut touches: EventReader<TouchInput>,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut held_keys: Local<HashSet<KeyCode>>,
+) {
+    let now = time.elapsed_seconds();
+    let state_label = |state: ButtonState| match state {
+        ButtonState::Pressed => "pressed",
+        ButtonState::Released => "released",
+    };
+
+    for event in keys.read() {
+        // Held keys repeat; only the first press is news
+        let changed = match event.state {
+            ButtonState::Pressed => held_keys.insert(event.key_code),
+            ButtonState::Released => held_keys.remove(&event.key_code),
+        };
+        if !changed {
+            continue;
+        }
+        log.push(now, format!("Key {:?} {}", event.key_code, state_label(event.state)));
+    }
+    for event in mouse_buttons.read() {
+        log.push(now, format!("Mouse {:?} {}", event.button, state_label(event.state)));
+    }
+    for event in mouse_wheel.read() {
+        let unit = match event.unit {
+            MouseScrollUnit::Line => "lines",
+            MouseScrollUnit::Pixel => "px",
+        };
+        log.push(now, format!("Wheel {:.1}, {:.1} {}", event.x, event.y, unit));
+    }
+    // Motion floods the log, so it is opt-in and only the latest is kept
+    if let Some(event) = cursor_moved.read().last() {
+        if log.log_mouse_motion {
+            log.push(now, format!("Cursor {:.0}, {:.0}", event.position.x, event.position.y));
+        }
+    }
+    for event in touches.read() {
+        let phase = match event.phase {
+            TouchPhase::Started => "down",
+            TouchPhase::Ended => "up",
+            TouchPhase::Canceled => "canceled",
+            // Moves flood the log like mouse motion does
+            TouchPhase::Moved if log.log_mouse_motion => "moved",
+            TouchPhase::Moved => continue,
+        };
+        let device = if is_pen_touch(event.force) { "Pen" } else { "Touch" };
+        let mut text = format!(
+            "{device} {} {phase} at {:.0}, {:.0}",
+            event.id, event.position.x, event.position.y
+        );
+        if let Some(force) = event.force {
+            text.push_str(&format!(" pressure {:.2}", touch_pressure(force)));
+        }
+        log.push(now, text);
+    }
+    for event in connections.read() {
+        let text = match &event.connection {
+            GamepadConnection::Connected(info) => format!("Gamepad {} connected: {}", event.gamepad.id, info.name),
+            GamepadConnection::Disconnected => format!("Gamepad {} disconnected", event.gamepad.id),
+        };
+        log.push(now, text);
+    }
+    for button in gamepad_buttons.get_just_pressed() {
+        log.push(now, format!("Gamepad {} {} pressed", button.gamepad.id, button_label(button.button_type)));
+    }
+    for button in gamepad_buttons.get_just_released() {
+        log.push(now, format!("Gamepad {} {} released", button.gamepad.id, button_label(button.button_type)));
+    }
+}
+
+fn button_label(button: GamepadButtonType) -> String {
+    GAMEPAD_BUTTONS
+        .iter()
+        .find(|(candidate, _)| *candidate == button)
+        .map(|(_, label)| label.to_string())
+        .unwrap_or_else(|| format!("{button:?}"))
+}
+
+pub fn draw_input_debug_panel(ui: &mut egui::Ui, log: &mut InputDebugLog, inputs: &GamepadInputs) {
+    ui.heading("Input Debug");
+    ui.separator();
+
+    ui.label(egui::RichText::new("Gamepads").strong());
+    if inputs.gamepads.iter().next().is_none() {
+        ui.label("No gamepads connected");
+    }
+    for gamepad in inputs.gamepads.iter() {
+        let name = inputs.gamepads.name(gamepad).unwrap_or("Unknown");
+        egui::CollapsingHeader::new(format!("{}: {}", gamepad.id, name))
+            .id_source(("input_debug_gamepad", gamepad.id))
+            .default_open(true)
+            .show(ui, |ui| draw_gamepad(ui, gamepad, inputs));
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("Events").strong());
+        ui.checkbox(&mut log.paused, "Pause");
+        ui.checkbox(&mut log.log_mouse_motion, "Motion")
+            .on_hover_text("Log mouse and touch movement");
+        if ui.button("Clear").clicked() {
+            log.entries.clear();
+        }
+    });
+    egui::ScrollArea::vertical()
+        .id_source("input_debug_log")
+        .stick_to_bottom(true)
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            if log.entries.is_empty() {
+                ui.label("Press a key, click or move a stick");
+            }
+            for entry in &log.entries {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("{:>8.2}", entry.time)).monospace().weak());
+                    ui.label(&entry.text);
+                });
+            }
+        });
+}
+
+fn draw_gamepad(ui: &mut egui::Ui, gamepad: Gamepad, inputs: &GamepadInputs) {
+    let axis = |axis_type| inputs.axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+    let trigger = |button_type| {
+        inputs
+            .button_axes
+            .get(GamepadButton::new(gamepad, button_type))
+            .unwrap_or(0.0)
+    };
+
+    ui.horizontal(|ui| {
+        for (label, x_axis, y_axis) in [
+            ("Left Stick", GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            ("Right Stick", GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        ] {
+            let dead_zone = inputs.settings.get_axis_settings(GamepadAxis::new(gamepad, x_axis)).deadzone_upperbound();
+            let value = egui::vec2(axis(x_axis), axis(y_axis));
+            ui.vertical(|ui| {
+                ui.label(label);
+                draw_stick(ui, value, dead_zone);
+                ui.label(egui::RichText::new(format!("{:+.2}, {:+.2}", value.x, value.y)).monospace().small());
+            });
+        }
+        ui.vertical(|ui| {
+            for (label, button_type) in [
+                ("LT", GamepadButtonType::LeftTrigger2),
+                ("RT", GamepadButtonType::RightTrigger2),
+            ] {
+                let value = trigger(button_type);
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    ui.add(egui::ProgressBar::new(value).desired_width(80.0).text(format!("{value:.2}")));
+                });
+            }
+        });
+    });
+
+    ui.horizontal_wrapped(|ui| {
+        for (button_type, label) in GAMEPAD_BUTTONS {
+            let pressed = inputs.buttons.pressed(GamepadButton::new(gamepad, button_type));
+            let text = egui::RichText::new(label).monospace();
+            let text = if pressed {
+                text.color(egui::Color32::BLACK).background_color(egui::Color32::from_rgb(120, 200, 120))
+            } else {
+                text.weak()
+            };
+            ui.label(text);
+        }
+    });
+}
+
+/// A stick's position in its circle, with the dead zone shaded
+fn draw_stick(ui: &mut egui::Ui, value: egui::Vec2, dead_zone: f32) {
+    let (rect, _) = ui.allocate_exact_size(egui::Vec2::splat(STICK_SIZE), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let center = rect.center();
+    let radius = STICK_SIZE * 0.5 - 2.0;
+    painter.circle(
+        center,
+        radius,
+        egui::Color32::from_rgb(28, 28, 32),
+        egui::Stroke::new(1.0, egui::Color32::from_rgb(90, 90, 100)),
+    );
+    painter.circle_filled(center, radius * dead_zone.clamp(0.0, 1.0), egui::Color32::from_rgb(60, 45, 45));
+    painter.line_segment(
+        [center - egui::vec2(radius, 0.0), center + egui::vec2(radius, 0.0)],
+        egui::Stroke::new(1.0, egui::Color32::from_rgb(50, 50, 58)),
+    );
+    painter.line_segment(
+        [center - egui::vec2(0.0, radius), center + egui::vec2(0.0, radius)],
+        egui::Stroke::new(1.0, egui::Color32::from_rgb(50, 50, 58)),
+    );
+    // Up on the stick is positive, up on screen is negative
+    let position = center + egui::vec2(value.x, -value.y) * radius;
+    painter.circle_filled(position, 4.0, egui::Color32::from_rgb(230, 180, 60));
+}
@@ -0,0 +1,234 @@
+//! Waffle Engine Inspector Targets
+//! The components an inspector tab edits are fetched for the entity that tab
+//! shows, while the tab is drawn. The main inspector shows the selection;
+//! extra inspectors can follow the selection too or stay pinned to one
+//! entity, so two entities can be compared and edited side by side.
+
+use bevy::ecs::entity::Entities;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use egui_dock::DockState;
+
+use super::sub_scene::{SceneReference, SubSceneInstance};
+use super::{find_descendant_with_material, EditorTab, HierarchySnapshot};
+use crate::audio::WaffleAudioSource;
+use crate::core::animation::WaffleAnimator;
+use crate::core::constraints::{FollowConstraint, LookAtConstraint, StickToSurfaceConstraint};
+use crate::core::keyframes::PropertyAnimation;
+use crate::core::state_machine::AnimationStateMachine;
+use crate::core::surface::{MaterialSurfaces, PhysicalSurface};
+use crate::core::vehicle::RaycastVehicle;
+use crate::rendering::ao_volume::{AoVolume, AoVolumeBaking};
+use crate::rendering::atmosphere::AtmosphereSettingsComponent;
+use crate::rendering::camera_rig::{CameraCrane, CameraDolly, CameraFocus, DollyTrack};
+use crate::rendering::camera_shake::CameraShake;
+use crate::rendering::foliage::FoliageLayer;
+use crate::rendering::instancing::InstancedMesh;
+use crate::rendering::lens_flare::LensFlare;
+use crate::rendering::lighting::WaffleLight;
+use crate::rendering::lod::{LodGroup, LodState};
+use crate::rendering::materials::PbrTextureOverrides;
+use crate::rendering::particles::ParticleEmitter;
+use crate::rendering::placeholders::MissingAsset;
+use crate::rendering::portal::Portal;
+use crate::rendering::scene::EnvironmentSettings;
+use crate::rendering::water::WaffleWater;
+use crate::scripting::LuaScript;
+use crate::terrain::WaffleTerrain;
+
+#[derive(SystemParam)]
+pub struct InspectorQueries<'w, 's> {
+    entities: &'w Entities,
+    name_query: Query<'w, 's, &'static mut Name>,
+    transform_query: Query<'w, 's, &'static mut Transform>,
+    global_transform_query: Query<'w, 's, &'static GlobalTransform>,
+    parent_query: Query<'w, 's, &'static Parent>,
+    material_handle_query: Query<'w, 's, &'static Handle<StandardMaterial>>,
+    pbr_overrides_query: Query<'w, 's, &'static mut PbrTextureOverrides>,
+    environment_query: Query<'w, 's, &'static mut EnvironmentSettings>,
+    atmosphere_query: Query<'w, 's, &'static mut AtmosphereSettingsComponent>,
+    waffle_light_query: Query<'w, 's, &'static mut WaffleLight>,
+    directional_light_query: Query<'w, 's, &'static mut DirectionalLight>,
+    point_light_query: Query<'w, 's, &'static mut PointLight>,
+    spot_light_query: Query<'w, 's, &'static mut SpotLight>,
+    look_at_query: Query<'w, 's, &'static mut LookAtConstraint>,
+    follow_query: Query<'w, 's, &'static mut FollowConstraint>,
+    stick_to_surface_query: Query<'w, 's, &'static mut StickToSurfaceConstraint>,
+    vehicle_query: Query<'w, 's, &'static mut RaycastVehicle>,
+    lens_flare_query: Query<'w, 's, &'static mut LensFlare>,
+    animator_query: Query<'w, 's, &'static mut WaffleAnimator>,
+    state_machine_query: Query<'w, 's, &'static mut AnimationStateMachine>,
+    property_animation_query: Query<'w, 's, &'static mut PropertyAnimation>,
+    ao_volume_query: Query<'w, 's, (&'static mut AoVolume, Has<AoVolumeBaking>)>,
+    camera_shake_query: Query<'w, 's, &'static mut CameraShake>,
+    dolly_track_query: Query<'w, 's, &'static mut DollyTrack>,
+    camera_dolly_query: Query<'w, 's, &'static mut CameraDolly>,
+    camera_crane_query: Query<'w, 's, &'static mut CameraCrane>,
+    camera_focus_query: Query<'w, 's, &'static mut CameraFocus>,
+    lua_script_query: Query<'w, 's, &'static mut LuaScript>,
+    particle_emitter_query: Query<'w, 's, &'static mut ParticleEmitter>,
+    terrain_query: Query<'w, 's, &'static mut WaffleTerrain>,
+    foliage_query: Query<'w, 's, &'static mut FoliageLayer>,
+    water_query: Query<'w, 's, &'static mut WaffleWater>,
+    lod_query: Query<'w, 's, (&'static mut LodGroup, Option<&'static mut LodState>)>,
+    sub_scene_query: Query<'w, 's, (&'static SceneReference, Option<&'static SubSceneInstance>)>,
+    audio_source_query: Query<'w, 's, &'static mut WaffleAudioSource>,
+    portal_query: Query<'w, 's, &'static mut Portal>,
+    render_layers_query: Query<'w, 's, &'static RenderLayers>,
+    surface_query: Query<'w, 's, &'static PhysicalSurface>,
+    material_surfaces: Res<'w, MaterialSurfaces>,
+    missing_asset_query: Query<'w, 's, &'static MissingAsset>,
+    camera_marker_query: Query<'w, 's, (), With<Camera>>,
+    instanced_query: Query<'w, 's, (), With<InstancedMesh>>,
+}
+
+/// The components of the entity one inspector tab shows, or nothing when it
+/// shows no entity
+pub struct InspectedEntity<'a> {
+    pub transform: Option<Mut<'a, Transform>>,
+    pub parent_transform: Option<GlobalTransform>,
+    pub name: Option<Mut<'a, Name>>,
+    /// The entity's material, or the first one among its descendants
+    pub material_handle: Option<Handle<StandardMaterial>>,
+    pub overrides: Option<Mut<'a, PbrTextureOverrides>>,
+    pub environment: Option<Mut<'a, EnvironmentSettings>>,
+    pub atmosphere: Option<Mut<'a, AtmosphereSettingsComponent>>,
+    pub waffle_light: Option<Mut<'a, WaffleLight>>,
+    pub directional_light: Option<Mut<'a, DirectionalLight>>,
+    pub point_light: Option<Mut<'a, PointLight>>,
+    pub spot_light: Option<Mut<'a, SpotLight>>,
+    pub look_at: Option<Mut<'a, LookAtConstraint>>,
+    pub follow: Option<Mut<'a, FollowConstraint>>,
+    pub stick_to_surface: Option<Mut<'a, StickToSurfaceConstraint>>,
+    pub vehicle: Option<Mut<'a, RaycastVehicle>>,
+    pub lens_flare: Option<Mut<'a, LensFlare>>,
+    pub animator: Option<Mut<'a, WaffleAnimator>>,
+    pub state_machine: Option<Mut<'a, AnimationStateMachine>>,
+    pub property_animation: Option<Mut<'a, PropertyAnimation>>,
+    /// The AO volume and whether it is baking
+    pub ao_volume: Option<(Mut<'a, AoVolume>, bool)>,
+    pub camera_shake: Option<Mut<'a, CameraShake>>,
+    pub dolly_track: Option<Mut<'a, DollyTrack>>,
+    pub camera_dolly: Option<Mut<'a, CameraDolly>>,
+    pub camera_crane: Option<Mut<'a, CameraCrane>>,
+    pub camera_focus: Option<Mut<'a, CameraFocus>>,
+    pub lua_script: Option<Mut<'a, LuaScript>>,
+    pub particle_emitter: Option<Mut<'a, ParticleEmitter>>,
+    pub terrain: Option<Mut<'a, WaffleTerrain>>,
+    pub foliage: Option<Mut<'a, FoliageLayer>>,
+    pub water: Option<Mut<'a, WaffleWater>>,
+    /// The LOD group and, once it has run, the level it draws
+    pub lod: Option<(Mut<'a, LodGroup>, Option<Mut<'a, LodState>>)>,
+    pub sub_scene: Option<(&'a SceneReference, Option<&'a SubSceneInstance>)>,
+    pub audio_source: Option<Mut<'a, WaffleAudioSource>>,
+    pub portal: Option<Mut<'a, Portal>>,
+    pub render_layers: Option<RenderLayers>,
+    pub missing_asset: Option<MissingAsset>,
+    pub surface: Option<PhysicalSurface>,
+    pub material_surface: Option<PhysicalSurface>,
+    pub is_camera: bool,
+    pub instanced: bool,
+}
+
+impl InspectorQueries<'_, '_> {
+    pub fn inspect(&mut self, entity: Option<Entity>, hierarchy: &HierarchySnapshot) -> InspectedEntity<'_> {
+        let material_entity = entity.and_then(|entity| {
+            if self.material_handle_query.get(entity).is_ok() {
+                return Some(entity);
+            }
+            find_descendant_with_material(entity, hierarchy, &self.material_handle_query)
+        });
+        let material_handle = material_entity
+            .and_then(|entity| self.material_handle_query.get(entity).ok().cloned());
+        let material_surface = material_handle
+            .as_ref()
+            .and_then(|handle| self.material_surfaces.get(handle));
+
+        InspectedEntity {
+            transform: entity.and_then(|entity| self.transform_query.get_mut(entity).ok()),
+            parent_transform: entity
+                .and_then(|entity| self.parent_query.get(entity).ok())
+                .and_then(|parent| self.global_transform_query.get(parent.get()).ok())
+                .copied(),
+            name: entity.and_then(|entity| self.name_query.get_mut(entity).ok()),
+            overrides: material_entity.and_then(|entity| self.pbr_overrides_query.get_mut(entity).ok()),
+            material_handle,
+            environment: entity.and_then(|entity| self.environment_query.get_mut(entity).ok()),
+            atmosphere: entity.and_then(|entity| self.atmosphere_query.get_mut(entity).ok()),
+            waffle_light: entity.and_then(|entity| self.waffle_light_query.get_mut(entity).ok()),
+            directional_light: entity.and_then(|entity| self.directional_light_query.get_mut(entity).ok()),
+            point_light: entity.and_then(|entity| self.point_light_query.get_mut(entity).ok()),
+            spot_light: entity.and_then(|entity| self.spot_light_query.get_mut(entity).ok()),
+            look_at: entity.and_then(|entity| self.look_at_query.get_mut(entity).ok()),
+            follow: entity.and_then(|entity| self.follow_query.get_mut(entity).ok()),
+            stick_to_surface: entity.and_then(|entity| self.stick_to_surface_query.get_mut(entity).ok()),
+            vehicle: entity.and_then(|entity| self.vehicle_query.get_mut(entity).ok()),
+            lens_flare: entity.and_then(|entity| self.lens_flare_query.get_mut(entity).ok()),
+            animator: entity.and_then(|entity| self.animator_query.get_mut(entity).ok()),
+            state_machine: entity.and_then(|entity| self.state_machine_query.get_mut(entity).ok()),
+            property_animation: entity.and_then(|entity| self.property_animation_query.get_mut(entity).ok()),
+            ao_volume: entity.and_then(|entity| self.ao_volume_query.get_mut(entity).ok()),
+            camera_shake: entity.and_then(|entity| self.camera_shake_query.get_mut(entity).ok()),
+            dolly_track: entity.and_then(|entity| self.dolly_track_query.get_mut(entity).ok()),
+            camera_dolly: entity.and_then(|entity| self.camera_dolly_query.get_mut(entity).ok()),
+            camera_crane: entity.and_then(|entity| self.camera_crane_query.get_mut(entity).ok()),
+            camera_focus: entity.and_then(|entity| self.camera_focus_query.get_mut(entity).ok()),
+            lua_script: entity.and_then(|entity| self.lua_script_query.get_mut(entity).ok()),
+            particle_emitter: entity.and_then(|entity| self.particle_emitter_query.get_mut(entity).ok()),
+            terrain: entity.and_then(|entity| self.terrain_query.get_mut(entity).ok()),
+            foliage: entity.and_then(|entity| self.foliage_query.get_mut(entity).ok()),
+            water: entity.and_then(|entity| self.water_query.get_mut(entity).ok()),
+            lod: entity.and_then(|entity| self.lod_query.get_mut(entity).ok()),
+            sub_scene: entity.and_then(|entity| self.sub_scene_query.get(entity).ok()),
+            audio_source: entity.and_then(|entity| self.audio_source_query.get_mut(entity).ok()),
+            portal: entity.and_then(|entity| self.portal_query.get_mut(entity).ok()),
+            render_layers: entity.and_then(|entity| self.render_layers_query.get(entity).ok()).cloned(),
+            missing_asset: entity.and_then(|entity| self.missing_asset_query.get(entity).ok()).cloned(),
+            surface: entity.and_then(|entity| self.surface_query.get(entity).ok()).copied(),
+            material_surface,
+            is_camera: entity.is_some_and(|entity| self.camera_marker_query.contains(entity)),
+            instanced: entity.is_some_and(|entity| self.instanced_query.contains(entity)),
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    pub fn name(&self, entity: Entity) -> Option<&Name> {
+        self.name_query.get(entity).ok()
+    }
+
+    pub fn particle_emitter(&mut self, entity: Option<Entity>) -> Option<Mut<'_, ParticleEmitter>> {
+        entity.and_then(|entity| self.particle_emitter_query.get_mut(entity).ok())
+    }
+}
+
+/// Entities the open extra inspectors are pinned to
+pub fn pinned_inspector_entities(dock_state: &DockState<EditorTab>) -> impl Iterator<Item = Entity> + '_ {
+    dock_state.iter_all_tabs().filter_map(|(_, tab)| match tab {
+        EditorTab::ExtraInspector { pinned, .. } => *pinned,
+        _ => None,
+    })
+}
+
+/// Open another inspector below the main one, pinned to `pinned` or
+/// following the selection
+pub fn open_extra_inspector(dock_state: &mut DockState<EditorTab>, pinned: Option<Entity>) {
+    let id = dock_state
+        .iter_all_tabs()
+        .filter_map(|(_, tab)| match tab {
+            EditorTab::ExtraInspector { id, .. } => Some(*id + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default();
+    let tab = EditorTab::ExtraInspector { id, pinned };
+    match dock_state.find_tab(&EditorTab::Inspector) {
+        Some((surface, node, _)) => {
+            dock_state[surface].split_below(node, 0.5, vec![tab]);
+        }
+        None => dock_state.push_to_focused_leaf(tab),
+    }
+}
@@ -0,0 +1,314 @@
+//! Waffle Engine Editor Jobs
+//! Shared worker threads for slow editor work such as asset scans and
+//! imports, so it never stalls the editor UI. Jobs run highest priority
+//! first, can be cancelled, and report progress to the Jobs panel.
+//!
+//! A job checks `JobContext::is_cancelled` between steps and returns early;
+//! cancelled jobs still queued never start.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use crossbeam_channel::{Receiver, TryRecvError};
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::core::guard::catch_panic;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JobPriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl JobPriority {
+    pub fn label(self) -> &'static str {
+        match self {
+            JobPriority::Low => "Low",
+            JobPriority::Normal => "Normal",
+            JobPriority::High => "High",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JobId(u64);
+
+/// What the Jobs panel shows about a queued or running job
+#[derive(Clone, Debug)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub name: String,
+    pub priority: JobPriority,
+    pub state: JobState,
+    /// 0 to 1, or `None` when the job cannot tell
+    pub progress: Option<f32>,
+    pub status: String,
+    pub cancelled: bool,
+}
+
+struct QueuedJob {
+    id: JobId,
+    priority: JobPriority,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+struct JobRecord {
+    info: JobInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct JobPool {
+    queue: Mutex<Vec<QueuedJob>>,
+    available: Condvar,
+    records: Mutex<Vec<JobRecord>>,
+    next_id: AtomicU64,
+}
+
+impl JobPool {
+    fn update(&self, id: JobId, f: impl FnOnce(&mut JobInfo)) {
+        if let Some(record) = self.records.lock().iter_mut().find(|record| record.info.id == id) {
+            f(&mut record.info);
+        }
+    }
+
+    fn finish(&self, id: JobId) {
+        self.records.lock().retain(|record| record.info.id != id);
+    }
+
+    /// Oldest of the highest priority jobs
+    fn take_next(&self) -> QueuedJob {
+        let mut queue = self.queue.lock();
+        loop {
+            let next = queue
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, job)| job.priority)
+                .map(|(index, _)| index);
+            if let Some(index) = next {
+                return queue.remove(index);
+            }
+            self.available.wait(&mut queue);
+        }
+    }
+
+    fn work(self: Arc<Self>) {
+        loop {
+            let job = self.take_next();
+            (job.run)();
+            self.finish(job.id);
+        }
+    }
+}
+
+/// Handed to a running job
+pub struct JobContext {
+    id: JobId,
+    pool: Arc<JobPool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_progress(&self, progress: f32) {
+        self.pool.update(self.id, |info| info.progress = Some(progress.clamp(0.0, 1.0)));
+    }
+
+    pub fn set_status(&self, status: impl Into<String>) {
+        let status = status.into();
+        self.pool.update(self.id, |info| info.status = status);
+    }
+}
+
+/// Result of a spawned job; dropping it lets the job run on unobserved
+pub struct JobHandle<R> {
+    id: JobId,
+    result: Receiver<Result<R, String>>,
+}
+
+impl<R> JobHandle<R> {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// The job's result once it has finished. Failed, panicked and cancelled
+    /// jobs give their error message.
+    pub fn poll(&self) -> Option<Result<R, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("cancelled".to_string())),
+        }
+    }
+}
+
+/// The editor's worker threads and their queue
+#[derive(Resource, Clone)]
+pub struct EditorJobs {
+    pool: Arc<JobPool>,
+}
+
+impl Default for EditorJobs {
+    fn default() -> Self {
+        let pool = Arc::new(JobPool::default());
+        // Leave a core for the main and render threads
+        let workers = std::thread::available_parallelism()
+            .map(|count| count.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1);
+        for index in 0..workers {
+            let pool = pool.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("editor-job-{index}"))
+                .spawn(move || pool.work());
+            if let Err(err) = spawned {
+                error!("Failed to start editor job thread: {err}");
+            }
+        }
+        Self { pool }
+    }
+}
+
+impl EditorJobs {
+    pub fn spawn<R: Send + 'static>(
+        &self,
+        name: impl Into<String>,
+        priority: JobPriority,
+        job: impl FnOnce(&JobContext) -> anyhow::Result<R> + Send + 'static,
+    ) -> JobHandle<R> {
+        let id = JobId(self.pool.next_id.fetch_add(1, Ordering::Relaxed));
+        let name = name.into();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, result) = crossbeam_channel::bounded(1);
+
+        let context = JobContext {
+            id,
+            pool: self.pool.clone(),
+            cancelled: cancelled.clone(),
+        };
+        let job_name = name.clone();
+        let run = move || {
+            if context.is_cancelled() {
+                return;
+            }
+            context.pool.update(id, |info| info.state = JobState::Running);
+            let outcome = match catch_panic(|| job(&context)) {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(err)) => Err(err.to_string()),
+                Err(message) => Err(format!("panicked: {message}")),
+            };
+            if context.is_cancelled() {
+                info!("Cancelled job \"{}\"", job_name);
+                return;
+            }
+            if let Err(message) = &outcome {
+                error!("Job \"{}\" failed: {}", job_name, message);
+            }
+            let _ = sender.send(outcome);
+        };
+
+        self.pool.records.lock().push(JobRecord {
+            info: JobInfo {
+                id,
+                name,
+                priority,
+                state: JobState::Queued,
+                progress: None,
+                status: String::new(),
+                cancelled: false,
+            },
+            cancelled,
+        });
+        self.pool.queue.lock().push(QueuedJob {
+            id,
+            priority,
+            run: Box::new(run),
+        });
+        self.pool.available.notify_one();
+        JobHandle { id, result }
+    }
+
+    /// Ask a job to stop. Queued jobs are dropped; running jobs stop at their
+    /// next cancellation check.
+    pub fn cancel(&self, id: JobId) {
+        if let Some(record) = self.pool.records.lock().iter_mut().find(|record| record.info.id == id) {
+            record.cancelled.store(true, Ordering::Relaxed);
+            record.info.cancelled = true;
+        }
+        let mut queue = self.pool.queue.lock();
+        if let Some(index) = queue.iter().position(|job| job.id == id) {
+            queue.remove(index);
+            drop(queue);
+            self.pool.finish(id);
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for job in self.jobs() {
+            self.cancel(job.id);
+        }
+    }
+
+    /// Queued and running jobs, running first
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.pool.records.lock().iter().map(|record| record.info.clone()).collect();
+        jobs.sort_by_key(|job| (job.state != JobState::Running, std::cmp::Reverse(job.priority)));
+        jobs
+    }
+}
+
+pub fn draw_jobs_panel(ui: &mut egui::Ui, jobs: &EditorJobs) {
+    let list = jobs.jobs();
+    let running = list.iter().filter(|job| job.state == JobState::Running).count();
+
+    ui.horizontal(|ui| {
+        ui.heading("Jobs");
+        ui.label(format!("{} running, {} queued", running, list.len() - running));
+        if !list.is_empty() && ui.button("Cancel All").clicked() {
+            jobs.cancel_all();
+        }
+    });
+    ui.separator();
+
+    if list.is_empty() {
+        ui.label("No background jobs.");
+        return;
+    }
+    egui::ScrollArea::vertical().id_source("editor_jobs").show(ui, |ui| {
+        for job in &list {
+            ui.horizontal(|ui| {
+                ui.strong(&job.name);
+                ui.weak(job.priority.label());
+                if job.cancelled {
+                    ui.weak("Cancelling...");
+                } else if ui.small_button("Cancel").clicked() {
+                    jobs.cancel(job.id);
+                }
+            });
+            let bar = match (job.state, job.progress) {
+                (JobState::Queued, _) => egui::ProgressBar::new(0.0).text("Queued"),
+                (JobState::Running, Some(progress)) => egui::ProgressBar::new(progress).show_percentage(),
+                (JobState::Running, None) => egui::ProgressBar::new(0.0).animate(true).text("Running"),
+            };
+            ui.add(bar);
+            if !job.status.is_empty() {
+                ui.small(&job.status);
+            }
+            ui.add_space(4.0);
+        }
+    });
+    // Progress comes from other threads
+    ui.ctx().request_repaint();
+}
@@ -1,37 +1,100 @@
-/// Waffle Engine Editor Module
-/// Contains the complete editor interface with dark theme
+//! Waffle Engine Editor Module
+//! Contains the complete editor interface with dark theme
 
 pub mod ui;
 pub mod windows;
 pub mod theme;
 pub mod panels;
+pub mod bookmarks;
+pub mod trash;
+pub mod external;
+pub mod vcs;
+pub mod collab;
+pub mod extensions;
+pub mod tools;
+pub mod asset_refs;
+pub mod jobs;
+pub mod scene_file;
+pub mod play_mode;
+pub mod selection;
+pub mod physics_debug;
+pub mod thumbnails;
+pub mod model_import;
+pub mod input_debug;
+pub mod accessibility;
+pub mod tour;
+pub mod templates;
+pub mod scene_diff;
+pub mod terrain_sculpt;
+pub mod foliage_paint;
+pub mod sub_scene;
+pub mod ecs_stats;
+pub mod systems_panel;
+pub mod resources_panel;
+pub mod inspector;
 
 use bevy::prelude::*;
-use bevy::ecs::system::{ParamSet, SystemParam};
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::CommandQueue;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::time::{Duration, Instant};
 use bevy::log::{tracing_subscriber, BoxedLayer, Level};
 use bevy::log::tracing_subscriber::Layer;
 use bevy::utils::tracing::{self, Subscriber};
-use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiSet, EguiSettings};
 use egui_dock::{DockArea, DockState, NodeIndex, Style};
 use bevy::render::render_resource::Extent3d;
 use bevy::input::mouse::MouseMotion;
 use bevy::input::mouse::MouseButton;
 use bevy::input::keyboard::KeyCode;
 use bevy::render::camera::Camera;
+use bevy::core_pipeline::contrast_adaptive_sharpening::ContrastAdaptiveSharpeningSettings;
 use bevy::render::mesh::Mesh;
 use bevy::render::primitives::Aabb;
+use bevy::render::view::RenderLayers;
 use serde::{Deserialize, Serialize};
 use crate::core::resources::EngineConfig;
-use crate::rendering::camera::{ViewportRenderTarget, WaffleCamera, WaffleMainCamera};
+use crate::rendering::camera::{
+    ortho_camera_transform, CameraSettings, OrthoView, OrthoViewTargets, ViewportRenderTarget, WaffleCamera,
+    WaffleMainCamera, WaffleOrthoCamera, ORTHO_VIEW_HEIGHT,
+};
 use crate::core::components::EditorHidden;
-use crate::rendering::scene::{EnvironmentSettings, SceneSettings, WaffleSceneRoot, WaffleSceneObject};
-use crate::rendering::atmosphere::AtmosphereSettingsComponent;
+use crate::core::constraints::{FollowConstraint, LookAtConstraint, StickToSurfaceConstraint};
+use crate::core::vehicle::RaycastVehicle;
+use crate::core::surface::{MaterialSurfaces, PhysicalSurface};
+use crate::core::asset_meta::{AssetMeta, ensure_asset_meta, is_meta_file, read_asset_meta, write_asset_meta};
+use crate::core::project::ProjectSettings;
+use crate::core::play::{PlaySession, PlayState};
+use crate::core::cursor::GameCursor;
+use crate::rendering::scene::{SceneSettings, WaffleSceneRoot, WaffleSceneObject};
 use crate::rendering::lighting::WaffleLight;
-use crate::rendering::materials::PbrTextureOverrides;
+use crate::rendering::obj::{OBJ_EXTENSION, OBJ_SCENE_LABEL};
+use crate::core::input::{InputAxis, TouchGesture};
+use crate::core::animation::WaffleAnimator;
+use crate::core::state_machine::AnimationStateMachine;
+use crate::rendering::particles::{ParticleEmitter, ParticleSystemState};
+use crate::rendering::foliage::FoliageLayer;
+use crate::rendering::water::WaffleWater;
+use crate::rendering::lod::LodGroup;
+use crate::rendering::instancing::{InstancedMesh, InstancingStats};
+use crate::terrain::{TerrainChunk, TerrainState, WaffleTerrain};
+use crate::core::keyframes::{KeyInterpolation, KeyedProperty, KeyedTargetQuery, Keyframe, PropertyAnimation};
+use crate::rendering::placeholders::LocateMissingAssetEvent;
+use crate::rendering::ao_volume::{AoVolume, BakeAoVolumeEvent};
+use crate::scripting::LuaScript;
+use crate::audio::{PlayAudioEvent, StopAudioEvent, WaffleAudioSource};
+use crate::rendering::camera_rig::{CameraCrane, CameraDolly, CameraFocus, DollyTrack};
+use crate::rendering::portal::{Portal, PortalView};
+use selection::EditorSelection;
+use accessibility::{
+    enable_editor_accesskit, forward_editor_access_actions, mirror_editor_access_tree, AccessibleName,
+    EditorAccessTree,
+};
+use model_import::{has_sub_assets, read_sub_assets, split_label, SubAsset, SubAssetKind};
+use crate::rendering::camera_shake::{CameraShakeEvent, PreviewCameraShakeEvent};
 use walkdir::WalkDir;
 use bevy::window::FileDragAndDrop;
 
@@ -39,6 +102,33 @@ use ui::*;
 use windows::*;
 use theme::*;
 use panels::*;
+use bookmarks::*;
+use trash::*;
+use external::*;
+use vcs::*;
+use collab::*;
+use extensions::*;
+use tools::*;
+use terrain_sculpt::{draw_terrain_brush, sculpt_terrain, TerrainSculptHistory};
+use foliage_paint::{draw_foliage_brush, paint_foliage, FoliagePainter};
+use sub_scene::{
+    apply_sub_scene_edit_events, despawn_removed_sub_scenes, refresh_sub_scenes, scene_name_from_asset_path,
+    sub_scene_pick_target, SceneReference, SubSceneEditEvent,
+};
+use asset_refs::*;
+use jobs::*;
+use ecs_stats::{collect_ecs_stats, EcsStats};
+use systems_panel::{collect_system_list, SystemsPanel};
+use resources_panel::{sync_resources_panel, ResourcesPanel};
+use inspector::{open_extra_inspector, pinned_inspector_entities, InspectorQueries};
+use scene_file::*;
+use play_mode::*;
+use physics_debug::*;
+use thumbnails::*;
+use input_debug::*;
+use tour::{draw_tour, TourProgress, TourSignals, TourTargets};
+use templates::{show_new_project_dialog, NewProjectDialog};
+use scene_diff::{capture_working_scene_for_diff, show_scene_diff_window, SceneDiffMode, SceneDiffWindow};
 
 /// Editor UI plugin
 pub struct WaffleEditorPlugin;
@@ -47,26 +137,174 @@ impl Plugin for WaffleEditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EguiPlugin)
             .add_systems(Startup, setup_editor)
-            .add_systems(Update, update_editor_ui)
+            .add_systems(PostStartup, apply_project_editor_defaults)
+            .add_systems(Update, (update_hierarchy_snapshot, update_editor_ui).chain())
             .add_systems(Update, sync_editor_camera_focus)
+            .add_systems(Update, sync_viewport_sharpening)
+            .add_systems(Update, sync_editor_ui_scale)
+            // Screen readers see the editor's widgets through Bevy's tree
+            .init_resource::<EditorAccessTree>()
+            .add_systems(
+                PreUpdate,
+                (enable_editor_accesskit, forward_editor_access_actions)
+                    .after(EguiSet::ProcessInput)
+                    .before(EguiSet::BeginFrame),
+            )
+            .add_systems(
+                PostUpdate,
+                mirror_editor_access_tree
+                    .after(EguiSet::ProcessOutput)
+                    .before(bevy::a11y::AccessibilitySystem::Update),
+            )
+            .add_systems(Update, sync_ortho_view_cameras.after(update_editor_ui))
+            .add_systems(
+                Update,
+                (load_scene_bookmarks, handle_camera_bookmarks.run_if(in_state(PlayState::Editing))).chain(),
+            )
             .add_systems(Update, update_selected_entity_transform)
+            .add_systems(Update, apply_selection_isolation.after(update_editor_ui))
             .add_systems(Update, update_editor_camera_orbit_focus.after(crate::rendering::camera::update_camera))
+            .add_systems(Update, update_editor_camera_touch.after(crate::rendering::camera::update_camera))
             .add_systems(Update, draw_selected_gizmos.after(crate::rendering::camera::update_camera))
+            .init_gizmo_group::<TransformGizmos>()
+            .add_systems(Update, draw_transform_gizmo.after(update_editor_ui))
+            .add_systems(Update, draw_vehicle_gizmos.after(crate::rendering::camera::update_camera))
+            .add_systems(Update, draw_dolly_track_gizmos.after(crate::rendering::camera::update_camera))
+            .init_resource::<PhysicsDebugContacts>()
+            .add_systems(
+                Update,
+                (collect_physics_debug_contacts, draw_physics_debug_gizmos)
+                    .chain()
+                    .after(crate::rendering::camera::update_camera),
+            )
             .add_systems(Update, draw_editor_grid.after(crate::rendering::camera::update_camera))
             .add_systems(Update, collect_editor_logs)
-            .add_systems(Update, refresh_asset_cache)
+            .add_systems(Update, (refresh_asset_cache, update_asset_thumbnails).chain())
             .add_systems(Update, apply_reparent_events)
-            .add_systems(Update, apply_delete_events)
+            .add_systems(Update, (apply_delete_events, apply_restore_events, apply_empty_trash_events).chain())
             .add_systems(Update, apply_spawn_primitive_events)
             .add_systems(Update, apply_spawn_asset_events)
+            .add_systems(
+                Update,
+                (
+                    apply_remote_collab_messages
+                        .before(apply_spawn_primitive_events)
+                        .before(apply_spawn_asset_events)
+                        .before(apply_delete_events),
+                    assign_collab_ids
+                        .after(apply_spawn_primitive_events)
+                        .after(apply_spawn_asset_events),
+                    publish_local_edits.after(update_editor_ui).after(assign_collab_ids),
+                    publish_local_deletes.before(apply_delete_events),
+                ),
+            )
+            .add_systems(Last, disconnect_collab_on_exit)
+            .add_systems(Update, apply_pivot_edit_events)
+            .add_systems(Update, apply_constraint_edit_events)
+            .add_systems(Update, apply_camera_rig_edit_events)
+            .add_systems(Update, apply_lua_script_edit_events)
+            .add_systems(Update, apply_state_machine_edit_events)
+            .add_systems(Update, apply_particle_emitter_edit_events)
+            .add_systems(Update, apply_terrain_edit_events)
+            .add_systems(Update, apply_foliage_edit_events)
+            .add_systems(Update, apply_water_edit_events)
+            .add_systems(Update, apply_lod_edit_events)
+            .add_systems(
+                Update,
+                (apply_sub_scene_edit_events, despawn_removed_sub_scenes, refresh_sub_scenes)
+                    .chain()
+                    .after(update_editor_ui),
+            )
+            .add_systems(Update, apply_audio_source_edit_events)
+            .add_systems(Update, apply_material_edit_events)
+            .add_systems(Update, apply_reimport_events)
+            .add_systems(Update, apply_material_library_events)
+            .add_systems(Update, apply_render_layers_edit_events)
+            .add_systems(Update, apply_instancing_edit_events)
+            .add_systems(Update, apply_surface_edit_events)
+            .add_systems(Update, apply_keyframe_edit_events)
+            .add_systems(Startup, load_external_tools)
+            .add_systems(Update, (apply_open_external_events, reimport_externally_edited_assets).chain())
+            .add_systems(Update, (refresh_vcs_status, apply_vcs_actions).chain())
+            .add_systems(Update, apply_asset_file_events.after(update_editor_ui))
+            .add_systems(Update, handle_scene_file_events.after(update_editor_ui))
+            .add_systems(Update, open_startup_scene.before(handle_scene_file_events))
+            .add_systems(Update, capture_working_scene_for_diff.after(update_editor_ui))
+            .init_resource::<PlaySnapshot>()
+            .add_systems(OnEnter(PlayState::Playing), take_play_snapshot)
+            .add_systems(OnExit(PlayState::Playing), restore_play_snapshot)
+            .add_systems(Update, rewrite_located_references.after(update_editor_ui))
+            .add_systems(Update, (apply_paint_tool_clicks, apply_measure_tool_clicks).after(update_editor_ui))
+            .add_systems(Update, draw_measure_tool.after(crate::rendering::camera::update_camera))
+            .add_systems(
+                Update,
+                sculpt_terrain
+                    .after(update_editor_ui)
+                    .before(crate::terrain::build_terrain_chunks),
+            )
+            .add_systems(Update, draw_terrain_brush.after(sculpt_terrain))
+            .add_systems(
+                Update,
+                paint_foliage
+                    .after(update_editor_ui)
+                    .before(crate::rendering::foliage::build_foliage_batches),
+            )
+            .add_systems(Update, draw_foliage_brush.after(paint_foliage))
             .init_resource::<EditorState>()
             .init_resource::<EditorSettings>()
             .init_resource::<EditorOutput>()
             .init_resource::<AssetBrowserCache>()
+            .init_resource::<AssetThumbnails>()
+            .init_resource::<CameraBookmarks>()
+            .init_resource::<EditorTrash>()
+            .init_resource::<ExternalToolSettings>()
+            .init_resource::<ExternallyEditedAssets>()
+            .init_resource::<VcsStatus>()
+            .init_resource::<CollabSession>()
+            .init_resource::<EditorExtensions>()
+            .init_resource::<ActiveTool>()
+            .init_resource::<TerrainSculptHistory>()
+            .init_resource::<FoliagePainter>()
+            .init_resource::<EditorJobs>()
+            .init_resource::<InputDebugLog>()
+            .init_resource::<EcsStats>()
+            .add_systems(Update, collect_ecs_stats.after(update_editor_ui))
+            .init_resource::<SystemsPanel>()
+            .add_systems(First, collect_system_list)
+            .init_resource::<ResourcesPanel>()
+            .add_systems(Update, sync_resources_panel.after(update_editor_ui))
+            .add_systems(Update, record_input_events)
+            .init_resource::<HierarchySnapshot>()
             .add_event::<HierarchyReparentEvent>()
             .add_event::<DeleteEntityEvent>()
+            .add_event::<RestoreDeletedEvent>()
+            .add_event::<EmptyTrashEvent>()
             .add_event::<SpawnPrimitiveEvent>()
-            .add_event::<SpawnAssetEvent>();
+            .add_event::<SpawnAssetEvent>()
+            .add_event::<PivotEditEvent>()
+            .add_event::<ConstraintEditEvent>()
+            .add_event::<CameraRigEditEvent>()
+            .add_event::<LuaScriptEditEvent>()
+            .add_event::<StateMachineEditEvent>()
+            .add_event::<ParticleEmitterEditEvent>()
+            .add_event::<TerrainEditEvent>()
+            .add_event::<FoliageEditEvent>()
+            .add_event::<WaterEditEvent>()
+            .add_event::<LodEditEvent>()
+            .add_event::<SubSceneEditEvent>()
+            .add_event::<AudioSourceEditEvent>()
+            .add_event::<MaterialEditEvent>()
+            .add_event::<ReimportAssetEvent>()
+            .add_event::<MaterialLibraryEvent>()
+            .add_event::<RenderLayersEditEvent>()
+            .add_event::<InstancingEditEvent>()
+            .add_event::<SurfaceEditEvent>()
+            .add_event::<KeyframeEditEvent>()
+            .add_event::<OpenExternalEvent>()
+            .add_event::<VcsActionEvent>()
+            .add_event::<AssetFileEvent>()
+            .add_event::<SceneFileEvent>()
+            .add_event::<ViewportToolClickEvent>();
     }
 }
 
@@ -75,24 +313,76 @@ impl Plugin for WaffleEditorPlugin {
 pub struct EditorState {
     pub dock_state: DockState<EditorTab>,
     pub show_demo_window: bool,
-    pub selected_entity: Option<Entity>,
-    pub gizmo_mode: GizmoMode,
+    pub show_project_settings: bool,
+    pub show_external_tools: bool,
+    pub show_collaboration: bool,
+    pub show_plugin_settings: bool,
+    pub show_preferences: bool,
+    pub selection: EditorSelection,
     pub active_axis: Option<GizmoAxis>,
+    /// Drag movement not yet applied because it is below the snap increment
+    pub snap_remainder: Vec3,
     pub axis_space: AxisSpace,
     pub gizmo_overlay: Option<GizmoOverlay>,
+    /// Where the transform gizmo sits, which way its axes point and their
+    /// length in world units
+    pub gizmo_placement: Option<(Vec3, Mat3, f32)>,
     pub viewport_focused: bool,
     pub viewport_size: Vec2,
     pub viewport_origin: Vec2,
     pub viewport_hovered: bool,
     pub viewport_clicked: bool,
     pub viewport_click_pos: Option<Vec2>,
+    /// Render target pixels per egui point of the clicked pane
+    pub viewport_click_pixels_per_point: f32,
+    /// Cursor over a viewport pane, in its render target pixels
+    pub viewport_pointer_pos: Option<Vec2>,
+    pub viewport_pointer_view: ViewportView,
+    /// Shift was held for the viewport click
+    pub viewport_click_additive: bool,
     pub viewport_focus_request: bool,
+    pub viewport_layout: ViewportLayout,
+    pub active_view: ViewportView,
+    pub viewport_click_view: ViewportView,
+    pub ortho_viewports: Vec<OrthoViewportState>,
+    pub inspector_space: AxisSpace,
+    pub transform_clipboard: Option<Transform>,
+    pub transform_text: String,
+    pub pivot_offset: Vec3,
+    pub rotation_display: RotationDisplay,
+    pub rotation_edit: Option<RotationEditCache>,
+    pub behavior_editor: BehaviorTreeEditorState,
+    pub dialogue_editor: DialogueEditorState,
+    pub particle_editor: ParticleEditorState,
+    pub isolate_selection: bool,
+    pub isolated_roots: Vec<Entity>,
     pub hierarchy_filter: String,
     pub asset_filter: String,
     pub selected_asset: Option<String>,
-    pub delete_confirm: Option<Entity>,
+    /// Sidecar of the selected asset, as edited in the inspector
+    pub selected_asset_meta: Option<(String, AssetMeta)>,
+    /// The selected asset when it is a material inside a model, kept loaded
+    /// while the inspector edits it
+    pub selected_asset_material: Option<Handle<StandardMaterial>>,
+    /// Asset path typed into the inspector's Save Material field
+    pub material_save_name: String,
+    /// Asset path typed into the terrain inspector's Save Heightmap field
+    pub terrain_save_name: String,
+    /// Name for the next material created in the material library
+    pub new_material_name: String,
+    /// Time and interpolation the inspector records keyframes with
+    pub keyframe_time: f32,
+    pub keyframe_interpolation: KeyInterpolation,
+    /// Entities the delete dialog is asking about; closed when empty
+    pub delete_confirm: Vec<Entity>,
+    pub revert_confirm: Option<String>,
+    pub asset_file_dialog: Option<AssetFileDialog>,
+    pub scene_file_dialog: Option<SceneFileDialog>,
+    pub new_project_dialog: Option<NewProjectDialog>,
+    pub scene_diff: Option<SceneDiffWindow>,
     pub layout_cache: String,
-    pub layout_last_save: Instant,
+    /// Serializing the dock is not free, so changes are checked once a second
+    pub layout_last_check: Instant,
 }
 
 impl Default for EditorState {
@@ -114,24 +404,70 @@ impl Default for EditorState {
         Self {
             dock_state,
             show_demo_window: false,
-            selected_entity: None,
-            gizmo_mode: GizmoMode::Move,
+            show_project_settings: false,
+            show_external_tools: false,
+            show_collaboration: false,
+            show_plugin_settings: false,
+            show_preferences: false,
+            selection: EditorSelection::default(),
             active_axis: None,
+            snap_remainder: Vec3::ZERO,
             axis_space: AxisSpace::Global,
             gizmo_overlay: None,
+            gizmo_placement: None,
             viewport_focused: false,
             viewport_size: Vec2::new(1280.0, 720.0),
             viewport_origin: Vec2::ZERO,
             viewport_hovered: false,
             viewport_clicked: false,
             viewport_click_pos: None,
+            viewport_click_pixels_per_point: 1.0,
+            viewport_pointer_pos: None,
+            viewport_pointer_view: ViewportView::Perspective,
+            viewport_click_additive: false,
             viewport_focus_request: false,
+            viewport_layout: ViewportLayout::Single,
+            active_view: ViewportView::Perspective,
+            viewport_click_view: ViewportView::Perspective,
+            ortho_viewports: OrthoView::ALL
+                .into_iter()
+                .map(|view| OrthoViewportState {
+                    view,
+                    size: Vec2::ZERO,
+                    zoom_delta: 0.0,
+                    pan_delta: Vec2::ZERO,
+                    gizmo_overlay: None,
+                })
+                .collect(),
+            inspector_space: AxisSpace::Local,
+            transform_clipboard: None,
+            transform_text: String::new(),
+            pivot_offset: Vec3::ZERO,
+            rotation_display: RotationDisplay::Euler,
+            rotation_edit: None,
+            behavior_editor: BehaviorTreeEditorState::default(),
+            dialogue_editor: DialogueEditorState::default(),
+            particle_editor: ParticleEditorState::default(),
+            isolate_selection: false,
+            isolated_roots: Vec::new(),
             hierarchy_filter: String::new(),
             asset_filter: String::new(),
             selected_asset: None,
-            delete_confirm: None,
+            selected_asset_meta: None,
+            selected_asset_material: None,
+            material_save_name: String::new(),
+            terrain_save_name: String::new(),
+            new_material_name: String::new(),
+            keyframe_time: 0.0,
+            keyframe_interpolation: KeyInterpolation::Linear,
+            delete_confirm: Vec::new(),
+            revert_confirm: None,
+            asset_file_dialog: None,
+            scene_file_dialog: None,
+            new_project_dialog: None,
+            scene_diff: None,
             layout_cache: String::new(),
-            layout_last_save: Instant::now(),
+            layout_last_check: Instant::now(),
         }
     }
 }
@@ -144,6 +480,25 @@ pub struct EditorSettings {
     pub show_debug_info: bool,
     pub grid_enabled: bool,
     pub grid_size: f32,
+    pub snap_enabled: bool,
+    /// Index into the project's snapping presets
+    pub snap_preset: usize,
+    /// Viewport resolution relative to its panel, from 0.5 to 2.0. Below 1
+    /// the image is upscaled, above 1 it is supersampled.
+    pub render_scale: f32,
+    /// Contrast adaptive sharpening, which offsets the blur of upscaling
+    pub sharpen: bool,
+    pub sharpening_strength: f32,
+    /// Draw collision shapes, contacts and velocities of every body
+    pub show_physics_debug: bool,
+    /// Show assets as thumbnails instead of a list
+    pub asset_grid_view: bool,
+    /// Outline the game UI's safe area, and the TV action and title safe
+    /// areas, over the viewport
+    pub show_safe_area: bool,
+    /// How far the user got through the guided tour
+    pub tour: TourProgress,
+    pub gizmo: GizmoSettings,
 }
 
 impl Default for EditorSettings {
@@ -154,10 +509,114 @@ impl Default for EditorSettings {
             show_debug_info: false,
             grid_enabled: true,
             grid_size: 1.0,
+            snap_enabled: false,
+            snap_preset: 0,
+            render_scale: 1.0,
+            sharpen: false,
+            sharpening_strength: 0.6,
+            show_physics_debug: false,
+            asset_grid_view: true,
+            show_safe_area: false,
+            tour: TourProgress::default(),
+            gizmo: GizmoSettings::default(),
         }
     }
 }
 
+/// How the transform gizmo is drawn and picked
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GizmoSettings {
+    /// Axis length as a share of the viewport height, kept the same on
+    /// screen however far away the selection is
+    pub screen_size: f32,
+    /// Line width in points
+    pub line_thickness: f32,
+    /// How far from a handle, in points, a click still grabs it
+    pub hit_radius: f32,
+    /// Draw the gizmo over the scene. Off, it is drawn into the scene and
+    /// geometry in front of it hides it.
+    pub always_on_top: bool,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            screen_size: 0.2,
+            line_thickness: 2.0,
+            hit_radius: 10.0,
+            always_on_top: true,
+        }
+    }
+}
+
+/// Transform gizmo handles drawn into the scene, when they aren't drawn on top
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct TransformGizmos;
+
+const EDITOR_SETTINGS_PATH: &str = "editor_settings.ron";
+
+/// The editor settings kept between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct SavedEditorSettings {
+    ui_scale: f32,
+    palette_preset: PalettePreset,
+    palette: EditorPalette,
+    show_fps: bool,
+    show_debug_info: bool,
+    render_scale: f32,
+    asset_grid_view: bool,
+    tour: TourProgress,
+    gizmo: GizmoSettings,
+}
+
+impl Default for SavedEditorSettings {
+    fn default() -> Self {
+        Self::from(&EditorSettings::default())
+    }
+}
+
+impl From<&EditorSettings> for SavedEditorSettings {
+    fn from(settings: &EditorSettings) -> Self {
+        Self {
+            ui_scale: settings.theme.ui_scale,
+            palette_preset: settings.theme.palette_preset,
+            palette: settings.theme.palette,
+            show_fps: settings.show_fps,
+            show_debug_info: settings.show_debug_info,
+            render_scale: settings.render_scale,
+            asset_grid_view: settings.asset_grid_view,
+            tour: settings.tour,
+            gizmo: settings.gizmo,
+        }
+    }
+}
+
+impl EditorSettings {
+    pub fn load() -> Option<Self> {
+        let data = std::fs::read_to_string(EDITOR_SETTINGS_PATH).ok()?;
+        let saved: SavedEditorSettings = ron::de::from_str(&data).ok()?;
+        let mut settings = Self::default();
+        settings.theme.ui_scale = saved.ui_scale;
+        settings.theme.palette_preset = saved.palette_preset;
+        settings.theme.palette = saved.palette;
+        settings.show_fps = saved.show_fps;
+        settings.show_debug_info = saved.show_debug_info;
+        settings.render_scale = saved.render_scale;
+        settings.asset_grid_view = saved.asset_grid_view;
+        settings.tour = saved.tour;
+        settings.gizmo = saved.gizmo;
+        Some(settings)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(&SavedEditorSettings::from(self), ron::ser::PrettyConfig::default())?;
+        std::fs::write(EDITOR_SETTINGS_PATH, data)?;
+        Ok(())
+    }
+}
+
 /// Editor tab types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EditorTab {
@@ -167,6 +626,26 @@ pub enum EditorTab {
     Assets,
     Console,
     Profiler,
+    Tweens,
+    BehaviorTree,
+    Dialogue,
+    Quests,
+    Jobs,
+    Materials,
+    InputDebug,
+    Particles,
+    EcsStats,
+    Systems,
+    Resources,
+    /// Another inspector, following the selection or pinned to one entity.
+    /// Pins name entities of this session, so saved layouts drop them.
+    ExtraInspector {
+        id: u32,
+        #[serde(skip)]
+        pinned: Option<Entity>,
+    },
+    /// Panel registered by a project plugin, by id
+    Custom(String),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -189,6 +668,107 @@ pub enum AxisSpace {
     Global,
 }
 
+/// Tree being authored in the behavior tree editor tab
+pub struct BehaviorTreeEditorState {
+    pub tree: crate::core::ai::BehaviorTree,
+    pub path: String,
+    /// Child indices from the root to the selected node
+    pub selected: Option<Vec<usize>>,
+    pub status: String,
+}
+
+impl Default for BehaviorTreeEditorState {
+    fn default() -> Self {
+        Self {
+            tree: crate::core::ai::BehaviorTree::default(),
+            path: "assets/ai/new_tree.bt.ron".to_string(),
+            selected: None,
+            status: String::new(),
+        }
+    }
+}
+
+/// Graph being authored in the dialogue editor tab
+pub struct DialogueEditorState {
+    pub graph: crate::core::dialogue::DialogueGraph,
+    pub path: String,
+    /// Index of the selected node
+    pub selected: Option<usize>,
+    pub status: String,
+}
+
+impl Default for DialogueEditorState {
+    fn default() -> Self {
+        Self {
+            graph: crate::core::dialogue::DialogueGraph::default(),
+            path: "assets/dialogue/new_dialogue.dialogue.ron".to_string(),
+            selected: None,
+            status: String::new(),
+        }
+    }
+}
+
+/// File the particle editor tab saves the selected emitter to
+pub struct ParticleEditorState {
+    pub path: String,
+    pub status: String,
+}
+
+impl Default for ParticleEditorState {
+    fn default() -> Self {
+        Self {
+            path: "assets/particles/new_effect.particle".to_string(),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RotationDisplay {
+    Euler,
+    Quaternion,
+}
+
+/// Euler angles last typed into the inspector, reused while the rotation they produced is unchanged
+#[derive(Clone, Copy)]
+pub struct RotationEditCache {
+    pub entity: Entity,
+    pub space: AxisSpace,
+    pub rotation: Quat,
+    pub euler_degrees: Vec3,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ViewportLayout {
+    Single,
+    Quad,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ViewportView {
+    Perspective,
+    Ortho(OrthoView),
+}
+
+/// Per-pane state for the orthographic views of the quad layout.
+#[derive(Clone)]
+pub struct OrthoViewportState {
+    pub view: OrthoView,
+    pub size: Vec2,
+    pub zoom_delta: f32,
+    pub pan_delta: Vec2,
+    pub gizmo_overlay: Option<GizmoOverlay>,
+}
+
+/// Scene counters shown by the viewport stats overlay
+#[derive(Clone, Default)]
+pub struct ViewportStats {
+    pub entity_count: usize,
+    pub triangle_count: usize,
+    pub draw_call_count: usize,
+    pub camera_position: Option<Vec3>,
+}
+
 #[derive(Clone)]
 pub struct GizmoOverlay {
     pub origin: Vec2,
@@ -205,9 +785,68 @@ pub struct RotateOverlay {
     pub z_points: Vec<Vec2>,
 }
 
-#[derive(Resource, Default)]
+/// One message in the Output panel
+pub struct OutputEntry {
+    pub level: Level,
+    pub message: String,
+    /// Since the editor started, of the latest repeat
+    pub time: Duration,
+    /// Times the message was logged in a row
+    pub count: u32,
+}
+
+/// Log messages shown in the Output panel, oldest first. Once full the
+/// oldest messages are dropped.
+#[derive(Resource)]
 pub struct EditorOutput {
-    pub lines: Vec<String>,
+    entries: std::collections::VecDeque<OutputEntry>,
+    started: Instant,
+    /// Fold a message repeated in a row into one entry with a counter
+    pub collapse_repeated: bool,
+}
+
+impl Default for EditorOutput {
+    fn default() -> Self {
+        Self {
+            entries: std::collections::VecDeque::new(),
+            started: Instant::now(),
+            collapse_repeated: true,
+        }
+    }
+}
+
+impl EditorOutput {
+    pub const MAX_ENTRIES: usize = 100_000;
+
+    pub fn push(&mut self, level: Level, message: String) {
+        let time = self.started.elapsed();
+        if self.collapse_repeated {
+            if let Some(last) = self.entries.back_mut() {
+                if last.level == level && last.message == message {
+                    last.count += 1;
+                    last.time = time;
+                    return;
+                }
+            }
+        }
+        if self.entries.len() == Self::MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(OutputEntry {
+            level,
+            message,
+            time,
+            count: 1,
+        });
+    }
+
+    pub fn entries(&self) -> &std::collections::VecDeque<OutputEntry> {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
 }
 
 #[derive(Event)]
@@ -221,6 +860,240 @@ pub struct DeleteEntityEvent {
     pub entity: Entity,
 }
 
+#[derive(Event, Clone, Copy)]
+pub struct PivotEditEvent {
+    pub entity: Entity,
+    pub kind: PivotEditKind,
+}
+
+#[derive(Clone, Copy)]
+pub enum PivotEditKind {
+    /// Move the pivot by a local-space offset
+    Offset(Vec3),
+    CenterToBounds,
+    BottomOfBounds,
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct ConstraintEditEvent {
+    pub entity: Entity,
+    pub kind: ConstraintKind,
+    pub add: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    LookAt,
+    Follow,
+    StickToSurface,
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct CameraRigEditEvent {
+    pub entity: Entity,
+    pub part: CameraRigPart,
+    pub add: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CameraRigPart {
+    Dolly,
+    Crane,
+    Focus,
+}
+
+/// Attach a script to an entity, or detach it with `None`
+#[derive(Event, Clone)]
+pub struct LuaScriptEditEvent {
+    pub entity: Entity,
+    pub path: Option<String>,
+}
+
+/// Give an animated model a state machine file, or take it away with `None`
+#[derive(Event, Clone)]
+pub struct StateMachineEditEvent {
+    pub entity: Entity,
+    pub graph: Option<String>,
+}
+
+/// Add, restart or remove an entity's particle emitter from the inspector
+#[derive(Event, Clone)]
+pub struct ParticleEmitterEditEvent {
+    pub entity: Entity,
+    pub kind: ParticleEmitterEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum ParticleEmitterEditKind {
+    /// Add an emitter, or replace the settings of the one there
+    Set(ParticleEmitter),
+    /// Drop the living particles so the effect starts over
+    Restart,
+    Remove,
+}
+
+/// Add or remove an entity's terrain from the inspector
+#[derive(Event, Clone)]
+pub struct TerrainEditEvent {
+    pub entity: Entity,
+    pub kind: TerrainEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum TerrainEditKind {
+    Set(WaffleTerrain),
+    Remove,
+    /// Write the sculpted heights to an image asset and use it as the heightmap
+    SaveHeightmap(String),
+    UndoStroke,
+    RedoStroke,
+}
+
+/// Add or remove an entity's foliage layer from the inspector
+#[derive(Event, Clone)]
+pub struct FoliageEditEvent {
+    pub entity: Entity,
+    pub kind: FoliageEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum FoliageEditKind {
+    Set(FoliageLayer),
+    Remove,
+}
+
+/// Add or remove an entity's water surface from the inspector
+#[derive(Event, Clone)]
+pub struct WaterEditEvent {
+    pub entity: Entity,
+    pub kind: WaterEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum WaterEditKind {
+    Set(WaffleWater),
+    Remove,
+}
+
+/// Add or remove an entity's LOD group from the inspector
+#[derive(Event, Clone)]
+pub struct LodEditEvent {
+    pub entity: Entity,
+    pub kind: LodEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum LodEditKind {
+    Set(LodGroup),
+    Remove,
+}
+
+/// Change an entity's audio source from the inspector
+#[derive(Event, Clone)]
+pub struct AudioSourceEditEvent {
+    pub entity: Entity,
+    pub kind: AudioSourceEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum AudioSourceEditKind {
+    /// Attach a source, or swap the clip of the existing one
+    SetClip(String),
+    Remove,
+    Preview,
+    StopPreview,
+}
+
+/// Write an asset's import settings to its sidecar and load it again
+#[derive(Event, Clone)]
+pub struct ReimportAssetEvent {
+    pub path: String,
+    pub meta: AssetMeta,
+}
+
+/// Save the inspected material to a `.wmat` file, or swap in one
+#[derive(Event, Clone)]
+pub struct MaterialEditEvent {
+    /// The inspected entity; `None` for a material selected in the asset browser
+    pub entity: Option<Entity>,
+    /// The material the inspector shows, which may belong to a child
+    pub material: Handle<StandardMaterial>,
+    pub kind: MaterialEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum MaterialEditKind {
+    /// Write the material to this asset path; everything using it then uses the file
+    Save(String),
+    /// Use the material file, or model material, at this asset path on the inspected entity
+    Apply(String),
+}
+
+/// Changes requested from the material library tab
+#[derive(Event, Clone)]
+pub enum MaterialLibraryEvent {
+    /// Add a default material to the library
+    Create { name: String },
+    /// Give every mesh in these entities' subtrees a library material
+    Apply { index: usize, entities: Vec<Entity> },
+}
+
+/// Debug draw label positioned in viewport pixels
+#[derive(Clone)]
+pub struct DebugLabel {
+    pub position: Vec2,
+    pub text: String,
+    pub color: Color,
+}
+
+/// Tag the inspected entity, or its material, with what it is made of
+#[derive(Event, Clone)]
+pub struct SurfaceEditEvent {
+    pub entity: Entity,
+    pub kind: SurfaceEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum SurfaceEditKind {
+    /// Tag the entity and everything under it; `None` removes the tag
+    Entity(Option<PhysicalSurface>),
+    /// Tag a material; `None` falls back on its `.wmat` file's surface
+    Material(Handle<StandardMaterial>, Option<PhysicalSurface>),
+}
+
+#[derive(Event, Clone)]
+pub struct KeyframeEditEvent {
+    pub entity: Entity,
+    pub kind: KeyframeEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum KeyframeEditKind {
+    /// Key the entity's current values of `properties` at `time`, giving it
+    /// an animation if it has none
+    Record {
+        properties: Vec<KeyedProperty>,
+        time: f32,
+        interpolation: KeyInterpolation,
+    },
+    Remove,
+}
+
+#[derive(Event, Clone)]
+pub struct RenderLayersEditEvent {
+    pub entity: Entity,
+    pub layers: RenderLayers,
+    pub include_children: bool,
+}
+
+/// Share the inspected entity's identical meshes and materials with other
+/// instanced entities, or stop
+#[derive(Event, Clone)]
+pub struct InstancingEditEvent {
+    pub entity: Entity,
+    pub instanced: bool,
+}
+
 #[derive(Event, Clone)]
 pub struct SpawnPrimitiveEvent {
     pub kind: SpawnPrimitiveKind,
@@ -233,7 +1106,7 @@ pub struct SpawnAssetEvent {
     pub parent: Option<Entity>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SpawnPrimitiveKind {
     Empty,
     Cube,
@@ -242,12 +1115,29 @@ pub enum SpawnPrimitiveKind {
     DirectionalLight,
     PointLight,
     SpotLight,
+    AoVolume,
+    DollyTrack,
+    Portal,
+    Mirror,
+    Terrain,
+    Foliage,
+    Water,
+}
+
+/// How an editor-created entity was made, so it can be recreated elsewhere
+#[derive(Component, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpawnSource {
+    Primitive(SpawnPrimitiveKind),
+    Asset(String),
 }
 
 #[derive(Clone)]
 pub struct AssetEntry {
     pub path: String,
     pub kind: AssetKind,
+    pub modified: Option<std::time::SystemTime>,
+    /// Meshes and materials inside a glTF or OBJ model
+    pub sub_assets: Vec<SubAsset>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -265,6 +1155,7 @@ pub struct AssetBrowserCache {
     pub(crate) root: PathBuf,
     pub(crate) entries: Vec<AssetEntry>,
     last_scan: Option<Instant>,
+    scan: Option<JobHandle<Vec<AssetEntry>>>,
 }
 
 impl Default for AssetBrowserCache {
@@ -273,6 +1164,7 @@ impl Default for AssetBrowserCache {
             root: PathBuf::from("assets"),
             entries: Vec::new(),
             last_scan: None,
+            scan: None,
         }
     }
 }
@@ -281,6 +1173,7 @@ impl Default for AssetBrowserCache {
 fn setup_editor(
     mut commands: Commands,
     mut editor_state: ResMut<EditorState>,
+    mut editor_settings: ResMut<EditorSettings>,
 ) {
     info!("Setting up Waffle Engine Editor");
 
@@ -293,47 +1186,114 @@ fn setup_editor(
     if let Some(loaded) = load_layout() {
         editor_state.dock_state = loaded;
     }
+    if let Some(loaded) = EditorSettings::load() {
+        *editor_settings = loaded;
+    }
     editor_state.layout_cache = ron::ser::to_string(&editor_state.dock_state).unwrap_or_default();
 }
 
 #[derive(SystemParam)]
 struct EditorUiWorldParams<'w, 's> {
-    queries: ParamSet<'w, 's, (
-        Query<'w, 's, (Entity, Option<&'static Name>, Option<&'static Parent>), Without<EditorHidden>>,
-        Query<'w, 's, &'static mut Name>,
-    )>,
-    scene_root_query: Query<'w, 's, Entity, With<WaffleSceneRoot>>,
-    transform_query: Query<'w, 's, &'static mut Transform>,
+    hierarchy: ResMut<'w, HierarchySnapshot>,
+    inspector: InspectorQueries<'w, 's>,
     global_transform_query: Query<'w, 's, &'static GlobalTransform>,
-    material_handle_query: Query<'w, 's, &'static Handle<StandardMaterial>>,
-    pbr_overrides_query: Query<'w, 's, &'static mut PbrTextureOverrides>,
-    environment_query: Query<'w, 's, &'static mut EnvironmentSettings>,
-    atmosphere_query: Query<'w, 's, &'static mut AtmosphereSettingsComponent>,
+    parent_query: Query<'w, 's, &'static Parent>,
     material_assets: ResMut<'w, Assets<StandardMaterial>>,
+    material_library: Res<'w, crate::rendering::materials::MaterialLibrary>,
     asset_server: Res<'w, AssetServer>,
     images: ResMut<'w, Assets<Image>>,
     meshes: Res<'w, Assets<Mesh>>,
-    waffle_light_query: Query<'w, 's, &'static mut crate::rendering::lighting::WaffleLight>,
-    directional_light_query: Query<'w, 's, &'static mut DirectionalLight>,
-    point_light_query: Query<'w, 's, &'static mut PointLight>,
-    spot_light_query: Query<'w, 's, &'static mut SpotLight>,
+    terrain_chunk_query: Query<'w, 's, (&'static TerrainChunk, &'static GlobalTransform)>,
+    portal_view_query: Query<'w, 's, &'static PortalView>,
+    project_settings: ResMut<'w, ProjectSettings>,
+    play_state: Res<'w, State<PlayState>>,
+    next_play_state: ResMut<'w, NextState<PlayState>>,
+    play_session: ResMut<'w, PlaySession>,
+    game_cursor: ResMut<'w, GameCursor>,
+    camera_settings: Option<Res<'w, CameraSettings>>,
+    debug_texts: Res<'w, crate::core::debug_draw::DebugTextQueue>,
+    debug_draw_settings: ResMut<'w, crate::core::debug_draw::DebugDrawSettings>,
+    replay_session: ResMut<'w, crate::core::replay::ReplaySession>,
     diagnostics: Res<'w, bevy::diagnostic::DiagnosticsStore>,
+    tweens: ResMut<'w, crate::core::tween::Tweens>,
+    quest_log: ResMut<'w, crate::core::quest::QuestLog>,
+    game_variables: ResMut<'w, crate::core::variables::GameVariables>,
+    entity_pools: Res<'w, crate::core::pool::EntityPools>,
+    instancing_stats: Res<'w, InstancingStats>,
     window_query: Query<'w, 's, (), With<bevy::window::PrimaryWindow>>,
     asset_cache: ResMut<'w, AssetBrowserCache>,
+    asset_thumbnails: ResMut<'w, AssetThumbnails>,
     viewport_target: ResMut<'w, ViewportRenderTarget>,
     reparent_events: EventWriter<'w, HierarchyReparentEvent>,
     delete_events: EventWriter<'w, DeleteEntityEvent>,
+    restore_events: EventWriter<'w, RestoreDeletedEvent>,
+    empty_trash_events: EventWriter<'w, EmptyTrashEvent>,
+    trash: Res<'w, EditorTrash>,
     spawn_primitive_events: EventWriter<'w, SpawnPrimitiveEvent>,
     spawn_asset_events: EventWriter<'w, SpawnAssetEvent>,
+    pivot_edit_events: EventWriter<'w, PivotEditEvent>,
+    constraint_edit_events: EventWriter<'w, ConstraintEditEvent>,
+    camera_rig_edit_events: EventWriter<'w, CameraRigEditEvent>,
+    lua_script_edit_events: EventWriter<'w, LuaScriptEditEvent>,
+    state_machine_edit_events: EventWriter<'w, StateMachineEditEvent>,
+    particle_emitter_edit_events: EventWriter<'w, ParticleEmitterEditEvent>,
+    terrain_edit_events: EventWriter<'w, TerrainEditEvent>,
+    foliage_edit_events: EventWriter<'w, FoliageEditEvent>,
+    water_edit_events: EventWriter<'w, WaterEditEvent>,
+    lod_edit_events: EventWriter<'w, LodEditEvent>,
+    sub_scene_edit_events: EventWriter<'w, SubSceneEditEvent>,
+    audio_source_edit_events: EventWriter<'w, AudioSourceEditEvent>,
+    material_edit_events: EventWriter<'w, MaterialEditEvent>,
+    material_library_events: EventWriter<'w, MaterialLibraryEvent>,
+    render_layers_edit_events: EventWriter<'w, RenderLayersEditEvent>,
+    instancing_edit_events: EventWriter<'w, InstancingEditEvent>,
+    surface_edit_events: EventWriter<'w, SurfaceEditEvent>,
+    keyframe_edit_events: EventWriter<'w, KeyframeEditEvent>,
+    locate_missing_events: EventWriter<'w, LocateMissingAssetEvent>,
+    bake_ao_volume_events: EventWriter<'w, BakeAoVolumeEvent>,
+    camera_shake_events: EventWriter<'w, CameraShakeEvent>,
+    preview_camera_shake_events: EventWriter<'w, PreviewCameraShakeEvent>,
+    open_external_events: EventWriter<'w, OpenExternalEvent>,
+    external_tools: ResMut<'w, ExternalToolSettings>,
+    vcs_status: Res<'w, VcsStatus>,
+    vcs_action_events: EventWriter<'w, VcsActionEvent>,
+    asset_file_events: EventWriter<'w, AssetFileEvent>,
+    reimport_events: EventWriter<'w, ReimportAssetEvent>,
+    scene_file_events: EventWriter<'w, SceneFileEvent>,
+    engine_state: Res<'w, crate::core::resources::EngineState>,
+    collab_session: ResMut<'w, CollabSession>,
+    collab_id_query: Query<'w, 's, (Entity, &'static CollabId)>,
+    extensions: ResMut<'w, EditorExtensions>,
+    disabled_systems: ResMut<'w, crate::core::guard::DisabledSystems>,
+    shader_warmup: ResMut<'w, crate::rendering::warmup::ShaderWarmup>,
+    editor_jobs: Res<'w, EditorJobs>,
+    input_debug_log: ResMut<'w, InputDebugLog>,
+    ecs_stats: ResMut<'w, EcsStats>,
+    systems_panel: ResMut<'w, SystemsPanel>,
+    system_toggles: ResMut<'w, crate::core::system_toggles::SystemToggles>,
+    resources_panel: ResMut<'w, ResourcesPanel>,
+    gamepad_inputs: GamepadInputs<'w>,
+    hdr_output_status: Res<'w, crate::rendering::hdr::HdrOutputStatus>,
+    active_tool: ResMut<'w, ActiveTool>,
+    tool_click_events: EventWriter<'w, ViewportToolClickEvent>,
     keyboard_input: Res<'w, ButtonInput<KeyCode>>,
     mouse_input: Res<'w, ButtonInput<MouseButton>>,
     file_drop_events: EventReader<'w, 's, FileDragAndDrop>,
     camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<WaffleMainCamera>>,
+    ortho_camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform, &'static WaffleOrthoCamera)>,
+    ortho_targets: ResMut<'w, OrthoViewTargets>,
     mesh_query: Query<'w, 's, (Entity, &'static GlobalTransform, &'static Handle<Mesh>), Without<EditorHidden>>,
+    visible_mesh_query: Query<
+        'w,
+        's,
+        (&'static Handle<Mesh>, Option<&'static Handle<StandardMaterial>>, &'static ViewVisibility),
+    >,
+    entities: &'w bevy::ecs::entity::Entities,
 }
 
 /// Main editor UI update system
 fn update_editor_ui(
+    mut commands: Commands,
     mut contexts: EguiContexts,
     mut editor_state: ResMut<EditorState>,
     mut editor_settings: ResMut<EditorSettings>,
@@ -350,6 +1310,56 @@ fn update_editor_ui(
         None => Some(contexts.add_image(world.viewport_target.image.clone())),
     };
 
+    let ortho_texture_ids: Vec<(OrthoView, egui::TextureId)> = world
+        .ortho_targets
+        .targets
+        .iter()
+        .map(|target| {
+            let texture_id = match contexts.image_id(&target.image) {
+                Some(texture_id) => texture_id,
+                None => contexts.add_image(target.image.clone()),
+            };
+            (target.view, texture_id)
+        })
+        .collect();
+
+    // Previews of the portals the inspectors show, the selected one and any pinned
+    let mut portal_previews: HashMap<Entity, egui::TextureId> = HashMap::new();
+    let inspected = editor_state
+        .selection
+        .primary()
+        .into_iter()
+        .chain(pinned_inspector_entities(&editor_state.dock_state));
+    for entity in inspected {
+        let Some(image) = world.portal_view_query.get(entity).ok().and_then(|view| view.images.first()) else {
+            continue;
+        };
+        let texture_id = match contexts.image_id(image) {
+            Some(texture_id) => texture_id,
+            None => contexts.add_image(image.clone()),
+        };
+        portal_previews.insert(entity, texture_id);
+    }
+
+    for image in world.asset_thumbnails.take_retired() {
+        contexts.remove_image(&image);
+    }
+    let asset_thumbnail_ids: HashMap<String, (egui::TextureId, egui::Vec2)> = if editor_settings.asset_grid_view {
+        world
+            .asset_thumbnails
+            .ready()
+            .map(|(path, image, size)| {
+                let texture_id = match contexts.image_id(image) {
+                    Some(texture_id) => texture_id,
+                    None => contexts.add_image(image.clone()),
+                };
+                (path.to_string(), (texture_id, egui::vec2(size.x as f32, size.y as f32)))
+            })
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
     let Some(ctx) = contexts.try_ctx_mut() else {
         return;
     };
@@ -358,118 +1368,194 @@ fn update_editor_ui(
     editor_settings.theme.apply(ctx);
 
     let mut dock_state = std::mem::replace(&mut editor_state.dock_state, DockState::new(Vec::new()));
+    let mut tour_targets = TourTargets::default();
 
-    let hierarchy = {
-        let entity_query = world.queries.p0();
-        let root = world.scene_root_query.get_single().ok();
-        build_hierarchy_snapshot(&entity_query, root)
-    };
+    if world.collab_session.is_active() {
+        let entities = world.collab_id_query.iter().map(|(entity, id)| (*id, entity)).collect();
+        world.hierarchy.presence = world.collab_session.presence_tags(&entities);
+    } else if !world.hierarchy.presence.is_empty() {
+        world.hierarchy.presence.clear();
+    }
+    let hierarchy = &*world.hierarchy;
     let mut reparent_queue: Vec<HierarchyReparentEvent> = Vec::new();
     let mut spawn_primitive_queue: Vec<SpawnPrimitiveEvent> = Vec::new();
     let mut spawn_asset_queue: Vec<SpawnAssetEvent> = Vec::new();
-
-    let selected_entity = editor_state.selected_entity;
-
-    let mut selected_transform = selected_entity
-        .and_then(|entity| world.transform_query.get_mut(entity).ok());
-    let mut name_query = world.queries.p1();
-    let mut selected_name = selected_entity
-        .and_then(|entity| name_query.get_mut(entity).ok());
-    let selected_material_entity = selected_entity.and_then(|entity| {
-        if world.material_handle_query.get(entity).is_ok() {
-            return Some(entity);
-        }
-        find_descendant_with_material(entity, &hierarchy, &world.material_handle_query)
-    });
-    let selected_material_handle = selected_material_entity
-        .and_then(|entity| world.material_handle_query.get(entity).ok().cloned());
-    let mut selected_overrides = selected_material_entity
-        .and_then(|entity| world.pbr_overrides_query.get_mut(entity).ok());
-    let mut selected_environment = selected_entity
-        .and_then(|entity| world.environment_query.get_mut(entity).ok());
-    let mut selected_atmosphere = selected_entity
-        .and_then(|entity| world.atmosphere_query.get_mut(entity).ok());
-    let mut selected_waffle_light = selected_entity
-        .and_then(|entity| world.waffle_light_query.get_mut(entity).ok());
-    let mut selected_directional_light = selected_entity
-        .and_then(|entity| world.directional_light_query.get_mut(entity).ok());
-    let mut selected_point_light = selected_entity
-        .and_then(|entity| world.point_light_query.get_mut(entity).ok());
-    let mut selected_spot_light = selected_entity
-        .and_then(|entity| world.spot_light_query.get_mut(entity).ok());
-
-    handle_file_drops(&mut world.file_drop_events, &mut world.asset_cache);
+    let mut pivot_edit_queue: Vec<PivotEditEvent> = Vec::new();
+    let mut constraint_edit_queue: Vec<ConstraintEditEvent> = Vec::new();
+    let mut camera_rig_edit_queue: Vec<CameraRigEditEvent> = Vec::new();
+    let mut lua_script_edit_queue: Vec<LuaScriptEditEvent> = Vec::new();
+    let mut state_machine_edit_queue: Vec<StateMachineEditEvent> = Vec::new();
+    let mut particle_emitter_edit_queue: Vec<ParticleEmitterEditEvent> = Vec::new();
+    let mut terrain_edit_queue: Vec<TerrainEditEvent> = Vec::new();
+    let mut foliage_edit_queue: Vec<FoliageEditEvent> = Vec::new();
+    let mut water_edit_queue: Vec<WaterEditEvent> = Vec::new();
+    let mut lod_edit_queue: Vec<LodEditEvent> = Vec::new();
+    let mut sub_scene_edit_queue: Vec<SubSceneEditEvent> = Vec::new();
+    let mut audio_source_edit_queue: Vec<AudioSourceEditEvent> = Vec::new();
+    let mut material_edit_queue: Vec<MaterialEditEvent> = Vec::new();
+    let mut material_library_queue: Vec<MaterialLibraryEvent> = Vec::new();
+    let mut render_layers_edit_queue: Vec<RenderLayersEditEvent> = Vec::new();
+    let mut instancing_edit_queue: Vec<InstancingEditEvent> = Vec::new();
+    let mut surface_edit_queue: Vec<SurfaceEditEvent> = Vec::new();
+    let mut keyframe_edit_queue: Vec<KeyframeEditEvent> = Vec::new();
+    let mut reimport_queue: Vec<ReimportAssetEvent> = Vec::new();
+    let mut locate_missing_queue: Vec<LocateMissingAssetEvent> = Vec::new();
+    let mut bake_ao_volume_queue: Vec<BakeAoVolumeEvent> = Vec::new();
+    let mut camera_shake_queue: Vec<CameraShakeEvent> = Vec::new();
+    let mut preview_camera_shake_queue: Vec<PreviewCameraShakeEvent> = Vec::new();
+    let mut pin_inspector_queue: Vec<Entity> = Vec::new();
+    let mut open_external_queue: Vec<OpenExternalEvent> = Vec::new();
+    let mut vcs_action_queue: Vec<VcsActionEvent> = Vec::new();
+    let mut extension_commands = CommandQueue::default();
+
+    let selected_entity = editor_state.selection.primary();
+
+    handle_file_drops(&mut world.file_drop_events, &world.asset_cache, &world.editor_jobs);
 
     editor_state.gizmo_overlay = None;
-    if let Some(selected) = selected_entity {
+    for pane in editor_state.ortho_viewports.iter_mut() {
+        pane.gizmo_overlay = None;
+    }
+    let gizmo_pivot = selected_entity
+        .and_then(|entity| world.global_transform_query.get(entity).ok())
+        .map(|primary| {
+            selection_gizmo_pivot(primary, &editor_state.selection, &world.parent_query, &world.global_transform_query)
+        });
+    let gizmo_settings = editor_settings.gizmo;
+    editor_state.gizmo_placement = None;
+    if let Some(transform) = gizmo_pivot.as_ref() {
+        let axis_space = editor_state.axis_space;
         if let Ok((camera, camera_transform)) = world.camera_query.get_single() {
-            if let Ok(transform) = world.global_transform_query.get(selected) {
-                let origin = transform.translation();
-                let distance = camera_transform.translation().distance(origin);
-                let axis_length = (distance * 0.2).clamp(0.5, 5.0);
-                let basis = match editor_state.axis_space {
-                    AxisSpace::Global => Mat3::IDENTITY,
-                    AxisSpace::Local => Mat3::from_quat(transform.compute_transform().rotation),
-                };
-                let axis_x = basis * Vec3::X;
-                let axis_y = basis * Vec3::Y;
-                let axis_z = basis * Vec3::Z;
-                if let (Some(origin_screen), Some(x_end), Some(y_end), Some(z_end)) = (
-                    camera.world_to_viewport(camera_transform, origin),
-                    camera.world_to_viewport(camera_transform, origin + axis_x * axis_length),
-                    camera.world_to_viewport(camera_transform, origin + axis_y * axis_length),
-                    camera.world_to_viewport(camera_transform, origin + axis_z * axis_length),
-                ) {
-                    let rotate_rings = build_rotate_overlay(
-                        camera,
-                        camera_transform,
-                        origin,
-                        axis_x,
-                        axis_y,
-                        axis_z,
-                        axis_length,
-                    );
-                    editor_state.gizmo_overlay = Some(GizmoOverlay {
-                        origin: origin_screen,
-                        x_end,
-                        y_end,
-                        z_end,
-                        rotate_rings,
+            let axis_length =
+                screen_constant_length(camera, camera_transform, transform.translation(), gizmo_settings.screen_size);
+            if let Some(axis_length) = axis_length {
+                editor_state.gizmo_overlay =
+                    build_gizmo_overlay(camera, camera_transform, transform, axis_space, axis_length);
+                let basis = gizmo_basis(transform, axis_space);
+                editor_state.gizmo_placement = Some((transform.translation(), basis, axis_length));
+            }
+        }
+        // Handles drawn into the scene are one size in every view, so the
+        // orthographic views pick against the perspective view's size
+        let scene_length = editor_state
+            .gizmo_placement
+            .filter(|_| !gizmo_settings.always_on_top)
+            .map(|(_, _, length)| length);
+        if editor_state.viewport_layout == ViewportLayout::Quad {
+            for (camera, camera_transform, ortho) in world.ortho_camera_query.iter() {
+                let overlay = scene_length
+                    .or_else(|| {
+                        screen_constant_length(
+                            camera,
+                            camera_transform,
+                            transform.translation(),
+                            gizmo_settings.screen_size,
+                        )
+                    })
+                    .and_then(|axis_length| {
+                        build_gizmo_overlay(camera, camera_transform, transform, axis_space, axis_length)
                     });
+                if let Some(pane) = editor_state
+                    .ortho_viewports
+                    .iter_mut()
+                    .find(|pane| pane.view == ortho.view)
+                {
+                    pane.gizmo_overlay = overlay;
                 }
             }
         }
     }
 
+    let mut debug_labels = collect_debug_labels(&world.debug_texts, &world.camera_query);
+    if let Some(distance) = world.active_tool.measured_distance() {
+        let midpoint = world.active_tool.measure_points.iter().sum::<Vec3>() / 2.0;
+        let position = world
+            .camera_query
+            .get_single()
+            .ok()
+            .and_then(|(camera, camera_transform)| camera.world_to_viewport(camera_transform, midpoint));
+        if let Some(position) = position {
+            debug_labels.push(DebugLabel {
+                position,
+                text: world.project_settings.units.format(distance),
+                color: Color::srgb(1.0, 0.85, 0.2),
+            });
+        }
+    }
+
+    let viewport_stats = editor_settings.show_debug_info.then(|| {
+        collect_viewport_stats(
+            world.entities,
+            &world.visible_mesh_query,
+            &world.meshes,
+            &world.camera_query,
+        )
+    });
+
     // Main editor window
     egui::CentralPanel::default().show(ctx, |ui| {
         if !ctx.wants_keyboard_input() && !world.mouse_input.pressed(MouseButton::Right) {
-            if world.keyboard_input.just_pressed(KeyCode::KeyQ) {
-                editor_state.gizmo_mode = GizmoMode::Move;
-            }
-            if world.keyboard_input.just_pressed(KeyCode::KeyW) {
-                editor_state.gizmo_mode = GizmoMode::Rotate;
+            for tool in EditorTool::BUILT_IN {
+                if tool.shortcut().is_some_and(|(key, _)| world.keyboard_input.just_pressed(key)) {
+                    world.active_tool.set(tool);
+                }
             }
-            if world.keyboard_input.just_pressed(KeyCode::KeyE) {
-                editor_state.gizmo_mode = GizmoMode::Scale;
+            let shift = world.keyboard_input.pressed(KeyCode::ShiftLeft)
+                || world.keyboard_input.pressed(KeyCode::ShiftRight);
+            if shift && world.keyboard_input.just_pressed(KeyCode::KeyH) {
+                editor_state.isolate_selection = !editor_state.isolate_selection;
             }
         }
         // Menu bar
         ui.horizontal(|ui| {
             ui.menu_button("File", |ui| {
+                if ui.button("New Project...").clicked() {
+                    editor_state.new_project_dialog = Some(NewProjectDialog::default());
+                    ui.close_menu();
+                }
+                ui.separator();
                 if ui.button("New Scene").clicked() {
                     // TODO: New scene
                 }
-                if ui.button("Open Scene").clicked() {
-                    // TODO: Open scene dialog
+                let current_scene = world.engine_state.current_scene.clone();
+                if ui.button("Open Scene...").clicked() {
+                    editor_state.scene_file_dialog =
+                        Some(SceneFileDialog::new(SceneFileAction::Open, current_scene.as_deref()));
+                    ui.close_menu();
                 }
                 if ui.button("Save Scene").clicked() {
-                    // TODO: Save scene
+                    match current_scene.clone() {
+                        Some(name) => {
+                            world.scene_file_events.send(SceneFileEvent::Save(name));
+                        }
+                        None => {
+                            editor_state.scene_file_dialog = Some(SceneFileDialog::new(SceneFileAction::Save, None));
+                        }
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("Save Scene As...").clicked() {
+                    editor_state.scene_file_dialog =
+                        Some(SceneFileDialog::new(SceneFileAction::Save, current_scene.as_deref()));
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Compare Scenes...").clicked() {
+                    editor_state.scene_diff =
+                        Some(SceneDiffWindow::new(SceneDiffMode::Compare, current_scene.as_deref()));
+                    ui.close_menu();
+                }
+                if ui.button("Merge Scenes...").clicked() {
+                    editor_state.scene_diff =
+                        Some(SceneDiffWindow::new(SceneDiffMode::Merge, current_scene.as_deref()));
+                    ui.close_menu();
                 }
                 ui.separator();
                 if ui.button("Exit").clicked() {
                     // TODO: Exit application
                 }
+                let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                world.extensions.draw_menu_items("File", ui, &mut extension_ctx);
             });
 
             ui.menu_button("Edit", |ui| {
@@ -479,18 +1565,88 @@ fn update_editor_ui(
                 if ui.button("Redo").clicked() {
                     // TODO: Redo
                 }
+                ui.separator();
+                let last_deleted = world.trash.entries.last().map(|entry| entry.entity);
+                if ui
+                    .add_enabled(last_deleted.is_some(), egui::Button::new("Undo Delete"))
+                    .clicked()
+                {
+                    if let Some(entity) = last_deleted {
+                        world.restore_events.send(RestoreDeletedEvent { entity });
+                    }
+                    ui.close_menu();
+                }
+                ui.add_enabled_ui(!world.trash.entries.is_empty(), |ui| {
+                    ui.menu_button("Recently Deleted", |ui| {
+                        for entry in world.trash.entries.iter().rev() {
+                            if ui.button(&entry.label).clicked() {
+                                world.restore_events.send(RestoreDeletedEvent { entity: entry.entity });
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Empty Trash").clicked() {
+                            world.empty_trash_events.send(EmptyTrashEvent);
+                            ui.close_menu();
+                        }
+                    });
+                });
+                ui.separator();
+                if ui.button("Project Settings...").clicked() {
+                    editor_state.show_project_settings = true;
+                    ui.close_menu();
+                }
+                if ui.button("External Tools...").clicked() {
+                    editor_state.show_external_tools = true;
+                    ui.close_menu();
+                }
+                if ui.button("Preferences...").clicked() {
+                    editor_state.show_preferences = true;
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        !world.extensions.settings_pages.is_empty() || !world.disabled_systems.systems.is_empty(),
+                        egui::Button::new("Plugin Settings..."),
+                    )
+                    .clicked()
+                {
+                    editor_state.show_plugin_settings = true;
+                    ui.close_menu();
+                }
+                let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                world.extensions.draw_menu_items("Edit", ui, &mut extension_ctx);
             });
 
             ui.menu_button("View", |ui| {
+                let mut quad_view = editor_state.viewport_layout == ViewportLayout::Quad;
+                if ui.checkbox(&mut quad_view, "Quad View").clicked() {
+                    editor_state.viewport_layout = if quad_view {
+                        ViewportLayout::Quad
+                    } else {
+                        ViewportLayout::Single
+                    };
+                }
                 if ui.checkbox(&mut editor_settings.show_fps, "Show FPS").clicked() {
                     // TODO: Toggle FPS display
                 }
-                if ui.checkbox(&mut editor_settings.show_debug_info, "Debug Info").clicked() {
-                    // TODO: Toggle debug info
-                }
+                ui.checkbox(&mut editor_settings.show_debug_info, "Stats Overlay");
+                draw_render_scale_settings(ui, &mut editor_settings);
+                ui.add_enabled(
+                    cfg!(debug_assertions),
+                    egui::Checkbox::new(&mut world.debug_draw_settings.enabled, "Debug Draw"),
+                )
+                .on_disabled_hover_text("Debug draw is compiled out of release builds");
+                ui.checkbox(&mut editor_settings.show_physics_debug, "Physics Debug")
+                    .on_hover_text("Collision bounds, contacts and velocities of every body");
+                ui.checkbox(&mut editor_settings.show_safe_area, "Safe Area")
+                    .on_hover_text("Where game UI stays clear of the edges TVs crop");
+                ui.checkbox(&mut editor_state.isolate_selection, "Isolate Selection (Shift+H)");
                 if ui.checkbox(&mut editor_settings.grid_enabled, "Grid").clicked() {
                     // TODO: Toggle grid
                 }
+                let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                world.extensions.draw_menu_items("View", ui, &mut extension_ctx);
             });
 
             ui.menu_button("Tools", |ui| {
@@ -500,62 +1656,222 @@ fn update_editor_ui(
                 if ui.button("Asset Browser").clicked() {
                     // TODO: Open asset browser
                 }
-            });
-
-            ui.menu_button("Help", |ui| {
-                if ui.button("About").clicked() {
-                    // TODO: Show about dialog
+                // The dock is taken out of the editor state while the UI is drawn.
+                if ui.button("Behavior Tree Editor").clicked() {
+                    open_tab(&mut dock_state, EditorTab::BehaviorTree);
+                    ui.close_menu();
                 }
-            });
+                if ui.button("Dialogue Editor").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Dialogue);
+                    ui.close_menu();
+                }
+                if ui.button("Particle Editor").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Particles);
+                    ui.close_menu();
+                }
+                if ui.button("Tweens").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Tweens);
+                    ui.close_menu();
+                }
+                if ui.button("Quests").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Quests);
+                    ui.close_menu();
+                }
+                if ui.button("Jobs").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Jobs);
+                    ui.close_menu();
+                }
+                if ui.button("Material Library").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Materials);
+                    ui.close_menu();
+                }
+                if ui.button("Input Debug").clicked() {
+                    open_tab(&mut dock_state, EditorTab::InputDebug);
+                    ui.close_menu();
+                }
+                if ui.button("ECS Stats").clicked() {
+                    open_tab(&mut dock_state, EditorTab::EcsStats);
+                    ui.close_menu();
+                }
+                if ui.button("Systems").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Systems);
+                    ui.close_menu();
+                }
+                if ui.button("Resources").clicked() {
+                    open_tab(&mut dock_state, EditorTab::Resources);
+                    ui.close_menu();
+                }
+                if ui.button("New Inspector").clicked() {
+                    open_extra_inspector(&mut dock_state, None);
+                    ui.close_menu();
+                }
+                for panel in &world.extensions.panels {
+                    if ui.button(&panel.title).clicked() {
+                        open_tab(&mut dock_state, EditorTab::Custom(panel.id.clone()));
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(!world.shader_warmup.is_running(), egui::Button::new("Warm Up Shaders"))
+                    .on_hover_text("Compile pipelines for every material in the scene and project")
+                    .clicked()
+                {
+                    world.shader_warmup.request();
+                    ui.close_menu();
+                }
+                if ui.button("Collaboration...").clicked() {
+                    editor_state.show_collaboration = true;
+                    ui.close_menu();
+                }
+                let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                world.extensions.draw_menu_items("Tools", ui, &mut extension_ctx);
+            });
+
+            for menu in world.extensions.extra_menus() {
+                ui.menu_button(&menu, |ui| {
+                    let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                    world.extensions.draw_menu_items(&menu, ui, &mut extension_ctx);
+                });
+            }
+
+            ui.menu_button("Help", |ui| {
+                if ui.button("Start Tour").clicked() {
+                    editor_settings.tour = TourProgress::default();
+                    ui.close_menu();
+                }
+                if ui.button("About").clicked() {
+                    // TODO: Show about dialog
+                }
+                let mut extension_ctx = EditorExtensionContext::new(selected_entity, &mut extension_commands);
+                world.extensions.draw_menu_items("Help", ui, &mut extension_ctx);
+            });
         });
 
         ui.separator();
 
-        ui.horizontal(|ui| {
-            let _ = ui.button(">");
-            let _ = ui.button("||");
-            let _ = ui.button("[]");
-            ui.separator();
+        let toolbar = ui.horizontal(|ui| {
+            let playing = *world.play_state.get() == PlayState::Playing;
+            let paused = playing && world.play_session.paused;
+            let play_hint = if paused { "Resume" } else { "Play" };
             if ui
-                .selectable_label(editor_state.gizmo_mode == GizmoMode::Move, "Move")
+                .add_enabled(!playing || paused, egui::Button::new(">"))
+                .on_hover_text(play_hint)
+                .accessible_name(play_hint)
                 .clicked()
             {
-                editor_state.gizmo_mode = GizmoMode::Move;
+                if paused {
+                    world.play_session.paused = false;
+                } else {
+                    world.next_play_state.set(PlayState::Playing);
+                }
             }
             if ui
-                .selectable_label(editor_state.gizmo_mode == GizmoMode::Rotate, "Rotate")
+                .add_enabled(playing, egui::Button::new("||").selected(paused))
+                .on_hover_text("Pause")
+                .accessible_name("Pause")
                 .clicked()
             {
-                editor_state.gizmo_mode = GizmoMode::Rotate;
+                world.play_session.paused = !paused;
             }
             if ui
-                .selectable_label(editor_state.gizmo_mode == GizmoMode::Scale, "Scale")
+                .add_enabled(playing, egui::Button::new("[]"))
+                .on_hover_text("Stop; changes made while playing are discarded")
+                .accessible_name("Stop")
                 .clicked()
             {
-                editor_state.gizmo_mode = GizmoMode::Scale;
+                world.next_play_state.set(PlayState::Editing);
             }
             ui.separator();
+            draw_replay_controls(ui, &mut world.replay_session, &mut world.next_play_state, playing);
+            ui.separator();
             if ui
                 .selectable_label(editor_state.axis_space == AxisSpace::Global, "Global")
+                .accessible_name("Global axes")
                 .clicked()
             {
                 editor_state.axis_space = AxisSpace::Global;
             }
             if ui
                 .selectable_label(editor_state.axis_space == AxisSpace::Local, "Local")
+                .accessible_name("Local axes")
                 .clicked()
             {
                 editor_state.axis_space = AxisSpace::Local;
             }
+            ui.separator();
+            ui.checkbox(&mut editor_settings.snap_enabled, "Snap");
+            let preset_name = world
+                .project_settings
+                .snap_preset(editor_settings.snap_preset)
+                .map(|preset| preset.name.clone())
+                .unwrap_or_else(|| "No presets".to_string());
+            egui::ComboBox::from_id_source("snap_preset")
+                .selected_text(preset_name)
+                .show_ui(ui, |ui| {
+                    for (index, preset) in world.project_settings.snap_presets.iter().enumerate() {
+                        ui.selectable_value(&mut editor_settings.snap_preset, index, &preset.name);
+                    }
+                })
+                .response
+                .accessible_name("Snap preset");
+            ui.separator();
+            if ui
+                .selectable_label(editor_state.viewport_layout == ViewportLayout::Single, "Single")
+                .accessible_name("Single viewport")
+                .clicked()
+            {
+                editor_state.viewport_layout = ViewportLayout::Single;
+                editor_state.active_view = ViewportView::Perspective;
+            }
+            if ui
+                .selectable_label(editor_state.viewport_layout == ViewportLayout::Quad, "Quad")
+                .accessible_name("Four viewports")
+                .clicked()
+            {
+                editor_state.viewport_layout = ViewportLayout::Quad;
+            }
         });
+        tour_targets.toolbar = Some(toolbar.response.rect);
 
         ui.separator();
 
+        // F6 and Shift+F6 move between the docked panels, so every panel can
+        // be reached without a mouse; Tab then moves through its widgets
+        let previous_panel = ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F6));
+        let next_panel = !previous_panel && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F6));
+        if previous_panel || next_panel {
+            cycle_focused_tab(&mut dock_state, previous_panel);
+        }
+
         // Main dock area
         let mut dock_style = Style::from_egui(ctx.style().as_ref());
         dock_style.tab_bar.bg_fill = editor_settings.theme.panel_color;
         dock_style.tab_bar.height = 24.0;
 
+        // Read the selected asset's sidecar when the selection changes, and
+        // until the scan has written one
+        let selected_meta_path = editor_state.selected_asset_meta.as_ref().map(|(path, _)| path.as_str());
+        if editor_state.selected_asset.as_deref() != selected_meta_path {
+            editor_state.selected_asset_meta = editor_state.selected_asset.clone().and_then(|path| {
+                let meta = read_asset_meta(&world.asset_cache.root, &path).ok()?;
+                Some((path, meta))
+            });
+        }
+        let selected_material_path = editor_state
+            .selected_asset
+            .as_deref()
+            .filter(|path| split_label(path).1.is_some_and(|label| label.starts_with("Material")));
+        let loaded_material_path = editor_state
+            .selected_asset_material
+            .as_ref()
+            .and_then(|handle| handle.path())
+            .map(|path| path.to_string());
+        if selected_material_path != loaded_material_path.as_deref() {
+            editor_state.selected_asset_material =
+                selected_material_path.map(|path| world.asset_server.load(path.to_string()));
+        }
+
         let selected_asset = editor_state.selected_asset.clone();
         DockArea::new(&mut dock_state)
             .style(dock_style)
@@ -563,77 +1879,281 @@ fn update_editor_ui(
                 editor_state: &mut editor_state,
                 editor_settings: &mut editor_settings,
                 editor_output: &mut editor_output,
-                hierarchy: &hierarchy,
-                selected_transform: selected_transform.as_deref_mut(),
-                selected_name: selected_name.as_deref_mut(),
-                selected_material_handle,
-                selected_overrides: selected_overrides.as_deref_mut(),
-                selected_environment: selected_environment.as_deref_mut(),
-                selected_atmosphere: selected_atmosphere.as_deref_mut(),
+                hierarchy,
+                inspector: &mut world.inspector,
+                portal_previews: &portal_previews,
                 material_assets: &mut world.material_assets,
+                material_library: &world.material_library,
                 asset_server: &world.asset_server,
                 selected_asset: selected_asset.as_deref(),
-                selected_waffle_light: selected_waffle_light.as_deref_mut(),
-                selected_directional_light: selected_directional_light.as_deref_mut(),
-                selected_point_light: selected_point_light.as_deref_mut(),
-                selected_spot_light: selected_spot_light.as_deref_mut(),
+                project_settings: &world.project_settings,
                 diagnostics: &world.diagnostics,
+                tweens: &mut world.tweens,
+                quest_log: &mut world.quest_log,
+                game_variables: &mut world.game_variables,
+                playing: *world.play_state.get() == PlayState::Playing,
+                entity_pools: &world.entity_pools,
+                instancing_stats: &world.instancing_stats,
                 asset_cache: &world.asset_cache,
+                asset_thumbnail_ids: &asset_thumbnail_ids,
+                vcs_status: &world.vcs_status,
                 reparent_queue: &mut reparent_queue,
                 spawn_primitive_queue: &mut spawn_primitive_queue,
                 spawn_asset_queue: &mut spawn_asset_queue,
+                pivot_edit_queue: &mut pivot_edit_queue,
+                constraint_edit_queue: &mut constraint_edit_queue,
+                camera_rig_edit_queue: &mut camera_rig_edit_queue,
+                lua_script_edit_queue: &mut lua_script_edit_queue,
+                state_machine_edit_queue: &mut state_machine_edit_queue,
+                particle_emitter_edit_queue: &mut particle_emitter_edit_queue,
+                terrain_edit_queue: &mut terrain_edit_queue,
+                foliage_edit_queue: &mut foliage_edit_queue,
+                water_edit_queue: &mut water_edit_queue,
+                lod_edit_queue: &mut lod_edit_queue,
+                sub_scene_edit_queue: &mut sub_scene_edit_queue,
+                audio_source_edit_queue: &mut audio_source_edit_queue,
+                material_edit_queue: &mut material_edit_queue,
+                material_library_queue: &mut material_library_queue,
+                render_layers_edit_queue: &mut render_layers_edit_queue,
+                instancing_edit_queue: &mut instancing_edit_queue,
+                surface_edit_queue: &mut surface_edit_queue,
+                keyframe_edit_queue: &mut keyframe_edit_queue,
+                reimport_queue: &mut reimport_queue,
+                locate_missing_queue: &mut locate_missing_queue,
+                bake_ao_volume_queue: &mut bake_ao_volume_queue,
+                camera_shake_queue: &mut camera_shake_queue,
+                preview_camera_shake_queue: &mut preview_camera_shake_queue,
+                pin_inspector_queue: &mut pin_inspector_queue,
+                open_external_queue: &mut open_external_queue,
+                vcs_action_queue: &mut vcs_action_queue,
                 viewport_texture_id,
+                ortho_texture_ids,
+                viewport_stats,
+                debug_labels,
+                extensions: &mut world.extensions,
+                extension_commands: &mut extension_commands,
+                active_tool: &mut world.active_tool,
+                jobs: &world.editor_jobs,
+                input_debug_log: &mut world.input_debug_log,
+                ecs_stats: &mut world.ecs_stats,
+                systems_panel: &mut world.systems_panel,
+                system_toggles: &mut world.system_toggles,
+                resources_panel: &mut world.resources_panel,
+                gamepad_inputs: &world.gamepad_inputs,
+                tab_rects: &mut tour_targets.tabs,
             });
     });
+    for entity in pin_inspector_queue {
+        open_extra_inspector(&mut dock_state, Some(entity));
+    }
     editor_state.dock_state = dock_state;
 
+    let tour_signals = TourSignals {
+        spawned: !spawn_primitive_queue.is_empty() || !spawn_asset_queue.is_empty(),
+        selected: editor_state.selection.primary().is_some(),
+        playing: *world.play_state.get() == PlayState::Playing,
+    };
+
     for event in reparent_queue {
         world.reparent_events.send(event);
     }
     for event in spawn_primitive_queue {
         world.spawn_primitive_events.send(event);
     }
+    for event in pivot_edit_queue {
+        world.pivot_edit_events.send(event);
+    }
+    for event in constraint_edit_queue {
+        world.constraint_edit_events.send(event);
+    }
+    for event in camera_rig_edit_queue {
+        world.camera_rig_edit_events.send(event);
+    }
+    for event in lua_script_edit_queue {
+        world.lua_script_edit_events.send(event);
+    }
+    for event in state_machine_edit_queue {
+        world.state_machine_edit_events.send(event);
+    }
+    for event in particle_emitter_edit_queue {
+        world.particle_emitter_edit_events.send(event);
+    }
+    for event in terrain_edit_queue {
+        world.terrain_edit_events.send(event);
+    }
+    for event in foliage_edit_queue {
+        world.foliage_edit_events.send(event);
+    }
+    for event in water_edit_queue {
+        world.water_edit_events.send(event);
+    }
+    for event in lod_edit_queue {
+        world.lod_edit_events.send(event);
+    }
+    for event in sub_scene_edit_queue {
+        world.sub_scene_edit_events.send(event);
+    }
+    for event in audio_source_edit_queue {
+        world.audio_source_edit_events.send(event);
+    }
+    for event in material_edit_queue {
+        world.material_edit_events.send(event);
+    }
+    for event in material_library_queue {
+        world.material_library_events.send(event);
+    }
+    for event in render_layers_edit_queue {
+        world.render_layers_edit_events.send(event);
+    }
+    for event in instancing_edit_queue {
+        world.instancing_edit_events.send(event);
+    }
+    for event in surface_edit_queue {
+        world.surface_edit_events.send(event);
+    }
+    for event in keyframe_edit_queue {
+        world.keyframe_edit_events.send(event);
+    }
+    for event in reimport_queue {
+        world.reimport_events.send(event);
+    }
+    for event in locate_missing_queue {
+        world.locate_missing_events.send(event);
+    }
+    for event in bake_ao_volume_queue {
+        world.bake_ao_volume_events.send(event);
+    }
+    for event in camera_shake_queue {
+        world.camera_shake_events.send(event);
+    }
+    for event in preview_camera_shake_queue {
+        world.preview_camera_shake_events.send(event);
+    }
     for event in spawn_asset_queue {
         world.spawn_asset_events.send(event);
     }
-
-    resize_viewport_target(&mut world.viewport_target, &mut world.images, editor_state.viewport_size);
-    handle_viewport_picking(
+    for event in open_external_queue {
+        world.open_external_events.send(event);
+    }
+    for event in vcs_action_queue {
+        world.vcs_action_events.send(event);
+    }
+    commands.append(&mut extension_commands);
+
+    let pointer_down = world.mouse_input.pressed(MouseButton::Left);
+    resize_viewport_target(
+        &mut world.viewport_target,
+        &mut world.images,
+        editor_state.viewport_size,
+        pointer_down,
+    );
+    if editor_state.viewport_layout == ViewportLayout::Quad && !pointer_down {
+        resize_ortho_view_targets(&mut world.ortho_targets, &mut world.images, &editor_state.ortho_viewports);
+    }
+    let tool_click = handle_viewport_picking(
         &mut editor_state,
+        &world.active_tool.tool,
         &world.camera_query,
+        &world.ortho_camera_query,
         &world.mesh_query,
+        &world.terrain_chunk_query,
         &world.meshes,
+        &world.hierarchy.locked_sub_scenes,
+        &world.parent_query,
+        editor_settings.gizmo.hit_radius,
     );
+    if let Some(click) = tool_click {
+        if let EditorTool::Custom(id) = &click.tool {
+            let mut extension_ctx = EditorExtensionContext::new(editor_state.selection.primary(), &mut extension_commands);
+            world.extensions.tool_clicked(id, &click, &mut extension_ctx);
+        }
+        world.tool_click_events.send(click);
+    }
     save_layout_if_changed(&mut editor_state);
 
-    if editor_state.delete_confirm.is_none()
+    // The game only sees the viewport, so map the cursor into it.
+    world.game_cursor.viewport = Some(Rect::from_corners(
+        editor_state.viewport_origin,
+        editor_state.viewport_origin + editor_state.viewport_size / (ctx.pixels_per_point() * editor_settings.render_scale),
+    ));
+    world.game_cursor.camera = world
+        .camera_settings
+        .as_ref()
+        .and_then(|settings| settings.main_camera_entity);
+
+    if editor_state.delete_confirm.is_empty()
         && world.keyboard_input.just_pressed(KeyCode::Delete)
-        && editor_state.selected_entity.is_some()
+        && !editor_state.selection.is_empty()
         && !ctx.wants_keyboard_input()
     {
-        editor_state.delete_confirm = editor_state.selected_entity;
+        editor_state.delete_confirm = editor_state.selection.entities().to_vec();
     }
 
-    if let Some(entity) = editor_state.delete_confirm {
-        let label = hierarchy
-            .names
-            .get(&entity)
-            .map(|name| name.as_str())
-            .unwrap_or("Entity");
+    if !editor_state.delete_confirm.is_empty() {
+        // Children go with their parents, so only the topmost are deleted
+        let targets: Vec<Entity> = editor_state
+            .delete_confirm
+            .iter()
+            .copied()
+            .filter(|entity| {
+                !editor_state
+                    .delete_confirm
+                    .iter()
+                    .any(|other| other != entity && has_ancestor(*entity, *other, &world.parent_query))
+            })
+            .collect();
+        let (title, question) = match targets.as_slice() {
+            [entity] => {
+                let label = hierarchy
+                    .names
+                    .get(entity)
+                    .map(|name| name.as_str())
+                    .unwrap_or("Entity");
+                ("Delete Entity?", format!("Delete \"{}\"?", label))
+            }
+            _ => ("Delete Entities?", format!("Delete {} selected entities?", targets.len())),
+        };
         let mut keep_open = true;
-        egui::Window::new("Delete Entity?")
+        egui::Window::new(title)
+            .id(egui::Id::new("delete_confirm"))
             .collapsible(false)
             .resizable(false)
             .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
             .show(ctx, |ui| {
-                ui.label(format!("Delete \"{}\"?", label));
+                ui.label(question);
                 ui.separator();
                 ui.horizontal(|ui| {
                     if ui.button("Delete").clicked() {
-                        world.delete_events.send(DeleteEntityEvent { entity });
-                        if editor_state.selected_entity == Some(entity) {
-                            editor_state.selected_entity = None;
+                        for entity in &targets {
+                            world.delete_events.send(DeleteEntityEvent { entity: *entity });
                         }
+                        for entity in &editor_state.delete_confirm.clone() {
+                            editor_state.selection.remove(*entity);
+                        }
+                        keep_open = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        keep_open = false;
+                    }
+                });
+            });
+        if !keep_open {
+            editor_state.delete_confirm.clear();
+        }
+    }
+
+    if let Some(path) = editor_state.revert_confirm.clone() {
+        let mut keep_open = true;
+        egui::Window::new("Revert File?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!("Discard all uncommitted changes to \"{}\"?", path));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Revert").clicked() {
+                        world.vcs_action_events.send(VcsActionEvent::Revert(path.clone()));
                         keep_open = false;
                     }
                     if ui.button("Cancel").clicked() {
@@ -642,7 +2162,39 @@ fn update_editor_ui(
                 });
             });
         if !keep_open {
-            editor_state.delete_confirm = None;
+            editor_state.revert_confirm = None;
+        }
+    }
+
+    show_asset_file_dialog(ctx, &mut editor_state.asset_file_dialog, &mut world.asset_file_events);
+    show_scene_file_dialog(ctx, &mut editor_state.scene_file_dialog, &mut world.scene_file_events);
+    show_new_project_dialog(ctx, &mut editor_state.new_project_dialog);
+    show_scene_diff_window(ctx, &mut editor_state.scene_diff, &mut world.scene_file_events);
+    show_project_settings_dialog(
+        ctx,
+        &mut editor_state.show_project_settings,
+        &mut world.project_settings,
+        &world.hdr_output_status,
+    );
+    show_external_tools_dialog(ctx, &mut editor_state.show_external_tools, &mut world.external_tools);
+    show_preferences_dialog(ctx, &mut editor_state.show_preferences, &mut editor_settings);
+    show_shader_warmup_window(ctx, &world.shader_warmup);
+    show_collaboration_dialog(ctx, &mut editor_state.show_collaboration, &mut world.collab_session);
+    show_plugin_settings_dialog(
+        ctx,
+        &mut editor_state.show_plugin_settings,
+        &mut world.extensions,
+        &mut world.disabled_systems,
+    );
+
+    if draw_tour(ctx, &mut editor_settings.tour, &tour_targets, &tour_signals) {
+        if let Some(tab) = editor_settings.tour.target_tab() {
+            if let Some(location) = editor_state.dock_state.find_tab(&tab) {
+                editor_state.dock_state.set_active_tab(location);
+            }
+        }
+        if let Err(error) = editor_settings.save() {
+            error!("Failed to save tour progress: {}", error);
         }
     }
 
@@ -669,56 +2221,234 @@ fn resize_viewport_target(
     target: &mut ViewportRenderTarget,
     images: &mut Assets<Image>,
     size: Vec2,
+    pointer_down: bool,
 ) {
+    // A resized back buffer is rendering; swap it in once it has a frame.
+    if target.swap_countdown > 0 {
+        target.swap_countdown -= 1;
+        if target.swap_countdown == 0 {
+            let front = std::mem::replace(&mut target.image, target.back_image.clone());
+            target.back_image = front;
+            if let Some(size) = target.pending_size.take() {
+                target.size = size;
+            }
+        }
+        return;
+    }
+
     let width = size.x.max(1.0).round() as u32;
     let height = size.y.max(1.0).round() as u32;
     let new_size = UVec2::new(width, height);
     if new_size == target.size {
+        target.pending_size = None;
         return;
     }
 
-    if let Some(image) = images.get_mut(&target.image) {
+    // While a splitter is being dragged the current image is stretched to fit;
+    // the resize is only committed once the drag ends.
+    target.pending_size = Some(new_size);
+    if pointer_down {
+        return;
+    }
+
+    if let Some(image) = images.get_mut(&target.back_image) {
         image.resize(Extent3d {
             width,
             height,
             ..default()
         });
     }
-    target.size = new_size;
+    target.swap_countdown = 2;
+}
+
+/// Save Replay / Load Replay toolbar actions
+fn draw_replay_controls(
+    ui: &mut egui::Ui,
+    session: &mut crate::core::replay::ReplaySession,
+    next_play_state: &mut NextState<PlayState>,
+    playing: bool,
+) {
+    use crate::core::replay::{list_replays, queue_replay, Replay, ReplayMode};
+
+    let has_recording = session
+        .recording
+        .as_ref()
+        .is_some_and(|recording| !recording.frames.is_empty());
+    if ui
+        .add_enabled(has_recording, egui::Button::new("Save Replay"))
+        .on_hover_text("Save the input recorded in the current or last play session")
+        .clicked()
+    {
+        match session.save_recording() {
+            Ok(path) => info!("Saved replay to {}", path.display()),
+            Err(err) => error!("Failed to save replay: {err}"),
+        }
+    }
+    ui.add_enabled_ui(!playing, |ui| {
+        ui.menu_button("Load Replay", |ui| {
+            let replays = list_replays();
+            if replays.is_empty() {
+                ui.label("No replays saved");
+            }
+            for path in replays {
+                let label = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if ui.button(label).clicked() {
+                    match Replay::load(&path) {
+                        Ok(replay) => queue_replay(session, next_play_state, replay),
+                        Err(err) => error!("Failed to load replay {}: {err}", path.display()),
+                    }
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+    match session.mode {
+        ReplayMode::Recording => {
+            ui.colored_label(egui::Color32::from_rgb(220, 80, 80), "REC");
+        }
+        ReplayMode::Playing => {
+            if let Some((frame, total)) = session.playback_progress() {
+                ui.label(format!("Replay {frame}/{total}"));
+            }
+        }
+        ReplayMode::Idle => {}
+    }
+}
+
+/// Project debug draw labels into viewport pixels
+fn collect_debug_labels(
+    texts: &crate::core::debug_draw::DebugTextQueue,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+) -> Vec<DebugLabel> {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return Vec::new();
+    };
+    texts
+        .frame
+        .iter()
+        .filter_map(|text| {
+            let position = camera.world_to_viewport(camera_transform, text.position)?;
+            Some(DebugLabel {
+                position,
+                text: text.text.clone(),
+                color: text.color,
+            })
+        })
+        .collect()
+}
+
+fn collect_viewport_stats(
+    entities: &bevy::ecs::entity::Entities,
+    visible_mesh_query: &Query<(&Handle<Mesh>, Option<&Handle<StandardMaterial>>, &ViewVisibility)>,
+    meshes: &Assets<Mesh>,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+) -> ViewportStats {
+    let mut stats = ViewportStats {
+        entity_count: entities.len() as usize,
+        camera_position: camera_query
+            .get_single()
+            .ok()
+            .map(|(_, transform)| transform.translation()),
+        ..default()
+    };
+    // Without render-world diagnostics each visible mesh and material pair is
+    // counted as one draw, since copies sharing both are drawn instanced.
+    let mut batches = std::collections::HashSet::new();
+    for (mesh_handle, material_handle, visibility) in visible_mesh_query.iter() {
+        if !visibility.get() {
+            continue;
+        }
+        let Some(mesh) = meshes.get(mesh_handle) else {
+            continue;
+        };
+        let vertex_count = mesh
+            .indices()
+            .map(|indices| indices.len())
+            .unwrap_or_else(|| mesh.count_vertices());
+        stats.triangle_count += vertex_count / 3;
+        batches.insert((mesh_handle.id(), material_handle.map(|handle| handle.id())));
+    }
+    stats.draw_call_count = batches.len();
+    stats
+}
+
+fn resize_ortho_view_targets(
+    targets: &mut OrthoViewTargets,
+    images: &mut Assets<Image>,
+    panes: &[OrthoViewportState],
+) {
+    for target in targets.targets.iter_mut() {
+        let Some(pane) = panes.iter().find(|pane| pane.view == target.view) else {
+            continue;
+        };
+        let new_size = UVec2::new(
+            pane.size.x.max(1.0).round() as u32,
+            pane.size.y.max(1.0).round() as u32,
+        );
+        if new_size == target.size {
+            continue;
+        }
+        if let Some(image) = images.get_mut(&target.image) {
+            image.resize(Extent3d {
+                width: new_size.x,
+                height: new_size.y,
+                ..default()
+            });
+        }
+        target.size = new_size;
+    }
 }
 
+/// Select what was clicked in the viewport, or hand the click to the active
+/// tool when it does something else with it
 fn handle_viewport_picking(
     editor_state: &mut EditorState,
+    active_tool: &EditorTool,
     camera_query: &Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+    ortho_camera_query: &Query<(&Camera, &GlobalTransform, &WaffleOrthoCamera)>,
     mesh_query: &Query<(Entity, &GlobalTransform, &Handle<Mesh>), Without<EditorHidden>>,
+    terrain_chunk_query: &Query<(&TerrainChunk, &GlobalTransform)>,
     meshes: &Assets<Mesh>,
-) {
+    locked_sub_scenes: &std::collections::HashSet<Entity>,
+    parent_query: &Query<&Parent>,
+    hit_radius: f32,
+) -> Option<ViewportToolClickEvent> {
     if !editor_state.viewport_clicked {
-        return;
+        return None;
     }
     editor_state.viewport_clicked = false;
     editor_state.active_axis = None;
 
-    let Some(local_pos) = editor_state.viewport_click_pos.take() else {
-        return;
-    };
-
-    if let Some(axis) = pick_gizmo_axis(
-        editor_state.gizmo_overlay.as_ref(),
-        editor_state.gizmo_mode,
-        local_pos,
-    ) {
-        editor_state.active_axis = Some(axis);
-        return;
-    }
+    let local_pos = editor_state.viewport_click_pos.take()?;
 
-    let Ok((camera, camera_transform)) = camera_query.get_single() else {
-        return;
+    let overlay = match editor_state.viewport_click_view {
+        ViewportView::Perspective => editor_state.gizmo_overlay.as_ref(),
+        ViewportView::Ortho(view) => editor_state
+            .ortho_viewports
+            .iter()
+            .find(|pane| pane.view == view)
+            .and_then(|pane| pane.gizmo_overlay.as_ref()),
     };
+    if let Some(mode) = active_tool.gizmo_mode() {
+        let threshold = hit_radius * editor_state.viewport_click_pixels_per_point;
+        if let Some(axis) = pick_gizmo_axis(overlay, mode, local_pos, threshold) {
+            editor_state.active_axis = Some(axis);
+            return None;
+        }
+    }
 
-    let Some(ray) = camera.viewport_to_world(camera_transform, local_pos) else {
-        return;
+    let camera = match editor_state.viewport_click_view {
+        ViewportView::Perspective => camera_query.get_single().ok(),
+        ViewportView::Ortho(view) => ortho_camera_query
+            .iter()
+            .find(|(_, _, ortho)| ortho.view == view)
+            .map(|(camera, camera_transform, _)| (camera, camera_transform)),
     };
+    let (camera, camera_transform) = camera?;
+    let ray = camera.viewport_to_world(camera_transform, local_pos)?;
 
     let mut best_hit: Option<(Entity, f32)> = None;
     for (entity, transform, mesh_handle) in mesh_query.iter() {
@@ -741,12 +2471,44 @@ fn handle_viewport_picking(
             best_hit = Some((entity, distance));
         }
     }
+    // Terrain chunks are hidden from the hierarchy; clicking one picks its terrain
+    for (chunk, transform) in terrain_chunk_query.iter() {
+        let Some(distance) =
+            ray_aabb_intersection_world(ray.origin, *ray.direction, &transform.compute_matrix(), &chunk.aabb)
+        else {
+            continue;
+        };
+        if best_hit.map(|(_, best)| distance < best).unwrap_or(true) {
+            best_hit = Some((chunk.terrain, distance));
+        }
+    }
+    let best_hit = best_hit.map(|(entity, distance)| {
+        (sub_scene_pick_target(entity, locked_sub_scenes, parent_query), distance)
+    });
 
-    if let Some((entity, _)) = best_hit {
-        editor_state.selected_entity = Some(entity);
-    } else {
-        editor_state.selected_entity = None;
+    if !active_tool.selects() {
+        let point = match best_hit {
+            Some((_, distance)) => Some(ray.get_point(distance)),
+            None => ray
+                .intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))
+                .map(|distance| ray.get_point(distance)),
+        };
+        return Some(ViewportToolClickEvent {
+            tool: active_tool.clone(),
+            entity: best_hit.map(|(entity, _)| entity),
+            point,
+        });
+    }
+
+    // Shift-click adds to or removes from the selection
+    let additive = std::mem::take(&mut editor_state.viewport_click_additive);
+    match best_hit {
+        Some((entity, _)) if additive => editor_state.selection.toggle(entity),
+        Some((entity, _)) => editor_state.selection.select(entity),
+        None if additive => {}
+        None => editor_state.selection.clear(),
     }
+    None
 }
 
 fn ray_aabb_intersection_world(
@@ -814,6 +2576,91 @@ fn ray_aabb_intersection(
     }
 }
 
+/// Where the gizmo sits: on the primary entity, or at the center of a
+/// multi-selection, turned like the primary entity
+fn selection_gizmo_pivot(
+    primary: &GlobalTransform,
+    selection: &EditorSelection,
+    parent_query: &Query<&Parent>,
+    transforms: &Query<&GlobalTransform>,
+) -> GlobalTransform {
+    if selection.len() < 2 {
+        return *primary;
+    }
+    let center = selection_pivot(selection, parent_query, transforms).unwrap_or(primary.translation());
+    GlobalTransform::from(Transform::from_translation(center).with_rotation(primary.compute_transform().rotation))
+}
+
+/// Centre of the selection's root entities in world space. The gizmo is drawn
+/// there, and dragging it rotates and scales around it.
+fn selection_pivot(
+    selection: &EditorSelection,
+    parent_query: &Query<&Parent>,
+    transforms: &Query<&GlobalTransform>,
+) -> Option<Vec3> {
+    let roots = selection.roots(parent_query);
+    let positions: Vec<Vec3> = transforms
+        .iter_many(&roots)
+        .map(|transform| transform.translation())
+        .collect();
+    (!positions.is_empty()).then(|| positions.iter().sum::<Vec3>() / positions.len() as f32)
+}
+
+/// World length at `point` that spans `fraction` of the camera's viewport height
+fn screen_constant_length(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    point: Vec3,
+    fraction: f32,
+) -> Option<f32> {
+    let viewport_height = camera.logical_viewport_size()?.y;
+    let start = camera.world_to_viewport(camera_transform, point)?;
+    let end = camera.world_to_viewport(camera_transform, point + *camera_transform.up())?;
+    let pixels_per_unit = start.distance(end);
+    (pixels_per_unit > f32::EPSILON).then(|| fraction * viewport_height / pixels_per_unit)
+}
+
+fn gizmo_basis(transform: &GlobalTransform, axis_space: AxisSpace) -> Mat3 {
+    match axis_space {
+        AxisSpace::Global => Mat3::IDENTITY,
+        AxisSpace::Local => Mat3::from_quat(transform.compute_transform().rotation),
+    }
+}
+
+fn build_gizmo_overlay(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    transform: &GlobalTransform,
+    axis_space: AxisSpace,
+    axis_length: f32,
+) -> Option<GizmoOverlay> {
+    let origin = transform.translation();
+    let basis = gizmo_basis(transform, axis_space);
+    let axis_x = basis * Vec3::X;
+    let axis_y = basis * Vec3::Y;
+    let axis_z = basis * Vec3::Z;
+    let origin_screen = camera.world_to_viewport(camera_transform, origin)?;
+    let x_end = camera.world_to_viewport(camera_transform, origin + axis_x * axis_length)?;
+    let y_end = camera.world_to_viewport(camera_transform, origin + axis_y * axis_length)?;
+    let z_end = camera.world_to_viewport(camera_transform, origin + axis_z * axis_length)?;
+    let rotate_rings = build_rotate_overlay(
+        camera,
+        camera_transform,
+        origin,
+        axis_x,
+        axis_y,
+        axis_z,
+        axis_length,
+    );
+    Some(GizmoOverlay {
+        origin: origin_screen,
+        x_end,
+        y_end,
+        z_end,
+        rotate_rings,
+    })
+}
+
 fn build_rotate_overlay(
     camera: &Camera,
     camera_transform: &GlobalTransform,
@@ -862,6 +2709,7 @@ fn pick_gizmo_axis(
     overlay: Option<&GizmoOverlay>,
     mode: GizmoMode,
     click_pos: Vec2,
+    threshold: f32,
 ) -> Option<GizmoAxis> {
     let overlay = overlay?;
     let origin_screen = overlay.origin;
@@ -869,7 +2717,6 @@ fn pick_gizmo_axis(
     let y_screen = overlay.y_end;
     let z_screen = overlay.z_end;
 
-    let threshold = 10.0;
     let mut best: Option<(GizmoAxis, f32)> = None;
 
     match mode {
@@ -935,9 +2782,10 @@ fn distance_to_polyline(point: Vec2, points: &[Vec2]) -> f32 {
 
 fn save_layout_if_changed(editor_state: &mut EditorState) {
     let now = Instant::now();
-    if now.duration_since(editor_state.layout_last_save) < Duration::from_secs(1) {
+    if now.duration_since(editor_state.layout_last_check) < Duration::from_secs(1) {
         return;
     }
+    editor_state.layout_last_check = now;
     let Ok(layout_string) = ron::ser::to_string(&editor_state.dock_state) else {
         return;
     };
@@ -946,7 +2794,35 @@ fn save_layout_if_changed(editor_state: &mut EditorState) {
     }
     if std::fs::write("editor_layout.ron", layout_string.as_bytes()).is_ok() {
         editor_state.layout_cache = layout_string;
-        editor_state.layout_last_save = now;
+    }
+}
+
+/// Focus a tab if it is already docked, otherwise open it in the focused leaf
+fn open_tab(dock_state: &mut DockState<EditorTab>, tab: EditorTab) {
+    if let Some(location) = dock_state.find_tab(&tab) {
+        dock_state.set_active_tab(location);
+    } else {
+        dock_state.push_to_focused_leaf(tab);
+    }
+}
+
+/// Focus the next docked panel, or the previous one, wrapping around
+fn cycle_focused_tab(dock_state: &mut DockState<EditorTab>, backwards: bool) {
+    let tabs: Vec<EditorTab> = dock_state.iter_all_tabs().map(|(_, tab)| tab.clone()).collect();
+    if tabs.is_empty() {
+        return;
+    }
+    let current = dock_state
+        .find_active_focused()
+        .and_then(|(_, focused)| tabs.iter().position(|tab| tab == focused));
+    let next = match current {
+        Some(index) if backwards => (index + tabs.len() - 1) % tabs.len(),
+        Some(index) => (index + 1) % tabs.len(),
+        None => 0,
+    };
+    if let Some((surface, node, tab)) = dock_state.find_tab(&tabs[next]) {
+        dock_state.set_active_tab((surface, node, tab));
+        dock_state.set_focused_node_and_surface((surface, node));
     }
 }
 
@@ -955,83 +2831,119 @@ fn load_layout() -> Option<DockState<EditorTab>> {
     ron::de::from_str(&data).ok()
 }
 
+/// Entity tree shown in the Hierarchy panel. Kept between frames and only
+/// rebuilt when entities are added, removed, renamed or reparented.
+#[derive(Resource, Default)]
 pub(crate) struct HierarchySnapshot {
     pub(crate) roots: Vec<Entity>,
     pub(crate) children: HashMap<Entity, Vec<Entity>>,
     pub(crate) names: HashMap<Entity, String>,
-}
-
-fn build_hierarchy_snapshot(
-    query: &Query<(Entity, Option<&Name>, Option<&Parent>), Without<EditorHidden>>,
+    /// Other editors' selections in a collaboration session
+    pub(crate) presence: HashMap<Entity, Vec<PresenceTag>>,
+    /// Scene references not opened for edit, shown without their contents
+    pub(crate) locked_sub_scenes: std::collections::HashSet<Entity>,
+    /// Scene root the tree was built from
     root: Option<Entity>,
-) -> HierarchySnapshot {
-    let mut children: HashMap<Entity, Vec<Entity>> = HashMap::new();
-    let mut names: HashMap<Entity, String> = HashMap::new();
-    let mut all_entities: Vec<Entity> = Vec::new();
-    let mut has_parent: HashMap<Entity, Entity> = HashMap::new();
-
-    for (entity, name, parent) in query.iter() {
-        let label = name
-            .map(|name| name.as_str().to_string())
-            .unwrap_or_else(|| format!("Entity {}", entity.index()));
-        names.insert(entity, label);
-        all_entities.push(entity);
+    entity_count: usize,
+    reachable: std::collections::HashSet<Entity>,
+    stack: Vec<Entity>,
+}
 
-        if let Some(parent) = parent {
-            children.entry(parent.get()).or_default().push(entity);
-            has_parent.insert(entity, parent.get());
+impl HierarchySnapshot {
+    /// Rebuild in place, keeping the allocations of the last build
+    fn rebuild(
+        &mut self,
+        query: &Query<(Entity, Option<&Name>, Option<&Parent>), Without<EditorHidden>>,
+        root: Option<Entity>,
+    ) {
+        self.root = root;
+        self.entity_count = 0;
+        self.roots.clear();
+        for child_list in self.children.values_mut() {
+            child_list.clear();
         }
-    }
-
-    let mut roots: Vec<Entity> = if let Some(root_entity) = root {
-        vec![root_entity]
-    } else {
-        all_entities
-            .iter()
-            .copied()
-            .filter(|entity| !has_parent.contains_key(entity))
-            .collect()
-    };
-
-    let name_lookup = |entity: &Entity| names.get(entity).map(|s| s.as_str()).unwrap_or("");
-    roots.sort_by(|a, b| name_lookup(a).cmp(name_lookup(b)));
+        self.names.retain(|entity, _| query.contains(*entity));
+
+        for (entity, name, parent) in query.iter() {
+            self.entity_count += 1;
+            let label = self.names.entry(entity).or_default();
+            label.clear();
+            match name {
+                Some(name) => label.push_str(name.as_str()),
+                None => {
+                    let _ = write!(label, "Entity {}", entity.index());
+                }
+            }
 
-    for child_list in children.values_mut() {
-        child_list.sort_by(|a, b| name_lookup(a).cmp(name_lookup(b)));
-    }
+            match parent {
+                Some(parent) => self.children.entry(parent.get()).or_default().push(entity),
+                None if root.is_none() => self.roots.push(entity),
+                None => {}
+            }
+        }
+        self.children.retain(|_, child_list| !child_list.is_empty());
+        if let Some(root_entity) = root {
+            self.roots.push(root_entity);
+        }
 
-    if let Some(root_entity) = root {
-        let mut filtered_children: HashMap<Entity, Vec<Entity>> = HashMap::new();
-        let mut filtered_names: HashMap<Entity, String> = HashMap::new();
+        let names = &self.names;
+        let name_lookup = |entity: &Entity| names.get(entity).map(|s| s.as_str()).unwrap_or("");
+        self.roots.sort_by(|a, b| name_lookup(a).cmp(name_lookup(b)));
+        for child_list in self.children.values_mut() {
+            child_list.sort_by(|a, b| name_lookup(a).cmp(name_lookup(b)));
+        }
 
-        let mut stack = vec![root_entity];
-        let mut visited: std::collections::HashSet<Entity> = std::collections::HashSet::new();
-        while let Some(current) = stack.pop() {
-            if !visited.insert(current) {
-                continue;
-            }
-            if let Some(name) = names.get(&current) {
-                filtered_names.insert(current, name.clone());
-            }
-            if let Some(children_list) = children.get(&current) {
-                filtered_children.insert(current, children_list.clone());
-                for child in children_list {
-                    stack.push(*child);
+        if let Some(root_entity) = root {
+            self.reachable.clear();
+            self.stack.clear();
+            self.stack.push(root_entity);
+            while let Some(current) = self.stack.pop() {
+                if !self.reachable.insert(current) {
+                    continue;
+                }
+                if let Some(children_list) = self.children.get(&current) {
+                    self.stack.extend(children_list.iter().copied());
                 }
             }
+            let reachable = &self.reachable;
+            self.children.retain(|entity, _| reachable.contains(entity));
+            self.names.retain(|entity, _| reachable.contains(entity));
         }
+    }
 
-        HierarchySnapshot {
-            roots,
-            children: filtered_children,
-            names: filtered_names,
-        }
-    } else {
-        HierarchySnapshot {
-            roots,
-            children,
-            names,
+    /// Entities top to bottom as the hierarchy panel lists them
+    pub(crate) fn listed_order(&self) -> Vec<Entity> {
+        let mut order = Vec::with_capacity(self.names.len());
+        let mut stack: Vec<Entity> = self.roots.iter().rev().copied().collect();
+        while let Some(entity) = stack.pop() {
+            order.push(entity);
+            if let Some(children) = self.children.get(&entity) {
+                stack.extend(children.iter().rev().copied());
+            }
         }
+        order
+    }
+}
+
+fn update_hierarchy_snapshot(
+    mut hierarchy: ResMut<HierarchySnapshot>,
+    entity_query: Query<(Entity, Option<&Name>, Option<&Parent>), Without<EditorHidden>>,
+    changed_query: Query<(), (Without<EditorHidden>, Or<(Changed<Name>, Changed<Parent>)>)>,
+    scene_root_query: Query<Entity, With<WaffleSceneRoot>>,
+    mut removed_names: RemovedComponents<Name>,
+    mut removed_parents: RemovedComponents<Parent>,
+    mut removed_hidden: RemovedComponents<EditorHidden>,
+) {
+    // Despawns show up as removed components; spawns and newly hidden
+    // entities change the count
+    let removed = removed_names.read().count() + removed_parents.read().count() + removed_hidden.read().count();
+    let root = scene_root_query.get_single().ok();
+    let changed = removed > 0
+        || !changed_query.is_empty()
+        || root != hierarchy.root
+        || entity_query.iter().len() != hierarchy.entity_count;
+    if changed {
+        hierarchy.rebuild(&entity_query, root);
     }
 }
 
@@ -1064,6 +2976,50 @@ fn find_descendant_with_material(
     None
 }
 
+/// Render scale slider and sharpening toggle for the View menu
+fn draw_render_scale_settings(ui: &mut egui::Ui, editor_settings: &mut EditorSettings) {
+    let mut percent = editor_settings.render_scale * 100.0;
+    ui.horizontal(|ui| {
+        ui.label("Render Scale");
+        if ui
+            .add(egui::Slider::new(&mut percent, 50.0..=200.0).step_by(5.0).suffix("%"))
+            .on_hover_text("Lower to keep heavy scenes responsive, raise to supersample")
+            .changed()
+        {
+            editor_settings.render_scale = percent / 100.0;
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut editor_settings.sharpen, "Sharpen");
+        ui.add_enabled(
+            editor_settings.sharpen,
+            egui::Slider::new(&mut editor_settings.sharpening_strength, 0.0..=1.0),
+        );
+    });
+}
+
+/// Apply the sharpening settings to the viewport camera
+fn sync_viewport_sharpening(
+    mut commands: Commands,
+    editor_settings: Res<EditorSettings>,
+    cameras: Query<(Entity, Option<&ContrastAdaptiveSharpeningSettings>), With<WaffleMainCamera>>,
+) {
+    for (camera, current) in &cameras {
+        let up_to_date = current.is_some_and(|current| {
+            current.enabled == editor_settings.sharpen
+                && current.sharpening_strength == editor_settings.sharpening_strength
+        });
+        if up_to_date {
+            continue;
+        }
+        commands.entity(camera).insert(ContrastAdaptiveSharpeningSettings {
+            enabled: editor_settings.sharpen,
+            sharpening_strength: editor_settings.sharpening_strength,
+            denoise: false,
+        });
+    }
+}
+
 fn sync_editor_camera_focus(
     editor_state: Res<EditorState>,
     mut cameras: Query<&mut WaffleCamera, With<WaffleMainCamera>>,
@@ -1080,10 +3036,16 @@ fn sync_editor_camera_focus(
 fn update_selected_entity_transform(
     mut contexts: EguiContexts,
     mut editor_state: ResMut<EditorState>,
+    editor_settings: Res<EditorSettings>,
+    project_settings: Res<ProjectSettings>,
+    active_tool: Res<ActiveTool>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mut mouse_motion: EventReader<MouseMotion>,
-    mut transforms: Query<&mut Transform, Without<WaffleMainCamera>>,
+    mut transforms: Query<&mut Transform, (Without<WaffleMainCamera>, Without<WaffleOrthoCamera>)>,
+    parent_query: Query<&Parent>,
+    global_transforms: Query<&GlobalTransform>,
     camera_query: Query<&Transform, With<WaffleMainCamera>>,
+    ortho_camera_query: Query<(&Transform, &WaffleOrthoCamera), Without<WaffleMainCamera>>,
 ) {
     let Some(ctx) = contexts.try_ctx_mut() else {
         mouse_motion.clear();
@@ -1097,14 +3059,19 @@ fn update_selected_entity_transform(
         mouse_motion.clear();
         return;
     }
+    let Some(gizmo_mode) = active_tool.tool.gizmo_mode() else {
+        mouse_motion.clear();
+        return;
+    };
 
     if !mouse_input.pressed(MouseButton::Left) {
         editor_state.active_axis = None;
+        editor_state.snap_remainder = Vec3::ZERO;
         mouse_motion.clear();
         return;
     }
 
-    let Some(selected) = editor_state.selected_entity else {
+    let Some(selected) = editor_state.selection.primary() else {
         mouse_motion.clear();
         return;
     };
@@ -1130,47 +3097,110 @@ fn update_selected_entity_transform(
         return;
     }
 
-    let Ok(mut transform) = transforms.get_mut(selected) else {
+    let Ok(transform) = transforms.get(selected).copied() else {
         return;
     };
+    let Ok(primary_global) = global_transforms.get(selected) else {
+        return;
+    };
+    // Children of selected entities follow their parents
+    let targets = editor_state.selection.roots(&parent_query);
+    // Worked out in world space like the gizmo, then taken into each target's
+    // parent space, as selected entities can have different parents
+    let Some(pivot) = selection_pivot(&editor_state.selection, &parent_query, &global_transforms) else {
+        return;
+    };
+    let parent_world = |entity: Entity| {
+        parent_query
+            .get(entity)
+            .ok()
+            .and_then(|parent| global_transforms.get(parent.get()).ok())
+            .copied()
+            .unwrap_or(GlobalTransform::IDENTITY)
+    };
+    let primary_rotation = primary_global.compute_transform().rotation;
+    let axis_space = editor_state.axis_space;
+    let axis_direction = |axis: GizmoAxis| {
+        let local = match axis {
+            GizmoAxis::X => Vec3::X,
+            GizmoAxis::Y => Vec3::Y,
+            GizmoAxis::Z => Vec3::Z,
+        };
+        match axis_space {
+            AxisSpace::Local => primary_rotation * local,
+            AxisSpace::Global => local,
+        }
+    };
 
-    let distance = camera.translation.distance(transform.translation).max(0.1);
-    let drag_speed = 0.002 * distance;
-    let right = camera.right();
-    let up = camera.up();
+    let ortho_camera = match editor_state.active_view {
+        ViewportView::Perspective => None,
+        ViewportView::Ortho(view) => ortho_camera_query
+            .iter()
+            .find(|(_, ortho)| ortho.view == view),
+    };
+    let (right, up, drag_speed) = if let Some((ortho_transform, ortho)) = ortho_camera {
+        let pane_height = editor_state
+            .ortho_viewports
+            .iter()
+            .find(|pane| pane.view == ortho.view)
+            .map(|pane| pane.size.y)
+            .unwrap_or(1.0)
+            .max(1.0);
+        (
+            ortho_transform.right(),
+            ortho_transform.up(),
+            ORTHO_VIEW_HEIGHT * ortho.zoom / pane_height,
+        )
+    } else {
+        let distance = camera.translation.distance(pivot).max(0.1);
+        (camera.right(), camera.up(), 0.002 * distance)
+    };
     let world_delta = (right * delta.x + up * -delta.y) * drag_speed;
 
-    match editor_state.gizmo_mode {
+    let snap = editor_settings
+        .snap_enabled
+        .then(|| project_settings.snap_preset(editor_settings.snap_preset))
+        .flatten();
+    let mut remainder = editor_state.snap_remainder;
+
+    match gizmo_mode {
         GizmoMode::Move => {
-            if let Some(axis) = editor_state.active_axis {
-                let axis_dir = match (editor_state.axis_space, axis) {
-                    (AxisSpace::Local, GizmoAxis::X) => transform.rotation * Vec3::X,
-                    (AxisSpace::Local, GizmoAxis::Y) => transform.rotation * Vec3::Y,
-                    (AxisSpace::Local, GizmoAxis::Z) => transform.rotation * Vec3::Z,
-                    (_, GizmoAxis::X) => Vec3::X,
-                    (_, GizmoAxis::Y) => Vec3::Y,
-                    (_, GizmoAxis::Z) => Vec3::Z,
-                };
-                let amount = world_delta.dot(axis_dir);
-                transform.translation += axis_dir * amount;
+            let step = snap.map(|preset| preset.translate);
+            let offset = if let Some(axis) = editor_state.active_axis {
+                let axis_dir = axis_direction(axis);
+                axis_dir * snap_increment(world_delta.dot(axis_dir), step, &mut remainder.x)
             } else {
-                transform.translation += world_delta;
+                Vec3::new(
+                    snap_increment(world_delta.x, step, &mut remainder.x),
+                    snap_increment(world_delta.y, step, &mut remainder.y),
+                    snap_increment(world_delta.z, step, &mut remainder.z),
+                )
+            };
+            for entity in &targets {
+                let parent = parent_world(*entity);
+                let Ok(mut target) = transforms.get_mut(*entity) else {
+                    continue;
+                };
+                target.translation += parent.affine().inverse().transform_vector3(offset);
             }
         }
         GizmoMode::Rotate => {
             let Some(axis) = editor_state.active_axis else {
                 return;
             };
-            let axis_dir = match (editor_state.axis_space, axis) {
-                (AxisSpace::Local, GizmoAxis::X) => transform.rotation * Vec3::X,
-                (AxisSpace::Local, GizmoAxis::Y) => transform.rotation * Vec3::Y,
-                (AxisSpace::Local, GizmoAxis::Z) => transform.rotation * Vec3::Z,
-                (_, GizmoAxis::X) => Vec3::X,
-                (_, GizmoAxis::Y) => Vec3::Y,
-                (_, GizmoAxis::Z) => Vec3::Z,
-            };
-            let angle = (delta.x + delta.y) * 0.004;
-            transform.rotate(Quat::from_axis_angle(axis_dir, angle));
+            let step = snap.map(|preset| preset.rotate_degrees.to_radians());
+            let angle = snap_increment((delta.x + delta.y) * 0.004, step, &mut remainder.x);
+            let rotation = Quat::from_axis_angle(axis_direction(axis), angle);
+            for entity in &targets {
+                let parent = parent_world(*entity);
+                let parent_rotation = parent.compute_transform().rotation;
+                let local_pivot = parent.affine().inverse().transform_point3(pivot);
+                let local_rotation = parent_rotation.inverse() * rotation * parent_rotation;
+                let Ok(mut target) = transforms.get_mut(*entity) else {
+                    continue;
+                };
+                target.rotate_around(local_pivot, local_rotation);
+            }
         }
         GizmoMode::Scale => {
             let Some(axis) = editor_state.active_axis else {
@@ -1178,17 +3208,186 @@ fn update_selected_entity_transform(
             };
             let amount = 1.0 + (delta.x + delta.y) * 0.005;
             let clamped = amount.clamp(0.1, 10.0);
-            match axis {
-                GizmoAxis::X => transform.scale.x = (transform.scale.x * clamped).max(0.01),
-                GizmoAxis::Y => transform.scale.y = (transform.scale.y * clamped).max(0.01),
-                GizmoAxis::Z => transform.scale.z = (transform.scale.z * clamped).max(0.01),
+            let step = snap.map(|preset| preset.scale);
+            let index = match axis {
+                GizmoAxis::X => 0,
+                GizmoAxis::Y => 1,
+                GizmoAxis::Z => 2,
+            };
+            // The primary entity sets the snapped factor the rest scale by
+            let scale = transform.scale[index];
+            let change = snap_increment(scale * (clamped - 1.0), step, &mut remainder.x);
+            let factor = (scale + change).max(0.01) / scale.max(0.01);
+            let spread = targets.len() > 1;
+            let axis_dir = axis_direction(axis);
+            for entity in &targets {
+                // Spread along the gizmo axis, away from or toward the pivot
+                let offset = global_transforms
+                    .get(*entity)
+                    .ok()
+                    .filter(|_| spread)
+                    .map(|global| axis_dir * (global.translation() - pivot).dot(axis_dir) * (factor - 1.0))
+                    .unwrap_or(Vec3::ZERO);
+                let parent = parent_world(*entity);
+                let Ok(mut target) = transforms.get_mut(*entity) else {
+                    continue;
+                };
+                target.scale[index] = (target.scale[index] * factor).max(0.01);
+                target.translation += parent.affine().inverse().transform_vector3(offset);
+            }
+        }
+    }
+    editor_state.snap_remainder = remainder;
+}
+
+/// Apply drag movement in whole `step`s, carrying the rest over to later frames
+fn snap_increment(amount: f32, step: Option<f32>, remainder: &mut f32) -> f32 {
+    let Some(step) = step.filter(|step| *step > 0.0) else {
+        return amount;
+    };
+    let total = amount + *remainder;
+    let snapped = (total / step).trunc() * step;
+    *remainder = total - snapped;
+    snapped
+}
+
+/// Start the editor with the project's defaults
+/// Scale the whole editor UI by the theme's UI scale
+fn sync_editor_ui_scale(editor_settings: Res<EditorSettings>, mut egui_settings: ResMut<EguiSettings>) {
+    let scale = editor_settings.theme.ui_scale.clamp(0.5, 3.0);
+    if egui_settings.scale_factor != scale {
+        egui_settings.scale_factor = scale;
+    }
+}
+
+fn apply_project_editor_defaults(
+    project_settings: Res<ProjectSettings>,
+    mut editor_settings: ResMut<EditorSettings>,
+) {
+    editor_settings.grid_size = project_settings.default_grid_size.max(0.1);
+}
+
+fn sync_ortho_view_cameras(
+    mut editor_state: ResMut<EditorState>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    target_query: Query<&GlobalTransform, Without<WaffleOrthoCamera>>,
+    mut ortho_cameras: Query<(&mut Camera, &mut Transform, &mut Projection, &mut WaffleOrthoCamera)>,
+) {
+    let quad = editor_state.viewport_layout == ViewportLayout::Quad;
+    let focus_target = if editor_state.viewport_focused && keyboard_input.just_pressed(KeyCode::KeyF) {
+        editor_state
+            .selection
+            .primary()
+            .and_then(|entity| target_query.get(entity).ok())
+            .map(|target| target.translation())
+    } else {
+        None
+    };
+
+    for (mut camera, mut transform, mut projection, mut ortho) in &mut ortho_cameras {
+        if camera.is_active != quad {
+            camera.is_active = quad;
+        }
+        if !quad {
+            continue;
+        }
+        let Some(pane) = editor_state
+            .ortho_viewports
+            .iter_mut()
+            .find(|pane| pane.view == ortho.view)
+        else {
+            continue;
+        };
+
+        if pane.zoom_delta != 0.0 {
+            ortho.zoom = (ortho.zoom * (1.0 - pane.zoom_delta * 0.1)).clamp(0.05, 100.0);
+            pane.zoom_delta = 0.0;
+        }
+        if pane.pan_delta != Vec2::ZERO {
+            let units_per_pixel = ORTHO_VIEW_HEIGHT * ortho.zoom / pane.size.y.max(1.0);
+            let pan = *transform.right() * pane.pan_delta.x - *transform.up() * pane.pan_delta.y;
+            ortho.center -= pan * units_per_pixel;
+            pane.pan_delta = Vec2::ZERO;
+        }
+        if let Some(target) = focus_target {
+            ortho.center = target;
+        }
+
+        let desired = ortho_camera_transform(ortho.view, ortho.center);
+        if *transform != desired {
+            *transform = desired;
+        }
+        if let Projection::Orthographic(ortho_projection) = projection.as_mut() {
+            if ortho_projection.scale != ortho.zoom {
+                ortho_projection.scale = ortho.zoom;
             }
         }
     }
 }
 
+/// The visibility an entity had before selection isolation hid it. Scene
+/// files save this one, so isolating never changes what gets saved.
+#[derive(Component)]
+pub(super) struct IsolationHidden(pub(super) Visibility);
+
+/// Hide everything outside the selected subtrees while isolation is on,
+/// restoring the previous visibility when it is turned off.
+fn apply_selection_isolation(
+    mut commands: Commands,
+    mut editor_state: ResMut<EditorState>,
+    parent_query: Query<&Parent>,
+    mut visibility_query: Query<
+        (Entity, &mut Visibility, Option<&IsolationHidden>),
+        (With<Handle<Mesh>>, Without<EditorHidden>),
+    >,
+) {
+    let roots = if editor_state.isolate_selection {
+        editor_state.selection.entities().to_vec()
+    } else {
+        Vec::new()
+    };
+    if roots == editor_state.isolated_roots {
+        return;
+    }
+
+    for (entity, mut visibility, hidden) in &mut visibility_query {
+        if let Some(hidden) = hidden {
+            *visibility = hidden.0;
+            commands.entity(entity).remove::<IsolationHidden>();
+        }
+    }
+    editor_state.isolated_roots = roots.clone();
+
+    if roots.is_empty() {
+        return;
+    }
+    for (entity, mut visibility, _) in &mut visibility_query {
+        // Keep the selected subtrees and their ancestors so inherited visibility still reaches them.
+        let kept = roots
+            .iter()
+            .any(|root| has_ancestor(entity, *root, &parent_query) || has_ancestor(*root, entity, &parent_query));
+        if kept {
+            continue;
+        }
+        commands.entity(entity).insert(IsolationHidden(*visibility));
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn has_ancestor(entity: Entity, ancestor: Entity, parent_query: &Query<&Parent>) -> bool {
+    let mut current = Some(entity);
+    while let Some(node) = current {
+        if node == ancestor {
+            return true;
+        }
+        current = parent_query.get(node).ok().map(|parent| parent.get());
+    }
+    false
+}
+
 fn update_editor_camera_orbit_focus(
     mut editor_state: ResMut<EditorState>,
+    project_settings: Res<ProjectSettings>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mut mouse_motion: EventReader<MouseMotion>,
@@ -1200,7 +3399,7 @@ fn update_editor_camera_orbit_focus(
         return;
     }
 
-    let Some(selected) = editor_state.selected_entity else {
+    let Some(selected) = editor_state.selection.primary() else {
         mouse_motion.clear();
         return;
     };
@@ -1229,6 +3428,11 @@ fn update_editor_camera_orbit_focus(
         for motion in mouse_motion.read() {
             delta += motion.delta;
         }
+        let input = &project_settings.input;
+        let delta = Vec2::new(
+            input.apply(InputAxis::MouseX, delta.x),
+            input.apply(InputAxis::MouseY, delta.y),
+        );
         if delta.length_squared() == 0.0 {
             return;
         }
@@ -1246,30 +3450,196 @@ fn update_editor_camera_orbit_focus(
     }
 }
 
+/// Distance ahead of the camera touch gestures orbit around when nothing is selected
+const TOUCH_ORBIT_DISTANCE: f32 = 10.0;
+
+/// Touch gestures on the viewport: two fingers orbit the selection, or a point
+/// ahead of the camera, and pinch to zoom towards it; three fingers pan
+fn update_editor_camera_touch(
+    editor_state: Res<EditorState>,
+    touches: Res<Touches>,
+    mut camera_query: Query<&mut Transform, With<WaffleMainCamera>>,
+    target_query: Query<&GlobalTransform>,
+) {
+    if !editor_state.viewport_hovered && !editor_state.viewport_focused {
+        return;
+    }
+    let gesture = TouchGesture::read(&touches);
+    if gesture.fingers < 2 || (gesture.drag == Vec2::ZERO && gesture.pinch == 1.0) {
+        return;
+    }
+    let Ok(mut camera) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let pivot = editor_state
+        .selection
+        .primary()
+        .and_then(|selected| target_query.get(selected).ok())
+        .map(|target| target.translation())
+        .unwrap_or_else(|| camera.translation + camera.forward() * TOUCH_ORBIT_DISTANCE);
+    let mut offset = camera.translation - pivot;
+
+    if gesture.fingers >= 3 {
+        // Pan faster the further away the pivot is, so the scene follows the fingers
+        let scale = offset.length().max(1.0) * 0.002;
+        let pan = (camera.right() * -gesture.drag.x + camera.up() * gesture.drag.y) * scale;
+        camera.translation += pan;
+        return;
+    }
+
+    if gesture.drag != Vec2::ZERO {
+        let yaw = -gesture.drag.x * 0.006;
+        let pitch = gesture.drag.y * 0.006;
+        offset = Quat::from_axis_angle(Vec3::Y, yaw) * offset;
+        let right = offset.cross(Vec3::Y).normalize_or_zero();
+        offset = Quat::from_axis_angle(right, pitch) * offset;
+    }
+    // Spreading the fingers apart zooms in
+    if gesture.pinch > 0.0 {
+        offset = (offset / gesture.pinch).clamp_length_min(0.5);
+    }
+    camera.translation = pivot + offset;
+    camera.look_at(pivot, Vec3::Y);
+}
+
 fn draw_selected_gizmos(
     editor_state: Res<EditorState>,
+    editor_settings: Res<EditorSettings>,
     mut gizmos: Gizmos,
     transform_query: Query<&GlobalTransform>,
     mesh_query: Query<&Handle<Mesh>>,
     meshes: Res<Assets<Mesh>>,
 ) {
-    let Some(selected) = editor_state.selected_entity else {
+    let palette = &editor_settings.theme.palette;
+    let mut bounds: Option<(Vec3, Vec3)> = None;
+    for selected in editor_state.selection.entities() {
+        let Ok(transform) = transform_query.get(*selected) else {
+            continue;
+        };
+        let Some(aabb) = mesh_query
+            .get(*selected)
+            .ok()
+            .and_then(|mesh_handle| meshes.get(mesh_handle))
+            .and_then(|mesh| mesh.compute_aabb())
+        else {
+            let point = transform.translation();
+            bounds = Some(bounds.map_or((point, point), |(min, max)| (min.min(point), max.max(point))));
+            continue;
+        };
+        let matrix = transform.compute_matrix();
+        draw_aabb_gizmo(&mut gizmos, matrix, &aabb, EditorPalette::color(palette.selection));
+
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+        for corner in 0..8 {
+            let local = Vec3::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            let point = matrix.transform_point3(local);
+            bounds = Some(bounds.map_or((point, point), |(min, max)| (min.min(point), max.max(point))));
+        }
+    }
+
+    // One box around the whole selection
+    if editor_state.selection.len() > 1 {
+        if let Some((min, max)) = bounds {
+            let aabb = Aabb::from_min_max(min, max);
+            draw_aabb_gizmo(&mut gizmos, Mat4::IDENTITY, &aabb, EditorPalette::color(palette.group_selection));
+        }
+    }
+}
+
+/// Transform gizmo drawn into the scene, so geometry in front of it hides it.
+/// Only enabled while the gizmo isn't set to be drawn on top.
+fn draw_transform_gizmo(
+    editor_state: Res<EditorState>,
+    editor_settings: Res<EditorSettings>,
+    active_tool: Res<ActiveTool>,
+    mut config_store: ResMut<GizmoConfigStore>,
+    mut gizmos: Gizmos<TransformGizmos>,
+) {
+    let settings = editor_settings.gizmo;
+    let (config, _) = config_store.config_mut::<TransformGizmos>();
+    config.enabled = !settings.always_on_top;
+    config.line_width = settings.line_thickness;
+    if settings.always_on_top {
+        return;
+    }
+    let Some((origin, basis, axis_length)) = editor_state.gizmo_placement else {
         return;
     };
-
-    let Ok(transform) = transform_query.get(selected) else {
+    let Some(mode) = active_tool.tool.gizmo_mode() else {
         return;
     };
-
-    if let Ok(mesh_handle) = mesh_query.get(selected) {
-        if let Some(mesh) = meshes.get(mesh_handle) {
-            if let Some(aabb) = mesh.compute_aabb() {
-                draw_aabb_gizmo(&mut gizmos, transform.compute_matrix(), &aabb, Color::srgb(1.0, 0.9, 0.1));
+    let palette = &editor_settings.theme.palette;
+    let axes = [(GizmoAxis::X, basis.x_axis), (GizmoAxis::Y, basis.y_axis), (GizmoAxis::Z, basis.z_axis)];
+    for (index, (axis, direction)) in axes.into_iter().enumerate() {
+        let color = palette.axis_color(index, editor_state.active_axis == Some(axis));
+        let end = origin + direction * axis_length;
+        match mode {
+            GizmoMode::Move => {
+                gizmos.arrow(origin, end, color).with_tip_length(axis_length * 0.15);
+            }
+            GizmoMode::Rotate => {
+                let Ok(normal) = Dir3::new(direction) else {
+                    continue;
+                };
+                gizmos.circle(origin, normal, axis_length, color);
+            }
+            GizmoMode::Scale => {
+                gizmos.line(origin, end, color);
+                let handle = Transform::from_translation(end)
+                    .with_rotation(Quat::from_mat3(&basis))
+                    .with_scale(Vec3::splat(axis_length * 0.08));
+                gizmos.cuboid(handle, color);
             }
         }
     }
 }
 
+/// Wheels and suspension of every vehicle; green while touching the ground
+fn draw_vehicle_gizmos(
+    mut gizmos: Gizmos,
+    vehicle_query: Query<(&RaycastVehicle, &GlobalTransform)>,
+) {
+    for (vehicle, transform) in &vehicle_query {
+        let (_, rotation, _) = transform.to_scale_rotation_translation();
+        for (index, wheel) in vehicle.wheels.iter().enumerate() {
+            let Some(center) = vehicle.wheel_center(index) else {
+                continue;
+            };
+            let state = vehicle.wheel_states().get(index);
+            let color = if state.is_some_and(|state| state.contact.is_some()) {
+                Color::srgb(0.3, 0.9, 0.4)
+            } else {
+                Color::srgb(0.6, 0.6, 0.6)
+            };
+            let steer = Quat::from_rotation_y(state.map_or(0.0, |state| state.steer_angle));
+            let anchor = transform.transform_point(wheel.position);
+            let hub = transform.transform_point(center);
+            let axle = Dir3::new(rotation * steer * Vec3::X).unwrap_or(Dir3::X);
+            gizmos.line(anchor, hub, Color::srgb(0.9, 0.7, 0.2));
+            gizmos.circle(hub, axle, wheel.radius, color);
+        }
+    }
+}
+
+/// Every dolly track's curve and control points
+fn draw_dolly_track_gizmos(
+    mut gizmos: Gizmos,
+    track_query: Query<(&DollyTrack, &GlobalTransform)>,
+) {
+    for (track, transform) in &track_query {
+        let line = track.polyline();
+        gizmos.linestrip(line.iter().map(|point| transform.transform_point(*point)), Color::srgb(0.9, 0.5, 0.9));
+        for point in &track.points {
+            gizmos.sphere(transform.transform_point(*point), Quat::IDENTITY, 0.1, Color::srgb(1.0, 0.8, 1.0));
+        }
+    }
+}
+
 fn draw_editor_grid(
     editor_settings: Res<EditorSettings>,
     mut gizmos: Gizmos,
@@ -1384,197 +3754,786 @@ impl tracing::field::Visit for EditorLogVisitor<'_> {
     }
 }
 
-pub fn editor_log_layer(app: &mut App) -> Option<BoxedLayer> {
-    let (sender, receiver) = mpsc::channel();
-
-    app.insert_non_send_resource(CapturedEditorLogs(receiver));
-    app.add_event::<EditorLogEvent>();
-    app.add_systems(Update, transfer_editor_logs);
-
-    Some(EditorLogLayer { sender }.boxed())
+pub fn editor_log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let (sender, receiver) = mpsc::channel();
+
+    app.insert_non_send_resource(CapturedEditorLogs(receiver));
+    app.add_event::<EditorLogEvent>();
+    app.add_systems(Update, transfer_editor_logs);
+
+    Some(EditorLogLayer { sender }.boxed())
+}
+
+fn collect_editor_logs(
+    mut log_reader: EventReader<EditorLogEvent>,
+    mut editor_output: ResMut<EditorOutput>,
+) {
+    for message in log_reader.read() {
+        editor_output.push(message.level, message.message.clone());
+    }
+}
+
+fn is_game_log_target(target: &str) -> bool {
+    matches!(target, "waffle_game" | "game" | "gameplay" | "lua")
+}
+
+fn refresh_asset_cache(mut cache: ResMut<AssetBrowserCache>, jobs: Res<EditorJobs>) {
+    if let Some(result) = cache.scan.as_ref().map(JobHandle::poll) {
+        let Some(result) = result else {
+            return;
+        };
+        cache.scan = None;
+        if let Ok(entries) = result {
+            cache.entries = entries;
+        }
+    }
+
+    let needs_scan = cache
+        .last_scan
+        .map(|last| last.elapsed() >= Duration::from_secs(1))
+        .unwrap_or(true);
+    if !needs_scan {
+        return;
+    }
+
+    let root = cache.root.clone();
+    // Sub-assets are only read again from models that changed
+    let known: HashMap<String, AssetEntry> = cache
+        .entries
+        .iter()
+        .filter(|entry| !entry.sub_assets.is_empty())
+        .map(|entry| (entry.path.clone(), entry.clone()))
+        .collect();
+    cache.scan = Some(jobs.spawn("Scan assets", JobPriority::Low, move |_| Ok(scan_assets(&root, &known))));
+    cache.last_scan = Some(Instant::now());
+}
+
+fn scan_assets(root: &std::path::Path, known: &HashMap<String, AssetEntry>) -> Vec<AssetEntry> {
+    let mut entries = Vec::new();
+    if root.exists() {
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file() && !is_meta_file(entry.path()))
+        {
+            let path = entry.path();
+            let rel = path.strip_prefix(root).unwrap_or(path);
+            let rel_str = rel.to_string_lossy().replace('\\', "/");
+            match ensure_asset_meta(root, &rel_str) {
+                Ok(true) => debug!("Wrote import settings for {}", rel_str),
+                Ok(false) => {}
+                Err(err) => debug!("Failed to write import settings for {}: {}", rel_str, err),
+            }
+            let kind = classify_asset(path.extension().and_then(|ext| ext.to_str()));
+            let modified = entry.metadata().ok().and_then(|metadata| metadata.modified().ok());
+            let sub_assets = match known.get(&rel_str) {
+                Some(known) if known.modified == modified => known.sub_assets.clone(),
+                _ if has_sub_assets(&rel_str) => read_sub_assets(path).unwrap_or_else(|err| {
+                    debug!("Failed to list the contents of {}: {}", rel_str, err);
+                    Vec::new()
+                }),
+                _ => Vec::new(),
+            };
+            entries.push(AssetEntry {
+                path: rel_str,
+                kind,
+                modified,
+                sub_assets,
+            });
+        }
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+/// Copy dropped files into the assets folder on a job; the next asset scan
+/// picks them up
+fn handle_file_drops(
+    file_drop_events: &mut EventReader<FileDragAndDrop>,
+    cache: &AssetBrowserCache,
+    jobs: &EditorJobs,
+) {
+    for event in file_drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+
+        let assets_root = cache.root.clone();
+        let path_buf = path_buf.clone();
+        let name = path_buf.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        jobs.spawn(format!("Import {name}"), JobPriority::High, move |_| {
+            if !assets_root.exists() {
+                std::fs::create_dir_all(&assets_root)?;
+            }
+
+            if path_buf.is_dir() {
+                copy_dir_recursively(&path_buf, &assets_root)?;
+            } else if path_buf.is_file() {
+                copy_file_unique(&path_buf, &assets_root)?;
+                if path_buf.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("gltf")).unwrap_or(false) {
+                    if let Some(stem) = path_buf.file_stem().and_then(|s| s.to_str()) {
+                        let bin_path = path_buf.with_file_name(format!("{stem}.bin"));
+                        if bin_path.exists() {
+                            let _ = copy_file_unique(&bin_path, &assets_root);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+fn copy_file_unique(src: &std::path::Path, dest_root: &std::path::Path) -> std::io::Result<()> {
+    let file_name = src.file_name().ok_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "missing file name",
+    ))?;
+    let mut dest = dest_root.join(file_name);
+    if dest.exists() {
+        let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+        let ext = src.extension().and_then(|s| s.to_str()).unwrap_or("");
+        for i in 1..=999 {
+            let candidate = if ext.is_empty() {
+                format!("{stem}_{i}")
+            } else {
+                format!("{stem}_{i}.{ext}")
+            };
+            let path = dest_root.join(candidate);
+            if !path.exists() {
+                dest = path;
+                break;
+            }
+        }
+    }
+    std::fs::copy(src, &dest)?;
+    Ok(())
+}
+
+fn copy_dir_recursively(src: &std::path::Path, dest_root: &std::path::Path) -> std::io::Result<()> {
+    let folder_name = src.file_name().ok_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "missing folder name",
+    ))?;
+    let dest_dir = dest_root.join(folder_name);
+    for entry in WalkDir::new(src).into_iter().filter_map(Result::ok) {
+        let rel = entry.path().strip_prefix(src).unwrap_or(entry.path());
+        let target = dest_dir.join(rel);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn classify_asset(extension: Option<&str>) -> AssetKind {
+    match extension.map(|ext| ext.to_ascii_lowercase()) {
+        Some(ext) if matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "tga" | "hdr") => {
+            AssetKind::Image
+        }
+        Some(ext) if matches!(ext.as_str(), "gltf" | "glb" | "obj") => AssetKind::Model,
+        Some(ext) if matches!(ext.as_str(), "wav" | "ogg" | "mp3") => AssetKind::Audio,
+        Some(ext) if matches!(ext.as_str(), "lua") => AssetKind::Script,
+        Some(ext) if matches!(ext.as_str(), "wmat" | "ron" | "json") => AssetKind::Material,
+        _ => AssetKind::Other,
+    }
+}
+
+fn apply_reparent_events(
+    mut commands: Commands,
+    mut events: EventReader<HierarchyReparentEvent>,
+    children_query: Query<&Children>,
+) {
+    for event in events.read() {
+        if let Some(parent) = event.new_parent {
+            if event.child == parent {
+                continue;
+            }
+            if is_descendant(event.child, parent, &children_query) {
+                continue;
+            }
+            commands.entity(event.child).set_parent(parent);
+        } else {
+            commands.entity(event.child).remove_parent();
+        }
+    }
+}
+
+/// Move an entity's pivot while keeping its geometry in place by shifting the
+/// entity and counter-offsetting its children (and its own mesh, if any).
+fn apply_pivot_edit_events(
+    mut events: EventReader<PivotEditEvent>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut transforms: Query<&mut Transform>,
+    children_query: Query<&Children>,
+    bounds_query: Query<(&GlobalTransform, Option<&Aabb>)>,
+    mesh_query: Query<&Handle<Mesh>>,
+) {
+    for event in events.read() {
+        let Ok((entity_global, _)) = bounds_query.get(event.entity) else {
+            continue;
+        };
+
+        let delta = match event.kind {
+            PivotEditKind::Offset(offset) => offset,
+            PivotEditKind::CenterToBounds | PivotEditKind::BottomOfBounds => {
+                let Some((min, max)) =
+                    subtree_local_bounds(event.entity, entity_global, &children_query, &bounds_query)
+                else {
+                    continue;
+                };
+                let center = (min + max) * 0.5;
+                match event.kind {
+                    PivotEditKind::BottomOfBounds => Vec3::new(center.x, min.y, center.z),
+                    _ => center,
+                }
+            }
+        };
+        if delta.length_squared() < 1e-10 {
+            continue;
+        }
+
+        if let Ok(mut transform) = transforms.get_mut(event.entity) {
+            let offset = transform.rotation * (transform.scale * delta);
+            transform.translation += offset;
+        }
+        if let Ok(children) = children_query.get(event.entity) {
+            for child in children.iter() {
+                if let Ok(mut child_transform) = transforms.get_mut(*child) {
+                    child_transform.translation -= delta;
+                }
+            }
+        }
+        if let Some(mesh) = mesh_query
+            .get(event.entity)
+            .ok()
+            .and_then(|handle| meshes.get(handle))
+        {
+            // Copy the mesh so other entities sharing it keep their geometry.
+            let shifted = mesh.clone().translated_by(-delta);
+            commands
+                .entity(event.entity)
+                .insert(meshes.add(shifted))
+                .remove::<Aabb>();
+        }
+    }
+}
+
+fn subtree_local_bounds(
+    root: Entity,
+    root_global: &GlobalTransform,
+    children_query: &Query<&Children>,
+    bounds_query: &Query<(&GlobalTransform, Option<&Aabb>)>,
+) -> Option<(Vec3, Vec3)> {
+    let to_local = root_global.affine().inverse();
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    let mut found = false;
+    let mut stack = vec![root];
+    while let Some(current) = stack.pop() {
+        if let Ok((global, Some(aabb))) = bounds_query.get(current) {
+            let center = Vec3::from(aabb.center);
+            let half = Vec3::from(aabb.half_extents);
+            for corner in 0..8 {
+                let sign = Vec3::new(
+                    if corner & 1 == 0 { -1.0 } else { 1.0 },
+                    if corner & 2 == 0 { -1.0 } else { 1.0 },
+                    if corner & 4 == 0 { -1.0 } else { 1.0 },
+                );
+                let world = global.transform_point(center + half * sign);
+                let local = to_local.transform_point3(world);
+                min = min.min(local);
+                max = max.max(local);
+                found = true;
+            }
+        }
+        if let Ok(children) = children_query.get(current) {
+            stack.extend(children.iter().copied());
+        }
+    }
+    found.then_some((min, max))
+}
+
+fn apply_constraint_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<ConstraintEditEvent>,
+) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match (event.kind, event.add) {
+            (ConstraintKind::LookAt, true) => {
+                entity.insert(LookAtConstraint::default());
+            }
+            (ConstraintKind::LookAt, false) => {
+                entity.remove::<LookAtConstraint>();
+            }
+            (ConstraintKind::Follow, true) => {
+                entity.insert(FollowConstraint::default());
+            }
+            (ConstraintKind::Follow, false) => {
+                entity.remove::<FollowConstraint>();
+            }
+            (ConstraintKind::StickToSurface, true) => {
+                entity.insert(StickToSurfaceConstraint::default());
+            }
+            (ConstraintKind::StickToSurface, false) => {
+                entity.remove::<StickToSurfaceConstraint>();
+            }
+        }
+    }
+}
+
+fn apply_lua_script_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<LuaScriptEditEvent>,
+) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.path {
+            Some(path) => {
+                entity.insert(LuaScript::new(path.clone()));
+            }
+            None => {
+                entity.remove::<LuaScript>();
+            }
+        }
+    }
+}
+
+fn apply_state_machine_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<StateMachineEditEvent>,
+) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.graph {
+            Some(graph) => {
+                entity.insert(AnimationStateMachine::new(graph.clone()));
+            }
+            None => {
+                entity.remove::<AnimationStateMachine>();
+            }
+        }
+    }
+}
+
+fn apply_particle_emitter_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<ParticleEmitterEditEvent>,
+    mut states: Query<&mut ParticleSystemState>,
+) {
+    for event in events.read() {
+        match &event.kind {
+            ParticleEmitterEditKind::Set(emitter) => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.insert(emitter.clone());
+                }
+            }
+            ParticleEmitterEditKind::Restart => {
+                if let Ok(mut state) = states.get_mut(event.entity) {
+                    state.clear();
+                }
+            }
+            ParticleEmitterEditKind::Remove => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.remove::<ParticleEmitter>();
+                }
+            }
+        }
+    }
 }
 
-fn collect_editor_logs(
-    mut log_reader: EventReader<EditorLogEvent>,
-    mut editor_output: ResMut<EditorOutput>,
+fn apply_terrain_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<TerrainEditEvent>,
+    cache: Res<AssetBrowserCache>,
+    asset_server: Res<AssetServer>,
+    mut history: ResMut<TerrainSculptHistory>,
+    mut terrains: Query<(&mut WaffleTerrain, &mut TerrainState)>,
 ) {
-    for message in log_reader.read() {
-        editor_output
-            .lines
-            .push(format!("[{}] {}", message.level, message.message));
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.kind {
+            TerrainEditKind::Set(terrain) => {
+                entity.insert(terrain.clone());
+            }
+            TerrainEditKind::Remove => {
+                entity.remove::<WaffleTerrain>();
+            }
+            TerrainEditKind::SaveHeightmap(path) => {
+                let Ok((mut terrain, mut state)) = terrains.get_mut(event.entity) else {
+                    continue;
+                };
+                let Some(heights) = state.heights.as_ref() else {
+                    continue;
+                };
+                if let Err(err) = heights.save_png(&cache.root.join(path)) {
+                    error!("Failed to save heightmap {}: {}", path, err);
+                    continue;
+                }
+                info!("Saved heightmap {}", path);
+                state.adopt_heightmap(path, &asset_server);
+                terrain.heightmap = path.clone();
+            }
+            TerrainEditKind::UndoStroke => {
+                history.undo(Some(event.entity), &mut terrains.transmute_lens::<&mut TerrainState>().query());
+            }
+            TerrainEditKind::RedoStroke => {
+                history.redo(Some(event.entity), &mut terrains.transmute_lens::<&mut TerrainState>().query());
+            }
+        }
     }
+}
 
-    const MAX_LINES: usize = 500;
-    if editor_output.lines.len() > MAX_LINES {
-        let excess = editor_output.lines.len() - MAX_LINES;
-        editor_output.lines.drain(0..excess);
+fn apply_foliage_edit_events(mut commands: Commands, mut events: EventReader<FoliageEditEvent>) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.kind {
+            FoliageEditKind::Set(layer) => {
+                entity.insert(layer.clone());
+            }
+            FoliageEditKind::Remove => {
+                entity.remove::<FoliageLayer>();
+            }
+        }
     }
 }
 
-fn is_game_log_target(target: &str) -> bool {
-    matches!(target, "waffle_game" | "game" | "gameplay")
+fn apply_water_edit_events(mut commands: Commands, mut events: EventReader<WaterEditEvent>) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.kind {
+            WaterEditKind::Set(water) => {
+                entity.insert(water.clone());
+            }
+            WaterEditKind::Remove => {
+                entity.remove::<WaffleWater>();
+            }
+        }
+    }
 }
 
-fn refresh_asset_cache(mut cache: ResMut<AssetBrowserCache>) {
-    let needs_scan = cache
-        .last_scan
-        .map(|last| last.elapsed() >= Duration::from_secs(1))
-        .unwrap_or(true);
-    if !needs_scan {
-        return;
+fn apply_lod_edit_events(mut commands: Commands, mut events: EventReader<LodEditEvent>) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match &event.kind {
+            LodEditKind::Set(group) => {
+                entity.insert(group.clone());
+            }
+            LodEditKind::Remove => {
+                entity.remove::<LodGroup>();
+            }
+        }
     }
+}
 
-    let mut entries = Vec::new();
-    if cache.root.exists() {
-        for entry in WalkDir::new(&cache.root)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|entry| entry.file_type().is_file())
-        {
-            let path = entry.path();
-            let rel = path.strip_prefix(&cache.root).unwrap_or(path);
-            let rel_str = rel.to_string_lossy().replace('\\', "/");
-            let kind = classify_asset(path.extension().and_then(|ext| ext.to_str()));
-            entries.push(AssetEntry {
-                path: rel_str,
-                kind,
-            });
+fn apply_audio_source_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<AudioSourceEditEvent>,
+    mut sources: Query<&mut WaffleAudioSource>,
+    mut play_events: EventWriter<PlayAudioEvent>,
+    mut stop_events: EventWriter<StopAudioEvent>,
+) {
+    for event in events.read() {
+        match &event.kind {
+            AudioSourceEditKind::SetClip(path) => {
+                if let Ok(mut source) = sources.get_mut(event.entity) {
+                    source.clip = path.clone();
+                } else if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.insert(WaffleAudioSource::new(path.clone()));
+                }
+            }
+            AudioSourceEditKind::Remove => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.remove::<WaffleAudioSource>();
+                }
+            }
+            AudioSourceEditKind::Preview => {
+                play_events.send(PlayAudioEvent::new(event.entity));
+            }
+            AudioSourceEditKind::StopPreview => {
+                stop_events.send(StopAudioEvent::new(event.entity));
+            }
         }
     }
-
-    entries.sort_by(|a, b| a.path.cmp(&b.path));
-    cache.entries = entries;
-    cache.last_scan = Some(Instant::now());
 }
 
-fn handle_file_drops(
-    file_drop_events: &mut EventReader<FileDragAndDrop>,
-    cache: &mut ResMut<AssetBrowserCache>,
+fn apply_reimport_events(
+    mut events: EventReader<ReimportAssetEvent>,
+    cache: Res<AssetBrowserCache>,
+    asset_server: Res<AssetServer>,
 ) {
-    for event in file_drop_events.read() {
-        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+    for event in events.read() {
+        if let Err(err) = write_asset_meta(&cache.root, &event.path, &event.meta) {
+            error!("Failed to save import settings for {}: {}", event.path, err);
             continue;
-        };
-
-        let assets_root = cache.root.clone();
-        if !assets_root.exists() {
-            if let Err(err) = std::fs::create_dir_all(&assets_root) {
-                error!("Failed to create assets directory: {err}");
-                continue;
-            }
         }
+        asset_server.reload(event.path.clone());
+        info!("Reimported {}", event.path);
+    }
+}
 
-        if path_buf.is_dir() {
-            if let Err(err) = copy_dir_recursively(path_buf, &assets_root) {
-                error!("Failed to import folder {}: {err}", path_buf.display());
-                continue;
-            }
-        } else if path_buf.is_file() {
-            if let Err(err) = copy_file_unique(path_buf, &assets_root) {
-                error!("Failed to import file {}: {err}", path_buf.display());
-                continue;
+fn apply_material_edit_events(
+    mut events: EventReader<MaterialEditEvent>,
+    cache: Res<AssetBrowserCache>,
+    asset_server: Res<AssetServer>,
+    materials: Res<Assets<StandardMaterial>>,
+    material_surfaces: Res<MaterialSurfaces>,
+    children_query: Query<&Children>,
+    mut material_query: Query<&mut Handle<StandardMaterial>>,
+) {
+    for event in events.read() {
+        match &event.kind {
+            MaterialEditKind::Save(path) => {
+                let Some(material) = materials.get(&event.material) else {
+                    continue;
+                };
+                let surface = material_surfaces.get(&event.material);
+                if let Err(err) = save_material_file(&cache.root.join(path), material, surface) {
+                    error!("Failed to save material {}: {}", path, err);
+                    continue;
+                }
+                info!("Saved material {}", path);
+                let saved: Handle<StandardMaterial> = asset_server.load(path.clone());
+                // Overwriting the file a material came from changes nothing in the scene
+                if saved == event.material {
+                    continue;
+                }
+                for mut handle in &mut material_query {
+                    if *handle == event.material {
+                        *handle = saved.clone();
+                    }
+                }
             }
-            if path_buf.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("gltf")).unwrap_or(false) {
-                if let Some(stem) = path_buf.file_stem().and_then(|s| s.to_str()) {
-                    let bin_path = path_buf.with_file_name(format!("{stem}.bin"));
-                    if bin_path.exists() {
-                        let _ = copy_file_unique(&bin_path, &assets_root);
+            MaterialEditKind::Apply(path) => {
+                let Some(entity) = event.entity else {
+                    continue;
+                };
+                let applied: Handle<StandardMaterial> = asset_server.load(path.clone());
+                let mut stack = vec![entity];
+                while let Some(current) = stack.pop() {
+                    if let Ok(mut handle) = material_query.get_mut(current) {
+                        if *handle == event.material {
+                            *handle = applied.clone();
+                        }
+                    }
+                    if let Ok(children) = children_query.get(current) {
+                        stack.extend(children.iter().copied());
                     }
                 }
             }
         }
-
-        cache.last_scan = None;
     }
 }
 
-fn copy_file_unique(src: &std::path::Path, dest_root: &std::path::Path) -> std::io::Result<()> {
-    let file_name = src.file_name().ok_or_else(|| std::io::Error::new(
-        std::io::ErrorKind::InvalidInput,
-        "missing file name",
-    ))?;
-    let mut dest = dest_root.join(file_name);
-    if dest.exists() {
-        let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-        let ext = src.extension().and_then(|s| s.to_str()).unwrap_or("");
-        for i in 1..=999 {
-            let candidate = if ext.is_empty() {
-                format!("{stem}_{i}")
-            } else {
-                format!("{stem}_{i}.{ext}")
-            };
-            let path = dest_root.join(candidate);
-            if !path.exists() {
-                dest = path;
-                break;
+fn apply_material_library_events(
+    mut commands: Commands,
+    mut events: EventReader<MaterialLibraryEvent>,
+    mut library: ResMut<crate::rendering::materials::MaterialLibrary>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(), (With<Handle<Mesh>>, Without<EditorHidden>)>,
+) {
+    for event in events.read() {
+        match event {
+            MaterialLibraryEvent::Create { name } => {
+                let handle = materials.add(StandardMaterial::default());
+                library.add(name.clone(), handle);
+            }
+            MaterialLibraryEvent::Apply { index, entities } => {
+                let Some(material) = library.materials.get(*index) else {
+                    continue;
+                };
+                let mut stack = entities.clone();
+                while let Some(current) = stack.pop() {
+                    if mesh_query.contains(current) {
+                        commands.entity(current).insert(material.clone());
+                    }
+                    if let Ok(children) = children_query.get(current) {
+                        stack.extend(children.iter().copied());
+                    }
+                }
             }
         }
     }
-    std::fs::copy(src, &dest)?;
+}
+
+fn save_material_file(
+    path: &std::path::Path,
+    material: &StandardMaterial,
+    surface: Option<PhysicalSurface>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = crate::rendering::materials::MaterialFile::from(material);
+    file.surface = surface;
+    let data = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, data)?;
     Ok(())
 }
 
-fn copy_dir_recursively(src: &std::path::Path, dest_root: &std::path::Path) -> std::io::Result<()> {
-    let folder_name = src.file_name().ok_or_else(|| std::io::Error::new(
-        std::io::ErrorKind::InvalidInput,
-        "missing folder name",
-    ))?;
-    let dest_dir = dest_root.join(folder_name);
-    for entry in WalkDir::new(src).into_iter().filter_map(Result::ok) {
-        let rel = entry.path().strip_prefix(src).unwrap_or(entry.path());
-        let target = dest_dir.join(rel);
-        if entry.file_type().is_dir() {
-            std::fs::create_dir_all(&target)?;
-        } else {
-            if let Some(parent) = target.parent() {
-                std::fs::create_dir_all(parent)?;
+fn apply_camera_rig_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<CameraRigEditEvent>,
+) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match (event.part, event.add) {
+            (CameraRigPart::Dolly, true) => {
+                entity.insert(CameraDolly::default());
+            }
+            (CameraRigPart::Dolly, false) => {
+                entity.remove::<CameraDolly>();
+            }
+            (CameraRigPart::Crane, true) => {
+                entity.insert(CameraCrane::default());
+            }
+            (CameraRigPart::Crane, false) => {
+                entity.remove::<CameraCrane>();
+            }
+            (CameraRigPart::Focus, true) => {
+                entity.insert(CameraFocus::default());
+            }
+            (CameraRigPart::Focus, false) => {
+                entity.remove::<CameraFocus>();
             }
-            std::fs::copy(entry.path(), &target)?;
         }
     }
-    Ok(())
 }
 
-fn classify_asset(extension: Option<&str>) -> AssetKind {
-    match extension.map(|ext| ext.to_ascii_lowercase()) {
-        Some(ext) if matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "tga" | "hdr") => {
-            AssetKind::Image
+fn apply_surface_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<SurfaceEditEvent>,
+    mut material_surfaces: ResMut<MaterialSurfaces>,
+) {
+    for event in events.read() {
+        match &event.kind {
+            SurfaceEditKind::Entity(surface) => {
+                let Some(mut entity) = commands.get_entity(event.entity) else {
+                    continue;
+                };
+                match surface {
+                    Some(surface) => {
+                        entity.insert(*surface);
+                    }
+                    None => {
+                        entity.remove::<PhysicalSurface>();
+                    }
+                }
+            }
+            SurfaceEditKind::Material(material, surface) => {
+                material_surfaces.set(material, *surface);
+            }
         }
-        Some(ext) if matches!(ext.as_str(), "gltf" | "glb" | "obj") => AssetKind::Model,
-        Some(ext) if matches!(ext.as_str(), "wav" | "ogg" | "mp3") => AssetKind::Audio,
-        Some(ext) if matches!(ext.as_str(), "lua") => AssetKind::Script,
-        Some(ext) if matches!(ext.as_str(), "ron" | "json") => AssetKind::Material,
-        _ => AssetKind::Other,
     }
 }
 
-fn apply_reparent_events(
+fn apply_keyframe_edit_events(
     mut commands: Commands,
-    mut events: EventReader<HierarchyReparentEvent>,
-    children_query: Query<&Children>,
+    mut events: EventReader<KeyframeEditEvent>,
+    mut animations: Query<&mut PropertyAnimation>,
+    targets: Query<KeyedTargetQuery>,
+    materials: Res<Assets<StandardMaterial>>,
 ) {
     for event in events.read() {
-        if let Some(parent) = event.new_parent {
-            if event.child == parent {
-                continue;
+        match &event.kind {
+            KeyframeEditKind::Record {
+                properties,
+                time,
+                interpolation,
+            } => {
+                let Ok(target) = targets.get(event.entity) else {
+                    continue;
+                };
+                let mut existing = animations.get_mut(event.entity).ok();
+                let mut added = PropertyAnimation::default();
+                let animation = match existing.as_deref_mut() {
+                    Some(animation) => animation,
+                    None => &mut added,
+                };
+                for property in properties {
+                    let Some(value) = target.read(*property, &materials) else {
+                        continue;
+                    };
+                    animation.track_mut(*property).insert(Keyframe {
+                        time: *time,
+                        value,
+                        interpolation: *interpolation,
+                    });
+                }
+                if existing.is_none() && !added.tracks.is_empty() {
+                    commands.entity(event.entity).insert(added);
+                }
             }
-            if is_descendant(event.child, parent, &children_query) {
-                continue;
+            KeyframeEditKind::Remove => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.remove::<PropertyAnimation>();
+                }
             }
-            commands.entity(event.child).set_parent(parent);
+        }
+    }
+}
+
+fn apply_instancing_edit_events(mut commands: Commands, mut events: EventReader<InstancingEditEvent>) {
+    for event in events.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        if event.instanced {
+            entity.insert(InstancedMesh);
         } else {
-            commands.entity(event.child).remove_parent();
+            entity.remove::<InstancedMesh>();
         }
     }
 }
 
-fn apply_delete_events(
+fn apply_render_layers_edit_events(
     mut commands: Commands,
-    mut events: EventReader<DeleteEntityEvent>,
+    mut events: EventReader<RenderLayersEditEvent>,
+    children_query: Query<&Children>,
 ) {
     for event in events.read() {
-        if let Some(entity) = commands.get_entity(event.entity) {
-            entity.despawn_recursive();
+        let targets: Vec<Entity> = if event.include_children {
+            std::iter::once(event.entity)
+                .chain(children_query.iter_descendants(event.entity))
+                .collect()
+        } else {
+            vec![event.entity]
+        };
+        for target in targets {
+            let Some(mut entity) = commands.get_entity(target) else {
+                continue;
+            };
+            // Layer 0 alone is the implicit default, so keep scenes free of the component.
+            if event.layers == RenderLayers::default() {
+                entity.remove::<RenderLayers>();
+            } else {
+                entity.insert(event.layers.clone());
+            }
         }
     }
 }
@@ -1708,8 +4667,52 @@ fn apply_spawn_primitive_events(
                 },
                 Name::new("Spot Light"),
             )),
+            SpawnPrimitiveKind::AoVolume => commands.spawn((
+                WaffleSceneObject,
+                Name::new("AO Volume"),
+                AoVolume::default(),
+                SpatialBundle::from_transform(Transform::from_scale(Vec3::new(20.0, 8.0, 20.0))),
+            )),
+            SpawnPrimitiveKind::DollyTrack => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Dolly Track"),
+                DollyTrack::default(),
+                SpatialBundle::default(),
+            )),
+            SpawnPrimitiveKind::Portal => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Portal"),
+                Portal::default(),
+                SpatialBundle::from_transform(Transform::from_xyz(0.0, 1.5, 0.0)),
+            )),
+            SpawnPrimitiveKind::Mirror => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Mirror"),
+                Portal::mirror(),
+                SpatialBundle::from_transform(Transform::from_xyz(0.0, 1.5, 0.0)),
+            )),
+            SpawnPrimitiveKind::Terrain => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Terrain"),
+                WaffleTerrain::default(),
+                SpatialBundle::default(),
+            )),
+            SpawnPrimitiveKind::Foliage => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Foliage"),
+                FoliageLayer::default(),
+                SpatialBundle::default(),
+            )),
+            SpawnPrimitiveKind::Water => commands.spawn((
+                WaffleSceneObject,
+                Name::new("Water"),
+                WaffleWater::default(),
+                SpatialBundle::default(),
+            )),
         };
 
+        entity_commands.insert(SpawnSource::Primitive(event.kind));
+
         if let Some(parent_entity) = parent {
             entity_commands.set_parent(parent_entity);
         }
@@ -1723,8 +4726,11 @@ fn apply_spawn_asset_events(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     scene_settings: Option<Res<SceneSettings>>,
+    project_settings: Res<ProjectSettings>,
+    cache: Res<AssetBrowserCache>,
     scene_root_query: Query<Entity, With<WaffleSceneRoot>>,
 ) {
+    let import_transform = Transform::from_rotation(project_settings.import_up_axis.import_rotation());
     let default_material = scene_settings
         .as_ref()
         .map(|settings| settings.default_material.clone())
@@ -1741,26 +4747,63 @@ fn apply_spawn_asset_events(
     for event in events.read() {
         let parent = event.parent.or(root);
         let path = event.path.clone();
-        let extension = std::path::Path::new(&path)
+        let (file, label) = split_label(&path);
+        let extension = std::path::Path::new(file)
             .extension()
             .and_then(|ext| ext.to_str())
             .unwrap_or("")
             .to_ascii_lowercase();
-        let name = std::path::Path::new(&path)
+        let name = std::path::Path::new(file)
             .file_name()
             .and_then(|s| s.to_str())
             .unwrap_or("Asset")
             .to_string();
+        let sub_asset = label.and_then(|label| {
+            cache
+                .entries
+                .iter()
+                .find(|entry| entry.path == file)?
+                .sub_assets
+                .iter()
+                .find(|sub_asset| sub_asset.label == label)
+        });
 
-        let mut entity_commands = if matches!(extension.as_str(), "gltf" | "glb") {
+        let mut entity_commands = if let Some(sub_asset) = sub_asset {
+            // A mesh from a model wears its own material; a material goes
+            // on a cube to look at
+            let (mesh, material) = match sub_asset.kind {
+                SubAssetKind::Mesh => (
+                    asset_server.load(path.clone()),
+                    sub_asset
+                        .material
+                        .as_ref()
+                        .map(|material| asset_server.load(format!("{file}#{material}")))
+                        .unwrap_or_else(|| default_material.clone()),
+                ),
+                SubAssetKind::Material => (meshes.add(Cuboid::new(1.0, 1.0, 1.0)), asset_server.load(path.clone())),
+            };
+            commands.spawn((
+                WaffleSceneObject,
+                Name::new(sub_asset.name.clone()),
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: import_transform,
+                    ..default()
+                },
+            ))
+        } else if matches!(extension.as_str(), "gltf" | "glb") {
             let scene_path = format!("{path}#Scene0");
             commands.spawn((
                 WaffleSceneObject,
                 Name::new(name),
                 SceneBundle {
                     scene: asset_server.load(scene_path),
+                    transform: import_transform,
                     ..default()
                 },
+                // Skinned characters play their first clip instead of T-posing
+                WaffleAnimator::default(),
             ))
         } else if matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "tga") {
             let texture = asset_server.load(path.clone());
@@ -1779,14 +4822,24 @@ fn apply_spawn_asset_events(
                     ..default()
                 },
             ))
-        } else if extension == "obj" {
-            let mesh_handle: Handle<Mesh> = asset_server.load(path.clone());
+        } else if let Some(scene) = scene_name_from_asset_path(file) {
+            commands.spawn((
+                WaffleSceneObject,
+                Name::new(scene.to_string()),
+                SpatialBundle::default(),
+                SceneReference {
+                    scene: scene.to_string(),
+                },
+            ))
+        } else if extension == OBJ_EXTENSION {
+            // Each object wears the material its MTL file gives it
+            let scene_path = format!("{path}#{OBJ_SCENE_LABEL}");
             commands.spawn((
                 WaffleSceneObject,
                 Name::new(name),
-                PbrBundle {
-                    mesh: mesh_handle,
-                    material: default_material.clone(),
+                SceneBundle {
+                    scene: asset_server.load(scene_path),
+                    transform: import_transform,
                     ..default()
                 },
             ))
@@ -1802,6 +4855,8 @@ fn apply_spawn_asset_events(
             ))
         };
 
+        entity_commands.insert(SpawnSource::Asset(path));
+
         if let Some(parent_entity) = parent {
             entity_commands.set_parent(parent_entity);
         }
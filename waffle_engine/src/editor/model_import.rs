@@ -0,0 +1,173 @@
+//! Model Import Module
+//! Lists the meshes and materials inside glTF and OBJ files, so the asset
+//! browser can show them as sub-assets under the file. Each one is addressed
+//! by the label its loader gives it, e.g. `ship.glb#Mesh0/Primitive0`,
+//! `ship.glb#Material2` or `crate.obj#Mesh1`, so it can be spawned, dragged
+//! onto entities and edited on its own. Only the files are read; nothing is
+//! loaded.
+
+use std::path::Path;
+
+use crate::rendering::obj::{obj_material_label, obj_material_libraries, obj_mesh_label, parse_obj, OBJ_EXTENSION};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubAssetKind {
+    Mesh,
+    Material,
+}
+
+/// A mesh primitive or material inside a model file
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubAsset {
+    pub kind: SubAssetKind,
+    /// Label Bevy loads it by
+    pub label: String,
+    /// Name from the file, or the label without one
+    pub name: String,
+    /// Label of the material a mesh primitive is drawn with
+    pub material: Option<String>,
+}
+
+impl SubAsset {
+    /// Asset path of this sub-asset of the file at `path`
+    pub fn path(&self, path: &str) -> String {
+        format!("{path}#{}", self.label)
+    }
+}
+
+/// Split an asset path into its file and sub-asset label
+pub fn split_label(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('#') {
+        Some((file, label)) => (file, Some(label)),
+        None => (path, None),
+    }
+}
+
+/// Whether the file at `path` is a model with sub-assets to list
+pub fn has_sub_assets(path: &str) -> bool {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    ["gltf", "glb", OBJ_EXTENSION]
+        .iter()
+        .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+}
+
+/// The sub-assets of a `.gltf`, `.glb` or `.obj` file
+pub fn read_sub_assets(file: &Path) -> anyhow::Result<Vec<SubAsset>> {
+    let is_obj = file
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(OBJ_EXTENSION));
+    if is_obj { read_obj_sub_assets(file) } else { read_gltf_sub_assets(file) }
+}
+
+/// The mesh primitives, then the materials, of a `.gltf` or `.glb` file
+fn read_gltf_sub_assets(file: &Path) -> anyhow::Result<Vec<SubAsset>> {
+    let bytes = std::fs::read(file)?;
+    let json = if bytes.starts_with(GLB_MAGIC) { glb_json_chunk(&bytes)? } else { &bytes[..] };
+    let document: serde_json::Value = serde_json::from_slice(json)?;
+
+    let name_or = |value: &serde_json::Value, fallback: String| {
+        value
+            .get("name")
+            .and_then(|name| name.as_str())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .unwrap_or(fallback)
+    };
+    let array = |key: &str| document.get(key).and_then(|value| value.as_array()).cloned().unwrap_or_default();
+
+    let mut sub_assets = Vec::new();
+    for (mesh_index, mesh) in array("meshes").iter().enumerate() {
+        let mesh_name = name_or(mesh, format!("Mesh{mesh_index}"));
+        let primitives = mesh.get("primitives").and_then(|value| value.as_array()).cloned().unwrap_or_default();
+        for (primitive_index, primitive) in primitives.iter().enumerate() {
+            let name = if primitives.len() > 1 {
+                format!("{mesh_name} ({primitive_index})")
+            } else {
+                mesh_name.clone()
+            };
+            sub_assets.push(SubAsset {
+                kind: SubAssetKind::Mesh,
+                label: format!("Mesh{mesh_index}/Primitive{primitive_index}"),
+                name,
+                material: primitive
+                    .get("material")
+                    .and_then(|index| index.as_u64())
+                    .map(|index| format!("Material{index}")),
+            });
+        }
+    }
+    for (index, material) in array("materials").iter().enumerate() {
+        sub_assets.push(SubAsset {
+            kind: SubAssetKind::Material,
+            label: format!("Material{index}"),
+            name: name_or(material, format!("Material{index}")),
+            material: None,
+        });
+    }
+    Ok(sub_assets)
+}
+
+/// The objects, then the materials, of an `.obj` file and its material
+/// libraries
+fn read_obj_sub_assets(file: &Path) -> anyhow::Result<Vec<SubAsset>> {
+    let bytes = std::fs::read(file)?;
+    let directory = file.parent().unwrap_or(Path::new(""));
+    let libraries: Vec<(String, Vec<u8>)> = obj_material_libraries(&bytes)
+        .into_iter()
+        .filter_map(|name| {
+            let library = std::fs::read(directory.join(name.replace('\\', "/"))).ok()?;
+            Some((name, library))
+        })
+        .collect();
+    let (models, materials) = parse_obj(&bytes, |name| {
+        libraries
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, library)| library.clone())
+    })?;
+
+    let mut sub_assets = Vec::new();
+    for (index, model) in models.iter().enumerate() {
+        sub_assets.push(SubAsset {
+            kind: SubAssetKind::Mesh,
+            label: obj_mesh_label(index),
+            name: if model.name.is_empty() { obj_mesh_label(index) } else { model.name.clone() },
+            material: model
+                .mesh
+                .material_id
+                .filter(|id| *id < materials.len())
+                .map(obj_material_label),
+        });
+    }
+    for (index, material) in materials.iter().enumerate() {
+        sub_assets.push(SubAsset {
+            kind: SubAssetKind::Material,
+            label: obj_material_label(index),
+            name: if material.name.is_empty() { obj_material_label(index) } else { material.name.clone() },
+            material: None,
+        });
+    }
+    Ok(sub_assets)
+}
+
+/// The JSON chunk of a binary glTF: a 12 byte header, then chunks of a
+/// length, a type and the data
+fn glb_json_chunk(bytes: &[u8]) -> anyhow::Result<&[u8]> {
+    let read_u32 = |offset: usize| -> anyhow::Result<u32> {
+        let word = bytes
+            .get(offset..offset + 4)
+            .ok_or_else(|| anyhow::anyhow!("binary glTF is truncated"))?;
+        Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+    let length = read_u32(12)? as usize;
+    if read_u32(16)? != GLB_JSON_CHUNK {
+        anyhow::bail!("binary glTF does not start with a JSON chunk");
+    }
+    bytes
+        .get(20..20 + length)
+        .ok_or_else(|| anyhow::anyhow!("binary glTF is truncated"))
+}
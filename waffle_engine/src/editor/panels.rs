@@ -1,215 +1,218 @@
-/// Editor Panels Module
-/// Individual panel implementations for the editor UI
+//! Editor Panels Module
+//! Individual panel implementations for the editor UI
 
 use bevy::prelude::*;
 use bevy_egui::egui;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use super::{
-    AssetBrowserCache, AssetEntry, AssetKind, EditorOutput, EditorState, EditorSettings,
-    HierarchyReparentEvent, HierarchySnapshot, SpawnAssetEvent, SpawnPrimitiveEvent,
-    SpawnPrimitiveKind,
+    AssetBrowserCache, BehaviorTreeEditorState, DialogueEditorState, ParticleEditorState, AssetEntry, DebugLabel, AssetKind, EditorOutput, OutputEntry, EditorState, EditorSettings,
+    GizmoAxis, GizmoMode, GizmoOverlay, HierarchyReparentEvent, HierarchySnapshot,
+    CameraRigEditEvent, CameraRigPart, ConstraintEditEvent, ConstraintKind, LuaScriptEditEvent, StateMachineEditEvent, ParticleEmitterEditEvent, ParticleEmitterEditKind, TerrainEditEvent, TerrainEditKind, FoliageEditEvent, FoliageEditKind, WaterEditEvent, WaterEditKind, LodEditEvent, LodEditKind, AudioSourceEditEvent, AudioSourceEditKind, MaterialEditEvent, MaterialEditKind, MaterialLibraryEvent, PivotEditEvent, PivotEditKind, RenderLayersEditEvent, InstancingEditEvent,
+    ReimportAssetEvent, SurfaceEditEvent, SurfaceEditKind, KeyframeEditEvent, KeyframeEditKind,
+    AxisSpace, RotationDisplay, RotationEditCache, SpawnAssetEvent, SpawnPrimitiveEvent, SpawnPrimitiveKind, ViewportLayout, ViewportStats,
+    ViewportView,
 };
+use super::external::OpenExternalEvent;
+use super::model_import::{split_label, SubAssetKind};
+use super::vcs::{VcsActionEvent, VcsFileStatus, VcsStatus};
+use super::thumbnails::THUMBNAIL_SIZE;
+use super::asset_refs::{AssetFileAction, AssetFileDialog};
+use super::collab::PresenceTag;
+use super::selection::SelectMode;
+use super::sub_scene::{
+    scene_name_from_asset_path, SceneReference, SubSceneEditEvent, SubSceneEditKind, SubSceneInstance,
+};
+use super::terrain_sculpt::TerrainBrushMode;
+use super::tools::{ActiveTool, CustomEditorTool, EditorTool};
+use super::theme::EditorPalette;
+use super::accessibility::{describe_hierarchy_row, AccessibleName};
+use crate::core::asset_meta::{AssetMeta, TextureFiltering};
+use crate::core::project::LengthUnit;
+use crate::core::surface::PhysicalSurface;
+use crate::rendering::color::WorkingColorSpace;
+use crate::rendering::camera::OrthoView;
+use crate::rendering::placeholders::{LocateMissingAssetEvent, MissingAsset, MissingAssetKind};
+use crate::rendering::sun::{GeoSunLocation, days_in_month, solar_position};
 
 #[derive(Clone)]
 enum DragPayload {
     Entity(Entity),
     Asset(String),
+    /// Index into the material library
+    Material(usize),
 }
 
 /// Draw the viewport panel
 pub fn draw_viewport_panel(
     ui: &mut egui::Ui,
     editor_state: &mut EditorState,
-    _editor_settings: &mut EditorSettings,
+    editor_settings: &mut EditorSettings,
+    active_tool: &mut ActiveTool,
+    custom_tools: &[CustomEditorTool],
+    units: LengthUnit,
+    safe_margin: Vec2,
     viewport_texture_id: Option<egui::TextureId>,
+    ortho_texture_ids: &[(OrthoView, egui::TextureId)],
+    viewport_stats: Option<&ViewportStats>,
+    debug_labels: &[DebugLabel],
+    diagnostics: &bevy::diagnostic::DiagnosticsStore,
 ) {
     ui.vertical_centered(|ui| {
         ui.heading("3D Viewport");
 
         ui.separator();
 
-        // Viewport placeholder
         let available_size = ui.available_size();
         let viewport_size = egui::vec2(
             available_size.x.max(0.0),
             (available_size.y - 50.0).max(0.0),
         );
-        let (viewport_rect, viewport_response) = if let Some(texture_id) = viewport_texture_id {
-            let image = egui::Image::new(egui::load::SizedTexture::new(
-                texture_id,
-                viewport_size,
-            ))
-            .fit_to_exact_size(viewport_size)
-            .sense(egui::Sense::click_and_drag());
-            let response = ui.add(image);
-            (response.rect, response)
-        } else {
-            let (rect, response) = ui.allocate_exact_size(viewport_size, egui::Sense::click_and_drag());
-            ui.painter().text(
-                rect.center(),
-                egui::Align2::CENTER_CENTER,
-                "Viewport render target not ready",
-                egui::TextStyle::Body.resolve(ui.style()),
-                egui::Color32::from_rgb(180, 180, 180),
-            );
-            (rect, response)
-        };
+        let (area_rect, _) = ui.allocate_exact_size(viewport_size, egui::Sense::hover());
 
-        let pixels_per_point = ui.ctx().pixels_per_point();
-        editor_state.viewport_size = Vec2::new(
-            viewport_rect.width() * pixels_per_point,
-            viewport_rect.height() * pixels_per_point,
-        );
-        editor_state.viewport_origin = Vec2::new(viewport_rect.min.x, viewport_rect.min.y);
-        editor_state.viewport_hovered = viewport_response.hovered();
-        let primary_pressed = ui.input(|i| i.pointer.primary_pressed());
-        editor_state.viewport_clicked = primary_pressed && viewport_response.hovered();
-        editor_state.viewport_click_pos = None;
-        if editor_state.viewport_clicked {
-            if let Some(pointer_pos) = ui.ctx().pointer_latest_pos() {
-                let local = pointer_pos - viewport_rect.min;
-                let local_pixels = local * pixels_per_point;
-                editor_state.viewport_click_pos = Some(Vec2::new(local_pixels.x, local_pixels.y));
+        let mut panes: Vec<(ViewportView, egui::Rect, Option<egui::TextureId>)> = Vec::new();
+        match editor_state.viewport_layout {
+            ViewportLayout::Single => {
+                panes.push((ViewportView::Perspective, area_rect, viewport_texture_id));
+            }
+            ViewportLayout::Quad => {
+                let gap = 2.0;
+                let cell_size = ((area_rect.size() - egui::vec2(gap, gap)) * 0.5).max(egui::Vec2::ZERO);
+                let cell = |col: f32, row: f32| {
+                    egui::Rect::from_min_size(
+                        area_rect.min + egui::vec2(col * (cell_size.x + gap), row * (cell_size.y + gap)),
+                        cell_size,
+                    )
+                };
+                panes.push((ViewportView::Perspective, cell(0.0, 0.0), viewport_texture_id));
+                for (view, (col, row)) in OrthoView::ALL
+                    .into_iter()
+                    .zip([(1.0, 0.0), (0.0, 1.0), (1.0, 1.0)])
+                {
+                    let texture_id = ortho_texture_ids
+                        .iter()
+                        .find(|(target_view, _)| *target_view == view)
+                        .map(|(_, texture_id)| *texture_id);
+                    panes.push((ViewportView::Ortho(view), cell(col, row), texture_id));
+                }
             }
         }
 
-        // Draw border
-        ui.painter().rect_stroke(
-            viewport_rect,
-            4.0,
-            egui::Stroke::new(1.0, egui::Color32::from_rgb(80, 80, 80))
-        );
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let primary_pressed = ui.input(|i| i.pointer.primary_pressed());
+        let scroll_delta = ui.input(|i| i.smooth_scroll_delta.y);
+        let pointer_pos = ui.ctx().pointer_latest_pos();
+        editor_state.viewport_hovered = false;
+        editor_state.viewport_clicked = false;
+        editor_state.viewport_click_pos = None;
+        editor_state.viewport_pointer_pos = None;
+        let mut pane_clicked = false;
 
-        // Draw label without blocking the scene
-        ui.painter().text(
-            viewport_rect.left_top() + egui::vec2(8.0, 6.0),
-            egui::Align2::LEFT_TOP,
-            "3D Scene Viewport",
-            egui::TextStyle::Body.resolve(ui.style()),
-            egui::Color32::from_rgb(180, 180, 180),
-        );
+        for (view, rect, texture_id) in panes {
+            let label = match (editor_state.viewport_layout, view) {
+                (ViewportLayout::Single, _) => "3D Scene Viewport",
+                (_, ViewportView::Perspective) => "Perspective",
+                (_, ViewportView::Ortho(ortho)) => ortho.label(),
+            };
+            let response = draw_viewport_pane(ui, rect, texture_id, label, view == editor_state.active_view);
+            // Render target pixels per egui point; only the perspective view
+            // is render scaled
+            let pixels_per_point = match view {
+                ViewportView::Perspective => pixels_per_point * editor_settings.render_scale,
+                ViewportView::Ortho(_) => pixels_per_point,
+            };
+            let size_pixels = Vec2::new(rect.width() * pixels_per_point, rect.height() * pixels_per_point);
 
-        if let Some(overlay) = editor_state.gizmo_overlay.as_ref() {
-            let painter = ui.painter();
-            let ppp = ui.ctx().pixels_per_point();
-            let to_points = |p: Vec2| egui::pos2(
-                viewport_rect.min.x + p.x / ppp,
-                viewport_rect.min.y + p.y / ppp,
-            );
-            let origin = to_points(overlay.origin);
-            let x_end = to_points(overlay.x_end);
-            let y_end = to_points(overlay.y_end);
-            let z_end = to_points(overlay.z_end);
-
-            let axis_color = |axis: super::GizmoAxis, active: Option<super::GizmoAxis>| {
-                let is_active = active == Some(axis);
-                match axis {
-                    super::GizmoAxis::X => if is_active {
-                        egui::Color32::from_rgb(255, 140, 140)
-                    } else {
-                        egui::Color32::from_rgb(230, 70, 70)
-                    },
-                    super::GizmoAxis::Y => if is_active {
-                        egui::Color32::from_rgb(140, 255, 140)
-                    } else {
-                        egui::Color32::from_rgb(70, 230, 70)
-                    },
-                    super::GizmoAxis::Z => if is_active {
-                        egui::Color32::from_rgb(140, 200, 255)
-                    } else {
-                        egui::Color32::from_rgb(70, 140, 230)
-                    },
+            let overlay = match view {
+                ViewportView::Perspective => {
+                    editor_state.viewport_size = size_pixels;
+                    editor_state.viewport_origin = Vec2::new(rect.min.x, rect.min.y);
+                    editor_state.gizmo_overlay.clone()
+                }
+                ViewportView::Ortho(ortho) => {
+                    let Some(pane) = editor_state
+                        .ortho_viewports
+                        .iter_mut()
+                        .find(|pane| pane.view == ortho)
+                    else {
+                        continue;
+                    };
+                    pane.size = size_pixels;
+                    if response.hovered() && scroll_delta != 0.0 {
+                        pane.zoom_delta += scroll_delta / 50.0;
+                    }
+                    if response.dragged_by(egui::PointerButton::Middle) {
+                        let delta = response.drag_delta() * pixels_per_point;
+                        pane.pan_delta += Vec2::new(delta.x, delta.y);
+                    }
+                    pane.gizmo_overlay.clone()
                 }
             };
 
-            match editor_state.gizmo_mode {
-                super::GizmoMode::Move => {
-                    draw_axis_arrow(
-                        painter,
-                        origin,
-                        x_end,
-                        axis_color(super::GizmoAxis::X, editor_state.active_axis),
-                    );
-                    draw_axis_arrow(
-                        painter,
-                        origin,
-                        y_end,
-                        axis_color(super::GizmoAxis::Y, editor_state.active_axis),
-                    );
-                    draw_axis_arrow(
-                        painter,
-                        origin,
-                        z_end,
-                        axis_color(super::GizmoAxis::Z, editor_state.active_axis),
-                    );
+            if view == ViewportView::Perspective {
+                draw_debug_labels(ui, rect, debug_labels, pixels_per_point);
+                if editor_settings.show_safe_area {
+                    draw_safe_area_overlay(ui, rect, safe_margin);
                 }
-                super::GizmoMode::Rotate => {
-                    if let Some(rings) = overlay.rotate_rings.as_ref() {
-                        let x_points: Vec<egui::Pos2> = rings
-                            .x_points
-                            .iter()
-                            .map(|p| to_points(*p))
-                            .collect();
-                        let y_points: Vec<egui::Pos2> = rings
-                            .y_points
-                            .iter()
-                            .map(|p| to_points(*p))
-                            .collect();
-                        let z_points: Vec<egui::Pos2> = rings
-                            .z_points
-                            .iter()
-                            .map(|p| to_points(*p))
-                            .collect();
-                        draw_axis_polyline(
-                            painter,
-                            &x_points,
-                            axis_color(super::GizmoAxis::X, editor_state.active_axis),
-                        );
-                        draw_axis_polyline(
-                            painter,
-                            &y_points,
-                            axis_color(super::GizmoAxis::Y, editor_state.active_axis),
-                        );
-                        draw_axis_polyline(
-                            painter,
-                            &z_points,
-                            axis_color(super::GizmoAxis::Z, editor_state.active_axis),
-                        );
-                    }
+            }
+            if let (ViewportView::Perspective, Some(stats)) = (view, viewport_stats) {
+                draw_stats_overlay(ui, rect, stats, diagnostics, editor_settings.show_fps);
+            }
+
+            if response.hovered() {
+                editor_state.viewport_hovered = true;
+                if let Some(pointer_pos) = pointer_pos {
+                    let local_pixels = (pointer_pos - rect.min) * pixels_per_point;
+                    editor_state.viewport_pointer_pos = Some(Vec2::new(local_pixels.x, local_pixels.y));
+                    editor_state.viewport_pointer_view = view;
                 }
-                super::GizmoMode::Scale => {
-                    draw_axis_scale(
-                        painter,
-                        origin,
-                        x_end,
-                        axis_color(super::GizmoAxis::X, editor_state.active_axis),
-                    );
-                    draw_axis_scale(
-                        painter,
-                        origin,
-                        y_end,
-                        axis_color(super::GizmoAxis::Y, editor_state.active_axis),
-                    );
-                    draw_axis_scale(
-                        painter,
-                        origin,
-                        z_end,
-                        axis_color(super::GizmoAxis::Z, editor_state.active_axis),
-                    );
+                if primary_pressed {
+                    editor_state.viewport_clicked = true;
+                    editor_state.viewport_click_additive = ui.input(|i| i.modifiers.shift);
+                    editor_state.viewport_click_view = view;
+                    editor_state.active_view = view;
+                    if let Some(pointer_pos) = pointer_pos {
+                        let local_pixels = (pointer_pos - rect.min) * pixels_per_point;
+                        editor_state.viewport_click_pos = Some(Vec2::new(local_pixels.x, local_pixels.y));
+                        editor_state.viewport_click_pixels_per_point = pixels_per_point;
+                    }
                 }
             }
+            if response.clicked() {
+                pane_clicked = true;
+            }
+
+            // Otherwise the gizmo is drawn into the scene
+            if let Some(overlay) = overlay.as_ref().filter(|_| editor_settings.gizmo.always_on_top) {
+                draw_gizmo_overlay(
+                    ui.painter(),
+                    rect,
+                    overlay,
+                    active_tool.tool.gizmo_mode(),
+                    editor_state.active_axis,
+                    &editor_settings.theme.palette,
+                    pixels_per_point,
+                    editor_settings.gizmo.line_thickness,
+                );
+            }
+        }
+
+        let palette_rect = draw_tool_palette(ui, area_rect, active_tool, custom_tools, units);
+        if pointer_pos.is_some_and(|pos| palette_rect.contains(pos)) {
+            editor_state.viewport_clicked = false;
+            editor_state.viewport_click_pos = None;
+            editor_state.viewport_pointer_pos = None;
         }
 
         // Handle viewport focus
         let primary_clicked = ui.input(|i| i.pointer.primary_clicked());
         let right_down = ui.input(|i| i.pointer.secondary_down());
         let escape_pressed = ui.input(|i| i.key_pressed(egui::Key::Escape));
-        if viewport_response.clicked() || (right_down && viewport_response.hovered()) {
+        if pane_clicked || (right_down && editor_state.viewport_hovered) {
             editor_state.viewport_focused = true;
         } else if escape_pressed {
             editor_state.viewport_focused = false;
-        } else if primary_clicked && !viewport_response.hovered() {
+        } else if primary_clicked && !editor_state.viewport_hovered {
             editor_state.viewport_focused = false;
         }
 
@@ -219,15 +222,321 @@ pub fn draw_viewport_panel(
     });
 }
 
+/// Vertical tool palette over the top-left of the viewport, followed by the
+/// active tool's options
+fn draw_tool_palette(
+    ui: &mut egui::Ui,
+    area_rect: egui::Rect,
+    active_tool: &mut ActiveTool,
+    custom_tools: &[CustomEditorTool],
+    units: LengthUnit,
+) -> egui::Rect {
+    let min = area_rect.left_top() + egui::vec2(8.0, 28.0);
+    let max = egui::pos2(min.x + 110.0, area_rect.bottom().max(min.y));
+    let mut palette_ui = ui.child_ui(
+        egui::Rect::from_min_max(min, max),
+        egui::Layout::top_down(egui::Align::Min),
+        None,
+    );
+    egui::Frame::none()
+        .fill(egui::Color32::from_black_alpha(170))
+        .rounding(4.0)
+        .inner_margin(4.0)
+        .show(&mut palette_ui, |ui| {
+            ui.set_width(96.0);
+            ui.spacing_mut().item_spacing.y = 2.0;
+            for tool in EditorTool::BUILT_IN {
+                let hint = match tool.shortcut() {
+                    Some((_, key)) => format!("{} ({})", tool.label(), key),
+                    None => tool.label().to_string(),
+                };
+                if ui
+                    .selectable_label(active_tool.tool == tool, tool.label())
+                    .on_hover_text(hint)
+                    .clicked()
+                {
+                    active_tool.set(tool);
+                }
+            }
+            if !custom_tools.is_empty() {
+                ui.separator();
+                for custom in custom_tools {
+                    let tool = EditorTool::Custom(custom.id.clone());
+                    if ui.selectable_label(active_tool.tool == tool, &custom.label).clicked() {
+                        active_tool.set(tool);
+                    }
+                }
+            }
+
+            match active_tool.tool {
+                EditorTool::Paint => {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Color");
+                        let mut color = color_to_egui(active_tool.paint_color);
+                        if ui.color_edit_button_srgba(&mut color).changed() {
+                            active_tool.paint_color = egui_to_color(color);
+                        }
+                    });
+                }
+                EditorTool::Measure => {
+                    ui.separator();
+                    match active_tool.measured_distance() {
+                        Some(distance) => {
+                            ui.label(units.format(distance));
+                        }
+                        None => {
+                            ui.small("Click two points");
+                        }
+                    }
+                    if !active_tool.measure_points.is_empty() && ui.small_button("Clear").clicked() {
+                        active_tool.measure_points.clear();
+                    }
+                }
+                EditorTool::Sculpt => {
+                    ui.separator();
+                    let brush = &mut active_tool.terrain_brush;
+                    for mode in TerrainBrushMode::ALL {
+                        ui.selectable_value(&mut brush.mode, mode, mode.label());
+                    }
+                    ui.label("Radius");
+                    ui.add(egui::DragValue::new(&mut brush.radius).speed(0.1).range(0.1..=1000.0))
+                        .accessible_name("Brush radius");
+                    ui.label("Strength");
+                    ui.add(egui::DragValue::new(&mut brush.strength).speed(0.05).range(0.01..=100.0))
+                        .accessible_name("Brush strength");
+                    ui.small("Ctrl+Z undoes a stroke");
+                }
+                EditorTool::Foliage => {
+                    ui.separator();
+                    let brush = &mut active_tool.foliage_brush;
+                    ui.selectable_value(&mut brush.erase, false, "Paint");
+                    ui.selectable_value(&mut brush.erase, true, "Erase");
+                    ui.label("Radius");
+                    ui.add(egui::DragValue::new(&mut brush.radius).speed(0.1).range(0.1..=1000.0))
+                        .accessible_name("Brush radius");
+                    ui.label("Density");
+                    ui.add(egui::DragValue::new(&mut brush.density).speed(0.05).range(0.01..=100.0))
+                        .accessible_name("Foliage density")
+                        .on_hover_text("Copies per square unit");
+                    ui.label("Scale");
+                    ui.add(egui::DragValue::new(&mut brush.scale_min).speed(0.01).range(0.01..=100.0))
+                        .accessible_name("Minimum scale");
+                    ui.add(egui::DragValue::new(&mut brush.scale_max).speed(0.01).range(0.01..=100.0))
+                        .accessible_name("Maximum scale");
+                    ui.checkbox(&mut brush.random_yaw, "Random Yaw");
+                    ui.checkbox(&mut brush.align_to_surface, "Align");
+                    ui.small("Paints the selected layer; Shift erases");
+                }
+                _ => {}
+            }
+        })
+        .response
+        .rect
+}
+
+fn draw_debug_labels(ui: &egui::Ui, rect: egui::Rect, labels: &[DebugLabel], pixels_per_point: f32) {
+    let painter = ui.painter_at(rect);
+    for label in labels {
+        let position = rect.min + egui::vec2(label.position.x, label.position.y) / pixels_per_point;
+        painter.text(
+            position,
+            egui::Align2::CENTER_BOTTOM,
+            &label.text,
+            egui::FontId::monospace(12.0),
+            color_to_egui(label.color),
+        );
+    }
+}
+
+/// Frames for the TV action and title safe areas, and the project's own
+/// safe area where it differs from both
+fn draw_safe_area_overlay(ui: &egui::Ui, rect: egui::Rect, safe_margin: Vec2) {
+    let painter = ui.painter_at(rect);
+    let inset = |margin: Vec2| rect.shrink2(egui::vec2(rect.width() * margin.x, rect.height() * margin.y));
+    let font = egui::FontId::proportional(11.0);
+
+    let reference = [
+        ("Action Safe", 0.035, egui::Color32::from_rgba_unmultiplied(120, 200, 255, 140)),
+        ("Title Safe", 0.05, egui::Color32::from_rgba_unmultiplied(255, 210, 90, 140)),
+    ];
+    for (label, margin, color) in reference {
+        let frame = inset(Vec2::splat(margin));
+        painter.rect_stroke(frame, 0.0, egui::Stroke::new(1.0, color));
+        painter.text(frame.right_top() + egui::vec2(-4.0, 2.0), egui::Align2::RIGHT_TOP, label, font.clone(), color);
+    }
+
+    let is_reference = [0.035, 0.05].iter().any(|margin| safe_margin == Vec2::splat(*margin));
+    if safe_margin != Vec2::ZERO && !is_reference {
+        let color = egui::Color32::from_rgb(120, 255, 140);
+        let frame = inset(safe_margin);
+        painter.rect_stroke(frame, 0.0, egui::Stroke::new(1.5, color));
+        painter.text(frame.left_bottom() + egui::vec2(4.0, -2.0), egui::Align2::LEFT_BOTTOM, "UI Safe Area", font, color);
+    }
+}
+
+fn draw_stats_overlay(
+    ui: &egui::Ui,
+    rect: egui::Rect,
+    stats: &ViewportStats,
+    diagnostics: &bevy::diagnostic::DiagnosticsStore,
+    show_fps: bool,
+) {
+    let mut lines = Vec::new();
+    if show_fps {
+        let fps = diagnostics
+            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|diag| diag.smoothed());
+        let frame_time = diagnostics
+            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|diag| diag.smoothed());
+        lines.push(format!(
+            "FPS: {}",
+            fps.map(|v| format!("{v:.1}")).unwrap_or_else(|| "--".into())
+        ));
+        lines.push(format!(
+            "Frame Time: {} ms",
+            frame_time.map(|v| format!("{v:.2}")).unwrap_or_else(|| "--".into())
+        ));
+    }
+    lines.push(format!("Entities: {}", stats.entity_count));
+    lines.push(format!("Triangles: {}", stats.triangle_count));
+    lines.push(format!("Draw Calls: {}", stats.draw_call_count));
+    if let Some(position) = stats.camera_position {
+        lines.push(format!(
+            "Camera: {:.2}, {:.2}, {:.2}",
+            position.x, position.y, position.z
+        ));
+    }
+
+    let painter = ui.painter().with_clip_rect(rect);
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let galley = painter.layout_no_wrap(
+        lines.join("\n"),
+        font,
+        egui::Color32::from_rgb(220, 220, 220),
+    );
+    let text_pos = egui::pos2(rect.right() - galley.size().x - 12.0, rect.top() + 10.0);
+    painter.rect_filled(
+        egui::Rect::from_min_size(text_pos, galley.size()).expand(6.0),
+        4.0,
+        egui::Color32::from_black_alpha(160),
+    );
+    painter.galley(text_pos, galley, egui::Color32::WHITE);
+}
+
+fn draw_viewport_pane(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    texture_id: Option<egui::TextureId>,
+    label: &str,
+    active: bool,
+) -> egui::Response {
+    let response = if let Some(texture_id) = texture_id {
+        let image = egui::Image::new(egui::load::SizedTexture::new(texture_id, rect.size()))
+            .fit_to_exact_size(rect.size())
+            .sense(egui::Sense::click_and_drag());
+        ui.put(rect, image)
+    } else {
+        let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Viewport render target not ready",
+            egui::TextStyle::Body.resolve(ui.style()),
+            egui::Color32::from_rgb(180, 180, 180),
+        );
+        response
+    };
+
+    // Draw border
+    let border_color = if active {
+        egui::Color32::from_rgb(110, 110, 110)
+    } else {
+        egui::Color32::from_rgb(80, 80, 80)
+    };
+    ui.painter().rect_stroke(rect, 4.0, egui::Stroke::new(1.0, border_color));
+
+    // Draw label without blocking the scene
+    ui.painter().text(
+        rect.left_top() + egui::vec2(8.0, 6.0),
+        egui::Align2::LEFT_TOP,
+        label,
+        egui::TextStyle::Body.resolve(ui.style()),
+        egui::Color32::from_rgb(180, 180, 180),
+    );
+
+    response
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_gizmo_overlay(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    overlay: &GizmoOverlay,
+    gizmo_mode: Option<GizmoMode>,
+    active_axis: Option<GizmoAxis>,
+    palette: &EditorPalette,
+    pixels_per_point: f32,
+    thickness: f32,
+) {
+    let Some(gizmo_mode) = gizmo_mode else {
+        return;
+    };
+    let to_points = |p: Vec2| egui::pos2(
+        rect.min.x + p.x / pixels_per_point,
+        rect.min.y + p.y / pixels_per_point,
+    );
+    let origin = to_points(overlay.origin);
+    let x_end = to_points(overlay.x_end);
+    let y_end = to_points(overlay.y_end);
+    let z_end = to_points(overlay.z_end);
+
+    let axis_color = |axis: GizmoAxis| {
+        let index = match axis {
+            GizmoAxis::X => 0,
+            GizmoAxis::Y => 1,
+            GizmoAxis::Z => 2,
+        };
+        palette.axis_color32(index, active_axis == Some(axis))
+    };
+
+    match gizmo_mode {
+        GizmoMode::Move => {
+            draw_axis_arrow(painter, origin, x_end, axis_color(GizmoAxis::X), thickness);
+            draw_axis_arrow(painter, origin, y_end, axis_color(GizmoAxis::Y), thickness);
+            draw_axis_arrow(painter, origin, z_end, axis_color(GizmoAxis::Z), thickness);
+        }
+        GizmoMode::Rotate => {
+            if let Some(rings) = overlay.rotate_rings.as_ref() {
+                for (axis, points) in [
+                    (GizmoAxis::X, &rings.x_points),
+                    (GizmoAxis::Y, &rings.y_points),
+                    (GizmoAxis::Z, &rings.z_points),
+                ] {
+                    let points: Vec<egui::Pos2> = points.iter().map(|p| to_points(*p)).collect();
+                    draw_axis_polyline(painter, &points, axis_color(axis), thickness);
+                }
+            }
+        }
+        GizmoMode::Scale => {
+            draw_axis_scale(painter, origin, x_end, axis_color(GizmoAxis::X), thickness);
+            draw_axis_scale(painter, origin, y_end, axis_color(GizmoAxis::Y), thickness);
+            draw_axis_scale(painter, origin, z_end, axis_color(GizmoAxis::Z), thickness);
+        }
+    }
+}
+
 fn draw_axis_arrow(
     painter: &egui::Painter,
     origin: egui::Pos2,
     end: egui::Pos2,
     color: egui::Color32,
+    thickness: f32,
 ) {
-    painter.line_segment([origin, end], egui::Stroke::new(2.0, color));
+    painter.line_segment([origin, end], egui::Stroke::new(thickness, color));
     let dir = (end - origin).normalized();
-    let head = 8.0;
+    let head = 4.0 * thickness;
     let left = egui::vec2(-dir.y, dir.x);
     let p1 = end - dir * head + left * (head * 0.6);
     let p2 = end - dir * head - left * (head * 0.6);
@@ -238,13 +547,14 @@ fn draw_axis_polyline(
     painter: &egui::Painter,
     points: &[egui::Pos2],
     color: egui::Color32,
+    thickness: f32,
 ) {
     if points.len() < 2 {
         return;
     }
     painter.add(egui::Shape::line(
         points.to_vec(),
-        egui::Stroke::new(2.0, color),
+        egui::Stroke::new(thickness, color),
     ));
 }
 
@@ -253,9 +563,10 @@ fn draw_axis_scale(
     origin: egui::Pos2,
     end: egui::Pos2,
     color: egui::Color32,
+    thickness: f32,
 ) {
-    painter.line_segment([origin, end], egui::Stroke::new(2.0, color));
-    let size = 6.0;
+    painter.line_segment([origin, end], egui::Stroke::new(thickness, color));
+    let size = 3.0 * thickness;
     let rect = egui::Rect::from_center_size(end, egui::vec2(size * 1.6, size * 1.6));
     painter.rect_filled(rect, 1.0, color);
 }
@@ -269,6 +580,7 @@ pub fn draw_hierarchy_panel(
     reparent_events: &mut Vec<HierarchyReparentEvent>,
     spawn_primitive_queue: &mut Vec<SpawnPrimitiveEvent>,
     spawn_asset_queue: &mut Vec<SpawnAssetEvent>,
+    material_library_queue: &mut Vec<MaterialLibraryEvent>,
 ) {
     ui.vertical(|ui| {
         ui.heading("Scene Hierarchy");
@@ -327,11 +639,60 @@ pub fn draw_hierarchy_panel(
                     });
                     ui.close_menu();
                 }
+                ui.separator();
+                if ui.button("AO Volume").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::AoVolume,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Dolly Track").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::DollyTrack,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Portal").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::Portal,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Mirror").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::Mirror,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Terrain").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::Terrain,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Foliage").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::Foliage,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Water").clicked() {
+                    spawn_primitive_queue.push(SpawnPrimitiveEvent {
+                        kind: SpawnPrimitiveKind::Water,
+                        parent: None,
+                    });
+                    ui.close_menu();
+                }
             });
             if ui.button("X").on_hover_text("Delete").clicked() {
-                if let Some(entity) = editor_state.selected_entity {
-                    editor_state.delete_confirm = Some(entity);
-                }
+                editor_state.delete_confirm = editor_state.selection.entities().to_vec();
             }
             if ui.button("D").clicked() {
                 // TODO: Duplicate selected entity
@@ -359,7 +720,7 @@ pub fn draw_hierarchy_panel(
                     ui.label("Scene Root");
                 });
                 if root_response.response.clicked() {
-                    editor_state.selected_entity = None;
+                    editor_state.selection.clear();
                     clicked_entity = true;
                 }
                 if let Some(payload) = dropped_root.map(|payload: std::sync::Arc<DragPayload>| (*payload).clone()) {
@@ -372,6 +733,7 @@ pub fn draw_hierarchy_panel(
                             path,
                             parent: None,
                         }),
+                        DragPayload::Material(_) => {}
                     }
                 }
 
@@ -386,6 +748,7 @@ pub fn draw_hierarchy_panel(
                             editor_state,
                             reparent_events,
                             spawn_asset_queue,
+                            material_library_queue,
                             &mut clicked_entity,
                         );
                     }
@@ -408,10 +771,12 @@ pub fn draw_hierarchy_panel(
                 if matches.is_empty() {
                     ui.label("No matching entities");
                 } else {
+                    let order: Vec<Entity> = matches.iter().map(|(entity, _)| *entity).collect();
                     for (entity, name) in matches {
-                        let selected = editor_state.selected_entity == Some(entity);
+                        let selected = editor_state.selection.contains(entity);
                         if ui.selectable_label(selected, name).clicked() {
-                            editor_state.selected_entity = Some(entity);
+                            let mode = SelectMode::from_egui(ui.input(|i| i.modifiers));
+                            editor_state.selection.click(entity, mode, &order);
                             clicked_entity = true;
                         }
                     }
@@ -423,7 +788,7 @@ pub fn draw_hierarchy_panel(
         if pointer_clicked && !clicked_entity {
             if let Some(pos) = pointer_pos {
                 if scroll_response.inner_rect.contains(pos) {
-                    editor_state.selected_entity = None;
+                    editor_state.selection.clear();
                 }
             }
         }
@@ -431,6 +796,13 @@ pub fn draw_hierarchy_panel(
     });
 }
 
+/// Ctrl-click toggles an entity, Shift-click selects the listed range
+fn select_in_hierarchy(ui: &egui::Ui, editor_state: &mut EditorState, entity: Entity, hierarchy: &HierarchySnapshot) {
+    let mode = SelectMode::from_egui(ui.input(|i| i.modifiers));
+    let order = if mode == SelectMode::Range { hierarchy.listed_order() } else { Vec::new() };
+    editor_state.selection.click(entity, mode, &order);
+}
+
 fn draw_hierarchy_node(
     ui: &mut egui::Ui,
     entity: Entity,
@@ -438,6 +810,7 @@ fn draw_hierarchy_node(
     editor_state: &mut EditorState,
     reparent_events: &mut Vec<HierarchyReparentEvent>,
     spawn_asset_queue: &mut Vec<SpawnAssetEvent>,
+    material_library_queue: &mut Vec<MaterialLibraryEvent>,
     clicked_entity: &mut bool,
 ) {
     let name = hierarchy
@@ -445,7 +818,7 @@ fn draw_hierarchy_node(
         .get(&entity)
         .map(|s| s.as_str())
         .unwrap_or("Entity");
-    let selected = editor_state.selected_entity == Some(entity);
+    let selected = editor_state.selection.contains(entity);
 
     let drag_payload = DragPayload::Entity(entity);
     let frame = egui::Frame::none()
@@ -453,7 +826,8 @@ fn draw_hierarchy_node(
         .inner_margin(egui::Margin::symmetric(4.0, 1.0));
 
     ui.push_id(entity, |ui| {
-        if let Some(children) = hierarchy.children.get(&entity) {
+        let children = hierarchy.children.get(&entity).filter(|_| !hierarchy.locked_sub_scenes.contains(&entity));
+        if let Some(children) = children {
             let id = ui.make_persistent_id(("hierarchy_collapse", entity));
             let state = egui::collapsing_header::CollapsingState::load_with_default_open(
                 ui.ctx(),
@@ -465,9 +839,11 @@ fn draw_hierarchy_node(
                     let mut label_clicked = false;
                     let (inner, dropped) = ui.dnd_drop_zone(frame, |ui| {
                         let label = ui.selectable_label(selected, name);
+                        draw_presence_tags(ui, label.rect, hierarchy.presence.get(&entity));
                         let drag_id = ui.make_persistent_id(("hierarchy_drag", entity));
                         let drag_response =
                             ui.interact(label.rect, drag_id, egui::Sense::click_and_drag());
+                        describe_hierarchy_row(&drag_response, name, selected, children.len());
                         drag_response.dnd_set_drag_payload(drag_payload.clone());
                         if drag_response.clicked() {
                             label_clicked = true;
@@ -489,10 +865,16 @@ fn draw_hierarchy_node(
                                     parent: Some(entity),
                                 });
                             }
+                            DragPayload::Material(index) => {
+                                material_library_queue.push(MaterialLibraryEvent::Apply {
+                                    index,
+                                    entities: vec![entity],
+                                });
+                            }
                         }
                     }
                       if label_clicked {
-                          editor_state.selected_entity = Some(entity);
+                          select_in_hierarchy(ui, editor_state, entity, hierarchy);
                           *clicked_entity = true;
                       }
                       inner.inner
@@ -506,28 +888,31 @@ fn draw_hierarchy_node(
                               editor_state,
                               reparent_events,
                               spawn_asset_queue,
+                              material_library_queue,
                               clicked_entity,
                           );
                       }
                   });
               if header.response.clicked() {
-                  editor_state.selected_entity = Some(entity);
+                  select_in_hierarchy(ui, editor_state, entity, hierarchy);
                   *clicked_entity = true;
               }
           } else {
             let mut label_clicked = false;
             let (inner, dropped) = ui.dnd_drop_zone(frame, |ui| {
                 let label = ui.selectable_label(selected, name);
+                draw_presence_tags(ui, label.rect, hierarchy.presence.get(&entity));
                 let drag_id = ui.make_persistent_id(("hierarchy_drag", entity));
                 let drag_response =
                     ui.interact(label.rect, drag_id, egui::Sense::click_and_drag());
+                describe_hierarchy_row(&drag_response, name, selected, 0);
                 drag_response.dnd_set_drag_payload(drag_payload.clone());
                 if drag_response.clicked() {
                     label_clicked = true;
                 }
             });
               if label_clicked || inner.response.clicked() {
-                  editor_state.selected_entity = Some(entity);
+                  select_in_hierarchy(ui, editor_state, entity, hierarchy);
                   *clicked_entity = true;
               }
             if let Some(payload) = dropped.map(|payload: std::sync::Arc<DragPayload>| (*payload).clone()) {
@@ -546,19 +931,54 @@ fn draw_hierarchy_node(
                             parent: Some(entity),
                         });
                     }
+                    DragPayload::Material(index) => {
+                        material_library_queue.push(MaterialLibraryEvent::Apply {
+                            index,
+                            entities: vec![entity],
+                        });
+                    }
                 }
             }
         }
     });
 }
 
+/// Colored initials after an entity's name for each collaborator that has it selected
+fn draw_presence_tags(ui: &mut egui::Ui, label_rect: egui::Rect, tags: Option<&Vec<PresenceTag>>) {
+    let Some(tags) = tags else {
+        return;
+    };
+    let radius = label_rect.height() * 0.4;
+    for (index, tag) in tags.iter().enumerate() {
+        let center = egui::pos2(
+            label_rect.right() + radius + 4.0 + index as f32 * (radius * 2.0 + 2.0),
+            label_rect.center().y,
+        );
+        ui.painter().circle_filled(center, radius, tag.color);
+        let initial: String = tag.name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or_default();
+        ui.painter().text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            initial,
+            egui::FontId::proportional(radius * 1.4),
+            egui::Color32::BLACK,
+        );
+        let hover_rect = egui::Rect::from_center_size(center, egui::vec2(radius * 2.0, radius * 2.0));
+        ui.interact(hover_rect, ui.id().with(("presence", index)), egui::Sense::hover())
+            .on_hover_text(format!("Selected by {}", tag.name));
+    }
+}
+
 /// Draw the inspector panel
 pub fn draw_inspector_panel(
     ui: &mut egui::Ui,
     editor_state: &mut EditorState,
     _editor_settings: &mut EditorSettings,
+    entity: Option<Entity>,
+    pinned: Option<&mut bool>,
     selected_transform: Option<&mut Transform>,
-    mut selected_name: Option<&mut Name>,
+    selected_parent_transform: Option<GlobalTransform>,
+    mut selected_name: Option<Mut<Name>>,
     selected_material_handle: Option<&Handle<StandardMaterial>>,
     mut selected_overrides: Option<&mut crate::rendering::materials::PbrTextureOverrides>,
     mut selected_environment: Option<&mut crate::rendering::scene::EnvironmentSettings>,
@@ -570,152 +990,332 @@ pub fn draw_inspector_panel(
     selected_directional_light: Option<&mut DirectionalLight>,
     selected_point_light: Option<&mut PointLight>,
     selected_spot_light: Option<&mut SpotLight>,
+    selected_look_at: Option<&mut crate::core::constraints::LookAtConstraint>,
+    selected_follow: Option<&mut crate::core::constraints::FollowConstraint>,
+    selected_stick_to_surface: Option<&mut crate::core::constraints::StickToSurfaceConstraint>,
+    selected_vehicle: Option<&mut crate::core::vehicle::RaycastVehicle>,
+    selected_lens_flare: Option<&mut crate::rendering::lens_flare::LensFlare>,
+    selected_animator: Option<&mut crate::core::animation::WaffleAnimator>,
+    selected_state_machine: Option<&mut crate::core::state_machine::AnimationStateMachine>,
+    selected_property_animation: Option<&mut crate::core::keyframes::PropertyAnimation>,
+    selected_ao_volume: Option<(&mut crate::rendering::ao_volume::AoVolume, bool)>,
+    selected_camera_shake: Option<&mut crate::rendering::camera_shake::CameraShake>,
+    selected_dolly_track: Option<&mut crate::rendering::camera_rig::DollyTrack>,
+    selected_camera_dolly: Option<&mut crate::rendering::camera_rig::CameraDolly>,
+    selected_camera_crane: Option<&mut crate::rendering::camera_rig::CameraCrane>,
+    selected_camera_focus: Option<&mut crate::rendering::camera_rig::CameraFocus>,
+    selected_lua_script: Option<&mut crate::scripting::LuaScript>,
+    selected_particle_emitter: Option<&mut crate::rendering::particles::ParticleEmitter>,
+    selected_terrain: Option<&mut crate::terrain::WaffleTerrain>,
+    selected_foliage: Option<&mut crate::rendering::foliage::FoliageLayer>,
+    selected_water: Option<&mut crate::rendering::water::WaffleWater>,
+    selected_lod: Option<(&mut crate::rendering::lod::LodGroup, Option<&mut crate::rendering::lod::LodState>)>,
+    selected_sub_scene: Option<(&SceneReference, Option<&SubSceneInstance>)>,
+    selected_audio_source: Option<&mut crate::audio::WaffleAudioSource>,
+    selected_portal: Option<(&mut crate::rendering::portal::Portal, Option<egui::TextureId>)>,
+    selected_render_layers: Option<&bevy::render::view::RenderLayers>,
+    selected_missing_asset: Option<&MissingAsset>,
+    selected_surface: Option<PhysicalSurface>,
+    selected_material_surface: Option<PhysicalSurface>,
+    selected_is_camera: bool,
+    selected_instanced: bool,
+    project_settings: &crate::core::project::ProjectSettings,
+    hierarchy: &HierarchySnapshot,
+    asset_entries: &[AssetEntry],
+    pivot_edit_queue: &mut Vec<PivotEditEvent>,
+    constraint_edit_queue: &mut Vec<ConstraintEditEvent>,
+    camera_rig_edit_queue: &mut Vec<CameraRigEditEvent>,
+    lua_script_edit_queue: &mut Vec<LuaScriptEditEvent>,
+    state_machine_edit_queue: &mut Vec<StateMachineEditEvent>,
+    particle_emitter_edit_queue: &mut Vec<ParticleEmitterEditEvent>,
+    terrain_edit_queue: &mut Vec<TerrainEditEvent>,
+    foliage_edit_queue: &mut Vec<FoliageEditEvent>,
+    water_edit_queue: &mut Vec<WaterEditEvent>,
+    lod_edit_queue: &mut Vec<LodEditEvent>,
+    sub_scene_edit_queue: &mut Vec<SubSceneEditEvent>,
+    audio_source_edit_queue: &mut Vec<AudioSourceEditEvent>,
+    material_edit_queue: &mut Vec<MaterialEditEvent>,
+    render_layers_edit_queue: &mut Vec<RenderLayersEditEvent>,
+    instancing_edit_queue: &mut Vec<InstancingEditEvent>,
+    surface_edit_queue: &mut Vec<SurfaceEditEvent>,
+    keyframe_edit_queue: &mut Vec<KeyframeEditEvent>,
+    reimport_queue: &mut Vec<ReimportAssetEvent>,
+    locate_missing_queue: &mut Vec<LocateMissingAssetEvent>,
+    bake_ao_volume_queue: &mut Vec<crate::rendering::ao_volume::BakeAoVolumeEvent>,
+    camera_shake_queue: &mut Vec<crate::rendering::camera_shake::CameraShakeEvent>,
+    preview_camera_shake_queue: &mut Vec<crate::rendering::camera_shake::PreviewCameraShakeEvent>,
+    pin_inspector_queue: &mut Vec<Entity>,
 ) {
+    let working_space = project_settings.color_management.working_space;
+    let is_extra = pinned.is_some();
+    let is_pinned = pinned.as_deref().copied().unwrap_or(false);
+    let has_light =
+        selected_directional_light.is_some() || selected_point_light.is_some() || selected_spot_light.is_some();
+    let has_material = selected_material_handle.is_some();
     ui.vertical(|ui| {
-        ui.heading("Inspector");
+        ui.horizontal(|ui| {
+            ui.heading("Inspector");
+            let Some(entity) = entity else {
+                return;
+            };
+            match pinned {
+                Some(pinned) => {
+                    ui.toggle_value(pinned, "Pin")
+                        .on_hover_text("Keep showing this entity when the selection changes");
+                }
+                None => {
+                    if ui.small_button("Pin").on_hover_text("Open another inspector pinned to this entity").clicked() {
+                        pin_inspector_queue.push(entity);
+                    }
+                }
+            }
+        });
 
         ui.separator();
 
-        if let Some(entity) = editor_state.selected_entity {
-            ui.label(format!("Selected Entity: {}", entity.index()));
+        if let Some(entity) = entity {
+            if is_pinned {
+                ui.label(format!("Pinned Entity: {}", entity.index()));
+            } else if editor_state.selection.len() > 1 {
+                ui.label(format!(
+                    "Selected Entity: {} (+{} more)",
+                    entity.index(),
+                    editor_state.selection.len() - 1
+                ));
+            } else {
+                ui.label(format!("Selected Entity: {}", entity.index()));
+            }
 
             ui.separator();
 
             ui.collapsing("Name", |ui| {
-                if let Some(name) = selected_name.as_deref_mut() {
+                // Only written on an edit, so the hierarchy isn't rebuilt
+                // every frame the entity is inspected
+                if let Some(name) = selected_name.as_mut() {
                     let mut value = name.as_str().to_string();
                     if ui.text_edit_singleline(&mut value).changed() {
-                        *name = Name::new(value);
+                        name.set_if_neq(Name::new(value));
                     }
                 } else {
                     ui.label("No name component");
                 }
             });
 
+            if let Some(missing) = selected_missing_asset {
+                ui.collapsing("Missing Asset", |ui| {
+                    draw_missing_asset_section(ui, missing, asset_entries, locate_missing_queue);
+                });
+            }
+
             // Transform component
             ui.collapsing("Transform", |ui| {
                 if let Some(transform) = selected_transform {
-                    let mut translation = transform.translation;
-                    let rotation = transform.rotation.to_euler(EulerRot::YXZ);
-                    let mut rotation_deg = Vec3::new(
-                        rotation.1.to_degrees(),
-                        rotation.0.to_degrees(),
-                        rotation.2.to_degrees(),
-                    );
-                    let mut scale = transform.scale;
+                    draw_transform_section(ui, editor_state, transform, selected_parent_transform);
+                } else {
+                    ui.label("No transform component");
+                }
+            });
+
+            ui.collapsing("Pivot", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Offset:");
+                    ui.add(egui::DragValue::new(&mut editor_state.pivot_offset.x).speed(0.01).prefix("X: "));
+                    ui.add(egui::DragValue::new(&mut editor_state.pivot_offset.y).speed(0.01).prefix("Y: "));
+                    ui.add(egui::DragValue::new(&mut editor_state.pivot_offset.z).speed(0.01).prefix("Z: "));
+                    if ui.button("Apply").clicked() {
+                        pivot_edit_queue.push(PivotEditEvent {
+                            entity,
+                            kind: PivotEditKind::Offset(editor_state.pivot_offset),
+                        });
+                        editor_state.pivot_offset = Vec3::ZERO;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Center Pivot").clicked() {
+                        pivot_edit_queue.push(PivotEditEvent {
+                            entity,
+                            kind: PivotEditKind::CenterToBounds,
+                        });
+                    }
+                    if ui.button("Pivot to Bottom").clicked() {
+                        pivot_edit_queue.push(PivotEditEvent {
+                            entity,
+                            kind: PivotEditKind::BottomOfBounds,
+                        });
+                    }
+                });
+            });
+
+            ui.collapsing("Constraints", |ui| {
+                let mut request = |kind: ConstraintKind, add: bool| {
+                    constraint_edit_queue.push(ConstraintEditEvent { entity, kind, add });
+                };
 
+                if let Some(look_at) = selected_look_at {
                     ui.horizontal(|ui| {
-                        ui.label("Position:");
-                        ui.add(egui::DragValue::new(&mut translation.x).prefix("X: "));
-                        ui.add(egui::DragValue::new(&mut translation.y).prefix("Y: "));
-                        ui.add(egui::DragValue::new(&mut translation.z).prefix("Z: "));
+                        ui.strong("Look At");
+                        if ui.small_button("Remove").clicked() {
+                            request(ConstraintKind::LookAt, false);
+                        }
+                    });
+                    constraint_target_field(ui, &mut look_at.target, hierarchy);
+                    ui.horizontal(|ui| {
+                        ui.label("Up:");
+                        ui.add(egui::DragValue::new(&mut look_at.up.x).speed(0.01).prefix("X: "));
+                        ui.add(egui::DragValue::new(&mut look_at.up.y).speed(0.01).prefix("Y: "));
+                        ui.add(egui::DragValue::new(&mut look_at.up.z).speed(0.01).prefix("Z: "));
                     });
+                    ui.separator();
+                }
 
+                if let Some(follow) = selected_follow {
+                    ui.horizontal(|ui| {
+                        ui.strong("Follow");
+                        if ui.small_button("Remove").clicked() {
+                            request(ConstraintKind::Follow, false);
+                        }
+                    });
+                    constraint_target_field(ui, &mut follow.target, hierarchy);
                     ui.horizontal(|ui| {
-                        ui.label("Rotation:");
-                        ui.add(egui::DragValue::new(&mut rotation_deg.x).prefix("Y: "));
-                        ui.add(egui::DragValue::new(&mut rotation_deg.y).prefix("X: "));
-                        ui.add(egui::DragValue::new(&mut rotation_deg.z).prefix("Z: "));
+                        ui.label("Offset:");
+                        ui.add(egui::DragValue::new(&mut follow.offset.x).speed(0.05).prefix("X: "));
+                        ui.add(egui::DragValue::new(&mut follow.offset.y).speed(0.05).prefix("Y: "));
+                        ui.add(egui::DragValue::new(&mut follow.offset.z).speed(0.05).prefix("Z: "));
                     });
+                    ui.horizontal(|ui| {
+                        ui.label("Lag (s):");
+                        ui.add(egui::DragValue::new(&mut follow.lag).speed(0.01).range(0.0..=10.0));
+                    });
+                    ui.separator();
+                }
 
+                if let Some(stick) = selected_stick_to_surface {
                     ui.horizontal(|ui| {
-                        ui.label("Scale:");
-                        ui.add(egui::DragValue::new(&mut scale.x).prefix("X: "));
-                        ui.add(egui::DragValue::new(&mut scale.y).prefix("Y: "));
-                        ui.add(egui::DragValue::new(&mut scale.z).prefix("Z: "));
-                    });
-
-                    transform.translation = translation;
-                    transform.rotation = Quat::from_euler(
-                        EulerRot::YXZ,
-                        rotation_deg.y.to_radians(),
-                        rotation_deg.x.to_radians(),
-                        rotation_deg.z.to_radians(),
-                    );
-                    transform.scale = scale;
-                } else {
-                    ui.label("No transform component");
+                        ui.strong("Stick To Surface");
+                        if ui.small_button("Remove").clicked() {
+                            request(ConstraintKind::StickToSurface, false);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Height:");
+                        ui.add(egui::DragValue::new(&mut stick.height).speed(0.01));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Max Distance:");
+                        ui.add(egui::DragValue::new(&mut stick.max_distance).speed(0.1).range(0.0..=10000.0));
+                    });
+                    ui.checkbox(&mut stick.align_to_normal, "Align To Normal");
+                    ui.separator();
                 }
-            });
 
-            if let Some(handle) = selected_material_handle {
-                if let Some(material) = material_assets.get_mut(handle) {
-                    ui.collapsing("Material", |ui| {
-                        let selected_texture = selected_asset
-                            .filter(|path| is_image_path(path))
-                            .map(|path| asset_server.load(path.to_string()));
-                        ui.label(format!("Source: {}", material_handle_label(&handle)));
+                ui.menu_button("Add Constraint", |ui| {
+                    for (kind, label) in [
+                        (ConstraintKind::LookAt, "Look At"),
+                        (ConstraintKind::Follow, "Follow"),
+                        (ConstraintKind::StickToSurface, "Stick To Surface"),
+                    ] {
+                        if ui.button(label).clicked() {
+                            request(kind, true);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Base Color:");
-                            let mut base_color = color_to_egui(material.base_color);
-                            if ui.color_edit_button_srgba(&mut base_color).changed() {
-                                material.base_color = egui_to_color(base_color);
-                            }
+            ui.collapsing("Lua Script", |ui| {
+                if let Some(script) = selected_lua_script {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Script: {}", script.path));
+                        if ui.small_button("Remove").clicked() {
+                            lua_script_edit_queue.push(LuaScriptEditEvent { entity, path: None });
+                        }
+                    });
+                    ui.checkbox(&mut script.enabled, "Enabled");
+                }
+                let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+                    ui.label("Drop a .lua script here");
+                });
+                if let Some(DragPayload::Asset(path)) = dropped.as_deref() {
+                    if path.ends_with(".lua") {
+                        lua_script_edit_queue.push(LuaScriptEditEvent {
+                            entity,
+                            path: Some(path.clone()),
                         });
+                    }
+                }
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Albedo Map:");
-                            ui.label(image_handle_label(&material.base_color_texture));
-                            if let Some(handle) = selected_texture.as_ref() {
-                                if ui.button("Use Selected").clicked() {
-                                    material.base_color_texture = Some(handle.clone());
-                                }
-                            }
-                        });
-
-                        ui.horizontal(|ui| {
-                            ui.label("Emissive:");
-                            let mut emissive = color_to_egui(Color::LinearRgba(material.emissive));
-                            if ui.color_edit_button_srgba(&mut emissive).changed() {
-                                material.emissive = egui_to_color(emissive).to_linear();
-                            }
-                        });
-
-                        ui.horizontal(|ui| {
-                            ui.label("Emissive Map:");
-                            ui.label(image_handle_label(&material.emissive_texture));
-                            if let Some(handle) = selected_texture.as_ref() {
-                                if ui.button("Use Selected").clicked() {
-                                    material.emissive_texture = Some(handle.clone());
-                                }
-                            }
+            ui.collapsing("Audio Source", |ui| {
+                if let Some(source) = selected_audio_source {
+                    draw_audio_source_fields(ui, entity, source, audio_source_edit_queue);
+                }
+                let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+                    ui.label("Drop a .wav or .ogg clip here");
+                });
+                if let Some(DragPayload::Asset(path)) = dropped.as_deref() {
+                    if path.ends_with(".wav") || path.ends_with(".ogg") {
+                        audio_source_edit_queue.push(AudioSourceEditEvent {
+                            entity,
+                            kind: AudioSourceEditKind::SetClip(path.clone()),
                         });
+                    }
+                }
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Roughness:");
-                            ui.add(egui::Slider::new(&mut material.perceptual_roughness, 0.0..=1.0));
-                        });
+            let render_layers_title = if selected_is_camera { "Visible Layers" } else { "Render Layers" };
+            ui.collapsing(render_layers_title, |ui| {
+                draw_render_layers_grid(
+                    ui,
+                    entity,
+                    selected_render_layers,
+                    project_settings,
+                    render_layers_edit_queue,
+                );
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Metallic:");
-                            ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0));
-                        });
+            ui.collapsing("Instancing", |ui| {
+                let mut instanced = selected_instanced;
+                if ui.checkbox(&mut instanced, "Share Identical Meshes").changed() {
+                    instancing_edit_queue.push(InstancingEditEvent { entity, instanced });
+                }
+                ui.label("Identical copies under instanced entities share a mesh and material and draw together");
+                ui.label(egui::RichText::new("Editing a shared material changes every copy").weak());
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Metal/Rough Map:");
-                            ui.label(image_handle_label(&material.metallic_roughness_texture));
-                        });
+            ui.collapsing("Surface", |ui| {
+                if let Some(surface) = surface_combo(ui, "entity_surface", "Made Of:", selected_surface, "Inherit") {
+                    surface_edit_queue.push(SurfaceEditEvent {
+                        entity,
+                        kind: SurfaceEditKind::Entity(surface),
+                    });
+                }
+                ui.label("Footsteps and impacts on this entity or its children use this surface");
+            });
 
-                        ui.horizontal(|ui| {
-                            ui.label("Normal Map:");
-                            ui.label(image_handle_label(&material.normal_map_texture));
-                            if let Some(handle) = selected_texture.as_ref() {
-                                if ui.button("Use Selected").clicked() {
-                                    material.normal_map_texture = Some(handle.clone());
-                                }
-                            }
-                        });
+            if let Some(handle) = selected_material_handle {
+                if let Some(material) = material_assets.get_mut(handle) {
+                    ui.collapsing("Material", |ui| {
+                        let selected_texture = selected_asset
+                            .filter(|path| is_image_path(path))
+                            .map(|path| asset_server.load(path.to_string()));
+                        ui.label(format!("Source: {}", material_handle_label(&handle)));
+                        draw_material_file_fields(
+                            ui,
+                            Some(entity),
+                            handle,
+                            &mut editor_state.material_save_name,
+                            material_edit_queue,
+                        );
+                        if let Some(surface) =
+                            surface_combo(ui, "material_surface", "Surface:", selected_material_surface, "None")
+                        {
+                            surface_edit_queue.push(SurfaceEditEvent {
+                                entity,
+                                kind: SurfaceEditKind::Material(handle.clone(), surface),
+                            });
+                        }
 
-                        ui.horizontal(|ui| {
-                            ui.label("AO Map:");
-                            ui.label(image_handle_label(&material.occlusion_texture));
-                            if let Some(handle) = selected_texture.as_ref() {
-                                if ui.button("Use Selected").clicked() {
-                                    material.occlusion_texture = Some(handle.clone());
-                                }
-                            }
-                        });
+                        draw_standard_material_fields(ui, material, selected_texture.as_ref(), working_space);
 
                         ui.separator();
                         ui.label("Separate Metallic/Roughness:");
@@ -752,17 +1352,19 @@ pub fn draw_inspector_panel(
                         ui.label("Time of Day:");
                         ui.add(egui::Slider::new(&mut env.time_of_day, 0.0..=24.0));
                     });
-                    ui.horizontal(|ui| {
-                        ui.label("Sun Azimuth:");
-                        ui.add(egui::Slider::new(&mut env.sun_azimuth, 0.0..=360.0));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Sun Color:");
-                        let mut color = color_to_egui(env.sun_color);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.sun_color = egui_to_color(color);
-                        }
-                    });
+                    let mut use_geo_sun = env.geo_sun.is_some();
+                    if ui.checkbox(&mut use_geo_sun, "Geographic Sun").changed() {
+                        env.geo_sun = use_geo_sun.then(GeoSunLocation::default);
+                    }
+                    if let Some(location) = env.geo_sun.as_mut() {
+                        draw_geo_sun_fields(ui, location, env.time_of_day);
+                    } else {
+                        ui.horizontal(|ui| {
+                            ui.label("Sun Azimuth:");
+                            ui.add(egui::Slider::new(&mut env.sun_azimuth, 0.0..=360.0));
+                        });
+                    }
+                    color_field(ui, "Sun Color:", &mut env.sun_color, working_space);
                     ui.horizontal(|ui| {
                         ui.label("Sun Intensity:");
                         ui.add(egui::DragValue::new(&mut env.sun_intensity).speed(100.0).range(0.0..=200000.0));
@@ -775,44 +1377,14 @@ pub fn draw_inspector_panel(
                         ui.label("Sun Disk Size:");
                         ui.add(egui::Slider::new(&mut env.sun_disk_size, 0.001..=0.1));
                     });
-                    ui.horizontal(|ui| {
-                        ui.label("Sky Top (Day):");
-                        let mut color = color_to_egui(env.sky_top_day);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.sky_top_day = egui_to_color(color);
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Sky Horizon (Day):");
-                        let mut color = color_to_egui(env.sky_horizon_day);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.sky_horizon_day = egui_to_color(color);
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Sky Top (Night):");
-                        let mut color = color_to_egui(env.sky_top_night);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.sky_top_night = egui_to_color(color);
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Sky Horizon (Night):");
-                        let mut color = color_to_egui(env.sky_horizon_night);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.sky_horizon_night = egui_to_color(color);
-                        }
-                    });
+                    color_field(ui, "Sky Top (Day):", &mut env.sky_top_day, working_space);
+                    color_field(ui, "Sky Horizon (Day):", &mut env.sky_horizon_day, working_space);
+                    color_field(ui, "Sky Top (Night):", &mut env.sky_top_night, working_space);
+                    color_field(ui, "Sky Horizon (Night):", &mut env.sky_horizon_night, working_space);
 
                     ui.separator();
                     ui.label("Ambient");
-                    ui.horizontal(|ui| {
-                        ui.label("Ambient Color:");
-                        let mut color = color_to_egui(env.ambient_color);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            env.ambient_color = egui_to_color(color);
-                        }
-                    });
+                    color_field(ui, "Ambient Color:", &mut env.ambient_color, working_space);
                     ui.horizontal(|ui| {
                         ui.label("Ambient Intensity:");
                         ui.add(egui::DragValue::new(&mut env.ambient_intensity).speed(10.0).range(0.0..=10000.0));
@@ -822,9 +1394,31 @@ pub fn draw_inspector_panel(
                     ui.label("Post Processing");
 
                     ui.horizontal(|ui| {
-                        ui.label("Exposure (EV100):");
-                        ui.add(egui::DragValue::new(&mut env.exposure_ev100));
+                        ui.label("Auto Exposure:");
+                        ui.checkbox(&mut env.auto_exposure.enabled, "");
                     });
+                    if env.auto_exposure.enabled {
+                        ui.horizontal(|ui| {
+                            ui.label("EV100 Range:");
+                            let max = env.auto_exposure.max_ev100;
+                            ui.add(egui::DragValue::new(&mut env.auto_exposure.min_ev100).speed(0.1).range(-8.0..=max));
+                            let min = env.auto_exposure.min_ev100;
+                            ui.add(egui::DragValue::new(&mut env.auto_exposure.max_ev100).speed(0.1).range(min..=24.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Brighten Speed:");
+                            ui.add(egui::Slider::new(&mut env.auto_exposure.speed_brighten, 0.1..=10.0).suffix(" EV/s"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Darken Speed:");
+                            ui.add(egui::Slider::new(&mut env.auto_exposure.speed_darken, 0.1..=10.0).suffix(" EV/s"));
+                        });
+                    } else {
+                        ui.horizontal(|ui| {
+                            ui.label("Exposure (EV100):");
+                            ui.add(egui::DragValue::new(&mut env.exposure_ev100));
+                        });
+                    }
 
                     ui.horizontal(|ui| {
                         ui.label("Tonemapping:");
@@ -883,13 +1477,7 @@ pub fn draw_inspector_panel(
                         ui.label("Enabled:");
                         ui.checkbox(&mut env.fog.enabled, "");
                     });
-                    ui.horizontal(|ui| {
-                        ui.label("Color:");
-                        let mut fog_color = color_to_egui(env.fog.color);
-                        if ui.color_edit_button_srgba(&mut fog_color).changed() {
-                            env.fog.color = egui_to_color(fog_color);
-                        }
-                    });
+                    color_field(ui, "Color:", &mut env.fog.color, working_space);
                     ui.horizontal(|ui| {
                         ui.label("Mode:");
                         egui::ComboBox::from_id_source("fog_mode")
@@ -1025,13 +1613,7 @@ pub fn draw_inspector_panel(
                 ui.collapsing("Light", |ui| {
                     if let Some(light) = selected_directional_light {
                         ui.label("Type: Directional");
-                        ui.horizontal(|ui| {
-                            ui.label("Color:");
-                            let mut color = color_to_egui(light.color);
-                            if ui.color_edit_button_srgba(&mut color).changed() {
-                                light.color = egui_to_color(color);
-                            }
-                        });
+                        color_field(ui, "Color:", &mut light.color, working_space);
                         ui.horizontal(|ui| {
                             ui.label("Illuminance:");
                             ui.add(egui::DragValue::new(&mut light.illuminance).speed(100.0).range(0.0..=200000.0));
@@ -1042,13 +1624,7 @@ pub fn draw_inspector_panel(
                     if let Some(light) = selected_point_light {
                         ui.separator();
                         ui.label("Type: Point");
-                        ui.horizontal(|ui| {
-                            ui.label("Color:");
-                            let mut color = color_to_egui(light.color);
-                            if ui.color_edit_button_srgba(&mut color).changed() {
-                                light.color = egui_to_color(color);
-                            }
-                        });
+                        color_field(ui, "Color:", &mut light.color, working_space);
                         ui.horizontal(|ui| {
                             ui.label("Intensity:");
                             ui.add(egui::DragValue::new(&mut light.intensity).speed(100.0).range(0.0..=200000.0));
@@ -1063,13 +1639,7 @@ pub fn draw_inspector_panel(
                     if let Some(light) = selected_spot_light {
                         ui.separator();
                         ui.label("Type: Spot");
-                        ui.horizontal(|ui| {
-                            ui.label("Color:");
-                            let mut color = color_to_egui(light.color);
-                            if ui.color_edit_button_srgba(&mut color).changed() {
-                                light.color = egui_to_color(color);
-                            }
-                        });
+                        color_field(ui, "Color:", &mut light.color, working_space);
                         ui.horizontal(|ui| {
                             ui.label("Intensity:");
                             ui.add(egui::DragValue::new(&mut light.intensity).speed(100.0).range(0.0..=200000.0));
@@ -1091,6 +1661,213 @@ pub fn draw_inspector_panel(
                 });
             }
 
+            if let Some(vehicle) = selected_vehicle {
+                ui.collapsing("Vehicle", |ui| {
+                    draw_vehicle_fields(ui, vehicle);
+                });
+            }
+
+            if let Some(flare) = selected_lens_flare {
+                ui.collapsing("Lens Flare", |ui| {
+                    draw_lens_flare_fields(ui, flare, working_space);
+                });
+            }
+
+            if let Some(animator) = selected_animator {
+                ui.collapsing("Animator", |ui| {
+                    match selected_state_machine {
+                        Some(machine) => draw_state_machine_fields(ui, entity, machine, state_machine_edit_queue),
+                        None => draw_animator_fields(ui, animator),
+                    }
+                    let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+                        ui.label("Drop a .wanim state machine here");
+                    });
+                    if let Some(DragPayload::Asset(path)) = dropped.as_deref() {
+                        if path.ends_with(".wanim") {
+                            state_machine_edit_queue.push(StateMachineEditEvent {
+                                entity,
+                                graph: Some(path.clone()),
+                            });
+                        }
+                    }
+                });
+            }
+
+            ui.collapsing("Particles", |ui| {
+                draw_particle_emitter_fields(
+                    ui,
+                    entity,
+                    selected_particle_emitter,
+                    working_space,
+                    particle_emitter_edit_queue,
+                );
+            });
+
+            ui.collapsing("Terrain", |ui| {
+                draw_terrain_fields(
+                    ui,
+                    entity,
+                    selected_terrain,
+                    working_space,
+                    &mut editor_state.terrain_save_name,
+                    terrain_edit_queue,
+                );
+            });
+
+            ui.collapsing("Foliage", |ui| {
+                draw_foliage_fields(ui, entity, selected_foliage, foliage_edit_queue);
+            });
+
+            ui.collapsing("Water", |ui| {
+                draw_water_fields(ui, entity, selected_water, working_space, water_edit_queue);
+            });
+
+            ui.collapsing("LOD", |ui| {
+                draw_lod_fields(ui, entity, selected_lod, lod_edit_queue);
+            });
+
+            ui.collapsing("Sub-Scene", |ui| {
+                draw_sub_scene_fields(ui, entity, selected_sub_scene, sub_scene_edit_queue);
+            });
+
+            ui.collapsing("Keyframes", |ui| {
+                draw_keyframe_fields(
+                    ui,
+                    entity,
+                    selected_property_animation,
+                    &mut editor_state.keyframe_time,
+                    &mut editor_state.keyframe_interpolation,
+                    has_light,
+                    has_material,
+                    keyframe_edit_queue,
+                );
+            });
+
+            if let Some(shake) = selected_camera_shake {
+                ui.collapsing("Camera Shake", |ui| {
+                    draw_camera_shake_fields(ui, shake, preview_camera_shake_queue);
+                });
+            } else if selected_is_camera && ui.button("Add Camera Shake").clicked() {
+                camera_shake_queue.push(crate::rendering::camera_shake::CameraShakeEvent {
+                    camera: Some(entity),
+                    trauma: 0.0,
+                });
+            }
+
+            if let Some(track) = selected_dolly_track {
+                ui.collapsing("Dolly Track", |ui| {
+                    draw_dolly_track_fields(ui, track);
+                });
+            }
+
+            if let Some((portal, preview)) = selected_portal {
+                let title = if portal.mirror { "Mirror" } else { "Portal" };
+                ui.collapsing(title, |ui| {
+                    draw_portal_fields(ui, portal, preview, hierarchy);
+                });
+            }
+
+            let has_rig = selected_camera_dolly.is_some() || selected_camera_crane.is_some() || selected_camera_focus.is_some();
+            if selected_is_camera || has_rig {
+                ui.collapsing("Camera Rig", |ui| {
+                    let mut request = |part: CameraRigPart, add: bool| {
+                        camera_rig_edit_queue.push(CameraRigEditEvent { entity, part, add });
+                    };
+
+                    if let Some(dolly) = selected_camera_dolly {
+                        ui.horizontal(|ui| {
+                            ui.strong("Dolly");
+                            if ui.small_button("Remove").clicked() {
+                                request(CameraRigPart::Dolly, false);
+                            }
+                        });
+                        entity_drop_field(ui, "Track:", &mut dolly.track, hierarchy);
+                        ui.horizontal(|ui| {
+                            ui.label("Position:");
+                            ui.add(egui::Slider::new(&mut dolly.position, 0.0..=1.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Speed:");
+                            ui.add(egui::DragValue::new(&mut dolly.speed).speed(0.05).suffix(" m/s"));
+                        });
+                        ui.checkbox(&mut dolly.looping, "Loop");
+                        ui.separator();
+                    }
+
+                    if let Some(crane) = selected_camera_crane {
+                        ui.horizontal(|ui| {
+                            ui.strong("Crane");
+                            if ui.small_button("Remove").clicked() {
+                                request(CameraRigPart::Crane, false);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Pivot:");
+                            ui.add(egui::DragValue::new(&mut crane.pivot.x).speed(0.05).prefix("X: "));
+                            ui.add(egui::DragValue::new(&mut crane.pivot.y).speed(0.05).prefix("Y: "));
+                            ui.add(egui::DragValue::new(&mut crane.pivot.z).speed(0.05).prefix("Z: "));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Base Height:");
+                            ui.add(egui::DragValue::new(&mut crane.base_height).speed(0.05));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Arm Length:");
+                            ui.add(egui::DragValue::new(&mut crane.length).speed(0.05).range(0.0..=1000.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Yaw:");
+                            ui.add(egui::DragValue::new(&mut crane.yaw).speed(0.5).suffix("°"));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Pitch:");
+                            ui.add(egui::DragValue::new(&mut crane.pitch).speed(0.5).range(-90.0..=90.0).suffix("°"));
+                        });
+                        ui.separator();
+                    }
+
+                    if let Some(focus) = selected_camera_focus {
+                        ui.horizontal(|ui| {
+                            ui.strong("Focus");
+                            if ui.small_button("Remove").clicked() {
+                                request(CameraRigPart::Focus, false);
+                            }
+                        });
+                        entity_drop_field(ui, "Target:", &mut focus.target, hierarchy);
+                        ui.horizontal(|ui| {
+                            ui.label("Offset:");
+                            ui.add(egui::DragValue::new(&mut focus.offset.x).speed(0.05).prefix("X: "));
+                            ui.add(egui::DragValue::new(&mut focus.offset.y).speed(0.05).prefix("Y: "));
+                            ui.add(egui::DragValue::new(&mut focus.offset.z).speed(0.05).prefix("Z: "));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Damping (s):");
+                            ui.add(egui::DragValue::new(&mut focus.damping).speed(0.01).range(0.0..=10.0));
+                        });
+                        ui.separator();
+                    }
+
+                    ui.menu_button("Add Rig Part", |ui| {
+                        for (part, label) in [
+                            (CameraRigPart::Dolly, "Dolly"),
+                            (CameraRigPart::Crane, "Crane"),
+                            (CameraRigPart::Focus, "Focus"),
+                        ] {
+                            if ui.button(label).clicked() {
+                                request(part, true);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            }
+
+            if let Some((volume, baking)) = selected_ao_volume {
+                ui.collapsing("AO Volume", |ui| {
+                    draw_ao_volume_fields(ui, entity, volume, baking, bake_ao_volume_queue);
+                });
+            }
+
             if let Some(light) = selected_waffle_light {
                 ui.collapsing("Waffle Light", |ui| {
                     ui.horizontal(|ui| {
@@ -1101,13 +1878,7 @@ pub fn draw_inspector_panel(
                         ui.label("Range:");
                         ui.add(egui::DragValue::new(&mut light.range).speed(0.5).range(0.0..=200.0));
                     });
-                    ui.horizontal(|ui| {
-                        ui.label("Color:");
-                        let mut color = color_to_egui(light.color);
-                        if ui.color_edit_button_srgba(&mut color).changed() {
-                            light.color = egui_to_color(color);
-                        }
-                    });
+                    color_field(ui, "Color:", &mut light.color, working_space);
                     ui.checkbox(&mut light.shadows_enabled, "Shadows");
                 });
             }
@@ -1118,16 +1889,92 @@ pub fn draw_inspector_panel(
                 ui.label("Select an entity from the Hierarchy panel");
             });
         }
+
+        // Asset settings belong to the browser's selection, so only the main inspector shows them
+        if is_extra {
+            return;
+        }
+
+        if let Some(handle) = editor_state.selected_asset_material.clone() {
+            ui.separator();
+            ui.collapsing("Model Material", |ui| {
+                ui.label(format!("Source: {}", material_handle_label(&handle)));
+                ui.label("Edits last until the model is reloaded; save a copy to keep them");
+                draw_material_file_fields(ui, None, &handle, &mut editor_state.material_save_name, material_edit_queue);
+                match material_assets.get_mut(&handle) {
+                    Some(material) => draw_standard_material_fields(ui, material, None, working_space),
+                    None => {
+                        ui.label("Loading...");
+                    }
+                }
+            });
+        }
+
+        if let Some((path, meta)) = editor_state.selected_asset_meta.as_mut() {
+            ui.separator();
+            ui.collapsing("Import Settings", |ui| {
+                draw_import_settings(ui, path, meta, reimport_queue);
+            });
+        }
     });
 }
 
+/// Import options of the selected asset; they take effect on Reimport
+fn draw_import_settings(
+    ui: &mut egui::Ui,
+    path: &str,
+    meta: &mut AssetMeta,
+    reimport_queue: &mut Vec<ReimportAssetEvent>,
+) {
+    ui.label(path);
+    ui.label(egui::RichText::new(format!("UUID: {}", meta.uuid)).weak().small());
+
+    let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str());
+    match super::classify_asset(extension) {
+        AssetKind::Image => {
+            ui.checkbox(&mut meta.import.srgb, "sRGB")
+                .on_hover_text("Off for normal maps, masks and other data textures");
+            ui.horizontal(|ui| {
+                ui.label("Filtering:");
+                egui::ComboBox::from_id_source("import_filtering")
+                    .selected_text(meta.import.filtering.label())
+                    .show_ui(ui, |ui| {
+                        for filtering in TextureFiltering::ALL {
+                            ui.selectable_value(&mut meta.import.filtering, filtering, filtering.label());
+                        }
+                    });
+            });
+        }
+        AssetKind::Model => {
+            ui.horizontal(|ui| {
+                ui.label("Mesh Scale:");
+                ui.add(egui::DragValue::new(&mut meta.import.mesh_scale).speed(0.01).range(0.001..=1000.0));
+            });
+        }
+        _ => {
+            ui.label("No import options for this kind of asset");
+        }
+    }
+
+    if ui.button("Reimport").clicked() {
+        reimport_queue.push(ReimportAssetEvent {
+            path: path.to_string(),
+            meta: meta.clone(),
+        });
+    }
+}
+
 /// Draw the assets panel
 pub fn draw_assets_panel(
     ui: &mut egui::Ui,
     editor_state: &mut EditorState,
-    _editor_settings: &mut EditorSettings,
+    editor_settings: &mut EditorSettings,
     asset_cache: &AssetBrowserCache,
+    thumbnail_ids: &HashMap<String, (egui::TextureId, egui::Vec2)>,
+    vcs_status: &VcsStatus,
     spawn_asset_queue: &mut Vec<SpawnAssetEvent>,
+    open_external_queue: &mut Vec<OpenExternalEvent>,
+    vcs_action_queue: &mut Vec<VcsActionEvent>,
 ) {
     ui.vertical(|ui| {
         ui.heading("Assets");
@@ -1137,6 +1984,9 @@ pub fn draw_assets_panel(
         ui.horizontal(|ui| {
             ui.label("Filter:");
             ui.text_edit_singleline(&mut editor_state.asset_filter);
+            ui.separator();
+            ui.selectable_value(&mut editor_settings.asset_grid_view, true, "Grid");
+            ui.selectable_value(&mut editor_settings.asset_grid_view, false, "List");
         });
 
         ui.separator();
@@ -1147,7 +1997,8 @@ pub fn draw_assets_panel(
 
             let mut folders: BTreeMap<String, Vec<&AssetEntry>> = BTreeMap::new();
             for entry in &asset_cache.entries {
-                if !filter.is_empty() && !entry.path.to_lowercase().contains(&filter) {
+                let matches = |text: &str| text.to_lowercase().contains(&filter);
+                if !filter.is_empty() && !matches(&entry.path) && !entry.sub_assets.iter().any(|sub| matches(&sub.name)) {
                     continue;
                 }
                 let folder = std::path::Path::new(&entry.path)
@@ -1163,10 +2014,46 @@ pub fn draw_assets_panel(
                 egui::CollapsingHeader::new(header)
                     .default_open(true)
                     .show(ui, |ui| {
+                        if editor_settings.asset_grid_view {
+                            ui.horizontal_wrapped(|ui| {
+                                for entry in &entries {
+                                    shown_any = true;
+                                    let selected = editor_state.selected_asset.as_ref() == Some(&entry.path);
+                                    let vcs_file_status = vcs_status.status(&entry.path);
+                                    let response = draw_asset_tile(
+                                        ui,
+                                        entry,
+                                        selected,
+                                        thumbnail_ids.get(&entry.path).copied(),
+                                        vcs_file_status,
+                                    );
+                                    handle_asset_entry_response(
+                                        response,
+                                        entry,
+                                        vcs_file_status,
+                                        editor_state,
+                                        asset_cache,
+                                        vcs_status,
+                                        spawn_asset_queue,
+                                        open_external_queue,
+                                        vcs_action_queue,
+                                    );
+                                }
+                            });
+                            for entry in entries.iter().filter(|entry| !entry.sub_assets.is_empty()) {
+                                draw_sub_assets(ui, entry, editor_state, spawn_asset_queue);
+                            }
+                            return;
+                        }
+
                         ui.horizontal(|ui| {
                             ui.label("Name");
                             ui.add_space(120.0);
                             ui.label("Type");
+                            if vcs_status.repo_root.is_some() {
+                                ui.add_space(40.0);
+                                ui.label("Git");
+                            }
                         });
                         ui.separator();
 
@@ -1213,19 +2100,34 @@ pub fn draw_assets_panel(
                                 egui::TextStyle::Body.resolve(ui.style()),
                                 egui::Color32::from_rgb(160, 160, 160),
                             );
-
-                            response.dnd_set_drag_payload(DragPayload::Asset(entry.path.clone()));
-                            if response.clicked() || response.drag_started() {
-                                editor_state.selected_asset = Some(entry.path.clone());
-                            }
-                            if response.double_clicked() {
-                                spawn_asset_queue.push(SpawnAssetEvent {
-                                    path: entry.path.clone(),
-                                    parent: None,
-                                });
+                            let vcs_file_status = vcs_status.status(&entry.path);
+                            if let Some(status) = vcs_file_status {
+                                ui.painter().text(
+                                    row_rect.min + egui::vec2(265.0, 3.0),
+                                    egui::Align2::LEFT_TOP,
+                                    status.badge(),
+                                    egui::TextStyle::Body.resolve(ui.style()),
+                                    status.color(),
+                                );
                             }
+                            handle_asset_entry_response(
+                                response,
+                                entry,
+                                vcs_file_status,
+                                editor_state,
+                                asset_cache,
+                                vcs_status,
+                                spawn_asset_queue,
+                                open_external_queue,
+                                vcs_action_queue,
+                            );
 
                             ui.allocate_space(egui::vec2(0.0, row_height));
+                            if !entry.sub_assets.is_empty() {
+                                ui.indent(("asset_sub_assets", &entry.path), |ui| {
+                                    draw_sub_assets(ui, entry, editor_state, spawn_asset_queue);
+                                });
+                            }
                         }
                     });
             }
@@ -1246,139 +2148,2944 @@ pub fn draw_assets_panel(
     });
 }
 
-fn color_to_egui(color: Color) -> egui::Color32 {
-    let srgba = color.to_srgba();
-    let r = (srgba.red.clamp(0.0, 1.0) * 255.0) as u8;
-    let g = (srgba.green.clamp(0.0, 1.0) * 255.0) as u8;
-    let b = (srgba.blue.clamp(0.0, 1.0) * 255.0) as u8;
-    let a = (srgba.alpha.clamp(0.0, 1.0) * 255.0) as u8;
-    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+/// The meshes and materials inside a model, each selectable, draggable and
+/// spawnable like an asset of its own
+fn draw_sub_assets(
+    ui: &mut egui::Ui,
+    entry: &AssetEntry,
+    editor_state: &mut EditorState,
+    spawn_asset_queue: &mut Vec<SpawnAssetEvent>,
+) {
+    let name = std::path::Path::new(&entry.path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&entry.path);
+    egui::CollapsingHeader::new(format!("{name} ({})", entry.sub_assets.len()))
+        .id_source(("sub_assets", &entry.path))
+        .default_open(false)
+        .show(ui, |ui| {
+            for sub in &entry.sub_assets {
+                let path = sub.path(&entry.path);
+                let kind = match sub.kind {
+                    SubAssetKind::Mesh => "[Mesh]",
+                    SubAssetKind::Material => "[Material]",
+                };
+                let selected = editor_state.selected_asset.as_ref() == Some(&path);
+                let response = ui
+                    .selectable_label(selected, format!("{} {}", sub.name, kind))
+                    .interact(egui::Sense::click_and_drag())
+                    .on_hover_text(&path);
+                response.dnd_set_drag_payload(DragPayload::Asset(path.clone()));
+                if response.clicked() || response.drag_started() {
+                    editor_state.selected_asset = Some(path.clone());
+                }
+                if response.double_clicked() {
+                    spawn_asset_queue.push(SpawnAssetEvent { path, parent: None });
+                }
+            }
+        });
 }
 
-fn egui_to_color(color: egui::Color32) -> Color {
-    let [r, g, b, a] = color.to_array();
-    Color::srgba(
-        r as f32 / 255.0,
-        g as f32 / 255.0,
-        b as f32 / 255.0,
-        a as f32 / 255.0,
-    )
-}
+/// One asset in the grid view: its thumbnail, or its type while there is
+/// none, above its name
+fn draw_asset_tile(
+    ui: &mut egui::Ui,
+    entry: &AssetEntry,
+    selected: bool,
+    thumbnail: Option<(egui::TextureId, egui::Vec2)>,
+    vcs_file_status: Option<VcsFileStatus>,
+) -> egui::Response {
+    let thumbnail_size = THUMBNAIL_SIZE as f32;
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(thumbnail_size + 8.0, thumbnail_size + 26.0),
+        egui::Sense::hover(),
+    );
+    let response = ui.interact(
+        rect,
+        ui.make_persistent_id(("asset_tile", &entry.path)),
+        egui::Sense::click_and_drag(),
+    );
+    if selected {
+        ui.painter().rect_filled(rect, 3.0, egui::Color32::from_rgb(45, 45, 55));
+    } else if response.hovered() {
+        ui.painter().rect_filled(rect, 3.0, egui::Color32::from_rgb(35, 35, 42));
+    }
 
-fn image_handle_label(handle: &Option<Handle<Image>>) -> String {
-    handle
+    let image_rect = egui::Rect::from_min_size(rect.min + egui::vec2(4.0, 4.0), egui::Vec2::splat(thumbnail_size));
+    match thumbnail {
+        Some((texture_id, size)) => {
+            let scale = (thumbnail_size / size.x.max(1.0)).min(thumbnail_size / size.y.max(1.0));
+            let fitted = egui::Rect::from_center_size(image_rect.center(), size * scale);
+            egui::Image::new(egui::load::SizedTexture::new(texture_id, fitted.size())).paint_at(ui, fitted);
+        }
+        None => {
+            ui.painter().rect_filled(image_rect, 2.0, egui::Color32::from_rgb(28, 28, 32));
+            ui.painter().text(
+                image_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                asset_kind_label(entry.kind),
+                egui::TextStyle::Small.resolve(ui.style()),
+                egui::Color32::from_rgb(140, 140, 140),
+            );
+        }
+    }
+    if let Some(status) = vcs_file_status {
+        ui.painter().text(
+            image_rect.right_top() + egui::vec2(-2.0, 2.0),
+            egui::Align2::RIGHT_TOP,
+            status.badge(),
+            egui::TextStyle::Body.resolve(ui.style()),
+            status.color(),
+        );
+    }
+
+    let name = std::path::Path::new(&entry.path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&entry.path);
+    let shown_name = if name.chars().count() > 14 {
+        format!("{}…", name.chars().take(13).collect::<String>())
+    } else {
+        name.to_string()
+    };
+    ui.painter().text(
+        egui::pos2(rect.center().x, rect.max.y - 4.0),
+        egui::Align2::CENTER_BOTTOM,
+        shown_name,
+        egui::TextStyle::Small.resolve(ui.style()),
+        egui::Color32::from_rgb(220, 220, 220),
+    );
+    response.on_hover_text(entry.path.as_str())
+}
+
+/// Dragging, selection, double clicks and the context menu, the same for
+/// list rows and grid tiles
+fn handle_asset_entry_response(
+    response: egui::Response,
+    entry: &AssetEntry,
+    vcs_file_status: Option<VcsFileStatus>,
+    editor_state: &mut EditorState,
+    asset_cache: &AssetBrowserCache,
+    vcs_status: &VcsStatus,
+    spawn_asset_queue: &mut Vec<SpawnAssetEvent>,
+    open_external_queue: &mut Vec<OpenExternalEvent>,
+    vcs_action_queue: &mut Vec<VcsActionEvent>,
+) {
+    let response = match vcs_file_status {
+        Some(status) => response.on_hover_text(status.label()),
+        None => response,
+    };
+
+    response.dnd_set_drag_payload(DragPayload::Asset(entry.path.clone()));
+    if response.clicked() || response.drag_started() {
+        editor_state.selected_asset = Some(entry.path.clone());
+    }
+    // Models and textures go into the scene; everything else is
+    // edited in its own application.
+    if response.double_clicked() {
+        if matches!(entry.kind, AssetKind::Model | AssetKind::Image) {
+            spawn_asset_queue.push(SpawnAssetEvent {
+                path: entry.path.clone(),
+                parent: None,
+            });
+        } else {
+            open_external_queue.push(OpenExternalEvent {
+                path: entry.path.clone(),
+            });
+        }
+    }
+    response.context_menu(|ui| {
+        if ui.button("Open in External Editor").clicked() {
+            open_external_queue.push(OpenExternalEvent {
+                path: entry.path.clone(),
+            });
+            ui.close_menu();
+        }
+        if ui.button("Add to Scene").clicked() {
+            spawn_asset_queue.push(SpawnAssetEvent {
+                path: entry.path.clone(),
+                parent: None,
+            });
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.button("Move / Rename...").clicked() {
+            editor_state.asset_file_dialog = Some(AssetFileDialog::new(
+                &asset_cache.root,
+                &entry.path,
+                AssetFileAction::Move,
+            ));
+            ui.close_menu();
+        }
+        if ui.button("Delete...").clicked() {
+            editor_state.asset_file_dialog = Some(AssetFileDialog::new(
+                &asset_cache.root,
+                &entry.path,
+                AssetFileAction::Delete,
+            ));
+            ui.close_menu();
+        }
+        if vcs_status.repo_root.is_some() {
+            ui.separator();
+            let changed = vcs_file_status.is_some_and(|status| status.can_revert());
+            if ui.add_enabled(changed, egui::Button::new("View Diff")).clicked() {
+                vcs_action_queue.push(VcsActionEvent::ViewDiff(entry.path.clone()));
+                ui.close_menu();
+            }
+            if ui.add_enabled(changed, egui::Button::new("Revert File...")).clicked() {
+                editor_state.revert_confirm = Some(entry.path.clone());
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+fn draw_geo_sun_fields(ui: &mut egui::Ui, location: &mut GeoSunLocation, time_of_day: f32) {
+    egui::Grid::new("geo_sun_grid").num_columns(2).show(ui, |ui| {
+        ui.label("Latitude:");
+        ui.add(egui::DragValue::new(&mut location.latitude).speed(0.1).range(-90.0..=90.0).suffix("°"));
+        ui.end_row();
+        ui.label("Longitude:");
+        ui.add(egui::DragValue::new(&mut location.longitude).speed(0.1).range(-180.0..=180.0).suffix("°"));
+        ui.end_row();
+        ui.label("Date:");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut location.year).range(1900..=2200));
+            ui.add(egui::DragValue::new(&mut location.month).range(1..=12));
+            let days = days_in_month(location.year, location.month);
+            location.day = location.day.min(days);
+            ui.add(egui::DragValue::new(&mut location.day).range(1..=days));
+        });
+        ui.end_row();
+        ui.label("UTC Offset:");
+        ui.add(egui::DragValue::new(&mut location.utc_offset).speed(0.25).range(-12.0..=14.0).suffix(" h"));
+        ui.end_row();
+        ui.label("North Heading:");
+        ui.add(egui::Slider::new(&mut location.north_heading, 0.0..=360.0));
+        ui.end_row();
+    });
+    let position = solar_position(location, time_of_day);
+    ui.label(format!(
+        "Sun: azimuth {:.1}°, elevation {:.1}°",
+        position.azimuth, position.elevation
+    ));
+}
+
+/// Color picker, followed by the linear values in the working space when
+/// that is not the renderer's own. Returns whether the color changed.
+fn color_field(ui: &mut egui::Ui, label: &str, color: &mut Color, space: WorkingColorSpace) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut picked = color_to_egui(*color);
+        if ui.color_edit_button_srgba(&mut picked).changed() {
+            *color = egui_to_color(picked);
+            return true;
+        }
+        if space == WorkingColorSpace::LinearSrgb {
+            return false;
+        }
+        let mut rgb = space.to_working(*color);
+        let mut changed = false;
+        for value in rgb.as_mut() {
+            changed |= ui
+                .add(egui::DragValue::new(value).speed(0.005).range(0.0..=100.0).max_decimals(3))
+                .on_hover_text(space.label())
+                .changed();
+        }
+        if changed {
+            *color = space.to_render(rgb, color.alpha());
+        }
+        changed
+    })
+    .inner
+}
+
+pub(super) fn color_to_egui(color: Color) -> egui::Color32 {
+    let srgba = color.to_srgba();
+    let r = (srgba.red.clamp(0.0, 1.0) * 255.0) as u8;
+    let g = (srgba.green.clamp(0.0, 1.0) * 255.0) as u8;
+    let b = (srgba.blue.clamp(0.0, 1.0) * 255.0) as u8;
+    let a = (srgba.alpha.clamp(0.0, 1.0) * 255.0) as u8;
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+pub(super) fn egui_to_color(color: egui::Color32) -> Color {
+    let [r, g, b, a] = color.to_array();
+    Color::srgba(
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0,
+        a as f32 / 255.0,
+    )
+}
+
+fn image_handle_label(handle: &Option<Handle<Image>>) -> String {
+    handle
         .as_ref()
         .and_then(|handle| handle.path())
         .map(|path| path.to_string())
         .unwrap_or_else(|| "None".to_string())
 }
 
-fn material_handle_label(handle: &Handle<StandardMaterial>) -> String {
-    handle
-        .path()
-        .map(|path| path.to_string())
-        .unwrap_or_else(|| "Runtime".to_string())
+fn draw_missing_asset_section(
+    ui: &mut egui::Ui,
+    missing: &MissingAsset,
+    asset_entries: &[AssetEntry],
+    locate_missing_queue: &mut Vec<LocateMissingAssetEvent>,
+) {
+    ui.colored_label(
+        egui::Color32::from_rgb(230, 90, 80),
+        format!("{} not found:", missing.kind.label()),
+    );
+    ui.label(&missing.path);
+
+    let kind = match missing.kind {
+        MissingAssetKind::Texture => AssetKind::Image,
+        MissingAssetKind::Scene | MissingAssetKind::Mesh => AssetKind::Model,
+    };
+    let file_name = std::path::Path::new(&missing.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&missing.path);
+    let mut candidates: Vec<&AssetEntry> = asset_entries.iter().filter(|entry| entry.kind == kind).collect();
+    // Files with the same name first; they are usually the moved original
+    candidates.sort_by_key(|entry| !entry.path.rsplit('/').next().is_some_and(|name| name == file_name));
+
+    ui.menu_button("Locate...", |ui| {
+        if candidates.is_empty() {
+            ui.label("No matching assets");
+        }
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            for entry in candidates {
+                if ui.button(&entry.path).clicked() {
+                    locate_missing_queue.push(LocateMissingAssetEvent {
+                        missing: missing.path.clone(),
+                        replacement: entry.path.clone(),
+                    });
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+}
+
+fn draw_vehicle_fields(ui: &mut egui::Ui, vehicle: &mut crate::core::vehicle::RaycastVehicle) {
+    let mut field = |ui: &mut egui::Ui, label: &str, value: &mut f32, speed: f64, max: f32| {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(egui::DragValue::new(value).speed(speed).range(0.0..=max));
+        });
+    };
+    field(ui, "Mass (kg):", &mut vehicle.mass, 10.0, 100000.0);
+    field(ui, "Engine Force:", &mut vehicle.engine_force, 50.0, 1000000.0);
+    field(ui, "Brake Force:", &mut vehicle.brake_force, 50.0, 1000000.0);
+    field(ui, "Max Steer (deg):", &mut vehicle.max_steer_angle, 0.5, 80.0);
+    field(ui, "Grip:", &mut vehicle.grip, 0.01, 5.0);
+    field(ui, "Drag:", &mut vehicle.drag, 0.01, 10.0);
+
+    ui.separator();
+    ui.strong("Suspension");
+    field(ui, "Length:", &mut vehicle.suspension_length, 0.01, 2.0);
+    field(ui, "Stiffness:", &mut vehicle.suspension_stiffness, 100.0, 1000000.0);
+    field(ui, "Damping:", &mut vehicle.suspension_damping, 10.0, 100000.0);
+
+    ui.separator();
+    ui.strong("Wheels");
+    let mut remove = None;
+    for (index, wheel) in vehicle.wheels.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("#{}", index));
+                ui.add(egui::DragValue::new(&mut wheel.position.x).speed(0.01).prefix("X: "));
+                ui.add(egui::DragValue::new(&mut wheel.position.y).speed(0.01).prefix("Y: "));
+                ui.add(egui::DragValue::new(&mut wheel.position.z).speed(0.01).prefix("Z: "));
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(egui::DragValue::new(&mut wheel.radius).speed(0.01).range(0.01..=5.0));
+                ui.checkbox(&mut wheel.steered, "Steered");
+                ui.checkbox(&mut wheel.driven, "Driven");
+            });
+        });
+    }
+    if let Some(index) = remove {
+        vehicle.wheels.remove(index);
+    }
+    if ui.button("Add Wheel").clicked() {
+        let wheel = vehicle.wheels.last().cloned().unwrap_or(crate::core::vehicle::VehicleWheel {
+            position: Vec3::new(0.0, -0.2, 0.0),
+            radius: 0.35,
+            steered: false,
+            driven: false,
+        });
+        vehicle.wheels.push(wheel);
+    }
+}
+
+fn draw_state_machine_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    machine: &mut crate::core::state_machine::AnimationStateMachine,
+    state_machine_edit_queue: &mut Vec<StateMachineEditEvent>,
+) {
+    ui.horizontal(|ui| {
+        ui.label(format!("State machine: {}", machine.graph));
+        if ui.small_button("Remove").clicked() {
+            state_machine_edit_queue.push(StateMachineEditEvent { entity, graph: None });
+        }
+    });
+    ui.label(format!("State: {}", machine.state.as_deref().unwrap_or("-")));
+
+    // Parameters can be nudged by hand to try transitions out
+    let mut floats: Vec<_> = machine.parameters.floats.iter_mut().collect();
+    floats.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in floats {
+        ui.horizontal(|ui| {
+            ui.label(name.as_str());
+            ui.add(egui::DragValue::new(value).speed(0.05));
+        });
+    }
+    let mut bools: Vec<_> = machine.parameters.bools.iter_mut().collect();
+    bools.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in bools {
+        ui.checkbox(value, name.as_str());
+    }
+}
+
+fn draw_animator_fields(ui: &mut egui::Ui, animator: &mut crate::core::animation::WaffleAnimator) {
+    if animator.clips.is_empty() {
+        ui.label("No animation clips");
+        return;
+    }
+
+    let current = animator.clip.clone().unwrap_or_else(|| animator.clips[0].clone());
+    ui.horizontal(|ui| {
+        ui.label("Clip");
+        egui::ComboBox::from_id_source("animator_clip")
+            .selected_text(&current)
+            .show_ui(ui, |ui| {
+                for clip in &animator.clips {
+                    if ui.selectable_label(*clip == current, clip).clicked() && *clip != current {
+                        animator.clip = Some(clip.clone());
+                        animator.playing = true;
+                    }
+                }
+            });
+    });
+
+    ui.horizontal(|ui| {
+        let label = if animator.playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            animator.playing = !animator.playing;
+        }
+        if ui.button("Restart").clicked() {
+            animator.seek = Some(0.0);
+        }
+    });
+
+    // Looping clips keep counting past their end
+    let duration = animator.duration;
+    let mut time = if duration > 0.0 { animator.time.rem_euclid(duration) } else { 0.0 };
+    ui.horizontal(|ui| {
+        ui.label("Time");
+        let slider = egui::Slider::new(&mut time, 0.0..=duration.max(0.0)).suffix(" s");
+        if ui.add_enabled(duration > 0.0, slider).changed() {
+            animator.seek = Some(time);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Speed");
+        ui.add(egui::DragValue::new(&mut animator.speed).speed(0.05).range(0.0..=10.0).suffix("x"));
+    });
+    ui.checkbox(&mut animator.looping, "Loop");
+}
+
+fn draw_keyframe_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    animation: Option<&mut crate::core::keyframes::PropertyAnimation>,
+    key_time: &mut f32,
+    interpolation: &mut crate::core::keyframes::KeyInterpolation,
+    has_light: bool,
+    has_material: bool,
+    keyframe_edit_queue: &mut Vec<KeyframeEditEvent>,
+) {
+    use crate::core::keyframes::{KeyInterpolation, KeyedProperty};
+
+    ui.horizontal(|ui| {
+        ui.label("Key At");
+        ui.add(egui::DragValue::new(key_time).speed(0.05).range(0.0..=3600.0).suffix(" s"));
+        egui::ComboBox::from_id_source("keyframe_interpolation")
+            .selected_text(interpolation.label())
+            .show_ui(ui, |ui| {
+                for candidate in KeyInterpolation::ALL {
+                    ui.selectable_value(interpolation, candidate, candidate.label());
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        let mut record = |properties: &[KeyedProperty]| {
+            keyframe_edit_queue.push(KeyframeEditEvent {
+                entity,
+                kind: KeyframeEditKind::Record {
+                    properties: properties.to_vec(),
+                    time: *key_time,
+                    interpolation: *interpolation,
+                },
+            });
+        };
+        if ui.button("Key Transform").clicked() {
+            record(&KeyedProperty::TRANSFORM);
+        }
+        if ui.add_enabled(has_light, egui::Button::new("Key Light")).clicked() {
+            record(&KeyedProperty::LIGHT);
+        }
+        if ui.add_enabled(has_material, egui::Button::new("Key Material")).clicked() {
+            record(&KeyedProperty::MATERIAL);
+        }
+    });
+
+    let Some(animation) = animation else {
+        ui.label("Key the current values to start animating this entity");
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("Speed");
+        ui.add(egui::DragValue::new(&mut animation.speed).speed(0.05).range(0.0..=10.0).suffix("x"));
+        ui.checkbox(&mut animation.looping, "Loop");
+        ui.label(format!("Length {:.2} s", animation.duration()));
+    });
+
+    let mut remove_track = None;
+    for (track_index, track) in animation.tracks.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(format!("{} ({} keys)", track.property.label(), track.keys.len()))
+            .id_source(("keyframe_track", track_index))
+            .show(ui, |ui| {
+                let mut remove_key = None;
+                for (key_index, key) in track.keys.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button(format!("{:.2} s", key.time)).on_hover_text("Key at this time").clicked() {
+                            *key_time = key.time;
+                        }
+                        egui::ComboBox::from_id_source(("keyframe_key", track_index, key_index))
+                            .width(70.0)
+                            .selected_text(key.interpolation.label())
+                            .show_ui(ui, |ui| {
+                                for candidate in KeyInterpolation::ALL {
+                                    ui.selectable_value(&mut key.interpolation, candidate, candidate.label());
+                                }
+                            });
+                        if ui.small_button("Delete").clicked() {
+                            remove_key = Some(key_index);
+                        }
+                    });
+                }
+                if let Some(index) = remove_key {
+                    track.keys.remove(index);
+                }
+                if ui.small_button("Remove Track").clicked() {
+                    remove_track = Some(track_index);
+                }
+            });
+    }
+    if let Some(index) = remove_track {
+        animation.tracks.remove(index);
+    }
+
+    if ui.button("Remove Keyframes").clicked() {
+        keyframe_edit_queue.push(KeyframeEditEvent {
+            entity,
+            kind: KeyframeEditKind::Remove,
+        });
+    }
+}
+
+fn draw_lens_flare_fields(
+    ui: &mut egui::Ui,
+    flare: &mut crate::rendering::lens_flare::LensFlare,
+    working_space: WorkingColorSpace,
+) {
+    use crate::rendering::lens_flare::{LensFlareElement, LensFlareShape};
+
+    ui.checkbox(&mut flare.enabled, "Enabled");
+    ui.horizontal(|ui| {
+        ui.label("Intensity:");
+        ui.add(egui::Slider::new(&mut flare.intensity, 0.0..=4.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Fade Speed:");
+        ui.add(egui::DragValue::new(&mut flare.fade_speed).speed(0.1).range(0.1..=60.0));
+    });
+
+    ui.separator();
+    ui.strong("Elements");
+    let mut remove = None;
+    for (index, element) in flare.elements.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("#{}", index));
+                egui::ComboBox::from_id_source("lens_flare_shape")
+                    .selected_text(element.shape.label())
+                    .show_ui(ui, |ui| {
+                        for shape in LensFlareShape::ALL {
+                            ui.selectable_value(&mut element.shape, shape, shape.label());
+                        }
+                    });
+                if element.texture.is_some() {
+                    ui.weak("Custom texture");
+                }
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut element.position).speed(0.01).range(-1.0..=3.0).prefix("Position: "));
+                ui.add(egui::DragValue::new(&mut element.size).speed(0.005).range(0.0..=2.0).prefix("Size: "));
+            });
+            color_field(ui, "Color:", &mut element.color, working_space);
+        });
+    }
+    if let Some(index) = remove {
+        flare.elements.remove(index);
+    }
+    if ui.button("Add Element").clicked() {
+        let element = flare.elements.last().cloned().unwrap_or(LensFlareElement {
+            shape: LensFlareShape::Ghost,
+            texture: None,
+            position: 1.0,
+            size: 0.08,
+            color: Color::srgba(1.0, 1.0, 1.0, 0.2),
+        });
+        flare.elements.push(element);
+    }
+}
+
+fn draw_particle_emitter_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    emitter: Option<&mut crate::rendering::particles::ParticleEmitter>,
+    working_space: WorkingColorSpace,
+    particle_emitter_edit_queue: &mut Vec<ParticleEmitterEditEvent>,
+) {
+    use crate::rendering::particles::{ParticleEmitter, ParticlePreset};
+
+    let Some(emitter) = emitter else {
+        ui.horizontal(|ui| {
+            ui.label("Add:");
+            if ui.button("Empty").clicked() {
+                particle_emitter_edit_queue.push(ParticleEmitterEditEvent {
+                    entity,
+                    kind: ParticleEmitterEditKind::Set(ParticleEmitter::default()),
+                });
+            }
+            for preset in ParticlePreset::ALL {
+                if ui.button(preset.label()).clicked() {
+                    particle_emitter_edit_queue.push(ParticleEmitterEditEvent {
+                        entity,
+                        kind: ParticleEmitterEditKind::Set(preset.emitter()),
+                    });
+                }
+            }
+        });
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut emitter.enabled, "Enabled");
+        if ui.small_button("Restart").clicked() {
+            particle_emitter_edit_queue.push(ParticleEmitterEditEvent {
+                entity,
+                kind: ParticleEmitterEditKind::Restart,
+            });
+        }
+        if ui.small_button("Remove").clicked() {
+            particle_emitter_edit_queue.push(ParticleEmitterEditEvent {
+                entity,
+                kind: ParticleEmitterEditKind::Remove,
+            });
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Preset:");
+        for preset in ParticlePreset::ALL {
+            if ui.small_button(preset.label()).clicked() {
+                *emitter = ParticleEmitter {
+                    enabled: emitter.enabled,
+                    ..preset.emitter()
+                };
+            }
+        }
+    });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Rate:");
+        ui.add(egui::DragValue::new(&mut emitter.rate).speed(0.5).range(0.0..=10000.0).suffix("/s"));
+        ui.label("Max:");
+        ui.add(egui::DragValue::new(&mut emitter.max_particles).speed(1.0).range(1..=100000));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Lifetime:");
+        ui.add(egui::DragValue::new(&mut emitter.lifetime).speed(0.05).range(0.01..=60.0).suffix(" s"));
+        ui.label("Variation:");
+        ui.add(egui::Slider::new(&mut emitter.variation, 0.0..=1.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Speed:");
+        ui.add(egui::DragValue::new(&mut emitter.speed).speed(0.05).range(0.0..=1000.0));
+        ui.label("Cone:");
+        ui.add(egui::Slider::new(&mut emitter.cone_angle, 0.0..=180.0).suffix("°"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Spawn Radius:");
+        ui.add(egui::DragValue::new(&mut emitter.spawn_radius).speed(0.01).range(0.0..=100.0));
+        ui.label("Drag:");
+        ui.add(egui::DragValue::new(&mut emitter.drag).speed(0.01).range(0.0..=10.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Gravity:");
+        ui.add(egui::DragValue::new(&mut emitter.gravity.x).speed(0.05).prefix("X: ")).accessible_name("Gravity X");
+        ui.add(egui::DragValue::new(&mut emitter.gravity.y).speed(0.05).prefix("Y: ")).accessible_name("Gravity Y");
+        ui.add(egui::DragValue::new(&mut emitter.gravity.z).speed(0.05).prefix("Z: ")).accessible_name("Gravity Z");
+    });
+
+    ui.separator();
+    // Curves are edited in the Particles tab, where they can be seen
+    if emitter.size_curve.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            ui.add(egui::DragValue::new(&mut emitter.start_size).speed(0.01).range(0.0..=100.0).prefix("Start: "))
+                .accessible_name("Start size");
+            ui.add(egui::DragValue::new(&mut emitter.end_size).speed(0.01).range(0.0..=100.0).prefix("End: "))
+                .accessible_name("End size");
+        });
+    } else {
+        ui.label("Size: from curve");
+    }
+    if emitter.color_gradient.is_empty() {
+        color_field(ui, "Start Color:", &mut emitter.start_color, working_space);
+        color_field(ui, "End Color:", &mut emitter.end_color, working_space);
+    } else {
+        ui.label("Color: from gradient");
+    }
+    ui.checkbox(&mut emitter.additive, "Additive (glow)");
+}
+
+fn draw_terrain_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    terrain: Option<&mut crate::terrain::WaffleTerrain>,
+    working_space: WorkingColorSpace,
+    save_name: &mut String,
+    terrain_edit_queue: &mut Vec<TerrainEditEvent>,
+) {
+    use crate::terrain::WaffleTerrain;
+
+    let Some(terrain) = terrain else {
+        if ui.button("Add Terrain").clicked() {
+            terrain_edit_queue.push(TerrainEditEvent {
+                entity,
+                kind: TerrainEditKind::Set(WaffleTerrain::default()),
+            });
+        }
+        return;
+    };
+
+    if ui.small_button("Remove").clicked() {
+        terrain_edit_queue.push(TerrainEditEvent {
+            entity,
+            kind: TerrainEditKind::Remove,
+        });
+    }
+    image_drop_field(ui, "Heightmap:", &mut terrain.heightmap);
+    // Sculpted heights live until the heightmap or resolution changes;
+    // saving them makes them the heightmap
+    ui.horizontal(|ui| {
+        let mut push = |kind| terrain_edit_queue.push(TerrainEditEvent { entity, kind });
+        if ui.small_button("Undo Stroke").clicked() {
+            push(TerrainEditKind::UndoStroke);
+        }
+        if ui.small_button("Redo Stroke").clicked() {
+            push(TerrainEditKind::RedoStroke);
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(save_name).hint_text("terrain/name").desired_width(140.0));
+        let name = save_name.trim().trim_end_matches(".png").to_string();
+        if ui.add_enabled(!name.is_empty(), egui::Button::new("Save Heightmap")).clicked() {
+            terrain_edit_queue.push(TerrainEditEvent {
+                entity,
+                kind: TerrainEditKind::SaveHeightmap(format!("{name}.png")),
+            });
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Size:");
+        ui.add(egui::DragValue::new(&mut terrain.size.x).speed(0.5).range(1.0..=100000.0).prefix("W: "))
+            .accessible_name("Terrain width");
+        ui.add(egui::DragValue::new(&mut terrain.size.y).speed(0.5).range(1.0..=100000.0).prefix("D: "))
+            .accessible_name("Terrain depth");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Height Scale:");
+        ui.add(egui::DragValue::new(&mut terrain.height_scale).speed(0.1).range(0.0..=10000.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Chunks:");
+        ui.add(egui::DragValue::new(&mut terrain.chunks).range(1..=32));
+        ui.label("Resolution:");
+        ui.add(egui::DragValue::new(&mut terrain.chunk_resolution).range(1..=256))
+            .on_hover_text("Quads along each side of a chunk");
+    });
+
+    ui.separator();
+    image_drop_field(ui, "Splat Map:", &mut terrain.splatmap);
+    for (index, (layer, channel)) in terrain.layers.iter_mut().zip(["Red", "Green", "Blue", "Alpha"]).enumerate() {
+        ui.label(format!("Layer {} ({channel})", index + 1));
+        ui.indent(("terrain_layer", index), |ui| {
+            image_drop_field(ui, "Texture:", &mut layer.texture);
+            color_field(ui, "Tint:", &mut layer.tint, working_space);
+            ui.horizontal(|ui| {
+                ui.label("Tiling:");
+                ui.add(egui::DragValue::new(&mut layer.tiling).speed(0.5).range(0.01..=10000.0));
+            });
+        });
+    }
+}
+
+fn draw_foliage_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    layer: Option<&mut crate::rendering::foliage::FoliageLayer>,
+    foliage_edit_queue: &mut Vec<FoliageEditEvent>,
+) {
+    use crate::rendering::foliage::FoliageLayer;
+
+    let Some(layer) = layer else {
+        if ui.button("Add Foliage Layer").clicked() {
+            foliage_edit_queue.push(FoliageEditEvent {
+                entity,
+                kind: FoliageEditKind::Set(FoliageLayer::default()),
+            });
+        }
+        return;
+    };
+
+    if ui.small_button("Remove").clicked() {
+        foliage_edit_queue.push(FoliageEditEvent {
+            entity,
+            kind: FoliageEditKind::Remove,
+        });
+    }
+    sub_asset_drop_field(ui, "Mesh:", &mut layer.mesh, "Mesh");
+    sub_asset_drop_field(ui, "Material:", &mut layer.material, "Material");
+    ui.checkbox(&mut layer.cast_shadows, "Cast Shadows");
+    ui.horizontal(|ui| {
+        ui.label(format!("{} instances", layer.instances.len()));
+        if !layer.instances.is_empty() && ui.small_button("Clear").clicked() {
+            layer.instances.clear();
+        }
+    });
+    if layer.mesh.is_empty() {
+        ui.label(egui::RichText::new("Drop a mesh from a model to paint with the Foliage tool").weak());
+    }
+}
+
+fn draw_water_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    water: Option<&mut crate::rendering::water::WaffleWater>,
+    working_space: WorkingColorSpace,
+    water_edit_queue: &mut Vec<WaterEditEvent>,
+) {
+    use crate::rendering::water::{WaffleWater, WaterReflections};
+
+    let Some(water) = water else {
+        if ui.button("Add Water").clicked() {
+            water_edit_queue.push(WaterEditEvent {
+                entity,
+                kind: WaterEditKind::Set(WaffleWater::default()),
+            });
+        }
+        return;
+    };
+
+    if ui.small_button("Remove").clicked() {
+        water_edit_queue.push(WaterEditEvent {
+            entity,
+            kind: WaterEditKind::Remove,
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Size:");
+        ui.add(egui::DragValue::new(&mut water.size.x).speed(0.1).range(0.01..=100000.0).prefix("X: "));
+        ui.add(egui::DragValue::new(&mut water.size.y).speed(0.1).range(0.01..=100000.0).prefix("Z: "));
+    });
+    color_field(ui, "Shallow:", &mut water.shallow_color, working_space);
+    color_field(ui, "Deep:", &mut water.deep_color, working_space);
+    ui.horizontal(|ui| {
+        ui.label("Absorption Depth:");
+        ui.add(egui::DragValue::new(&mut water.absorption_depth).speed(0.05).range(0.01..=1000.0));
+    });
+
+    ui.separator();
+    image_drop_field(ui, "Normal Map:", &mut water.normal_map);
+    ui.horizontal(|ui| {
+        ui.label("Wave Scale:");
+        ui.add(egui::DragValue::new(&mut water.wave_scale).speed(0.05).range(0.01..=1000.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Wave Speed:");
+        ui.add(egui::DragValue::new(&mut water.wave_speed).speed(0.01).range(0.0..=100.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Wave Direction:");
+        ui.add(egui::DragValue::new(&mut water.wave_direction).speed(1.0).range(-360.0..=360.0).suffix("°"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Wave Strength:");
+        ui.add(egui::Slider::new(&mut water.wave_strength, 0.0..=2.0));
+    });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Reflections:");
+        egui::ComboBox::from_id_source("water_reflections")
+            .selected_text(water.reflections.label())
+            .show_ui(ui, |ui| {
+                for reflections in WaterReflections::ALL {
+                    ui.selectable_value(&mut water.reflections, reflections, reflections.label());
+                }
+            });
+    });
+    if water.reflections != WaterReflections::None {
+        ui.horizontal(|ui| {
+            ui.label("Reflection Strength:");
+            ui.add(egui::Slider::new(&mut water.reflection_strength, 0.0..=1.0));
+        });
+    }
+    if water.reflections == WaterReflections::Planar {
+        ui.horizontal(|ui| {
+            ui.label("Resolution:");
+            ui.add(egui::DragValue::new(&mut water.reflection_resolution).speed(4.0).range(64..=4096));
+        });
+    }
+}
+
+fn draw_lod_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    lod: Option<(&mut crate::rendering::lod::LodGroup, Option<&mut crate::rendering::lod::LodState>)>,
+    lod_edit_queue: &mut Vec<LodEditEvent>,
+) {
+    use crate::rendering::lod::{LodGroup, LodLevel};
+
+    let Some((group, state)) = lod else {
+        if ui.button("Add LOD Group").clicked() {
+            lod_edit_queue.push(LodEditEvent {
+                entity,
+                kind: LodEditKind::Set(LodGroup::default()),
+            });
+        }
+        return;
+    };
+
+    if ui.small_button("Remove").clicked() {
+        lod_edit_queue.push(LodEditEvent {
+            entity,
+            kind: LodEditKind::Remove,
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Hysteresis:");
+        ui.add(egui::DragValue::new(&mut group.hysteresis).speed(0.05).range(0.0..=1000.0));
+    });
+
+    ui.separator();
+    let active = state.as_ref().and_then(|state| state.active());
+    let mut removed = None;
+    for (index, level) in group.levels.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.horizontal(|ui| {
+                let title = format!("LOD {}", index);
+                if active == Some(index) {
+                    ui.strong(title);
+                } else {
+                    ui.label(title);
+                }
+                ui.label("From:");
+                ui.add(egui::DragValue::new(&mut level.distance).speed(0.1).range(0.0..=100000.0));
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(index);
+                }
+            });
+            sub_asset_drop_field(ui, "Mesh:", &mut level.mesh, "Mesh");
+        });
+    }
+    if let Some(index) = removed {
+        group.levels.remove(index);
+    }
+    if ui.button("Add Level").clicked() {
+        let distance = group.levels.last().map_or(0.0, |level| level.distance + 10.0);
+        group.levels.push(LodLevel {
+            mesh: String::new(),
+            distance,
+        });
+    }
+    if group.levels.windows(2).any(|pair| pair[1].distance < pair[0].distance) {
+        ui.colored_label(egui::Color32::from_rgb(220, 80, 80), "Levels should go from nearest to farthest");
+    }
+    ui.label(egui::RichText::new("Levels without a mesh draw the entity's own mesh").weak());
+
+    let Some(state) = state else {
+        return;
+    };
+    ui.separator();
+    let distance = match state.camera_distance() {
+        Some(distance) => format!("{:.1}", distance),
+        None => "No camera".to_string(),
+    };
+    ui.label(format!("Camera Distance: {}", distance));
+    let level_label = |level: Option<usize>| match level {
+        Some(level) => format!("LOD {}", level),
+        None => "By Distance".to_string(),
+    };
+    ui.horizontal(|ui| {
+        ui.label("Preview:");
+        egui::ComboBox::from_id_source("lod_preview")
+            .selected_text(level_label(state.preview))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.preview, None, level_label(None));
+                for level in 0..group.levels.len() {
+                    ui.selectable_value(&mut state.preview, Some(level), level_label(Some(level)));
+                }
+            });
+    });
+}
+
+/// A model sub-asset path, e.g. `models/tree.glb#Mesh0/Primitive0`, set by
+/// dropping one of `kind` (`Mesh` or `Material`) from the asset browser.
+/// Material files are taken as materials too.
+fn sub_asset_drop_field(ui: &mut egui::Ui, label: &str, path: &mut String, kind: &str) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let shown = if path.is_empty() { "None".to_string() } else { path.clone() };
+        let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+            ui.label(shown);
+        });
+        if let Some(DragPayload::Asset(dropped)) = dropped.as_deref() {
+            let is_kind = split_label(dropped).1.is_some_and(|label| label.starts_with(kind))
+                || (kind == "Material"
+                    && dropped.ends_with(&format!(".{}", crate::rendering::materials::MATERIAL_EXTENSION)));
+            if is_kind {
+                *path = dropped.clone();
+            }
+        }
+        if !path.is_empty() && ui.small_button("Clear").clicked() {
+            path.clear();
+        }
+    });
+}
+
+fn draw_sub_scene_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    sub_scene: Option<(&SceneReference, Option<&SubSceneInstance>)>,
+    sub_scene_edit_queue: &mut Vec<SubSceneEditEvent>,
+) {
+    let mut push = |kind| sub_scene_edit_queue.push(SubSceneEditEvent { entity, kind });
+    if let Some((reference, instance)) = sub_scene {
+        ui.horizontal(|ui| {
+            ui.label(format!("Scene: {}", reference.scene));
+            if ui.small_button("Remove").clicked() {
+                push(SubSceneEditKind::Remove);
+            }
+        });
+        if let Some(error) = instance.and_then(|instance| instance.error.as_ref()) {
+            ui.colored_label(egui::Color32::from_rgb(220, 80, 80), error);
+        }
+        if instance.is_some_and(|instance| instance.editing) {
+            ui.label("Open for edit. Saving writes the contents back to the scene.");
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    push(SubSceneEditKind::Save);
+                }
+                if ui.button("Revert").clicked() {
+                    push(SubSceneEditKind::Revert);
+                }
+            });
+        } else {
+            ui.label(egui::RichText::new("Read-only; updates when the scene is saved").weak());
+            let loaded = instance.is_some_and(|instance| instance.error.is_none());
+            if ui.add_enabled(loaded, egui::Button::new("Open for Edit")).clicked() {
+                push(SubSceneEditKind::OpenForEdit);
+            }
+        }
+    }
+    let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+        ui.label("Drop a .scene.ron file here");
+    });
+    if let Some(DragPayload::Asset(path)) = dropped.as_deref() {
+        if let Some(scene) = scene_name_from_asset_path(path) {
+            push(SubSceneEditKind::SetScene(scene.to_string()));
+        }
+    }
+}
+
+/// An image asset path, set by dropping an image from the asset browser
+fn image_drop_field(ui: &mut egui::Ui, label: &str, path: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let shown = if path.is_empty() { "None".to_string() } else { path.clone() };
+        let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+            ui.label(shown);
+        });
+        if let Some(DragPayload::Asset(dropped)) = dropped.as_deref() {
+            if is_image_path(dropped) {
+                *path = dropped.clone();
+            }
+        }
+        if !path.is_empty() && ui.small_button("Clear").clicked() {
+            path.clear();
+        }
+    });
+}
+
+fn draw_portal_fields(
+    ui: &mut egui::Ui,
+    portal: &mut crate::rendering::portal::Portal,
+    preview: Option<egui::TextureId>,
+    hierarchy: &HierarchySnapshot,
+) {
+    ui.checkbox(&mut portal.mirror, "Mirror");
+    if !portal.mirror {
+        entity_drop_field(ui, "Linked:", &mut portal.linked, hierarchy);
+        ui.horizontal(|ui| {
+            ui.label("Recursion:");
+            ui.add(egui::Slider::new(&mut portal.recursion, 1..=crate::rendering::portal::MAX_PORTAL_RECURSION));
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Size:");
+        ui.add(egui::DragValue::new(&mut portal.size.x).speed(0.05).range(0.01..=1000.0).prefix("W: "));
+        ui.add(egui::DragValue::new(&mut portal.size.y).speed(0.05).range(0.01..=1000.0).prefix("H: "));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Resolution:");
+        ui.add(egui::DragValue::new(&mut portal.resolution.x).speed(4.0).range(16..=4096));
+        ui.label("x");
+        ui.add(egui::DragValue::new(&mut portal.resolution.y).speed(4.0).range(16..=4096));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Fallback:");
+        let mut picked = color_to_egui(portal.fallback);
+        if ui.color_edit_button_srgba(&mut picked).changed() {
+            portal.fallback = egui_to_color(picked);
+        }
+    });
+
+    if let Some(texture_id) = preview {
+        ui.separator();
+        ui.label("Preview:");
+        let aspect = portal.resolution.x.max(1) as f32 / portal.resolution.y.max(1) as f32;
+        let height = 160.0;
+        ui.image(egui::load::SizedTexture::new(texture_id, egui::vec2(height * aspect, height)));
+    } else if !portal.mirror && portal.linked.is_none() {
+        ui.label("Link another portal to see through this one");
+    }
+}
+
+fn draw_audio_source_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    source: &mut crate::audio::WaffleAudioSource,
+    audio_source_edit_queue: &mut Vec<AudioSourceEditEvent>,
+) {
+    let mut push = |kind| audio_source_edit_queue.push(AudioSourceEditEvent { entity, kind });
+    ui.horizontal(|ui| {
+        ui.label(format!("Clip: {}", source.clip));
+        if ui.small_button("Remove").clicked() {
+            push(AudioSourceEditKind::Remove);
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Volume:");
+        ui.add(egui::Slider::new(&mut source.volume, 0.0..=2.0));
+    });
+    ui.checkbox(&mut source.looping, "Loop");
+    ui.checkbox(&mut source.play_on_start, "Play On Start");
+    ui.checkbox(&mut source.spatial, "Spatial");
+    if source.spatial {
+        ui.horizontal(|ui| {
+            ui.label("Distance:");
+            ui.add(egui::DragValue::new(&mut source.min_distance).speed(0.1).range(0.0..=source.max_distance).prefix("Min: "));
+            ui.add(egui::DragValue::new(&mut source.max_distance).speed(0.1).range(source.min_distance..=10000.0).prefix("Max: "));
+        });
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Preview").clicked() {
+            push(AudioSourceEditKind::Preview);
+        }
+        if ui.button("Stop").clicked() {
+            push(AudioSourceEditKind::StopPreview);
+        }
+    });
+}
+
+fn draw_dolly_track_fields(ui: &mut egui::Ui, track: &mut crate::rendering::camera_rig::DollyTrack) {
+    ui.label(format!("Length: {:.2}", track.length()));
+    ui.checkbox(&mut track.closed, "Closed");
+
+    let mut remove = None;
+    for (index, point) in track.points.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{}:", index));
+            ui.add(egui::DragValue::new(&mut point.x).speed(0.05).prefix("X: "));
+            ui.add(egui::DragValue::new(&mut point.y).speed(0.05).prefix("Y: "));
+            ui.add(egui::DragValue::new(&mut point.z).speed(0.05).prefix("Z: "));
+            if ui.small_button("X").on_hover_text("Remove point").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        track.points.remove(index);
+    }
+
+    if ui.button("Add Point").clicked() {
+        // Continue in the direction of the last segment
+        let next = match track.points.as_slice() {
+            [.., before, last] => *last + (*last - *before),
+            [last] => *last + Vec3::X * 2.0,
+            [] => Vec3::ZERO,
+        };
+        track.points.push(next);
+    }
+}
+
+fn draw_camera_shake_fields(
+    ui: &mut egui::Ui,
+    shake: &mut crate::rendering::camera_shake::CameraShake,
+    preview_camera_shake_queue: &mut Vec<crate::rendering::camera_shake::PreviewCameraShakeEvent>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Trauma:");
+        ui.add(egui::ProgressBar::new(shake.trauma).desired_width(120.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Decay:");
+        ui.add(egui::DragValue::new(&mut shake.decay).speed(0.05).range(0.0..=10.0).suffix(" /s"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Exponent:");
+        ui.add(egui::DragValue::new(&mut shake.trauma_exponent).speed(0.05).range(0.1..=4.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Frequency:");
+        ui.add(egui::DragValue::new(&mut shake.frequency).speed(0.1).range(0.1..=100.0).suffix(" Hz"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Max Offset:");
+        ui.add(egui::DragValue::new(&mut shake.max_translation.x).speed(0.01).range(0.0..=5.0).prefix("X: "));
+        ui.add(egui::DragValue::new(&mut shake.max_translation.y).speed(0.01).range(0.0..=5.0).prefix("Y: "));
+        ui.add(egui::DragValue::new(&mut shake.max_translation.z).speed(0.01).range(0.0..=5.0).prefix("Z: "));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Max Rotation:");
+        ui.add(egui::DragValue::new(&mut shake.max_rotation.x).speed(0.1).range(0.0..=45.0).prefix("Pitch: "));
+        ui.add(egui::DragValue::new(&mut shake.max_rotation.y).speed(0.1).range(0.0..=45.0).prefix("Yaw: "));
+        ui.add(egui::DragValue::new(&mut shake.max_rotation.z).speed(0.1).range(0.0..=45.0).prefix("Roll: "));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        ui.add(egui::DragValue::new(&mut shake.seed));
+    });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Test in viewport:");
+        for (label, trauma) in [("Light", 0.3), ("Medium", 0.6), ("Heavy", 1.0)] {
+            if ui.button(label).clicked() {
+                let mut preview = shake.clone();
+                preview.trauma = 0.0;
+                preview.shake(trauma);
+                preview_camera_shake_queue.push(crate::rendering::camera_shake::PreviewCameraShakeEvent {
+                    shake: preview,
+                });
+            }
+        }
+    });
+}
+
+fn draw_ao_volume_fields(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    volume: &mut crate::rendering::ao_volume::AoVolume,
+    baking: bool,
+    bake_ao_volume_queue: &mut Vec<crate::rendering::ao_volume::BakeAoVolumeEvent>,
+) {
+    ui.checkbox(&mut volume.enabled, "Enabled");
+    ui.horizontal(|ui| {
+        ui.label("Strength:");
+        ui.add(egui::Slider::new(&mut volume.strength, 0.0..=1.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Probes:");
+        ui.add(egui::DragValue::new(&mut volume.resolution.x).range(1..=64).prefix("X: "));
+        ui.add(egui::DragValue::new(&mut volume.resolution.y).range(1..=64).prefix("Y: "));
+        ui.add(egui::DragValue::new(&mut volume.resolution.z).range(1..=64).prefix("Z: "));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rays per Side:");
+        ui.add(egui::DragValue::new(&mut volume.rays_per_side).range(1..=256));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Max Distance:");
+        ui.add(egui::DragValue::new(&mut volume.max_distance).speed(0.1).range(0.1..=200.0));
+    });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        if ui.add_enabled(!baking, egui::Button::new("Bake")).clicked() {
+            bake_ao_volume_queue.push(crate::rendering::ao_volume::BakeAoVolumeEvent { entity });
+        }
+        if ui.add_enabled(!baking && volume.bake.is_some(), egui::Button::new("Clear")).clicked() {
+            volume.bake = None;
+        }
+        if baking {
+            ui.spinner();
+            ui.label("Baking...");
+        } else if let Some(bake) = &volume.bake {
+            ui.weak(format!("Baked {}x{}x{}", bake.resolution.x, bake.resolution.y, bake.resolution.z));
+        } else {
+            ui.weak("Not baked");
+        }
+    });
+}
+
+fn draw_transform_section(
+    ui: &mut egui::Ui,
+    editor_state: &mut EditorState,
+    transform: &mut Transform,
+    parent_transform: Option<GlobalTransform>,
+) {
+    let parent = parent_transform.unwrap_or(GlobalTransform::IDENTITY);
+    let shown = match editor_state.inspector_space {
+        AxisSpace::Local => *transform,
+        AxisSpace::Global => parent.mul_transform(*transform).compute_transform(),
+    };
+    let mut edited = shown;
+
+    ui.horizontal(|ui| {
+        ui.label("Space:");
+        if ui
+            .selectable_label(editor_state.inspector_space == AxisSpace::Local, "Local")
+            .clicked()
+        {
+            editor_state.inspector_space = AxisSpace::Local;
+        }
+        if ui
+            .selectable_label(editor_state.inspector_space == AxisSpace::Global, "World")
+            .clicked()
+        {
+            editor_state.inspector_space = AxisSpace::Global;
+        }
+        ui.separator();
+        if ui.button("Copy").clicked() {
+            editor_state.transform_clipboard = Some(shown);
+        }
+        if ui
+            .add_enabled(editor_state.transform_clipboard.is_some(), egui::Button::new("Paste"))
+            .clicked()
+        {
+            if let Some(copied) = editor_state.transform_clipboard {
+                edited = copied;
+            }
+        }
+        if ui.button("Reset").clicked() {
+            edited = Transform::IDENTITY;
+        }
+    });
+
+    let mut translation = edited.translation;
+    let mut rotation_deg = cached_euler_degrees(editor_state, edited.rotation);
+    let mut rotation_quat = edited.rotation;
+    let mut rotation_changed = false;
+    let mut scale = edited.scale;
+
+    ui.horizontal(|ui| {
+        ui.label("Position:");
+        ui.add(egui::DragValue::new(&mut translation.x).prefix("X: ")).accessible_name("Position X");
+        ui.add(egui::DragValue::new(&mut translation.y).prefix("Y: ")).accessible_name("Position Y");
+        ui.add(egui::DragValue::new(&mut translation.z).prefix("Z: ")).accessible_name("Position Z");
+        if ui.small_button("Reset").accessible_name("Reset position").clicked() {
+            translation = Vec3::ZERO;
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Rotation:");
+        match editor_state.rotation_display {
+            RotationDisplay::Euler => {
+                for (value, axis) in [(&mut rotation_deg.x, "Y"), (&mut rotation_deg.y, "X"), (&mut rotation_deg.z, "Z")] {
+                    rotation_changed |= ui
+                        .add(egui::DragValue::new(value).prefix(format!("{axis}: ")))
+                        .accessible_name(format!("Rotation {axis} degrees"))
+                        .changed();
+                }
+            }
+            RotationDisplay::Quaternion => {
+                rotation_changed |= ui
+                    .add(egui::DragValue::new(&mut rotation_quat.x).speed(0.01).prefix("X: "))
+                    .accessible_name("Rotation quaternion X")
+                    .changed();
+                rotation_changed |= ui
+                    .add(egui::DragValue::new(&mut rotation_quat.y).speed(0.01).prefix("Y: "))
+                    .accessible_name("Rotation quaternion Y")
+                    .changed();
+                rotation_changed |= ui
+                    .add(egui::DragValue::new(&mut rotation_quat.z).speed(0.01).prefix("Z: "))
+                    .accessible_name("Rotation quaternion Z")
+                    .changed();
+                rotation_changed |= ui
+                    .add(egui::DragValue::new(&mut rotation_quat.w).speed(0.01).prefix("W: "))
+                    .accessible_name("Rotation quaternion W")
+                    .changed();
+            }
+        }
+        if ui.small_button("Reset").accessible_name("Reset rotation").clicked() {
+            edited.rotation = Quat::IDENTITY;
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rotation Mode:");
+        ui.radio_value(&mut editor_state.rotation_display, RotationDisplay::Euler, "Euler");
+        ui.radio_value(&mut editor_state.rotation_display, RotationDisplay::Quaternion, "Quaternion");
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Scale:");
+        ui.add(egui::DragValue::new(&mut scale.x).prefix("X: ")).accessible_name("Scale X");
+        ui.add(egui::DragValue::new(&mut scale.y).prefix("Y: ")).accessible_name("Scale Y");
+        ui.add(egui::DragValue::new(&mut scale.z).prefix("Z: ")).accessible_name("Scale Z");
+        if ui.small_button("Reset").accessible_name("Reset scale").clicked() {
+            scale = Vec3::ONE;
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let label = ui.label("Text:");
+        ui.add(egui::TextEdit::singleline(&mut editor_state.transform_text).desired_width(180.0))
+            .labelled_by(label.id);
+        if ui.button("Copy Text").clicked() {
+            let text = format_transform(&shown);
+            ui.output_mut(|output| output.copied_text = text.clone());
+            editor_state.transform_text = text;
+        }
+        if ui.button("Apply").clicked() {
+            if let Some(parsed) = parse_transform(&editor_state.transform_text) {
+                edited = parsed;
+                translation = parsed.translation;
+                scale = parsed.scale;
+            }
+        }
+    });
+
+    edited.translation = translation;
+    edited.scale = scale;
+    if rotation_changed {
+        match editor_state.rotation_display {
+            RotationDisplay::Euler => {
+                edited.rotation = Quat::from_euler(
+                    EulerRot::YXZ,
+                    rotation_deg.y.to_radians(),
+                    rotation_deg.x.to_radians(),
+                    rotation_deg.z.to_radians(),
+                );
+                if let Some(entity) = editor_state.selection.primary() {
+                    editor_state.rotation_edit = Some(RotationEditCache {
+                        entity,
+                        space: editor_state.inspector_space,
+                        rotation: edited.rotation,
+                        euler_degrees: rotation_deg,
+                    });
+                }
+            }
+            RotationDisplay::Quaternion => {
+                edited.rotation = if rotation_quat.length_squared() > f32::EPSILON {
+                    rotation_quat.normalize()
+                } else {
+                    Quat::IDENTITY
+                };
+            }
+        }
+    }
+
+    if edited == shown {
+        return;
+    }
+    *transform = match editor_state.inspector_space {
+        AxisSpace::Local => edited,
+        AxisSpace::Global => GlobalTransform::from(edited).reparented_to(&parent),
+    };
+}
+
+/// Euler angles for the rotation row, reusing the typed values while the quaternion
+/// still matches so editing near gimbal poles does not flip or drift.
+fn cached_euler_degrees(editor_state: &EditorState, rotation: Quat) -> Vec3 {
+    if let Some(cache) = editor_state.rotation_edit {
+        if Some(cache.entity) == editor_state.selection.primary()
+            && cache.space == editor_state.inspector_space
+            && cache.rotation.abs_diff_eq(rotation, 1e-4)
+        {
+            return cache.euler_degrees;
+        }
+    }
+    let (y, x, z) = rotation.to_euler(EulerRot::YXZ);
+    Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+/// Checkbox grid over the project's named render layers. Entities without a
+/// `RenderLayers` component are on layer 0.
+/// Pick a physical surface or none; returns the new choice when it changes
+fn surface_combo(
+    ui: &mut egui::Ui,
+    id: &str,
+    label: &str,
+    current: Option<PhysicalSurface>,
+    none_label: &'static str,
+) -> Option<Option<PhysicalSurface>> {
+    let mut changed = None;
+    ui.horizontal(|ui| {
+        ui.label(label);
+        egui::ComboBox::from_id_source(id)
+            .selected_text(current.map_or(none_label, PhysicalSurface::label))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(current.is_none(), none_label).clicked() && current.is_some() {
+                    changed = Some(None);
+                }
+                for surface in PhysicalSurface::ALL {
+                    if ui.selectable_label(current == Some(surface), surface.label()).clicked()
+                        && current != Some(surface)
+                    {
+                        changed = Some(Some(surface));
+                    }
+                }
+            });
+    });
+    changed
+}
+
+fn draw_render_layers_grid(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    current: Option<&bevy::render::view::RenderLayers>,
+    project_settings: &crate::core::project::ProjectSettings,
+    render_layers_edit_queue: &mut Vec<RenderLayersEditEvent>,
+) {
+    let current = current.cloned().unwrap_or_default();
+    let mut edited = current.clone();
+    egui::Grid::new("render_layers_grid").num_columns(4).show(ui, |ui| {
+        for layer in 0..crate::core::project::RENDER_LAYER_COUNT {
+            let mut enabled = current.intersects(&bevy::render::view::RenderLayers::layer(layer));
+            if ui.checkbox(&mut enabled, project_settings.render_layer_name(layer)).changed() {
+                let layers = std::mem::take(&mut edited);
+                edited = if enabled { layers.with(layer) } else { layers.without(layer) };
+            }
+            if layer % 4 == 3 {
+                ui.end_row();
+            }
+        }
+    });
+    if edited != current {
+        render_layers_edit_queue.push(RenderLayersEditEvent {
+            entity,
+            layers: edited.clone(),
+            include_children: false,
+        });
+    }
+    if ui
+        .button("Apply to Children")
+        .on_hover_text("Copy these layers to every descendant, e.g. the meshes of an imported model")
+        .clicked()
+    {
+        render_layers_edit_queue.push(RenderLayersEditEvent {
+            entity,
+            layers: edited,
+            include_children: true,
+        });
+    }
+}
+
+/// Target picker for constraints: drop an entity from the hierarchy onto the field
+fn constraint_target_field(ui: &mut egui::Ui, target: &mut Option<Entity>, hierarchy: &HierarchySnapshot) {
+    entity_drop_field(ui, "Target:", target, hierarchy);
+}
+
+/// Entity reference that takes entities dragged from the hierarchy
+fn entity_drop_field(ui: &mut egui::Ui, label: &str, target: &mut Option<Entity>, hierarchy: &HierarchySnapshot) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let label = target
+            .map(|entity| {
+                hierarchy
+                    .names
+                    .get(&entity)
+                    .cloned()
+                    .unwrap_or_else(|| format!("Entity {}", entity.index()))
+            })
+            .unwrap_or_else(|| "None (drop entity here)".to_string());
+        let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+            ui.label(label);
+        });
+        if let Some(DragPayload::Entity(entity)) = dropped.as_deref() {
+            *target = Some(*entity);
+        }
+        if target.is_some() && ui.small_button("Clear").clicked() {
+            *target = None;
+        }
+    });
+}
+
+/// Format a transform as `pos: x, y, z; rot: x, y, z, w; scale: x, y, z`
+fn format_transform(transform: &Transform) -> String {
+    let t = transform.translation;
+    let r = transform.rotation;
+    let s = transform.scale;
+    format!(
+        "pos: {}, {}, {}; rot: {}, {}, {}, {}; scale: {}, {}, {}",
+        t.x, t.y, t.z, r.x, r.y, r.z, r.w, s.x, s.y, s.z
+    )
+}
+
+fn parse_transform(text: &str) -> Option<Transform> {
+    let mut transform = Transform::IDENTITY;
+    for part in text.split(';') {
+        let (key, values) = part.split_once(':')?;
+        let values: Vec<f32> = values
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        match (key.trim(), values.as_slice()) {
+            ("pos", [x, y, z]) => transform.translation = Vec3::new(*x, *y, *z),
+            ("rot", [x, y, z, w]) => {
+                transform.rotation = Quat::from_xyzw(*x, *y, *z, *w).normalize();
+            }
+            ("scale", [x, y, z]) => transform.scale = Vec3::new(*x, *y, *z),
+            _ => return None,
+        }
+    }
+    Some(transform)
+}
+
+/// Colors, maps and PBR values of a material. "Use Selected" puts the image
+/// selected in the asset browser in a map slot.
+fn draw_standard_material_fields(
+    ui: &mut egui::Ui,
+    material: &mut StandardMaterial,
+    selected_texture: Option<&Handle<Image>>,
+    working_space: WorkingColorSpace,
+) {
+    color_field(ui, "Base Color:", &mut material.base_color, working_space);
+
+    ui.horizontal(|ui| {
+        ui.label("Albedo Map:");
+        ui.label(image_handle_label(&material.base_color_texture));
+        if let Some(handle) = selected_texture {
+            if ui.button("Use Selected").clicked() {
+                material.base_color_texture = Some(handle.clone());
+            }
+        }
+    });
+
+    let mut emissive = Color::LinearRgba(material.emissive);
+    if color_field(ui, "Emissive:", &mut emissive, working_space) {
+        material.emissive = emissive.to_linear();
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Emissive Map:");
+        ui.label(image_handle_label(&material.emissive_texture));
+        if let Some(handle) = selected_texture {
+            if ui.button("Use Selected").clicked() {
+                material.emissive_texture = Some(handle.clone());
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Roughness:");
+        ui.add(egui::Slider::new(&mut material.perceptual_roughness, 0.0..=1.0));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Metallic:");
+        ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Metal/Rough Map:");
+        ui.label(image_handle_label(&material.metallic_roughness_texture));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Normal Map:");
+        ui.label(image_handle_label(&material.normal_map_texture));
+        if let Some(handle) = selected_texture {
+            if ui.button("Use Selected").clicked() {
+                material.normal_map_texture = Some(handle.clone());
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("AO Map:");
+        ui.label(image_handle_label(&material.occlusion_texture));
+        if let Some(handle) = selected_texture {
+            if ui.button("Use Selected").clicked() {
+                material.occlusion_texture = Some(handle.clone());
+            }
+        }
+    });
+}
+
+/// Save the material to a `.wmat` file or swap in a dropped one
+fn draw_material_file_fields(
+    ui: &mut egui::Ui,
+    entity: Option<Entity>,
+    handle: &Handle<StandardMaterial>,
+    save_name: &mut String,
+    material_edit_queue: &mut Vec<MaterialEditEvent>,
+) {
+    use crate::rendering::materials::MATERIAL_EXTENSION;
+
+    let mut push = |kind| {
+        material_edit_queue.push(MaterialEditEvent {
+            entity,
+            material: handle.clone(),
+            kind,
+        })
+    };
+    let file_path = handle
+        .path()
+        .map(|path| path.to_string())
+        .filter(|path| path.ends_with(&format!(".{MATERIAL_EXTENSION}")));
+    if let Some(path) = &file_path {
+        if ui.button("Save Material").on_hover_text(format!("Overwrite {path}")).clicked() {
+            push(MaterialEditKind::Save(path.clone()));
+        }
+    }
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(save_name).hint_text("materials/name").desired_width(140.0));
+        let name = save_name.trim().trim_end_matches(&format!(".{MATERIAL_EXTENSION}")).to_string();
+        let label = if file_path.is_some() { "Save As" } else { "Save Material" };
+        if ui.add_enabled(!name.is_empty(), egui::Button::new(label)).clicked() {
+            push(MaterialEditKind::Save(format!("{name}.{MATERIAL_EXTENSION}")));
+        }
+    });
+    // Only a material on an entity can be swapped for another
+    if entity.is_none() {
+        return;
+    }
+    let (_, dropped) = ui.dnd_drop_zone(egui::Frame::group(ui.style()), |ui| {
+        ui.label("Drop a .wmat or model material here");
+    });
+    if let Some(DragPayload::Asset(path)) = dropped.as_deref() {
+        let model_material = split_label(path).1.is_some_and(|label| label.starts_with("Material"));
+        if path.ends_with(&format!(".{MATERIAL_EXTENSION}")) || model_material {
+            push(MaterialEditKind::Apply(path.clone()));
+        }
+    }
+}
+
+fn material_handle_label(handle: &Handle<StandardMaterial>) -> String {
+    handle
+        .path()
+        .map(|path| path.to_string())
+        .unwrap_or_else(|| "Runtime".to_string())
+}
+
+fn asset_kind_label(kind: AssetKind) -> &'static str {
+    match kind {
+        AssetKind::Image => "[Image]",
+        AssetKind::Model => "[Model]",
+        AssetKind::Audio => "[Audio]",
+        AssetKind::Script => "[Script]",
+        AssetKind::Material => "[Material]",
+        AssetKind::Other => "[Other]",
+    }
+}
+
+fn is_image_path(path: &str) -> bool {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "tga" | "hdr")
+}
+
+/// Draw the console panel
+pub fn draw_console_panel(
+    ui: &mut egui::Ui,
+    _editor_settings: &mut EditorSettings,
+    editor_output: &mut EditorOutput,
+) {
+    ui.vertical(|ui| {
+        ui.heading("Output");
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                editor_output.clear();
+            }
+            ui.checkbox(&mut editor_output.collapse_repeated, "Collapse")
+                .on_hover_text("Show a message repeated in a row once, with a counter");
+            ui.label(format!("{} messages", editor_output.entries().len()));
+        });
+
+        ui.separator();
+
+        // Output area; only the visible rows are laid out
+        let entries = editor_output.entries();
+        if entries.is_empty() {
+            ui.label("No output yet");
+            return;
+        }
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                for entry in entries.range(rows) {
+                    draw_output_entry(ui, entry);
+                }
+            });
+    });
+}
+
+fn draw_output_entry(ui: &mut egui::Ui, entry: &OutputEntry) {
+    use bevy::log::Level;
+
+    let color = match entry.level {
+        Level::ERROR => egui::Color32::from_rgb(230, 90, 90),
+        Level::WARN => egui::Color32::from_rgb(230, 180, 80),
+        Level::INFO => ui.visuals().text_color(),
+        Level::DEBUG | Level::TRACE => egui::Color32::from_rgb(140, 140, 140),
+    };
+    let seconds = entry.time.as_secs();
+    let first_line = entry.message.lines().next().unwrap_or("");
+    ui.horizontal(|ui| {
+        ui.weak(format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60));
+        ui.colored_label(color, entry.level.as_str());
+        if entry.count > 1 {
+            ui.label(egui::RichText::new(format!("x{}", entry.count)).strong());
+        }
+        let label = ui.add(egui::Label::new(egui::RichText::new(first_line).color(color)).truncate());
+        if first_line.len() < entry.message.len() {
+            label.on_hover_text(&entry.message);
+        }
+    });
+}
+
+/// Draw the profiler panel
+pub fn draw_profiler_panel(
+    ui: &mut egui::Ui,
+    _editor_state: &mut EditorState,
+    _editor_settings: &mut EditorSettings,
+    diagnostics: &bevy::diagnostic::DiagnosticsStore,
+    entity_pools: &crate::core::pool::EntityPools,
+    instancing: &crate::rendering::instancing::InstancingStats,
+) {
+    ui.vertical(|ui| {
+        ui.heading("Profiler");
+
+        ui.separator();
+
+        let fps = diagnostics
+            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|diag| diag.smoothed());
+        let frame_time = diagnostics
+            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|diag| diag.smoothed());
+
+        // Performance metrics
+        ui.collapsing("Performance", |ui| {
+            ui.label(format!(
+                "FPS: {}",
+                fps.map(|v| format!("{v:.1}")).unwrap_or_else(|| "--".into())
+            ));
+            ui.label(format!(
+                "Frame Time: {} ms",
+                frame_time
+                    .map(|v| format!("{:.2}", v))
+                    .unwrap_or_else(|| "--".into())
+            ));
+            ui.label("CPU Usage: 45%");
+            ui.label("Memory: 128 MB");
+            ui.label(format!("Mesh Draws: {} for {} meshes", instancing.batches, instancing.meshes));
+        });
+
+        ui.collapsing("Instancing", |ui| {
+            ui.label(format!("Instanced: {} meshes", instancing.instanced));
+            if instancing.unbatched > 0 {
+                ui.label(format!("Not batched: {} meshes", instancing.unbatched));
+            }
+            if instancing.top.is_empty() {
+                ui.label("No visible meshes");
+                return;
+            }
+            egui::Grid::new("instancing_stats").striped(true).show(ui, |ui| {
+                ui.strong("Mesh");
+                ui.strong("Instances");
+                ui.end_row();
+                for batch in &instancing.top {
+                    ui.label(&batch.label);
+                    ui.label(batch.instances.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+
+        // System timings
+        ui.collapsing("Systems", |ui| {
+            ui.label("Rendering: 8.3 ms");
+            ui.label("Physics: 0.0 ms (disabled)");
+            ui.label("UI: 2.1 ms");
+            ui.label("Audio: 0.0 ms");
+        });
+
+        // Memory usage
+        ui.collapsing("Memory", |ui| {
+            ui.label("Textures: 64 MB");
+            ui.label("Meshes: 8 MB");
+            ui.label("Audio: 0 MB");
+            ui.label("Scripts: 0 MB");
+        });
+
+        ui.collapsing("Entity Pools", |ui| {
+            if entity_pools.pools.is_empty() {
+                ui.label("No pools registered");
+                return;
+            }
+            let mut names: Vec<&String> = entity_pools.pools.keys().collect();
+            names.sort();
+            egui::Grid::new("entity_pool_stats").striped(true).show(ui, |ui| {
+                ui.strong("Pool");
+                ui.strong("Active");
+                ui.strong("Free");
+                ui.strong("Peak");
+                ui.strong("Misses");
+                ui.end_row();
+                for name in names {
+                    let pool = &entity_pools.pools[name];
+                    ui.label(name.as_str());
+                    ui.label(pool.stats.active.to_string());
+                    ui.label(pool.free.len().to_string());
+                    ui.label(pool.stats.peak_active.to_string());
+                    ui.label(pool.stats.misses.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+/// Draw the tweens panel
+pub fn draw_tweens_panel(
+    ui: &mut egui::Ui,
+    tweens: &mut crate::core::tween::Tweens,
+    hierarchy: &HierarchySnapshot,
+) {
+    ui.vertical(|ui| {
+        ui.heading("Tweens");
+
+        ui.separator();
+
+        if tweens.active.is_empty() {
+            ui.label("No active tweens");
+            return;
+        }
+
+        let mut cancel = None;
+        egui::Grid::new("active_tweens")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Entity");
+                ui.strong("Property");
+                ui.strong("Easing");
+                ui.strong("Progress");
+                ui.end_row();
+
+                for active in tweens.active.iter() {
+                    let entity = active.tween.entity;
+                    ui.label(
+                        hierarchy
+                            .names
+                            .get(&entity)
+                            .cloned()
+                            .unwrap_or_else(|| format!("Entity {}", entity.index())),
+                    );
+                    ui.label(active.tween.property.label());
+                    ui.label(active.tween.easing.label());
+                    ui.add(
+                        egui::ProgressBar::new(active.progress())
+                            .desired_width(120.0)
+                            .text(format!("{:.2}s / {:.2}s", active.elapsed, active.tween.delay + active.tween.duration)),
+                    );
+                    if ui.small_button("Cancel").clicked() {
+                        cancel = Some(active.id);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(id) = cancel {
+            tweens.cancel(id);
+        }
+    });
+}
+
+/// Materials in the material library; drag one onto a hierarchy entry or
+/// apply it to the selection
+pub fn draw_material_library_panel(
+    ui: &mut egui::Ui,
+    editor_state: &mut EditorState,
+    library: &crate::rendering::materials::MaterialLibrary,
+    material_assets: &Assets<StandardMaterial>,
+    material_library_queue: &mut Vec<MaterialLibraryEvent>,
+) {
+    ui.vertical(|ui| {
+        ui.heading("Material Library");
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut editor_state.new_material_name)
+                    .hint_text("New material")
+                    .desired_width(140.0),
+            );
+            let name = editor_state.new_material_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Create")).clicked() {
+                material_library_queue.push(MaterialLibraryEvent::Create { name });
+                editor_state.new_material_name.clear();
+            }
+        });
+
+        ui.separator();
+
+        if library.materials.is_empty() {
+            ui.label("The library is empty");
+            return;
+        }
+
+        let selected = editor_state.selection.entities().to_vec();
+        egui::ScrollArea::vertical().id_source("material_library").show(ui, |ui| {
+            for (index, (name, handle)) in library.iter().enumerate() {
+                let material = material_assets.get(handle);
+                ui.horizontal(|ui| {
+                    let (swatch, _) = ui.allocate_exact_size(egui::vec2(18.0, 18.0), egui::Sense::hover());
+                    let fill = material.map_or(egui::Color32::DARK_GRAY, |material| color_to_egui(material.base_color));
+                    ui.painter().rect_filled(swatch, 3.0, fill);
+                    ui.painter().rect_stroke(swatch, 3.0, egui::Stroke::new(1.0, egui::Color32::from_gray(90)));
+
+                    let label = ui
+                        .add(egui::Label::new(name).sense(egui::Sense::click_and_drag()))
+                        .on_hover_text(match material {
+                            Some(material) => format!(
+                                "Roughness {:.2}, metallic {:.2}\nDrag onto an entity in the hierarchy",
+                                material.perceptual_roughness, material.metallic
+                            ),
+                            None => "Loading".to_string(),
+                        });
+                    label.dnd_set_drag_payload(DragPayload::Material(index));
+
+                    if ui
+                        .add_enabled(!selected.is_empty(), egui::Button::new("Apply to Selected").small())
+                        .clicked()
+                    {
+                        material_library_queue.push(MaterialLibraryEvent::Apply {
+                            index,
+                            entities: selected.clone(),
+                        });
+                    }
+                });
+            }
+        });
+    });
+}
+
+/// Quest state and game variables of the running game
+pub fn draw_quests_panel(
+    ui: &mut egui::Ui,
+    quest_log: &mut crate::core::quest::QuestLog,
+    variables: &mut crate::core::variables::GameVariables,
+    playing: bool,
+) {
+    use crate::core::quest::QuestStatus;
+
+    ui.vertical(|ui| {
+        ui.heading("Quests");
+
+        ui.separator();
+
+        if !playing {
+            ui.label(format!(
+                "{} quest definitions loaded. Enter play mode to follow quest progress.",
+                quest_log.definitions.len()
+            ));
+            return;
+        }
+
+        let mut start = None;
+        egui::ScrollArea::vertical().id_source("quest_list").show(ui, |ui| {
+            for definition in &quest_log.definitions {
+                let status = quest_log.status(&definition.id);
+                let color = match status {
+                    QuestStatus::Inactive => egui::Color32::from_rgb(140, 140, 140),
+                    QuestStatus::Active => egui::Color32::from_rgb(120, 180, 255),
+                    QuestStatus::Completed => egui::Color32::from_rgb(100, 200, 120),
+                    QuestStatus::Failed => egui::Color32::from_rgb(220, 90, 90),
+                };
+                ui.horizontal(|ui| {
+                    ui.strong(&definition.title);
+                    ui.colored_label(color, status.label());
+                    if status == QuestStatus::Inactive && ui.small_button("Start").clicked() {
+                        start = Some(definition.id.clone());
+                    }
+                });
+                if status != QuestStatus::Active {
+                    continue;
+                }
+                for objective in &definition.objectives {
+                    let done = quest_log.is_objective_complete(&definition.id, &objective.id);
+                    let mark = if done { "[x]" } else { "[ ]" };
+                    let optional = if objective.optional { " (optional)" } else { "" };
+                    ui.label(format!("  {mark} {}{optional}", objective.description))
+                        .on_hover_text(objective.condition.label());
+                }
+            }
+        });
+        if let Some(id) = start {
+            quest_log.start(id);
+        }
+
+        ui.separator();
+        ui.collapsing("Flags", |ui| {
+            let mut names: Vec<String> = variables.flags.keys().cloned().collect();
+            names.sort();
+            for name in names {
+                if let Some(value) = variables.flags.get_mut(&name) {
+                    ui.checkbox(value, name);
+                }
+            }
+        });
+        ui.collapsing("Values", |ui| {
+            let mut names: Vec<String> = variables.values.keys().cloned().collect();
+            names.sort();
+            for name in names {
+                if let Some(value) = variables.values.get_mut(&name) {
+                    ui.horizontal(|ui| {
+                        ui.label(&name);
+                        ui.add(egui::DragValue::new(value).speed(0.1));
+                    });
+                }
+            }
+        });
+    });
+}
+
+/// Draw the behavior tree editor panel
+pub fn draw_behavior_tree_panel(ui: &mut egui::Ui, state: &mut BehaviorTreeEditorState) {
+    use crate::core::ai::{BehaviorNode, BehaviorTree};
+
+    ui.vertical(|ui| {
+        ui.heading("Behavior Tree");
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.add(egui::TextEdit::singleline(&mut state.path).desired_width(240.0));
+            if ui.button("New").clicked() {
+                state.tree = BehaviorTree::default();
+                state.selected = None;
+                state.status.clear();
+            }
+            if ui.button("Load").clicked() {
+                match BehaviorTree::load(&state.path) {
+                    Ok(tree) => {
+                        state.tree = tree;
+                        state.selected = None;
+                        state.status = format!("Loaded {}", state.path);
+                    }
+                    Err(err) => state.status = format!("Load failed: {err}"),
+                }
+            }
+            if ui.button("Save").clicked() {
+                state.status = match state.tree.save(&state.path) {
+                    Ok(()) => format!("Saved {}", state.path),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut state.tree.name);
+            if !state.status.is_empty() {
+                ui.label(egui::RichText::new(&state.status).weak());
+            }
+        });
+
+        ui.separator();
+
+        ui.columns(2, |columns| {
+            egui::ScrollArea::both()
+                .id_source("behavior_tree_canvas")
+                .show(&mut columns[0], |ui| {
+                    draw_behavior_tree_canvas(ui, &state.tree.root, &mut state.selected);
+                });
+
+            let ui = &mut columns[1];
+            let Some(path) = state.selected.clone() else {
+                ui.label("Select a node to edit it");
+                return;
+            };
+            let Some(node) = behavior_node_at_mut(&mut state.tree.root, &path) else {
+                state.selected = None;
+                return;
+            };
+
+            ui.strong(node.label());
+            match node {
+                BehaviorNode::Condition(name) | BehaviorNode::Action(name) => {
+                    ui.horizontal(|ui| {
+                        ui.label("Leaf:");
+                        ui.text_edit_singleline(name);
+                    });
+                }
+                BehaviorNode::Wait(seconds) => {
+                    ui.horizontal(|ui| {
+                        ui.label("Seconds:");
+                        ui.add(egui::DragValue::new(seconds).speed(0.05).range(0.0..=600.0));
+                    });
+                }
+                _ => {}
+            }
+
+            ui.separator();
+            let can_have_children = node.children_mut().is_some();
+            if can_have_children {
+                ui.menu_button("Add Child", |ui| {
+                    let new_node = behavior_node_palette(ui);
+                    if let (Some(new_node), Some(children)) = (new_node, node.children_mut()) {
+                        children.push(new_node);
+                        ui.close_menu();
+                    }
+                });
+            }
+            ui.menu_button("Replace With", |ui| {
+                if let Some(mut new_node) = behavior_node_palette(ui) {
+                    // Keep the existing children when swapping one composite for another.
+                    if let (Some(old), Some(new)) = (node.children_mut().map(std::mem::take), new_node.children_mut()) {
+                        *new = old;
+                    }
+                    *node = new_node;
+                    ui.close_menu();
+                }
+            });
+            if ui.button("Wrap In Inverter").clicked() {
+                let inner = std::mem::replace(node, BehaviorNode::Selector(Vec::new()));
+                *node = BehaviorNode::Inverter(Box::new(inner));
+            }
+
+            if let Some((last, parent_path)) = path.split_last() {
+                let parent = behavior_node_at_mut(&mut state.tree.root, parent_path);
+                if let Some(siblings) = parent.and_then(BehaviorNode::children_mut) {
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(*last > 0, egui::Button::new("Move Up")).clicked() {
+                            siblings.swap(*last, *last - 1);
+                            let mut moved = parent_path.to_vec();
+                            moved.push(*last - 1);
+                            state.selected = Some(moved);
+                        }
+                        if ui
+                            .add_enabled(*last + 1 < siblings.len(), egui::Button::new("Move Down"))
+                            .clicked()
+                        {
+                            siblings.swap(*last, *last + 1);
+                            let mut moved = parent_path.to_vec();
+                            moved.push(*last + 1);
+                            state.selected = Some(moved);
+                        }
+                        if ui.button("Delete").clicked() {
+                            siblings.remove(*last);
+                            state.selected = None;
+                        }
+                    });
+                }
+            }
+        });
+    });
 }
 
-fn asset_kind_label(kind: AssetKind) -> &'static str {
-    match kind {
-        AssetKind::Image => "[Image]",
-        AssetKind::Model => "[Model]",
-        AssetKind::Audio => "[Audio]",
-        AssetKind::Script => "[Script]",
-        AssetKind::Material => "[Material]",
-        AssetKind::Other => "[Other]",
+fn behavior_node_palette(ui: &mut egui::Ui) -> Option<crate::core::ai::BehaviorNode> {
+    use crate::core::ai::BehaviorNode;
+
+    if ui.button("Selector").clicked() {
+        return Some(BehaviorNode::Selector(Vec::new()));
+    }
+    if ui.button("Sequence").clicked() {
+        return Some(BehaviorNode::Sequence(Vec::new()));
+    }
+    if ui.button("Condition").clicked() {
+        return Some(BehaviorNode::Condition("HasTarget".to_string()));
     }
+    if ui.button("Action").clicked() {
+        return Some(BehaviorNode::Action("Idle".to_string()));
+    }
+    if ui.button("Wait").clicked() {
+        return Some(BehaviorNode::Wait(1.0));
+    }
+    None
 }
 
-fn is_image_path(path: &str) -> bool {
-    let ext = std::path::Path::new(path)
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_ascii_lowercase();
-    matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "tga" | "hdr")
+fn behavior_node_at_mut<'a>(
+    node: &'a mut crate::core::ai::BehaviorNode,
+    path: &[usize],
+) -> Option<&'a mut crate::core::ai::BehaviorNode> {
+    use crate::core::ai::BehaviorNode;
+
+    let Some((first, rest)) = path.split_first() else {
+        return Some(node);
+    };
+    let child = match node {
+        BehaviorNode::Selector(children) | BehaviorNode::Sequence(children) => children.get_mut(*first)?,
+        BehaviorNode::Inverter(child) if *first == 0 => child.as_mut(),
+        _ => return None,
+    };
+    behavior_node_at_mut(child, rest)
 }
 
-/// Draw the console panel
-pub fn draw_console_panel(
+/// Lay the tree out top-down, one column per leaf, and draw it as a node graph
+fn draw_behavior_tree_canvas(
     ui: &mut egui::Ui,
-    _editor_settings: &mut EditorSettings,
-    editor_output: &mut EditorOutput,
+    root: &crate::core::ai::BehaviorNode,
+    selected: &mut Option<Vec<usize>>,
 ) {
-    ui.vertical(|ui| {
-        ui.heading("Output");
+    use crate::core::ai::BehaviorNode;
 
-        ui.separator();
+    const NODE_SIZE: egui::Vec2 = egui::vec2(120.0, 28.0);
+    const SPACING: egui::Vec2 = egui::vec2(16.0, 36.0);
 
-        // Output area
-        egui::ScrollArea::vertical().show(ui, |ui| {
-            if editor_output.lines.is_empty() {
-                ui.label("No output yet");
-            } else {
-                for line in &editor_output.lines {
-                    ui.label(line);
+    fn leaf_count(node: &BehaviorNode) -> usize {
+        node.children().iter().map(leaf_count).sum::<usize>().max(1)
+    }
+    fn depth(node: &BehaviorNode) -> usize {
+        1 + node.children().iter().map(depth).max().unwrap_or(0)
+    }
+
+    let columns = leaf_count(root) as f32;
+    let rows = depth(root) as f32;
+    let desired = egui::vec2(
+        columns * (NODE_SIZE.x + SPACING.x),
+        rows * (NODE_SIZE.y + SPACING.y),
+    );
+    let (canvas, _) = ui.allocate_exact_size(desired.max(ui.available_size()), egui::Sense::hover());
+    let painter = ui.painter_at(canvas);
+
+    let mut nodes: Vec<(Vec<usize>, egui::Rect, String)> = Vec::new();
+    let mut edges: Vec<(egui::Pos2, egui::Pos2)> = Vec::new();
+    let mut stack: Vec<(&BehaviorNode, Vec<usize>, f32, usize)> = vec![(root, Vec::new(), 0.0, 0)];
+    while let Some((node, path, column, row)) = stack.pop() {
+        let width = leaf_count(node) as f32;
+        let center_x = canvas.left() + (column + width * 0.5) * (NODE_SIZE.x + SPACING.x);
+        let top = canvas.top() + row as f32 * (NODE_SIZE.y + SPACING.y) + SPACING.y * 0.5;
+        let rect = egui::Rect::from_center_size(egui::pos2(center_x, top + NODE_SIZE.y * 0.5), NODE_SIZE);
+
+        let mut child_column = column;
+        for (index, child) in node.children().iter().enumerate() {
+            let child_width = leaf_count(child) as f32;
+            let child_x = canvas.left() + (child_column + child_width * 0.5) * (NODE_SIZE.x + SPACING.x);
+            let child_top = top + NODE_SIZE.y + SPACING.y;
+            edges.push((rect.center_bottom(), egui::pos2(child_x, child_top)));
+            let mut child_path = path.clone();
+            child_path.push(index);
+            stack.push((child, child_path, child_column, row + 1));
+            child_column += child_width;
+        }
+        nodes.push((path, rect, node.label()));
+    }
+
+    let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(120, 120, 120));
+    for (from, to) in edges {
+        painter.line_segment([from, to], stroke);
+    }
+    for (path, rect, label) in nodes {
+        let response = ui.interact(rect, ui.id().with(("bt_node", &path)), egui::Sense::click());
+        let is_selected = selected.as_ref() == Some(&path);
+        let fill = if is_selected {
+            egui::Color32::from_rgb(60, 90, 140)
+        } else if response.hovered() {
+            egui::Color32::from_rgb(70, 70, 70)
+        } else {
+            egui::Color32::from_rgb(50, 50, 50)
+        };
+        painter.rect_filled(rect, 4.0, fill);
+        painter.rect_stroke(rect, 4.0, stroke);
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            label,
+            egui::TextStyle::Body.resolve(ui.style()),
+            egui::Color32::from_rgb(220, 220, 220),
+        );
+        if response.clicked() {
+            *selected = Some(path);
+        }
+    }
+}
+
+pub fn draw_particle_editor_panel(
+    ui: &mut egui::Ui,
+    state: &mut ParticleEditorState,
+    entity: Option<Entity>,
+    mut emitter: Option<&mut crate::rendering::particles::ParticleEmitter>,
+    working_space: WorkingColorSpace,
+    particle_emitter_edit_queue: &mut Vec<ParticleEmitterEditEvent>,
+) {
+    use crate::rendering::particles::ParticleEffect;
+
+    ui.vertical(|ui| {
+        ui.heading("Particle Editor");
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.add(egui::TextEdit::singleline(&mut state.path).desired_width(240.0));
+            if ui.add_enabled(entity.is_some(), egui::Button::new("Load")).clicked() {
+                match (ParticleEffect::load(&state.path), entity) {
+                    (Ok(effect), Some(entity)) => {
+                        particle_emitter_edit_queue.push(ParticleEmitterEditEvent {
+                            entity,
+                            kind: ParticleEmitterEditKind::Set(effect.0),
+                        });
+                        state.status = format!("Loaded {}", state.path);
+                    }
+                    (Err(err), _) => state.status = format!("Load failed: {err}"),
+                    (Ok(_), None) => {}
+                }
+            }
+            if ui.add_enabled(emitter.is_some(), egui::Button::new("Save")).clicked() {
+                if let Some(emitter) = emitter.as_deref() {
+                    state.status = match ParticleEffect(emitter.clone()).save(&state.path) {
+                        Ok(()) => format!("Saved {}", state.path),
+                        Err(err) => format!("Save failed: {err}"),
+                    };
                 }
             }
         });
+        if !state.status.is_empty() {
+            ui.label(egui::RichText::new(&state.status).weak());
+        }
 
         ui.separator();
 
-        // Output-only console (no input)
+        let Some(entity) = entity else {
+            ui.label("Select an entity to edit its particles");
+            return;
+        };
+        egui::ScrollArea::vertical().id_source("particle_editor").show(ui, |ui| {
+            // Edits go straight to the selected emitter, so the viewport
+            // shows them as they are made
+            draw_particle_emitter_fields(
+                ui,
+                entity,
+                emitter.as_deref_mut(),
+                working_space,
+                particle_emitter_edit_queue,
+            );
+            let Some(emitter) = emitter else {
+                return;
+            };
+
+            ui.separator();
+            ui.strong("Size over Life");
+            draw_size_curve_editor(ui, emitter);
+
+            ui.separator();
+            ui.strong("Color over Life");
+            draw_color_gradient_editor(ui, emitter);
+        });
     });
 }
 
-/// Draw the profiler panel
-pub fn draw_profiler_panel(
-    ui: &mut egui::Ui,
-    _editor_state: &mut EditorState,
-    _editor_settings: &mut EditorSettings,
-    diagnostics: &bevy::diagnostic::DiagnosticsStore,
-) {
+fn draw_size_curve_editor(ui: &mut egui::Ui, emitter: &mut crate::rendering::particles::ParticleEmitter) {
+    use crate::rendering::particles::SizeKey;
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 64.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let samples = 48;
+    let sizes: Vec<f32> = (0..=samples).map(|i| emitter.size_at(i as f32 / samples as f32)).collect();
+    let largest = sizes.iter().copied().fold(0.0, f32::max).max(0.001);
+    let points: Vec<egui::Pos2> = sizes
+        .iter()
+        .enumerate()
+        .map(|(i, size)| {
+            let x = rect.left() + rect.width() * i as f32 / samples as f32;
+            let y = rect.bottom() - (rect.height() - 8.0) * size.max(0.0) / largest - 4.0;
+            egui::pos2(x, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, ui.visuals().selection.bg_fill)));
+    painter.text(
+        rect.left_top() + egui::vec2(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{largest:.2}"),
+        egui::FontId::monospace(10.0),
+        ui.visuals().weak_text_color(),
+    );
+
+    if emitter.size_curve.is_empty() {
+        if ui.button("Use Curve").on_hover_text("Replace the start and end size with a curve").clicked() {
+            emitter.size_curve = vec![
+                SizeKey { time: 0.0, size: emitter.start_size },
+                SizeKey { time: 1.0, size: emitter.end_size },
+            ];
+        }
+        return;
+    }
+
+    let mut remove = None;
+    let mut reorder = false;
+    for (index, key) in emitter.size_curve.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let time = ui.add(egui::DragValue::new(&mut key.time).speed(0.005).range(0.0..=1.0).prefix("Life: "));
+            reorder |= time.drag_stopped() || time.lost_focus();
+            ui.add(egui::DragValue::new(&mut key.size).speed(0.01).range(0.0..=100.0).prefix("Size: "));
+            if ui.small_button("x").accessible_name("Remove key").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        emitter.size_curve.remove(index);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add Key").clicked() {
+            let time = widest_gap(emitter.size_curve.iter().map(|key| key.time));
+            emitter.size_curve.push(SizeKey { time, size: emitter.size_at(time) });
+            reorder = true;
+        }
+        if ui.button("Clear Curve").clicked() {
+            emitter.size_curve.clear();
+        }
+    });
+    if reorder {
+        emitter.sort_keys();
+    }
+}
+
+fn draw_color_gradient_editor(ui: &mut egui::Ui, emitter: &mut crate::rendering::particles::ParticleEmitter) {
+    use crate::rendering::particles::{sample_gradient, ColorStop};
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 20.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let gradient = emitter.linear_gradient();
+    let segments = 48;
+    for i in 0..segments {
+        let t = (i as f32 + 0.5) / segments as f32;
+        let left = rect.left() + rect.width() * i as f32 / segments as f32;
+        let right = rect.left() + rect.width() * (i + 1) as f32 / segments as f32;
+        painter.rect_filled(
+            egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(right + 0.5, rect.bottom())),
+            0.0,
+            color_to_egui(Color::from(sample_gradient(&gradient, t))),
+        );
+    }
+    painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+    if emitter.color_gradient.is_empty() {
+        if ui.button("Use Gradient").on_hover_text("Replace the start and end color with a gradient").clicked() {
+            emitter.color_gradient = vec![
+                ColorStop { time: 0.0, color: emitter.start_color },
+                ColorStop { time: 1.0, color: emitter.end_color },
+            ];
+        }
+        return;
+    }
+
+    let mut remove = None;
+    let mut reorder = false;
+    for (index, stop) in emitter.color_gradient.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let time = ui.add(egui::DragValue::new(&mut stop.time).speed(0.005).range(0.0..=1.0).prefix("Life: "));
+            reorder |= time.drag_stopped() || time.lost_focus();
+            let mut picked = color_to_egui(stop.color);
+            if ui.color_edit_button_srgba(&mut picked).changed() {
+                stop.color = egui_to_color(picked);
+            }
+            if ui.small_button("x").accessible_name("Remove stop").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        emitter.color_gradient.remove(index);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add Stop").clicked() {
+            let time = widest_gap(emitter.color_gradient.iter().map(|stop| stop.time));
+            let color = Color::from(sample_gradient(&emitter.linear_gradient(), time));
+            emitter.color_gradient.push(ColorStop { time, color });
+            reorder = true;
+        }
+        if ui.button("Clear Gradient").clicked() {
+            emitter.color_gradient.clear();
+        }
+    });
+    if reorder {
+        emitter.sort_keys();
+    }
+}
+
+/// The middle of the widest gap between sorted key times, for a new key
+fn widest_gap(times: impl Iterator<Item = f32>) -> f32 {
+    let mut previous = 0.0;
+    let mut best = (0.0, 0.5);
+    for time in times.chain(std::iter::once(1.0)) {
+        if time - previous > best.0 {
+            best = (time - previous, (time + previous) * 0.5);
+        }
+        previous = time;
+    }
+    best.1
+}
+
+pub fn draw_dialogue_panel(ui: &mut egui::Ui, state: &mut DialogueEditorState) {
+    use crate::core::dialogue::{DialogueChoice, DialogueGraph, DialogueNode};
+
     ui.vertical(|ui| {
-        ui.heading("Profiler");
+        ui.heading("Dialogue");
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.add(egui::TextEdit::singleline(&mut state.path).desired_width(240.0));
+            if ui.button("New").clicked() {
+                state.graph = DialogueGraph::default();
+                state.selected = None;
+                state.status.clear();
+            }
+            if ui.button("Load").clicked() {
+                match DialogueGraph::load(&state.path) {
+                    Ok(graph) => {
+                        state.graph = graph;
+                        state.selected = None;
+                        state.status = format!("Loaded {}", state.path);
+                    }
+                    Err(err) => state.status = format!("Load failed: {err}"),
+                }
+            }
+            if ui.button("Save").clicked() {
+                state.status = match state.graph.save(&state.path) {
+                    Ok(()) => format!("Saved {}", state.path),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut state.graph.name);
+            if !state.status.is_empty() {
+                ui.label(egui::RichText::new(&state.status).weak());
+            }
+        });
+        let missing = state.graph.missing_targets();
+        if !missing.is_empty() {
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 160, 60),
+                format!("Missing nodes: {}", missing.join(", ")),
+            );
+        }
 
         ui.separator();
 
-        let fps = diagnostics
-            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FPS)
-            .and_then(|diag| diag.smoothed());
-        let frame_time = diagnostics
-            .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FRAME_TIME)
-            .and_then(|diag| diag.smoothed());
+        ui.columns(2, |columns| {
+            egui::ScrollArea::both()
+                .id_source("dialogue_canvas")
+                .show(&mut columns[0], |ui| {
+                    draw_dialogue_canvas(ui, &mut state.graph, &mut state.selected);
+                });
 
-        // Performance metrics
-        ui.collapsing("Performance", |ui| {
-            ui.label(format!(
-                "FPS: {}",
-                fps.map(|v| format!("{v:.1}")).unwrap_or_else(|| "--".into())
-            ));
-            ui.label(format!(
-                "Frame Time: {} ms",
-                frame_time
-                    .map(|v| format!("{:.2}", v))
-                    .unwrap_or_else(|| "--".into())
-            ));
-            ui.label("CPU Usage: 45%");
-            ui.label("Memory: 128 MB");
-            ui.label("Draw Calls: 150");
+            let ui = &mut columns[1];
+            if ui.button("Add Node").clicked() {
+                let position = state
+                    .selected
+                    .and_then(|index| state.graph.nodes.get(index))
+                    .map(|node| (node.position.0 + 200.0, node.position.1))
+                    .unwrap_or((20.0, 20.0 + 64.0 * state.graph.nodes.len() as f32));
+                let mut node = DialogueNode::new(state.graph.unique_id("line"));
+                node.position = position;
+                state.graph.nodes.push(node);
+                state.selected = Some(state.graph.nodes.len() - 1);
+            }
+            ui.separator();
+
+            let Some(index) = state.selected.filter(|index| *index < state.graph.nodes.len()) else {
+                ui.label("Select a node to edit it");
+                return;
+            };
+            let ids: Vec<String> = state.graph.nodes.iter().map(|node| node.id.clone()).collect();
+            let is_start = state.graph.start == ids[index];
+            let node = &mut state.graph.nodes[index];
+            let mut rename = None;
+
+            ui.horizontal(|ui| {
+                ui.label("Id:");
+                let mut id = node.id.clone();
+                let response = ui.text_edit_singleline(&mut id);
+                if response.changed() && !id.is_empty() && !ids.contains(&id) {
+                    rename = Some((node.id.clone(), id));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Speaker:");
+                ui.text_edit_singleline(&mut node.speaker);
+            });
+            ui.label("Text:");
+            ui.add(egui::TextEdit::multiline(&mut node.text).desired_rows(3).desired_width(f32::INFINITY));
+            ui.horizontal(|ui| {
+                ui.label("Sets Flags:");
+                let mut flags = node.set_flags.join(", ");
+                if ui.text_edit_singleline(&mut flags).changed() {
+                    node.set_flags = flags
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|flag| !flag.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                }
+            });
+            if node.choices.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label("Next:");
+                    dialogue_target_combo(ui, "dialogue_next", &mut node.next, &ids);
+                });
+            }
+
+            ui.separator();
+            ui.strong("Choices");
+            let mut remove = None;
+            for (choice_index, choice) in node.choices.iter_mut().enumerate() {
+                ui.push_id(choice_index, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", choice_index + 1));
+                        ui.text_edit_singleline(&mut choice.text);
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(choice_index);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Leads To:");
+                        dialogue_target_combo(ui, "choice_next", &mut choice.next, &ids);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Shown If:");
+                        variable_condition_field(ui, &mut choice.condition);
+                    });
+                });
+            }
+            if let Some(choice_index) = remove {
+                node.choices.remove(choice_index);
+            }
+            if ui.button("Add Choice").clicked() {
+                node.choices.push(DialogueChoice {
+                    text: "...".to_string(),
+                    next: None,
+                    condition: None,
+                });
+            }
+
+            ui.separator();
+            let mut delete = false;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!is_start, egui::Button::new("Set As Start")).clicked() {
+                    state.graph.start = ids[index].clone();
+                }
+                delete = ui.button("Delete Node").clicked();
+            });
+
+            if let Some((old, new)) = rename {
+                rename_dialogue_node(&mut state.graph, &old, &new);
+            }
+            if delete {
+                state.graph.nodes.remove(index);
+                state.selected = None;
+            }
         });
+    });
+}
 
-        // System timings
-        ui.collapsing("Systems", |ui| {
-            ui.label("Rendering: 8.3 ms");
-            ui.label("Physics: 0.0 ms (disabled)");
-            ui.label("UI: 2.1 ms");
-            ui.label("Audio: 0.0 ms");
+/// Rename a node and every reference to it
+fn rename_dialogue_node(graph: &mut crate::core::dialogue::DialogueGraph, old: &str, new: &str) {
+    let retarget = |target: &mut Option<String>| {
+        if target.as_deref() == Some(old) {
+            *target = Some(new.to_string());
+        }
+    };
+    if graph.start == old {
+        graph.start = new.to_string();
+    }
+    for node in &mut graph.nodes {
+        if node.id == old {
+            node.id = new.to_string();
+        }
+        retarget(&mut node.next);
+        for choice in &mut node.choices {
+            retarget(&mut choice.next);
+        }
+    }
+}
+
+fn dialogue_target_combo(ui: &mut egui::Ui, id_source: &str, target: &mut Option<String>, ids: &[String]) {
+    egui::ComboBox::from_id_source(ui.id().with(id_source))
+        .selected_text(target.as_deref().unwrap_or("(end)"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(target, None, "(end)");
+            for id in ids {
+                ui.selectable_value(target, Some(id.clone()), id);
+            }
         });
+}
 
-        // Memory usage
-        ui.collapsing("Memory", |ui| {
-            ui.label("Textures: 64 MB");
-            ui.label("Meshes: 8 MB");
-            ui.label("Audio: 0 MB");
-            ui.label("Scripts: 0 MB");
+fn variable_condition_field(ui: &mut egui::Ui, condition: &mut Option<crate::core::variables::VariableCondition>) {
+    use crate::core::variables::VariableCondition;
+
+    const KINDS: [&str; 5] = ["Always", "Flag", "Not Flag", "At Least", "Below"];
+    let current = match condition {
+        None => 0,
+        Some(VariableCondition::Flag(_)) => 1,
+        Some(VariableCondition::NotFlag(_)) => 2,
+        Some(VariableCondition::AtLeast(..)) => 3,
+        Some(VariableCondition::Below(..)) => 4,
+    };
+    let mut kind = current;
+    egui::ComboBox::from_id_source(ui.id().with("condition_kind"))
+        .selected_text(KINDS[current])
+        .show_ui(ui, |ui| {
+            for (index, label) in KINDS.iter().enumerate() {
+                ui.selectable_value(&mut kind, index, *label);
+            }
         });
-    });
+    if kind != current {
+        let name = condition.as_ref().map(|condition| condition.variable().to_string()).unwrap_or_default();
+        *condition = match kind {
+            1 => Some(VariableCondition::Flag(name)),
+            2 => Some(VariableCondition::NotFlag(name)),
+            3 => Some(VariableCondition::AtLeast(name, 1.0)),
+            4 => Some(VariableCondition::Below(name, 1.0)),
+            _ => None,
+        };
+    }
+    match condition {
+        Some(VariableCondition::Flag(name)) | Some(VariableCondition::NotFlag(name)) => {
+            ui.add(egui::TextEdit::singleline(name).desired_width(100.0));
+        }
+        Some(VariableCondition::AtLeast(name, value)) | Some(VariableCondition::Below(name, value)) => {
+            ui.add(egui::TextEdit::singleline(name).desired_width(100.0));
+            ui.add(egui::DragValue::new(value).speed(0.1));
+        }
+        None => {}
+    }
 }
 
+/// Draw dialogue nodes at their canvas positions; drag nodes to move them
+fn draw_dialogue_canvas(
+    ui: &mut egui::Ui,
+    graph: &mut crate::core::dialogue::DialogueGraph,
+    selected: &mut Option<usize>,
+) {
+    const NODE_SIZE: egui::Vec2 = egui::vec2(160.0, 44.0);
+
+    let extent = graph
+        .nodes
+        .iter()
+        .fold(egui::Vec2::ZERO, |extent, node| extent.max(egui::vec2(node.position.0, node.position.1)))
+        + NODE_SIZE
+        + egui::vec2(40.0, 40.0);
+    let (canvas, _) = ui.allocate_exact_size(extent.max(ui.available_size()), egui::Sense::hover());
+    let painter = ui.painter_at(canvas);
+    let node_rect = |position: (f32, f32)| {
+        egui::Rect::from_min_size(canvas.min + egui::vec2(position.0, position.1), NODE_SIZE)
+    };
+
+    let edge_color = egui::Color32::from_rgb(120, 120, 120);
+    for node in &graph.nodes {
+        let from = node_rect(node.position).right_center();
+        for target in node.targets() {
+            if let Some(target) = graph.node(target) {
+                let to = node_rect(target.position).left_center();
+                painter.line_segment([from, to], egui::Stroke::new(1.5, edge_color));
+                painter.circle_filled(to, 3.5, edge_color);
+            }
+        }
+    }
+
+    let start = graph.start.clone();
+    for (index, node) in graph.nodes.iter_mut().enumerate() {
+        let rect = node_rect(node.position);
+        let response = ui.interact(rect, ui.id().with(("dialogue_node", index)), egui::Sense::click_and_drag());
+        if response.dragged() {
+            let delta = response.drag_delta();
+            node.position.0 = (node.position.0 + delta.x).max(0.0);
+            node.position.1 = (node.position.1 + delta.y).max(0.0);
+        }
+        if response.clicked() || response.drag_started() {
+            *selected = Some(index);
+        }
+
+        let fill = if *selected == Some(index) {
+            egui::Color32::from_rgb(60, 90, 140)
+        } else if response.hovered() {
+            egui::Color32::from_rgb(70, 70, 70)
+        } else {
+            egui::Color32::from_rgb(50, 50, 50)
+        };
+        let stroke = if node.id == start {
+            egui::Stroke::new(2.0, egui::Color32::from_rgb(90, 200, 110))
+        } else {
+            egui::Stroke::new(1.5, edge_color)
+        };
+        painter.rect_filled(rect, 4.0, fill);
+        painter.rect_stroke(rect, 4.0, stroke);
+        let preview: String = if node.text.chars().count() > 22 {
+            node.text.chars().take(21).chain(std::iter::once('…')).collect()
+        } else {
+            node.text.clone()
+        };
+        let title = if node.speaker.is_empty() {
+            node.id.clone()
+        } else {
+            format!("{} ({})", node.id, node.speaker)
+        };
+        painter.text(
+            rect.left_top() + egui::vec2(6.0, 4.0),
+            egui::Align2::LEFT_TOP,
+            title,
+            egui::TextStyle::Body.resolve(ui.style()),
+            egui::Color32::from_rgb(220, 220, 220),
+        );
+        painter.text(
+            rect.left_bottom() + egui::vec2(6.0, -4.0),
+            egui::Align2::LEFT_BOTTOM,
+            preview,
+            egui::TextStyle::Small.resolve(ui.style()),
+            egui::Color32::from_rgb(160, 160, 160),
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Physics Debug Module
+//! A viewport overlay for everything that moves or collides, drawn whether or
+//! not it is selected. Collision shapes are the mesh bounds that ray casts
+//! cull against, contacts are wheel contacts and recent projectile hits, and
+//! velocities are drawn as arrows from each moving body.
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use super::{draw_aabb_gizmo, EditorSettings};
+use crate::core::components::EditorHidden;
+use crate::core::destruction::DebrisChunk;
+use crate::core::projectile::{Projectile, ProjectileHitEvent};
+use crate::core::vehicle::RaycastVehicle;
+
+/// How long a projectile hit stays marked
+const HIT_MARKER_SECONDS: f32 = 1.5;
+
+const SHAPE_COLOR: Color = Color::srgb(0.2, 0.7, 1.0);
+const CONTACT_COLOR: Color = Color::srgb(1.0, 0.25, 0.25);
+const VELOCITY_COLOR: Color = Color::srgb(0.3, 1.0, 0.5);
+const ANGULAR_VELOCITY_COLOR: Color = Color::srgb(1.0, 0.5, 1.0);
+
+/// Recent contacts, kept so single-frame hits stay visible for a moment
+#[derive(Resource, Default)]
+pub struct PhysicsDebugContacts {
+    hits: Vec<(Vec3, Vec3, f32)>,
+}
+
+pub fn collect_physics_debug_contacts(
+    time: Res<Time>,
+    editor_settings: Res<EditorSettings>,
+    mut contacts: ResMut<PhysicsDebugContacts>,
+    mut hit_events: EventReader<ProjectileHitEvent>,
+) {
+    if !editor_settings.show_physics_debug {
+        hit_events.clear();
+        contacts.hits.clear();
+        return;
+    }
+    let delta = time.delta_seconds();
+    contacts.hits.retain_mut(|(_, _, age)| {
+        *age += delta;
+        *age < HIT_MARKER_SECONDS
+    });
+    contacts
+        .hits
+        .extend(hit_events.read().map(|hit| (hit.point, hit.normal, 0.0)));
+}
+
+pub fn draw_physics_debug_gizmos(
+    editor_settings: Res<EditorSettings>,
+    contacts: Res<PhysicsDebugContacts>,
+    mut gizmos: Gizmos,
+    shape_query: Query<(&GlobalTransform, &Aabb), (With<Handle<Mesh>>, Without<EditorHidden>)>,
+    vehicle_query: Query<(&RaycastVehicle, &GlobalTransform)>,
+    projectile_query: Query<(&Projectile, &GlobalTransform)>,
+    debris_query: Query<(&DebrisChunk, &GlobalTransform)>,
+) {
+    if !editor_settings.show_physics_debug {
+        return;
+    }
+
+    for (transform, aabb) in &shape_query {
+        draw_aabb_gizmo(&mut gizmos, transform.compute_matrix(), aabb, SHAPE_COLOR);
+    }
+
+    for (vehicle, transform) in &vehicle_query {
+        let center = transform.translation();
+        draw_velocity(&mut gizmos, center, vehicle.velocity, VELOCITY_COLOR);
+        draw_velocity(&mut gizmos, center, vehicle.angular_velocity, ANGULAR_VELOCITY_COLOR);
+        for contact in vehicle.wheel_states().iter().filter_map(|state| state.contact) {
+            gizmos.sphere(contact, Quat::IDENTITY, 0.06, CONTACT_COLOR);
+        }
+    }
+
+    for (projectile, transform) in &projectile_query {
+        draw_velocity(&mut gizmos, transform.translation(), projectile.velocity, VELOCITY_COLOR);
+    }
+
+    for (chunk, transform) in &debris_query {
+        let center = transform.translation();
+        gizmos.sphere(center, Quat::IDENTITY, chunk.radius, SHAPE_COLOR);
+        draw_velocity(&mut gizmos, center, chunk.velocity, VELOCITY_COLOR);
+    }
+
+    for (point, normal, age) in &contacts.hits {
+        let fade = 1.0 - age / HIT_MARKER_SECONDS;
+        let color = CONTACT_COLOR.with_alpha(fade);
+        gizmos.sphere(*point, Quat::IDENTITY, 0.08, color);
+        gizmos.arrow(*point, *point + *normal * 0.5, color);
+    }
+}
+
+/// One world unit of arrow per metre per second, so arrows compare across bodies
+fn draw_velocity(gizmos: &mut Gizmos, origin: Vec3, velocity: Vec3, color: Color) {
+    if velocity.length_squared() < 1e-4 {
+        return;
+    }
+    gizmos.arrow(origin, origin + velocity, color);
+}
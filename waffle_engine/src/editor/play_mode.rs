@@ -0,0 +1,256 @@
+//! Waffle Engine Play Mode
+//! Pressing Play snapshots the scene and pressing Stop puts it back, so
+//! nothing done while playing sticks. Entities that made it through keep
+//! their ids, ones despawned or deleted while playing come back, and ones
+//! spawned while playing are removed. Material edits and gameplay state such
+//! as health are rolled back too.
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::scene_file::{
+    capture_scene, link_scene_entities, restore_scene_entity, spawn_scene_entity, SceneEntityQuery, SceneFile,
+};
+use super::trash::EditorTrash;
+use super::EditorState;
+use crate::core::components::{EditorHidden, PlaySpawned};
+use crate::core::destruction::Destructible;
+use crate::core::health::{Damageable, Dead, Health, Team};
+use crate::rendering::scene::SceneRootEntity;
+
+#[derive(Resource, Default)]
+pub struct PlaySnapshot {
+    scene: Option<CapturedScene>,
+}
+
+struct CapturedScene {
+    file: SceneFile,
+    /// The entity each of `file.entities` was captured from
+    entities: Vec<Entity>,
+    handles: Vec<(Option<Handle<Mesh>>, Option<Handle<StandardMaterial>>)>,
+    /// Everything under the scene root, including model children
+    known: HashSet<Entity>,
+    materials: Vec<(Handle<StandardMaterial>, StandardMaterial)>,
+    /// Entities that were already in the trash
+    trashed: HashSet<Entity>,
+    gameplay: HashMap<Entity, CapturedGameplay>,
+}
+
+/// Gameplay components that scene files don't keep but playing changes
+#[derive(Default)]
+struct CapturedGameplay {
+    health: Option<Health>,
+    damageable: Option<Damageable>,
+    team: Option<Team>,
+    destructible: Option<Destructible>,
+    dead: bool,
+}
+
+type GameplayQueryData = (
+    Option<&'static Health>,
+    Option<&'static Damageable>,
+    Option<&'static Team>,
+    Option<&'static Destructible>,
+    Has<Dead>,
+);
+
+fn restore_gameplay(entity_commands: &mut EntityCommands, gameplay: &CapturedGameplay) {
+    match &gameplay.health {
+        Some(health) => entity_commands.insert(health.clone()),
+        None => entity_commands.remove::<Health>(),
+    };
+    match &gameplay.damageable {
+        Some(damageable) => entity_commands.insert(damageable.clone()),
+        None => entity_commands.remove::<Damageable>(),
+    };
+    match gameplay.team {
+        Some(team) => entity_commands.insert(team),
+        None => entity_commands.remove::<Team>(),
+    };
+    match &gameplay.destructible {
+        Some(destructible) => entity_commands.insert(destructible.clone()),
+        None => entity_commands.remove::<Destructible>(),
+    };
+    if gameplay.dead {
+        entity_commands.insert(Dead);
+    } else {
+        entity_commands.remove::<Dead>();
+    }
+}
+
+/// Despawns without complaining when the entity already went with its parent
+fn despawn_if_alive(commands: &mut Commands, entity: Entity) {
+    commands.add(move |world: &mut World| {
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+    });
+}
+
+pub fn take_play_snapshot(
+    mut snapshot: ResMut<PlaySnapshot>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    trash: Res<EditorTrash>,
+    children_query: Query<&Children>,
+    scene_query: Query<SceneEntityQuery>,
+    handle_query: Query<(Option<&Handle<Mesh>>, Option<&Handle<StandardMaterial>>)>,
+    gameplay_query: Query<GameplayQueryData>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+) {
+    let Some(root) = scene_root.map(|root| root.0) else {
+        return;
+    };
+    let (file, entities) = capture_scene(root, &children_query, &scene_query, &meshes, &materials);
+    let handles = entities
+        .iter()
+        .map(|entity| {
+            handle_query
+                .get(*entity)
+                .map(|(mesh, material)| (mesh.cloned(), material.cloned()))
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let mut known = HashSet::new();
+    let mut stack = vec![root];
+    while let Some(current) = stack.pop() {
+        if let Ok(children) = children_query.get(current) {
+            stack.extend(children.iter().copied());
+            known.extend(children.iter().copied());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut saved_materials = Vec::new();
+    for (_, material) in handle_query.iter_many(&known) {
+        let Some(handle) = material else {
+            continue;
+        };
+        if !seen.insert(handle.id()) {
+            continue;
+        }
+        if let Some(data) = materials.get(handle) {
+            saved_materials.push((handle.clone(), data.clone()));
+        }
+    }
+
+    let gameplay = known
+        .iter()
+        .filter_map(|entity| {
+            let (health, damageable, team, destructible, dead) = gameplay_query.get(*entity).ok()?;
+            let captured = CapturedGameplay {
+                health: health.cloned(),
+                damageable: damageable.cloned(),
+                team: team.copied(),
+                destructible: destructible.cloned(),
+                dead,
+            };
+            Some((*entity, captured))
+        })
+        .collect();
+
+    snapshot.scene = Some(CapturedScene {
+        file,
+        entities,
+        handles,
+        known,
+        materials: saved_materials,
+        trashed: trash.entries.iter().map(|entry| entry.entity).collect(),
+        gameplay,
+    });
+}
+
+pub fn restore_play_snapshot(
+    mut commands: Commands,
+    mut snapshot: ResMut<PlaySnapshot>,
+    mut editor_state: ResMut<EditorState>,
+    mut trash: ResMut<EditorTrash>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    children_query: Query<&Children>,
+    parent_query: Query<&Parent>,
+    hidden_query: Query<(), With<EditorHidden>>,
+    spawned_query: Query<Entity, With<PlaySpawned>>,
+) {
+    let Some(scene) = snapshot.scene.take() else {
+        return;
+    };
+    let Some(root) = scene_root.map(|root| root.0) else {
+        return;
+    };
+
+    // Drop what was spawned while playing, under the scene root or not
+    for entity in spawned_query.iter() {
+        despawn_if_alive(&mut commands, entity);
+    }
+    let mut stack = vec![root];
+    while let Some(current) = stack.pop() {
+        for child in children_query.get(current).into_iter().flatten() {
+            if hidden_query.contains(*child) {
+                continue;
+            }
+            if scene.known.contains(child) {
+                stack.push(*child);
+            } else {
+                despawn_if_alive(&mut commands, *child);
+            }
+        }
+    }
+
+    // What was deleted while playing is respawned from the snapshot below
+    trash.entries.retain(|entry| scene.trashed.contains(&entry.entity));
+
+    for (handle, material) in scene.materials {
+        materials.insert(&handle, material);
+    }
+
+    editor_state.selection.retain(|entity| scene.known.contains(&entity));
+
+    let mut restored: Vec<Entity> = Vec::with_capacity(scene.entities.len());
+    for ((entity, captured), (mesh, material)) in scene.file.entities.iter().zip(&scene.entities).zip(scene.handles) {
+        let parent = entity
+            .parent
+            .and_then(|index| restored.get(index).copied())
+            .unwrap_or(root);
+        let gameplay = scene.gameplay.get(captured);
+        let restored_entity = match commands.get_entity(*captured) {
+            Some(mut entity_commands) => {
+                restore_scene_entity(&mut entity_commands, entity, &asset_server);
+                if parent_query.get(*captured).map(|current| current.get()).ok() != Some(parent) {
+                    entity_commands.set_parent(parent);
+                }
+                if let Some(gameplay) = gameplay {
+                    restore_gameplay(&mut entity_commands, gameplay);
+                }
+                *captured
+            }
+            None => {
+                let mut entity_commands = spawn_scene_entity(&mut commands, entity, mesh, material, &asset_server);
+                entity_commands.set_parent(parent);
+                if let Some(gameplay) = gameplay {
+                    restore_gameplay(&mut entity_commands, gameplay);
+                }
+                entity_commands.id()
+            }
+        };
+        if restored_entity != *captured {
+            editor_state.selection.replace(*captured, restored_entity);
+        }
+        restored.push(restored_entity);
+    }
+    link_scene_entities(&mut commands, &scene.file, &restored);
+
+    // Model children aren't in the scene file but keep their own gameplay state
+    let in_file: HashSet<&Entity> = scene.entities.iter().collect();
+    for (entity, gameplay) in &scene.gameplay {
+        if in_file.contains(entity) {
+            continue;
+        }
+        if let Some(mut entity_commands) = commands.get_entity(*entity) {
+            restore_gameplay(&mut entity_commands, gameplay);
+        }
+    }
+}
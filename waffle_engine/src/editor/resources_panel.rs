@@ -0,0 +1,414 @@
+//! Waffle Engine Resources Panel
+//! Lists the resources in the world whose types reflect `Resource`, and edits
+//! the fields of the selected one through reflection while the game runs.
+//! The panel edits a copy of the resource, which is written back to the world
+//! after the UI has drawn and then taken again, so changes made by systems
+//! show up as well. Save writes the resource to a config file that is applied
+//! over it at startup.
+
+use bevy::prelude::*;
+use bevy::reflect::{Array, DynamicEnum, DynamicVariant, Enum, List, Map, ReflectMut, Tuple, TypeInfo, VariantInfo};
+use bevy_egui::egui;
+use std::path::PathBuf;
+
+use super::panels::{color_to_egui, egui_to_color};
+use crate::core::resource_config::{resource_config_path, save_resource_config};
+
+#[derive(Clone, Debug)]
+pub struct ListedResource {
+    pub type_path: String,
+    pub short_name: String,
+    /// From this engine rather than Bevy or another dependency
+    pub engine: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConfigAction {
+    Save,
+    Forget,
+}
+
+#[derive(Resource, Default)]
+pub struct ResourcesPanel {
+    pub resources: Vec<ListedResource>,
+    /// Only resources whose names contain this
+    pub filter: String,
+    /// List Bevy's resources along with the engine's
+    pub show_all: bool,
+    /// Type path of the resource being edited
+    pub selected: Option<String>,
+    /// Save the selected resource to its config file after every edit
+    pub save_on_edit: bool,
+    /// Copy of the selected resource that the fields edit
+    value: Option<Box<dyn Reflect>>,
+    edited: bool,
+    action: Option<ConfigAction>,
+    /// Outcome of the last save, shown above the fields
+    status: Option<String>,
+    /// Drawn since the last sync; the world is only read while it is
+    visible: bool,
+}
+
+/// Write the panel's edits back to the selected resource, then take a fresh
+/// copy of it. Runs after the editor UI, while the panel is open.
+pub fn sync_resources_panel(world: &mut World) {
+    if !world.get_resource::<ResourcesPanel>().is_some_and(|panel| panel.visible) {
+        return;
+    }
+    let crate_name = module_path!().split("::").next().unwrap_or_default();
+    world.resource_scope(|world, mut panel: Mut<ResourcesPanel>| {
+        panel.visible = false;
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+
+        let mut resources: Vec<ListedResource> = registry
+            .iter()
+            .filter(|registration| {
+                registration.data::<ReflectResource>().is_some_and(|resource| resource.reflect(world).is_some())
+            })
+            .map(|registration| {
+                let table = registration.type_info().type_path_table();
+                ListedResource {
+                    type_path: table.path().to_string(),
+                    short_name: table.short_path().to_string(),
+                    engine: table.path().strip_prefix(crate_name).is_some_and(|path| path.starts_with("::")),
+                }
+            })
+            .collect();
+        resources.sort_by(|a, b| a.short_name.cmp(&b.short_name));
+        panel.resources = resources;
+
+        let registration = panel.selected.as_deref().and_then(|path| registry.get_with_type_path(path));
+        let Some((registration, reflect_resource)) = registration
+            .and_then(|registration| Some((registration, registration.data::<ReflectResource>()?)))
+            .filter(|(_, resource)| resource.reflect(world).is_some())
+        else {
+            panel.selected = None;
+            panel.value = None;
+            panel.edited = false;
+            panel.action = None;
+            return;
+        };
+
+        let edited = std::mem::take(&mut panel.edited);
+        if edited {
+            if let Some(value) = panel.value.take() {
+                reflect_resource.apply(world, &*value);
+            }
+        }
+        let short_name = registration.type_info().type_path_table().short_path();
+        match panel.action.take().or((edited && panel.save_on_edit).then_some(ConfigAction::Save)) {
+            Some(ConfigAction::Save) => {
+                if let Some(value) = reflect_resource.reflect(world) {
+                    panel.status = Some(match save_resource_config(value, short_name, &registry) {
+                        Ok(path) => format!("Saved to {}", path.display()),
+                        Err(error) => {
+                            warn!("Failed to save {}: {}", short_name, error);
+                            format!("Failed to save: {error}")
+                        }
+                    });
+                }
+            }
+            Some(ConfigAction::Forget) => {
+                let path = resource_config_path(short_name);
+                panel.status = Some(match std::fs::remove_file(&path) {
+                    Ok(()) => format!("Removed {}", path.display()),
+                    Err(error) => format!("Failed to remove {}: {error}", path.display()),
+                });
+            }
+            None => {}
+        }
+        panel.value = reflect_resource.reflect(world).map(|value| value.clone_value());
+    });
+}
+
+pub fn draw_resources_panel(ui: &mut egui::Ui, panel: &mut ResourcesPanel) {
+    panel.visible = true;
+    ui.heading("Resources");
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut panel.filter).hint_text("Filter resources").desired_width(160.0));
+        ui.checkbox(&mut panel.show_all, "Bevy resources");
+    });
+    ui.separator();
+
+    let filter = panel.filter.trim().to_lowercase();
+    let show_all = panel.show_all;
+    let mut clicked = None;
+    egui::ScrollArea::vertical().id_source("resources_list").max_height(160.0).show(ui, |ui| {
+        let shown = panel
+            .resources
+            .iter()
+            .filter(|resource| show_all || resource.engine)
+            .filter(|resource| filter.is_empty() || resource.short_name.to_lowercase().contains(&filter));
+        for resource in shown {
+            let selected = panel.selected.as_deref() == Some(resource.type_path.as_str());
+            if ui.selectable_label(selected, &resource.short_name).on_hover_text(&resource.type_path).clicked() {
+                clicked = Some(resource.type_path.clone());
+            }
+        }
+    });
+    if let Some(path) = clicked {
+        if panel.selected.as_deref() != Some(path.as_str()) {
+            panel.selected = Some(path);
+            panel.value = None;
+            panel.edited = false;
+            panel.status = None;
+        }
+    }
+    ui.separator();
+
+    let Some(selected) = panel.selected.clone() else {
+        ui.label("Select a resource to edit its fields.");
+        return;
+    };
+    let short_name = panel
+        .resources
+        .iter()
+        .find(|resource| resource.type_path == selected)
+        .map_or_else(|| selected.clone(), |resource| resource.short_name.clone());
+    let config_path = resource_config_path(&short_name);
+    ui.horizontal(|ui| {
+        ui.strong(&short_name);
+        if ui.button("Save").on_hover_text(format!("Write to {} and apply at startup", config_path.display())).clicked()
+        {
+            panel.action = Some(ConfigAction::Save);
+        }
+        if ui
+            .add_enabled(config_path.exists(), egui::Button::new("Forget Saved"))
+            .on_hover_text("Delete the config file; the current values stay until the next run")
+            .clicked()
+        {
+            panel.action = Some(ConfigAction::Forget);
+        }
+        ui.checkbox(&mut panel.save_on_edit, "Save on edit");
+    });
+    if let Some(status) = &panel.status {
+        ui.weak(status);
+    }
+
+    let Some(value) = panel.value.as_mut() else {
+        ui.label("Reading...");
+        return;
+    };
+    let mut changed = false;
+    egui::ScrollArea::vertical().id_source("resource_fields").show(ui, |ui| {
+        changed = reflect_fields(ui, value.as_mut(), egui::Id::new(("resource_fields", &selected)));
+    });
+    if changed {
+        panel.edited = true;
+    }
+}
+
+/// Fields of a struct, tuple, list or enum variant in a grid, one row each.
+/// Returns whether any changed.
+fn reflect_fields(ui: &mut egui::Ui, value: &mut dyn Reflect, id: egui::Id) -> bool {
+    let mut changed = false;
+    egui::Grid::new(id).num_columns(2).striped(true).show(ui, |ui| match value.reflect_mut() {
+        ReflectMut::Struct(fields) => {
+            for index in 0..fields.field_len() {
+                let name = fields.name_at(index).map(field_label).unwrap_or_default();
+                if let Some(field) = fields.field_at_mut(index) {
+                    changed |= reflect_row(ui, &name, field, id.with(index));
+                }
+            }
+        }
+        ReflectMut::TupleStruct(fields) => {
+            for index in 0..fields.field_len() {
+                if let Some(field) = fields.field_mut(index) {
+                    changed |= reflect_row(ui, &index.to_string(), field, id.with(index));
+                }
+            }
+        }
+        ReflectMut::Tuple(fields) => {
+            for index in 0..fields.field_len() {
+                if let Some(field) = fields.field_mut(index) {
+                    changed |= reflect_row(ui, &index.to_string(), field, id.with(index));
+                }
+            }
+        }
+        ReflectMut::List(items) => {
+            for index in 0..items.len() {
+                if let Some(item) = items.get_mut(index) {
+                    changed |= reflect_row(ui, &format!("[{index}]"), item, id.with(index));
+                }
+            }
+        }
+        ReflectMut::Array(items) => {
+            for index in 0..items.len() {
+                if let Some(item) = items.get_mut(index) {
+                    changed |= reflect_row(ui, &format!("[{index}]"), item, id.with(index));
+                }
+            }
+        }
+        ReflectMut::Enum(variant) => {
+            for index in 0..variant.field_len() {
+                let name = variant.name_at(index).map_or_else(|| index.to_string(), field_label);
+                if let Some(field) = variant.field_at_mut(index) {
+                    changed |= reflect_row(ui, &name, field, id.with(index));
+                }
+            }
+        }
+        ReflectMut::Map(_) | ReflectMut::Value(_) => {}
+    });
+    changed
+}
+
+fn reflect_row(ui: &mut egui::Ui, name: &str, value: &mut dyn Reflect, id: egui::Id) -> bool {
+    ui.label(name);
+    let changed = ui.vertical(|ui| reflect_value(ui, value, id)).inner;
+    ui.end_row();
+    changed
+}
+
+/// An editor for one reflected value: a widget for numbers, text, paths,
+/// colors and plain enums, and a collapsing list of fields for the rest
+fn reflect_value(ui: &mut egui::Ui, value: &mut dyn Reflect, id: egui::Id) -> bool {
+    let type_path = value.get_represented_type_info().map_or(value.reflect_type_path(), |info| info.type_path());
+    if type_path == Color::type_path() {
+        // Copies hold colors as dynamic enums, so go through a real `Color`
+        let Some(color) = Color::from_reflect(&*value) else {
+            return false;
+        };
+        let mut picked = color_to_egui(color);
+        if ui.color_edit_button_srgba(&mut picked).changed() {
+            value.apply(&egui_to_color(picked));
+            return true;
+        }
+        return false;
+    }
+    if type_path.starts_with("bevy_asset::handle::Handle<") {
+        ui.weak("Asset handle");
+        return false;
+    }
+
+    if let Some(flag) = value.downcast_mut::<bool>() {
+        return ui.checkbox(flag, "").changed();
+    }
+    if let Some(number) = value.downcast_mut::<f32>() {
+        return ui.add(egui::DragValue::new(number).speed(0.01)).changed();
+    }
+    if let Some(number) = value.downcast_mut::<f64>() {
+        return ui.add(egui::DragValue::new(number).speed(0.01)).changed();
+    }
+    macro_rules! integer_fields {
+        ($($integer:ty),*) => {
+            $(
+                if let Some(number) = value.downcast_mut::<$integer>() {
+                    return ui.add(egui::DragValue::new(number)).changed();
+                }
+            )*
+        };
+    }
+    integer_fields!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+    if let Some(text) = value.downcast_mut::<String>() {
+        return ui.text_edit_singleline(text).changed();
+    }
+    if let Some(path) = value.downcast_mut::<PathBuf>() {
+        let mut text = path.to_string_lossy().to_string();
+        if ui.text_edit_singleline(&mut text).changed() {
+            *path = PathBuf::from(text);
+            return true;
+        }
+        return false;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Enum(variant) => return reflect_enum(ui, variant, id),
+        ReflectMut::Map(map) => {
+            ui.weak(format!("{} entries", map.len()));
+            return false;
+        }
+        ReflectMut::Value(value) => {
+            ui.weak(format!("{value:?}"));
+            return false;
+        }
+        _ => {}
+    }
+    if let Some(changed) = inline_numbers(ui, value) {
+        return changed;
+    }
+    let mut label = value
+        .get_represented_type_info()
+        .map_or(value.reflect_short_type_path(), |info| info.type_path_table().short_path())
+        .to_string();
+    if let ReflectMut::List(items) = value.reflect_mut() {
+        label.push_str(&format!(" ({})", items.len()));
+    }
+    let mut changed = false;
+    egui::CollapsingHeader::new(label).id_source(id).show(ui, |ui| {
+        changed = reflect_fields(ui, value, id.with("fields"));
+    });
+    changed
+}
+
+/// A picker over the variants of enums whose variants hold nothing, like
+/// quality levels; the fields of the current variant otherwise
+fn reflect_enum(ui: &mut egui::Ui, value: &mut dyn Enum, id: egui::Id) -> bool {
+    let unit_variants = match value.get_represented_type_info() {
+        Some(TypeInfo::Enum(info)) if info.iter().all(|variant| matches!(variant, VariantInfo::Unit(_))) => {
+            Some(info.variant_names())
+        }
+        _ => None,
+    };
+    if let Some(variants) = unit_variants {
+        let current = value.variant_name().to_string();
+        let mut picked = current.clone();
+        egui::ComboBox::from_id_source(id).selected_text(&current).show_ui(ui, |ui| {
+            for variant in variants {
+                ui.selectable_value(&mut picked, variant.to_string(), *variant);
+            }
+        });
+        if picked != current {
+            value.apply(&DynamicEnum::new(picked, DynamicVariant::Unit));
+            return true;
+        }
+        return false;
+    }
+    if value.field_len() == 0 {
+        ui.label(value.variant_name());
+        return false;
+    }
+    let mut changed = false;
+    egui::CollapsingHeader::new(value.variant_name().to_string()).id_source(id).show(ui, |ui| {
+        changed = reflect_fields(ui, value.as_reflect_mut(), id.with("fields"));
+    });
+    changed
+}
+
+/// Structs of a few numbers, like vectors, edited on one line
+fn inline_numbers(ui: &mut egui::Ui, value: &mut dyn Reflect) -> Option<bool> {
+    let ReflectMut::Struct(fields) = value.reflect_mut() else {
+        return None;
+    };
+    let count = fields.field_len();
+    let numbers = (0..count).all(|index| fields.field_at(index).is_some_and(|field| field.is::<f32>()));
+    if count == 0 || count > 4 || !numbers {
+        return None;
+    }
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        for index in 0..count {
+            let name = fields.name_at(index).unwrap_or_default().to_string();
+            if let Some(number) = fields.field_at_mut(index).and_then(|field| field.downcast_mut::<f32>()) {
+                ui.label(name);
+                changed |= ui.add(egui::DragValue::new(number).speed(0.01)).changed();
+            }
+        }
+    });
+    Some(changed)
+}
+
+/// `shadow_distance` as "Shadow Distance"
+fn field_label(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
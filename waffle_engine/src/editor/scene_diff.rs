@@ -0,0 +1,847 @@
+//! Waffle Engine Scene Diff and Merge
+//! Compares two versions of a scene entity by entity and merges edits made on both
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::process::Command;
+
+use super::scene_file::{
+    capture_scene, SceneEntity, SceneEntityQuery, SceneFile, SceneFileEvent, SceneMaterial, SceneMesh,
+};
+use super::EditorState;
+use crate::rendering::scene::SceneRootEntity;
+
+const ADDED_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 200, 110);
+const REMOVED_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 90, 90);
+const CHANGED_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 160, 60);
+
+/// Property values longer than this are cut short in the lists
+const VALUE_PREVIEW_LENGTH: usize = 60;
+
+/// A property of a scene entity, as compared and merged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneProperty {
+    Transform,
+    Visible,
+    Source,
+    Mesh,
+    Material,
+    Model,
+    Light,
+    Environment,
+    LensFlare,
+    AoVolume,
+    DollyTrack,
+    LuaScript,
+    AudioSource,
+    Surface,
+    Animator,
+    StateMachine,
+    PropertyAnimation,
+    ParticleEmitter,
+    Terrain,
+    Foliage,
+    Instancing,
+    Water,
+    Lod,
+    SceneReference,
+}
+
+impl SceneProperty {
+    pub const ALL: [SceneProperty; 24] = [
+        SceneProperty::Transform,
+        SceneProperty::Visible,
+        SceneProperty::Source,
+        SceneProperty::Mesh,
+        SceneProperty::Material,
+        SceneProperty::Model,
+        SceneProperty::Light,
+        SceneProperty::Environment,
+        SceneProperty::LensFlare,
+        SceneProperty::AoVolume,
+        SceneProperty::DollyTrack,
+        SceneProperty::LuaScript,
+        SceneProperty::AudioSource,
+        SceneProperty::Surface,
+        SceneProperty::Animator,
+        SceneProperty::StateMachine,
+        SceneProperty::PropertyAnimation,
+        SceneProperty::ParticleEmitter,
+        SceneProperty::Terrain,
+        SceneProperty::Foliage,
+        SceneProperty::Instancing,
+        SceneProperty::Water,
+        SceneProperty::Lod,
+        SceneProperty::SceneReference,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SceneProperty::Transform => "Transform",
+            SceneProperty::Visible => "Visible",
+            SceneProperty::Source => "Source",
+            SceneProperty::Mesh => "Mesh",
+            SceneProperty::Material => "Material",
+            SceneProperty::Model => "Model",
+            SceneProperty::Light => "Light",
+            SceneProperty::Environment => "Environment",
+            SceneProperty::LensFlare => "Lens Flare",
+            SceneProperty::AoVolume => "AO Volume",
+            SceneProperty::DollyTrack => "Dolly Track",
+            SceneProperty::LuaScript => "Lua Script",
+            SceneProperty::AudioSource => "Audio Source",
+            SceneProperty::Surface => "Surface",
+            SceneProperty::Animator => "Animator",
+            SceneProperty::StateMachine => "State Machine",
+            SceneProperty::PropertyAnimation => "Keyframes",
+            SceneProperty::ParticleEmitter => "Particles",
+            SceneProperty::Terrain => "Terrain",
+            SceneProperty::Foliage => "Foliage",
+            SceneProperty::Instancing => "Instancing",
+            SceneProperty::Water => "Water",
+            SceneProperty::Lod => "LOD",
+            SceneProperty::SceneReference => "Sub-Scene",
+        }
+    }
+
+    /// The value as RON, to compare and show; `None` when the entity lacks it
+    fn describe(self, flat: &FlatEntity) -> Option<String> {
+        let entity = &flat.entity;
+        match self {
+            SceneProperty::Transform => Some(to_ron(&entity.transform)),
+            SceneProperty::Visible => Some(entity.visible.to_string()),
+            SceneProperty::Source => entity.source.as_ref().map(to_ron),
+            SceneProperty::Mesh => flat.mesh.as_ref().map(to_ron),
+            SceneProperty::Material => flat.material.as_ref().map(to_ron),
+            SceneProperty::Model => entity.model.clone(),
+            SceneProperty::Light => entity.light.as_ref().map(to_ron),
+            SceneProperty::Environment => entity.environment.as_ref().map(to_ron),
+            SceneProperty::LensFlare => entity.lens_flare.as_ref().map(to_ron),
+            SceneProperty::AoVolume => entity.ao_volume.as_ref().map(to_ron),
+            SceneProperty::DollyTrack => entity.dolly_track.as_ref().map(to_ron),
+            SceneProperty::LuaScript => entity.lua_script.as_ref().map(to_ron),
+            SceneProperty::AudioSource => entity.audio_source.as_ref().map(to_ron),
+            SceneProperty::Surface => entity.surface.as_ref().map(to_ron),
+            SceneProperty::Animator => entity.animator.as_ref().map(to_ron),
+            SceneProperty::StateMachine => entity.state_machine.as_ref().map(to_ron),
+            SceneProperty::PropertyAnimation => entity.property_animation.as_ref().map(to_ron),
+            SceneProperty::ParticleEmitter => entity.particle_emitter.as_ref().map(to_ron),
+            SceneProperty::Terrain => entity.terrain.as_ref().map(to_ron),
+            SceneProperty::Foliage => entity.foliage.as_ref().map(to_ron),
+            SceneProperty::Instancing => entity.instanced.map(|_| "Shared".to_string()),
+            SceneProperty::Water => entity.water.as_ref().map(to_ron),
+            SceneProperty::Lod => entity.lod.as_ref().map(to_ron),
+            SceneProperty::SceneReference => entity.scene_reference.as_ref().map(|reference| reference.scene.clone()),
+        }
+    }
+
+    /// Set the property of `to` to its value in `from`
+    fn copy(self, from: &FlatEntity, to: &mut FlatEntity) {
+        let (source, target) = (&from.entity, &mut to.entity);
+        match self {
+            SceneProperty::Transform => target.transform = source.transform,
+            SceneProperty::Visible => target.visible = source.visible,
+            SceneProperty::Source => target.source = source.source.clone(),
+            SceneProperty::Mesh => to.mesh = from.mesh.clone(),
+            SceneProperty::Material => to.material = from.material.clone(),
+            SceneProperty::Model => target.model = source.model.clone(),
+            SceneProperty::Light => target.light = source.light.clone(),
+            SceneProperty::Environment => target.environment = source.environment.clone(),
+            SceneProperty::LensFlare => target.lens_flare = source.lens_flare.clone(),
+            SceneProperty::AoVolume => target.ao_volume = source.ao_volume.clone(),
+            SceneProperty::DollyTrack => target.dolly_track = source.dolly_track.clone(),
+            SceneProperty::LuaScript => target.lua_script = source.lua_script.clone(),
+            SceneProperty::AudioSource => target.audio_source = source.audio_source.clone(),
+            SceneProperty::Surface => target.surface = source.surface,
+            SceneProperty::Animator => target.animator = source.animator.clone(),
+            SceneProperty::StateMachine => target.state_machine = source.state_machine.clone(),
+            SceneProperty::PropertyAnimation => target.property_animation = source.property_animation.clone(),
+            SceneProperty::ParticleEmitter => target.particle_emitter = source.particle_emitter.clone(),
+            SceneProperty::Terrain => target.terrain = source.terrain.clone(),
+            SceneProperty::Foliage => target.foliage = source.foliage.clone(),
+            SceneProperty::Instancing => target.instanced = source.instanced,
+            SceneProperty::Water => target.water = source.water.clone(),
+            SceneProperty::Lod => target.lod = source.lod.clone(),
+            SceneProperty::SceneReference => target.scene_reference = source.scene_reference.clone(),
+        }
+    }
+}
+
+fn to_ron<T: Serialize>(value: &T) -> String {
+    ron::ser::to_string(value).unwrap_or_default()
+}
+
+/// A scene entity with its path, and its mesh and material instead of
+/// indices into one file's tables, so it can move between files. Scene files
+/// keep no identity for entities, so versions are matched by the path of
+/// names from the scene root, numbered where siblings share a name; a renamed
+/// or reparented entity shows as removed and added again.
+#[derive(Clone)]
+struct FlatEntity {
+    path: String,
+    parent: Option<String>,
+    entity: SceneEntity,
+    mesh: Option<SceneMesh>,
+    material: Option<SceneMaterial>,
+}
+
+impl FlatEntity {
+    fn same_as(&self, other: &FlatEntity) -> bool {
+        SceneProperty::ALL
+            .iter()
+            .all(|property| property.describe(self) == property.describe(other))
+    }
+}
+
+fn flatten(file: &SceneFile) -> Vec<FlatEntity> {
+    let mut paths: Vec<String> = Vec::with_capacity(file.entities.len());
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    for entity in &file.entities {
+        let name = entity.name.as_deref().unwrap_or("Entity");
+        let path = match entity.parent.and_then(|index| paths.get(index)) {
+            Some(parent) => format!("{parent}/{name}"),
+            None => name.to_string(),
+        };
+        let count = taken.entry(path.clone()).or_insert(0);
+        *count += 1;
+        paths.push(if *count == 1 { path } else { format!("{path} #{count}") });
+    }
+    file.entities
+        .iter()
+        .zip(&paths)
+        .map(|(entity, path)| FlatEntity {
+            path: path.clone(),
+            parent: entity.parent.and_then(|index| paths.get(index)).cloned(),
+            entity: entity.clone(),
+            mesh: entity.mesh.and_then(|index| file.meshes.get(index)).cloned(),
+            material: entity.material.and_then(|index| file.materials.get(index)).cloned(),
+        })
+        .collect()
+}
+
+/// Build a scene file back from entities, parents first, sharing meshes and
+/// materials that are the same
+fn unflatten(version: u32, entities: Vec<FlatEntity>) -> SceneFile {
+    let present: HashSet<String> = entities.iter().map(|flat| flat.path.clone()).collect();
+    let mut ordered: Vec<FlatEntity> = Vec::with_capacity(entities.len());
+    let mut placed: HashMap<String, usize> = HashMap::new();
+    let mut pending = entities;
+    while !pending.is_empty() {
+        let waiting = pending.len();
+        let mut rest = Vec::new();
+        for flat in pending {
+            // Entities whose parent didn't make it go under the scene root
+            let ready = flat
+                .parent
+                .as_ref()
+                .map_or(true, |parent| placed.contains_key(parent) || !present.contains(parent));
+            if ready {
+                placed.insert(flat.path.clone(), ordered.len());
+                ordered.push(flat);
+            } else {
+                rest.push(flat);
+            }
+        }
+        if rest.len() == waiting {
+            for mut flat in rest {
+                flat.parent = None;
+                placed.insert(flat.path.clone(), ordered.len());
+                ordered.push(flat);
+            }
+            break;
+        }
+        pending = rest;
+    }
+
+    let mut file = SceneFile {
+        version,
+        entities: Vec::with_capacity(ordered.len()),
+        meshes: Vec::new(),
+        materials: Vec::new(),
+        asset_uuids: BTreeMap::new(),
+    };
+    let mut mesh_indices: HashMap<String, usize> = HashMap::new();
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    for flat in ordered {
+        let mut entity = flat.entity;
+        entity.parent = flat.parent.as_ref().and_then(|parent| placed.get(parent)).copied();
+        entity.mesh = flat.mesh.map(|mesh| {
+            *mesh_indices.entry(to_ron(&mesh)).or_insert_with(|| {
+                file.meshes.push(mesh);
+                file.meshes.len() - 1
+            })
+        });
+        entity.material = flat.material.map(|material| {
+            *material_indices.entry(to_ron(&material)).or_insert_with(|| {
+                file.materials.push(material);
+                file.materials.len() - 1
+            })
+        });
+        file.entities.push(entity);
+    }
+    file
+}
+
+#[derive(Clone, Debug)]
+pub struct PropertyChange {
+    pub property: SceneProperty,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum EntityChange {
+    Added,
+    Removed,
+    Modified(Vec<PropertyChange>),
+}
+
+#[derive(Clone, Debug)]
+pub struct EntityDiff {
+    pub path: String,
+    pub change: EntityChange,
+}
+
+/// What changed from `before` to `after`; unchanged entities are left out
+pub fn diff_scenes(before: &SceneFile, after: &SceneFile) -> Vec<EntityDiff> {
+    let before = flatten(before);
+    let after = flatten(after);
+    let before_by_path: HashMap<&str, &FlatEntity> = before.iter().map(|flat| (flat.path.as_str(), flat)).collect();
+    let after_paths: HashSet<&str> = after.iter().map(|flat| flat.path.as_str()).collect();
+
+    let mut diffs = Vec::new();
+    for flat in &after {
+        let Some(old) = before_by_path.get(flat.path.as_str()) else {
+            diffs.push(EntityDiff {
+                path: flat.path.clone(),
+                change: EntityChange::Added,
+            });
+            continue;
+        };
+        let changes: Vec<PropertyChange> = SceneProperty::ALL
+            .iter()
+            .filter_map(|property| {
+                let before = property.describe(old);
+                let after = property.describe(flat);
+                (before != after).then_some(PropertyChange {
+                    property: *property,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        if !changes.is_empty() {
+            diffs.push(EntityDiff {
+                path: flat.path.clone(),
+                change: EntityChange::Modified(changes),
+            });
+        }
+    }
+    for flat in before.iter().filter(|flat| !after_paths.contains(flat.path.as_str())) {
+        diffs.push(EntityDiff {
+            path: flat.path.clone(),
+            change: EntityChange::Removed,
+        });
+    }
+    diffs
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeConflict {
+    /// Both sides changed a property, differently
+    Property {
+        path: String,
+        property: SceneProperty,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    /// One side removed an entity the other changed; resolving to the side
+    /// that kept it keeps it
+    Removed { path: String, kept_by: MergeSide },
+}
+
+/// A three-way merge of a scene and the conflicts left to resolve. Changes
+/// only one side made are taken; every conflict needs a side picked before
+/// the result is saved.
+pub struct SceneMerge {
+    base: Vec<FlatEntity>,
+    ours: Vec<FlatEntity>,
+    theirs: Vec<FlatEntity>,
+    version: u32,
+    pub conflicts: Vec<MergeConflict>,
+    pub resolutions: Vec<Option<MergeSide>>,
+    /// Changes taken from one side without a conflict
+    pub merged_changes: usize,
+}
+
+impl SceneMerge {
+    pub fn new(base: &SceneFile, ours: &SceneFile, theirs: &SceneFile) -> Self {
+        let mut merge = Self {
+            base: flatten(base),
+            ours: flatten(ours),
+            theirs: flatten(theirs),
+            version: ours.version.max(theirs.version),
+            conflicts: Vec::new(),
+            resolutions: Vec::new(),
+            merged_changes: 0,
+        };
+        let (_, conflicts, merged_changes) = merge.run();
+        merge.resolutions = vec![None; conflicts.len()];
+        merge.conflicts = conflicts;
+        merge.merged_changes = merged_changes;
+        merge
+    }
+
+    pub fn resolved(&self) -> bool {
+        self.resolutions.iter().all(Option::is_some)
+    }
+
+    /// The merged scene; conflicts not yet resolved keep our side
+    pub fn result(&self) -> SceneFile {
+        unflatten(self.version, self.run().0)
+    }
+
+    fn resolution(&self, conflict: &MergeConflict) -> Option<MergeSide> {
+        let index = self.conflicts.iter().position(|existing| existing == conflict)?;
+        self.resolutions.get(index).copied().flatten()
+    }
+
+    fn run(&self) -> (Vec<FlatEntity>, Vec<MergeConflict>, usize) {
+        let by_path = |list: &[FlatEntity]| -> HashMap<String, usize> {
+            list.iter().enumerate().map(|(index, flat)| (flat.path.clone(), index)).collect()
+        };
+        let (base_paths, our_paths, their_paths) = (by_path(&self.base), by_path(&self.ours), by_path(&self.theirs));
+
+        // Our order, then what only they have, then what only the base had
+        let mut paths: Vec<&str> = self.ours.iter().map(|flat| flat.path.as_str()).collect();
+        paths.extend(self.theirs.iter().map(|flat| flat.path.as_str()).filter(|path| !our_paths.contains_key(*path)));
+        paths.extend(
+            self.base
+                .iter()
+                .map(|flat| flat.path.as_str())
+                .filter(|path| !our_paths.contains_key(*path) && !their_paths.contains_key(*path)),
+        );
+
+        let mut merged = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut merged_changes = 0;
+        for path in paths {
+            let base = base_paths.get(path).map(|index| &self.base[*index]);
+            let ours = our_paths.get(path).map(|index| &self.ours[*index]);
+            let theirs = their_paths.get(path).map(|index| &self.theirs[*index]);
+            match (ours, theirs) {
+                (Some(ours), Some(theirs)) => {
+                    let mut entity = ours.clone();
+                    for property in SceneProperty::ALL {
+                        let our_value = property.describe(ours);
+                        let their_value = property.describe(theirs);
+                        if our_value == their_value {
+                            continue;
+                        }
+                        let base_value = base.and_then(|base| property.describe(base));
+                        if our_value == base_value {
+                            property.copy(theirs, &mut entity);
+                            merged_changes += 1;
+                        } else if their_value == base_value {
+                            merged_changes += 1;
+                        } else {
+                            let conflict = MergeConflict::Property {
+                                path: path.to_string(),
+                                property,
+                                ours: our_value,
+                                theirs: their_value,
+                            };
+                            if self.resolution(&conflict) == Some(MergeSide::Theirs) {
+                                property.copy(theirs, &mut entity);
+                            }
+                            conflicts.push(conflict);
+                        }
+                    }
+                    merged.push(entity);
+                }
+                (Some(kept), None) | (None, Some(kept)) => {
+                    let kept_by = if ours.is_some() { MergeSide::Ours } else { MergeSide::Theirs };
+                    match base {
+                        // Added on one side
+                        None => merged.push(kept.clone()),
+                        // Removed on the other, which nobody minds
+                        Some(base) if base.same_as(kept) => {}
+                        Some(_) => {
+                            let conflict = MergeConflict::Removed {
+                                path: path.to_string(),
+                                kept_by,
+                            };
+                            let side = self.resolution(&conflict).unwrap_or(MergeSide::Ours);
+                            if side == kept_by {
+                                merged.push(kept.clone());
+                            }
+                            conflicts.push(conflict);
+                            continue;
+                        }
+                    }
+                    merged_changes += 1;
+                }
+                // Removed on both sides
+                (None, None) => {}
+            }
+        }
+        (merged, conflicts, merged_changes)
+    }
+}
+
+/// A version of a scene in git's index while a merge is conflicted: 1 is
+/// the common base, 2 ours and 3 theirs
+fn read_git_stage(name: &str, stage: u8) -> anyhow::Result<SceneFile> {
+    let path = SceneFile::path(name);
+    let spec = format!(":{stage}:./{}", path.to_string_lossy().replace('\\', "/"));
+    let output = Command::new("git").args(["show", &spec]).output()?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(ron::de::from_str(&String::from_utf8_lossy(&output.stdout))?)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneDiffMode {
+    Compare,
+    Merge,
+}
+
+/// State of the Scene Diff window
+pub struct SceneDiffWindow {
+    pub mode: SceneDiffMode,
+    /// Saved scenes, listed when the window opened
+    pub scenes: Vec<String>,
+    pub before: String,
+    /// Scene compared against `before`; the scene being edited when `None`
+    pub after: Option<String>,
+    /// The older scene, waiting for the one being edited to be captured
+    pub pending_before: Option<SceneFile>,
+    pub diff: Option<Vec<EntityDiff>>,
+    /// Scene the merge is saved to, which is also our side
+    pub target: String,
+    pub base: String,
+    pub theirs: String,
+    pub merge: Option<SceneMerge>,
+    pub status: String,
+}
+
+impl SceneDiffWindow {
+    pub fn new(mode: SceneDiffMode, current_scene: Option<&str>) -> Self {
+        let scenes = SceneFile::list();
+        let current = current_scene
+            .map(str::to_string)
+            .or_else(|| scenes.first().cloned())
+            .unwrap_or_default();
+        Self {
+            mode,
+            scenes,
+            before: current.clone(),
+            after: None,
+            pending_before: None,
+            diff: None,
+            target: current.clone(),
+            base: current.clone(),
+            theirs: current,
+            merge: None,
+            status: String::new(),
+        }
+    }
+}
+
+/// Capture the scene being edited for a comparison waiting on it
+pub fn capture_working_scene_for_diff(
+    mut editor_state: ResMut<EditorState>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    root_children: Query<&Children>,
+    scene_query: Query<SceneEntityQuery>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+) {
+    let waiting = editor_state
+        .scene_diff
+        .as_ref()
+        .is_some_and(|window| window.pending_before.is_some());
+    let Some(root) = scene_root.map(|root| root.0).filter(|_| waiting) else {
+        return;
+    };
+    let (working, _) = capture_scene(root, &root_children, &scene_query, &meshes, &materials);
+    if let Some(window) = editor_state.scene_diff.as_mut() {
+        if let Some(before) = window.pending_before.take() {
+            window.diff = Some(diff_scenes(&before, &working));
+        }
+    }
+}
+
+fn scene_combo(ui: &mut egui::Ui, id: &str, scenes: &[String], selected: &mut String) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(selected.as_str())
+        .width(180.0)
+        .show_ui(ui, |ui| {
+            for scene in scenes {
+                ui.selectable_value(selected, scene.clone(), scene);
+            }
+        });
+}
+
+fn preview(value: &Option<String>) -> String {
+    match value {
+        None => "(none)".to_string(),
+        Some(value) if value.chars().count() > VALUE_PREVIEW_LENGTH => {
+            format!("{}…", value.chars().take(VALUE_PREVIEW_LENGTH).collect::<String>())
+        }
+        Some(value) => value.clone(),
+    }
+}
+
+/// A value in a list, shown whole on hover
+fn value_label(ui: &mut egui::Ui, value: &Option<String>) {
+    let response = ui.monospace(preview(value));
+    if let Some(value) = value.as_ref().filter(|value| value.chars().count() > VALUE_PREVIEW_LENGTH) {
+        response.on_hover_text(value);
+    }
+}
+
+pub fn show_scene_diff_window(
+    ctx: &egui::Context,
+    window: &mut Option<SceneDiffWindow>,
+    scene_file_events: &mut EventWriter<SceneFileEvent>,
+) {
+    let Some(state) = window.as_mut() else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new("Scene Diff")
+        .open(&mut open)
+        .default_size([560.0, 480.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut state.mode, SceneDiffMode::Compare, "Compare");
+                ui.selectable_value(&mut state.mode, SceneDiffMode::Merge, "Merge");
+            });
+            ui.separator();
+            match state.mode {
+                SceneDiffMode::Compare => draw_compare(ui, state),
+                SceneDiffMode::Merge => draw_merge(ui, state, scene_file_events),
+            }
+        });
+    if !open {
+        *window = None;
+    }
+}
+
+fn draw_compare(ui: &mut egui::Ui, state: &mut SceneDiffWindow) {
+    egui::Grid::new("scene_diff_sources").num_columns(2).show(ui, |ui| {
+        ui.label("Before:");
+        scene_combo(ui, "scene_diff_before", &state.scenes, &mut state.before);
+        ui.end_row();
+        ui.label("After:");
+        egui::ComboBox::from_id_source("scene_diff_after")
+            .selected_text(state.after.as_deref().unwrap_or("Scene being edited"))
+            .width(180.0)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.after, None, "Scene being edited");
+                for scene in &state.scenes {
+                    ui.selectable_value(&mut state.after, Some(scene.clone()), scene);
+                }
+            });
+        ui.end_row();
+    });
+    if ui.button("Compare").clicked() {
+        state.diff = None;
+        state.status.clear();
+        match SceneFile::load(&state.before) {
+            Ok(before) => match &state.after {
+                None => state.pending_before = Some(before),
+                Some(after) => match SceneFile::load(after) {
+                    Ok(after) => state.diff = Some(diff_scenes(&before, &after)),
+                    Err(err) => state.status = format!("Failed to open \"{after}\": {err}"),
+                },
+            },
+            Err(err) => state.status = format!("Failed to open \"{}\": {err}", state.before),
+        }
+    }
+    if !state.status.is_empty() {
+        ui.colored_label(REMOVED_COLOR, &state.status);
+    }
+    let Some(diff) = &state.diff else {
+        return;
+    };
+
+    ui.separator();
+    let count = |wanted: fn(&EntityChange) -> bool| diff.iter().filter(|entry| wanted(&entry.change)).count();
+    ui.horizontal(|ui| {
+        ui.colored_label(ADDED_COLOR, format!("{} added", count(|change| matches!(change, EntityChange::Added))));
+        ui.colored_label(REMOVED_COLOR, format!("{} removed", count(|change| matches!(change, EntityChange::Removed))));
+        ui.colored_label(
+            CHANGED_COLOR,
+            format!("{} modified", count(|change| matches!(change, EntityChange::Modified(_)))),
+        );
+    });
+    if diff.is_empty() {
+        ui.label("The scenes are the same.");
+        return;
+    }
+    egui::ScrollArea::vertical().id_source("scene_diff_entries").show(ui, |ui| {
+        for entry in diff {
+            match &entry.change {
+                EntityChange::Added => {
+                    ui.colored_label(ADDED_COLOR, format!("+ {}", entry.path));
+                }
+                EntityChange::Removed => {
+                    ui.colored_label(REMOVED_COLOR, format!("- {}", entry.path));
+                }
+                EntityChange::Modified(changes) => {
+                    egui::CollapsingHeader::new(egui::RichText::new(format!("~ {}", entry.path)).color(CHANGED_COLOR))
+                        .id_source(("scene_diff_entry", &entry.path))
+                        .show(ui, |ui| {
+                            egui::Grid::new(("scene_diff_changes", &entry.path)).num_columns(3).show(ui, |ui| {
+                                for change in changes {
+                                    ui.label(change.property.label());
+                                    value_label(ui, &change.before);
+                                    value_label(ui, &change.after);
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                }
+            }
+        }
+    });
+}
+
+fn draw_merge(ui: &mut egui::Ui, state: &mut SceneDiffWindow, scene_file_events: &mut EventWriter<SceneFileEvent>) {
+    egui::Grid::new("scene_merge_sources").num_columns(2).show(ui, |ui| {
+        ui.label("Scene:");
+        scene_combo(ui, "scene_merge_target", &state.scenes, &mut state.target);
+        ui.end_row();
+        ui.label("Base:");
+        scene_combo(ui, "scene_merge_base", &state.scenes, &mut state.base);
+        ui.end_row();
+        ui.label("Theirs:");
+        scene_combo(ui, "scene_merge_theirs", &state.scenes, &mut state.theirs);
+        ui.end_row();
+    });
+    ui.horizontal(|ui| {
+        if ui
+            .button("Merge Scenes")
+            .on_hover_text("Merge the changes from Base to Theirs into Scene")
+            .clicked()
+        {
+            let loaded = SceneFile::load(&state.base)
+                .and_then(|base| Ok((base, SceneFile::load(&state.target)?, SceneFile::load(&state.theirs)?)));
+            match loaded {
+                Ok((base, ours, theirs)) => {
+                    state.merge = Some(SceneMerge::new(&base, &ours, &theirs));
+                    state.status.clear();
+                }
+                Err(err) => state.status = format!("Failed to open scenes: {err}"),
+            }
+        }
+        if ui
+            .button("Load Git Conflict")
+            .on_hover_text("Merge the two sides git could not merge in Scene")
+            .clicked()
+        {
+            let loaded = read_git_stage(&state.target, 1)
+                .and_then(|base| Ok((base, read_git_stage(&state.target, 2)?, read_git_stage(&state.target, 3)?)));
+            match loaded {
+                Ok((base, ours, theirs)) => {
+                    state.merge = Some(SceneMerge::new(&base, &ours, &theirs));
+                    state.status.clear();
+                }
+                Err(err) => state.status = format!("No git conflict to load: {err}"),
+            }
+        }
+    });
+    if !state.status.is_empty() {
+        ui.label(&state.status);
+    }
+    let Some(merge) = state.merge.as_mut() else {
+        return;
+    };
+
+    ui.separator();
+    ui.label(format!(
+        "{} changes merged, {} conflicts",
+        merge.merged_changes,
+        merge.conflicts.len()
+    ));
+    if !merge.conflicts.is_empty() {
+        ui.horizontal(|ui| {
+            if ui.button("Take All Ours").clicked() {
+                merge.resolutions.fill(Some(MergeSide::Ours));
+            }
+            if ui.button("Take All Theirs").clicked() {
+                merge.resolutions.fill(Some(MergeSide::Theirs));
+            }
+        });
+        egui::ScrollArea::vertical()
+            .id_source("scene_merge_conflicts")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                for (conflict, resolution) in merge.conflicts.iter().zip(merge.resolutions.iter_mut()) {
+                    let color = if resolution.is_some() { ui.visuals().text_color() } else { CHANGED_COLOR };
+                    match conflict {
+                        MergeConflict::Property {
+                            path,
+                            property,
+                            ours,
+                            theirs,
+                        } => {
+                            ui.colored_label(color, format!("{path}: {}", property.label()));
+                            ui.horizontal(|ui| {
+                                ui.radio_value(resolution, Some(MergeSide::Ours), "Ours");
+                                value_label(ui, ours);
+                            });
+                            ui.horizontal(|ui| {
+                                ui.radio_value(resolution, Some(MergeSide::Theirs), "Theirs");
+                                value_label(ui, theirs);
+                            });
+                        }
+                        MergeConflict::Removed { path, kept_by } => {
+                            let (keep, remove) = match kept_by {
+                                MergeSide::Ours => (MergeSide::Ours, MergeSide::Theirs),
+                                MergeSide::Theirs => (MergeSide::Theirs, MergeSide::Ours),
+                            };
+                            let who = if *kept_by == MergeSide::Ours { "They" } else { "We" };
+                            ui.colored_label(color, format!("{path}: {who} removed it, the other side changed it"));
+                            ui.horizontal(|ui| {
+                                ui.radio_value(resolution, Some(keep), "Keep");
+                                ui.radio_value(resolution, Some(remove), "Remove");
+                            });
+                        }
+                    }
+                    ui.separator();
+                }
+            });
+    }
+
+    let resolved = merge.resolved();
+    let remaining = merge.resolutions.iter().filter(|resolution| resolution.is_none()).count();
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(resolved, egui::Button::new("Save Merged Scene"))
+            .on_disabled_hover_text(format!("{remaining} conflicts left to resolve"))
+            .clicked()
+        {
+            let name = state.target.clone();
+            match merge.result().save(&name) {
+                Ok(()) => {
+                    info!("Saved merged scene \"{}\"", name);
+                    state.status = format!("Saved \"{name}\"; stage it in git to finish a git merge");
+                    scene_file_events.send(SceneFileEvent::Open(name));
+                }
+                Err(err) => state.status = format!("Failed to save \"{name}\": {err}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,1515 @@
+//! Waffle Engine Scene Files
+//! Saves the scene under the `WaffleSceneRoot` to a RON file and loads it back
+
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::RenderLayers;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::asset_refs::{follow_moved_assets, referenced_asset_uuids};
+use super::sub_scene::SceneReference;
+use super::{EditorState, IsolationHidden, SpawnSource};
+use crate::core::components::EditorHidden;
+use crate::core::surface::PhysicalSurface;
+use crate::core::animation::WaffleAnimator;
+use crate::core::health::{Damageable, Health, Team};
+use crate::core::constraints::{FollowConstraint, LookAtConstraint, StickToSurfaceConstraint};
+use crate::core::state_machine::AnimationStateMachine;
+use crate::core::vehicle::RaycastVehicle;
+use crate::core::interaction::Interactable;
+use crate::rendering::particles::ParticleEmitter;
+use crate::terrain::WaffleTerrain;
+use crate::rendering::foliage::FoliageLayer;
+use crate::rendering::instancing::InstancedMesh;
+use crate::rendering::water::WaffleWater;
+use crate::rendering::lod::{LodGroup, LodState};
+use crate::core::keyframes::PropertyAnimation;
+use crate::core::events::EngineUpdateEvent;
+use crate::core::project::ProjectSettings;
+use crate::rendering::ao_volume::AoVolume;
+use crate::rendering::camera_shake::CameraShake;
+use crate::rendering::camera_rig::{CameraCrane, CameraDolly, CameraFocus, DollyTrack};
+use crate::rendering::lens_flare::{LensFlare, LensFlareElement, LensFlareShape};
+use crate::rendering::lighting::{
+    LightType, WaffleDirectionalLight, WaffleLight, WafflePointLight, WaffleSpotLight,
+};
+use crate::rendering::materials::MaterialFile;
+use crate::rendering::portal::Portal;
+use crate::rendering::scene::{EnvironmentSettings, SceneRootEntity, WaffleSceneObject};
+use crate::scripting::LuaScript;
+use crate::audio::WaffleAudioSource;
+
+/// Asset paths in scene files are relative to this
+const ASSET_DIR: &str = "assets";
+pub const SCENE_DIR: &str = "assets/scenes";
+pub const SCENE_EXTENSION: &str = "scene.ron";
+const SCENE_FORMAT_VERSION: u32 = 1;
+
+/// Models, meshes and materials loaded from assets are stored by path,
+/// generated meshes and materials inline, each once however many entities
+/// share it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    pub entities: Vec<SceneEntity>,
+    #[serde(default)]
+    pub meshes: Vec<SceneMesh>,
+    #[serde(default)]
+    pub materials: Vec<SceneMaterial>,
+    /// UUIDs of the asset files named by path, by path, which find them
+    /// again by their sidecars if they are moved outside the editor
+    #[serde(default)]
+    pub asset_uuids: BTreeMap<String, Uuid>,
+}
+
+/// One entity; parents come before their children
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneEntity {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Index into `SceneFile::entities`; `None` for children of the root
+    #[serde(default)]
+    pub parent: Option<usize>,
+    pub transform: SceneTransform,
+    #[serde(default = "visible_by_default")]
+    pub visible: bool,
+    #[serde(default)]
+    pub source: Option<SpawnSource>,
+    /// Index into `SceneFile::meshes`
+    #[serde(default)]
+    pub mesh: Option<usize>,
+    /// Index into `SceneFile::materials`
+    #[serde(default)]
+    pub material: Option<usize>,
+    /// Asset path of a model scene, e.g. `models/crate.glb#Scene0`
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub light: Option<SceneLight>,
+    #[serde(default)]
+    pub environment: Option<EnvironmentSettings>,
+    #[serde(default)]
+    pub lens_flare: Option<SceneLensFlare>,
+    #[serde(default)]
+    pub ao_volume: Option<AoVolume>,
+    #[serde(default)]
+    pub dolly_track: Option<DollyTrack>,
+    #[serde(default)]
+    pub lua_script: Option<LuaScript>,
+    #[serde(default)]
+    pub audio_source: Option<WaffleAudioSource>,
+    #[serde(default)]
+    pub surface: Option<PhysicalSurface>,
+    #[serde(default)]
+    pub animator: Option<WaffleAnimator>,
+    #[serde(default)]
+    pub state_machine: Option<AnimationStateMachine>,
+    #[serde(default)]
+    pub property_animation: Option<PropertyAnimation>,
+    #[serde(default)]
+    pub particle_emitter: Option<ParticleEmitter>,
+    #[serde(default)]
+    pub terrain: Option<WaffleTerrain>,
+    #[serde(default)]
+    pub foliage: Option<FoliageLayer>,
+    #[serde(default)]
+    pub instanced: Option<InstancedMesh>,
+    #[serde(default)]
+    pub water: Option<WaffleWater>,
+    #[serde(default)]
+    pub lod: Option<LodGroup>,
+    #[serde(default)]
+    pub scene_reference: Option<SceneReference>,
+    #[serde(default)]
+    pub look_at: Option<SceneLookAt>,
+    #[serde(default)]
+    pub follow: Option<SceneFollow>,
+    #[serde(default)]
+    pub stick_to_surface: Option<StickToSurfaceConstraint>,
+    /// Layers the entity renders on; `None` for just the default layer 0
+    #[serde(default)]
+    pub render_layers: Option<Vec<usize>>,
+    #[serde(default)]
+    pub vehicle: Option<RaycastVehicle>,
+    #[serde(default)]
+    pub health: Option<Health>,
+    #[serde(default)]
+    pub damageable: Option<Damageable>,
+    #[serde(default)]
+    pub team: Option<Team>,
+    #[serde(default)]
+    pub interactable: Option<Interactable>,
+    #[serde(default)]
+    pub crane: Option<CameraCrane>,
+    #[serde(default)]
+    pub dolly: Option<SceneCameraDolly>,
+    #[serde(default)]
+    pub focus: Option<SceneCameraFocus>,
+    #[serde(default)]
+    pub portal: Option<ScenePortal>,
+    #[serde(default)]
+    pub camera_shake: Option<SceneCameraShake>,
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl From<Transform> for SceneTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+impl From<SceneTransform> for Transform {
+    fn from(transform: SceneTransform) -> Self {
+        Transform {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneMesh {
+    Asset(String),
+    /// A generated triangle list
+    Inline {
+        positions: Vec<[f32; 3]>,
+        #[serde(default)]
+        normals: Vec<[f32; 3]>,
+        #[serde(default)]
+        uvs: Vec<[f32; 2]>,
+        #[serde(default)]
+        indices: Vec<u32>,
+    },
+}
+
+impl SceneMesh {
+    fn capture(handle: &Handle<Mesh>, meshes: &Assets<Mesh>) -> Option<Self> {
+        if let Some(path) = handle.path() {
+            return Some(SceneMesh::Asset(path.to_string()));
+        }
+        let mesh = meshes.get(handle)?;
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return None;
+        };
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => normals.clone(),
+            _ => Vec::new(),
+        };
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => uvs.clone(),
+            _ => Vec::new(),
+        };
+        let indices = mesh
+            .indices()
+            .map(|indices| indices.iter().map(|index| index as u32).collect())
+            .unwrap_or_default();
+        Some(SceneMesh::Inline {
+            positions: positions.clone(),
+            normals,
+            uvs,
+            indices,
+        })
+    }
+
+    fn load(&self, asset_server: &AssetServer, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        match self {
+            SceneMesh::Asset(path) => asset_server.load(path.clone()),
+            SceneMesh::Inline {
+                positions,
+                normals,
+                uvs,
+                indices,
+            } => {
+                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+                    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+                if !normals.is_empty() {
+                    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals.clone());
+                }
+                if !uvs.is_empty() {
+                    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs.clone());
+                }
+                if !indices.is_empty() {
+                    mesh.insert_indices(Indices::U32(indices.clone()));
+                }
+                meshes.add(mesh)
+            }
+        }
+    }
+}
+
+/// `LookAtConstraint` with its target as an index into `SceneFile::entities`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneLookAt {
+    #[serde(default)]
+    pub target: Option<usize>,
+    pub up: Vec3,
+}
+
+/// `FollowConstraint` with its target as an index into `SceneFile::entities`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFollow {
+    #[serde(default)]
+    pub target: Option<usize>,
+    pub offset: Vec3,
+    pub lag: f32,
+}
+
+/// `CameraDolly` with its track as an index into `SceneFile::entities`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCameraDolly {
+    #[serde(default)]
+    pub track: Option<usize>,
+    pub position: f32,
+    pub speed: f32,
+    pub looping: bool,
+}
+
+/// `CameraFocus` with its target as an index into `SceneFile::entities`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCameraFocus {
+    #[serde(default)]
+    pub target: Option<usize>,
+    pub offset: Vec3,
+    pub damping: f32,
+    pub up: Vec3,
+}
+
+/// `Portal` with the portal it looks out of as an index into `SceneFile::entities`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenePortal {
+    #[serde(default)]
+    pub linked: Option<usize>,
+    pub mirror: bool,
+    pub size: Vec2,
+    pub resolution: UVec2,
+    pub recursion: u32,
+    pub fallback: Color,
+}
+
+/// A `CameraShake`'s settings; trauma and noise start over on load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCameraShake {
+    pub decay: f32,
+    pub trauma_exponent: f32,
+    pub frequency: f32,
+    pub max_translation: Vec3,
+    pub max_rotation: Vec3,
+    pub seed: u64,
+}
+
+impl From<&CameraShake> for SceneCameraShake {
+    fn from(shake: &CameraShake) -> Self {
+        Self {
+            decay: shake.decay,
+            trauma_exponent: shake.trauma_exponent,
+            frequency: shake.frequency,
+            max_translation: shake.max_translation,
+            max_rotation: shake.max_rotation,
+            seed: shake.seed,
+        }
+    }
+}
+
+impl SceneCameraShake {
+    fn to_camera_shake(&self) -> CameraShake {
+        let mut shake = CameraShake::new(self.seed);
+        shake.decay = self.decay;
+        shake.trauma_exponent = self.trauma_exponent;
+        shake.frequency = self.frequency;
+        shake.max_translation = self.max_translation;
+        shake.max_rotation = self.max_rotation;
+        shake
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneMaterial {
+    Asset(String),
+    Inline(MaterialFile),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneLight {
+    Directional {
+        color: Color,
+        illuminance: f32,
+        shadows_enabled: bool,
+    },
+    Point {
+        color: Color,
+        intensity: f32,
+        range: f32,
+        radius: f32,
+        shadows_enabled: bool,
+    },
+    Spot {
+        color: Color,
+        intensity: f32,
+        range: f32,
+        radius: f32,
+        shadows_enabled: bool,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneLensFlare {
+    pub enabled: bool,
+    pub intensity: f32,
+    pub fade_speed: f32,
+    pub elements: Vec<SceneLensFlareElement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneLensFlareElement {
+    pub shape: LensFlareShape,
+    #[serde(default)]
+    pub texture: Option<String>,
+    pub position: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+impl From<&LensFlare> for SceneLensFlare {
+    fn from(flare: &LensFlare) -> Self {
+        Self {
+            enabled: flare.enabled,
+            intensity: flare.intensity,
+            fade_speed: flare.fade_speed,
+            elements: flare
+                .elements
+                .iter()
+                .map(|element| SceneLensFlareElement {
+                    shape: element.shape,
+                    texture: element
+                        .texture
+                        .as_ref()
+                        .and_then(|texture| texture.path())
+                        .map(|path| path.to_string()),
+                    position: element.position,
+                    size: element.size,
+                    color: element.color,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SceneLensFlare {
+    fn to_lens_flare(&self, asset_server: &AssetServer) -> LensFlare {
+        LensFlare {
+            enabled: self.enabled,
+            intensity: self.intensity,
+            fade_speed: self.fade_speed,
+            elements: self
+                .elements
+                .iter()
+                .map(|element| LensFlareElement {
+                    shape: element.shape,
+                    texture: element.texture.as_ref().map(|path| asset_server.load(path.clone())),
+                    position: element.position,
+                    size: element.size,
+                    color: element.color,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SceneFile {
+    /// File of a scene by name, e.g. `assets/scenes/level1.scene.ron`
+    pub fn path(name: &str) -> PathBuf {
+        PathBuf::from(SCENE_DIR).join(format!("{name}.{SCENE_EXTENSION}"))
+    }
+
+    /// Names of the saved scenes, sorted
+    pub fn list() -> Vec<String> {
+        let suffix = format!(".{SCENE_EXTENSION}");
+        let mut names: Vec<String> = std::fs::read_dir(SCENE_DIR)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                file_name.strip_suffix(&suffix).map(str::to_string)
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let mut data = std::fs::read_to_string(Self::path(name))?;
+        let mut file: SceneFile = ron::de::from_str(&data)?;
+        if file.version > SCENE_FORMAT_VERSION {
+            anyhow::bail!("scene format version {} is newer than this editor supports", file.version);
+        }
+        let moves = follow_moved_assets(Path::new(ASSET_DIR), &mut data, &file.asset_uuids);
+        if !moves.is_empty() {
+            for (from, to) in &moves {
+                info!("{} was moved to {}", from, to);
+            }
+            file = ron::de::from_str(&data)?;
+        }
+        Ok(file)
+    }
+
+    /// Write the scene with the UUIDs of the assets it names
+    pub fn save(&mut self, name: &str) -> anyhow::Result<()> {
+        std::fs::create_dir_all(SCENE_DIR)?;
+        self.asset_uuids.clear();
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        self.asset_uuids = referenced_asset_uuids(Path::new(ASSET_DIR), &data);
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(Self::path(name), data)?;
+        Ok(())
+    }
+}
+
+/// Save the current scene under a name, or replace it with a saved one
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum SceneFileEvent {
+    Save(String),
+    Open(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneFileAction {
+    Open,
+    Save,
+}
+
+/// State of the Open Scene / Save Scene As dialog
+#[derive(Debug, Clone)]
+pub struct SceneFileDialog {
+    pub action: SceneFileAction,
+    pub name: String,
+    /// Saved scenes, listed when the dialog opened
+    pub scenes: Vec<String>,
+}
+
+impl SceneFileDialog {
+    pub fn new(action: SceneFileAction, name: Option<&str>) -> Self {
+        Self {
+            action,
+            name: name.unwrap_or_default().to_string(),
+            scenes: SceneFile::list(),
+        }
+    }
+}
+
+pub fn show_scene_file_dialog(
+    ctx: &egui::Context,
+    dialog: &mut Option<SceneFileDialog>,
+    events: &mut EventWriter<SceneFileEvent>,
+) {
+    let Some(state) = dialog.as_mut() else {
+        return;
+    };
+    let title = match state.action {
+        SceneFileAction::Open => "Open Scene",
+        SceneFileAction::Save => "Save Scene As",
+    };
+    let mut keep_open = true;
+    egui::Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            if state.scenes.is_empty() {
+                ui.label(format!("No scenes in {}.", SCENE_DIR));
+            } else {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for scene in &state.scenes {
+                        if ui.selectable_label(state.name == *scene, scene).clicked() {
+                            state.name = scene.clone();
+                        }
+                    }
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Scene Name:");
+                ui.add(egui::TextEdit::singleline(&mut state.name).desired_width(220.0));
+            });
+
+            let name = state.name.trim().to_string();
+            let valid = !name.is_empty() && !name.contains(['/', '\\']);
+            let exists = state.scenes.contains(&name);
+            if state.action == SceneFileAction::Save && exists {
+                ui.label(
+                    egui::RichText::new("A scene with this name will be replaced.")
+                        .color(egui::Color32::from_rgb(230, 160, 60)),
+                );
+            }
+            ui.horizontal(|ui| {
+                let (label, enabled) = match state.action {
+                    SceneFileAction::Open => ("Open", valid && exists),
+                    SceneFileAction::Save => ("Save", valid),
+                };
+                if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                    events.send(match state.action {
+                        SceneFileAction::Open => SceneFileEvent::Open(name.clone()),
+                        SceneFileAction::Save => SceneFileEvent::Save(name.clone()),
+                    });
+                    keep_open = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    keep_open = false;
+                }
+            });
+        });
+    if !keep_open {
+        *dialog = None;
+    }
+}
+
+#[derive(QueryData)]
+pub struct SceneEntityQuery {
+    name: Option<&'static Name>,
+    transform: &'static Transform,
+    visibility: Option<&'static Visibility>,
+    isolation_hidden: Option<&'static IsolationHidden>,
+    children: Option<&'static Children>,
+    source: Option<&'static SpawnSource>,
+    mesh: Option<&'static Handle<Mesh>>,
+    material: Option<&'static Handle<StandardMaterial>>,
+    model: Option<&'static Handle<Scene>>,
+    directional_light: Option<&'static DirectionalLight>,
+    point_light: Option<&'static PointLight>,
+    spot_light: Option<&'static SpotLight>,
+    environment: Option<&'static EnvironmentSettings>,
+    lens_flare: Option<&'static LensFlare>,
+    ao_volume: Option<&'static AoVolume>,
+    dolly_track: Option<&'static DollyTrack>,
+    lua_script: Option<&'static LuaScript>,
+    audio_source: Option<&'static WaffleAudioSource>,
+    surface: Option<&'static PhysicalSurface>,
+    animator: Option<&'static WaffleAnimator>,
+    state_machine: Option<&'static AnimationStateMachine>,
+    property_animation: Option<&'static PropertyAnimation>,
+    particle_emitter: Option<&'static ParticleEmitter>,
+    terrain: Option<&'static WaffleTerrain>,
+    foliage: Option<&'static FoliageLayer>,
+    instanced: Option<&'static InstancedMesh>,
+    water: Option<&'static WaffleWater>,
+    lod: Option<&'static LodGroup>,
+    lod_state: Option<&'static LodState>,
+    scene_reference: Option<&'static SceneReference>,
+    look_at: Option<&'static LookAtConstraint>,
+    follow: Option<&'static FollowConstraint>,
+    stick_to_surface: Option<&'static StickToSurfaceConstraint>,
+    render_layers: Option<&'static RenderLayers>,
+    vehicle: Option<&'static RaycastVehicle>,
+    health: Option<&'static Health>,
+    damageable: Option<&'static Damageable>,
+    team: Option<&'static Team>,
+    interactable: Option<&'static Interactable>,
+    crane: Option<&'static CameraCrane>,
+    dolly: Option<&'static CameraDolly>,
+    focus: Option<&'static CameraFocus>,
+    portal: Option<&'static Portal>,
+    camera_shake: Option<&'static CameraShake>,
+    hidden: Has<EditorHidden>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_scene_file_events(
+    mut commands: Commands,
+    mut events: EventReader<SceneFileEvent>,
+    mut editor_state: ResMut<EditorState>,
+    mut engine_events: EventWriter<EngineUpdateEvent>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    root_children: Query<&Children>,
+    scene_query: Query<SceneEntityQuery>,
+) {
+    let Some(root) = scene_root.map(|root| root.0) else {
+        return;
+    };
+    for event in events.read() {
+        match event {
+            SceneFileEvent::Save(name) => {
+                let (mut file, _) = capture_scene(root, &root_children, &scene_query, &meshes, &materials);
+                match file.save(name) {
+                    Ok(()) => {
+                        info!("Saved scene \"{}\" with {} entities", name, file.entities.len());
+                        engine_events.send(EngineUpdateEvent::SceneLoaded(name.clone()));
+                    }
+                    Err(err) => error!("Failed to save scene \"{}\": {}", name, err),
+                }
+            }
+            SceneFileEvent::Open(name) => {
+                let file = match SceneFile::load(name) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        error!("Failed to open scene \"{}\": {}", name, err);
+                        continue;
+                    }
+                };
+                // Keep editor-only children such as the sky dome
+                for child in root_children.get(root).into_iter().flatten() {
+                    if scene_query.get(*child).is_ok_and(|item| !item.hidden) {
+                        commands.entity(*child).despawn_recursive();
+                    }
+                }
+                spawn_scene(&mut commands, root, &file, &asset_server, &mut meshes, &mut materials);
+                editor_state.selection.clear();
+                editor_state.delete_confirm.clear();
+                info!("Opened scene \"{}\" with {} entities", name, file.entities.len());
+                engine_events.send(EngineUpdateEvent::SceneLoaded(name.clone()));
+            }
+        }
+    }
+}
+
+/// Open the project's startup scene once the scene root exists
+pub fn open_startup_scene(
+    project_settings: Res<ProjectSettings>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    mut events: EventWriter<SceneFileEvent>,
+    mut opened: Local<bool>,
+) {
+    if *opened || scene_root.is_none() {
+        return;
+    }
+    *opened = true;
+    if !project_settings.startup_scene.is_empty() {
+        events.send(SceneFileEvent::Open(project_settings.startup_scene.clone()));
+    }
+}
+
+/// The scene under `root`, with the entity each scene entity was captured from
+pub(super) fn capture_scene(
+    root: Entity,
+    children_query: &Query<&Children>,
+    scene_query: &Query<SceneEntityQuery>,
+    meshes: &Assets<Mesh>,
+    materials: &Assets<StandardMaterial>,
+) -> (SceneFile, Vec<Entity>) {
+    let top_level: Vec<Entity> = children_query.get(root).into_iter().flatten().copied().collect();
+    capture_entities(&top_level, scene_query, meshes, materials)
+}
+
+/// `entity` and everything under it, as a scene file whose first entity is
+/// `entity` itself
+pub(super) fn capture_subtree(
+    entity: Entity,
+    scene_query: &Query<SceneEntityQuery>,
+    meshes: &Assets<Mesh>,
+    materials: &Assets<StandardMaterial>,
+) -> SceneFile {
+    capture_entities(&[entity], scene_query, meshes, materials).0
+}
+
+fn capture_entities(
+    top_level: &[Entity],
+    scene_query: &Query<SceneEntityQuery>,
+    meshes: &Assets<Mesh>,
+    materials: &Assets<StandardMaterial>,
+) -> (SceneFile, Vec<Entity>) {
+    let mut captured = Vec::new();
+    let mut file = SceneFile {
+        version: SCENE_FORMAT_VERSION,
+        entities: Vec::new(),
+        meshes: Vec::new(),
+        materials: Vec::new(),
+        asset_uuids: BTreeMap::new(),
+    };
+    let mut mesh_indices: HashMap<AssetId<Mesh>, usize> = HashMap::new();
+    let mut material_indices: HashMap<AssetId<StandardMaterial>, usize> = HashMap::new();
+
+    // Depth first, so parents are written before their children
+    let mut stack: Vec<(Entity, Option<usize>)> = top_level.iter().rev().map(|entity| (*entity, None)).collect();
+    while let Some((entity, parent)) = stack.pop() {
+        let Ok(item) = scene_query.get(entity) else {
+            continue;
+        };
+        if item.hidden {
+            continue;
+        }
+
+        // A LOD group's entity is saved with its own mesh, not the level
+        // drawn at the moment
+        let mesh = item.lod_state.map(LodState::base).or(item.mesh).and_then(|handle| {
+            if let Some(index) = mesh_indices.get(&handle.id()) {
+                return Some(*index);
+            }
+            let mesh = SceneMesh::capture(handle, meshes)?;
+            file.meshes.push(mesh);
+            mesh_indices.insert(handle.id(), file.meshes.len() - 1);
+            Some(file.meshes.len() - 1)
+        });
+        let material = item.material.and_then(|handle| {
+            if let Some(index) = material_indices.get(&handle.id()) {
+                return Some(*index);
+            }
+            let material = match handle.path() {
+                Some(path) => SceneMaterial::Asset(path.to_string()),
+                None => SceneMaterial::Inline(MaterialFile::from(materials.get(handle)?)),
+            };
+            file.materials.push(material);
+            material_indices.insert(handle.id(), file.materials.len() - 1);
+            Some(file.materials.len() - 1)
+        });
+        let light = if let Some(light) = item.directional_light {
+            Some(SceneLight::Directional {
+                color: light.color,
+                illuminance: light.illuminance,
+                shadows_enabled: light.shadows_enabled,
+            })
+        } else if let Some(light) = item.point_light {
+            Some(SceneLight::Point {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                radius: light.radius,
+                shadows_enabled: light.shadows_enabled,
+            })
+        } else {
+            item.spot_light.map(|light| SceneLight::Spot {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                radius: light.radius,
+                shadows_enabled: light.shadows_enabled,
+                inner_angle: light.inner_angle,
+                outer_angle: light.outer_angle,
+            })
+        };
+        let model = item.model.and_then(|handle| handle.path()).map(|path| path.to_string());
+
+        // Selection isolation only hides entities in the editor
+        let visibility = item.isolation_hidden.map(|hidden| &hidden.0).or(item.visibility);
+
+        let index = file.entities.len();
+        captured.push(entity);
+        file.entities.push(SceneEntity {
+            name: item.name.map(|name| name.as_str().to_string()),
+            parent,
+            transform: (*item.transform).into(),
+            visible: visibility.is_none_or(|visibility| *visibility != Visibility::Hidden),
+            source: item.source.cloned(),
+            mesh,
+            material,
+            model: model.clone(),
+            light,
+            environment: item.environment.cloned(),
+            lens_flare: item.lens_flare.map(SceneLensFlare::from),
+            ao_volume: item.ao_volume.cloned(),
+            dolly_track: item.dolly_track.cloned(),
+            lua_script: item.lua_script.cloned(),
+            audio_source: item.audio_source.cloned(),
+            surface: item.surface.copied(),
+            animator: item.animator.cloned(),
+            state_machine: item.state_machine.cloned(),
+            property_animation: item.property_animation.cloned(),
+            particle_emitter: item.particle_emitter.cloned(),
+            terrain: item.terrain.cloned(),
+            foliage: item.foliage.cloned(),
+            instanced: item.instanced.copied(),
+            water: item.water.cloned(),
+            lod: item.lod.cloned(),
+            scene_reference: item.scene_reference.cloned(),
+            look_at: None,
+            follow: None,
+            stick_to_surface: item.stick_to_surface.cloned(),
+            render_layers: item.render_layers.map(|layers| layers.iter().collect()),
+            vehicle: item.vehicle.cloned(),
+            health: item.health.cloned(),
+            damageable: item.damageable.cloned(),
+            team: item.team.copied(),
+            interactable: item.interactable.cloned(),
+            crane: item.crane.cloned(),
+            dolly: None,
+            focus: None,
+            portal: None,
+            camera_shake: item.camera_shake.map(SceneCameraShake::from),
+        });
+
+        // A model's or sub-scene's children are spawned from it again on load
+        let respawned = model.is_some() || item.scene_reference.is_some();
+        if let Some(children) = item.children.filter(|_| !respawned) {
+            stack.extend(children.iter().rev().map(|child| (*child, Some(index))));
+        }
+    }
+
+    // Targets are stored as indices, known once every entity has one
+    let indices: HashMap<Entity, usize> = captured.iter().enumerate().map(|(index, entity)| (*entity, index)).collect();
+    let target_index = |target: Option<Entity>| target.and_then(|target| indices.get(&target).copied());
+    for (scene_entity, entity) in file.entities.iter_mut().zip(&captured) {
+        let Ok(item) = scene_query.get(*entity) else {
+            continue;
+        };
+        scene_entity.look_at = item.look_at.map(|constraint| SceneLookAt {
+            target: target_index(constraint.target),
+            up: constraint.up,
+        });
+        scene_entity.follow = item.follow.map(|constraint| SceneFollow {
+            target: target_index(constraint.target),
+            offset: constraint.offset,
+            lag: constraint.lag,
+        });
+        scene_entity.dolly = item.dolly.map(|dolly| SceneCameraDolly {
+            track: target_index(dolly.track),
+            position: dolly.position,
+            speed: dolly.speed,
+            looping: dolly.looping,
+        });
+        scene_entity.focus = item.focus.map(|focus| SceneCameraFocus {
+            target: target_index(focus.target),
+            offset: focus.offset,
+            damping: focus.damping,
+            up: focus.up,
+        });
+        scene_entity.portal = item.portal.map(|portal| ScenePortal {
+            linked: target_index(portal.linked),
+            mirror: portal.mirror,
+            size: portal.size,
+            resolution: portal.resolution,
+            recursion: portal.recursion,
+            fallback: portal.fallback,
+        });
+    }
+    (file, captured)
+}
+
+/// Spawn a scene file's entities under `root`; returns them in file order
+pub(super) fn spawn_scene(
+    commands: &mut Commands,
+    root: Entity,
+    file: &SceneFile,
+    asset_server: &AssetServer,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) -> Vec<Entity> {
+    let mesh_handles: Vec<Handle<Mesh>> = file.meshes.iter().map(|mesh| mesh.load(asset_server, meshes)).collect();
+    let material_handles: Vec<Handle<StandardMaterial>> = file
+        .materials
+        .iter()
+        .map(|material| match material {
+            SceneMaterial::Asset(path) => asset_server.load(path.clone()),
+            SceneMaterial::Inline(data) => materials.add(data.to_material(|path| asset_server.load(path.to_string()))),
+        })
+        .collect();
+
+    let mut spawned: Vec<Entity> = Vec::with_capacity(file.entities.len());
+    for entity in &file.entities {
+        let mesh = entity.mesh.and_then(|index| mesh_handles.get(index)).cloned();
+        let material = entity.material.and_then(|index| material_handles.get(index)).cloned();
+        let mut entity_commands = spawn_scene_entity(commands, entity, mesh, material, asset_server);
+        let parent = entity
+            .parent
+            .and_then(|index| spawned.get(index).copied())
+            .unwrap_or(root);
+        entity_commands.set_parent(parent);
+        spawned.push(entity_commands.id());
+    }
+    link_scene_entities(commands, file, &spawned);
+    spawned
+}
+
+/// Insert the components that point at other entities of `file`, once all
+/// of them exist as `entities`, in file order
+pub(super) fn link_scene_entities(commands: &mut Commands, file: &SceneFile, entities: &[Entity]) {
+    let target = |index: Option<usize>| index.and_then(|index| entities.get(index).copied());
+    for (entity, spawned) in file.entities.iter().zip(entities) {
+        let mut entity_commands = commands.entity(*spawned);
+        match &entity.look_at {
+            Some(look_at) => entity_commands.insert(LookAtConstraint {
+                target: target(look_at.target),
+                up: look_at.up,
+            }),
+            None => entity_commands.remove::<LookAtConstraint>(),
+        };
+        match &entity.follow {
+            Some(follow) => entity_commands.insert(FollowConstraint {
+                target: target(follow.target),
+                offset: follow.offset,
+                lag: follow.lag,
+            }),
+            None => entity_commands.remove::<FollowConstraint>(),
+        };
+        match &entity.dolly {
+            Some(dolly) => entity_commands.insert(CameraDolly {
+                track: target(dolly.track),
+                position: dolly.position,
+                speed: dolly.speed,
+                looping: dolly.looping,
+            }),
+            None => entity_commands.remove::<CameraDolly>(),
+        };
+        match &entity.focus {
+            Some(focus) => entity_commands.insert(CameraFocus {
+                target: target(focus.target),
+                offset: focus.offset,
+                damping: focus.damping,
+                up: focus.up,
+            }),
+            None => entity_commands.remove::<CameraFocus>(),
+        };
+        match &entity.portal {
+            Some(portal) => entity_commands.insert(Portal {
+                linked: target(portal.linked),
+                mirror: portal.mirror,
+                size: portal.size,
+                resolution: portal.resolution,
+                recursion: portal.recursion,
+                fallback: portal.fallback,
+            }),
+            None => entity_commands.remove::<Portal>(),
+        };
+    }
+}
+
+/// Spawn one entity of a scene file; the caller parents it
+pub(super) fn spawn_scene_entity<'a>(
+    commands: &'a mut Commands,
+    entity: &SceneEntity,
+    mesh: Option<Handle<Mesh>>,
+    material: Option<Handle<StandardMaterial>>,
+    asset_server: &AssetServer,
+) -> EntityCommands<'a> {
+    let transform = Transform::from(entity.transform);
+    let visibility = if entity.visible { Visibility::Inherited } else { Visibility::Hidden };
+    let mut entity_commands = commands.spawn((
+        WaffleSceneObject,
+        SpatialBundle {
+            transform,
+            visibility,
+            ..default()
+        },
+    ));
+
+    if let Some(mesh) = mesh {
+        entity_commands.insert(mesh);
+    }
+    if let Some(material) = material {
+        entity_commands.insert(material);
+    }
+    if let Some(model) = &entity.model {
+        entity_commands.insert(SceneBundle {
+            scene: asset_server.load(model.clone()),
+            transform,
+            visibility,
+            ..default()
+        });
+    }
+    insert_scene_components(&mut entity_commands, entity, asset_server);
+    entity_commands
+}
+
+/// Put an existing entity back to how a scene file describes it. Its mesh,
+/// material and model are left alone.
+pub(super) fn restore_scene_entity(entity_commands: &mut EntityCommands, entity: &SceneEntity, asset_server: &AssetServer) {
+    let visibility = if entity.visible { Visibility::Inherited } else { Visibility::Hidden };
+    entity_commands.insert((Transform::from(entity.transform), visibility));
+    if entity.name.is_none() {
+        entity_commands.remove::<Name>();
+    }
+    if entity.environment.is_none() {
+        entity_commands.remove::<EnvironmentSettings>();
+    }
+    if entity.lens_flare.is_none() {
+        entity_commands.remove::<LensFlare>();
+    }
+    if entity.ao_volume.is_none() {
+        entity_commands.remove::<AoVolume>();
+    }
+    if entity.dolly_track.is_none() {
+        entity_commands.remove::<DollyTrack>();
+    }
+    if entity.lua_script.is_none() {
+        entity_commands.remove::<LuaScript>();
+    }
+    if entity.audio_source.is_none() {
+        entity_commands.remove::<WaffleAudioSource>();
+    }
+    if entity.surface.is_none() {
+        entity_commands.remove::<PhysicalSurface>();
+    }
+    if entity.animator.is_none() {
+        entity_commands.remove::<WaffleAnimator>();
+    }
+    if entity.state_machine.is_none() {
+        entity_commands.remove::<AnimationStateMachine>();
+    }
+    if entity.property_animation.is_none() {
+        entity_commands.remove::<PropertyAnimation>();
+    }
+    if entity.particle_emitter.is_none() {
+        entity_commands.remove::<ParticleEmitter>();
+    }
+    if entity.terrain.is_none() {
+        entity_commands.remove::<WaffleTerrain>();
+    }
+    if entity.foliage.is_none() {
+        entity_commands.remove::<FoliageLayer>();
+    }
+    if entity.instanced.is_none() {
+        entity_commands.remove::<InstancedMesh>();
+    }
+    if entity.water.is_none() {
+        entity_commands.remove::<WaffleWater>();
+    }
+    if entity.lod.is_none() {
+        entity_commands.remove::<LodGroup>();
+    }
+    if entity.scene_reference.is_none() {
+        entity_commands.remove::<SceneReference>();
+    }
+    if entity.stick_to_surface.is_none() {
+        entity_commands.remove::<StickToSurfaceConstraint>();
+    }
+    if entity.render_layers.is_none() {
+        entity_commands.remove::<RenderLayers>();
+    }
+    if entity.vehicle.is_none() {
+        entity_commands.remove::<RaycastVehicle>();
+    }
+    if entity.health.is_none() {
+        entity_commands.remove::<Health>();
+    }
+    if entity.damageable.is_none() {
+        entity_commands.remove::<Damageable>();
+    }
+    if entity.team.is_none() {
+        entity_commands.remove::<Team>();
+    }
+    if entity.interactable.is_none() {
+        entity_commands.remove::<Interactable>();
+    }
+    if entity.crane.is_none() {
+        entity_commands.remove::<CameraCrane>();
+    }
+    if entity.camera_shake.is_none() {
+        entity_commands.remove::<CameraShake>();
+    }
+    insert_scene_components(entity_commands, entity, asset_server);
+}
+
+fn insert_scene_components(entity_commands: &mut EntityCommands, entity: &SceneEntity, asset_server: &AssetServer) {
+    let transform = Transform::from(entity.transform);
+    let visibility = if entity.visible { Visibility::Inherited } else { Visibility::Hidden };
+
+    if let Some(name) = &entity.name {
+        entity_commands.insert(Name::new(name.clone()));
+    }
+    if let Some(source) = &entity.source {
+        entity_commands.insert(source.clone());
+    }
+    if let Some(environment) = &entity.environment {
+        entity_commands.insert(environment.clone());
+    }
+    if let Some(flare) = &entity.lens_flare {
+        entity_commands.insert(flare.to_lens_flare(asset_server));
+    }
+    if let Some(volume) = &entity.ao_volume {
+        entity_commands.insert(volume.clone());
+    }
+    if let Some(track) = &entity.dolly_track {
+        entity_commands.insert(track.clone());
+    }
+    if let Some(script) = &entity.lua_script {
+        entity_commands.insert(script.clone());
+    }
+    if let Some(source) = &entity.audio_source {
+        entity_commands.insert(source.clone());
+    }
+    if let Some(surface) = entity.surface {
+        entity_commands.insert(surface);
+    }
+    if let Some(animator) = &entity.animator {
+        entity_commands.insert(animator.clone());
+    }
+    if let Some(machine) = &entity.state_machine {
+        entity_commands.insert(machine.clone());
+    }
+    if let Some(animation) = &entity.property_animation {
+        entity_commands.insert(animation.clone());
+    }
+    if let Some(emitter) = &entity.particle_emitter {
+        entity_commands.insert(emitter.clone());
+    }
+    if let Some(terrain) = &entity.terrain {
+        entity_commands.insert(terrain.clone());
+    }
+    if let Some(foliage) = &entity.foliage {
+        entity_commands.insert(foliage.clone());
+    }
+    if let Some(water) = &entity.water {
+        entity_commands.insert(water.clone());
+    }
+    if let Some(lod) = &entity.lod {
+        entity_commands.insert(lod.clone());
+    }
+    if let Some(instanced) = entity.instanced {
+        entity_commands.insert(instanced);
+    }
+    if let Some(reference) = &entity.scene_reference {
+        entity_commands.insert(reference.clone());
+    }
+    if let Some(constraint) = &entity.stick_to_surface {
+        entity_commands.insert(constraint.clone());
+    }
+    if let Some(layers) = &entity.render_layers {
+        entity_commands.insert(RenderLayers::from_layers(layers));
+    }
+    if let Some(vehicle) = &entity.vehicle {
+        entity_commands.insert(vehicle.clone());
+    }
+    if let Some(health) = &entity.health {
+        entity_commands.insert(health.clone());
+    }
+    if let Some(damageable) = &entity.damageable {
+        entity_commands.insert(damageable.clone());
+    }
+    if let Some(team) = entity.team {
+        entity_commands.insert(team);
+    }
+    if let Some(interactable) = &entity.interactable {
+        entity_commands.insert(interactable.clone());
+    }
+    if let Some(crane) = &entity.crane {
+        entity_commands.insert(crane.clone());
+    }
+    if let Some(shake) = &entity.camera_shake {
+        entity_commands.insert(shake.to_camera_shake());
+    }
+    match entity.light.clone() {
+        Some(SceneLight::Directional {
+            color,
+            illuminance,
+            shadows_enabled,
+        }) => {
+            entity_commands.insert((
+                WaffleLight {
+                    light_type: LightType::Directional,
+                    intensity: illuminance,
+                    color,
+                    range: 100.0,
+                    shadows_enabled,
+                },
+                WaffleDirectionalLight,
+                DirectionalLightBundle {
+                    directional_light: DirectionalLight {
+                        color,
+                        illuminance,
+                        shadows_enabled,
+                        ..default()
+                    },
+                    transform,
+                    visibility,
+                    ..default()
+                },
+            ));
+        }
+        Some(SceneLight::Point {
+            color,
+            intensity,
+            range,
+            radius,
+            shadows_enabled,
+        }) => {
+            entity_commands.insert((
+                WaffleLight {
+                    light_type: LightType::Point,
+                    intensity,
+                    color,
+                    range,
+                    shadows_enabled,
+                },
+                WafflePointLight,
+                PointLightBundle {
+                    point_light: PointLight {
+                        color,
+                        intensity,
+                        range,
+                        radius,
+                        shadows_enabled,
+                        ..default()
+                    },
+                    transform,
+                    visibility,
+                    ..default()
+                },
+            ));
+        }
+        Some(SceneLight::Spot {
+            color,
+            intensity,
+            range,
+            radius,
+            shadows_enabled,
+            inner_angle,
+            outer_angle,
+        }) => {
+            entity_commands.insert((
+                WaffleLight {
+                    light_type: LightType::Spot,
+                    intensity,
+                    color,
+                    range,
+                    shadows_enabled,
+                },
+                WaffleSpotLight,
+                SpotLightBundle {
+                    spot_light: SpotLight {
+                        color,
+                        intensity,
+                        range,
+                        radius,
+                        shadows_enabled,
+                        inner_angle,
+                        outer_angle,
+                        ..default()
+                    },
+                    transform,
+                    visibility,
+                    ..default()
+                },
+            ));
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_asset::<Scene>();
+        app
+    }
+
+    fn capture(world: &mut World, root: Entity) -> SceneFile {
+        world.run_system_once_with(
+            root,
+            |In(root): In<Entity>,
+             children: Query<&Children>,
+             scene_query: Query<SceneEntityQuery>,
+             meshes: Res<Assets<Mesh>>,
+             materials: Res<Assets<StandardMaterial>>| {
+                capture_scene(root, &children, &scene_query, &meshes, &materials).0
+            },
+        )
+    }
+
+    /// Save `file` to RON, load it back and spawn it under a new root
+    fn reload(world: &mut World, file: &SceneFile) -> (Entity, Vec<Entity>) {
+        let text = ron::to_string(file).expect("scene serializes");
+        let file: SceneFile = ron::from_str(&text).expect("scene deserializes");
+        world.run_system_once_with(
+            file,
+            |In(file): In<SceneFile>,
+             mut commands: Commands,
+             asset_server: Res<AssetServer>,
+             mut meshes: ResMut<Assets<Mesh>>,
+             mut materials: ResMut<Assets<StandardMaterial>>| {
+                let root = commands.spawn(SpatialBundle::default()).id();
+                let spawned = spawn_scene(&mut commands, root, &file, &asset_server, &mut meshes, &mut materials);
+                (root, spawned)
+            },
+        )
+    }
+
+    #[test]
+    fn constraints_round_trip() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let root = world.spawn(SpatialBundle::default()).id();
+        let target = world.spawn((Name::new("Target"), SpatialBundle::default())).set_parent(root).id();
+        world.spawn((
+            Name::new("Looker"),
+            SpatialBundle::default(),
+            LookAtConstraint {
+                target: Some(target),
+                up: Vec3::Z,
+            },
+            FollowConstraint {
+                target: Some(target),
+                offset: Vec3::new(1.0, 2.0, 3.0),
+                lag: 0.25,
+            },
+            StickToSurfaceConstraint {
+                height: 0.5,
+                max_distance: 20.0,
+                align_to_normal: true,
+            },
+        )).set_parent(root);
+
+        let file = capture(world, root);
+        let (new_root, spawned) = reload(world, &file);
+        assert_eq!(spawned.len(), 2);
+        let (new_target, looker) = (spawned[0], spawned[1]);
+
+        let look_at = world.get::<LookAtConstraint>(looker).expect("look at constraint restored");
+        assert_eq!(look_at.target, Some(new_target));
+        assert_eq!(look_at.up, Vec3::Z);
+        let follow = world.get::<FollowConstraint>(looker).expect("follow constraint restored");
+        assert_eq!(follow.target, Some(new_target));
+        assert_eq!(follow.offset, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(follow.lag, 0.25);
+        assert_eq!(
+            world.get::<StickToSurfaceConstraint>(looker),
+            Some(&StickToSurfaceConstraint {
+                height: 0.5,
+                max_distance: 20.0,
+                align_to_normal: true,
+            })
+        );
+
+        let again = capture(world, new_root);
+        assert_eq!(again.entities[1].look_at, file.entities[1].look_at);
+        assert_eq!(again.entities[1].follow, file.entities[1].follow);
+        assert_eq!(again.entities[1].look_at.as_ref().and_then(|look_at| look_at.target), Some(0));
+    }
+
+    #[test]
+    fn render_layers_round_trip() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let root = world.spawn(SpatialBundle::default()).id();
+        world.spawn((SpatialBundle::default(), RenderLayers::from_layers(&[1, 4]))).set_parent(root);
+        world.spawn(SpatialBundle::default()).set_parent(root);
+
+        let file = capture(world, root);
+        assert_eq!(file.entities[0].render_layers, Some(vec![1, 4]));
+        assert_eq!(file.entities[1].render_layers, None);
+
+        let (_, spawned) = reload(world, &file);
+        assert_eq!(world.get::<RenderLayers>(spawned[0]), Some(&RenderLayers::from_layers(&[1, 4])));
+        assert!(world.get::<RenderLayers>(spawned[1]).is_none());
+    }
+
+    #[test]
+    fn health_round_trip() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let root = world.spawn(SpatialBundle::default()).id();
+        let health = Health {
+            current: 40.0,
+            max: 80.0,
+            regeneration: 2.5,
+        };
+        let damageable = Damageable {
+            multiplier: 0.5,
+            invulnerability: 1.0,
+            invulnerable_for: 0.75,
+        };
+        world
+            .spawn((SpatialBundle::default(), health.clone(), damageable.clone(), Team(3)))
+            .set_parent(root);
+
+        let file = capture(world, root);
+        let (_, spawned) = reload(world, &file);
+        assert_eq!(world.get::<Health>(spawned[0]), Some(&health));
+        // Time left invulnerable is play state and starts over
+        assert_eq!(
+            world.get::<Damageable>(spawned[0]),
+            Some(&Damageable {
+                invulnerable_for: 0.0,
+                ..damageable
+            })
+        );
+        assert_eq!(world.get::<Team>(spawned[0]), Some(&Team(3)));
+    }
+
+    #[test]
+    fn camera_rig_round_trip() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let root = world.spawn(SpatialBundle::default()).id();
+        let track = world.spawn((SpatialBundle::default(), DollyTrack::default())).set_parent(root).id();
+        let crane = CameraCrane {
+            pivot: Vec3::new(1.0, 0.0, 2.0),
+            base_height: 1.5,
+            length: 6.0,
+            yaw: 45.0,
+            pitch: 10.0,
+        };
+        world
+            .spawn((
+                SpatialBundle::default(),
+                CameraDolly {
+                    track: Some(track),
+                    position: 0.25,
+                    speed: 2.0,
+                    looping: true,
+                },
+                crane.clone(),
+                CameraFocus {
+                    target: Some(track),
+                    offset: Vec3::Y,
+                    damping: 0.5,
+                    up: Vec3::Y,
+                },
+            ))
+            .set_parent(root);
+
+        let file = capture(world, root);
+        let (_, spawned) = reload(world, &file);
+        let dolly = world.get::<CameraDolly>(spawned[1]).expect("dolly restored");
+        assert_eq!(dolly.track, Some(spawned[0]));
+        assert_eq!((dolly.position, dolly.speed, dolly.looping), (0.25, 2.0, true));
+        assert_eq!(world.get::<CameraCrane>(spawned[1]), Some(&crane));
+        let focus = world.get::<CameraFocus>(spawned[1]).expect("focus restored");
+        assert_eq!(focus.target, Some(spawned[0]));
+        assert_eq!((focus.offset, focus.damping), (Vec3::Y, 0.5));
+    }
+
+    #[test]
+    fn portal_pair_round_trip() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let root = world.spawn(SpatialBundle::default()).id();
+        let first = world.spawn(SpatialBundle::default()).set_parent(root).id();
+        let second = world.spawn((SpatialBundle::default(), Portal::linked_to(first))).set_parent(root).id();
+        world.entity_mut(first).insert(Portal {
+            recursion: 3,
+            ..Portal::linked_to(second)
+        });
+
+        let file = capture(world, root);
+        let (_, spawned) = reload(world, &file);
+        let first_portal = world.get::<Portal>(spawned[0]).expect("first portal restored");
+        assert_eq!(first_portal.linked, Some(spawned[1]));
+        assert_eq!(first_portal.recursion, 3);
+        let second_portal = world.get::<Portal>(spawned[1]).expect("second portal restored");
+        assert_eq!(second_portal.linked, Some(spawned[0]));
+    }
+}
@@ -0,0 +1,159 @@
+//! Editor Selection
+//! The set of entities selected in the hierarchy and viewport. The most
+//! recently selected entity is the primary one: the inspector shows it and
+//! local gizmo axes follow it, while the gizmo itself sits at the center of
+//! the whole selection and moves every selected entity together.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+#[derive(Clone, Debug, Default)]
+pub struct EditorSelection {
+    /// Selection order; the last entity is the primary one
+    entities: Vec<Entity>,
+    /// Where Shift-click ranges start from
+    anchor: Option<Entity>,
+}
+
+/// How a click changes the selection
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectMode {
+    /// Select only the clicked entity
+    #[default]
+    Replace,
+    /// Ctrl-click: add or remove the clicked entity
+    Toggle,
+    /// Shift-click: everything between the anchor and the clicked entity
+    Range,
+}
+
+impl SelectMode {
+    pub fn from_egui(modifiers: egui::Modifiers) -> Self {
+        if modifiers.shift {
+            SelectMode::Range
+        } else if modifiers.command {
+            SelectMode::Toggle
+        } else {
+            SelectMode::Replace
+        }
+    }
+}
+
+impl EditorSelection {
+    pub fn primary(&self) -> Option<Entity> {
+        self.entities.last().copied()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Select only `entity`
+    pub fn select(&mut self, entity: Entity) {
+        self.entities.clear();
+        self.entities.push(entity);
+        self.anchor = Some(entity);
+    }
+
+    /// Select `entity` as well, making it the primary one
+    pub fn add(&mut self, entity: Entity) {
+        self.entities.retain(|selected| *selected != entity);
+        self.entities.push(entity);
+        self.anchor = Some(entity);
+    }
+
+    pub fn toggle(&mut self, entity: Entity) {
+        if self.contains(entity) {
+            self.remove(entity);
+            self.anchor = Some(entity);
+        } else {
+            self.add(entity);
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.entities.retain(|selected| *selected != entity);
+        if self.anchor == Some(entity) {
+            self.anchor = self.primary();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.anchor = None;
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        self.entities.retain(|entity| keep(*entity));
+        if self.anchor.is_some_and(|anchor| !keep(anchor)) {
+            self.anchor = self.primary();
+        }
+    }
+
+    /// Swap `old` for `new` in place, e.g. when an entity is respawned
+    pub fn replace(&mut self, old: Entity, new: Entity) {
+        for entity in &mut self.entities {
+            if *entity == old {
+                *entity = new;
+            }
+        }
+        if self.anchor == Some(old) {
+            self.anchor = Some(new);
+        }
+    }
+
+    /// Apply a click on `entity`. `order` is the order entities are listed
+    /// in, which Shift-click ranges follow.
+    pub fn click(&mut self, entity: Entity, mode: SelectMode, order: &[Entity]) {
+        match mode {
+            SelectMode::Replace => self.select(entity),
+            SelectMode::Toggle => self.toggle(entity),
+            SelectMode::Range => {
+                let anchor = self.anchor.unwrap_or(entity);
+                let (Some(from), Some(to)) = (
+                    order.iter().position(|listed| *listed == anchor),
+                    order.iter().position(|listed| *listed == entity),
+                ) else {
+                    self.add(entity);
+                    return;
+                };
+                let range = if from <= to { &order[from..=to] } else { &order[to..=from] };
+                self.entities = range.iter().copied().filter(|listed| *listed != entity).collect();
+                self.entities.push(entity);
+                // Further Shift-clicks grow from the same anchor
+                self.anchor = Some(anchor);
+            }
+        }
+    }
+
+    /// Selected entities that have no selected ancestor, so moving them moves
+    /// every selected entity exactly once
+    pub fn roots(&self, parent_query: &Query<&Parent>) -> Vec<Entity> {
+        self.entities
+            .iter()
+            .copied()
+            .filter(|entity| {
+                let mut current = parent_query.get(*entity).ok().map(|parent| parent.get());
+                while let Some(ancestor) = current {
+                    if self.contains(ancestor) {
+                        return false;
+                    }
+                    current = parent_query.get(ancestor).ok().map(|parent| parent.get());
+                }
+                true
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,218 @@
+//! Waffle Engine Sub-Scenes
+//! A scene reference places another saved scene inside this one as a single
+//! object, like a level nested in a world. Its contents are spawned from the
+//! scene file, left out when this scene is saved, and spawned again whenever
+//! the file changes. They can't be picked or edited on their own until the
+//! reference is opened for edit; saving then writes them back to the
+//! referenced scene, which updates every other reference to it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::scene_file::{capture_scene, spawn_scene, SceneEntityQuery, SceneFile};
+use super::HierarchySnapshot;
+
+/// How often referenced scene files are checked for changes
+const SUB_SCENE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Another scene, by name, spawned as the children of this entity
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneReference {
+    pub scene: String,
+}
+
+/// The contents spawned for a scene reference, which are all of its children
+#[derive(Component, Debug)]
+pub struct SubSceneInstance {
+    scene: String,
+    /// Modified time of the scene file the contents were spawned from
+    modified: Option<SystemTime>,
+    /// Spawned from the file again on the next update
+    reload: bool,
+    /// Opened for edit; changes to the file are not picked up meanwhile
+    pub editing: bool,
+    pub error: Option<String>,
+}
+
+/// Change a scene reference from the inspector
+#[derive(Event, Clone)]
+pub struct SubSceneEditEvent {
+    pub entity: Entity,
+    pub kind: SubSceneEditKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum SubSceneEditKind {
+    SetScene(String),
+    OpenForEdit,
+    /// Write the contents back to the referenced scene
+    Save,
+    /// Drop the edits and spawn the referenced scene again
+    Revert,
+    Remove,
+}
+
+fn modified_time(scene: &str) -> Option<SystemTime> {
+    std::fs::metadata(SceneFile::path(scene)).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The scene a scene file path from the asset browser refers to, e.g.
+/// `scenes/level1.scene.ron` is `level1`
+pub fn scene_name_from_asset_path(path: &str) -> Option<&str> {
+    let scene_dir = super::scene_file::SCENE_DIR.strip_prefix("assets/")?;
+    path.strip_prefix(scene_dir)?
+        .strip_prefix('/')?
+        .strip_suffix(super::scene_file::SCENE_EXTENSION)?
+        .strip_suffix('.')
+}
+
+/// The outermost sub-scene `entity` is locked inside of, or `entity` itself.
+/// Clicking on a sub-scene picks the whole of it.
+pub fn sub_scene_pick_target(entity: Entity, locked: &HashSet<Entity>, parent_query: &Query<&Parent>) -> Entity {
+    let mut target = entity;
+    let mut current = entity;
+    while let Ok(parent) = parent_query.get(current) {
+        current = parent.get();
+        if locked.contains(&current) {
+            target = current;
+        }
+    }
+    target
+}
+
+/// Spawn the contents of new scene references, and again for ones whose
+/// scene file changed
+#[allow(clippy::too_many_arguments)]
+pub fn refresh_sub_scenes(
+    mut commands: Commands,
+    mut last_poll: Local<Option<Instant>>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut hierarchy: ResMut<HierarchySnapshot>,
+    mut references: Query<(Entity, &SceneReference, Option<&mut SubSceneInstance>)>,
+    reference_query: Query<&SceneReference>,
+    parent_query: Query<&Parent>,
+) {
+    let poll = last_poll.is_none_or(|last| last.elapsed() >= SUB_SCENE_POLL_INTERVAL);
+    if poll {
+        *last_poll = Some(Instant::now());
+    }
+
+    let mut locked = HashSet::new();
+    for (entity, reference, instance) in references.iter_mut() {
+        if instance.as_ref().is_none_or(|instance| !instance.editing) {
+            locked.insert(entity);
+        }
+        let stale = match &instance {
+            None => true,
+            Some(instance) => {
+                instance.reload
+                    || instance.scene != reference.scene
+                    || (poll && !instance.editing && modified_time(&reference.scene) != instance.modified)
+            }
+        };
+        if !stale {
+            continue;
+        }
+        commands.entity(entity).despawn_descendants();
+
+        let modified = modified_time(&reference.scene);
+        // A scene can't be nested inside itself
+        let mut ancestors = std::iter::successors(Some(entity), |current| {
+            parent_query.get(*current).ok().map(|parent| parent.get())
+        })
+        .skip(1);
+        let nested_in_itself =
+            ancestors.any(|ancestor| reference_query.get(ancestor).is_ok_and(|outer| outer.scene == reference.scene));
+        let loaded = if nested_in_itself {
+            Err(format!("\"{}\" can't contain itself", reference.scene))
+        } else {
+            SceneFile::load(&reference.scene).map_err(|err| err.to_string())
+        };
+        let error = match loaded {
+            Ok(file) => {
+                spawn_scene(&mut commands, entity, &file, &asset_server, &mut meshes, &mut materials);
+                None
+            }
+            Err(err) => {
+                warn!("Failed to spawn sub-scene \"{}\": {}", reference.scene, err);
+                Some(err)
+            }
+        };
+        commands.entity(entity).insert(SubSceneInstance {
+            scene: reference.scene.clone(),
+            modified,
+            reload: false,
+            editing: false,
+            error,
+        });
+    }
+    if hierarchy.locked_sub_scenes != locked {
+        hierarchy.locked_sub_scenes = locked;
+    }
+}
+
+/// Despawn the contents of entities that stopped being scene references
+pub fn despawn_removed_sub_scenes(
+    mut commands: Commands,
+    instances: Query<Entity, (With<SubSceneInstance>, Without<SceneReference>)>,
+) {
+    for entity in instances.iter() {
+        commands.entity(entity).despawn_descendants().remove::<SubSceneInstance>();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_sub_scene_edit_events(
+    mut commands: Commands,
+    mut events: EventReader<SubSceneEditEvent>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut instances: Query<(&SceneReference, &mut SubSceneInstance)>,
+    children_query: Query<&Children>,
+    scene_query: Query<SceneEntityQuery>,
+) {
+    for event in events.read() {
+        match &event.kind {
+            SubSceneEditKind::SetScene(scene) => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.insert(SceneReference { scene: scene.clone() });
+                }
+            }
+            SubSceneEditKind::Remove => {
+                if let Some(mut entity) = commands.get_entity(event.entity) {
+                    entity.remove::<SceneReference>();
+                }
+            }
+            SubSceneEditKind::OpenForEdit => {
+                if let Ok((_, mut instance)) = instances.get_mut(event.entity) {
+                    instance.editing = true;
+                }
+            }
+            SubSceneEditKind::Save => {
+                let Ok((reference, mut instance)) = instances.get_mut(event.entity) else {
+                    continue;
+                };
+                let (mut file, _) = capture_scene(event.entity, &children_query, &scene_query, &meshes, &materials);
+                if let Err(err) = file.save(&reference.scene) {
+                    error!("Failed to save sub-scene \"{}\": {}", reference.scene, err);
+                    continue;
+                }
+                info!("Saved sub-scene \"{}\" with {} entities", reference.scene, file.entities.len());
+                // The contents already match the file; other references to
+                // it pick the change up when they next check
+                instance.editing = false;
+                instance.modified = modified_time(&reference.scene);
+            }
+            SubSceneEditKind::Revert => {
+                if let Ok((_, mut instance)) = instances.get_mut(event.entity) {
+                    instance.editing = false;
+                    instance.reload = true;
+                }
+            }
+        }
+    }
+}
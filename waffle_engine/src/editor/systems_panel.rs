@@ -0,0 +1,145 @@
+//! Waffle Engine Systems Panel
+//! Lists the engine's systems in each schedule, grouped by the plugin module
+//! they come from. Systems registered with `toggleable` have a checkbox that
+//! pauses them, so a misbehaving one can be found by switching them off one
+//! at a time while the game runs. The list is read from the schedules when
+//! the panel asks for it.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::core::system_toggles::{is_toggle_condition, SystemToggles};
+
+#[derive(Clone, Debug)]
+pub struct ListedSystem {
+    /// Full type name, which pausing goes by
+    pub name: String,
+    pub short_name: String,
+    pub schedule: String,
+    /// Top-level module of the engine the system is in, e.g. `rendering`
+    pub plugin: String,
+    pub toggleable: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct SystemsPanel {
+    pub systems: Vec<ListedSystem>,
+    refresh_requested: bool,
+    /// Only systems whose names contain this
+    pub filter: String,
+    pub toggleable_only: bool,
+}
+
+impl SystemsPanel {
+    pub fn request_refresh(&mut self) {
+        self.refresh_requested = true;
+    }
+}
+
+/// List the engine's systems when the panel asks. Runs in `First`, while
+/// the other main schedules are stored and can be read.
+pub fn collect_system_list(world: &mut World) {
+    if !world.get_resource::<SystemsPanel>().is_some_and(|panel| panel.refresh_requested) {
+        return;
+    }
+    let crate_name = module_path!().split("::").next().unwrap_or_default();
+    let mut systems = Vec::new();
+    if let Some(schedules) = world.get_resource::<Schedules>() {
+        for (label, schedule) in schedules.iter() {
+            for (_, system, conditions) in schedule.graph().systems() {
+                let name = system.name().to_string();
+                let Some(path) = name.strip_prefix(crate_name).and_then(|path| path.strip_prefix("::")) else {
+                    continue;
+                };
+                // Closures, like the ones `guarded` wraps systems in, have no name to show
+                if path.contains("{{closure}}") {
+                    continue;
+                }
+                let plugin = path.split("::").next().unwrap_or_default().to_string();
+                let short_name = path.split('<').next().unwrap_or(path).rsplit("::").next().unwrap_or(path);
+                systems.push(ListedSystem {
+                    short_name: short_name.to_string(),
+                    schedule: format!("{label:?}"),
+                    plugin,
+                    toggleable: conditions.iter().any(|condition| is_toggle_condition(&condition.name())),
+                    name,
+                });
+            }
+        }
+    }
+    systems.sort_by(|a, b| {
+        a.plugin.cmp(&b.plugin).then_with(|| a.short_name.cmp(&b.short_name)).then_with(|| a.schedule.cmp(&b.schedule))
+    });
+
+    let mut panel = world.resource_mut::<SystemsPanel>();
+    panel.refresh_requested = false;
+    panel.systems = systems;
+}
+
+pub fn draw_systems_panel(ui: &mut egui::Ui, panel: &mut SystemsPanel, toggles: &mut SystemToggles) {
+    if panel.systems.is_empty() && !panel.refresh_requested {
+        panel.request_refresh();
+    }
+    ui.horizontal(|ui| {
+        ui.heading("Systems");
+        if ui.button("Refresh").clicked() {
+            panel.request_refresh();
+        }
+        let paused = toggles.paused_count();
+        if ui.add_enabled(paused > 0, egui::Button::new(format!("Resume All ({paused})"))).clicked() {
+            toggles.resume_all();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut panel.filter).hint_text("Filter systems").desired_width(160.0));
+        ui.checkbox(&mut panel.toggleable_only, "Toggleable only");
+    });
+    ui.separator();
+
+    let filter = panel.filter.trim().to_lowercase();
+    let toggleable_only = panel.toggleable_only;
+    let shown: Vec<&ListedSystem> = panel
+        .systems
+        .iter()
+        .filter(|system| !toggleable_only || system.toggleable)
+        .filter(|system| filter.is_empty() || system.short_name.to_lowercase().contains(&filter))
+        .collect();
+    if shown.is_empty() {
+        ui.label("No systems");
+        return;
+    }
+    egui::ScrollArea::vertical().id_source("systems_panel").show(ui, |ui| {
+        for group in shown.chunk_by(|a, b| a.plugin == b.plugin) {
+            let plugin = &group[0].plugin;
+            let paused = group.iter().filter(|system| toggles.is_paused(&system.name)).count();
+            let mut header = format!("{} ({})", capitalize(plugin), group.len());
+            if paused > 0 {
+                header.push_str(&format!(", {paused} paused"));
+            }
+            egui::CollapsingHeader::new(header).id_source(("systems_plugin", plugin)).show(ui, |ui| {
+                egui::Grid::new(("systems_grid", plugin)).num_columns(2).striped(true).show(ui, |ui| {
+                    for system in group {
+                        let mut running = !toggles.is_paused(&system.name);
+                        let checkbox = ui
+                            .add_enabled(system.toggleable, egui::Checkbox::new(&mut running, &system.short_name))
+                            .on_hover_text(&system.name)
+                            .on_disabled_hover_text("Always runs; register it with toggleable() to pause it");
+                        if checkbox.changed() {
+                            toggles.set_paused(&system.name, !running);
+                        }
+                        ui.weak(&system.schedule);
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+    });
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,215 @@
+//! Waffle Engine Project Templates
+//! The New Project dialog. Each template is a folder bundled with the engine
+//! under `templates/`, holding a `project.ron` with its input settings and
+//! startup scene, and an `assets` folder of scenes, scripts and models. A new
+//! project is a copy of one, and opens in a new editor window.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Folder the bundled templates are in, next to the executable
+const TEMPLATES_DIR: &str = "templates";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectTemplate {
+    Empty,
+    ThirdPerson,
+    Fps,
+    Archviz,
+}
+
+impl ProjectTemplate {
+    pub const ALL: [ProjectTemplate; 4] = [
+        ProjectTemplate::Empty,
+        ProjectTemplate::ThirdPerson,
+        ProjectTemplate::Fps,
+        ProjectTemplate::Archviz,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProjectTemplate::Empty => "Empty",
+            ProjectTemplate::ThirdPerson => "Third-Person Starter",
+            ProjectTemplate::Fps => "FPS Starter",
+            ProjectTemplate::Archviz => "Archviz",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ProjectTemplate::Empty => "A sun and a ground plane, ready for your own scene.",
+            ProjectTemplate::ThirdPerson => {
+                "An open field with a player stand-in, bobbing pickups and a patrolling guard. Stick and mouse \
+                 look are tuned for an orbiting camera."
+            }
+            ProjectTemplate::Fps => {
+                "A walled arena with cover, a spawn point and sliding targets. Mouse look is raw and stick aim \
+                 eases in near the center."
+            }
+            ProjectTemplate::Archviz => {
+                "A furnished room lit by a lamp and a sun that sweeps through the day, in centimeters with \
+                 snapping for laying out rooms and furniture."
+            }
+        }
+    }
+
+    fn folder(self) -> &'static str {
+        match self {
+            ProjectTemplate::Empty => "empty",
+            ProjectTemplate::ThirdPerson => "third_person",
+            ProjectTemplate::Fps => "fps",
+            ProjectTemplate::Archviz => "archviz",
+        }
+    }
+
+    /// The template's folder: beside the executable in a release, in the
+    /// source tree when run with cargo
+    pub fn path(self) -> PathBuf {
+        let bundled = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(TEMPLATES_DIR)))
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join(TEMPLATES_DIR));
+        bundled.join(self.folder())
+    }
+}
+
+/// Copy a template into a new project folder, which must not exist yet or
+/// be empty
+pub fn create_project(template: ProjectTemplate, destination: &Path) -> anyhow::Result<()> {
+    let source = template.path();
+    if !source.is_dir() {
+        anyhow::bail!("template folder {} is missing", source.display());
+    }
+    if destination.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        anyhow::bail!("{} is not empty", destination.display());
+    }
+    for entry in WalkDir::new(&source) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(&source)?;
+        let target = destination.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_name() != ".gitkeep" {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Start another editor in a project folder. Scenes and settings are found
+/// from the working directory, assets from `BEVY_ASSET_ROOT`.
+pub fn open_project(project: &Path) -> anyhow::Result<()> {
+    let project = project.canonicalize()?;
+    std::process::Command::new(std::env::current_exe()?)
+        .current_dir(&project)
+        .env("BEVY_ASSET_ROOT", &project)
+        .spawn()?;
+    Ok(())
+}
+
+/// State of the New Project dialog
+#[derive(Debug, Clone)]
+pub struct NewProjectDialog {
+    pub template: ProjectTemplate,
+    pub name: String,
+    /// Folder the project folder is made in
+    pub location: String,
+    /// The project made last, to offer opening it
+    pub created: Option<PathBuf>,
+    pub status: String,
+}
+
+impl Default for NewProjectDialog {
+    fn default() -> Self {
+        // Beside the current project, by default
+        let location = std::env::current_dir()
+            .ok()
+            .and_then(|dir| dir.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        Self {
+            template: ProjectTemplate::ThirdPerson,
+            name: "MyGame".to_string(),
+            location: location.display().to_string(),
+            created: None,
+            status: String::new(),
+        }
+    }
+}
+
+pub fn show_new_project_dialog(ctx: &egui::Context, dialog: &mut Option<NewProjectDialog>) {
+    let Some(state) = dialog.as_mut() else {
+        return;
+    };
+    let mut open = true;
+    let mut close = false;
+    egui::Window::new("New Project")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            ui.label("Template:");
+            for template in ProjectTemplate::ALL {
+                ui.radio_value(&mut state.template, template, template.label());
+                if state.template == template {
+                    ui.indent(template.folder(), |ui| {
+                        ui.label(egui::RichText::new(template.description()).weak());
+                    });
+                }
+            }
+
+            ui.separator();
+            egui::Grid::new("new_project_fields").num_columns(2).show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut state.name);
+                ui.end_row();
+                ui.label("Location:");
+                ui.add(egui::TextEdit::singleline(&mut state.location).desired_width(280.0));
+                ui.end_row();
+            });
+            let destination = Path::new(&state.location).join(state.name.trim());
+            ui.label(egui::RichText::new(destination.display().to_string()).weak());
+
+            if !state.status.is_empty() {
+                ui.label(&state.status);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                let valid = !state.name.trim().is_empty() && !state.location.trim().is_empty();
+                if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+                    match create_project(state.template, &destination) {
+                        Ok(()) => {
+                            info!(
+                                "Created project {} from the {} template",
+                                destination.display(),
+                                state.template.label()
+                            );
+                            state.status = format!("Created {}", destination.display());
+                            state.created = Some(destination.clone());
+                        }
+                        Err(error) => {
+                            state.status = format!("Failed to create project: {error}");
+                            state.created = None;
+                        }
+                    }
+                }
+                if let Some(created) = state.created.clone() {
+                    if ui.button("Open Project").clicked() {
+                        match open_project(&created) {
+                            Ok(()) => close = true,
+                            Err(error) => state.status = format!("Failed to open project: {error}"),
+                        }
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+    if !open || close {
+        *dialog = None;
+    }
+}
@@ -0,0 +1,301 @@
+//! Waffle Engine Terrain Sculpting
+//! The Sculpt tool raises, lowers, smooths or flattens the terrain under the
+//! cursor while the left mouse button is held. Each stroke remembers the
+//! heights it changed so it can be undone with Ctrl+Z and redone with
+//! Ctrl+Shift+Z.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::tools::{viewport_pointer_ray, ActiveTool, EditorTool};
+use super::EditorState;
+use crate::rendering::camera::{WaffleMainCamera, WaffleOrthoCamera};
+use crate::terrain::{TerrainState, WaffleTerrain};
+
+/// Strokes kept for undo
+const MAX_TERRAIN_STROKES: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerrainBrushMode {
+    Raise,
+    Lower,
+    /// Even out bumps toward the average of each vertex's neighbors
+    Smooth,
+    /// Level toward the height under the cursor where the stroke started
+    Flatten,
+}
+
+impl TerrainBrushMode {
+    pub const ALL: [TerrainBrushMode; 4] = [
+        TerrainBrushMode::Raise,
+        TerrainBrushMode::Lower,
+        TerrainBrushMode::Smooth,
+        TerrainBrushMode::Flatten,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TerrainBrushMode::Raise => "Raise",
+            TerrainBrushMode::Lower => "Lower",
+            TerrainBrushMode::Smooth => "Smooth",
+            TerrainBrushMode::Flatten => "Flatten",
+        }
+    }
+}
+
+/// Options of the Sculpt tool
+#[derive(Clone, Debug)]
+pub struct TerrainBrush {
+    pub mode: TerrainBrushMode,
+    /// In the terrain's units
+    pub radius: f32,
+    /// Height raised or lowered per second at the center of the brush; for
+    /// Smooth and Flatten, how quickly the ground settles
+    pub strength: f32,
+}
+
+impl Default for TerrainBrush {
+    fn default() -> Self {
+        Self {
+            mode: TerrainBrushMode::Raise,
+            radius: 4.0,
+            strength: 2.0,
+        }
+    }
+}
+
+/// Heights a stroke changed, as they were before it. Undoing swaps them with
+/// the current heights, which then redo the stroke.
+#[derive(Clone, Debug)]
+pub struct TerrainStroke {
+    pub terrain: Entity,
+    heights: HashMap<usize, f32>,
+}
+
+impl TerrainStroke {
+    /// Swap the stored heights with the terrain's and mesh the changed
+    /// chunks again
+    fn swap(&mut self, state: &mut TerrainState) {
+        let Some(heights) = state.heights.as_mut() else {
+            return;
+        };
+        let resolution = heights.resolution;
+        let mut min = UVec2::MAX;
+        let mut max = UVec2::ZERO;
+        for (index, value) in self.heights.iter_mut() {
+            let Some(current) = heights.values.get_mut(*index) else {
+                continue;
+            };
+            std::mem::swap(current, value);
+            let vertex = UVec2::new((*index % resolution) as u32, (*index / resolution) as u32);
+            min = min.min(vertex);
+            max = max.max(vertex);
+        }
+        if min.cmple(max).all() {
+            state.mark_dirty(min, max);
+        }
+    }
+}
+
+/// Sculpt strokes for undo and redo, and the stroke being painted
+#[derive(Resource, Default)]
+pub struct TerrainSculptHistory {
+    pub undo: Vec<TerrainStroke>,
+    pub redo: Vec<TerrainStroke>,
+    stroke: Option<ActiveStroke>,
+    /// Where the brush is over the terrain, in world space, and its radius
+    pub brush: Option<(Vec3, f32)>,
+}
+
+struct ActiveStroke {
+    stroke: TerrainStroke,
+    /// Height Flatten levels toward, from 0 to 1
+    flatten_height: f32,
+}
+
+impl TerrainSculptHistory {
+    /// Undo the last stroke, or the last one on `terrain`
+    pub fn undo(&mut self, terrain: Option<Entity>, terrains: &mut Query<&mut TerrainState>) {
+        let Some(index) = self.undo.iter().rposition(|stroke| terrain.is_none_or(|t| stroke.terrain == t)) else {
+            return;
+        };
+        let mut stroke = self.undo.remove(index);
+        if let Ok(mut state) = terrains.get_mut(stroke.terrain) {
+            stroke.swap(&mut state);
+        }
+        self.redo.push(stroke);
+    }
+
+    /// Redo the last undone stroke, or the last one on `terrain`
+    pub fn redo(&mut self, terrain: Option<Entity>, terrains: &mut Query<&mut TerrainState>) {
+        let Some(index) = self.redo.iter().rposition(|stroke| terrain.is_none_or(|t| stroke.terrain == t)) else {
+            return;
+        };
+        let mut stroke = self.redo.remove(index);
+        if let Ok(mut state) = terrains.get_mut(stroke.terrain) {
+            stroke.swap(&mut state);
+        }
+        self.undo.push(stroke);
+    }
+
+    fn finish_stroke(&mut self) {
+        let Some(active) = self.stroke.take() else {
+            return;
+        };
+        if active.stroke.heights.is_empty() {
+            return;
+        }
+        self.undo.push(active.stroke);
+        if self.undo.len() > MAX_TERRAIN_STROKES {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+}
+
+/// Paint with the Sculpt tool on the terrain under the cursor
+#[allow(clippy::too_many_arguments)]
+pub fn sculpt_terrain(
+    editor_state: Res<EditorState>,
+    active_tool: Res<ActiveTool>,
+    mut history: ResMut<TerrainSculptHistory>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+    ortho_camera_query: Query<(&Camera, &GlobalTransform, &WaffleOrthoCamera)>,
+    mut terrains: Query<(Entity, &WaffleTerrain, &GlobalTransform, &mut TerrainState)>,
+) {
+    history.brush = None;
+    if active_tool.tool != EditorTool::Sculpt {
+        history.finish_stroke();
+        return;
+    }
+
+    let ctrl = keyboard_input.pressed(KeyCode::ControlLeft) || keyboard_input.pressed(KeyCode::ControlRight);
+    let shift = keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+    if ctrl && keyboard_input.just_pressed(KeyCode::KeyZ) && editor_state.viewport_hovered {
+        history.finish_stroke();
+        let mut states = terrains.transmute_lens::<&mut TerrainState>();
+        if shift {
+            history.redo(None, &mut states.query());
+        } else {
+            history.undo(None, &mut states.query());
+        }
+        return;
+    }
+
+    let painting = mouse_input.pressed(MouseButton::Left)
+        && !mouse_input.pressed(MouseButton::Right)
+        && !mouse_input.pressed(MouseButton::Middle);
+    if !painting {
+        history.finish_stroke();
+    }
+    let Some(ray) = viewport_pointer_ray(&editor_state, &camera_query, &ortho_camera_query) else {
+        return;
+    };
+
+    // Nearest terrain under the cursor; a stroke stays on the terrain it began on
+    let stroke_terrain = history.stroke.as_ref().map(|active| active.stroke.terrain);
+    let mut best: Option<(Entity, Vec3, f32)> = None;
+    for (entity, terrain, transform, state) in terrains.iter() {
+        if stroke_terrain.is_some_and(|stroke| stroke != entity) {
+            continue;
+        }
+        let local_from_world = transform.affine().inverse();
+        let origin = local_from_world.transform_point3(ray.origin);
+        let direction = local_from_world.transform_vector3(*ray.direction);
+        let Some(t) = state.raycast(terrain, origin, direction) else {
+            continue;
+        };
+        let distance = transform.transform_point(origin + direction * t).distance(ray.origin);
+        if best.is_none_or(|(_, _, best)| distance < best) {
+            best = Some((entity, origin + direction * t, distance));
+        }
+    }
+    let Some((entity, local_hit, _)) = best else {
+        return;
+    };
+    let Ok((_, terrain, transform, mut state)) = terrains.get_mut(entity) else {
+        return;
+    };
+    let brush = &active_tool.terrain_brush;
+    history.brush = Some((transform.transform_point(local_hit), brush.radius * transform.compute_transform().scale.x));
+    if !painting {
+        return;
+    }
+
+    let history = &mut *history;
+    let active = history.stroke.get_or_insert_with(|| ActiveStroke {
+        stroke: TerrainStroke {
+            terrain: entity,
+            heights: HashMap::new(),
+        },
+        flatten_height: local_hit.y / terrain.height_scale.max(0.001),
+    });
+    let flatten_height = active.flatten_height;
+    let Some(heights) = state.heights.as_mut() else {
+        return;
+    };
+
+    let resolution = heights.resolution;
+    let last = (resolution.max(2) - 1) as f32;
+    let step = terrain.size / last;
+    let center = (local_hit.xz() + terrain.size * 0.5) / step;
+    let reach = Vec2::splat(brush.radius) / step;
+    let min = (center - reach).floor().max(Vec2::ZERO).as_uvec2();
+    let max = (center + reach).ceil().min(Vec2::splat(last)).as_uvec2();
+    if min.cmpgt(max).any() {
+        return;
+    }
+
+    let amount = brush.strength * time.delta_seconds();
+    let height_step = amount / terrain.height_scale.max(0.001);
+    // Smoothing reads the heights from before this frame's changes
+    let before = (brush.mode == TerrainBrushMode::Smooth).then(|| heights.clone());
+    for z in min.y..=max.y {
+        for x in min.x..=max.x {
+            let offset = (Vec2::new(x as f32, z as f32) - center) * step;
+            let distance = offset.length() / brush.radius.max(0.001);
+            if distance >= 1.0 {
+                continue;
+            }
+            let falloff = 0.5 + 0.5 * (distance * std::f32::consts::PI).cos();
+            let (x, z) = (x as usize, z as usize);
+            let index = z * resolution + x;
+            let current = heights.values[index];
+            let settle = (amount * falloff).min(1.0);
+            let target = match brush.mode {
+                TerrainBrushMode::Raise => current + height_step * falloff,
+                TerrainBrushMode::Lower => current - height_step * falloff,
+                TerrainBrushMode::Smooth => match &before {
+                    Some(before) => {
+                        let average = (before.get(x.saturating_sub(1), z)
+                            + before.get(x + 1, z)
+                            + before.get(x, z.saturating_sub(1))
+                            + before.get(x, z + 1))
+                            * 0.25;
+                        current + (average - current) * settle
+                    }
+                    None => current,
+                },
+                TerrainBrushMode::Flatten => current + (flatten_height - current) * settle,
+            }
+            .clamp(0.0, 1.0);
+            if target != current {
+                active.stroke.heights.entry(index).or_insert(current);
+                heights.values[index] = target;
+            }
+        }
+    }
+    state.mark_dirty(min, max);
+}
+
+pub fn draw_terrain_brush(history: Res<TerrainSculptHistory>, mut gizmos: Gizmos) {
+    let Some((center, radius)) = history.brush else {
+        return;
+    };
+    let color = Color::srgb(1.0, 0.85, 0.2);
+    gizmos.circle(center + Vec3::Y * 0.05, Dir3::Y, radius, color);
+    gizmos.circle(center + Vec3::Y * 0.05, Dir3::Y, radius * 0.5, color.with_alpha(0.5));
+}
@@ -1,7 +1,10 @@
-/// Editor Theme Module
-/// Dark theme configuration for the editor UI
+//! Editor Theme Module
+//! Dark theme configuration for the editor UI, its scale and the colors of
+//! gizmos and selections
 
+use bevy::prelude::Color;
 use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
 
 /// Editor theme configuration
 #[derive(Clone)]
@@ -13,6 +16,136 @@ pub struct EditorTheme {
     pub border_color: egui::Color32,
     pub hover_color: egui::Color32,
     pub active_color: egui::Color32,
+    /// Size of the whole editor UI relative to the window's scale
+    pub ui_scale: f32,
+    pub palette_preset: PalettePreset,
+    pub palette: EditorPalette,
+}
+
+/// Colors of the transform gizmo's axes and of selected entities' bounds, as
+/// sRGB bytes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EditorPalette {
+    pub x_axis: [u8; 3],
+    pub y_axis: [u8; 3],
+    pub z_axis: [u8; 3],
+    pub selection: [u8; 3],
+    /// Box around a selection of several entities
+    pub group_selection: [u8; 3],
+}
+
+impl Default for EditorPalette {
+    fn default() -> Self {
+        PalettePreset::Standard.palette()
+    }
+}
+
+impl EditorPalette {
+    pub fn color32(rgb: [u8; 3]) -> egui::Color32 {
+        egui::Color32::from_rgb(rgb[0], rgb[1], rgb[2])
+    }
+
+    pub fn color(rgb: [u8; 3]) -> Color {
+        Color::srgb_u8(rgb[0], rgb[1], rgb[2])
+    }
+
+    /// An axis color, lightened while the axis is being dragged
+    pub fn axis_color(&self, axis: usize, active: bool) -> Color {
+        let rgb = [self.x_axis, self.y_axis, self.z_axis][axis.min(2)];
+        if active {
+            Self::color(rgb.map(|channel| (channel as f32 + (255.0 - channel as f32) * 0.45).round() as u8))
+        } else {
+            Self::color(rgb)
+        }
+    }
+
+    pub fn axis_color32(&self, axis: usize, active: bool) -> egui::Color32 {
+        let rgb = [self.x_axis, self.y_axis, self.z_axis][axis.min(2)];
+        let color = Self::color32(rgb);
+        if active {
+            lerp_color32(color, egui::Color32::WHITE, 0.45)
+        } else {
+            color
+        }
+    }
+}
+
+fn lerp_color32(from: egui::Color32, to: egui::Color32, amount: f32) -> egui::Color32 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    egui::Color32::from_rgb(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
+}
+
+/// Built-in gizmo and selection palettes. The colorblind-safe ones are drawn
+/// from the Okabe-Ito set and keep the axes apart by brightness as well as hue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PalettePreset {
+    #[default]
+    Standard,
+    /// Deuteranopia and protanopia
+    RedGreenSafe,
+    /// Tritanopia
+    BlueYellowSafe,
+    HighContrast,
+    /// Edited by hand
+    Custom,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 5] = [
+        PalettePreset::Standard,
+        PalettePreset::RedGreenSafe,
+        PalettePreset::BlueYellowSafe,
+        PalettePreset::HighContrast,
+        PalettePreset::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PalettePreset::Standard => "Standard",
+            PalettePreset::RedGreenSafe => "Red-Green Safe",
+            PalettePreset::BlueYellowSafe => "Blue-Yellow Safe",
+            PalettePreset::HighContrast => "High Contrast",
+            PalettePreset::Custom => "Custom",
+        }
+    }
+
+    /// The preset's colors; custom palettes start from the standard one
+    pub fn palette(self) -> EditorPalette {
+        match self {
+            PalettePreset::Standard | PalettePreset::Custom => EditorPalette {
+                x_axis: [230, 70, 70],
+                y_axis: [70, 230, 70],
+                z_axis: [70, 140, 230],
+                selection: [255, 230, 25],
+                group_selection: [255, 153, 25],
+            },
+            PalettePreset::RedGreenSafe => EditorPalette {
+                x_axis: [213, 94, 0],
+                y_axis: [240, 228, 66],
+                z_axis: [0, 114, 178],
+                selection: [86, 180, 233],
+                group_selection: [204, 121, 167],
+            },
+            PalettePreset::BlueYellowSafe => EditorPalette {
+                x_axis: [213, 94, 0],
+                y_axis: [0, 158, 115],
+                z_axis: [204, 121, 167],
+                selection: [240, 240, 240],
+                group_selection: [150, 150, 150],
+            },
+            PalettePreset::HighContrast => EditorPalette {
+                x_axis: [255, 50, 50],
+                y_axis: [50, 255, 50],
+                z_axis: [60, 150, 255],
+                selection: [255, 255, 255],
+                group_selection: [0, 255, 255],
+            },
+        }
+    }
 }
 
 impl Default for EditorTheme {
@@ -25,6 +158,9 @@ impl Default for EditorTheme {
             border_color: egui::Color32::from_rgb(60, 60, 60),
             hover_color: egui::Color32::from_rgb(80, 80, 80),
             active_color: egui::Color32::from_rgb(100, 100, 100),
+            ui_scale: 1.0,
+            palette_preset: PalettePreset::Standard,
+            palette: EditorPalette::default(),
         }
     }
 }
@@ -56,9 +192,14 @@ impl EditorTheme {
         visuals.widgets.active.fg_stroke.color = self.text_color;
         visuals.widgets.active.bg_stroke.color = self.accent_color;
 
-        // Selection colors
-        visuals.selection.bg_fill = self.accent_color.linear_multiply(0.3);
-        visuals.selection.stroke.color = self.accent_color;
+        // Selection colors follow the palette, so selected rows read the
+        // same as selected entities in the viewport
+        let selection = EditorPalette::color32(self.palette.selection);
+        visuals.selection.bg_fill = selection.linear_multiply(0.3);
+        visuals.selection.stroke.color = selection;
+
+        // Keyboard focus has to stand out when there is no mouse to hover
+        visuals.widgets.hovered.bg_stroke.width = 1.5;
 
         // Hyperlink colors
         visuals.hyperlink_color = self.accent_color;
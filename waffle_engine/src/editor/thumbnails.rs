@@ -0,0 +1,422 @@
+//! Asset Thumbnails Module
+//! Small previews for the asset browser's grid view. Images are decoded and
+//! downscaled, models are drawn as shaded clay from a three-quarter view,
+//! both on editor jobs. Finished thumbnails are cached as PNGs under
+//! `.waffle/thumbnails`, keyed on the asset's path and modification time, so
+//! they are only made again once the asset changes.
+
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use image::{Rgba, RgbaImage};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::jobs::{EditorJobs, JobHandle, JobPriority};
+use super::{AssetBrowserCache, AssetKind, EditorSettings};
+
+/// Width and height thumbnails fit in
+pub const THUMBNAIL_SIZE: u32 = 96;
+
+const THUMBNAIL_DIR: &str = ".waffle/thumbnails";
+
+/// Thumbnails being made at once, so a large project doesn't flood the job
+/// queue ahead of scans and imports
+const MAX_IN_FLIGHT: usize = 4;
+
+const CLAY_COLOR: [f32; 3] = [200.0, 190.0, 178.0];
+
+enum ThumbnailState {
+    /// Reading the cached thumbnail, or decoding an image
+    Loading(JobHandle<Option<RgbaImage>>),
+    /// Waiting for a model the cache didn't have to load
+    LoadingModel(ModelHandle),
+    Rendering(JobHandle<RgbaImage>),
+    Ready(Handle<Image>, UVec2),
+    Failed,
+}
+
+enum ModelHandle {
+    Gltf(Handle<Gltf>),
+    Mesh(Handle<Mesh>),
+}
+
+struct Thumbnail {
+    /// Modification time of the asset the thumbnail was made from
+    modified: Option<SystemTime>,
+    state: ThumbnailState,
+}
+
+impl Thumbnail {
+    fn in_flight(&self) -> bool {
+        matches!(
+            self.state,
+            ThumbnailState::Loading(_) | ThumbnailState::LoadingModel(_) | ThumbnailState::Rendering(_)
+        )
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AssetThumbnails {
+    thumbnails: HashMap<String, Thumbnail>,
+    /// Images of replaced thumbnails, for the UI to forget
+    retired: Vec<Handle<Image>>,
+}
+
+impl AssetThumbnails {
+    /// Thumbnails ready to show, with their size in pixels
+    pub fn ready(&self) -> impl Iterator<Item = (&str, &Handle<Image>, UVec2)> {
+        self.thumbnails.iter().filter_map(|(path, thumbnail)| match &thumbnail.state {
+            ThumbnailState::Ready(image, size) => Some((path.as_str(), image, *size)),
+            _ => None,
+        })
+    }
+
+    pub fn take_retired(&mut self) -> Vec<Handle<Image>> {
+        std::mem::take(&mut self.retired)
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(Thumbnail {
+            state: ThumbnailState::Ready(image, _),
+            ..
+        }) = self.thumbnails.remove(path)
+        {
+            self.retired.push(image);
+        }
+    }
+}
+
+/// Make thumbnails for the images and models in the asset browser while its
+/// grid view is on, and drop those of assets that went away
+pub fn update_asset_thumbnails(
+    mut thumbnails: ResMut<AssetThumbnails>,
+    cache: Res<AssetBrowserCache>,
+    editor_settings: Res<EditorSettings>,
+    jobs: Res<EditorJobs>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    gltfs: Res<Assets<Gltf>>,
+    scenes: Res<Assets<Scene>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !editor_settings.asset_grid_view {
+        return;
+    }
+
+    let entries: HashMap<&str, (AssetKind, Option<SystemTime>)> = cache
+        .entries
+        .iter()
+        .filter(|entry| matches!(entry.kind, AssetKind::Image | AssetKind::Model))
+        .map(|entry| (entry.path.as_str(), (entry.kind, entry.modified)))
+        .collect();
+    let stale: Vec<String> = thumbnails
+        .thumbnails
+        .iter()
+        .filter(|(path, thumbnail)| {
+            entries
+                .get(path.as_str())
+                .is_none_or(|(_, modified)| *modified != thumbnail.modified)
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    for path in stale {
+        thumbnails.remove(&path);
+    }
+
+    let mut finished: Vec<(String, ThumbnailState)> = Vec::new();
+    for (path, thumbnail) in &thumbnails.thumbnails {
+        let next = match &thumbnail.state {
+            ThumbnailState::Loading(job) => match job.poll() {
+                None => continue,
+                Some(Ok(Some(image))) => ready_state(image, &mut images),
+                // Not cached yet; models are loaded here and drawn on a job
+                Some(Ok(None)) => ThumbnailState::LoadingModel(load_model(&asset_server, path)),
+                Some(Err(err)) => {
+                    warn!("Failed to make a thumbnail for {}: {}", path, err);
+                    ThumbnailState::Failed
+                }
+            },
+            ThumbnailState::LoadingModel(model) => {
+                let id = match model {
+                    ModelHandle::Gltf(handle) => handle.id().untyped(),
+                    ModelHandle::Mesh(handle) => handle.id().untyped(),
+                };
+                if matches!(asset_server.get_load_state(id), Some(bevy::asset::LoadState::Failed(_))) {
+                    ThumbnailState::Failed
+                } else if !asset_server.is_loaded_with_dependencies(id) {
+                    continue;
+                } else {
+                    let triangles = model_triangles(model, &gltfs, &scenes, &meshes);
+                    let cached = cache_file(path, thumbnail.modified);
+                    let name = file_name(path);
+                    ThumbnailState::Rendering(jobs.spawn(format!("Thumbnail {name}"), JobPriority::Low, move |_| {
+                        let image = render_model_thumbnail(&triangles);
+                        save_thumbnail(&cached, &image)?;
+                        Ok(image)
+                    }))
+                }
+            }
+            ThumbnailState::Rendering(job) => match job.poll() {
+                None => continue,
+                Some(Ok(image)) => ready_state(image, &mut images),
+                Some(Err(err)) => {
+                    warn!("Failed to make a thumbnail for {}: {}", path, err);
+                    ThumbnailState::Failed
+                }
+            },
+            ThumbnailState::Ready(..) | ThumbnailState::Failed => continue,
+        };
+        finished.push((path.clone(), next));
+    }
+    for (path, state) in finished {
+        if let Some(thumbnail) = thumbnails.thumbnails.get_mut(&path) {
+            thumbnail.state = state;
+        }
+    }
+
+    let mut in_flight = thumbnails.thumbnails.values().filter(|thumbnail| thumbnail.in_flight()).count();
+    let known: HashSet<String> = thumbnails.thumbnails.keys().cloned().collect();
+    for entry in &cache.entries {
+        if in_flight >= MAX_IN_FLIGHT {
+            break;
+        }
+        let Some((kind, modified)) = entries.get(entry.path.as_str()) else {
+            continue;
+        };
+        if known.contains(&entry.path) {
+            continue;
+        }
+        let source = cache.root.join(&entry.path);
+        let cached = cache_file(&entry.path, *modified);
+        let is_image = *kind == AssetKind::Image;
+        let job = jobs.spawn(format!("Thumbnail {}", file_name(&entry.path)), JobPriority::Low, move |_| {
+            if let Ok(image) = image::open(&cached) {
+                return Ok(Some(image.to_rgba8()));
+            }
+            if !is_image {
+                return Ok(None);
+            }
+            let image = image::open(&source)?
+                .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                .to_rgba8();
+            save_thumbnail(&cached, &image)?;
+            Ok(Some(image))
+        });
+        thumbnails.thumbnails.insert(entry.path.clone(), Thumbnail {
+            modified: *modified,
+            state: ThumbnailState::Loading(job),
+        });
+        in_flight += 1;
+    }
+}
+
+fn ready_state(thumbnail: RgbaImage, images: &mut Assets<Image>) -> ThumbnailState {
+    let (width, height) = thumbnail.dimensions();
+    let image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        thumbnail.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    ThumbnailState::Ready(images.add(image), UVec2::new(width, height))
+}
+
+fn load_model(asset_server: &AssetServer, path: &str) -> ModelHandle {
+    let is_obj = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("obj"));
+    if is_obj {
+        ModelHandle::Mesh(asset_server.load(path.to_string()))
+    } else {
+        ModelHandle::Gltf(asset_server.load(path.to_string()))
+    }
+}
+
+/// Every triangle of a loaded model, placed the way its scene places it
+fn model_triangles(
+    model: &ModelHandle,
+    gltfs: &Assets<Gltf>,
+    scenes: &Assets<Scene>,
+    meshes: &Assets<Mesh>,
+) -> Vec<[Vec3; 3]> {
+    let mut triangles = Vec::new();
+    match model {
+        ModelHandle::Mesh(handle) => {
+            if let Some(mesh) = meshes.get(handle) {
+                mesh_triangles(mesh, Mat4::IDENTITY, &mut triangles);
+            }
+        }
+        ModelHandle::Gltf(handle) => {
+            let scene = gltfs
+                .get(handle)
+                .and_then(|gltf| gltf.default_scene.as_ref().or(gltf.scenes.first()))
+                .and_then(|scene| scenes.get(scene));
+            let Some(scene) = scene else {
+                return triangles;
+            };
+            for entity in scene.world.iter_entities() {
+                let Some(mesh) = entity.get::<Handle<Mesh>>().and_then(|mesh| meshes.get(mesh)) else {
+                    continue;
+                };
+                mesh_triangles(mesh, scene_matrix(&scene.world, entity.id()), &mut triangles);
+            }
+        }
+    }
+    triangles
+}
+
+/// World matrix of an entity in a scene that hasn't been spawned, so has no
+/// global transforms yet
+fn scene_matrix(world: &World, entity: Entity) -> Mat4 {
+    let mut matrix = Mat4::IDENTITY;
+    let mut current = Some(entity);
+    while let Some(entity) = current.and_then(|entity| world.get_entity(entity)) {
+        if let Some(transform) = entity.get::<Transform>() {
+            matrix = transform.compute_matrix() * matrix;
+        }
+        current = entity.get::<Parent>().map(Parent::get);
+    }
+    matrix
+}
+
+fn mesh_triangles(mesh: &Mesh, matrix: Mat4, triangles: &mut Vec<[Vec3; 3]>) {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return;
+    };
+    let positions: Vec<Vec3> = positions
+        .iter()
+        .map(|position| matrix.transform_point3(Vec3::from(*position)))
+        .collect();
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    for triangle in indices.chunks_exact(3) {
+        if let (Some(a), Some(b), Some(c)) = (
+            positions.get(triangle[0]),
+            positions.get(triangle[1]),
+            positions.get(triangle[2]),
+        ) {
+            triangles.push([*a, *b, *c]);
+        }
+    }
+}
+
+/// Draw the triangles as lit clay, framed to fit, on a transparent
+/// background. Drawn at twice the size and scaled down to smooth the edges.
+fn render_model_thumbnail(triangles: &[[Vec3; 3]]) -> RgbaImage {
+    let size = THUMBNAIL_SIZE * 2;
+    let mut image = RgbaImage::new(size, size);
+    let mut depth = vec![f32::NEG_INFINITY; (size * size) as usize];
+
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for vertex in triangles.iter().flatten() {
+        min = min.min(*vertex);
+        max = max.max(*vertex);
+    }
+    let center = (min + max) * 0.5;
+    let radius = triangles
+        .iter()
+        .flatten()
+        .map(|vertex| vertex.distance(center))
+        .fold(1e-4, f32::max);
+
+    // Looking down from the front right, lit from over the camera's shoulder
+    let toward_camera = Vec3::new(1.0, 0.8, 1.0).normalize();
+    let right = Vec3::Y.cross(toward_camera).normalize();
+    let up = toward_camera.cross(right);
+    let light = Vec3::new(0.4, 1.0, 0.7).normalize();
+    let half = size as f32 * 0.5;
+    let scale = half * 0.95 / radius;
+    let project = |vertex: Vec3| {
+        let offset = vertex - center;
+        Vec3::new(
+            half + offset.dot(right) * scale,
+            half - offset.dot(up) * scale,
+            offset.dot(toward_camera),
+        )
+    };
+
+    for triangle in triangles {
+        let Some(normal) = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]).try_normalize() else {
+            continue;
+        };
+        // Both sides are drawn, so light whichever one faces the camera
+        let normal = if normal.dot(toward_camera) < 0.0 { -normal } else { normal };
+        let shade = 0.3 + 0.7 * normal.dot(light).max(0.0);
+        let color = Rgba([
+            (CLAY_COLOR[0] * shade) as u8,
+            (CLAY_COLOR[1] * shade) as u8,
+            (CLAY_COLOR[2] * shade) as u8,
+            255,
+        ]);
+
+        let [a, b, c] = triangle.map(project);
+        let area = edge(a, b, c);
+        if area.abs() < 1e-6 {
+            continue;
+        }
+        let x_range = a.x.min(b.x).min(c.x).floor().max(0.0) as u32..(a.x.max(b.x).max(c.x).ceil().min(size as f32)) as u32;
+        let y_range = a.y.min(b.y).min(c.y).floor().max(0.0) as u32..(a.y.max(b.y).max(c.y).ceil().min(size as f32)) as u32;
+        for y in y_range {
+            for x in x_range.clone() {
+                let pixel = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let wa = edge(b, c, pixel) / area;
+                let wb = edge(c, a, pixel) / area;
+                let wc = edge(a, b, pixel) / area;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let z = wa * a.z + wb * b.z + wc * c.z;
+                let index = (y * size + x) as usize;
+                if z > depth[index] {
+                    depth[index] = z;
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    image::imageops::thumbnail(&image, THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+}
+
+/// Twice the signed screen area of the triangle `a`, `b`, `p`
+fn edge(a: Vec3, b: Vec3, p: Vec3) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn cache_file(path: &str, modified: Option<SystemTime>) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    Path::new(THUMBNAIL_DIR).join(format!("{:016x}.png", hasher.finish()))
+}
+
+fn save_thumbnail(path: &Path, image: &RgbaImage) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save(path)?;
+    Ok(())
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path)
+}
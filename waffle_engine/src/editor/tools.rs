@@ -0,0 +1,228 @@
+//! Waffle Engine Editor Tools
+//! The active viewport tool decides what clicking and dragging in the viewport
+//! does. Project plugins can add their own tools with `add_editor_tool`.
+
+use bevy::input::keyboard::KeyCode;
+use bevy::prelude::*;
+
+use super::{EditorState, GizmoMode, ViewportView};
+use super::extensions::EditorExtensionContext;
+use super::foliage_paint::FoliageBrush;
+use super::terrain_sculpt::TerrainBrush;
+use crate::rendering::camera::{WaffleMainCamera, WaffleOrthoCamera};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditorTool {
+    Select,
+    Move,
+    Rotate,
+    Scale,
+    /// Give clicked meshes the paint color
+    Paint,
+    /// Click two points to measure the distance between them
+    Measure,
+    /// Paint terrain heights with the terrain brush
+    Sculpt,
+    /// Scatter copies of a mesh over surfaces into the selected foliage layer
+    Foliage,
+    /// Tool registered by a project plugin, by id
+    Custom(String),
+}
+
+impl EditorTool {
+    pub const BUILT_IN: [EditorTool; 8] = [
+        EditorTool::Select,
+        EditorTool::Move,
+        EditorTool::Rotate,
+        EditorTool::Scale,
+        EditorTool::Paint,
+        EditorTool::Measure,
+        EditorTool::Sculpt,
+        EditorTool::Foliage,
+    ];
+
+    /// Transform gizmo shown and dragged while this tool is active
+    pub fn gizmo_mode(&self) -> Option<GizmoMode> {
+        match self {
+            EditorTool::Move => Some(GizmoMode::Move),
+            EditorTool::Rotate => Some(GizmoMode::Rotate),
+            EditorTool::Scale => Some(GizmoMode::Scale),
+            _ => None,
+        }
+    }
+
+    /// Whether clicking in the viewport changes the selection
+    pub fn selects(&self) -> bool {
+        matches!(
+            self,
+            EditorTool::Select | EditorTool::Move | EditorTool::Rotate | EditorTool::Scale
+        )
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            EditorTool::Select => "Select",
+            EditorTool::Move => "Move",
+            EditorTool::Rotate => "Rotate",
+            EditorTool::Scale => "Scale",
+            EditorTool::Paint => "Paint",
+            EditorTool::Measure => "Measure",
+            EditorTool::Sculpt => "Sculpt",
+            EditorTool::Foliage => "Foliage",
+            EditorTool::Custom(id) => id,
+        }
+    }
+
+    pub fn shortcut(&self) -> Option<(KeyCode, &'static str)> {
+        match self {
+            EditorTool::Select => Some((KeyCode::KeyV, "V")),
+            EditorTool::Move => Some((KeyCode::KeyQ, "Q")),
+            EditorTool::Rotate => Some((KeyCode::KeyW, "W")),
+            EditorTool::Scale => Some((KeyCode::KeyE, "E")),
+            EditorTool::Paint => Some((KeyCode::KeyB, "B")),
+            EditorTool::Measure => Some((KeyCode::KeyM, "M")),
+            EditorTool::Sculpt => Some((KeyCode::KeyT, "T")),
+            EditorTool::Foliage => Some((KeyCode::KeyG, "G")),
+            EditorTool::Custom(_) => None,
+        }
+    }
+}
+
+/// The selected viewport tool and the options of the built-in tools
+#[derive(Resource)]
+pub struct ActiveTool {
+    pub tool: EditorTool,
+    /// Base color the Paint tool gives meshes
+    pub paint_color: Color,
+    /// Points picked with the Measure tool; a third click starts over
+    pub measure_points: Vec<Vec3>,
+    pub terrain_brush: TerrainBrush,
+    pub foliage_brush: FoliageBrush,
+}
+
+impl Default for ActiveTool {
+    fn default() -> Self {
+        Self {
+            tool: EditorTool::Move,
+            paint_color: Color::srgb(0.8, 0.3, 0.3),
+            measure_points: Vec::new(),
+            terrain_brush: TerrainBrush::default(),
+            foliage_brush: FoliageBrush::default(),
+        }
+    }
+}
+
+impl ActiveTool {
+    pub fn set(&mut self, tool: EditorTool) {
+        if self.tool != tool {
+            self.tool = tool;
+            self.measure_points.clear();
+        }
+    }
+
+    pub fn measured_distance(&self) -> Option<f32> {
+        match self.measure_points.as_slice() {
+            [start, end] => Some(start.distance(*end)),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when the viewport is clicked with a tool that does not select
+#[derive(Event, Clone, Debug)]
+pub struct ViewportToolClickEvent {
+    pub tool: EditorTool,
+    /// Mesh under the cursor
+    pub entity: Option<Entity>,
+    /// World position under the cursor, on the ground plane when no mesh was hit
+    pub point: Option<Vec3>,
+}
+
+/// Ray through the cursor from the camera of the viewport pane it is over
+pub fn viewport_pointer_ray(
+    editor_state: &EditorState,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+    ortho_camera_query: &Query<(&Camera, &GlobalTransform, &WaffleOrthoCamera)>,
+) -> Option<Ray3d> {
+    let pointer = editor_state.viewport_pointer_pos.filter(|_| editor_state.viewport_hovered)?;
+    let (camera, transform) = match editor_state.viewport_pointer_view {
+        ViewportView::Perspective => camera_query.get_single().ok()?,
+        ViewportView::Ortho(view) => ortho_camera_query
+            .iter()
+            .find(|(_, _, ortho)| ortho.view == view)
+            .map(|(camera, camera_transform, _)| (camera, camera_transform))?,
+    };
+    camera.viewport_to_world(transform, pointer)
+}
+
+pub type ToolClickFn = Box<dyn FnMut(&ViewportToolClickEvent, &mut EditorExtensionContext) + Send + Sync>;
+
+pub struct CustomEditorTool {
+    pub id: String,
+    pub label: String,
+    pub on_click: ToolClickFn,
+    /// Panic message once the tool has been disabled
+    pub error: Option<String>,
+}
+
+/// Give painted meshes their own copy of their material with the paint color
+pub fn apply_paint_tool_clicks(
+    mut commands: Commands,
+    mut events: EventReader<ViewportToolClickEvent>,
+    active_tool: Res<ActiveTool>,
+    material_query: Query<&Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in events.read() {
+        if event.tool != EditorTool::Paint {
+            continue;
+        }
+        let Some(entity) = event.entity else {
+            continue;
+        };
+        let Some(mut material) = material_query
+            .get(entity)
+            .ok()
+            .and_then(|handle| materials.get(handle))
+            .cloned()
+        else {
+            continue;
+        };
+        if material.base_color == active_tool.paint_color {
+            continue;
+        }
+        material.base_color = active_tool.paint_color;
+        commands.entity(entity).insert(materials.add(material));
+    }
+}
+
+pub fn apply_measure_tool_clicks(
+    mut events: EventReader<ViewportToolClickEvent>,
+    mut active_tool: ResMut<ActiveTool>,
+) {
+    for event in events.read() {
+        if event.tool != EditorTool::Measure {
+            continue;
+        }
+        let Some(point) = event.point else {
+            continue;
+        };
+        if active_tool.measure_points.len() >= 2 {
+            active_tool.measure_points.clear();
+        }
+        active_tool.measure_points.push(point);
+    }
+}
+
+pub fn draw_measure_tool(active_tool: Res<ActiveTool>, mut gizmos: Gizmos) {
+    if active_tool.tool != EditorTool::Measure {
+        return;
+    }
+    let color = Color::srgb(1.0, 0.85, 0.2);
+    for point in &active_tool.measure_points {
+        gizmos.sphere(*point, Quat::IDENTITY, 0.05, color);
+    }
+    if let [start, end] = active_tool.measure_points.as_slice() {
+        gizmos.line(*start, *end, color);
+    }
+}
@@ -0,0 +1,279 @@
+//! Waffle Engine Guided Tour
+//! Walks new users through the editor one step at a time, dimming everything
+//! but the panel a step talks about and explaining it in a callout beside it.
+//! Some steps ask the user to do something, like adding a cube, and move on
+//! by themselves once it's done. Any step can be skipped and the whole tour
+//! ended; how far the user got is kept in the editor settings, so the tour
+//! resumes where it was left and doesn't come back once finished.
+
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use super::EditorTab;
+
+/// How far the user is through the tour
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TourProgress {
+    /// Index of the step shown
+    pub step: usize,
+    /// Finished or ended early; Help > Start Tour starts it over
+    pub finished: bool,
+}
+
+impl TourProgress {
+    pub fn active(&self) -> bool {
+        !self.finished && self.step < STEPS.len()
+    }
+
+    /// The tab the current step points at, to bring to the front
+    pub fn target_tab(&self) -> Option<EditorTab> {
+        match STEPS.get(self.step)?.target {
+            TourTarget::Tab(tab) => Some(tab.tab()),
+            _ => None,
+        }
+    }
+}
+
+/// The part of the editor a step highlights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TourTarget {
+    /// No highlight; the callout sits in the middle of the window
+    Center,
+    Toolbar,
+    Tab(TourTab),
+}
+
+/// The docked panels steps can point at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TourTab {
+    Viewport,
+    Hierarchy,
+    Inspector,
+    Assets,
+}
+
+impl TourTab {
+    fn tab(self) -> EditorTab {
+        match self {
+            TourTab::Viewport => EditorTab::Viewport,
+            TourTab::Hierarchy => EditorTab::Hierarchy,
+            TourTab::Inspector => EditorTab::Inspector,
+            TourTab::Assets => EditorTab::Assets,
+        }
+    }
+}
+
+/// What moves a step on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TourAction {
+    /// The Next button
+    Next,
+    /// Adding an object to the scene
+    Spawn,
+    /// Selecting an object
+    Select,
+    /// Starting play mode
+    Play,
+}
+
+struct TourStep {
+    title: &'static str,
+    text: &'static str,
+    target: TourTarget,
+    action: TourAction,
+}
+
+const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Welcome to Waffle Engine",
+        text: "This short tour shows you around the editor. Use Next to move on, or End Tour to leave it at any time. \
+               You can start it again from Help > Start Tour.",
+        target: TourTarget::Center,
+        action: TourAction::Next,
+    },
+    TourStep {
+        title: "The Viewport",
+        text: "This is the viewport, where you see and arrange your scene. Hold the right mouse button and use \
+               WASD to fly around, and drag the gizmo of a selected object to move it.",
+        target: TourTarget::Tab(TourTab::Viewport),
+        action: TourAction::Next,
+    },
+    TourStep {
+        title: "The Hierarchy",
+        text: "This is the hierarchy. It lists every object in the scene; drag one onto another to make it a child.",
+        target: TourTarget::Tab(TourTab::Hierarchy),
+        action: TourAction::Next,
+    },
+    TourStep {
+        title: "Add a Cube",
+        text: "Open the + menu at the top of the hierarchy and choose Cube to drop a cube into the scene.",
+        target: TourTarget::Tab(TourTab::Hierarchy),
+        action: TourAction::Spawn,
+    },
+    TourStep {
+        title: "Select It",
+        text: "Click the cube, in the viewport or in the hierarchy, to select it.",
+        target: TourTarget::Tab(TourTab::Hierarchy),
+        action: TourAction::Select,
+    },
+    TourStep {
+        title: "The Inspector",
+        text: "The inspector shows the components of the selected object. Change its position, rotation and scale \
+               here, or add lights, scripts and more.",
+        target: TourTarget::Tab(TourTab::Inspector),
+        action: TourAction::Next,
+    },
+    TourStep {
+        title: "Your Assets",
+        text: "The asset browser shows the files of your project. Drag a model from here into the viewport to \
+               place it.",
+        target: TourTarget::Tab(TourTab::Assets),
+        action: TourAction::Next,
+    },
+    TourStep {
+        title: "Play Your Scene",
+        text: "Press > in the toolbar to play the scene. Stopping play puts everything back as it was.",
+        target: TourTarget::Toolbar,
+        action: TourAction::Play,
+    },
+    TourStep {
+        title: "You're Ready",
+        text: "That's the tour. Save your scene from the File menu, and have fun making something!",
+        target: TourTarget::Center,
+        action: TourAction::Next,
+    },
+];
+
+/// Where the highlighted parts of the editor were drawn this frame
+#[derive(Default)]
+pub struct TourTargets {
+    pub toolbar: Option<egui::Rect>,
+    pub tabs: Vec<(EditorTab, egui::Rect)>,
+}
+
+/// What the user did this frame, for steps waiting on an action
+#[derive(Default)]
+pub struct TourSignals {
+    pub spawned: bool,
+    pub selected: bool,
+    pub playing: bool,
+}
+
+impl TourSignals {
+    fn done(&self, action: TourAction) -> bool {
+        match action {
+            TourAction::Next => false,
+            TourAction::Spawn => self.spawned,
+            TourAction::Select => self.selected,
+            TourAction::Play => self.playing,
+        }
+    }
+}
+
+/// Draw the current step over the editor. Returns whether the progress
+/// changed, so it can be saved.
+pub fn draw_tour(
+    ctx: &egui::Context,
+    progress: &mut TourProgress,
+    targets: &TourTargets,
+    signals: &TourSignals,
+) -> bool {
+    if !progress.active() {
+        return false;
+    }
+    let before = *progress;
+    let step = &STEPS[progress.step];
+    if signals.done(step.action) {
+        progress.step += 1;
+        return true;
+    }
+
+    let highlight = match step.target {
+        TourTarget::Center => None,
+        TourTarget::Toolbar => targets.toolbar,
+        TourTarget::Tab(tab) => {
+            let tab = tab.tab();
+            targets.tabs.iter().find(|(drawn, _)| *drawn == tab).map(|(_, rect)| *rect)
+        }
+    };
+
+    // Dim the rest of the editor around the highlighted panel
+    let screen = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("editor_tour_dim")));
+    let dim = egui::Color32::from_black_alpha(140);
+    match highlight {
+        Some(rect) => {
+            let rect = rect.expand(4.0);
+            painter.rect_filled(egui::Rect::from_min_max(screen.min, egui::pos2(screen.max.x, rect.min.y)), 0.0, dim);
+            painter.rect_filled(egui::Rect::from_min_max(egui::pos2(screen.min.x, rect.max.y), screen.max), 0.0, dim);
+            painter.rect_filled(
+                egui::Rect::from_min_max(egui::pos2(screen.min.x, rect.min.y), egui::pos2(rect.min.x, rect.max.y)),
+                0.0,
+                dim,
+            );
+            painter.rect_filled(
+                egui::Rect::from_min_max(egui::pos2(rect.max.x, rect.min.y), egui::pos2(screen.max.x, rect.max.y)),
+                0.0,
+                dim,
+            );
+            painter.rect_stroke(rect, 4.0, egui::Stroke::new(2.0, ctx.style().visuals.selection.bg_fill));
+        }
+        None => {
+            painter.rect_filled(screen, 0.0, dim);
+        }
+    }
+
+    // The callout goes beside the highlight where there's room, inside it
+    // when the panel fills the window
+    let (pivot, position) = match highlight {
+        Some(rect) if screen.max.x - rect.max.x > 320.0 => {
+            (egui::Align2::LEFT_TOP, rect.right_top() + egui::vec2(12.0, 0.0))
+        }
+        Some(rect) if rect.min.x - screen.min.x > 320.0 => {
+            (egui::Align2::RIGHT_TOP, rect.left_top() - egui::vec2(12.0, 0.0))
+        }
+        Some(rect) if screen.max.y - rect.max.y > 160.0 => {
+            (egui::Align2::CENTER_TOP, rect.center_bottom() + egui::vec2(0.0, 12.0))
+        }
+        Some(rect) => (egui::Align2::CENTER_CENTER, rect.center()),
+        None => (egui::Align2::CENTER_CENTER, screen.center()),
+    };
+
+    egui::Window::new(step.title)
+        .id(egui::Id::new("editor_tour"))
+        .collapsible(false)
+        .resizable(false)
+        .order(egui::Order::Tooltip)
+        .pivot(pivot)
+        .current_pos(position)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            ui.set_max_width(300.0);
+            ui.label(step.text);
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.weak(format!("Step {} of {}", progress.step + 1, STEPS.len()));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let last = progress.step + 1 == STEPS.len();
+                    let next_label = match step.action {
+                        TourAction::Next if last => "Done",
+                        TourAction::Next => "Next",
+                        _ => "Skip Step",
+                    };
+                    if ui.button(next_label).clicked() {
+                        progress.step += 1;
+                        progress.finished = last;
+                    }
+                    if progress.step > 0 && ui.button("Back").clicked() {
+                        progress.step -= 1;
+                    }
+                    if !last && ui.button("End Tour").clicked() {
+                        progress.finished = true;
+                    }
+                });
+            });
+        });
+
+    *progress != before
+}
@@ -0,0 +1,108 @@
+//! Waffle Engine Editor Trash
+//! Deleted subtrees are saved as scene snapshots and despawned, so their
+//! scripts and systems stop with them. Restoring spawns them again from the
+//! snapshot for the rest of the session.
+
+use bevy::prelude::*;
+
+use super::scene_file::{capture_subtree, spawn_scene, SceneEntityQuery, SceneFile};
+use super::DeleteEntityEvent;
+use crate::rendering::scene::SceneRootEntity;
+
+/// Number of deletes kept before the oldest is dropped for good
+pub const TRASH_CAPACITY: usize = 20;
+
+/// A deleted subtree
+pub struct TrashEntry {
+    /// The deleted entity; it no longer exists, but names the entry
+    pub entity: Entity,
+    pub label: String,
+    pub parent: Option<Entity>,
+    /// The subtree as it was when deleted, the deleted entity first
+    pub scene: SceneFile,
+}
+
+#[derive(Resource, Default)]
+pub struct EditorTrash {
+    pub entries: Vec<TrashEntry>,
+}
+
+#[derive(Event)]
+pub struct RestoreDeletedEvent {
+    pub entity: Entity,
+}
+
+#[derive(Event)]
+pub struct EmptyTrashEvent;
+
+pub fn apply_delete_events(
+    mut commands: Commands,
+    mut events: EventReader<DeleteEntityEvent>,
+    mut trash: ResMut<EditorTrash>,
+    parent_query: Query<&Parent>,
+    scene_query: Query<SceneEntityQuery>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+) {
+    for event in events.read() {
+        let entity = event.entity;
+        if commands.get_entity(entity).is_none() || trash.entries.iter().any(|entry| entry.entity == entity) {
+            continue;
+        }
+
+        // Editor-only entities are not part of the scene and can't be deleted
+        let scene = capture_subtree(entity, &scene_query, &meshes, &materials);
+        let Some(captured) = scene.entities.first() else {
+            continue;
+        };
+        let label = captured
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("Entity {}", entity.index()));
+        trash.entries.push(TrashEntry {
+            entity,
+            label,
+            parent: parent_query.get(entity).ok().map(|parent| parent.get()),
+            scene,
+        });
+        commands.entity(entity).despawn_recursive();
+
+        if trash.entries.len() > TRASH_CAPACITY {
+            trash.entries.remove(0);
+        }
+    }
+}
+
+pub fn apply_restore_events(
+    mut commands: Commands,
+    mut events: EventReader<RestoreDeletedEvent>,
+    mut trash: ResMut<EditorTrash>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scene_root: Option<Res<SceneRootEntity>>,
+) {
+    for event in events.read() {
+        let Some(index) = trash.entries.iter().position(|entry| entry.entity == event.entity) else {
+            continue;
+        };
+        // Back under the old parent, or the scene root if that is gone too
+        let Some(parent) = trash.entries[index]
+            .parent
+            .filter(|parent| commands.get_entity(*parent).is_some())
+            .or_else(|| scene_root.as_ref().map(|root| root.0))
+        else {
+            continue;
+        };
+        let entry = trash.entries.remove(index);
+        spawn_scene(&mut commands, parent, &entry.scene, &asset_server, &mut meshes, &mut materials);
+        info!("Restored {}", entry.label);
+    }
+}
+
+pub fn apply_empty_trash_events(mut events: EventReader<EmptyTrashEvent>, mut trash: ResMut<EditorTrash>) {
+    if events.read().count() == 0 {
+        return;
+    }
+    trash.entries.clear();
+}
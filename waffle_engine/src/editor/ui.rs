@@ -1,5 +1,5 @@
-/// Editor UI Module
-/// Main UI components and tab management
+//! Editor UI Module
+//! Main UI components and tab management
 
 use bevy::prelude::*;
 use bevy_egui::egui;
@@ -7,38 +7,99 @@ use egui_dock::{DockState, TabViewer};
 
 use super::{
     AssetBrowserCache, EditorOutput, EditorState, EditorSettings, EditorTab, HierarchyReparentEvent,
-    HierarchySnapshot, SpawnAssetEvent, SpawnPrimitiveEvent,
+    CameraRigEditEvent, ConstraintEditEvent, DebugLabel, HierarchySnapshot, LuaScriptEditEvent, StateMachineEditEvent, ParticleEmitterEditEvent, TerrainEditEvent, FoliageEditEvent, WaterEditEvent, LodEditEvent, AudioSourceEditEvent, MaterialEditEvent, MaterialLibraryEvent, PivotEditEvent, RenderLayersEditEvent, InstancingEditEvent, SpawnAssetEvent, SurfaceEditEvent, SpawnPrimitiveEvent,
+    ReimportAssetEvent, KeyframeEditEvent, ViewportStats,
 };
+use super::external::OpenExternalEvent;
+use super::vcs::{VcsActionEvent, VcsStatus};
+use super::extensions::{EditorExtensionContext, EditorExtensions};
+use super::tools::ActiveTool;
+use super::sub_scene::SubSceneEditEvent;
+use super::jobs::{draw_jobs_panel, EditorJobs};
+use super::input_debug::{draw_input_debug_panel, GamepadInputs, InputDebugLog};
+use super::ecs_stats::{draw_ecs_stats_panel, EcsStats};
+use super::systems_panel::{draw_systems_panel, SystemsPanel};
+use super::resources_panel::{draw_resources_panel, ResourcesPanel};
+use super::inspector::InspectorQueries;
+use bevy::ecs::world::CommandQueue;
+use std::collections::HashMap;
 use super::panels::*;
 
 /// Tab viewer for the dock system
-pub struct EditorTabViewer<'a> {
+pub struct EditorTabViewer<'a, 'w, 's> {
     pub editor_state: &'a mut EditorState,
     pub editor_settings: &'a mut EditorSettings,
     pub editor_output: &'a mut EditorOutput,
     pub hierarchy: &'a HierarchySnapshot,
-    pub selected_transform: Option<&'a mut Transform>,
-    pub selected_name: Option<&'a mut Name>,
-    pub selected_material_handle: Option<Handle<StandardMaterial>>,
-    pub selected_overrides: Option<&'a mut crate::rendering::materials::PbrTextureOverrides>,
-    pub selected_environment: Option<&'a mut crate::rendering::scene::EnvironmentSettings>,
-    pub selected_atmosphere: Option<&'a mut crate::rendering::atmosphere::AtmosphereSettingsComponent>,
+    /// Components of the entities the inspector tabs show
+    pub inspector: &'a mut InspectorQueries<'w, 's>,
+    /// Portal preview textures, by portal entity
+    pub portal_previews: &'a HashMap<Entity, egui::TextureId>,
     pub material_assets: &'a mut Assets<StandardMaterial>,
+    pub material_library: &'a crate::rendering::materials::MaterialLibrary,
     pub asset_server: &'a AssetServer,
     pub selected_asset: Option<&'a str>,
-    pub selected_waffle_light: Option<&'a mut crate::rendering::lighting::WaffleLight>,
-    pub selected_directional_light: Option<&'a mut DirectionalLight>,
-    pub selected_point_light: Option<&'a mut PointLight>,
-    pub selected_spot_light: Option<&'a mut SpotLight>,
+    pub project_settings: &'a crate::core::project::ProjectSettings,
     pub diagnostics: &'a bevy::diagnostic::DiagnosticsStore,
+    pub tweens: &'a mut crate::core::tween::Tweens,
+    pub quest_log: &'a mut crate::core::quest::QuestLog,
+    pub game_variables: &'a mut crate::core::variables::GameVariables,
+    pub playing: bool,
+    pub entity_pools: &'a crate::core::pool::EntityPools,
+    pub instancing_stats: &'a crate::rendering::instancing::InstancingStats,
     pub asset_cache: &'a AssetBrowserCache,
+    /// Thumbnail textures and their sizes, by asset path
+    pub asset_thumbnail_ids: &'a HashMap<String, (egui::TextureId, egui::Vec2)>,
+    pub vcs_status: &'a VcsStatus,
     pub reparent_queue: &'a mut Vec<HierarchyReparentEvent>,
     pub spawn_primitive_queue: &'a mut Vec<SpawnPrimitiveEvent>,
     pub spawn_asset_queue: &'a mut Vec<SpawnAssetEvent>,
+    pub pivot_edit_queue: &'a mut Vec<PivotEditEvent>,
+    pub constraint_edit_queue: &'a mut Vec<ConstraintEditEvent>,
+    pub camera_rig_edit_queue: &'a mut Vec<CameraRigEditEvent>,
+    pub lua_script_edit_queue: &'a mut Vec<LuaScriptEditEvent>,
+    pub state_machine_edit_queue: &'a mut Vec<StateMachineEditEvent>,
+    pub particle_emitter_edit_queue: &'a mut Vec<ParticleEmitterEditEvent>,
+    pub terrain_edit_queue: &'a mut Vec<TerrainEditEvent>,
+    pub foliage_edit_queue: &'a mut Vec<FoliageEditEvent>,
+    pub water_edit_queue: &'a mut Vec<WaterEditEvent>,
+    pub lod_edit_queue: &'a mut Vec<LodEditEvent>,
+    pub sub_scene_edit_queue: &'a mut Vec<SubSceneEditEvent>,
+    pub audio_source_edit_queue: &'a mut Vec<AudioSourceEditEvent>,
+    pub material_edit_queue: &'a mut Vec<MaterialEditEvent>,
+    pub material_library_queue: &'a mut Vec<MaterialLibraryEvent>,
+    pub render_layers_edit_queue: &'a mut Vec<RenderLayersEditEvent>,
+    pub instancing_edit_queue: &'a mut Vec<InstancingEditEvent>,
+    pub surface_edit_queue: &'a mut Vec<SurfaceEditEvent>,
+    pub keyframe_edit_queue: &'a mut Vec<KeyframeEditEvent>,
+    pub reimport_queue: &'a mut Vec<ReimportAssetEvent>,
+    pub locate_missing_queue: &'a mut Vec<crate::rendering::placeholders::LocateMissingAssetEvent>,
+    pub bake_ao_volume_queue: &'a mut Vec<crate::rendering::ao_volume::BakeAoVolumeEvent>,
+    pub camera_shake_queue: &'a mut Vec<crate::rendering::camera_shake::CameraShakeEvent>,
+    pub preview_camera_shake_queue: &'a mut Vec<crate::rendering::camera_shake::PreviewCameraShakeEvent>,
+    /// Entities to open pinned inspectors for
+    pub pin_inspector_queue: &'a mut Vec<Entity>,
+    pub open_external_queue: &'a mut Vec<OpenExternalEvent>,
+    pub vcs_action_queue: &'a mut Vec<VcsActionEvent>,
     pub viewport_texture_id: Option<egui::TextureId>,
+    pub ortho_texture_ids: Vec<(crate::rendering::camera::OrthoView, egui::TextureId)>,
+    pub viewport_stats: Option<ViewportStats>,
+    pub debug_labels: Vec<DebugLabel>,
+    pub extensions: &'a mut EditorExtensions,
+    pub extension_commands: &'a mut CommandQueue,
+    pub active_tool: &'a mut ActiveTool,
+    pub jobs: &'a EditorJobs,
+    pub input_debug_log: &'a mut InputDebugLog,
+    pub ecs_stats: &'a mut EcsStats,
+    pub systems_panel: &'a mut SystemsPanel,
+    pub system_toggles: &'a mut crate::core::system_toggles::SystemToggles,
+    pub resources_panel: &'a mut ResourcesPanel,
+    pub gamepad_inputs: &'a GamepadInputs<'a>,
+    /// Where each tab was drawn, for the guided tour to point at
+    pub tab_rects: &'a mut Vec<(EditorTab, egui::Rect)>,
 }
 
-impl<'a> TabViewer for EditorTabViewer<'a> {
+impl TabViewer for EditorTabViewer<'_, '_, '_> {
     type Tab = EditorTab;
 
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
@@ -49,17 +110,43 @@ impl<'a> TabViewer for EditorTabViewer<'a> {
             EditorTab::Assets => "Assets".into(),
             EditorTab::Console => "Output".into(),
             EditorTab::Profiler => "Profiler".into(),
+            EditorTab::Tweens => "Tweens".into(),
+            EditorTab::Quests => "Quests".into(),
+            EditorTab::Jobs => "Jobs".into(),
+            EditorTab::Materials => "Materials".into(),
+            EditorTab::InputDebug => "Input Debug".into(),
+            EditorTab::EcsStats => "ECS Stats".into(),
+            EditorTab::Systems => "Systems".into(),
+            EditorTab::Resources => "Resources".into(),
+            EditorTab::ExtraInspector { pinned: Some(entity), .. } => match self.inspector.name(*entity) {
+                Some(name) => format!("Inspector: {name}").into(),
+                None => format!("Inspector: {}", entity.index()).into(),
+            },
+            EditorTab::ExtraInspector { pinned: None, .. } => "Inspector".into(),
+            EditorTab::BehaviorTree => "Behavior Tree".into(),
+            EditorTab::Dialogue => "Dialogue".into(),
+            EditorTab::Particles => "Particles".into(),
+            EditorTab::Custom(id) => self.extensions.panel_title(id).unwrap_or(id.as_str()).to_string().into(),
         }
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        self.tab_rects.push((tab.clone(), ui.max_rect()));
         match tab {
             EditorTab::Viewport => {
                 draw_viewport_panel(
                     ui,
                     self.editor_state,
                     self.editor_settings,
+                    self.active_tool,
+                    &self.extensions.tools,
+                    self.project_settings.units,
+                    self.project_settings.game_ui.safe_margin(),
                     self.viewport_texture_id,
+                    &self.ortho_texture_ids,
+                    self.viewport_stats.as_ref(),
+                    &self.debug_labels,
+                    self.diagnostics,
                 );
             }
             EditorTab::Hierarchy => {
@@ -71,35 +158,49 @@ impl<'a> TabViewer for EditorTabViewer<'a> {
                     self.reparent_queue,
                     self.spawn_primitive_queue,
                     self.spawn_asset_queue,
+                    self.material_library_queue,
                 );
             }
             EditorTab::Inspector => {
-                draw_inspector_panel(
-                    ui,
-                    self.editor_state,
-                    self.editor_settings,
-                    self.selected_transform.as_deref_mut(),
-                    self.selected_name.as_deref_mut(),
-                    self.selected_material_handle.as_ref(),
-                    self.selected_overrides.as_deref_mut(),
-                    self.selected_environment.as_deref_mut(),
-                    self.selected_atmosphere.as_deref_mut(),
-                    self.material_assets,
-                    self.asset_server,
-                    self.selected_asset,
-                    self.selected_waffle_light.as_deref_mut(),
-                    self.selected_directional_light.as_deref_mut(),
-                    self.selected_point_light.as_deref_mut(),
-                    self.selected_spot_light.as_deref_mut(),
-                );
+                let entity = self.editor_state.selection.primary();
+                self.draw_inspector(ui, entity, None);
             }
+            EditorTab::ExtraInspector { pinned, .. } => match *pinned {
+                Some(entity) if !self.inspector.contains(entity) => {
+                    ui.heading("Inspector");
+                    ui.separator();
+                    ui.label("The pinned entity no longer exists");
+                    if ui.button("Follow Selection").clicked() {
+                        *pinned = None;
+                    }
+                }
+                Some(entity) => {
+                    let mut is_pinned = true;
+                    self.draw_inspector(ui, Some(entity), Some(&mut is_pinned));
+                    if !is_pinned {
+                        *pinned = None;
+                    }
+                }
+                None => {
+                    let entity = self.editor_state.selection.primary();
+                    let mut is_pinned = false;
+                    self.draw_inspector(ui, entity, Some(&mut is_pinned));
+                    if is_pinned {
+                        *pinned = entity;
+                    }
+                }
+            },
             EditorTab::Assets => {
                 draw_assets_panel(
                     ui,
                     self.editor_state,
                     self.editor_settings,
                     self.asset_cache,
+                    self.asset_thumbnail_ids,
+                    self.vcs_status,
                     self.spawn_asset_queue,
+                    self.open_external_queue,
+                    self.vcs_action_queue,
                 );
             }
             EditorTab::Console => {
@@ -115,8 +216,68 @@ impl<'a> TabViewer for EditorTabViewer<'a> {
                     self.editor_state,
                     self.editor_settings,
                     self.diagnostics,
+                    self.entity_pools,
+                    self.instancing_stats,
+                );
+            }
+            EditorTab::Tweens => {
+                draw_tweens_panel(ui, self.tweens, self.hierarchy);
+            }
+            EditorTab::Quests => {
+                draw_quests_panel(ui, self.quest_log, self.game_variables, self.playing);
+            }
+            EditorTab::Jobs => {
+                draw_jobs_panel(ui, self.jobs);
+            }
+            EditorTab::InputDebug => {
+                draw_input_debug_panel(ui, self.input_debug_log, self.gamepad_inputs);
+            }
+            EditorTab::EcsStats => {
+                draw_ecs_stats_panel(ui, self.ecs_stats);
+            }
+            EditorTab::Systems => {
+                draw_systems_panel(ui, self.systems_panel, self.system_toggles);
+            }
+            EditorTab::Resources => {
+                draw_resources_panel(ui, self.resources_panel);
+            }
+            EditorTab::Materials => {
+                draw_material_library_panel(
+                    ui,
+                    self.editor_state,
+                    self.material_library,
+                    self.material_assets,
+                    self.material_library_queue,
+                );
+            }
+            EditorTab::BehaviorTree => {
+                draw_behavior_tree_panel(ui, &mut self.editor_state.behavior_editor);
+            }
+            EditorTab::Dialogue => {
+                draw_dialogue_panel(ui, &mut self.editor_state.dialogue_editor);
+            }
+            EditorTab::Particles => {
+                draw_particle_editor_panel(
+                    ui,
+                    &mut self.editor_state.particle_editor,
+                    self.editor_state.selection.primary(),
+                    self.inspector.particle_emitter(self.editor_state.selection.primary()).as_deref_mut(),
+                    self.project_settings.color_management.working_space,
+                    self.particle_emitter_edit_queue,
                 );
             }
+            EditorTab::Custom(id) => {
+                let mut ctx = EditorExtensionContext::new(self.editor_state.selection.primary(), self.extension_commands);
+                self.extensions.draw_panel(id, ui, &mut ctx);
+            }
+        }
+    }
+
+    fn id(&mut self, tab: &mut Self::Tab) -> egui::Id {
+        match tab {
+            // Extra inspectors share titles with each other and the main one
+            EditorTab::ExtraInspector { id, .. } => egui::Id::new(("extra_inspector", *id)),
+            _ => egui::Id::new(self.title(tab).text()),
         }
     }
 
@@ -133,3 +294,87 @@ impl<'a> TabViewer for EditorTabViewer<'a> {
     }
 }
 
+impl EditorTabViewer<'_, '_, '_> {
+    /// Draw an inspector for `entity`. `pinned` is set on extra inspectors,
+    /// whose pin toggle it holds; the main inspector always follows the selection.
+    fn draw_inspector(&mut self, ui: &mut egui::Ui, entity: Option<Entity>, pinned: Option<&mut bool>) {
+        let mut target = self.inspector.inspect(entity, self.hierarchy);
+        let portal_preview = entity.and_then(|entity| self.portal_previews.get(&entity).copied());
+        draw_inspector_panel(
+            ui,
+            self.editor_state,
+            self.editor_settings,
+            entity,
+            pinned,
+            target.transform.as_deref_mut(),
+            target.parent_transform,
+            target.name.as_mut().map(|name| name.reborrow()),
+            target.material_handle.as_ref(),
+            target.overrides.as_deref_mut(),
+            target.environment.as_deref_mut(),
+            target.atmosphere.as_deref_mut(),
+            self.material_assets,
+            self.asset_server,
+            self.selected_asset,
+            target.waffle_light.as_deref_mut(),
+            target.directional_light.as_deref_mut(),
+            target.point_light.as_deref_mut(),
+            target.spot_light.as_deref_mut(),
+            target.look_at.as_deref_mut(),
+            target.follow.as_deref_mut(),
+            target.stick_to_surface.as_deref_mut(),
+            target.vehicle.as_deref_mut(),
+            target.lens_flare.as_deref_mut(),
+            target.animator.as_deref_mut(),
+            target.state_machine.as_deref_mut(),
+            target.property_animation.as_deref_mut(),
+            target.ao_volume.as_mut().map(|(volume, baking)| (&mut **volume, *baking)),
+            target.camera_shake.as_deref_mut(),
+            target.dolly_track.as_deref_mut(),
+            target.camera_dolly.as_deref_mut(),
+            target.camera_crane.as_deref_mut(),
+            target.camera_focus.as_deref_mut(),
+            target.lua_script.as_deref_mut(),
+            target.particle_emitter.as_deref_mut(),
+            target.terrain.as_deref_mut(),
+            target.foliage.as_deref_mut(),
+            target.water.as_deref_mut(),
+            target.lod.as_mut().map(|(group, state)| (&mut **group, state.as_deref_mut())),
+            target.sub_scene,
+            target.audio_source.as_deref_mut(),
+            target.portal.as_deref_mut().map(|portal| (portal, portal_preview)),
+            target.render_layers.as_ref(),
+            target.missing_asset.as_ref(),
+            target.surface,
+            target.material_surface,
+            target.is_camera,
+            target.instanced,
+            self.project_settings,
+            self.hierarchy,
+            &self.asset_cache.entries,
+            self.pivot_edit_queue,
+            self.constraint_edit_queue,
+            self.camera_rig_edit_queue,
+            self.lua_script_edit_queue,
+            self.state_machine_edit_queue,
+            self.particle_emitter_edit_queue,
+            self.terrain_edit_queue,
+            self.foliage_edit_queue,
+            self.water_edit_queue,
+            self.lod_edit_queue,
+            self.sub_scene_edit_queue,
+            self.audio_source_edit_queue,
+            self.material_edit_queue,
+            self.render_layers_edit_queue,
+            self.instancing_edit_queue,
+            self.surface_edit_queue,
+            self.keyframe_edit_queue,
+            self.reimport_queue,
+            self.locate_missing_queue,
+            self.bake_ao_volume_queue,
+            self.camera_shake_queue,
+            self.preview_camera_shake_queue,
+            self.pin_inspector_queue,
+        );
+    }
+}
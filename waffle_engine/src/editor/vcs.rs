@@ -0,0 +1,275 @@
+//! Waffle Engine Version Control
+//! Git status badges for the Assets panel, so artists can see what they are
+//! about to commit. Uses the `git` command line tool when it is installed.
+
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on, poll_once};
+use bevy_egui::egui;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use super::AssetBrowserCache;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VcsFileStatus {
+    Modified,
+    Added,
+    Untracked,
+    Deleted,
+    Renamed,
+    Conflicted,
+    Ignored,
+}
+
+impl VcsFileStatus {
+    pub fn badge(self) -> &'static str {
+        match self {
+            VcsFileStatus::Modified => "M",
+            VcsFileStatus::Added => "A",
+            VcsFileStatus::Untracked => "New",
+            VcsFileStatus::Deleted => "D",
+            VcsFileStatus::Renamed => "R",
+            VcsFileStatus::Conflicted => "!",
+            VcsFileStatus::Ignored => "Ignored",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VcsFileStatus::Modified => "Modified",
+            VcsFileStatus::Added => "Added",
+            VcsFileStatus::Untracked => "New (untracked)",
+            VcsFileStatus::Deleted => "Deleted",
+            VcsFileStatus::Renamed => "Renamed",
+            VcsFileStatus::Conflicted => "Merge conflict",
+            VcsFileStatus::Ignored => "Ignored",
+        }
+    }
+
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            VcsFileStatus::Modified | VcsFileStatus::Renamed => egui::Color32::from_rgb(230, 180, 80),
+            VcsFileStatus::Added | VcsFileStatus::Untracked => egui::Color32::from_rgb(120, 200, 120),
+            VcsFileStatus::Deleted | VcsFileStatus::Conflicted => egui::Color32::from_rgb(230, 90, 90),
+            VcsFileStatus::Ignored => egui::Color32::from_rgb(120, 120, 120),
+        }
+    }
+
+    /// Whether the file has committed contents that a revert can restore.
+    /// A renamed file's new path isn't in the last commit, so it can't be
+    /// checked out from there.
+    pub fn can_revert(self) -> bool {
+        matches!(self, VcsFileStatus::Modified | VcsFileStatus::Deleted | VcsFileStatus::Conflicted)
+    }
+}
+
+struct GitSnapshot {
+    repo_root: PathBuf,
+    files: HashMap<String, VcsFileStatus>,
+    ignored_dirs: Vec<String>,
+}
+
+/// Git status of the files in the assets folder, keyed by asset path
+#[derive(Resource, Default)]
+pub struct VcsStatus {
+    /// `None` when the project is not inside a git repository
+    pub repo_root: Option<PathBuf>,
+    files: HashMap<String, VcsFileStatus>,
+    ignored_dirs: Vec<String>,
+    task: Option<Task<Option<GitSnapshot>>>,
+    last_refresh: Option<Instant>,
+}
+
+impl VcsStatus {
+    pub fn status(&self, path: &str) -> Option<VcsFileStatus> {
+        self.files.get(path).copied().or_else(|| {
+            self.ignored_dirs
+                .iter()
+                .any(|dir| path.starts_with(dir.as_str()))
+                .then_some(VcsFileStatus::Ignored)
+        })
+    }
+
+    pub fn request_refresh(&mut self) {
+        self.last_refresh = None;
+    }
+}
+
+#[derive(Event)]
+pub enum VcsActionEvent {
+    /// Restore the committed version of an asset
+    Revert(String),
+    /// Show the asset's changes in the user's configured `git difftool`
+    ViewDiff(String),
+}
+
+/// Poll git in the background and pick up the results
+pub fn refresh_vcs_status(mut vcs: ResMut<VcsStatus>, asset_cache: Res<AssetBrowserCache>) {
+    if let Some(task) = vcs.task.as_mut() {
+        let Some(snapshot) = block_on(poll_once(task)) else {
+            return;
+        };
+        vcs.task = None;
+        match snapshot {
+            Some(snapshot) => {
+                vcs.repo_root = Some(snapshot.repo_root);
+                vcs.files = snapshot.files;
+                vcs.ignored_dirs = snapshot.ignored_dirs;
+            }
+            None => {
+                vcs.repo_root = None;
+                vcs.files.clear();
+                vcs.ignored_dirs.clear();
+            }
+        }
+    }
+
+    let due = vcs
+        .last_refresh
+        .is_none_or(|last| last.elapsed() >= REFRESH_INTERVAL);
+    if !due {
+        return;
+    }
+    vcs.last_refresh = Some(Instant::now());
+    let assets_root = asset_cache.root.clone();
+    vcs.task = Some(IoTaskPool::get().spawn(async move { query_git_status(&assets_root) }));
+}
+
+pub fn apply_vcs_actions(
+    mut events: EventReader<VcsActionEvent>,
+    mut vcs: ResMut<VcsStatus>,
+    asset_cache: Res<AssetBrowserCache>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        let Some(repo_root) = vcs.repo_root.clone() else {
+            continue;
+        };
+        match event {
+            VcsActionEvent::Revert(path) => {
+                let file = asset_cache.root.join(path);
+                match run_git(&repo_root, &["checkout", "HEAD", "--"], &file) {
+                    Ok(()) => {
+                        info!("Reverted {}", path);
+                        asset_server.reload(path.clone());
+                    }
+                    Err(err) => error!("Failed to revert {}: {err}", path),
+                }
+                vcs.request_refresh();
+            }
+            VcsActionEvent::ViewDiff(path) => {
+                let file = asset_cache.root.join(path);
+                let spawned = Command::new("git")
+                    .current_dir(&repo_root)
+                    .args(["difftool", "--no-prompt", "--"])
+                    .arg(absolute(&file))
+                    .spawn();
+                if let Err(err) = spawned {
+                    error!("Failed to open diff for {}: {err}", path);
+                }
+            }
+        }
+    }
+}
+
+fn run_git(repo_root: &Path, args: &[&str], file: &Path) -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .current_dir(repo_root)
+        .args(args)
+        .arg(absolute(file))
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn query_git_status(assets_root: &Path) -> Option<GitSnapshot> {
+    let assets_root = assets_root.canonicalize().ok()?;
+    let output = Command::new("git")
+        .current_dir(&assets_root)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let repo_root = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+        .canonicalize()
+        .ok()?;
+
+    let output = Command::new("git")
+        .current_dir(&repo_root)
+        .args([
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--ignored=matching",
+            "--",
+        ])
+        .arg(&assets_root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut files = HashMap::new();
+    let mut ignored_dirs = Vec::new();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for (path, status) in parse_porcelain_status(&stdout) {
+        let Ok(relative) = repo_root.join(path).strip_prefix(&assets_root).map(Path::to_path_buf) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if status == VcsFileStatus::Ignored && path.ends_with('/') {
+            ignored_dirs.push(format!("{}/", relative.trim_end_matches('/')));
+        } else {
+            files.insert(relative, status);
+        }
+    }
+
+    Some(GitSnapshot {
+        repo_root,
+        files,
+        ignored_dirs,
+    })
+}
+
+/// Paths and statuses from `git status --porcelain=v1 -z` output, relative
+/// to the repository root
+fn parse_porcelain_status(output: &str) -> Vec<(&str, VcsFileStatus)> {
+    let mut parsed = Vec::new();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let Some((code, path)) = entry.split_at_checked(3) else {
+            continue;
+        };
+        let code = code.trim_end();
+        let status = match code {
+            "??" => VcsFileStatus::Untracked,
+            "!!" => VcsFileStatus::Ignored,
+            _ if code.contains('U') || code == "AA" || code == "DD" => VcsFileStatus::Conflicted,
+            _ if code.starts_with('R') || code.starts_with('C') => {
+                // The original path follows as its own entry, without a
+                // status code, however short it is.
+                entries.next();
+                VcsFileStatus::Renamed
+            }
+            _ if code.contains('D') => VcsFileStatus::Deleted,
+            _ if code.starts_with('A') => VcsFileStatus::Added,
+            _ => VcsFileStatus::Modified,
+        };
+        parsed.push((path, status));
+    }
+    parsed
+}
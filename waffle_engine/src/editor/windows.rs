@@ -1,10 +1,23 @@
-/// Editor Windows Module
-/// Floating windows and dialogs for the editor
+//! Editor Windows Module
+//! Floating windows and dialogs for the editor
 
 use bevy::prelude::*;
 use bevy_egui::egui;
 
-use super::{EditorState, EditorSettings};
+use super::{AssetKind, EditorState, EditorSettings, GizmoSettings};
+use super::external::ExternalToolSettings;
+use super::collab::{CollabRole, CollabSession, DEFAULT_COLLAB_PORT};
+use super::asset_refs::{can_write_placeholder, AssetFileAction, AssetFileDialog, AssetFileEvent};
+use super::extensions::{draw_disabled_extension, run_guarded, EditorExtensions};
+use super::theme::{EditorPalette, PalettePreset};
+use crate::core::guard::DisabledSystems;
+use crate::core::input::{InputAxis, ResponseCurve};
+use crate::rendering::warmup::ShaderWarmup;
+use crate::rendering::hdr::HdrOutputStatus;
+use crate::rendering::color::{OutputTransform, WorkingColorSpace};
+use crate::core::project::{
+    BundleDefinition, HdrOutputMode, LengthUnit, ProjectSettings, SafeAreaPreset, SnapPreset, UiScaleMode, UpAxis,
+};
 
 /// About dialog window
 pub fn show_about_dialog(ctx: &egui::Context, open: &mut bool) {
@@ -65,13 +78,35 @@ pub fn show_preferences_dialog(
 
                 ui.separator();
 
+                ui.heading("Accessibility");
+
+                draw_ui_scale_setting(ui, &mut editor_settings.theme.ui_scale);
+                draw_palette_settings(ui, &mut editor_settings.theme.palette_preset, &mut editor_settings.theme.palette);
+
+                ui.separator();
+
+                ui.heading("Gizmos");
+
+                draw_gizmo_settings(ui, &mut editor_settings.gizmo);
+
+                ui.separator();
+
                 ui.heading("Controls");
 
-                // TODO: Add control settings
+                ui.label("F6 / Shift+F6: next / previous panel");
+                ui.label("Tab / Shift+Tab: next / previous control in a panel");
+                ui.label("Space or Enter: press the focused control");
 
                 ui.separator();
 
                 ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        match editor_settings.save() {
+                            Ok(()) => info!("Saved editor preferences"),
+                            Err(error) => error!("Failed to save editor preferences: {}", error),
+                        }
+                    }
+
                     if ui.button("Reset to Defaults").clicked() {
                         *editor_settings = EditorSettings::default();
                     }
@@ -88,6 +123,87 @@ pub fn show_preferences_dialog(
     *open = is_open;
 }
 
+fn draw_gizmo_settings(ui: &mut egui::Ui, gizmo: &mut GizmoSettings) {
+    ui.horizontal(|ui| {
+        ui.label("Size:");
+        ui.add(
+            egui::Slider::new(&mut gizmo.screen_size, 0.05..=0.5)
+                .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+        )
+        .on_hover_text("Axis length as a share of the viewport height, the same at any distance");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Line Thickness:");
+        ui.add(egui::Slider::new(&mut gizmo.line_thickness, 1.0..=8.0).step_by(0.5));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Handle Pick Radius:");
+        ui.add(egui::Slider::new(&mut gizmo.hit_radius, 2.0..=40.0).step_by(1.0).suffix(" pt"));
+    });
+    ui.checkbox(&mut gizmo.always_on_top, "Always on Top")
+        .on_hover_text("Off, objects in front of the gizmo hide it");
+}
+
+fn draw_ui_scale_setting(ui: &mut egui::Ui, ui_scale: &mut f32) {
+    // Rescaling the UI mid-drag would pull the slider out from under the
+    // pointer, so the new scale applies once the slider is let go
+    let pending_id = ui.make_persistent_id("ui_scale_pending");
+    let mut percent = ui
+        .data(|data| data.get_temp::<f32>(pending_id))
+        .unwrap_or(*ui_scale * 100.0);
+    let response = ui
+        .horizontal(|ui| {
+            ui.label("UI Scale:");
+            ui.add(egui::Slider::new(&mut percent, 50.0..=300.0).step_by(5.0).suffix("%"))
+        })
+        .inner;
+    if response.dragged() {
+        ui.data_mut(|data| data.insert_temp(pending_id, percent));
+    } else {
+        ui.data_mut(|data| data.remove::<f32>(pending_id));
+        if response.changed() || response.drag_stopped() {
+            *ui_scale = percent / 100.0;
+        }
+    }
+}
+
+fn draw_palette_settings(ui: &mut egui::Ui, preset: &mut PalettePreset, palette: &mut EditorPalette) {
+    ui.horizontal(|ui| {
+        ui.label("Gizmo Colors:");
+        egui::ComboBox::from_id_source("palette_preset")
+            .selected_text(preset.label())
+            .show_ui(ui, |ui| {
+                for candidate in PalettePreset::ALL {
+                    if ui.selectable_label(*preset == candidate, candidate.label()).clicked() && *preset != candidate {
+                        *preset = candidate;
+                        // Custom keeps the colors it is switched from
+                        if candidate != PalettePreset::Custom {
+                            *palette = candidate.palette();
+                        }
+                    }
+                }
+            });
+    });
+
+    egui::Grid::new("palette_grid").num_columns(2).show(ui, |ui| {
+        let mut edited = false;
+        for (label, color) in [
+            ("X Axis:", &mut palette.x_axis),
+            ("Y Axis:", &mut palette.y_axis),
+            ("Z Axis:", &mut palette.z_axis),
+            ("Selection:", &mut palette.selection),
+            ("Multiple Selection:", &mut palette.group_selection),
+        ] {
+            ui.label(label);
+            edited |= ui.color_edit_button_srgb(color).changed();
+            ui.end_row();
+        }
+        if edited {
+            *preset = PalettePreset::Custom;
+        }
+    });
+}
+
 /// Asset import dialog
 pub fn show_asset_import_dialog(ctx: &egui::Context, open: &mut bool) {
     let mut is_open = *open;
@@ -127,29 +243,428 @@ pub fn show_asset_import_dialog(ctx: &egui::Context, open: &mut bool) {
     *open = is_open;
 }
 
-/// Save scene dialog
-pub fn show_save_scene_dialog(ctx: &egui::Context, open: &mut bool) {
+/// Project settings window; changes are shared with the team through project.ron
+pub fn show_project_settings_dialog(
+    ctx: &egui::Context,
+    open: &mut bool,
+    project_settings: &mut ProjectSettings,
+    hdr_status: &HdrOutputStatus,
+) {
     let mut is_open = *open;
     let mut should_close = false;
-    egui::Window::new("Save Scene")
+    egui::Window::new("Project Settings")
         .open(&mut is_open)
+        .resizable(false)
         .show(ctx, |ui| {
             ui.vertical(|ui| {
-                ui.heading("Save Scene");
+                ui.heading("General");
+
+                egui::Grid::new("project_general").num_columns(2).show(ui, |ui| {
+                    ui.label("Units:");
+                    egui::ComboBox::from_id_source("project_units")
+                        .selected_text(project_settings.units.label())
+                        .show_ui(ui, |ui| {
+                            for unit in LengthUnit::ALL {
+                                ui.selectable_value(&mut project_settings.units, unit, unit.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    let units = project_settings.units;
+                    ui.label("Default Grid Size:");
+                    length_drag_value(ui, &mut project_settings.default_grid_size, units, 0.1..=10.0);
+                    ui.end_row();
+
+                    ui.label("Import Up Axis:");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut project_settings.import_up_axis, UpAxis::Y, "Y up");
+                        ui.selectable_value(&mut project_settings.import_up_axis, UpAxis::Z, "Z up");
+                    });
+                    ui.end_row();
+
+                    ui.label("Startup Scene:");
+                    ui.add(egui::TextEdit::singleline(&mut project_settings.startup_scene).hint_text("None"));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.heading("Snapping Presets");
+
+                let units = project_settings.units;
+                let mut remove = None;
+                egui::Grid::new("project_snap_presets").num_columns(5).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.label("Move");
+                    ui.label("Rotate");
+                    ui.label("Scale");
+                    ui.end_row();
+                    for (index, preset) in project_settings.snap_presets.iter_mut().enumerate() {
+                        ui.add(egui::TextEdit::singleline(&mut preset.name).desired_width(90.0));
+                        length_drag_value(ui, &mut preset.translate, units, 0.001..=100.0);
+                        ui.add(
+                            egui::DragValue::new(&mut preset.rotate_degrees)
+                                .range(0.1..=180.0)
+                                .suffix("°"),
+                        );
+                        ui.add(egui::DragValue::new(&mut preset.scale).speed(0.01).range(0.001..=10.0));
+                        if ui.small_button("x").on_hover_text("Remove preset").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = remove {
+                    project_settings.snap_presets.remove(index);
+                }
+                if ui.button("Add Preset").clicked() {
+                    project_settings.snap_presets.push(SnapPreset {
+                        name: format!("Preset {}", project_settings.snap_presets.len() + 1),
+                        translate: 0.5,
+                        rotate_degrees: 15.0,
+                        scale: 0.1,
+                    });
+                }
+
+                ui.separator();
+                ui.heading("Discord Rich Presence");
+
+                let discord = &mut project_settings.discord_presence;
+                ui.checkbox(&mut discord.enabled, "Show what you are doing on Discord");
+                ui.add_enabled_ui(discord.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Application Id:");
+                        ui.text_edit_singleline(&mut discord.application_id);
+                    });
+                });
+                if discord.enabled && !cfg!(feature = "discord") {
+                    ui.label(
+                        egui::RichText::new("This build does not include the \"discord\" feature.")
+                            .color(egui::Color32::from_rgb(230, 160, 60)),
+                    );
+                }
+
+                ui.separator();
+                ui.heading("Asset Bundles");
+
+                let mut remove_bundle = None;
+                for (index, bundle) in project_settings.bundles.iter_mut().enumerate() {
+                    ui.push_id(("bundle", index), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Name:");
+                            ui.add(egui::TextEdit::singleline(&mut bundle.name).desired_width(120.0));
+                            let buildable = !bundle.name.trim().is_empty();
+                            if ui.add_enabled(buildable, egui::Button::new("Build")).clicked() {
+                                match bundle.build() {
+                                    Ok(count) => info!(
+                                        "Built {} with {} files",
+                                        bundle.output_path().display(),
+                                        count
+                                    ),
+                                    Err(err) => error!("Failed to build bundle \"{}\": {}", bundle.name, err),
+                                }
+                            }
+                            if ui.button("Remove").clicked() {
+                                remove_bundle = Some(index);
+                            }
+                        });
+                        let mut include = bundle.include.join(", ");
+                        ui.horizontal(|ui| {
+                            ui.label("Include:");
+                            if ui
+                                .add(egui::TextEdit::singleline(&mut include).hint_text("levels/dlc1, textures/dlc1"))
+                                .changed()
+                            {
+                                bundle.include = include
+                                    .split(',')
+                                    .map(|part| part.trim().to_string())
+                                    .filter(|part| !part.is_empty())
+                                    .collect();
+                            }
+                        });
+                    });
+                }
+                if let Some(index) = remove_bundle {
+                    project_settings.bundles.remove(index);
+                }
+                if ui.button("Add Bundle").clicked() {
+                    project_settings.bundles.push(BundleDefinition {
+                        name: format!("bundle{}", project_settings.bundles.len() + 1),
+                        include: Vec::new(),
+                    });
+                }
+
+                ui.separator();
+                ui.heading("Rendering");
 
-                ui.label("Scene Name:");
-                let mut scene_name = String::from("Untitled Scene");
-                ui.text_edit_singleline(&mut scene_name);
+                ui.checkbox(&mut project_settings.warm_up_shaders_on_start, "Warm up shaders on start")
+                    .on_hover_text("Compile the materials recorded by earlier warm-ups before they are first drawn");
+
+                let color = &mut project_settings.color_management;
+                egui::Grid::new("project_color_management").num_columns(2).show(ui, |ui| {
+                    ui.label("Working Space:");
+                    egui::ComboBox::from_id_source("project_working_space")
+                        .selected_text(color.working_space.label())
+                        .show_ui(ui, |ui| {
+                            for space in WorkingColorSpace::ALL {
+                                ui.selectable_value(&mut color.working_space, space, space.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Output Transform:");
+                    egui::ComboBox::from_id_source("project_output_transform")
+                        .selected_text(color.output_transform.label())
+                        .show_ui(ui, |ui| {
+                            for transform in OutputTransform::ALL {
+                                ui.selectable_value(&mut color.output_transform, transform, transform.label());
+                            }
+                        });
+                    ui.end_row();
+                });
+
+                let hdr = &mut project_settings.hdr_output;
+                egui::Grid::new("project_hdr_output").num_columns(2).show(ui, |ui| {
+                    ui.label("Display Output:");
+                    egui::ComboBox::from_id_source("project_hdr_mode")
+                        .selected_text(hdr.mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in HdrOutputMode::ALL {
+                                ui.selectable_value(&mut hdr.mode, mode, mode.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    // Only meaningful once the swapchain is really HDR, which Bevy 0.14 never sets up
+                    let hdr_presented = hdr_status.active_mode().is_some_and(|active| active != HdrOutputMode::Sdr);
+                    ui.label("Paper White:");
+                    ui.add_enabled(
+                        hdr_presented,
+                        egui::DragValue::new(&mut hdr.paper_white_nits).range(80.0..=500.0).suffix(" nits"),
+                    )
+                    .on_disabled_hover_text("Unsupported: the renderer only presents SDR");
+                    ui.end_row();
+
+                    ui.label("Peak Brightness:");
+                    ui.add_enabled(
+                        hdr_presented,
+                        egui::DragValue::new(&mut hdr.peak_brightness_nits)
+                            .range(hdr.paper_white_nits..=10000.0)
+                            .suffix(" nits"),
+                    )
+                    .on_disabled_hover_text("Unsupported: the renderer only presents SDR");
+                    ui.end_row();
+                });
+                if hdr_status.active_mode().is_some_and(|active| active != hdr.mode) && hdr.mode != HdrOutputMode::Sdr {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(230, 180, 80),
+                        "HDR output is not available on this display; showing SDR.",
+                    );
+                }
+
+                ui.separator();
+                ui.heading("Game UI");
+
+                let game_ui = &mut project_settings.game_ui;
+                egui::Grid::new("project_game_ui").num_columns(2).show(ui, |ui| {
+                    ui.label("Scaling:");
+                    egui::ComboBox::from_id_source("project_ui_scale_mode")
+                        .selected_text(game_ui.scale_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in UiScaleMode::ALL {
+                                ui.selectable_value(&mut game_ui.scale_mode, mode, mode.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    let scales_with_height = game_ui.scale_mode == UiScaleMode::ScaleWithHeight;
+                    ui.label("Reference Height:");
+                    ui.add_enabled(
+                        scales_with_height,
+                        egui::DragValue::new(&mut game_ui.reference_height).range(120.0..=4320.0).suffix(" px"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Scale Range:");
+                    ui.add_enabled_ui(scales_with_height, |ui| {
+                        ui.horizontal(|ui| {
+                            let max = game_ui.max_scale;
+                            ui.add(egui::DragValue::new(&mut game_ui.min_scale).speed(0.05).range(0.1..=max));
+                            let min = game_ui.min_scale;
+                            ui.add(egui::DragValue::new(&mut game_ui.max_scale).speed(0.05).range(min..=10.0));
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Safe Area:");
+                    egui::ComboBox::from_id_source("project_safe_area")
+                        .selected_text(game_ui.safe_area.label())
+                        .show_ui(ui, |ui| {
+                            for preset in SafeAreaPreset::ALL {
+                                ui.selectable_value(&mut game_ui.safe_area, preset, preset.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    if game_ui.safe_area == SafeAreaPreset::Custom {
+                        ui.label("Margins:");
+                        ui.horizontal(|ui| {
+                            let margin = &mut game_ui.custom_safe_margin;
+                            let mut percent = *margin * 100.0;
+                            let x = ui.add(egui::DragValue::new(&mut percent.x).speed(0.1).range(0.0..=25.0).prefix("X: ").suffix("%"));
+                            let y = ui.add(egui::DragValue::new(&mut percent.y).speed(0.1).range(0.0..=25.0).prefix("Y: ").suffix("%"));
+                            if x.changed() || y.changed() {
+                                *margin = percent / 100.0;
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Input");
+
+                let input = &mut project_settings.input;
+                egui::Grid::new("project_input_axes").num_columns(5).striped(true).show(ui, |ui| {
+                    ui.label("Axis");
+                    ui.label("Dead Zone");
+                    ui.label("Sensitivity");
+                    ui.label("Curve");
+                    ui.label("Invert");
+                    ui.end_row();
+
+                    for axis in InputAxis::ALL {
+                        let mut settings = input.axis(axis);
+                        ui.label(axis.label());
+                        if axis.is_bounded() {
+                            ui.add(egui::Slider::new(&mut settings.dead_zone, 0.0..=0.9).fixed_decimals(2));
+                        } else {
+                            ui.add(egui::DragValue::new(&mut settings.dead_zone).speed(0.1).range(0.0..=20.0));
+                        }
+                        ui.add(egui::DragValue::new(&mut settings.sensitivity).speed(0.01).range(0.01..=20.0));
+                        ui.add_enabled_ui(axis.is_bounded(), |ui| {
+                            egui::ComboBox::from_id_source(("project_input_curve", axis))
+                                .selected_text(settings.curve.label())
+                                .show_ui(ui, |ui| {
+                                    for curve in ResponseCurve::ALL {
+                                        ui.selectable_value(&mut settings.curve, curve, curve.label());
+                                    }
+                                });
+                        });
+                        ui.checkbox(&mut settings.invert, "");
+                        ui.end_row();
+
+                        if settings != input.axis(axis) {
+                            *input.axis_mut(axis) = settings;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Mouse dead zones are in pixels; stick and trigger ones are a fraction of full tilt.");
+                    if ui.button("Reset Input").clicked() {
+                        input.axes.clear();
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Analytics");
+
+                ui.horizontal(|ui| {
+                    ui.label("Endpoint:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut project_settings.analytics_endpoint)
+                            .hint_text("https://collector.example.com/events"),
+                    );
+                });
+                ui.label("Events are only sent for players who opt in.");
+
+                ui.separator();
+                ui.heading("Render Layers");
+
+                egui::Grid::new("project_render_layers").num_columns(2).show(ui, |ui| {
+                    for (layer, name) in project_settings.render_layers.iter_mut().enumerate() {
+                        ui.label(format!("Layer {}:", layer));
+                        ui.text_edit_singleline(name);
+                        ui.end_row();
+                    }
+                });
 
                 ui.separator();
 
                 ui.horizontal(|ui| {
                     if ui.button("Save").clicked() {
-                        // TODO: Save scene
+                        match project_settings.save() {
+                            Ok(()) => info!("Saved project settings"),
+                            Err(error) => error!("Failed to save project settings: {}", error),
+                        }
+                    }
+
+                    if ui.button("Close").clicked() {
                         should_close = true;
                     }
+                });
+            });
+        });
+    if should_close {
+        is_open = false;
+    }
+    *open = is_open;
+}
 
-                    if ui.button("Cancel").clicked() {
+/// Edit a length stored in meters in the project's display unit
+fn length_drag_value(ui: &mut egui::Ui, meters: &mut f32, units: LengthUnit, range: std::ops::RangeInclusive<f32>) {
+    let mut value = units.from_meters(*meters);
+    let range = units.from_meters(*range.start())..=units.from_meters(*range.end());
+    let response = ui.add(
+        egui::DragValue::new(&mut value)
+            .speed(units.from_meters(0.01))
+            .range(range)
+            .suffix(format!(" {}", units.suffix())),
+    );
+    if response.changed() {
+        *meters = units.to_meters(value);
+    }
+}
+
+/// Per-kind commands used by "Open in External Editor"
+pub fn show_external_tools_dialog(ctx: &egui::Context, open: &mut bool, tools: &mut ExternalToolSettings) {
+    let mut is_open = *open;
+    let mut should_close = false;
+    egui::Window::new("External Tools")
+        .open(&mut is_open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.label("Leave empty to use the system default application.");
+                ui.label("{file} is replaced with the asset path, e.g. \"code --wait {file}\".");
+
+                ui.separator();
+
+                egui::Grid::new("external_tools_grid").num_columns(2).show(ui, |ui| {
+                    for (kind, label) in [
+                        (AssetKind::Script, "Scripts:"),
+                        (AssetKind::Image, "Textures:"),
+                        (AssetKind::Model, "Models:"),
+                        (AssetKind::Audio, "Audio:"),
+                        (AssetKind::Material, "Materials:"),
+                        (AssetKind::Other, "Other:"),
+                    ] {
+                        ui.label(label);
+                        ui.text_edit_singleline(tools.tool_mut(kind));
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        match tools.save() {
+                            Ok(()) => info!("Saved external tools"),
+                            Err(error) => error!("Failed to save external tools: {}", error),
+                        }
+                    }
+
+                    if ui.button("Close").clicked() {
                         should_close = true;
                     }
                 });
@@ -161,3 +676,292 @@ pub fn show_save_scene_dialog(ctx: &egui::Context, open: &mut bool) {
     *open = is_open;
 }
 
+/// Host or join a shared editing session
+/// Progress of a shader warm-up; shown while one runs
+pub fn show_shader_warmup_window(ctx: &egui::Context, warmup: &ShaderWarmup) {
+    if !warmup.is_running() {
+        return;
+    }
+    egui::Window::new("Warming Up Shaders")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+        .show(ctx, |ui| match &warmup.status {
+            Some(status) => {
+                ui.label(format!("{} materials", status.materials));
+                ui.add(
+                    egui::ProgressBar::new(status.fraction())
+                        .text(format!("{} / {} pipelines", status.compiled, status.compiled + status.waiting)),
+                );
+            }
+            None => {
+                ui.label("Waiting for materials to load...");
+            }
+        });
+}
+
+pub fn show_collaboration_dialog(ctx: &egui::Context, open: &mut bool, session: &mut CollabSession) {
+    let mut is_open = *open;
+    let mut should_close = false;
+    egui::Window::new("Collaboration")
+        .open(&mut is_open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Your Name:");
+                    ui.text_edit_singleline(&mut session.user_name);
+                    ui.color_edit_button_srgb(&mut session.color);
+                });
+
+                ui.separator();
+
+                match session.role {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.label("Address:");
+                            ui.text_edit_singleline(&mut session.address);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Session Token:");
+                            ui.add(egui::TextEdit::singleline(&mut session.token).password(true));
+                            if ui.small_button("Copy").clicked() {
+                                ui.output_mut(|output| output.copied_text = session.token.clone());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Hosts share this with collaborators, who paste it here before joining");
+                        ui.checkbox(&mut session.allow_lan, "Allow LAN Connections")
+                            .on_hover_text("Host for other machines on the network, not only this one");
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Host Session")
+                                .on_hover_text(format!("Listen for other editors on port {}", DEFAULT_COLLAB_PORT))
+                                .clicked()
+                            {
+                                if let Err(error) = session.host(DEFAULT_COLLAB_PORT) {
+                                    error!("Failed to host collaboration session: {}", error);
+                                }
+                            }
+                            if ui.button("Join Session").clicked() {
+                                if let Err(error) = session.join() {
+                                    error!("Failed to join {}: {}", session.address, error);
+                                }
+                            }
+                        });
+                    }
+                    Some(role) => {
+                        ui.label(match role {
+                            CollabRole::Host => format!("Hosting on port {}", DEFAULT_COLLAB_PORT),
+                            CollabRole::Client => format!("Connected to {}", session.address),
+                        });
+                        ui.label("Connected editors:");
+                        if session.peers.is_empty() {
+                            ui.label("  (nobody yet)");
+                        }
+                        for peer in session.peers.values() {
+                            let [r, g, b] = peer.color;
+                            ui.colored_label(egui::Color32::from_rgb(r, g, b), format!("  {}", peer.name));
+                        }
+                        if ui.button("Disconnect").clicked() {
+                            session.disconnect();
+                        }
+                    }
+                }
+
+                ui.separator();
+
+                if ui.button("Close").clicked() {
+                    should_close = true;
+                }
+            });
+        });
+    if should_close {
+        is_open = false;
+    }
+    *open = is_open;
+}
+
+/// Settings pages registered by project plugins, and plugin systems that were
+/// disabled after panicking
+pub fn show_plugin_settings_dialog(
+    ctx: &egui::Context,
+    open: &mut bool,
+    extensions: &mut EditorExtensions,
+    disabled_systems: &mut DisabledSystems,
+) {
+    let mut is_open = *open;
+    let mut should_close = false;
+    egui::Window::new("Plugin Settings")
+        .open(&mut is_open)
+        .default_width(480.0)
+        .show(ctx, |ui| {
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    for (index, page) in extensions.settings_pages.iter().enumerate() {
+                        if ui
+                            .selectable_label(extensions.selected_settings_page == index, &page.title)
+                            .clicked()
+                        {
+                            extensions.selected_settings_page = index;
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                ui.vertical(|ui| {
+                    let selected = extensions.selected_settings_page;
+                    match extensions.settings_pages.get_mut(selected) {
+                        Some(page) => {
+                            ui.heading(page.title.clone());
+                            if page.error.is_some() {
+                                draw_disabled_extension(ui, &mut page.error);
+                            } else {
+                                run_guarded("Settings page", &page.title, &mut page.error, || (page.draw)(ui));
+                            }
+                        }
+                        None => {
+                            ui.label("No plugin settings registered");
+                        }
+                    }
+                });
+            });
+
+            if !disabled_systems.systems.is_empty() {
+                ui.separator();
+                ui.heading("Disabled Systems");
+                let mut enable = None;
+                egui::Grid::new("disabled_plugin_systems").num_columns(2).show(ui, |ui| {
+                    for (name, message) in &disabled_systems.systems {
+                        ui.label(name).on_hover_text(message);
+                        if ui.button("Enable Again").clicked() {
+                            enable = Some(name.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(name) = enable {
+                    disabled_systems.enable(&name);
+                }
+            }
+
+            ui.separator();
+
+            if ui.button("Close").clicked() {
+                should_close = true;
+            }
+        });
+    if should_close {
+        is_open = false;
+    }
+    *open = is_open;
+}
+
+/// Move or delete an asset, offering to fix what refers to it
+pub fn show_asset_file_dialog(
+    ctx: &egui::Context,
+    dialog: &mut Option<AssetFileDialog>,
+    events: &mut EventWriter<AssetFileEvent>,
+) {
+    let Some(state) = dialog.as_mut() else {
+        return;
+    };
+    let title = match state.action {
+        AssetFileAction::Move => "Move Asset",
+        AssetFileAction::Delete => "Delete Asset?",
+    };
+    let mut keep_open = true;
+    egui::Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            match state.action {
+                AssetFileAction::Move => {
+                    ui.horizontal(|ui| {
+                        ui.label("Move to:");
+                        ui.add(egui::TextEdit::singleline(&mut state.target).desired_width(280.0));
+                    });
+                }
+                AssetFileAction::Delete => {
+                    ui.label(format!("Delete \"{}\"?", state.path));
+                }
+            }
+
+            if state.references.is_empty() {
+                ui.label("No other assets refer to it.");
+            } else {
+                let uses: usize = state.references.iter().map(|reference| reference.count).sum();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Referenced {} times in {} files:",
+                        uses,
+                        state.references.len()
+                    ))
+                    .color(egui::Color32::from_rgb(230, 160, 60)),
+                );
+                egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                    for reference in &state.references {
+                        ui.label(format!("{} ({})", reference.file, reference.count));
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                match state.action {
+                    AssetFileAction::Move => {
+                        let target = state.target.trim().trim_start_matches('/').replace('\\', "/");
+                        let valid = !target.is_empty() && target != state.path;
+                        let label = if state.references.is_empty() { "Move" } else { "Move and Update References" };
+                        if ui.add_enabled(valid, egui::Button::new(label)).clicked() {
+                            events.send(AssetFileEvent::Move {
+                                from: state.path.clone(),
+                                to: target.clone(),
+                                fix_references: true,
+                            });
+                            keep_open = false;
+                        }
+                        if !state.references.is_empty() && ui.add_enabled(valid, egui::Button::new("Move Only")).clicked() {
+                            events.send(AssetFileEvent::Move {
+                                from: state.path.clone(),
+                                to: target,
+                                fix_references: false,
+                            });
+                            keep_open = false;
+                        }
+                    }
+                    AssetFileAction::Delete => {
+                        if !state.references.is_empty() && can_write_placeholder(&state.path) {
+                            if ui
+                                .button("Replace with Placeholder")
+                                .on_hover_text("Keep a magenta stand-in at this path so references still load")
+                                .clicked()
+                            {
+                                events.send(AssetFileEvent::Delete {
+                                    path: state.path.clone(),
+                                    placeholder: true,
+                                });
+                                keep_open = false;
+                            }
+                        }
+                        let label = if state.references.is_empty() { "Delete" } else { "Delete Anyway" };
+                        if ui.button(label).clicked() {
+                            events.send(AssetFileEvent::Delete {
+                                path: state.path.clone(),
+                                placeholder: false,
+                            });
+                            keep_open = false;
+                        }
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    keep_open = false;
+                }
+            });
+        });
+    if !keep_open {
+        *dialog = None;
+    }
+}
@@ -2,6 +2,7 @@
 // A complete game engine built on Bevy with Lua scripting support
 
 use bevy::prelude::*;
+use bevy::asset::AssetMetaCheck;
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::log::LogPlugin;
 use bevy::window::WindowMode;
@@ -12,14 +13,35 @@ mod core;
 mod rendering;
 // Import editor module
 mod editor;
+// Import scripting module
+mod scripting;
+// Import audio module
+mod audio;
+// Import terrain module
+mod terrain;
 
 use core::*;
+use core::bundles::WaffleBundlesPlugin;
+use core::testing::WaffleTestPlugin;
 use rendering::*;
 use editor::*;
+use scripting::WaffleScriptingPlugin;
+use audio::WaffleAudioPlugin;
+use terrain::WaffleTerrainPlugin;
 
 // Main engine application
-fn main() {
+fn main() -> AppExit {
+    // `waffle_engine test [--update-goldens] [filter]` runs the project's tests headless
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("test") {
+        let (flags, filters): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+        let update_goldens = flags.iter().any(|flag| flag == "--update-goldens");
+        return run_tests(filters.into_iter().next(), update_goldens);
+    }
+
     App::new()
+        // Asset bundles replace the default asset source, so they come first
+        .add_plugins(WaffleBundlesPlugin)
         // Core plugins
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -33,6 +55,10 @@ fn main() {
         }).set(LogPlugin {
             custom_layer: editor::editor_log_layer,
             ..default()
+        }).set(AssetPlugin {
+            // `.meta` files hold our import settings, not Bevy's
+            meta_check: AssetMetaCheck::Never,
+            ..default()
         }))
         .add_plugins(FrameTimeDiagnosticsPlugin::default())
         .add_plugins(LogDiagnosticsPlugin::default())
@@ -43,8 +69,36 @@ fn main() {
         // Engine modules
         .add_plugins(WaffleCorePlugin)
         .add_plugins(WaffleRenderingPlugin)
+        .add_plugins(WaffleScriptingPlugin)
+        .add_plugins(WaffleAudioPlugin)
+        .add_plugins(WaffleTerrainPlugin)
         .add_plugins(WaffleEditorPlugin)
 
         // Start the engine
-        .run();
+        .run()
+}
+
+// Headless test application: no editor, hidden window for screenshots
+fn run_tests(filter: Option<String>, update_goldens: bool) -> AppExit {
+    App::new()
+        .add_plugins(WaffleBundlesPlugin)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Waffle Engine Tests".into(),
+                resolution: (1280.0, 720.0).into(),
+                visible: false,
+                ..default()
+            }),
+            ..default()
+        }).set(AssetPlugin {
+            meta_check: AssetMetaCheck::Never,
+            ..default()
+        }))
+        .add_plugins(WaffleCorePlugin)
+        .add_plugins(WaffleRenderingPlugin)
+        .add_plugins(WaffleScriptingPlugin)
+        .add_plugins(WaffleAudioPlugin)
+        .add_plugins(WaffleTerrainPlugin)
+        .add_plugins(WaffleTestPlugin { filter, update_goldens })
+        .run()
 }
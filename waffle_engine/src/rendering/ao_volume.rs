@@ -0,0 +1,421 @@
+//! Ambient Occlusion Volume Module
+//! Baked sky occlusion for caves, interiors and the ground under structures.
+//! A volume covers the unit cube of its entity's transform. Baking voxelizes
+//! the scene meshes around it and traces rays from a grid of probes to find
+//! how much sky each probe side sees. The result is drawn as an irradiance
+//! volume of negative ambient light, so materials inside lose the occluded
+//! share of the ambient term, on top of what SSAO takes away.
+
+use bevy::math::Affine3A;
+use bevy::pbr::irradiance_volume::IrradianceVolume;
+use bevy::pbr::LightProbe;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+
+/// Finest occupancy grid a bake may use, per axis
+const MAX_VOXELS_PER_AXIS: f32 = 192.0;
+
+/// Bakeable sky occlusion over the unit cube of the entity's transform
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AoVolume {
+    pub enabled: bool,
+    /// Probes along the volume's local axes
+    pub resolution: UVec3,
+    /// Rays traced from each side of a probe
+    pub rays_per_side: u32,
+    /// Geometry farther than this from a probe does not occlude it
+    pub max_distance: f32,
+    /// 0 keeps the ambient light, 1 removes all of its occluded share
+    pub strength: f32,
+    pub bake: Option<AoVolumeBake>,
+}
+
+impl Default for AoVolume {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: UVec3::new(16, 8, 16),
+            rays_per_side: 32,
+            max_distance: 10.0,
+            strength: 1.0,
+            bake: None,
+        }
+    }
+}
+
+/// Sky visibility of each probe side, from 0 for fully occluded to 255
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AoVolumeBake {
+    pub resolution: UVec3,
+    /// Six sides per probe in +X, -X, +Y, -Y, +Z, -Z order; probes X first,
+    /// then Y, then Z
+    pub visibility: Vec<u8>,
+}
+
+impl AoVolumeBake {
+    fn side(&self, probe: UVec3, side: usize) -> f32 {
+        let index = (probe.x + self.resolution.x * (probe.y + self.resolution.y * probe.z)) as usize;
+        self.visibility.get(index * 6 + side).copied().unwrap_or(255) as f32 / 255.0
+    }
+}
+
+/// Bake an `AoVolume` from the current scene geometry
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BakeAoVolumeEvent {
+    pub entity: Entity,
+}
+
+/// A bake running on the async compute pool
+#[derive(Component)]
+pub struct AoVolumeBaking(Task<AoVolumeBake>);
+
+/// What the volume's irradiance texture was built from
+#[derive(Component)]
+pub struct AoVolumeVoxels {
+    ambient_color: Color,
+    bake: AoVolumeBake,
+}
+
+struct BakeInput {
+    world_from_volume: Affine3A,
+    resolution: UVec3,
+    rays_per_side: u32,
+    max_distance: f32,
+    triangles: Vec<[Vec3; 3]>,
+}
+
+/// Collect the scene triangles for requested bakes and trace them off the
+/// main thread. Every visible scene mesh counts as static geometry.
+pub fn start_ao_volume_bakes(
+    mut commands: Commands,
+    mut events: EventReader<BakeAoVolumeEvent>,
+    meshes: Res<Assets<Mesh>>,
+    volumes: Query<(&AoVolume, &GlobalTransform)>,
+    mesh_query: Query<(&Handle<Mesh>, &GlobalTransform), Without<EditorHidden>>,
+) {
+    for event in events.read() {
+        let Ok((volume, transform)) = volumes.get(event.entity) else {
+            continue;
+        };
+        let mut triangles = Vec::new();
+        for (handle, mesh_transform) in &mesh_query {
+            if let Some(mesh) = meshes.get(handle) {
+                collect_triangles(mesh, mesh_transform, &mut triangles);
+            }
+        }
+        let input = BakeInput {
+            world_from_volume: transform.affine(),
+            resolution: volume.resolution.max(UVec3::ONE),
+            rays_per_side: volume.rays_per_side.max(1),
+            max_distance: volume.max_distance.max(0.01),
+            triangles,
+        };
+        info!(
+            "Baking AO volume {:?} from {} triangles",
+            event.entity,
+            input.triangles.len()
+        );
+        let task = AsyncComputeTaskPool::get().spawn(async move { bake_ao_volume(&input) });
+        commands.entity(event.entity).insert(AoVolumeBaking(task));
+    }
+}
+
+pub fn finish_ao_volume_bakes(
+    mut commands: Commands,
+    mut volumes: Query<(Entity, &mut AoVolume, &mut AoVolumeBaking)>,
+) {
+    for (entity, mut volume, mut baking) in &mut volumes {
+        let Some(bake) = block_on(poll_once(&mut baking.0)) else {
+            continue;
+        };
+        volume.bake = Some(bake);
+        commands.entity(entity).remove::<AoVolumeBaking>();
+        info!("Baked AO volume {:?}", entity);
+    }
+}
+
+/// Keep each baked volume's irradiance probe in step with the ambient light
+pub fn apply_ao_volumes(
+    mut commands: Commands,
+    ambient_light: Res<AmbientLight>,
+    mut images: ResMut<Assets<Image>>,
+    volumes: Query<(Entity, Ref<AoVolume>, Option<&IrradianceVolume>, Option<&AoVolumeVoxels>)>,
+) {
+    for (entity, volume, irradiance, voxels) in &volumes {
+        if !volume.is_changed() && !ambient_light.is_changed() {
+            continue;
+        }
+        let Some(bake) = volume.bake.as_ref().filter(|_| volume.enabled) else {
+            if irradiance.is_some() {
+                commands
+                    .entity(entity)
+                    .remove::<(LightProbe, IrradianceVolume, AoVolumeVoxels)>();
+            }
+            continue;
+        };
+
+        let up_to_date = voxels.is_some_and(|voxels| {
+            voxels.ambient_color == ambient_light.color && voxels.bake == *bake
+        });
+        let handle = match irradiance {
+            Some(irradiance) if up_to_date => irradiance.voxels.clone(),
+            Some(irradiance) => {
+                images.insert(irradiance.voxels.id(), bake_image(bake, ambient_light.color));
+                irradiance.voxels.clone()
+            }
+            None => images.add(bake_image(bake, ambient_light.color)),
+        };
+        let intensity = ambient_light.brightness * volume.strength.clamp(0.0, 1.0);
+        if up_to_date && irradiance.is_some_and(|irradiance| irradiance.intensity == intensity) {
+            continue;
+        }
+        commands.entity(entity).insert((
+            LightProbe,
+            IrradianceVolume {
+                voxels: handle,
+                intensity,
+            },
+            AoVolumeVoxels {
+                ambient_color: ambient_light.color,
+                bake: bake.clone(),
+            },
+        ));
+    }
+}
+
+fn collect_triangles(mesh: &Mesh, transform: &GlobalTransform, triangles: &mut Vec<[Vec3; 3]>) {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return;
+    };
+    let affine = transform.affine();
+    let vertex = |index: usize| positions.get(index).map(|position| affine.transform_point3(Vec3::from(*position)));
+    let mut push = |a: usize, b: usize, c: usize| {
+        if let (Some(a), Some(b), Some(c)) = (vertex(a), vertex(b), vertex(c)) {
+            triangles.push([a, b, c]);
+        }
+    };
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                push(tri[0] as usize, tri[1] as usize, tri[2] as usize);
+            }
+        }
+        Some(Indices::U32(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                push(tri[0] as usize, tri[1] as usize, tri[2] as usize);
+            }
+        }
+        None => {
+            for start in (0..positions.len().saturating_sub(2)).step_by(3) {
+                push(start, start + 1, start + 2);
+            }
+        }
+    }
+}
+
+/// Solid cells of the scene around a volume
+struct VoxelGrid {
+    min: Vec3,
+    voxel_size: f32,
+    dims: UVec3,
+    solid: Vec<bool>,
+}
+
+impl VoxelGrid {
+    fn new(min: Vec3, max: Vec3, voxel_size: f32) -> Self {
+        let voxel_size = voxel_size.max((max - min).max_element() / MAX_VOXELS_PER_AXIS);
+        let dims = ((max - min) / voxel_size).ceil().as_uvec3().max(UVec3::ONE);
+        Self {
+            min,
+            voxel_size,
+            dims,
+            solid: vec![false; (dims.x * dims.y * dims.z) as usize],
+        }
+    }
+
+    fn cell(&self, point: Vec3) -> Option<usize> {
+        let cell = ((point - self.min) / self.voxel_size).floor();
+        if cell.cmplt(Vec3::ZERO).any() {
+            return None;
+        }
+        let cell = cell.as_uvec3();
+        if cell.cmpge(self.dims).any() {
+            return None;
+        }
+        Some((cell.x + self.dims.x * (cell.y + self.dims.y * cell.z)) as usize)
+    }
+
+    fn is_solid(&self, point: Vec3) -> bool {
+        self.cell(point).is_some_and(|cell| self.solid[cell])
+    }
+
+    /// Mark the cells a triangle passes through by sampling its surface at
+    /// half the voxel size
+    fn fill_triangle(&mut self, [a, b, c]: [Vec3; 3]) {
+        let max = self.min + self.dims.as_vec3() * self.voxel_size;
+        if a.min(b).min(c).cmpgt(max).any() || a.max(b).max(c).cmplt(self.min).any() {
+            return;
+        }
+        let longest = a.distance(b).max(b.distance(c)).max(c.distance(a));
+        let steps = (longest / (self.voxel_size * 0.5)).ceil().max(1.0) as u32;
+        for i in 0..=steps {
+            for j in 0..=steps - i {
+                let point = a + (b - a) * (i as f32 / steps as f32) + (c - a) * (j as f32 / steps as f32);
+                if let Some(cell) = self.cell(point) {
+                    self.solid[cell] = true;
+                }
+            }
+        }
+    }
+
+    /// Whether a ray reaches `max_distance` without entering a solid cell.
+    /// The probe's own cell is skipped so probes resting on a surface still
+    /// see the sky above it.
+    fn ray_escapes(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        let step = self.voxel_size * 0.5;
+        let mut t = self.voxel_size;
+        while t < max_distance {
+            if self.is_solid(origin + direction * t) {
+                return false;
+            }
+            t += step;
+        }
+        true
+    }
+}
+
+fn bake_ao_volume(input: &BakeInput) -> AoVolumeBake {
+    let resolution = input.resolution;
+    let corners = (0..8).map(|index| {
+        let corner = Vec3::new(
+            if index & 1 == 0 { -0.5 } else { 0.5 },
+            if index & 2 == 0 { -0.5 } else { 0.5 },
+            if index & 4 == 0 { -0.5 } else { 0.5 },
+        );
+        input.world_from_volume.transform_point3(corner)
+    });
+    let (min, max) = corners.fold((Vec3::MAX, Vec3::MIN), |(min, max), corner| (min.min(corner), max.max(corner)));
+
+    // Half the probe spacing, so thin walls between probes are not missed
+    let axes = input.world_from_volume.matrix3;
+    let extent = Vec3::new(axes.x_axis.length(), axes.y_axis.length(), axes.z_axis.length());
+    let spacing = extent / resolution.as_vec3();
+    let mut grid = VoxelGrid::new(
+        min - Vec3::splat(input.max_distance),
+        max + Vec3::splat(input.max_distance),
+        spacing.min_element() * 0.5,
+    );
+    for triangle in &input.triangles {
+        grid.fill_triangle(*triangle);
+    }
+
+    let sides: Vec<Vec<Vec3>> = [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z]
+        .into_iter()
+        .map(|normal| hemisphere_directions(normal, input.rays_per_side))
+        .collect();
+
+    let mut visibility = Vec::with_capacity((resolution.x * resolution.y * resolution.z * 6) as usize);
+    for z in 0..resolution.z {
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let local = (UVec3::new(x, y, z).as_vec3() + 0.5) / resolution.as_vec3() - 0.5;
+                let probe = input.world_from_volume.transform_point3(local);
+                for directions in &sides {
+                    let open = directions
+                        .iter()
+                        .filter(|direction| grid.ray_escapes(probe, **direction, input.max_distance))
+                        .count();
+                    visibility.push((open * 255 / directions.len()) as u8);
+                }
+            }
+        }
+    }
+
+    AoVolumeBake { resolution, visibility }
+}
+
+/// Cosine weighted directions over the hemisphere around `normal`, so the
+/// share of open rays matches the ambient light a surface facing it gets
+fn hemisphere_directions(normal: Vec3, count: u32) -> Vec<Vec3> {
+    let tangent = normal.any_orthonormal_vector();
+    let bitangent = normal.cross(tangent);
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    (0..count)
+        .map(|index| {
+            let u = (index as f32 + 0.5) / count as f32;
+            let radius = u.sqrt();
+            let (sin, cos) = (index as f32 * golden_angle).sin_cos();
+            (tangent * radius * cos + bitangent * radius * sin + normal * (1.0 - u).sqrt()).normalize()
+        })
+        .collect()
+}
+
+/// Irradiance volume texture of a bake, laid out as Bevy expects: the three
+/// axes stacked along depth, and the side facing down an axis in the upper
+/// half of its slice
+fn bake_image(bake: &AoVolumeBake, ambient_color: Color) -> Image {
+    let resolution = bake.resolution;
+    let ambient = LinearRgba::from(ambient_color);
+    let size = Extent3d {
+        width: resolution.x,
+        height: resolution.y * 2,
+        depth_or_array_layers: resolution.z * 3,
+    };
+    let mut data = vec![0u8; (size.width * size.height * size.depth_or_array_layers * 8) as usize];
+    for z in 0..resolution.z {
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let probe = UVec3::new(x, y, z);
+                for side in 0..6 {
+                    let axis = side as u32 / 2;
+                    let negative = side % 2 == 1;
+                    let t = y + if negative { resolution.y } else { 0 };
+                    let p = z + axis * resolution.z;
+                    let texel = (x + size.width * (t + size.height * p)) as usize * 8;
+                    // Subtracts the occluded share of the ambient light
+                    let occlusion = 1.0 - bake.side(probe, side);
+                    let rgba = [
+                        -occlusion * ambient.red,
+                        -occlusion * ambient.green,
+                        -occlusion * ambient.blue,
+                        1.0,
+                    ];
+                    for (channel, value) in rgba.into_iter().enumerate() {
+                        let offset = texel + channel * 2;
+                        data[offset..offset + 2].copy_from_slice(&f32_to_f16(value).to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+    Image::new(
+        size,
+        TextureDimension::D3,
+        data,
+        TextureFormat::Rgba16Float,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Half float bits of a value in roughly -65504..65504, flushing values too
+/// small for a normal half to zero
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent <= 0 {
+        return sign;
+    }
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    sign | ((exponent as u16) << 10) | ((bits & 0x7f_ffff) >> 13) as u16
+}
@@ -1,5 +1,5 @@
-/// Atmosphere Module
-/// Handles atmospheric scattering and sky rendering
+//! Atmosphere Module
+//! Handles atmospheric scattering and sky rendering
 
 use bevy::prelude::*;
 use crate::rendering::scene::{SceneRootEntity, WaffleSceneObject};
@@ -1,5 +1,5 @@
-/// Camera Module
-/// Handles camera setup, control, and rendering
+//! Camera Module
+//! Handles camera setup, control, and rendering
 
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
@@ -7,10 +7,16 @@ use bevy::input::mouse::MouseMotion;
 use bevy::input::keyboard::KeyCode;
 use bevy::input::mouse::MouseButton;
 use bevy::input::mouse::MouseWheel;
-use bevy::render::camera::RenderTarget;
+use bevy::render::camera::{RenderTarget, ScalingMode};
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
 use bevy::window::CursorGrabMode;
 use crate::core::components::EditorHidden;
+use crate::core::cursor::GameCursor;
+use crate::core::input::{InputAxes, InputAxis};
+use crate::core::play::PlayState;
+
+/// Mouse pixels a second a fully tilted right stick turns the fly camera by
+const GAMEPAD_LOOK_SPEED: f32 = 900.0;
 
 #[derive(Component)]
 pub struct WaffleCamera {
@@ -27,6 +33,65 @@ pub struct WaffleMainCamera;
 #[derive(Component)]
 pub struct WaffleEditorCamera;
 
+/// Orthographic editor camera used by the quad viewport layout.
+#[derive(Component)]
+pub struct WaffleOrthoCamera {
+    pub view: OrthoView,
+    pub center: Vec3,
+    pub zoom: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OrthoView {
+    Top,
+    Front,
+    Right,
+}
+
+impl OrthoView {
+    pub const ALL: [OrthoView; 3] = [OrthoView::Top, OrthoView::Front, OrthoView::Right];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OrthoView::Top => "Top",
+            OrthoView::Front => "Front",
+            OrthoView::Right => "Right",
+        }
+    }
+
+    /// Direction from the view center towards the camera.
+    pub fn offset_direction(self) -> Vec3 {
+        match self {
+            OrthoView::Top => Vec3::Y,
+            OrthoView::Front => Vec3::Z,
+            OrthoView::Right => Vec3::X,
+        }
+    }
+
+    pub fn up(self) -> Vec3 {
+        match self {
+            OrthoView::Top => Vec3::NEG_Z,
+            OrthoView::Front | OrthoView::Right => Vec3::Y,
+        }
+    }
+}
+
+/// Vertical extent in world units visible in an ortho view at zoom 1.0.
+pub const ORTHO_VIEW_HEIGHT: f32 = 10.0;
+const ORTHO_CAMERA_DISTANCE: f32 = 100.0;
+
+#[derive(Clone)]
+pub struct OrthoViewTarget {
+    pub view: OrthoView,
+    pub image: Handle<Image>,
+    pub size: UVec2,
+}
+
+#[derive(Resource, Clone, Default)]
+pub struct OrthoViewTargets {
+    pub targets: Vec<OrthoViewTarget>,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum CameraType {
     Perspective,
@@ -47,8 +112,37 @@ pub struct CameraSettings {
 
 #[derive(Resource, Clone)]
 pub struct ViewportRenderTarget {
+    /// Image currently shown in the viewport panel.
     pub image: Handle<Image>,
+    /// Second buffer that receives the resized render before it is swapped in.
+    pub back_image: Handle<Image>,
     pub size: UVec2,
+    /// Size requested by the dock layout that has not been committed yet.
+    pub pending_size: Option<UVec2>,
+    /// Frames left before the back buffer has rendered and can be displayed.
+    pub swap_countdown: u8,
+}
+
+impl ViewportRenderTarget {
+    /// Image the main camera should currently render into.
+    pub fn render_image(&self) -> &Handle<Image> {
+        if self.swap_countdown > 0 {
+            &self.back_image
+        } else {
+            &self.image
+        }
+    }
+}
+
+pub fn create_viewport_image(size: Extent3d) -> Image {
+    let mut image = Image::default();
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Bgra8UnormSrgb;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image.resize(size);
+    image
 }
 
 pub fn setup_camera(
@@ -65,18 +159,14 @@ pub fn setup_camera(
         height: 720,
         ..default()
     };
-    let mut image = Image::default();
-    image.texture_descriptor.size = size;
-    image.texture_descriptor.dimension = TextureDimension::D2;
-    image.texture_descriptor.format = TextureFormat::Bgra8UnormSrgb;
-    image.texture_descriptor.usage =
-        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
-    image.resize(size);
-
-    let image_handle = images.add(image);
+    let image_handle = images.add(create_viewport_image(size));
+    let back_image_handle = images.add(create_viewport_image(size));
     commands.insert_resource(ViewportRenderTarget {
         image: image_handle.clone(),
+        back_image: back_image_handle,
         size: UVec2::new(size.width, size.height),
+        pending_size: None,
+        swap_countdown: 0,
     });
 
     // Create main perspective camera
@@ -102,6 +192,49 @@ pub fn setup_camera(
         Name::new("Main Camera"),
     )).id();
 
+    // Orthographic views for the quad viewport layout, inactive until enabled
+    let ortho_size = Extent3d {
+        width: 640,
+        height: 360,
+        ..default()
+    };
+    let mut ortho_targets = OrthoViewTargets::default();
+    for (index, view) in OrthoView::ALL.into_iter().enumerate() {
+        let image = images.add(create_viewport_image(ortho_size));
+        let center = Vec3::ZERO;
+        commands.spawn((
+            WaffleOrthoCamera {
+                view,
+                center,
+                zoom: 1.0,
+            },
+            EditorHidden,
+            Camera3dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(image.clone()),
+                    order: -2 - index as isize,
+                    is_active: false,
+                    ..default()
+                },
+                projection: OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical(ORTHO_VIEW_HEIGHT),
+                    far: ORTHO_CAMERA_DISTANCE * 2.0,
+                    ..default()
+                }
+                .into(),
+                transform: ortho_camera_transform(view, center),
+                ..default()
+            },
+            Name::new(format!("{} Camera", view.label())),
+        ));
+        ortho_targets.targets.push(OrthoViewTarget {
+            view,
+            image,
+            size: UVec2::new(ortho_size.width, ortho_size.height),
+        });
+    }
+    commands.insert_resource(ortho_targets);
+
     // Store camera settings
     commands.insert_resource(CameraSettings {
         main_camera_entity: Some(camera_entity),
@@ -112,6 +245,33 @@ pub fn setup_camera(
     });
 }
 
+pub fn ortho_camera_transform(view: OrthoView, center: Vec3) -> Transform {
+    Transform::from_translation(center + view.offset_direction() * ORTHO_CAMERA_DISTANCE)
+        .looking_at(center, view.up())
+}
+
+pub fn sync_viewport_camera_target(
+    target: Option<Res<ViewportRenderTarget>>,
+    mut cameras: Query<(&mut Camera, &mut Projection), With<WaffleMainCamera>>,
+) {
+    let Some(target) = target else {
+        return;
+    };
+    if !target.is_changed() {
+        return;
+    }
+
+    let desired = target.render_image();
+    for (mut camera, mut projection) in &mut cameras {
+        let up_to_date = matches!(&camera.target, RenderTarget::Image(handle) if handle == desired);
+        if !up_to_date {
+            camera.target = RenderTarget::Image(desired.clone());
+            // Force the camera to pick up the new target size this frame.
+            projection.set_changed();
+        }
+    }
+}
+
 pub fn update_camera(
     time: Res<Time>,
     mut camera_query: Query<(&mut Transform, &mut WaffleCamera), With<Camera3d>>,
@@ -120,11 +280,23 @@ pub fn update_camera(
     mut mouse_motion: EventReader<MouseMotion>,
     mut mouse_wheel: EventReader<MouseWheel>,
     mut window_query_mut: Query<&mut Window, With<PrimaryWindow>>,
+    play_state: Res<State<PlayState>>,
+    game_cursor: Res<GameCursor>,
+    input_axes: InputAxes,
 ) {
+    // While playing the game owns the window cursor; once it captures the
+    // pointer the editor fly camera stands down entirely.
+    let playing = *play_state.get() == PlayState::Playing;
+    if playing && game_cursor.is_captured() {
+        mouse_motion.clear();
+        mouse_wheel.clear();
+        return;
+    }
+
     let rmb_down = mouse_input.pressed(MouseButton::Right);
     let any_active = camera_query.iter().any(|(_, camera)| camera.is_active);
 
-    if let Ok(mut window) = window_query_mut.get_single_mut() {
+    if let Some(mut window) = window_query_mut.get_single_mut().ok().filter(|_| !playing) {
         if rmb_down && any_active {
             window.cursor.grab_mode = CursorGrabMode::Locked;
             window.cursor.visible = false;
@@ -156,15 +328,41 @@ pub fn update_camera(
         }
     }
 
+    // A gamepad flies the camera without holding the right mouse button, but
+    // only while editing with the viewport focused; in play the pad is the
+    // game's, and a camera is only active while its viewport has focus
+    let gamepad_flies = !playing && any_active;
+    if gamepad_flies {
+        let left_stick = input_axes.stick(InputAxis::LeftStickX, InputAxis::LeftStickY);
+        move_input.x += left_stick.x;
+        move_input.z -= left_stick.y;
+        move_input.y += input_axes.value(InputAxis::RightTrigger) - input_axes.value(InputAxis::LeftTrigger);
+    }
+
     let mut rotation_delta = Vec2::ZERO;
     for motion in mouse_motion.read() {
         rotation_delta += motion.delta;
     }
+    let config = input_axes.config();
+    rotation_delta = if rmb_down {
+        Vec2::new(
+            config.apply(InputAxis::MouseX, rotation_delta.x),
+            config.apply(InputAxis::MouseY, rotation_delta.y),
+        )
+    } else {
+        Vec2::ZERO
+    };
+    // Full tilt turns about as fast as a brisk mouse flick
+    if gamepad_flies {
+        let right_stick = input_axes.stick(InputAxis::RightStickX, InputAxis::RightStickY);
+        rotation_delta += Vec2::new(right_stick.x, -right_stick.y) * GAMEPAD_LOOK_SPEED * time.delta_seconds();
+    }
 
     let mut wheel_delta = 0.0;
     for wheel in mouse_wheel.read() {
         wheel_delta += wheel.y;
     }
+    let wheel_delta = config.apply(InputAxis::MouseWheel, wheel_delta);
 
     let speed_multiplier = if rmb_down
         && (keyboard_input.pressed(KeyCode::ShiftLeft)
@@ -187,17 +385,22 @@ pub fn update_camera(
             let up = transform.up();
             let local_dir =
                 (right * move_input.x) + (up * move_input.y) + (forward * -move_input.z);
-            let movement =
-                local_dir.normalize() * camera.movement_speed * speed_multiplier * time.delta_seconds();
+            // Keys move at full speed; a half-tilted stick moves at half
+            let movement = local_dir.clamp_length_max(1.0)
+                * camera.movement_speed
+                * speed_multiplier
+                * time.delta_seconds();
             transform.translation += movement;
         }
 
-        if rmb_down {
+        if rotation_delta != Vec2::ZERO {
             let yaw = -rotation_delta.x * camera.rotation_speed * 0.004;
             let pitch = -rotation_delta.y * camera.rotation_speed * 0.004;
             transform.rotate_y(yaw);
             transform.rotate_local_x(pitch);
+        }
 
+        if rmb_down {
             if wheel_delta.abs() > 0.0 {
                 let min_speed = 0.5;
                 let max_speed = 50.0;
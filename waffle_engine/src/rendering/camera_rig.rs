@@ -0,0 +1,255 @@
+//! Camera Rig Module
+//! Cinematic camera rigs for laying out cutscenes in the editor. A camera
+//! rides a `DollyTrack` with `CameraDolly`, swings on a `CameraCrane` arm
+//! from the dolly point (or a fixed pivot), and turns towards a target with
+//! `CameraFocus`. Every rig value is a plain field so cutscene tooling and
+//! scripts can key or drive them directly.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Samples per track segment when measuring or drawing the curve
+const TRACK_SAMPLES_PER_SEGMENT: usize = 16;
+
+/// Catmull-Rom curve through `points`, in the track entity's local space
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DollyTrack {
+    pub points: Vec<Vec3>,
+    /// Join the last point back to the first
+    pub closed: bool,
+}
+
+impl Default for DollyTrack {
+    fn default() -> Self {
+        Self {
+            points: vec![
+                Vec3::new(-4.0, 1.5, 0.0),
+                Vec3::new(0.0, 1.5, -2.0),
+                Vec3::new(4.0, 1.5, 0.0),
+            ],
+            closed: false,
+        }
+    }
+}
+
+impl DollyTrack {
+    fn segment_count(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            len if self.closed => len,
+            len => len - 1,
+        }
+    }
+
+    fn point(&self, index: isize) -> Vec3 {
+        let len = self.points.len() as isize;
+        let index = if self.closed { index.rem_euclid(len) } else { index.clamp(0, len - 1) };
+        self.points[index as usize]
+    }
+
+    /// Point on segment `segment` at `t` from 0 to 1
+    fn segment_point(&self, segment: usize, t: f32) -> Vec3 {
+        let i = segment as isize;
+        let (p0, p1, p2, p3) = (self.point(i - 1), self.point(i), self.point(i + 1), self.point(i + 2));
+        let t2 = t * t;
+        let t3 = t2 * t;
+        0.5 * ((2.0 * p1)
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    /// The curve as a local-space polyline
+    pub fn polyline(&self) -> Vec<Vec3> {
+        let segments = self.segment_count();
+        if segments == 0 {
+            return self.points.clone();
+        }
+        let mut line = Vec::with_capacity(segments * TRACK_SAMPLES_PER_SEGMENT + 1);
+        for segment in 0..segments {
+            for step in 0..TRACK_SAMPLES_PER_SEGMENT {
+                line.push(self.segment_point(segment, step as f32 / TRACK_SAMPLES_PER_SEGMENT as f32));
+            }
+        }
+        line.push(self.segment_point(segments - 1, 1.0));
+        line
+    }
+
+    /// Local length of the curve
+    pub fn length(&self) -> f32 {
+        self.polyline().windows(2).map(|pair| pair[0].distance(pair[1])).sum()
+    }
+
+    /// Local point at `fraction` of the curve's length, from 0 to 1
+    pub fn sample(&self, fraction: f32) -> Option<Vec3> {
+        let line = self.polyline();
+        let first = *line.first()?;
+        let total: f32 = line.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+        let mut remaining = fraction.clamp(0.0, 1.0) * total;
+        for pair in line.windows(2) {
+            let length = pair[0].distance(pair[1]);
+            if remaining <= length && length > 0.0 {
+                return Some(pair[0].lerp(pair[1], remaining / length));
+            }
+            remaining -= length;
+        }
+        Some(line.last().copied().unwrap_or(first))
+    }
+}
+
+/// Keep a camera on a dolly track
+#[derive(Component, Reflect, Clone, Debug)]
+pub struct CameraDolly {
+    /// Entity with the `DollyTrack`
+    pub track: Option<Entity>,
+    /// Fraction of the track's length, from 0 to 1
+    pub position: f32,
+    /// World units per second the dolly travels while playing
+    pub speed: f32,
+    /// Wrap around at the end of the track instead of stopping
+    pub looping: bool,
+}
+
+impl Default for CameraDolly {
+    fn default() -> Self {
+        Self {
+            track: None,
+            position: 0.0,
+            speed: 0.0,
+            looping: false,
+        }
+    }
+}
+
+/// Crane arm the camera sits at the tip of
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraCrane {
+    /// World pivot of the arm, used when the camera has no dolly
+    pub pivot: Vec3,
+    /// Height of the arm's base above the pivot or dolly point
+    pub base_height: f32,
+    pub length: f32,
+    /// Arm heading in degrees, 0 pointing along -Z
+    pub yaw: f32,
+    /// Arm elevation in degrees
+    pub pitch: f32,
+}
+
+impl Default for CameraCrane {
+    fn default() -> Self {
+        Self {
+            pivot: Vec3::ZERO,
+            base_height: 1.0,
+            length: 4.0,
+            yaw: 0.0,
+            pitch: 20.0,
+        }
+    }
+}
+
+impl CameraCrane {
+    /// Offset of the arm's tip from its pivot
+    pub fn tip_offset(&self) -> Vec3 {
+        let direction = Quat::from_euler(EulerRot::YXZ, self.yaw.to_radians(), self.pitch.to_radians(), 0.0)
+            * Vec3::NEG_Z;
+        Vec3::Y * self.base_height + direction * self.length
+    }
+}
+
+/// Turn the camera towards a target, easing in over `damping` seconds
+#[derive(Component, Reflect, Clone, Debug)]
+pub struct CameraFocus {
+    pub target: Option<Entity>,
+    /// Offset from the target's origin, in the target's space
+    pub offset: Vec3,
+    /// Approximate seconds to catch up with the target; 0 snaps every frame
+    pub damping: f32,
+    pub up: Vec3,
+}
+
+impl Default for CameraFocus {
+    fn default() -> Self {
+        Self {
+            target: None,
+            offset: Vec3::ZERO,
+            damping: 0.3,
+            up: Vec3::Y,
+        }
+    }
+}
+
+pub fn advance_camera_dollies(
+    time: Res<Time>,
+    tracks: Query<(&DollyTrack, &GlobalTransform)>,
+    mut dollies: Query<&mut CameraDolly>,
+) {
+    for mut dolly in &mut dollies {
+        if dolly.speed == 0.0 {
+            continue;
+        }
+        let Some((track, global)) = dolly.track.and_then(|track| tracks.get(track).ok()) else {
+            continue;
+        };
+        let length = track.length() * global.compute_transform().scale.max_element();
+        if length <= f32::EPSILON {
+            continue;
+        }
+        let position = dolly.position + dolly.speed * time.delta_seconds() / length;
+        dolly.position = if dolly.looping { position.rem_euclid(1.0) } else { position.clamp(0.0, 1.0) };
+    }
+}
+
+/// Place and aim rigged cameras; runs before transforms propagate
+pub fn apply_camera_rigs(
+    time: Res<Time>,
+    tracks: Query<(&DollyTrack, &GlobalTransform)>,
+    globals: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    mut rigs: Query<
+        (Entity, Option<&CameraDolly>, Option<&CameraCrane>, Option<&CameraFocus>, &mut Transform),
+        Or<(With<CameraDolly>, With<CameraCrane>, With<CameraFocus>)>,
+    >,
+) {
+    for (entity, dolly, crane, focus, mut transform) in &mut rigs {
+        let parent = parents
+            .get(entity)
+            .ok()
+            .and_then(|parent| globals.get(parent.get()).ok())
+            .copied()
+            .unwrap_or(GlobalTransform::IDENTITY);
+
+        let dolly_point = dolly.and_then(|dolly| {
+            let (track, global) = dolly.track.and_then(|track| tracks.get(track).ok())?;
+            Some(global.transform_point(track.sample(dolly.position)?))
+        });
+        let position = match (dolly_point, crane) {
+            (Some(point), Some(crane)) => Some(point + crane.tip_offset()),
+            (None, Some(crane)) => Some(crane.pivot + crane.tip_offset()),
+            (point, None) => point,
+        };
+        if let Some(position) = position {
+            transform.translation = parent.affine().inverse().transform_point3(position);
+        }
+
+        let Some(focus) = focus else {
+            continue;
+        };
+        let Some(target) = focus.target.and_then(|target| globals.get(target).ok()) else {
+            continue;
+        };
+        let world_position = position.unwrap_or_else(|| parent.transform_point(transform.translation));
+        let direction = target.transform_point(focus.offset) - world_position;
+        if direction.length_squared() < 1e-8 {
+            continue;
+        }
+        let parent_rotation = parent.compute_transform().rotation;
+        let desired = parent_rotation.inverse() * Transform::IDENTITY.looking_to(direction, focus.up).rotation;
+        transform.rotation = if focus.damping > 0.0 {
+            let blend = 1.0 - (-time.delta_seconds() / focus.damping).exp();
+            transform.rotation.slerp(desired, blend)
+        } else {
+            desired
+        };
+    }
+}
@@ -0,0 +1,150 @@
+//! Camera Shake Module
+//! Trauma based screen shake. Gameplay adds trauma with `CameraShake::shake`
+//! or a `CameraShakeEvent`; trauma decays over time and the shake strength
+//! is trauma raised to `trauma_exponent`, so small hits stay subtle and big
+//! ones feel violent. The offset is applied to the camera's global transform
+//! after propagation, so it never drifts into the camera's own transform.
+
+use bevy::prelude::*;
+
+use crate::core::random::Noise;
+use crate::rendering::camera::CameraSettings;
+
+/// How far apart the noise rows of each shaken axis sit
+const NOISE_ROW_SPACING: f32 = 37.0;
+
+#[derive(Component, Clone, Debug)]
+pub struct CameraShake {
+    /// 0 for still, 1 for the strongest shake
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+    pub trauma_exponent: f32,
+    /// Noise samples per second; higher is more jittery
+    pub frequency: f32,
+    /// Largest offset along the camera's right, up and back axes
+    pub max_translation: Vec3,
+    /// Largest pitch, yaw and roll in degrees
+    pub max_rotation: Vec3,
+    pub seed: u64,
+    noise: Noise,
+    noise_seed: u64,
+    time: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl CameraShake {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.2,
+            trauma_exponent: 2.0,
+            frequency: 18.0,
+            max_translation: Vec3::new(0.15, 0.15, 0.05),
+            max_rotation: Vec3::new(2.0, 2.0, 4.0),
+            seed,
+            noise: Noise::new(seed),
+            noise_seed: seed,
+            time: 0.0,
+        }
+    }
+
+    /// Add trauma, e.g. `camera.shake(0.6)` for an explosion nearby
+    pub fn shake(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma.max(0.0)).min(1.0);
+    }
+
+    /// Current strength, from 0 to 1
+    pub fn intensity(&self) -> f32 {
+        self.trauma.clamp(0.0, 1.0).powf(self.trauma_exponent.max(0.1))
+    }
+
+    /// Local offset of the camera this frame
+    pub fn offset(&self) -> Transform {
+        let intensity = self.intensity();
+        if intensity <= 0.0 {
+            return Transform::IDENTITY;
+        }
+        let sample = |row: f32| self.noise.perlin2(self.time * self.frequency, row * NOISE_ROW_SPACING);
+        let translation = Vec3::new(sample(0.0), sample(1.0), sample(2.0)) * self.max_translation * intensity;
+        let rotation = Vec3::new(sample(3.0), sample(4.0), sample(5.0)) * self.max_rotation * intensity;
+        Transform::from_translation(translation).with_rotation(Quat::from_euler(
+            EulerRot::YXZ,
+            rotation.y.to_radians(),
+            rotation.x.to_radians(),
+            rotation.z.to_radians(),
+        ))
+    }
+}
+
+/// Add trauma to a camera's shake; `None` shakes the active camera. Cameras
+/// without a `CameraShake` get the default one.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CameraShakeEvent {
+    pub camera: Option<Entity>,
+    pub trauma: f32,
+}
+
+/// Play a shake profile on the active camera, for previewing from the editor
+#[derive(Event, Clone, Debug)]
+pub struct PreviewCameraShakeEvent {
+    pub shake: CameraShake,
+}
+
+pub fn handle_camera_shake_events(
+    mut commands: Commands,
+    mut events: EventReader<CameraShakeEvent>,
+    mut previews: EventReader<PreviewCameraShakeEvent>,
+    camera_settings: Option<Res<CameraSettings>>,
+    mut shakes: Query<&mut CameraShake>,
+) {
+    let active_camera = camera_settings.and_then(|settings| settings.active_camera_entity);
+    for event in events.read() {
+        let Some(camera) = event.camera.or(active_camera) else {
+            continue;
+        };
+        match shakes.get_mut(camera) {
+            Ok(mut shake) => shake.shake(event.trauma),
+            Err(_) => {
+                let mut shake = CameraShake::default();
+                shake.shake(event.trauma);
+                if let Some(mut entity) = commands.get_entity(camera) {
+                    entity.insert(shake);
+                }
+            }
+        }
+    }
+    for preview in previews.read() {
+        if let Some(mut entity) = active_camera.and_then(|camera| commands.get_entity(camera)) {
+            entity.insert(preview.shake.clone());
+        }
+    }
+}
+
+pub fn update_camera_shakes(time: Res<Time>, mut shakes: Query<&mut CameraShake>) {
+    let delta = time.delta_seconds();
+    for mut shake in &mut shakes {
+        if shake.trauma <= 0.0 {
+            continue;
+        }
+        if shake.noise_seed != shake.seed {
+            shake.noise = Noise::new(shake.seed);
+            shake.noise_seed = shake.seed;
+        }
+        shake.time += delta;
+        shake.trauma = (shake.trauma - shake.decay.max(0.0) * delta).max(0.0);
+    }
+}
+
+pub fn apply_camera_shakes(mut cameras: Query<(&CameraShake, &mut GlobalTransform)>) {
+    for (shake, mut transform) in &mut cameras {
+        if shake.trauma > 0.0 {
+            *transform = transform.mul_transform(shake.offset());
+        }
+    }
+}
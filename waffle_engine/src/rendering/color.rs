@@ -0,0 +1,91 @@
+//! Color Management
+//! The renderer always shades in linear sRGB (Rec. 709 primaries), and colors
+//! are stored in that space. The project's working space decides the space
+//! color math such as the sky gradient is done in, and the RGB values the
+//! inspector offers for material, light and sky colors. The output transform
+//! picks one view transform for every camera, or leaves it to each
+//! environment's tonemapping. Display encoding is covered by `hdr`.
+
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+
+pub use crate::core::project::{OutputTransform, WorkingColorSpace};
+
+/// AP1 to Rec. 709, with a Bradford adaptation from D60 to D65
+const ACESCG_TO_LINEAR_SRGB: Mat3 = Mat3::from_cols(
+    Vec3::new(1.705_051, -0.130_256, -0.024_003),
+    Vec3::new(-0.621_792, 1.140_804, -0.128_969),
+    Vec3::new(-0.083_259, -0.010_548, 1.152_972),
+);
+const LINEAR_SRGB_TO_ACESCG: Mat3 = Mat3::from_cols(
+    Vec3::new(0.613_097, 0.070_194, 0.020_616),
+    Vec3::new(0.339_523, 0.916_354, 0.109_570),
+    Vec3::new(0.047_379, 0.013_452, 0.869_815),
+);
+
+impl WorkingColorSpace {
+    pub const ALL: [WorkingColorSpace; 2] = [WorkingColorSpace::LinearSrgb, WorkingColorSpace::AcesCg];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WorkingColorSpace::LinearSrgb => "Linear sRGB",
+            WorkingColorSpace::AcesCg => "ACEScg",
+        }
+    }
+
+    /// Linear RGB of `color` in this space
+    pub fn to_working(self, color: Color) -> Vec3 {
+        let linear = Vec3::from_slice(&color.to_linear().to_f32_array_no_alpha());
+        match self {
+            WorkingColorSpace::LinearSrgb => linear,
+            WorkingColorSpace::AcesCg => LINEAR_SRGB_TO_ACESCG * linear,
+        }
+    }
+
+    /// Color for linear RGB in this space, clamped to what the renderer
+    /// can represent
+    pub fn to_render(self, rgb: Vec3, alpha: f32) -> Color {
+        let linear = match self {
+            WorkingColorSpace::LinearSrgb => rgb,
+            WorkingColorSpace::AcesCg => ACESCG_TO_LINEAR_SRGB * rgb,
+        };
+        let linear = linear.max(Vec3::ZERO);
+        Color::linear_rgba(linear.x, linear.y, linear.z, alpha)
+    }
+
+    /// Blend two colors in this space
+    pub fn mix(self, a: Color, b: Color, t: f32) -> Vec3 {
+        self.to_working(a).lerp(self.to_working(b), t)
+    }
+}
+
+impl OutputTransform {
+    pub const ALL: [OutputTransform; 5] = [
+        OutputTransform::PerEnvironment,
+        OutputTransform::Standard,
+        OutputTransform::AcesFitted,
+        OutputTransform::AgX,
+        OutputTransform::TonyMcMapface,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputTransform::PerEnvironment => "Per Environment",
+            OutputTransform::Standard => "Standard",
+            OutputTransform::AcesFitted => "ACES Fitted",
+            OutputTransform::AgX => "AgX",
+            OutputTransform::TonyMcMapface => "Tony McMapface",
+        }
+    }
+
+    /// Tonemapping every camera uses, or `None` to follow the environment
+    pub fn tonemapping(self) -> Option<Tonemapping> {
+        match self {
+            OutputTransform::PerEnvironment => None,
+            OutputTransform::Standard => Some(Tonemapping::None),
+            OutputTransform::AcesFitted => Some(Tonemapping::AcesFitted),
+            OutputTransform::AgX => Some(Tonemapping::AgX),
+            OutputTransform::TonyMcMapface => Some(Tonemapping::TonyMcMapface),
+        }
+    }
+}
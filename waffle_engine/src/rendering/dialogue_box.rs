@@ -0,0 +1,173 @@
+//! Dialogue Box Module
+//! In-game UI for the playing dialogue: speaker, line and choice buttons along
+//! the bottom of the game UI canvas. Choices can also be picked with the
+//! number keys, and lines without choices advance with Space or Enter.
+
+use bevy::prelude::*;
+
+use crate::core::dialogue::{DialogueEvent, DialogueInputEvent, DialoguePlayer};
+use crate::core::variables::GameVariables;
+use crate::rendering::camera::CameraSettings;
+use crate::rendering::game_ui::GameUiCanvas;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DialogueBox;
+
+/// Picks the choice with this index in the current node's choice list
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DialogueChoiceButton(pub usize);
+
+const DIGIT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Rebuild the box whenever the dialogue shows a new line or ends
+pub fn update_dialogue_box(
+    mut commands: Commands,
+    mut events: EventReader<DialogueEvent>,
+    player: Res<DialoguePlayer>,
+    variables: Res<GameVariables>,
+    camera_settings: Option<Res<CameraSettings>>,
+    canvases: Query<Entity, With<GameUiCanvas>>,
+    boxes: Query<Entity, With<DialogueBox>>,
+) {
+    let changed = events
+        .read()
+        .filter(|event| matches!(event, DialogueEvent::Line { .. } | DialogueEvent::Ended { .. }))
+        .count()
+        > 0;
+    if !changed {
+        return;
+    }
+    for entity in &boxes {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(node) = player.current_node() else {
+        return;
+    };
+
+    let mut root = commands.spawn((
+        DialogueBox,
+        Name::new("Dialogue Box"),
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(10.0),
+                right: Val::Percent(10.0),
+                bottom: Val::Px(24.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                ..default()
+            },
+            background_color: Color::srgba(0.05, 0.05, 0.08, 0.85).into(),
+            ..default()
+        },
+    ));
+    // Inside the safe area; without a canvas yet, straight on the camera,
+    // which in the editor draws into the viewport texture, not the window
+    if let Ok(canvas) = canvases.get_single() {
+        root.set_parent(canvas);
+    } else if let Some(camera) = camera_settings.and_then(|settings| settings.active_camera_entity) {
+        root.insert(TargetCamera(camera));
+    }
+
+    let choices = player.available_choices(&variables);
+    root.with_children(|panel| {
+        if !node.speaker.is_empty() {
+            panel.spawn(TextBundle::from_section(
+                node.speaker.clone(),
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::srgb(1.0, 0.85, 0.4),
+                    ..default()
+                },
+            ));
+        }
+        panel.spawn(TextBundle::from_section(
+            node.text.clone(),
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+        if node.choices.is_empty() {
+            panel.spawn(TextBundle::from_section(
+                "[Space] Continue",
+                TextStyle {
+                    font_size: 13.0,
+                    color: Color::srgb(0.6, 0.6, 0.6),
+                    ..default()
+                },
+            ));
+        }
+        for (number, (index, choice)) in choices.iter().enumerate() {
+            panel
+                .spawn((
+                    DialogueChoiceButton(*index),
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: Color::srgba(1.0, 1.0, 1.0, 0.08).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        format!("{}. {}", number + 1, choice.text),
+                        TextStyle {
+                            font_size: 16.0,
+                            color: Color::srgb(0.85, 0.9, 1.0),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+pub fn handle_dialogue_box_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    player: Res<DialoguePlayer>,
+    variables: Res<GameVariables>,
+    mut buttons: Query<(&DialogueChoiceButton, &Interaction, &mut BackgroundColor), Changed<Interaction>>,
+    mut inputs: EventWriter<DialogueInputEvent>,
+) {
+    let Some(node) = player.current_node() else {
+        return;
+    };
+
+    for (button, interaction, mut background) in &mut buttons {
+        match interaction {
+            Interaction::Pressed => {
+                inputs.send(DialogueInputEvent::Choose(button.0));
+            }
+            Interaction::Hovered => background.0 = Color::srgba(1.0, 1.0, 1.0, 0.2),
+            Interaction::None => background.0 = Color::srgba(1.0, 1.0, 1.0, 0.08),
+        }
+    }
+
+    if node.choices.is_empty() {
+        if keyboard.any_just_pressed([KeyCode::Space, KeyCode::Enter]) {
+            inputs.send(DialogueInputEvent::Advance);
+        }
+        return;
+    }
+    let choices = player.available_choices(&variables);
+    for (key, (index, _)) in DIGIT_KEYS.iter().zip(choices) {
+        if keyboard.just_pressed(*key) {
+            inputs.send(DialogueInputEvent::Choose(index));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Scene Environment Module
+//! Binds each scene root to its environment and resolves the one in use: the
+//! environment of the current `SceneRootEntity` drives ambient light, the sun
+//! and the sky, and switching scene roots blends from the old environment to
+//! the new one.
+
+use bevy::prelude::*;
+use crate::rendering::scene::{EnvironmentSettings, SceneRootEntity, WaffleSceneRoot};
+
+/// The environment entity used by a scene root
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SceneEnvironment(pub Entity);
+
+#[derive(Resource)]
+pub struct ActiveEnvironment {
+    /// Settings in use this frame; a mix of two environments while blending
+    pub settings: Option<EnvironmentSettings>,
+    pub scene_root: Option<Entity>,
+    /// Seconds taken to blend to a new scene's environment
+    pub blend_duration: f32,
+    blend_from: Option<EnvironmentSettings>,
+    blend_elapsed: f32,
+}
+
+impl Default for ActiveEnvironment {
+    fn default() -> Self {
+        Self {
+            settings: None,
+            scene_root: None,
+            blend_duration: 1.5,
+            blend_from: None,
+            blend_elapsed: 0.0,
+        }
+    }
+}
+
+impl ActiveEnvironment {
+    pub fn is_blending(&self) -> bool {
+        self.blend_from.is_some()
+    }
+}
+
+/// Bind environments spawned under a scene root that has none yet, e.g. ones
+/// loaded from a scene file
+pub fn bind_scene_environments(
+    mut commands: Commands,
+    env_query: Query<Entity, (Added<EnvironmentSettings>, Without<Camera>)>,
+    parent_query: Query<&Parent>,
+    root_query: Query<Option<&SceneEnvironment>, With<WaffleSceneRoot>>,
+    existing_query: Query<(), With<EnvironmentSettings>>,
+) {
+    let mut bound: Vec<Entity> = Vec::new();
+    for env in &env_query {
+        let Some(root) = parent_query
+            .iter_ancestors(env)
+            .find(|ancestor| root_query.contains(*ancestor))
+        else {
+            continue;
+        };
+        let current = root_query.get(root).ok().flatten().map(|binding| binding.0);
+        if current == Some(env) {
+            continue;
+        }
+        let has_other = bound.contains(&root) || current.is_some_and(|other| existing_query.contains(other));
+        if has_other {
+            warn!("Scene root {:?} already has an environment; ignoring {:?}", root, env);
+            continue;
+        }
+        commands.entity(root).insert(SceneEnvironment(env));
+        bound.push(root);
+    }
+}
+
+pub fn update_active_environment(
+    time: Res<Time>,
+    mut active: ResMut<ActiveEnvironment>,
+    scene_root: Option<Res<SceneRootEntity>>,
+    binding_query: Query<&SceneEnvironment>,
+    env_query: Query<&EnvironmentSettings>,
+) {
+    let root = scene_root.map(|root| root.0);
+    if active.scene_root != root {
+        active.scene_root = root;
+        active.blend_from = active.settings.clone();
+        active.blend_elapsed = 0.0;
+    }
+
+    // Keep the previous environment until the new scene's one exists
+    let Some(target) = root
+        .and_then(|root| binding_query.get(root).ok())
+        .and_then(|binding| env_query.get(binding.0).ok())
+    else {
+        return;
+    };
+
+    if let Some(from) = active.blend_from.clone() {
+        let elapsed = active.blend_elapsed + time.delta_seconds();
+        let t = if active.blend_duration > 0.0 {
+            (elapsed / active.blend_duration).min(1.0)
+        } else {
+            1.0
+        };
+        active.blend_elapsed = elapsed;
+        active.settings = Some(from.lerp(target, t * t * (3.0 - 2.0 * t)));
+        if t >= 1.0 {
+            active.blend_from = None;
+        }
+        return;
+    }
+
+    if active.settings.as_ref() != Some(target) {
+        active.settings = Some(target.clone());
+    }
+}
@@ -1,5 +1,5 @@
-/// Fog Module
-/// Handles fog rendering and configuration
+//! Fog Module
+//! Handles fog rendering and configuration
 
 use bevy::prelude::*;
 
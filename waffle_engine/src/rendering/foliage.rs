@@ -0,0 +1,296 @@
+//! Foliage Module
+//! A `FoliageLayer` scatters copies of one mesh, like grass, rocks or trees,
+//! each with its own position, rotation and scale. Rather than an entity per
+//! copy, the instances are grouped into square cells and each cell is drawn
+//! as one mesh holding all of its copies, so a field of thousands of tufts
+//! costs a draw per cell. Only the cells whose instances changed are built
+//! again, which keeps painting responsive.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use bevy::asset::LoadState;
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+
+/// Width of the square cells instances are batched in, in the layer's units
+pub const FOLIAGE_CELL_SIZE: f32 = 16.0;
+
+/// One copy of the layer's mesh, in the layer's space
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FoliageInstance {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+}
+
+impl FoliageInstance {
+    pub fn cell(&self) -> IVec2 {
+        (self.position.xz() / FOLIAGE_CELL_SIZE).floor().as_ivec2()
+    }
+
+    fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(Vec3::splat(self.scale), self.rotation, self.position)
+    }
+}
+
+/// Copies of a mesh scattered over the scene, usually painted with the
+/// editor's Foliage tool
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FoliageLayer {
+    /// Mesh asset path, e.g. `models/grass.glb#Mesh0/Primitive0`
+    pub mesh: String,
+    /// Material asset path; a plain green when empty
+    pub material: String,
+    pub cast_shadows: bool,
+    pub instances: Vec<FoliageInstance>,
+}
+
+impl Default for FoliageLayer {
+    fn default() -> Self {
+        Self {
+            mesh: String::new(),
+            material: String::new(),
+            cast_shadows: true,
+            instances: Vec::new(),
+        }
+    }
+}
+
+/// The cell meshes built for a foliage layer
+#[derive(Component)]
+pub struct FoliageBatches {
+    mesh_path: String,
+    material_path: String,
+    cast_shadows: bool,
+    mesh: Option<Handle<Mesh>>,
+    material: Handle<StandardMaterial>,
+    /// Hash of the instances each cell was built from, and its entity
+    cells: HashMap<IVec2, (u64, Entity)>,
+    /// Built again once the mesh has loaded
+    pending: bool,
+}
+
+impl FoliageBatches {
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// One cell of a foliage layer, drawn as a single mesh
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FoliageCell {
+    pub layer: Entity,
+    pub coord: IVec2,
+    pub instances: usize,
+}
+
+fn default_foliage_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::srgb(0.32, 0.5, 0.2),
+        perceptual_roughness: 0.9,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    }
+}
+
+fn despawn_cells(commands: &mut Commands, batches: &mut FoliageBatches) {
+    for (_, (_, cell)) in batches.cells.drain() {
+        if let Some(cell) = commands.get_entity(cell) {
+            cell.despawn_recursive();
+        }
+    }
+}
+
+/// Build the cells of foliage layers whose instances, mesh or material
+/// changed
+pub fn build_foliage_batches(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut layers: Query<(Entity, Ref<FoliageLayer>, Option<&mut FoliageBatches>)>,
+) {
+    for (entity, layer, batches) in layers.iter_mut() {
+        let Some(mut batches) = batches else {
+            commands.entity(entity).insert(FoliageBatches {
+                mesh_path: String::new(),
+                material_path: String::new(),
+                cast_shadows: layer.cast_shadows,
+                mesh: None,
+                material: materials.add(default_foliage_material()),
+                cells: HashMap::new(),
+                pending: true,
+            });
+            continue;
+        };
+        if !layer.is_changed() && !batches.pending {
+            continue;
+        }
+        batches.pending = false;
+
+        if batches.mesh_path != layer.mesh
+            || batches.material_path != layer.material
+            || batches.cast_shadows != layer.cast_shadows
+        {
+            despawn_cells(&mut commands, &mut batches);
+            batches.mesh_path = layer.mesh.clone();
+            batches.material_path = layer.material.clone();
+            batches.cast_shadows = layer.cast_shadows;
+            batches.mesh = (!layer.mesh.is_empty()).then(|| asset_server.load(layer.mesh.clone()));
+            batches.material = if layer.material.is_empty() {
+                materials.add(default_foliage_material())
+            } else {
+                asset_server.load(layer.material.clone())
+            };
+        }
+        let Some(handle) = batches.mesh.clone() else {
+            despawn_cells(&mut commands, &mut batches);
+            continue;
+        };
+        let Some(source) = meshes.get(&handle) else {
+            if matches!(asset_server.load_state(&handle), LoadState::Failed(_)) {
+                warn!("Failed to load foliage mesh \"{}\"", layer.mesh);
+            } else {
+                batches.pending = true;
+            }
+            continue;
+        };
+
+        let mut groups: HashMap<IVec2, Vec<&FoliageInstance>> = HashMap::new();
+        for instance in &layer.instances {
+            groups.entry(instance.cell()).or_default().push(instance);
+        }
+        let emptied: Vec<IVec2> = batches.cells.keys().filter(|coord| !groups.contains_key(*coord)).copied().collect();
+        for coord in emptied {
+            if let Some((_, cell)) = batches.cells.remove(&coord) {
+                if let Some(cell) = commands.get_entity(cell) {
+                    cell.despawn_recursive();
+                }
+            }
+        }
+
+        let mut built = Vec::new();
+        for (coord, instances) in &groups {
+            let hash = hash_instances(instances);
+            if batches.cells.get(coord).is_some_and(|(built, _)| *built == hash) {
+                continue;
+            }
+            let Some(mesh) = merge_instances(source, instances) else {
+                warn!("Foliage mesh \"{}\" is not a triangle mesh with positions", layer.mesh);
+                break;
+            };
+            built.push((*coord, hash, mesh, instances.len()));
+        }
+        for (coord, hash, mesh, count) in built {
+            if let Some((_, old)) = batches.cells.remove(&coord) {
+                if let Some(old) = commands.get_entity(old) {
+                    old.despawn_recursive();
+                }
+            }
+            let mut cell = commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(mesh),
+                    material: batches.material.clone(),
+                    ..default()
+                },
+                FoliageCell {
+                    layer: entity,
+                    coord,
+                    instances: count,
+                },
+                EditorHidden,
+                Name::new(format!("Foliage Cell {},{}", coord.x, coord.y)),
+            ));
+            if !layer.cast_shadows {
+                cell.insert(NotShadowCaster);
+            }
+            let cell = cell.set_parent(entity).id();
+            batches.cells.insert(coord, (hash, cell));
+        }
+    }
+}
+
+fn hash_instances(instances: &[&FoliageInstance]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for instance in instances {
+        for value in instance.position.to_array().into_iter().chain(instance.rotation.to_array()) {
+            value.to_bits().hash(&mut hasher);
+        }
+        instance.scale.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// One mesh with a transformed copy of `source` for every instance
+fn merge_instances(source: &Mesh, instances: &[&FoliageInstance]) -> Option<Mesh> {
+    if source.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(source_positions)) = source.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return None;
+    };
+    let source_normals = match source.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals),
+        _ => None,
+    };
+    let source_uvs = match source.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+        _ => None,
+    };
+    let source_indices: Vec<u32> = match source.indices() {
+        Some(indices) => indices.iter().map(|index| index as u32).collect(),
+        None => (0..source_positions.len() as u32).collect(),
+    };
+
+    let vertex_count = source_positions.len() * instances.len();
+    let mut positions = Vec::with_capacity(vertex_count);
+    let mut normals = Vec::with_capacity(vertex_count);
+    let mut uvs = Vec::with_capacity(if source_uvs.is_some() { vertex_count } else { 0 });
+    let mut indices = Vec::with_capacity(source_indices.len() * instances.len());
+    for instance in instances {
+        let matrix = instance.matrix();
+        let first = positions.len() as u32;
+        for (index, position) in source_positions.iter().enumerate() {
+            positions.push(matrix.transform_point3(Vec3::from(*position)).to_array());
+            let normal = source_normals.and_then(|normals| normals.get(index)).map_or(Vec3::Y, |n| Vec3::from(*n));
+            normals.push((instance.rotation * normal).to_array());
+            if let Some(source_uvs) = source_uvs {
+                uvs.push(source_uvs.get(index).copied().unwrap_or_default());
+            }
+        }
+        indices.extend(source_indices.iter().map(|index| first + index));
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(Indices::U32(indices));
+    if source_uvs.is_some() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    Some(mesh)
+}
+
+/// Despawn the cells of foliage layers that were removed
+pub fn despawn_orphaned_foliage_cells(
+    mut commands: Commands,
+    cells: Query<(Entity, &FoliageCell)>,
+    layers: Query<(), With<FoliageLayer>>,
+) {
+    for (entity, cell) in cells.iter() {
+        if !layers.contains(cell.layer) {
+            commands.entity(entity).despawn_recursive();
+            if let Some(mut layer) = commands.get_entity(cell.layer) {
+                layer.remove::<FoliageBatches>();
+            }
+        }
+    }
+}
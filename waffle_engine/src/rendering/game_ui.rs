@@ -0,0 +1,103 @@
+//! Game UI Module
+//! The canvas in-game UI is laid out on. UI is scaled for the display as the
+//! project's settings ask, either keeping its size on screen whatever the
+//! DPI or keeping its share of the screen's height. `GameUiCanvas` covers the
+//! active camera's view minus the project's safe-area margins, so UI placed
+//! under it stays clear of the edges TVs crop.
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::window::PrimaryWindow;
+
+use crate::core::components::EditorHidden;
+use crate::core::project::{ProjectSettings, UiScaleMode};
+use crate::rendering::camera::CameraSettings;
+
+/// Root node for game UI, inset by the safe area. Parent UI to it to keep
+/// it inside.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GameUiCanvas;
+
+/// Set `UiScale` from the project's scale mode and the active camera's view
+pub fn update_game_ui_scale(
+    project_settings: Res<ProjectSettings>,
+    camera_settings: Option<Res<CameraSettings>>,
+    cameras: Query<&Camera>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Some(camera) = camera_settings
+        .and_then(|settings| settings.active_camera_entity)
+        .and_then(|entity| cameras.get(entity).ok())
+    else {
+        return;
+    };
+    let settings = &project_settings.game_ui;
+    let scale = match settings.scale_mode {
+        // Bevy applies the window's scale factor to UI drawn to the window,
+        // but not to UI drawn into an image such as the editor viewport
+        UiScaleMode::ConstantSize => match camera.target {
+            RenderTarget::Image(_) => windows.get_single().map(Window::scale_factor).unwrap_or(1.0),
+            _ => 1.0,
+        },
+        UiScaleMode::ScaleWithHeight => {
+            let Some(viewport) = camera.logical_viewport_size() else {
+                return;
+            };
+            (viewport.y / settings.reference_height.max(1.0))
+                .clamp(settings.min_scale.min(settings.max_scale), settings.max_scale)
+        }
+    };
+    if scale > 0.0 && ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
+
+/// Keep one canvas on the active camera, inset by the safe-area margins
+pub fn update_game_ui_canvas(
+    mut commands: Commands,
+    project_settings: Res<ProjectSettings>,
+    camera_settings: Option<Res<CameraSettings>>,
+    mut canvases: Query<(Entity, &mut Style, Option<&TargetCamera>), With<GameUiCanvas>>,
+) {
+    let margin = project_settings.game_ui.safe_margin() * 100.0;
+    let insets = UiRect {
+        left: Val::Vw(margin.x),
+        right: Val::Vw(margin.x),
+        top: Val::Vh(margin.y),
+        bottom: Val::Vh(margin.y),
+    };
+
+    let Ok((entity, mut style, target_camera)) = canvases.get_single_mut() else {
+        commands.spawn((
+            GameUiCanvas,
+            EditorHidden,
+            Name::new("Game UI Canvas"),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: insets.left,
+                    right: insets.right,
+                    top: insets.top,
+                    bottom: insets.bottom,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        return;
+    };
+
+    if style.left != insets.left || style.right != insets.right || style.top != insets.top || style.bottom != insets.bottom {
+        style.left = insets.left;
+        style.right = insets.right;
+        style.top = insets.top;
+        style.bottom = insets.bottom;
+    }
+    // In the editor the game camera draws into the viewport texture, not the window
+    if let Some(camera) = camera_settings.and_then(|settings| settings.active_camera_entity) {
+        if target_camera.map(|target| target.0) != Some(camera) {
+            commands.entity(entity).insert(TargetCamera(camera));
+        }
+    }
+}
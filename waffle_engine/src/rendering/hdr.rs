@@ -0,0 +1,77 @@
+//! HDR Display Output
+//! Checks the project's HDR output mode against the swapchain the renderer
+//! actually presents to. Bevy 0.14 always configures an 8-bit sRGB surface
+//! and wgpu 0.20 cannot set an HDR color space, so for now HDR10 and scRGB
+//! fall back to SDR with a warning, and the `EnvironmentSettings` tonemapper
+//! keeps mapping to SDR white. Paper white and peak brightness are stored so
+//! projects are ready once an HDR surface can be configured.
+
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::view::ExtractedWindows;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::core::project::{HdrOutputMode, ProjectSettings};
+
+const NO_FORMAT: u8 = 0;
+const SDR_FORMAT: u8 = 1;
+const HDR10_FORMAT: u8 = 2;
+const SCRGB_FORMAT: u8 = 3;
+
+/// Output mode of the primary window's swapchain, from the render world
+#[derive(Resource, Clone, Default)]
+pub struct HdrOutputStatus {
+    format: Arc<AtomicU8>,
+}
+
+impl HdrOutputStatus {
+    /// `None` until the first frame has been presented
+    pub fn active_mode(&self) -> Option<HdrOutputMode> {
+        match self.format.load(Ordering::Relaxed) {
+            SDR_FORMAT => Some(HdrOutputMode::Sdr),
+            HDR10_FORMAT => Some(HdrOutputMode::Hdr10),
+            SCRGB_FORMAT => Some(HdrOutputMode::ScRgb),
+            _ => None,
+        }
+    }
+}
+
+/// Runs in the render app
+pub fn report_swapchain_format(windows: Res<ExtractedWindows>, status: Res<HdrOutputStatus>) {
+    let format = windows
+        .primary
+        .and_then(|primary| windows.windows.get(&primary))
+        .and_then(|window| window.swap_chain_texture_format);
+    let format = match format {
+        None => NO_FORMAT,
+        Some(TextureFormat::Rgb10a2Unorm) => HDR10_FORMAT,
+        Some(TextureFormat::Rgba16Float) => SCRGB_FORMAT,
+        Some(_) => SDR_FORMAT,
+    };
+    status.format.store(format, Ordering::Relaxed);
+}
+
+/// Warn once per requested mode that the display fell back to SDR
+pub fn check_hdr_output(
+    project_settings: Res<ProjectSettings>,
+    status: Res<HdrOutputStatus>,
+    mut warned: Local<Option<HdrOutputMode>>,
+) {
+    let requested = project_settings.hdr_output.mode;
+    let Some(active) = status.active_mode() else {
+        return;
+    };
+    if requested == HdrOutputMode::Sdr || requested == active {
+        *warned = None;
+        return;
+    }
+    if *warned != Some(requested) {
+        warn!(
+            "{} output is not available on this display or renderer; using {}",
+            requested.label(),
+            active.label()
+        );
+        *warned = Some(requested);
+    }
+}
@@ -0,0 +1,185 @@
+//! Highlight Module
+//! Outlines and glows around gameplay objects. Adding a `Highlight` to an
+//! entity wraps every mesh under it in a slightly larger copy that only
+//! shows its back faces, so a rim of the highlight color shows around the
+//! silhouette. The interactable in focus is highlighted the same way, using
+//! the style in `InteractionSettings`.
+
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::render_resource::Face;
+use std::collections::{HashMap, HashSet};
+
+use crate::core::components::EditorHidden;
+use crate::core::interaction::{InteractionFocus, InteractionSettings};
+
+/// How a highlight is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum HighlightStyle {
+    /// A solid rim in the highlight color
+    #[default]
+    Outline,
+    /// A translucent halo added on top of what is behind it
+    Glow,
+}
+
+/// Outline this entity and every mesh under it
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct Highlight {
+    pub color: Color,
+    pub style: HighlightStyle,
+    /// World units the rim reaches past the silhouette
+    pub width: f32,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            style: HighlightStyle::Outline,
+            width: 0.03,
+        }
+    }
+}
+
+impl Highlight {
+    pub fn outline(color: Color) -> Self {
+        Self { color, ..default() }
+    }
+
+    pub fn glow(color: Color) -> Self {
+        Self {
+            color,
+            style: HighlightStyle::Glow,
+            width: 0.08,
+        }
+    }
+
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width.max(0.0);
+        self
+    }
+
+    fn material(&self) -> StandardMaterial {
+        let alpha_mode = match self.style {
+            HighlightStyle::Outline if self.color.alpha() < 1.0 => AlphaMode::Blend,
+            HighlightStyle::Outline => AlphaMode::Opaque,
+            HighlightStyle::Glow => AlphaMode::Add,
+        };
+        StandardMaterial {
+            base_color: self.color,
+            unlit: true,
+            cull_mode: Some(Face::Front),
+            alpha_mode,
+            fog_enabled: false,
+            ..default()
+        }
+    }
+}
+
+/// The enlarged copy of `target` drawn for `owner`'s highlight
+#[derive(Component, Debug)]
+pub struct HighlightHull {
+    owner: Entity,
+    target: Entity,
+    highlight: Highlight,
+}
+
+/// Keep a hull around every mesh of every highlighted entity, and remove
+/// hulls whose highlight has gone
+pub fn update_highlight_hulls(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    focus: Res<InteractionFocus>,
+    interaction_settings: Res<InteractionSettings>,
+    highlights: Query<(Entity, &Highlight)>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Handle<Mesh>, &Aabb, &GlobalTransform), (Without<HighlightHull>, Without<EditorHidden>)>,
+    mut hulls: Query<(Entity, &mut HighlightHull, &Handle<StandardMaterial>, &mut Transform)>,
+) {
+    let mut wanted: HashMap<Entity, Highlight> = highlights
+        .iter()
+        .map(|(entity, highlight)| (entity, *highlight))
+        .collect();
+    if let (Some(entity), Some(highlight)) = (focus.entity, interaction_settings.focus_highlight) {
+        // Gameplay highlights win over the focus one
+        wanted.entry(entity).or_insert(highlight);
+    }
+
+    let mut covered: HashSet<(Entity, Entity)> = HashSet::new();
+    for (hull_entity, mut hull, material, mut transform) in &mut hulls {
+        let Some(highlight) = wanted.get(&hull.owner) else {
+            commands.entity(hull_entity).despawn_recursive();
+            continue;
+        };
+        let Ok((_, aabb, target_transform)) = mesh_query.get(hull.target) else {
+            commands.entity(hull_entity).despawn_recursive();
+            continue;
+        };
+        if hull.highlight != *highlight {
+            hull.highlight = *highlight;
+            if let Some(material) = materials.get_mut(material) {
+                *material = highlight.material();
+            }
+        }
+        let hull_transform = hull_transform(aabb, target_transform, highlight.width);
+        if *transform != hull_transform {
+            *transform = hull_transform;
+        }
+        covered.insert((hull.target, hull.owner));
+    }
+
+    for (owner, highlight) in &wanted {
+        let mut stack = vec![*owner];
+        while let Some(current) = stack.pop() {
+            if let Ok(children) = children_query.get(current) {
+                stack.extend(children.iter().copied());
+            }
+            if covered.contains(&(current, *owner)) {
+                continue;
+            }
+            let Ok((mesh, aabb, target_transform)) = mesh_query.get(current) else {
+                continue;
+            };
+            let hull = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: materials.add(highlight.material()),
+                        transform: hull_transform(aabb, target_transform, highlight.width),
+                        ..default()
+                    },
+                    HighlightHull {
+                        owner: *owner,
+                        target: current,
+                        highlight: *highlight,
+                    },
+                    NotShadowCaster,
+                    EditorHidden,
+                    Name::new("Highlight"),
+                ))
+                .id();
+            commands.entity(current).add_child(hull);
+        }
+    }
+}
+
+/// Scale about the mesh bounds' center so the rim is `width` wide in world
+/// units on every side, whatever the target's own scale
+fn hull_transform(aabb: &Aabb, target_transform: &GlobalTransform, width: f32) -> Transform {
+    let center = Vec3::from(aabb.center);
+    let half_extents = Vec3::from(aabb.half_extents);
+    let world_scale = target_transform.compute_transform().scale.abs().max(Vec3::splat(1e-4));
+    let world_half_extents = half_extents * world_scale;
+    let scale = Vec3::select(
+        world_half_extents.cmpgt(Vec3::splat(1e-4)),
+        (world_half_extents + width) / world_half_extents.max(Vec3::splat(1e-4)),
+        Vec3::ONE,
+    );
+    Transform {
+        translation: center * (Vec3::ONE - scale),
+        scale,
+        ..default()
+    }
+}
@@ -0,0 +1,232 @@
+//! Mesh Instancing Module
+//! Swaps identical meshes and materials for shared assets so their copies batch into one draw
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::render::batching::NoAutomaticBatching;
+use bevy::render::mesh::Indices;
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+
+/// How often the instancing stats are counted again
+const STATS_INTERVAL: Duration = Duration::from_millis(500);
+/// Pairs listed in the stats, the most instances first
+const MAX_LISTED_BATCHES: usize = 20;
+
+/// A mesh and the material it is drawn with; `None` for meshes without one
+type BatchKey = (AssetId<Mesh>, Option<AssetId<StandardMaterial>>);
+
+/// Share identical meshes and materials in this entity and its descendants so
+/// their copies are drawn instanced. The renderer only batches copies that
+/// share both asset handles, which spawned primitives, imported OBJ files and
+/// painted meshes don't. Editing a shared material changes every copy.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstancedMesh;
+
+/// The shared asset for each mesh and material content seen under an
+/// `InstancedMesh`
+#[derive(Resource, Default)]
+pub struct InstancingRegistry {
+    meshes: HashMap<u64, Handle<Mesh>>,
+    materials: HashMap<u64, Handle<StandardMaterial>>,
+    mesh_keys: HashMap<AssetId<Mesh>, u64>,
+    material_keys: HashMap<AssetId<StandardMaterial>, u64>,
+}
+
+impl InstancingRegistry {
+    fn mesh_key(&mut self, id: AssetId<Mesh>, meshes: &Assets<Mesh>) -> Option<u64> {
+        if let Some(key) = self.mesh_keys.get(&id) {
+            return Some(*key);
+        }
+        let key = hash_mesh(meshes.get(id)?);
+        self.mesh_keys.insert(id, key);
+        Some(key)
+    }
+
+    fn material_key(&mut self, id: AssetId<StandardMaterial>, materials: &Assets<StandardMaterial>) -> Option<u64> {
+        if let Some(key) = self.material_keys.get(&id) {
+            return Some(*key);
+        }
+        // Debug output covers every field, and textures by id
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        format!("{:?}", materials.get(id)?).hash(&mut hasher);
+        let key = hasher.finish();
+        self.material_keys.insert(id, key);
+        Some(key)
+    }
+
+    fn forget_mesh(&mut self, id: AssetId<Mesh>) {
+        self.mesh_keys.remove(&id);
+        self.meshes.retain(|_, handle| handle.id() != id);
+    }
+
+    fn forget_material(&mut self, id: AssetId<StandardMaterial>) {
+        self.material_keys.remove(&id);
+        self.materials.retain(|_, handle| handle.id() != id);
+    }
+}
+
+fn hash_mesh(mesh: &Mesh) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    mesh.primitive_topology().hash(&mut hasher);
+    for (id, values) in mesh.attributes() {
+        id.hash(&mut hasher);
+        values.get_bytes().hash(&mut hasher);
+    }
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.hash(&mut hasher),
+        Some(Indices::U32(indices)) => indices.hash(&mut hasher),
+        None => {}
+    }
+    hasher.finish()
+}
+
+/// Point meshes under `InstancedMesh` entities at the shared copy of their
+/// mesh and material
+#[allow(clippy::too_many_arguments)]
+pub fn share_instanced_assets(
+    mut registry: ResMut<InstancingRegistry>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut material_events: EventReader<AssetEvent<StandardMaterial>>,
+    added: Query<(), Added<InstancedMesh>>,
+    changed: Query<(), Or<(Changed<Handle<Mesh>>, Changed<Handle<StandardMaterial>>)>>,
+    instanced_query: Query<(), With<InstancedMesh>>,
+    parent_query: Query<&Parent>,
+    mut mesh_query: Query<
+        (Entity, &mut Handle<Mesh>, Option<&mut Handle<StandardMaterial>>),
+        (Without<EditorHidden>, Without<NoAutomaticBatching>),
+    >,
+) {
+    let mut stale = false;
+    for event in mesh_events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } | AssetEvent::LoadedWithDependencies { id } =
+            event
+        {
+            registry.forget_mesh(*id);
+            stale = true;
+        }
+    }
+    for event in material_events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } | AssetEvent::LoadedWithDependencies { id } =
+            event
+        {
+            registry.forget_material(*id);
+            stale = true;
+        }
+    }
+    if !stale && added.is_empty() && changed.is_empty() {
+        return;
+    }
+    if instanced_query.is_empty() {
+        return;
+    }
+
+    let registry = &mut *registry;
+    for (entity, mut mesh, material) in mesh_query.iter_mut() {
+        let instanced = std::iter::successors(Some(entity), |current| {
+            parent_query.get(*current).ok().map(|parent| parent.get())
+        })
+        .any(|ancestor| instanced_query.contains(ancestor));
+        if !instanced {
+            continue;
+        }
+        if let Some(key) = registry.mesh_key(mesh.id(), &meshes) {
+            let shared = registry.meshes.entry(key).or_insert_with(|| mesh.clone());
+            if *shared != *mesh {
+                *mesh = shared.clone();
+            }
+        }
+        if let Some(mut material) = material {
+            if let Some(key) = registry.material_key(material.id(), &materials) {
+                let shared = registry.materials.entry(key).or_insert_with(|| material.clone());
+                if *shared != *material {
+                    *material = shared.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Visible meshes drawn with one mesh and material pair
+#[derive(Clone, Debug)]
+pub struct InstanceBatch {
+    /// Name of the first entity in the batch, or its mesh path
+    pub label: String,
+    pub instances: usize,
+}
+
+/// Visible meshes counted by mesh and material, for the profiler
+#[derive(Resource, Default)]
+pub struct InstancingStats {
+    pub meshes: usize,
+    /// Mesh and material pairs, each drawn with one instanced draw
+    pub batches: usize,
+    /// Meshes drawn along with others in a batch
+    pub instanced: usize,
+    /// Meshes kept out of batching
+    pub unbatched: usize,
+    /// The largest batches
+    pub top: Vec<InstanceBatch>,
+}
+
+pub fn collect_instancing_stats(
+    mut stats: ResMut<InstancingStats>,
+    mut last: Local<Option<Instant>>,
+    asset_server: Res<AssetServer>,
+    mesh_query: Query<(
+        Entity,
+        &Handle<Mesh>,
+        Option<&Handle<StandardMaterial>>,
+        &ViewVisibility,
+        Has<NoAutomaticBatching>,
+        Option<&Name>,
+    )>,
+) {
+    if last.is_some_and(|last| last.elapsed() < STATS_INTERVAL) {
+        return;
+    }
+    *last = Some(Instant::now());
+
+    let mut counts: HashMap<BatchKey, (Entity, usize)> = HashMap::new();
+    let mut unbatched = 0;
+    let mut meshes = 0;
+    for (entity, mesh, material, visibility, no_batching, _) in mesh_query.iter() {
+        if !visibility.get() {
+            continue;
+        }
+        meshes += 1;
+        if no_batching {
+            unbatched += 1;
+            continue;
+        }
+        counts.entry((mesh.id(), material.map(|material| material.id()))).or_insert((entity, 0)).1 += 1;
+    }
+
+    let mut batches: Vec<(BatchKey, (Entity, usize))> = counts.into_iter().collect();
+    batches.sort_by(|a, b| b.1 .1.cmp(&a.1 .1));
+    *stats = InstancingStats {
+        meshes,
+        batches: batches.len() + unbatched,
+        instanced: batches.iter().map(|(_, (_, count))| *count).filter(|count| *count > 1).sum(),
+        unbatched,
+        top: batches
+            .iter()
+            .take(MAX_LISTED_BATCHES)
+            .map(|((mesh, _), (entity, count))| InstanceBatch {
+                label: mesh_query
+                    .get(*entity)
+                    .ok()
+                    .and_then(|(.., name)| name.map(|name| name.to_string()))
+                    .or_else(|| asset_server.get_path(*mesh).map(|path| path.to_string()))
+                    .unwrap_or_else(|| format!("Entity {}", entity.index())),
+                instances: *count,
+            })
+            .collect(),
+    };
+}
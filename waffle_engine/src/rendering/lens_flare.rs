@@ -0,0 +1,700 @@
+//! Lens Flare Module
+//! Sun glare and ghosts for directional lights, drawn as UI images over the
+//! active camera. Ghosts sit along the line from the light through the
+//! screen center.
+//!
+//! Occlusion is read from the active camera's depth prepass, at a few points
+//! across the sun disk so partly covered suns dim instead of popping. A
+//! compute pass writes whether each point shows open sky, and the result is
+//! read back a few frames later without stalling the GPU.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+
+use bevy::core_pipeline::prepass::{DepthPrepass, ViewPrepassTextures};
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
+use bevy::render::render_resource::binding_types::{
+    storage_buffer_read_only_sized, storage_buffer_sized, texture_depth_2d, texture_depth_2d_multisampled,
+};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BindGroupLayoutEntryBuilder, Buffer,
+    BufferDescriptor, BufferInitDescriptor, BufferUsages, CachedComputePipelineId, ComputePassDescriptor,
+    ComputePipelineDescriptor, Extent3d, Maintain, MapMode, PipelineCache, ShaderStages, TextureDimension,
+    TextureFormat,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+use crate::rendering::camera::CameraSettings;
+
+pub const LENS_FLARE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x7d14_c2e9_5a03_4b8f_9e61_08fb_3ad5_c27e);
+
+const TEXTURE_SIZE: u32 = 64;
+/// How far in front of the camera a light is placed to project it on screen
+const LIGHT_DISTANCE: f32 = 1000.0;
+/// Angular radius, in radians, of the occlusion samples around the sun
+const OCCLUSION_SPREAD: f32 = 0.01;
+const OCCLUSION_SAMPLES: usize = 5;
+/// Lights whose occlusion is tested each frame; flares past these keep their
+/// last result
+const MAX_OCCLUDED_LIGHTS: usize = 8;
+const MAX_OCCLUSION_POINTS: usize = MAX_OCCLUDED_LIGHTS * OCCLUSION_SAMPLES;
+/// Readback buffers waiting on the GPU at once; tests are skipped while all
+/// are in flight
+const OCCLUSION_READBACKS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LensFlareShape {
+    /// Soft glow around the light
+    Glow,
+    /// Filled disc with a brighter rim
+    Ghost,
+    /// Thin halo
+    Ring,
+}
+
+impl LensFlareShape {
+    pub const ALL: [LensFlareShape; 3] = [LensFlareShape::Glow, LensFlareShape::Ghost, LensFlareShape::Ring];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LensFlareShape::Glow => "Glow",
+            LensFlareShape::Ghost => "Ghost",
+            LensFlareShape::Ring => "Ring",
+        }
+    }
+
+    /// Opacity at `radius`, 0 at the center and 1 at the edge of the texture
+    fn alpha(self, radius: f32) -> f32 {
+        let alpha = match self {
+            LensFlareShape::Glow => (1.0 - radius).max(0.0).powi(3),
+            LensFlareShape::Ghost => {
+                let edge = ((1.0 - radius) * 8.0).clamp(0.0, 1.0);
+                edge * (0.35 + 0.65 * radius.powi(4))
+            }
+            LensFlareShape::Ring => (1.0 - ((radius - 0.85) / 0.1).abs()).max(0.0).powi(2),
+        };
+        alpha.clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LensFlareElement {
+    pub shape: LensFlareShape,
+    /// Drawn instead of the shape's built-in texture
+    pub texture: Option<Handle<Image>>,
+    /// Along the flare axis: 0 at the light, 1 at the screen center, 2
+    /// mirrored across it
+    pub position: f32,
+    /// Diameter as a fraction of the viewport height
+    pub size: f32,
+    pub color: Color,
+}
+
+/// Lens flare of a directional light, seen looking towards the light
+#[derive(Component, Clone, Debug)]
+pub struct LensFlare {
+    pub enabled: bool,
+    pub intensity: f32,
+    /// How fast the flare fades in and out as the light is covered, per second
+    pub fade_speed: f32,
+    pub elements: Vec<LensFlareElement>,
+}
+
+impl Default for LensFlare {
+    fn default() -> Self {
+        let element = |shape, position, size, color| LensFlareElement {
+            shape,
+            texture: None,
+            position,
+            size,
+            color,
+        };
+        Self {
+            enabled: true,
+            intensity: 1.0,
+            fade_speed: 8.0,
+            elements: vec![
+                element(LensFlareShape::Glow, 0.0, 0.5, Color::srgba(1.0, 0.95, 0.85, 0.8)),
+                element(LensFlareShape::Ring, 0.0, 0.28, Color::srgba(1.0, 0.9, 0.7, 0.15)),
+                element(LensFlareShape::Ghost, 0.45, 0.05, Color::srgba(0.6, 0.8, 1.0, 0.25)),
+                element(LensFlareShape::Ghost, 0.8, 0.09, Color::srgba(0.9, 0.6, 1.0, 0.15)),
+                element(LensFlareShape::Ghost, 1.3, 0.04, Color::srgba(0.5, 1.0, 0.6, 0.25)),
+                element(LensFlareShape::Ring, 1.6, 0.16, Color::srgba(1.0, 0.7, 0.4, 0.12)),
+                element(LensFlareShape::Ghost, 1.9, 0.12, Color::srgba(0.6, 0.7, 1.0, 0.12)),
+            ],
+        }
+    }
+}
+
+/// Built-in textures of the flare shapes
+#[derive(Resource)]
+pub struct LensFlareTextures {
+    pub glow: Handle<Image>,
+    pub ghost: Handle<Image>,
+    pub ring: Handle<Image>,
+}
+
+impl LensFlareTextures {
+    pub fn get(&self, shape: LensFlareShape) -> &Handle<Image> {
+        match shape {
+            LensFlareShape::Glow => &self.glow,
+            LensFlareShape::Ghost => &self.ghost,
+            LensFlareShape::Ring => &self.ring,
+        }
+    }
+}
+
+/// UI root drawing one light's flare
+#[derive(Component, Debug)]
+pub struct LensFlareRoot {
+    pub light: Entity,
+    /// 0 while the light is hidden or covered, 1 in full view
+    pub visibility: f32,
+}
+
+impl LensFlareRoot {
+    fn new(light: Entity) -> Self {
+        Self { light, visibility: 0.0 }
+    }
+}
+
+/// Occlusion tests shared by the main and render worlds: the main world asks
+/// for the pixels of the lights on screen, the render world answers with how
+/// much of each light its depth prepass left open
+#[derive(Resource, Clone, Default)]
+pub struct LensFlareOcclusion {
+    request: Arc<Mutex<Option<OcclusionRequest>>>,
+    open: Arc<Mutex<HashMap<Entity, f32>>>,
+}
+
+impl LensFlareOcclusion {
+    /// Fraction of the light's samples that last showed open sky, if they
+    /// have been read back yet
+    pub fn open(&self, light: Entity) -> Option<f32> {
+        self.open.lock().get(&light).copied()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct OcclusionRequest {
+    camera: Entity,
+    lights: Vec<Entity>,
+    /// `OCCLUSION_SAMPLES` physical pixels per light
+    points: Vec<UVec2>,
+}
+
+/// One element's image under a `LensFlareRoot`
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LensFlareGhost {
+    pub index: usize,
+}
+
+pub fn setup_lens_flare_textures(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut texture = |shape: LensFlareShape| {
+        let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+        let half = TEXTURE_SIZE as f32 * 0.5;
+        for y in 0..TEXTURE_SIZE {
+            for x in 0..TEXTURE_SIZE {
+                let offset = Vec2::new(x as f32 + 0.5 - half, y as f32 + 0.5 - half);
+                let alpha = shape.alpha(offset.length() / half);
+                data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+            }
+        }
+        images.add(Image::new(
+            Extent3d {
+                width: TEXTURE_SIZE,
+                height: TEXTURE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        ))
+    };
+    commands.insert_resource(LensFlareTextures {
+        glow: texture(LensFlareShape::Glow),
+        ghost: texture(LensFlareShape::Ghost),
+        ring: texture(LensFlareShape::Ring),
+    });
+}
+
+/// Give every lens flare a UI root with one image per element, and drop
+/// roots whose light or flare is gone
+pub fn spawn_lens_flares(
+    mut commands: Commands,
+    flares: Query<(Entity, &LensFlare)>,
+    roots: Query<(Entity, &LensFlareRoot, Option<&Children>)>,
+) {
+    let mut drawn = Vec::new();
+    for (root, flare_root, children) in &roots {
+        let element_count = children.map(|children| children.len()).unwrap_or(0);
+        let keep = flares
+            .get(flare_root.light)
+            .is_ok_and(|(_, flare)| flare.elements.len() == element_count);
+        if keep {
+            drawn.push(flare_root.light);
+        } else {
+            commands.entity(root).despawn_recursive();
+        }
+    }
+
+    for (light, flare) in &flares {
+        if drawn.contains(&light) {
+            continue;
+        }
+        commands
+            .spawn((
+                LensFlareRoot::new(light),
+                EditorHidden,
+                Name::new("Lens Flare"),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ))
+            .with_children(|root| {
+                for index in 0..flare.elements.len() {
+                    root.spawn((
+                        LensFlareGhost { index },
+                        ImageBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                }
+            });
+    }
+}
+
+/// Place each flare's elements for the active camera and fade the flare by
+/// how much of the light is in view
+#[allow(clippy::too_many_arguments)]
+pub fn update_lens_flares(
+    mut commands: Commands,
+    time: Res<Time>,
+    textures: Res<LensFlareTextures>,
+    camera_settings: Option<Res<CameraSettings>>,
+    ui_scale: Res<UiScale>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    flares: Query<(&LensFlare, &GlobalTransform)>,
+    occlusion: Res<LensFlareOcclusion>,
+    mut roots: Query<(Entity, &mut LensFlareRoot, &mut Visibility, Option<&TargetCamera>, &Children)>,
+    mut ghosts: Query<(&LensFlareGhost, &mut Style, &mut UiImage), Without<LensFlareRoot>>,
+) {
+    let camera = camera_settings
+        .and_then(|settings| settings.active_camera_entity)
+        .and_then(|entity| cameras.get(entity).ok().map(|camera| (entity, camera)));
+    let mut request = camera.map(|(entity, _)| OcclusionRequest {
+        camera: entity,
+        lights: Vec::new(),
+        points: Vec::new(),
+    });
+
+    for (root, mut flare_root, mut visibility, target_camera, children) in &mut roots {
+        let Ok((flare, light_transform)) = flares.get(flare_root.light) else {
+            continue;
+        };
+        let Some((camera_entity, (camera, camera_transform))) = camera else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let Some(viewport) = camera.logical_viewport_size() else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        if target_camera.map(|target| target.0) != Some(camera_entity) {
+            commands.entity(root).insert(TargetCamera(camera_entity));
+        }
+
+        // Directional lights shine along their forward axis
+        let towards_light = light_transform.back().as_vec3();
+        let camera_position = camera_transform.translation();
+        let screen = (flare.enabled && camera_transform.forward().dot(towards_light) > 0.0)
+            .then(|| camera.world_to_ndc(camera_transform, camera_position + towards_light * LIGHT_DISTANCE))
+            .flatten()
+            .map(|ndc| ndc.truncate())
+            .filter(|ndc| ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0)
+            .map(|ndc| Vec2::new((ndc.x + 1.0) * 0.5 * viewport.x, (1.0 - ndc.y) * 0.5 * viewport.y));
+
+        if let (Some(request), Some(pixels)) = (
+            request.as_mut().filter(|request| request.lights.len() < MAX_OCCLUDED_LIGHTS),
+            screen.and_then(|_| occlusion_pixels(camera, camera_transform, towards_light)),
+        ) {
+            request.lights.push(flare_root.light);
+            request.points.extend(pixels);
+        }
+        // Lights not read back yet are drawn as if nothing covers them
+        let target = match screen {
+            Some(_) => occlusion.open(flare_root.light).unwrap_or(1.0),
+            None => 0.0,
+        };
+        let step = flare.fade_speed.max(0.0) * time.delta_seconds();
+        let faded = if screen.is_none() {
+            0.0
+        } else {
+            flare_root.visibility + (target - flare_root.visibility).clamp(-step, step)
+        };
+        if flare_root.visibility != faded {
+            flare_root.visibility = faded;
+        }
+        let Some(screen) = screen.filter(|_| faded > 0.0) else {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+
+        let center = viewport * 0.5;
+        for &child in children {
+            let Ok((ghost, mut style, mut image)) = ghosts.get_mut(child) else {
+                continue;
+            };
+            let Some(element) = flare.elements.get(ghost.index) else {
+                continue;
+            };
+            // In UI pixels, which `UiScale` scales back up to the viewport's
+            let position = (screen + (center - screen) * element.position) / ui_scale.0;
+            let size = element.size * viewport.y / ui_scale.0;
+            style.left = Val::Px(position.x - size * 0.5);
+            style.top = Val::Px(position.y - size * 0.5);
+            style.width = Val::Px(size);
+            style.height = Val::Px(size);
+
+            let texture = element.texture.as_ref().unwrap_or(textures.get(element.shape));
+            if image.texture != *texture {
+                image.texture = texture.clone();
+            }
+            let alpha = (element.color.alpha() * flare.intensity * faded).clamp(0.0, 1.0);
+            image.color = element.color.with_alpha(alpha);
+        }
+    }
+
+    *occlusion.request.lock() = request.filter(|request| !request.lights.is_empty());
+}
+
+/// Give the active camera a depth prepass while any lens flare needs its
+/// occlusion tested
+pub fn require_lens_flare_depth(
+    mut commands: Commands,
+    camera_settings: Option<Res<CameraSettings>>,
+    flares: Query<&LensFlare>,
+    cameras: Query<(), (With<Camera3d>, Without<DepthPrepass>)>,
+) {
+    if !flares.iter().any(|flare| flare.enabled) {
+        return;
+    }
+    let Some(camera) = camera_settings.and_then(|settings| settings.active_camera_entity) else {
+        return;
+    };
+    if cameras.contains(camera) {
+        commands.entity(camera).insert(DepthPrepass);
+    }
+}
+
+/// Physical pixels of the light's occlusion samples on the camera's target,
+/// which its depth prepass matches
+fn occlusion_pixels(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    towards_light: Vec3,
+) -> Option<[UVec2; OCCLUSION_SAMPLES]> {
+    let viewport = camera.physical_viewport_rect()?;
+    let size = viewport.size();
+    let mut pixels = [UVec2::ZERO; OCCLUSION_SAMPLES];
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let direction = occlusion_sample(towards_light, index);
+        let ndc = camera.world_to_ndc(camera_transform, camera_transform.translation() + direction * LIGHT_DISTANCE)?;
+        let uv = (Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5).clamp(Vec2::ZERO, Vec2::ONE);
+        *pixel = viewport.min + (uv * size.as_vec2()).as_uvec2().min(size.saturating_sub(UVec2::ONE));
+    }
+    Some(pixels)
+}
+
+/// Direction of occlusion sample `index`: the light's center, then points
+/// around the sun disk
+fn occlusion_sample(towards_light: Vec3, index: usize) -> Vec3 {
+    let side = towards_light.any_orthonormal_vector();
+    let up = towards_light.cross(side);
+    match index {
+        1 => towards_light + side * OCCLUSION_SPREAD,
+        2 => towards_light - side * OCCLUSION_SPREAD,
+        3 => towards_light + up * OCCLUSION_SPREAD,
+        4 => towards_light - up * OCCLUSION_SPREAD,
+        _ => towards_light,
+    }
+}
+
+/// Runs in the render app after the prepasses, testing the occlusion samples
+/// of the camera they were taken from
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct LensFlareOcclusionLabel;
+
+#[derive(Resource)]
+pub struct LensFlareOcclusionPipelines {
+    layout: BindGroupLayout,
+    multisampled_layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+    multisampled_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for LensFlareOcclusionPipelines {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = |label: &'static str, depth: BindGroupLayoutEntryBuilder| {
+            device.create_bind_group_layout(
+                label,
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::COMPUTE,
+                    (depth, storage_buffer_read_only_sized(false, None), storage_buffer_sized(false, None)),
+                ),
+            )
+        };
+        let single_layout = layout("lens_flare_occlusion_layout", texture_depth_2d());
+        let multisampled_layout = layout("lens_flare_occlusion_multisampled_layout", texture_depth_2d_multisampled());
+
+        let cache = world.resource::<PipelineCache>();
+        let pipeline = |layout: &BindGroupLayout, shader_defs| {
+            cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some("lens_flare_occlusion_pipeline".into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: LENS_FLARE_SHADER_HANDLE,
+                shader_defs,
+                entry_point: "main".into(),
+            })
+        };
+        Self {
+            pipeline: pipeline(&single_layout, Vec::new()),
+            multisampled_pipeline: pipeline(&multisampled_layout, vec!["MULTISAMPLED".into()]),
+            layout: single_layout,
+            multisampled_layout,
+        }
+    }
+}
+
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+enum ReadbackState {
+    Free,
+    /// Written by this frame's test of these lights
+    Claimed(Vec<Entity>),
+    /// Waiting for the GPU to map the results of these lights
+    Mapping(Vec<Entity>),
+}
+
+struct OcclusionReadback {
+    buffer: Buffer,
+    state: ReadbackState,
+    /// One of the `MAP_*` states, set by the map callback
+    mapped: Arc<AtomicU8>,
+}
+
+/// This frame's occlusion test
+struct OcclusionFrame {
+    camera: Entity,
+    readback: usize,
+    count: usize,
+    points: Buffer,
+    open: Buffer,
+    /// Set by the node once the test is recorded
+    written: AtomicBool,
+}
+
+/// Render world buffers the occlusion results are read back through
+#[derive(Resource, Default)]
+pub struct LensFlareReadbacks {
+    readbacks: Vec<OcclusionReadback>,
+    frame: Option<OcclusionFrame>,
+}
+
+/// Runs in the render app: hands finished readbacks to the main world and
+/// uploads the samples of the main world's latest request
+pub fn prepare_lens_flare_occlusion(
+    device: Res<RenderDevice>,
+    occlusion: Res<LensFlareOcclusion>,
+    mut readbacks: ResMut<LensFlareReadbacks>,
+) {
+    device.poll(Maintain::Poll);
+    for readback in &mut readbacks.readbacks {
+        let ReadbackState::Mapping(lights) = &readback.state else {
+            continue;
+        };
+        match readback.mapped.load(Ordering::Acquire) {
+            MAP_DONE => {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                let mut open = occlusion.open.lock();
+                for (light, samples) in lights.iter().zip(data.chunks_exact(4 * OCCLUSION_SAMPLES)) {
+                    let open_samples = samples
+                        .chunks_exact(4)
+                        .filter(|sample| u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) != 0)
+                        .count();
+                    open.insert(*light, open_samples as f32 / OCCLUSION_SAMPLES as f32);
+                }
+                drop(data);
+                readback.buffer.unmap();
+                readback.state = ReadbackState::Free;
+            }
+            MAP_FAILED => readback.state = ReadbackState::Free,
+            _ => {}
+        }
+    }
+
+    let Some(request) = occlusion.request.lock().take() else {
+        return;
+    };
+    let readback = match readbacks
+        .readbacks
+        .iter()
+        .position(|readback| matches!(readback.state, ReadbackState::Free))
+    {
+        Some(readback) => readback,
+        None if readbacks.readbacks.len() < OCCLUSION_READBACKS => {
+            readbacks.readbacks.push(OcclusionReadback {
+                buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("lens_flare_occlusion_readback"),
+                    size: (MAX_OCCLUSION_POINTS * 4) as u64,
+                    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                state: ReadbackState::Free,
+                mapped: Arc::default(),
+            });
+            readbacks.readbacks.len() - 1
+        }
+        None => return,
+    };
+
+    let count = request.points.len().min(MAX_OCCLUSION_POINTS);
+    let points: Vec<u8> = request.points[..count]
+        .iter()
+        .flat_map(|point| point.to_array())
+        .flat_map(u32::to_le_bytes)
+        .collect();
+    let points = device.create_buffer_with_data(&BufferInitDescriptor {
+        label: Some("lens_flare_occlusion_points"),
+        contents: &points,
+        usage: BufferUsages::STORAGE,
+    });
+    let open = device.create_buffer(&BufferDescriptor {
+        label: Some("lens_flare_occlusion_open"),
+        size: (count * 4) as u64,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    readbacks.readbacks[readback].state = ReadbackState::Claimed(request.lights);
+    readbacks.frame = Some(OcclusionFrame {
+        camera: request.camera,
+        readback,
+        count,
+        points,
+        open,
+        written: AtomicBool::new(false),
+    });
+}
+
+/// Tests this frame's occlusion samples against the view's depth prepass
+#[derive(Default)]
+pub struct LensFlareOcclusionNode;
+
+impl ViewNode for LensFlareOcclusionNode {
+    type ViewQuery = &'static ViewPrepassTextures;
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        prepass_textures: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let readbacks = world.resource::<LensFlareReadbacks>();
+        let Some(frame) = readbacks.frame.as_ref() else {
+            return Ok(());
+        };
+        if frame.camera != graph.view_entity() || frame.written.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let Some(depth) = prepass_textures.depth.as_ref() else {
+            return Ok(());
+        };
+
+        let pipelines = world.resource::<LensFlareOcclusionPipelines>();
+        let (layout, pipeline) = if depth.texture.texture.sample_count() > 1 {
+            (&pipelines.multisampled_layout, pipelines.multisampled_pipeline)
+        } else {
+            (&pipelines.layout, pipelines.pipeline)
+        };
+        let Some(pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(pipeline) else {
+            return Ok(());
+        };
+        let bind_group = render_context.render_device().create_bind_group(
+            "lens_flare_occlusion_bind_group",
+            layout,
+            &BindGroupEntries::sequential((
+                &depth.texture.default_view,
+                frame.points.as_entire_binding(),
+                frame.open.as_entire_binding(),
+            )),
+        );
+
+        let encoder = render_context.command_encoder();
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("lens_flare_occlusion"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((frame.count as u32).div_ceil(64), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(
+            &frame.open,
+            0,
+            &readbacks.readbacks[frame.readback].buffer,
+            0,
+            (frame.count * 4) as u64,
+        );
+        frame.written.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Runs in the render app once the frame is submitted: starts mapping the
+/// readback the node wrote, or frees it if the camera wasn't drawn
+pub fn map_lens_flare_occlusion(mut readbacks: ResMut<LensFlareReadbacks>) {
+    let Some(frame) = readbacks.frame.take() else {
+        return;
+    };
+    let readback = &mut readbacks.readbacks[frame.readback];
+    let ReadbackState::Claimed(lights) = std::mem::replace(&mut readback.state, ReadbackState::Free) else {
+        return;
+    };
+    if !frame.written.load(Ordering::Relaxed) {
+        return;
+    }
+    readback.mapped.store(MAP_PENDING, Ordering::Release);
+    let mapped = readback.mapped.clone();
+    readback.buffer.slice(..).map_async(MapMode::Read, move |result| {
+        mapped.store(if result.is_ok() { MAP_DONE } else { MAP_FAILED }, Ordering::Release);
+    });
+    readback.state = ReadbackState::Mapping(lights);
+}
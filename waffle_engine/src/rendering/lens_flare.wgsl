@@ -0,0 +1,21 @@
+// Waffle Engine lens flare occlusion
+// Reads the depth prepass under each occlusion sample of a flare's light and
+// writes 1 where nothing was drawn in front of the sky, 0 where something was.
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var depth: texture_depth_multisampled_2d;
+#else
+@group(0) @binding(0) var depth: texture_depth_2d;
+#endif
+@group(0) @binding(1) var<storage, read> points: array<vec2<u32>>;
+@group(0) @binding(2) var<storage, read_write> open: array<u32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= arrayLength(&points) {
+        return;
+    }
+    let pixel = min(points[id.x], textureDimensions(depth) - vec2(1u));
+    // Reverse-Z: the depth is still cleared to 0 wherever only sky is seen
+    open[id.x] = select(0u, 1u, textureLoad(depth, pixel, 0) <= 0.0);
+}
@@ -1,9 +1,11 @@
-/// Lighting Module
-/// Handles all types of lights and lighting effects
+//! Lighting Module
+//! Handles all types of lights and lighting effects
 
 use bevy::prelude::*;
 use std::f32::consts::PI;
-use crate::rendering::scene::{EnvironmentSettings, SceneRootEntity};
+use crate::rendering::lens_flare::LensFlare;
+use crate::rendering::environment::ActiveEnvironment;
+use crate::rendering::scene::SceneRootEntity;
 
 #[derive(Component)]
 pub struct WaffleLight {
@@ -55,6 +57,7 @@ pub fn setup_lighting(
                 shadows_enabled: true,
             },
             WaffleDirectionalLight,
+            LensFlare::default(),
             DirectionalLightBundle {
                 directional_light: DirectionalLight {
                     illuminance: 10000.0,
@@ -223,16 +226,19 @@ fn spawn_spot_light(
 }
 
 pub fn sync_sun_from_environment(
-    env_query: Query<&EnvironmentSettings, Or<(Added<EnvironmentSettings>, Changed<EnvironmentSettings>)>>,
+    active: Res<ActiveEnvironment>,
     mut light_query: Query<(&mut DirectionalLight, &mut Transform), With<WaffleDirectionalLight>>,
 ) {
-    let Some(env) = env_query.iter().next() else {
+    if !active.is_changed() {
+        return;
+    }
+    let Some(env) = active.settings.as_ref() else {
         return;
     };
     for (mut light, mut transform) in &mut light_query {
         light.color = env.sun_color;
         light.illuminance = env.sun_intensity;
-        let direction = sun_direction(env);
+        let direction = env.sun_direction();
         transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, -direction);
     }
 }
@@ -265,12 +271,3 @@ pub fn sync_light_components(
         }
     }
 }
-
-fn sun_direction(env: &EnvironmentSettings) -> Vec3 {
-    let time = env.time_of_day.rem_euclid(24.0);
-    let elevation = (time / 24.0) * std::f32::consts::TAU - std::f32::consts::FRAC_PI_2;
-    let azimuth = env.sun_azimuth.to_radians();
-    let (sin_e, cos_e) = elevation.sin_cos();
-    let (sin_a, cos_a) = azimuth.sin_cos();
-    Vec3::new(cos_a * cos_e, sin_e, sin_a * cos_e).normalize_or_zero()
-}
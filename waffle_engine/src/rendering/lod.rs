@@ -0,0 +1,170 @@
+//! LOD Module
+//! A `LodGroup` swaps an entity's mesh for simpler ones as the camera moves
+//! away. Each level names a mesh and the camera distance it takes over at;
+//! a level without a mesh draws the mesh the entity had when the group was
+//! added. With split screen the nearest camera decides. Hysteresis keeps the
+//! current level until the camera is that far past a switch distance, so an
+//! object sitting right on one doesn't flicker between meshes.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::rendering::camera::WaffleMainCamera;
+
+/// One mesh of a LOD group
+#[derive(Reflect, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LodLevel {
+    /// Mesh asset path, e.g. `models/tree.glb#Mesh1/Primitive0`; the
+    /// entity's own mesh when empty
+    pub mesh: String,
+    /// Camera distance this level is drawn from
+    pub distance: f32,
+}
+
+/// Meshes to draw at increasing camera distances, nearest first
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LodGroup {
+    pub levels: Vec<LodLevel>,
+    /// How far past a switch distance the camera has to be before the level
+    /// changes; zero switches right on it
+    pub hysteresis: f32,
+}
+
+impl Default for LodGroup {
+    fn default() -> Self {
+        Self {
+            levels: vec![LodLevel::default()],
+            hysteresis: 1.0,
+        }
+    }
+}
+
+impl LodGroup {
+    /// The level to draw at `distance` from the camera, given the one drawn now
+    pub fn level_at(&self, distance: f32, current: Option<usize>) -> usize {
+        let reached = |offset: f32| {
+            self.levels
+                .iter()
+                .rposition(|level| distance >= level.distance + offset)
+                .unwrap_or(0)
+        };
+        let target = reached(0.0);
+        match current.filter(|current| *current < self.levels.len()) {
+            // Going out, the camera has to pass the next switch by the
+            // hysteresis; coming back in, fall short of the current one by it
+            Some(current) if target > current => reached(self.hysteresis).max(current),
+            Some(current) if target < current => reached(-self.hysteresis).min(current),
+            _ => target,
+        }
+    }
+}
+
+/// The loaded meshes of a LOD group and the level being drawn
+#[derive(Component)]
+pub struct LodState {
+    /// The entity's mesh from before the group was added
+    base: Handle<Mesh>,
+    paths: Vec<String>,
+    /// Loaded mesh of each level; `None` for the base mesh
+    meshes: Vec<Option<Handle<Mesh>>>,
+    active: Option<usize>,
+    camera_distance: Option<f32>,
+    /// Level drawn whatever the distance, for previewing in the editor
+    pub preview: Option<usize>,
+}
+
+impl LodState {
+    fn new(base: Handle<Mesh>) -> Self {
+        Self {
+            base,
+            paths: Vec::new(),
+            meshes: Vec::new(),
+            active: None,
+            camera_distance: None,
+            preview: None,
+        }
+    }
+
+    pub fn base(&self) -> &Handle<Mesh> {
+        &self.base
+    }
+
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Distance to the nearest camera, when there is one
+    pub fn camera_distance(&self) -> Option<f32> {
+        self.camera_distance
+    }
+}
+
+/// Swap the mesh of each LOD group for the level its camera distance calls for
+pub fn update_lod_groups(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<WaffleMainCamera>>,
+    mut groups: Query<(Entity, &LodGroup, &GlobalTransform, &mut Handle<Mesh>, Option<&mut LodState>)>,
+) {
+    let cameras: Vec<Vec3> = camera_query
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation())
+        .collect();
+
+    for (entity, group, transform, mut mesh, state) in groups.iter_mut() {
+        let Some(mut state) = state else {
+            commands.entity(entity).insert(LodState::new(mesh.clone()));
+            continue;
+        };
+        let paths_changed = state.paths.len() != group.levels.len()
+            || state.paths.iter().zip(&group.levels).any(|(path, level)| *path != level.mesh);
+        if paths_changed {
+            state.paths = group.levels.iter().map(|level| level.mesh.clone()).collect();
+            state.meshes = group
+                .levels
+                .iter()
+                .map(|level| (!level.mesh.is_empty()).then(|| asset_server.load(level.mesh.clone())))
+                .collect();
+        }
+
+        let position = transform.translation();
+        let distance = cameras.iter().map(|camera| camera.distance(position)).reduce(f32::min);
+        state.camera_distance = distance;
+        let level = match state.preview.filter(|level| *level < group.levels.len()) {
+            Some(level) => Some(level),
+            None if group.levels.is_empty() => None,
+            None => distance.map(|distance| group.level_at(distance, state.active)),
+        };
+        if level.is_none() && !group.levels.is_empty() {
+            continue;
+        }
+        if state.active == level && !paths_changed {
+            continue;
+        }
+        state.active = level;
+        let handle = level
+            .and_then(|level| state.meshes[level].clone())
+            .unwrap_or_else(|| state.base.clone());
+        if *mesh != handle {
+            *mesh = handle;
+        }
+    }
+}
+
+/// Put the original mesh back on entities whose LOD group was removed
+pub fn restore_lod_base_meshes(
+    mut commands: Commands,
+    mut removed: RemovedComponents<LodGroup>,
+    mut state_query: Query<(&LodState, &mut Handle<Mesh>), Without<LodGroup>>,
+) {
+    for entity in removed.read() {
+        let Ok((state, mut mesh)) = state_query.get_mut(entity) else {
+            continue;
+        };
+        *mesh = state.base.clone();
+        commands.entity(entity).remove::<LodState>();
+    }
+}
@@ -0,0 +1,362 @@
+//! World Markers Module
+//! UI nodes anchored to 3D entities: icons, nameplates and health bars
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::core::health::Health;
+use crate::core::interaction::{Interactable, InteractionFocus};
+use crate::core::project::ProjectSettings;
+use crate::core::spatial::SpatialQuery;
+use crate::rendering::camera::CameraSettings;
+
+/// Keeps an absolutely positioned UI node over a world entity
+#[derive(Component, Debug, Clone)]
+pub struct WorldMarker {
+    pub target: Entity,
+    /// World-space offset from the target's origin, e.g. above the head
+    pub offset: Vec3,
+    /// Pin the marker to the screen edge when the target is off screen
+    pub clamp_to_screen: bool,
+    pub screen_margin: f32,
+    /// Distance at which the marker is drawn at full size; `None` disables scaling
+    pub reference_distance: Option<f32>,
+    pub min_scale: f32,
+    /// Markers further away than this are hidden
+    pub max_distance: Option<f32>,
+    /// Opacity used while geometry blocks the line of sight; `None` disables the check
+    pub occluded_opacity: Option<f32>,
+    pub opacity: f32,
+}
+
+impl WorldMarker {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            offset: Vec3::ZERO,
+            clamp_to_screen: false,
+            screen_margin: 16.0,
+            reference_distance: None,
+            min_scale: 0.4,
+            max_distance: None,
+            occluded_opacity: None,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn clamped(mut self, margin: f32) -> Self {
+        self.clamp_to_screen = true;
+        self.screen_margin = margin;
+        self
+    }
+
+    pub fn scaled(mut self, reference_distance: f32, min_scale: f32) -> Self {
+        self.reference_distance = Some(reference_distance.max(0.01));
+        self.min_scale = min_scale.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn fade_when_occluded(mut self, opacity: f32) -> Self {
+        self.occluded_opacity = Some(opacity.clamp(0.0, 1.0));
+        self
+    }
+}
+
+/// Fill node of a nameplate health bar, sized from the target's `Health`
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HealthBarFill {
+    pub target: Entity,
+}
+
+/// Spawn a nameplate with a name label and optional health bar above `target`.
+pub fn spawn_nameplate(commands: &mut Commands, target: Entity, name: &str, health_bar: bool) -> Entity {
+    let marker = WorldMarker::new(target)
+        .with_offset(Vec3::Y * 2.2)
+        .scaled(10.0, 0.5)
+        .fade_when_occluded(0.35);
+    commands
+        .spawn((
+            marker,
+            Name::new(format!("{name} Nameplate")),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|plate| {
+            plate.spawn(TextBundle::from_section(
+                name,
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            if health_bar {
+                plate
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(80.0),
+                            height: Val::Px(6.0),
+                            ..default()
+                        },
+                        background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                        ..default()
+                    })
+                    .with_children(|bar| {
+                        bar.spawn((
+                            HealthBarFill { target },
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.0),
+                                    height: Val::Percent(100.0),
+                                    ..default()
+                                },
+                                background_color: Color::srgb(0.85, 0.2, 0.2).into(),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        })
+        .id()
+}
+
+/// Prompt marker shown over the focused interactable
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InteractionPrompt;
+
+pub fn spawn_interaction_prompt(commands: &mut Commands, target: Entity, prompt: &str, offset: Vec3) -> Entity {
+    commands
+        .spawn((
+            InteractionPrompt,
+            WorldMarker::new(target).with_offset(offset),
+            Name::new("Interaction Prompt"),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+        ))
+        .with_children(|prompt_node| {
+            prompt_node.spawn(TextBundle::from_section(
+                prompt,
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
+
+/// Keep one prompt over the focused interactable, replacing it when the
+/// focus or its prompt text changes
+pub fn update_interaction_prompts(
+    mut commands: Commands,
+    focus: Option<Res<InteractionFocus>>,
+    interactables: Query<Ref<Interactable>>,
+    prompts: Query<(Entity, &WorldMarker), With<InteractionPrompt>>,
+) {
+    let focused = focus
+        .and_then(|focus| focus.entity)
+        .and_then(|entity| interactables.get(entity).ok().map(|interactable| (entity, interactable)))
+        .filter(|(_, interactable)| interactable.show_prompt);
+
+    let mut current = false;
+    for (prompt, marker) in &prompts {
+        let keep = focused
+            .as_ref()
+            .is_some_and(|(entity, interactable)| marker.target == *entity && !interactable.is_changed());
+        if keep && !current {
+            current = true;
+        } else {
+            commands.entity(prompt).despawn_recursive();
+        }
+    }
+
+    if let (Some((entity, interactable)), false) = (focused, current) {
+        spawn_interaction_prompt(&mut commands, entity, &interactable.prompt, interactable.prompt_offset);
+    }
+}
+
+/// Position markers over their targets as seen by the active camera
+pub fn update_world_markers(
+    mut commands: Commands,
+    camera_settings: Option<Res<CameraSettings>>,
+    project_settings: Res<ProjectSettings>,
+    ui_scale: Res<UiScale>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    targets: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    spatial: SpatialQuery,
+    mut markers: Query<(
+        Entity,
+        &mut WorldMarker,
+        &mut Style,
+        &mut Transform,
+        &mut Visibility,
+        &Node,
+        Option<&TargetCamera>,
+        Has<Parent>,
+    )>,
+) {
+    let Some(camera_entity) = camera_settings.and_then(|settings| settings.active_camera_entity) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get(camera_entity) else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_size() else {
+        return;
+    };
+    let camera_position = camera_transform.translation();
+    let view_from_world = camera_transform.affine().inverse();
+
+    for (entity, mut marker, mut style, mut transform, mut visibility, node, target_camera, has_parent) in
+        &mut markers
+    {
+        let Ok(target_transform) = targets.get(marker.target) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        // Root markers draw into whichever camera they are tracked against,
+        // which in the editor is the viewport texture rather than the window.
+        if !has_parent && target_camera.map(|target| target.0) != Some(camera_entity) {
+            commands.entity(entity).insert(TargetCamera(camera_entity));
+        }
+        let world_position = target_transform.translation() + marker.offset;
+        let distance = camera_position.distance(world_position);
+        if marker.max_distance.is_some_and(|max| distance > max) {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        let Some(ndc) = camera.world_to_ndc(camera_transform, world_position) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        // Points behind the camera project mirrored; flip them back so clamping
+        // pushes the marker towards the side the target is actually on.
+        let behind = view_from_world.transform_point3(world_position).z > 0.0;
+        let mut ndc = ndc.truncate();
+        if behind {
+            ndc = -ndc;
+        }
+        let on_screen = !behind && ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0;
+        if !on_screen && !marker.clamp_to_screen {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        if !on_screen {
+            let max = ndc.x.abs().max(ndc.y.abs());
+            if behind || max > 1.0 {
+                ndc /= max.max(f32::EPSILON);
+            }
+        }
+
+        let mut screen = Vec2::new((ndc.x + 1.0) * 0.5 * viewport.x, (1.0 - ndc.y) * 0.5 * viewport.y);
+        if marker.clamp_to_screen {
+            // Clamped markers stay inside the safe area too
+            let safe_margin = viewport * project_settings.game_ui.safe_margin();
+            let margin = (Vec2::splat(marker.screen_margin * ui_scale.0) + safe_margin).min(viewport * 0.5);
+            screen = screen.clamp(margin, viewport - margin);
+        }
+
+        // Node sizes and `Val::Px` are in UI pixels, which `UiScale` scales
+        let half_size = node.size() * 0.5;
+        style.position_type = PositionType::Absolute;
+        style.left = Val::Px(screen.x / ui_scale.0 - half_size.x);
+        style.top = Val::Px(screen.y / ui_scale.0 - half_size.y);
+        *visibility = Visibility::Inherited;
+
+        let scale = marker
+            .reference_distance
+            .map(|reference| (reference / distance.max(0.01)).clamp(marker.min_scale, 1.0))
+            .unwrap_or(1.0);
+        if transform.scale.x != scale {
+            transform.scale = Vec3::new(scale, scale, 1.0);
+        }
+
+        let opacity = match marker.occluded_opacity {
+            Some(occluded) if on_screen => {
+                let target = marker.target;
+                let blocked = spatial
+                    .cast_ray_filtered(camera_position, world_position - camera_position, distance, |candidate| {
+                        !std::iter::once(candidate)
+                            .chain(parents.iter_ancestors(candidate))
+                            .any(|owner| owner == target)
+                    })
+                    .is_some();
+                if blocked { occluded } else { 1.0 }
+            }
+            _ => 1.0,
+        };
+        if marker.opacity != opacity {
+            marker.opacity = opacity;
+        }
+    }
+}
+
+/// Apply marker opacity to the marker node and everything under it, scaling
+/// each node's authored alpha as it was when the marker first changed.
+pub fn apply_world_marker_opacity(
+    markers: Query<(Entity, &WorldMarker), Changed<WorldMarker>>,
+    mut removed: RemovedComponents<WorldMarker>,
+    children: Query<&Children>,
+    mut backgrounds: Query<&mut BackgroundColor>,
+    mut images: Query<&mut UiImage>,
+    mut texts: Query<&mut Text>,
+    mut base_alpha: Local<HashMap<Entity, HashMap<Entity, f32>>>,
+) {
+    for entity in removed.read() {
+        base_alpha.remove(&entity);
+    }
+
+    for (entity, marker) in &markers {
+        let bases = base_alpha.entry(entity).or_default();
+        for node in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+            if let Ok(mut background) = backgrounds.get_mut(node) {
+                let base = *bases.entry(node).or_insert(background.0.alpha());
+                background.0.set_alpha(base * marker.opacity);
+            } else if let Ok(mut image) = images.get_mut(node) {
+                let base = *bases.entry(node).or_insert(image.color.alpha());
+                image.color.set_alpha(base * marker.opacity);
+            } else if let Ok(mut text) = texts.get_mut(node) {
+                let Some(first) = text.sections.first() else {
+                    continue;
+                };
+                let base = *bases.entry(node).or_insert(first.style.color.alpha());
+                for section in text.sections.iter_mut() {
+                    section.style.color.set_alpha(base * marker.opacity);
+                }
+            }
+        }
+    }
+}
+
+pub fn update_health_bars(healths: Query<&Health>, mut fills: Query<(&HealthBarFill, &mut Style)>) {
+    for (fill, mut style) in &mut fills {
+        let fraction = healths.get(fill.target).map(Health::fraction).unwrap_or(0.0);
+        let width = Val::Percent(fraction * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
@@ -1,8 +1,16 @@
-/// Materials Module
-/// Handles material creation, management, and rendering
+//! Materials Module
+//! Handles material creation, management, and rendering
 
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
-use bevy::render::render_resource::{TextureFormat, TextureUsages};
+use bevy::render::render_resource::{Face, TextureFormat, TextureUsages};
+use serde::{Deserialize, Serialize};
+
+use crate::core::surface::{MaterialSurfaces, PhysicalSurface};
+
+/// Extension of material asset files
+pub const MATERIAL_EXTENSION: &str = "wmat";
 
 #[derive(Component)]
 pub struct WaffleMaterial {
@@ -30,6 +38,19 @@ pub struct MaterialLibrary {
     pub material_names: Vec<String>,
 }
 
+impl MaterialLibrary {
+    /// Add a material under `name`, returning its index
+    pub fn add(&mut self, name: impl Into<String>, material: Handle<StandardMaterial>) -> usize {
+        self.materials.push(material);
+        self.material_names.push(name.into());
+        self.materials.len() - 1
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<StandardMaterial>)> {
+        self.material_names.iter().map(String::as_str).zip(&self.materials)
+    }
+}
+
 #[derive(Component, Default)]
 pub struct PbrTextureOverrides {
     pub metallic_map: Option<Handle<Image>>,
@@ -289,3 +310,179 @@ pub fn update_pbr_texture_overrides(
         overrides.last_roughness = overrides.roughness_map.clone();
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaterialAlphaMode {
+    Opaque,
+    Mask(f32),
+    Blend,
+    Premultiplied,
+    AlphaToCoverage,
+    Add,
+    Multiply,
+}
+
+impl From<AlphaMode> for MaterialAlphaMode {
+    fn from(mode: AlphaMode) -> Self {
+        match mode {
+            AlphaMode::Opaque => MaterialAlphaMode::Opaque,
+            AlphaMode::Mask(cutoff) => MaterialAlphaMode::Mask(cutoff),
+            AlphaMode::Blend => MaterialAlphaMode::Blend,
+            AlphaMode::Premultiplied => MaterialAlphaMode::Premultiplied,
+            AlphaMode::AlphaToCoverage => MaterialAlphaMode::AlphaToCoverage,
+            AlphaMode::Add => MaterialAlphaMode::Add,
+            AlphaMode::Multiply => MaterialAlphaMode::Multiply,
+        }
+    }
+}
+
+impl From<MaterialAlphaMode> for AlphaMode {
+    fn from(mode: MaterialAlphaMode) -> Self {
+        match mode {
+            MaterialAlphaMode::Opaque => AlphaMode::Opaque,
+            MaterialAlphaMode::Mask(cutoff) => AlphaMode::Mask(cutoff),
+            MaterialAlphaMode::Blend => AlphaMode::Blend,
+            MaterialAlphaMode::Premultiplied => AlphaMode::Premultiplied,
+            MaterialAlphaMode::AlphaToCoverage => AlphaMode::AlphaToCoverage,
+            MaterialAlphaMode::Add => AlphaMode::Add,
+            MaterialAlphaMode::Multiply => AlphaMode::Multiply,
+        }
+    }
+}
+
+/// A `StandardMaterial` as stored in `.wmat` files and scenes; textures are
+/// asset paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialFile {
+    pub base_color: Color,
+    pub base_color_texture: Option<String>,
+    pub emissive: Color,
+    pub emissive_texture: Option<String>,
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+    pub metallic_roughness_texture: Option<String>,
+    pub reflectance: f32,
+    pub normal_map_texture: Option<String>,
+    pub occlusion_texture: Option<String>,
+    pub alpha_mode: MaterialAlphaMode,
+    pub double_sided: bool,
+    pub unlit: bool,
+    /// What footsteps and impacts on the material sound like
+    pub surface: Option<PhysicalSurface>,
+}
+
+impl Default for MaterialFile {
+    fn default() -> Self {
+        Self::from(&StandardMaterial::default())
+    }
+}
+
+impl From<&StandardMaterial> for MaterialFile {
+    fn from(material: &StandardMaterial) -> Self {
+        let path = |texture: &Option<Handle<Image>>| {
+            texture
+                .as_ref()
+                .and_then(|texture| texture.path())
+                .map(|path| path.to_string())
+        };
+        Self {
+            base_color: material.base_color,
+            base_color_texture: path(&material.base_color_texture),
+            emissive: material.emissive.into(),
+            emissive_texture: path(&material.emissive_texture),
+            perceptual_roughness: material.perceptual_roughness,
+            metallic: material.metallic,
+            metallic_roughness_texture: path(&material.metallic_roughness_texture),
+            reflectance: material.reflectance,
+            normal_map_texture: path(&material.normal_map_texture),
+            occlusion_texture: path(&material.occlusion_texture),
+            alpha_mode: material.alpha_mode.into(),
+            double_sided: material.double_sided,
+            unlit: material.unlit,
+            surface: None,
+        }
+    }
+}
+
+impl MaterialFile {
+    /// Build the material, getting textures from `load`
+    pub fn to_material(&self, mut load: impl FnMut(&str) -> Handle<Image>) -> StandardMaterial {
+        let mut texture = |path: &Option<String>| path.as_deref().map(&mut load);
+        StandardMaterial {
+            base_color: self.base_color,
+            base_color_texture: texture(&self.base_color_texture),
+            emissive: self.emissive.to_linear(),
+            emissive_texture: texture(&self.emissive_texture),
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            metallic_roughness_texture: texture(&self.metallic_roughness_texture),
+            reflectance: self.reflectance,
+            normal_map_texture: texture(&self.normal_map_texture),
+            occlusion_texture: texture(&self.occlusion_texture),
+            alpha_mode: self.alpha_mode.into(),
+            double_sided: self.double_sided,
+            cull_mode: if self.double_sided { None } else { Some(Face::Back) },
+            unlit: self.unlit,
+            ..default()
+        }
+    }
+}
+
+/// Loads `.wmat` files as `StandardMaterial` assets, recording the surface
+/// each names in `MaterialSurfaces`
+pub struct MaterialFileLoader {
+    surfaces: MaterialSurfaces,
+}
+
+impl FromWorld for MaterialFileLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            surfaces: world.get_resource_or_insert_with(MaterialSurfaces::default).clone(),
+        }
+    }
+}
+
+impl AssetLoader for MaterialFileLoader {
+    type Asset = StandardMaterial;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<StandardMaterial, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: MaterialFile = ron::de::from_bytes(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        self.surfaces.set_for_path(load_context.asset_path().to_string(), file.surface);
+        Ok(file.to_material(|path| load_context.load(path.to_string())))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[MATERIAL_EXTENSION]
+    }
+}
+/// Texture slots of a material, in a fixed order
+pub fn material_textures(material: &StandardMaterial) -> [&Option<Handle<Image>>; 5] {
+    [
+        &material.base_color_texture,
+        &material.normal_map_texture,
+        &material.metallic_roughness_texture,
+        &material.emissive_texture,
+        &material.occlusion_texture,
+    ]
+}
+
+pub fn material_textures_mut(material: &mut StandardMaterial) -> [&mut Option<Handle<Image>>; 5] {
+    [
+        &mut material.base_color_texture,
+        &mut material.normal_map_texture,
+        &mut material.metallic_roughness_texture,
+        &mut material.emissive_texture,
+        &mut material.occlusion_texture,
+    ]
+}
@@ -0,0 +1,150 @@
+//! Minimap Module
+//! Top-down orthographic cameras that render into textures for game UI
+
+use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, RenderTarget, ScalingMode};
+use bevy::render::render_resource::Extent3d;
+use bevy::render::view::RenderLayers;
+
+use crate::core::components::EditorHidden;
+use crate::rendering::camera::create_viewport_image;
+
+/// Render layer for map-only geometry such as icons and simplified terrain.
+/// The main camera renders layer 0 only, so entities here appear just on minimaps.
+pub const MINIMAP_LAYER: usize = 1;
+
+const MINIMAP_CAMERA_ORDER: isize = -10;
+
+/// Renders a top-down orthographic view into `image`.
+///
+/// Spawn it with `MinimapCamera::new(size)`; the camera and texture are
+/// created on the next frame and `image` can then be shown with
+/// `minimap_image_bundle`.
+#[derive(Component, Clone)]
+pub struct MinimapCamera {
+    /// Entity the view stays centered on; `None` keeps `center`
+    pub follow: Option<Entity>,
+    pub center: Vec3,
+    /// World units visible from top to bottom of the texture
+    pub extent: f32,
+    pub height: f32,
+    /// Turn the map with the followed entity's heading
+    pub rotate_with_target: bool,
+    pub size: UVec2,
+    /// Layers this minimap draws; defaults to the scene plus `MINIMAP_LAYER`
+    pub layers: RenderLayers,
+    pub background: Color,
+    pub image: Handle<Image>,
+}
+
+impl MinimapCamera {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            follow: None,
+            center: Vec3::ZERO,
+            extent: 50.0,
+            height: 200.0,
+            rotate_with_target: false,
+            size,
+            layers: RenderLayers::from_layers(&[0, MINIMAP_LAYER]),
+            background: Color::srgb(0.08, 0.09, 0.1),
+            image: Handle::default(),
+        }
+    }
+
+    pub fn following(mut self, entity: Entity) -> Self {
+        self.follow = Some(entity);
+        self
+    }
+
+    pub fn with_extent(mut self, extent: f32) -> Self {
+        self.extent = extent.max(0.1);
+        self
+    }
+
+    pub fn with_layers(mut self, layers: RenderLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+/// UI image node showing a minimap texture
+pub fn minimap_image_bundle(minimap: &MinimapCamera, size: Val) -> ImageBundle {
+    ImageBundle {
+        image: UiImage::new(minimap.image.clone()),
+        style: Style {
+            width: size,
+            height: size,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// Create the render texture and camera for newly added minimaps
+pub fn setup_minimap_cameras(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut minimaps: Query<(Entity, &mut MinimapCamera), Added<MinimapCamera>>,
+) {
+    for (entity, mut minimap) in &mut minimaps {
+        let size = Extent3d {
+            width: minimap.size.x.max(1),
+            height: minimap.size.y.max(1),
+            ..default()
+        };
+        minimap.image = images.add(create_viewport_image(size));
+        commands.entity(entity).insert((
+            EditorHidden,
+            Camera3dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(minimap.image.clone()),
+                    order: MINIMAP_CAMERA_ORDER,
+                    clear_color: ClearColorConfig::Custom(minimap.background),
+                    ..default()
+                },
+                projection: OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical(minimap.extent),
+                    far: minimap.height * 2.0,
+                    ..default()
+                }
+                .into(),
+                transform: minimap_transform(&minimap, None),
+                ..default()
+            },
+            minimap.layers.clone(),
+        ));
+    }
+}
+
+/// Keep minimap cameras above their target and in sync with their settings
+pub fn update_minimap_cameras(
+    mut minimaps: Query<(&MinimapCamera, &mut Transform, &mut Projection, &mut RenderLayers)>,
+    targets: Query<&GlobalTransform>,
+) {
+    for (minimap, mut transform, mut projection, mut layers) in &mut minimaps {
+        let target = minimap.follow.and_then(|entity| targets.get(entity).ok());
+        *transform = minimap_transform(minimap, target);
+
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            if !matches!(ortho.scaling_mode, ScalingMode::FixedVertical(extent) if extent == minimap.extent) {
+                ortho.scaling_mode = ScalingMode::FixedVertical(minimap.extent);
+            }
+        }
+        if *layers != minimap.layers {
+            *layers = minimap.layers.clone();
+        }
+    }
+}
+
+fn minimap_transform(minimap: &MinimapCamera, target: Option<&GlobalTransform>) -> Transform {
+    let center = target.map(|target| target.translation()).unwrap_or(minimap.center);
+    let forward = target
+        .filter(|_| minimap.rotate_with_target)
+        .map(|target| {
+            let forward = target.forward().as_vec3();
+            Vec3::new(forward.x, 0.0, forward.z).normalize_or(Vec3::NEG_Z)
+        })
+        .unwrap_or(Vec3::NEG_Z);
+    Transform::from_translation(center + Vec3::Y * minimap.height).looking_at(center, forward)
+}
@@ -1,62 +1,348 @@
-/// Waffle Engine 3D Rendering Module
-/// Contains all 3D rendering functionality and systems
+//! Waffle Engine 3D Rendering Module
+//! Contains all 3D rendering functionality and systems
 
 pub mod scene;
+pub mod environment;
+pub mod sun;
 pub mod lighting;
 pub mod materials;
+pub mod obj;
 pub mod camera;
 pub mod post_processing;
 pub mod shadows;
 pub mod atmosphere;
 pub mod fog;
+pub mod minimap;
+pub mod markers;
+pub mod dialogue_box;
+pub mod game_ui;
+pub mod placeholders;
+pub mod warmup;
+pub mod hdr;
+pub mod color;
+pub mod lens_flare;
+pub mod ao_volume;
+pub mod camera_shake;
+pub mod camera_rig;
+pub mod split_screen;
+pub mod portal;
+pub mod highlight;
+pub mod weather;
+pub mod particles;
+pub mod foliage;
+pub mod instancing;
+pub mod water;
+pub mod lod;
 
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::auto_exposure::AutoExposurePlugin;
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
 use bevy::prelude::*;
+use bevy::render::render_graph::{RenderGraphApp, ViewNodeRunner};
+use bevy::render::{Render, RenderApp, RenderSet};
+use crate::core::system_toggles::toggleable;
 use scene::*;
+use environment::*;
 use lighting::*;
 use materials::*;
+use obj::*;
 use camera::*;
 use post_processing::*;
 use shadows::*;
 use fog::*;
+use minimap::*;
+use markers::*;
+use dialogue_box::*;
+use game_ui::*;
+use placeholders::*;
+use warmup::*;
+use hdr::*;
+use lens_flare::*;
+use ao_volume::*;
+use camera_shake::*;
+use camera_rig::*;
+use split_screen::*;
+use portal::*;
+use highlight::*;
+use weather::*;
+use particles::*;
+use foliage::*;
+use instancing::*;
+use water::*;
+use lod::*;
 
 pub struct WaffleRenderingPlugin;
 
 impl Plugin for WaffleRenderingPlugin {
     fn build(&self, app: &mut App) {
+        load_internal_asset!(app, WATER_SHADER_HANDLE, "water.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, LENS_FLARE_SHADER_HANDLE, "lens_flare.wgsl", Shader::from_wgsl);
         app
             // Add 3D scene systems
+            .register_type::<SceneSettings>()
             .add_systems(Startup, setup_3d_scene)
-            .add_systems(Update, update_3d_scene)
-            .add_systems(Update, apply_environment_settings)
-            .add_systems(Update, update_sky_dome)
-            .add_systems(Update, sync_sky_dome_to_camera)
-            .add_systems(Update, ensure_scene_root_parenting)
+            .add_systems(Update, toggleable(update_3d_scene))
+            .init_resource::<ActiveEnvironment>()
+            .add_systems(Update, (toggleable(bind_scene_environments), toggleable(update_active_environment)).chain())
+            .add_plugins(AutoExposurePlugin)
+            .add_systems(Update, toggleable(apply_environment_settings).after(update_active_environment))
+            .add_systems(Update, toggleable(sync_auto_exposure_hdr).after(apply_environment_settings))
+            .add_systems(Update, toggleable(update_sky_dome).after(update_active_environment))
+            .add_systems(Update, toggleable(sync_sky_dome_to_camera))
+            .add_systems(Update, toggleable(ensure_scene_root_parenting))
 
             // Add lighting systems
             .add_systems(Startup, setup_lighting.after(setup_3d_scene))
-            .add_systems(Update, sync_sun_from_environment)
-            .add_systems(Update, sync_light_components)
+            .add_systems(Update, toggleable(sync_sun_from_environment).after(update_active_environment))
+            .add_systems(Update, toggleable(sync_light_components))
 
             // Add material systems
+            .init_asset_loader::<MaterialFileLoader>()
+            .init_asset_loader::<ObjLoader>()
             .add_systems(Startup, setup_materials.after(setup_3d_scene))
-            .add_systems(Update, update_materials)
-            .add_systems(Update, ensure_pbr_overrides)
-            .add_systems(Update, update_pbr_texture_overrides)
+            .add_systems(Update, toggleable(update_materials))
+            .add_systems(Update, toggleable(ensure_pbr_overrides))
+            .add_systems(Update, toggleable(update_pbr_texture_overrides))
 
             // Add camera systems
             .add_systems(Startup, setup_camera)
-            .add_systems(Update, update_camera)
+            .add_systems(Update, toggleable(update_camera))
+            .add_systems(
+                PostUpdate,
+                sync_viewport_camera_target.before(bevy::render::camera::CameraUpdateSystem),
+            )
+
+            // Add cinematic camera rigs; they place cameras before transforms propagate
+            .add_systems(Update, advance_camera_dollies.run_if(crate::core::play::is_playing))
+            .add_systems(
+                PostUpdate,
+                apply_camera_rigs.before(bevy::transform::TransformSystem::TransformPropagate),
+            )
+
+            // Add trauma based camera shake on top of the final camera transforms
+            .add_event::<CameraShakeEvent>()
+            .add_event::<PreviewCameraShakeEvent>()
+            .add_systems(Update, (handle_camera_shake_events, update_camera_shakes).chain())
+            .add_systems(
+                PostUpdate,
+                apply_camera_shakes.after(bevy::transform::TransformSystem::TransformPropagate),
+            )
+
+            // Add split screen local players; sounds are routed once bevy
+            // audio has placed its listener
+            .init_resource::<LocalPlayers>()
+            .add_systems(
+                PostUpdate,
+                layout_split_screen
+                    .after(sync_viewport_camera_target)
+                    .before(bevy::render::camera::CameraUpdateSystem),
+            )
+            .add_systems(Last, route_split_screen_audio)
+
+            // Add portals and mirrors; their cameras are placed from this
+            // frame's transforms before projections are computed
+            .add_plugins((
+                bevy::render::camera::CameraProjectionPlugin::<PortalProjection>::default(),
+                bevy::pbr::PbrProjectionPlugin::<PortalProjection>::default(),
+            ))
+            .register_type::<Portal>()
+            .add_systems(Update, (build_portal_views, despawn_orphaned_portal_cameras).chain())
+            .add_systems(Update, toggleable(show_portals_to_scene_cameras))
+            .add_systems(
+                PostUpdate,
+                update_portal_cameras
+                    .after(apply_camera_rigs)
+                    .before(bevy::render::camera::CameraUpdateSystem),
+            )
+
+            // Add gameplay highlights, following the interaction focus
+            .register_type::<Highlight>()
+            .add_systems(Update, update_highlight_hulls.after(crate::core::interaction::update_interaction_focus))
+
+            // Add weather; its fog goes on top of the environment's
+            .init_resource::<Weather>()
+            .init_resource::<WeatherSettings>()
+            .init_resource::<WetMaterials>()
+            .init_resource::<WeatherBeforePlay>()
+            .add_systems(Startup, setup_weather)
+            .add_systems(
+                Update,
+                (
+                    toggleable(update_weather),
+                    toggleable(update_precipitation),
+                    toggleable(apply_surface_wetness),
+                    toggleable(update_weather_ambience),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, toggleable(apply_weather_fog).after(apply_environment_settings).after(update_weather))
+            .add_systems(OnEnter(crate::core::play::PlayState::Playing), save_weather_before_play)
+            .add_systems(OnExit(crate::core::play::PlayState::Playing), restore_weather_after_play)
+
+            // Add CPU particle emitters; quads face the camera once it has moved
+            .register_type::<ParticleEmitter>()
+            .init_asset::<ParticleEffect>()
+            .init_asset_loader::<ParticleEffectLoader>()
+            .add_systems(Startup, setup_particle_texture)
+            .add_systems(
+                Update,
+                (
+                    toggleable(despawn_orphaned_particle_visuals),
+                    toggleable(spawn_particle_visuals),
+                    toggleable(simulate_particles),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                toggleable(build_particle_meshes).after(bevy::transform::TransformSystem::TransformPropagate),
+            )
+
+            // Add foliage layers, batched into one mesh per cell
+            .register_type::<FoliageLayer>()
+            .add_systems(
+                Update,
+                (toggleable(despawn_orphaned_foliage_cells), toggleable(build_foliage_batches)).chain(),
+            )
+
+            // Add instancing; identical meshes under an InstancedMesh share assets so they batch
+            .register_type::<InstancedMesh>()
+            .init_resource::<InstancingRegistry>()
+            .init_resource::<InstancingStats>()
+            .add_systems(Update, toggleable(share_instanced_assets))
+            .add_systems(
+                PostUpdate,
+                collect_instancing_stats.after(bevy::render::view::VisibilitySystems::CheckVisibility),
+            )
+
+            // Add water; planar reflection cameras are placed like portal cameras
+            .add_plugins((
+                MaterialPlugin::<WaterMaterial>::default(),
+                bevy::render::camera::CameraProjectionPlugin::<WaterReflectionProjection>::default(),
+                bevy::pbr::PbrProjectionPlugin::<WaterReflectionProjection>::default(),
+            ))
+            .register_type::<WaffleWater>()
+            .add_systems(Startup, setup_default_water_normals)
+            .add_systems(
+                Update,
+                (
+                    toggleable(build_water_views),
+                    toggleable(despawn_orphaned_water_cameras),
+                    toggleable(add_water_depth_prepass),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                toggleable(update_water_reflections)
+                    .after(apply_camera_rigs)
+                    .before(bevy::render::camera::CameraUpdateSystem),
+            )
+
+            // Add LOD groups; meshes swap by distance to the nearest camera
+            .register_type::<LodGroup>()
+            .add_systems(Update, (toggleable(restore_lod_base_meshes), toggleable(update_lod_groups)).chain())
+
+            // Add minimap systems
+            .add_systems(Update, (toggleable(setup_minimap_cameras), toggleable(update_minimap_cameras)).chain())
+
+            // Add the game UI canvas and its scaling
+            .add_systems(Update, (toggleable(update_game_ui_scale), toggleable(update_game_ui_canvas)))
+
+            // Add world marker systems; UI layout runs before transform
+            // propagation, so markers track last frame's global transforms
+            .add_systems(
+                PostUpdate,
+                (update_interaction_prompts, update_world_markers, apply_world_marker_opacity, update_health_bars)
+                    .chain()
+                    .after(bevy::render::camera::CameraUpdateSystem)
+                    .before(bevy::ui::UiSystem::Layout),
+            )
+
+            // Add lens flares of directional lights
+            .add_systems(Startup, setup_lens_flare_textures)
+            .add_systems(
+                PostUpdate,
+                (spawn_lens_flares, update_lens_flares)
+                    .chain()
+                    .after(bevy::render::camera::CameraUpdateSystem)
+                    .before(bevy::ui::UiSystem::Layout),
+            )
+            .add_systems(Update, require_lens_flare_depth)
+
+            // Add baked AO volumes
+            .add_event::<BakeAoVolumeEvent>()
+            .add_systems(
+                Update,
+                (start_ao_volume_bakes, finish_ao_volume_bakes, apply_ao_volumes)
+                    .chain()
+                    .after(apply_environment_settings),
+            )
+
+            // Add the in-game dialogue box
+            .add_systems(
+                Update,
+                (
+                    handle_dialogue_box_input
+                        .run_if(crate::core::play::is_playing)
+                        .before(crate::core::dialogue::run_dialogues),
+                    update_dialogue_box.after(crate::core::dialogue::run_dialogues),
+                ),
+            )
+
+            // Add placeholders for assets that went missing
+            .add_event::<LocateMissingAssetEvent>()
+            .add_systems(Startup, setup_placeholder_assets)
+            .add_systems(Update, (replace_missing_assets, relink_missing_assets).chain())
+
+            // Add shader warm-up; pipeline counts come from the render app
+            .init_resource::<ShaderWarmup>()
+            .add_systems(Startup, start_boot_shader_warmup.after(crate::core::project::load_project_settings))
+            .add_systems(Update, run_shader_warmup)
+
+            // Add the HDR output check
+            .add_systems(Update, check_hdr_output)
 
             // Add post-processing systems
             .add_systems(Startup, setup_post_processing)
             .add_systems(Update, update_post_processing)
 
             // Add shadow systems
+            .register_type::<ShadowSettings>()
             .add_systems(Startup, setup_shadows)
             .add_systems(Update, update_shadows)
 
             // Add fog systems
             .add_systems(Startup, setup_fog)
             .add_systems(Update, update_fog);
+
+        let pipeline_progress = PipelineProgress::default();
+        let hdr_output_status = HdrOutputStatus::default();
+        let lens_flare_occlusion = LensFlareOcclusion::default();
+        app.insert_resource(pipeline_progress.clone())
+            .insert_resource(hdr_output_status.clone())
+            .insert_resource(lens_flare_occlusion.clone());
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(pipeline_progress)
+                .insert_resource(hdr_output_status)
+                .insert_resource(lens_flare_occlusion)
+                .init_resource::<LensFlareReadbacks>()
+                .add_systems(Render, prepare_lens_flare_occlusion.in_set(RenderSet::Prepare))
+                .add_systems(
+                    Render,
+                    (report_pipeline_progress, report_swapchain_format, map_lens_flare_occlusion)
+                        .in_set(RenderSet::Cleanup),
+                )
+                .add_render_graph_node::<ViewNodeRunner<LensFlareOcclusionNode>>(Core3d, LensFlareOcclusionLabel)
+                .add_render_graph_edges(Core3d, (Node3d::EndPrepasses, LensFlareOcclusionLabel, Node3d::StartMainPass));
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Compute pipelines need the render device, which is only ready now
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<LensFlareOcclusionPipelines>();
+        }
     }
 }
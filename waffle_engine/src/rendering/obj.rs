@@ -0,0 +1,245 @@
+//! OBJ Module
+//! Loads Wavefront `.obj` models along with the materials of their `.mtl`
+//! libraries. The file itself loads as a single mesh of every object in it,
+//! for thumbnails and anything else that wants the whole shape. Each object
+//! is also a labeled `Mesh{i}`, each MTL material a `Material{i}`, and
+//! `Scene` places every object on an entity wearing its material, the way a
+//! glTF's scenes do. MTL specular maps have no slot in `StandardMaterial`, so
+//! they become the roughness channel of a generated metallic/roughness map.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageLoaderSettings;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const OBJ_EXTENSION: &str = "obj";
+
+/// Label of the scene with every object of a model in place
+pub const OBJ_SCENE_LABEL: &str = "Scene";
+
+pub fn obj_mesh_label(index: usize) -> String {
+    format!("Mesh{index}")
+}
+
+pub fn obj_material_label(index: usize) -> String {
+    format!("Material{index}")
+}
+
+/// Material libraries an OBJ file names, as written in it
+pub fn obj_material_libraries(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("mtllib "))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The objects and materials of an OBJ file. `read_library` returns the
+/// contents of a material library by the name the file gives it; a missing
+/// library leaves the objects without materials.
+pub fn parse_obj(
+    bytes: &[u8],
+    read_library: impl Fn(&str) -> Option<Vec<u8>>,
+) -> anyhow::Result<(Vec<tobj::Model>, Vec<tobj::Material>)> {
+    let (models, materials) = tobj::load_obj_buf(&mut &bytes[..], &tobj::GPU_LOAD_OPTIONS, |path| {
+        let library = read_library(&path.to_string_lossy()).ok_or(tobj::LoadError::OpenFileFailed)?;
+        tobj::load_mtl_buf(&mut &library[..])
+    })?;
+    let materials = materials.unwrap_or_else(|err| {
+        warn!("Failed to read OBJ materials: {}", err);
+        Vec::new()
+    });
+    Ok((models, materials))
+}
+
+/// One mesh of the given objects
+pub fn obj_mesh<'a>(meshes: impl IntoIterator<Item = &'a tobj::Mesh>) -> Mesh {
+    let mut indices = Vec::new();
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    for mesh in meshes {
+        let offset = positions.len() as u32;
+        positions.extend(mesh.positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]));
+        normals.extend(mesh.normals.chunks_exact(3).map(|n| [n[0], n[1], n[2]]));
+        // OBJ texture coordinates start at the bottom
+        uvs.extend(mesh.texcoords.chunks_exact(2).map(|t| [t[0], 1.0 - t[1]]));
+        indices.extend(mesh.indices.iter().map(|index| index + offset));
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_indices(Indices::U32(indices));
+    // Attributes only some objects have can't be used for all of them
+    let vertex_count = positions.len();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    if uvs.len() == vertex_count {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    if normals.len() == vertex_count {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    } else {
+        mesh.duplicate_vertices();
+        mesh.compute_flat_normals();
+    }
+    mesh
+}
+
+/// File named by an MTL texture statement, without options like `-bm 0.5`
+fn texture_file(statement: &str) -> &str {
+    if statement.starts_with('-') {
+        statement.split_whitespace().last().unwrap_or(statement)
+    } else {
+        statement
+    }
+}
+
+/// Roughness from a specular map: shiny where the map is bright
+fn specular_to_roughness(bytes: &[u8]) -> anyhow::Result<Image> {
+    let specular = image::load_from_memory(bytes)?.to_luma8();
+    let (width, height) = specular.dimensions();
+    let data = specular
+        .pixels()
+        .flat_map(|pixel| [255, 255 - pixel.0[0], 0, 255])
+        .collect();
+    Ok(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::default(),
+    ))
+}
+
+/// Loads `.obj` files as meshes, with their objects, materials and scene as
+/// labeled sub-assets
+#[derive(Default)]
+pub struct ObjLoader;
+
+impl AssetLoader for ObjLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<Mesh> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let directory = load_context.path().parent().map(Path::to_path_buf).unwrap_or_default();
+        let file_path = |name: &str| -> PathBuf { directory.join(name.replace('\\', "/")) };
+
+        let mut libraries = HashMap::new();
+        for name in obj_material_libraries(&bytes) {
+            match load_context.read_asset_bytes(file_path(&name)).await {
+                Ok(library) => {
+                    libraries.insert(name, library);
+                }
+                Err(err) => warn!("Failed to read material library {} of {}: {}", name, load_context.path().display(), err),
+            }
+        }
+        let (models, obj_materials) = parse_obj(&bytes, |name| libraries.get(name).cloned())?;
+
+        let mut materials = Vec::new();
+        for (index, obj_material) in obj_materials.iter().enumerate() {
+            let mut material = StandardMaterial {
+                perceptual_roughness: 0.8,
+                metallic: 0.0,
+                ..default()
+            };
+            if let Some([r, g, b]) = obj_material.diffuse {
+                material.base_color = Color::srgb(r, g, b);
+            }
+            if let Some(opacity) = obj_material.dissolve.filter(|opacity| *opacity < 1.0) {
+                material.base_color.set_alpha(opacity);
+                material.alpha_mode = AlphaMode::Blend;
+            }
+            // Phong exponents run from about 0 (rough) to 1000 (mirror)
+            if let Some(shininess) = obj_material.shininess {
+                material.perceptual_roughness = (2.0 / (shininess.max(0.0) + 2.0)).sqrt();
+            }
+            if let Some([r, g, b]) = obj_material.specular {
+                material.reflectance = ((r + g + b) / 3.0).clamp(0.0, 1.0);
+            }
+            if let Some(texture) = &obj_material.diffuse_texture {
+                material.base_color_texture = Some(load_context.load(file_path(texture_file(texture))));
+            }
+            if let Some(texture) = &obj_material.normal_texture {
+                material.normal_map_texture = Some(
+                    load_context
+                        .loader()
+                        .with_settings(|settings: &mut ImageLoaderSettings| settings.is_srgb = false)
+                        .load(file_path(texture_file(texture))),
+                );
+            }
+            if let Some(texture) = &obj_material.specular_texture {
+                let path = file_path(texture_file(texture));
+                let roughness = match load_context.read_asset_bytes(path.clone()).await {
+                    Ok(bytes) => specular_to_roughness(&bytes),
+                    Err(err) => Err(err.into()),
+                };
+                match roughness {
+                    Ok(image) => {
+                        let label = format!("{}/Roughness", obj_material_label(index));
+                        material.metallic_roughness_texture = Some(load_context.add_labeled_asset(label, image));
+                        material.perceptual_roughness = 1.0;
+                    }
+                    Err(err) => warn!("Failed to read specular map {}: {}", path.display(), err),
+                }
+            }
+            materials.push(material);
+        }
+
+        let mut world = World::new();
+        let mut default_material = None;
+        for (index, model) in models.iter().enumerate() {
+            let mut mesh = obj_mesh([&model.mesh]);
+            let obj_material = model.mesh.material_id.filter(|id| *id < materials.len());
+            let material = match obj_material {
+                Some(id) => {
+                    // Normal maps are read along the mesh's tangents
+                    if materials[id].normal_map_texture.is_some() {
+                        if let Err(err) = mesh.generate_tangents() {
+                            warn!("Failed to generate tangents for {}: {}", model.name, err);
+                        }
+                    }
+                    load_context.get_label_handle(obj_material_label(id))
+                }
+                None => default_material
+                    .get_or_insert_with(|| load_context.add_labeled_asset("DefaultMaterial".to_string(), StandardMaterial::default()))
+                    .clone(),
+            };
+            let mesh = load_context.add_labeled_asset(obj_mesh_label(index), mesh);
+            world.spawn((
+                Name::new(model.name.clone()),
+                PbrBundle {
+                    mesh,
+                    material,
+                    ..default()
+                },
+            ));
+        }
+        for (index, material) in materials.into_iter().enumerate() {
+            load_context.add_labeled_asset(obj_material_label(index), material);
+        }
+        load_context.add_labeled_asset(OBJ_SCENE_LABEL.to_string(), Scene::new(world));
+
+        Ok(obj_mesh(models.iter().map(|model| &model.mesh)))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[OBJ_EXTENSION]
+    }
+}
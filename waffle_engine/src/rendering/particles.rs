@@ -0,0 +1,556 @@
+//! Particles Module
+//! CPU particle effects like fire, smoke and sparkles. A `ParticleEmitter`
+//! spawns particles at a steady rate into a cone around its up axis; each one
+//! is pulled by the emitter's gravity, slowed by drag, and grows, shrinks and
+//! changes color over its life. Particles live in world space, so moving the
+//! emitter leaves a trail.
+//! Size and color can instead follow a curve and a gradient over the life of
+//! a particle. An emitter's settings can be saved as a `.particle` effect
+//! and reused on other entities.
+//! The living particles of an emitter are drawn as one mesh of quads facing
+//! the main camera, rebuilt every frame, so each emitter is a single draw.
+
+use std::path::Path;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::view::NoFrustumCulling;
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+use crate::core::random::WaffleRng;
+use crate::rendering::camera::CameraSettings;
+
+/// Extension of particle effect files
+pub const PARTICLE_EXTENSION: &str = "particle";
+
+const TEXTURE_SIZE: u32 = 64;
+/// At most this many particles are spawned in one frame, so a hitch doesn't
+/// release a burst
+const MAX_SPAWN_PER_FRAME: u32 = 256;
+
+/// A key of the size curve
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SizeKey {
+    /// Fraction of the particle's life, 0 to 1
+    pub time: f32,
+    pub size: f32,
+}
+
+/// A stop of the color gradient
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    /// Fraction of the particle's life, 0 to 1
+    pub time: f32,
+    pub color: Color,
+}
+
+/// The two keys around `t` in keys sorted by time, and how far `t` is from
+/// the first to the second
+fn key_span<K>(keys: &[K], t: f32, time: impl Fn(&K) -> f32) -> Option<(&K, &K, f32)> {
+    let first = keys.first()?;
+    let next = keys.iter().position(|key| time(key) > t);
+    Some(match next {
+        Some(0) => (first, first, 0.0),
+        Some(index) => {
+            let (from, to) = (&keys[index - 1], &keys[index]);
+            let span = (time(to) - time(from)).max(f32::EPSILON);
+            (from, to, ((t - time(from)) / span).clamp(0.0, 1.0))
+        }
+        None => {
+            let last = keys.last()?;
+            (last, last, 0.0)
+        }
+    })
+}
+
+/// Emits particles from the entity it is on
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleEmitter {
+    pub enabled: bool,
+    /// Particles spawned per second
+    pub rate: f32,
+    pub max_particles: u32,
+    /// Seconds a particle lives
+    pub lifetime: f32,
+    /// Fraction the lifetime and speed vary by, 0 to 1
+    pub variation: f32,
+    /// Starting speed in meters per second
+    pub speed: f32,
+    /// Angle in degrees between the emitter's up axis and the edge of the
+    /// cone particles leave in; 180 sends them every way
+    pub cone_angle: f32,
+    /// Particles start anywhere within this distance of the emitter
+    pub spawn_radius: f32,
+    /// Acceleration in world space; upwards for rising smoke
+    pub gravity: Vec3,
+    /// Fraction of their speed particles lose per second
+    pub drag: f32,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    /// Size over the particle's life, sorted by time; the start and end size
+    /// are used when empty
+    pub size_curve: Vec<SizeKey>,
+    /// Color over the particle's life, sorted by time; the start and end
+    /// color are used when empty
+    pub color_gradient: Vec<ColorStop>,
+    /// Add particle colors onto what is behind them instead of blending, for
+    /// glowing effects like fire and sparkles
+    pub additive: bool,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate: 20.0,
+            max_particles: 200,
+            lifetime: 2.0,
+            variation: 0.2,
+            speed: 1.0,
+            cone_angle: 20.0,
+            spawn_radius: 0.0,
+            gravity: Vec3::ZERO,
+            drag: 0.0,
+            start_size: 0.2,
+            end_size: 0.2,
+            start_color: Color::WHITE,
+            end_color: Color::srgba(1.0, 1.0, 1.0, 0.0),
+            size_curve: Vec::new(),
+            color_gradient: Vec::new(),
+            additive: false,
+        }
+    }
+}
+
+impl ParticleEmitter {
+    /// Size of a particle `t` of the way through its life
+    pub fn size_at(&self, t: f32) -> f32 {
+        match key_span(&self.size_curve, t, |key| key.time) {
+            Some((from, to, t)) => from.size.lerp(to.size, t),
+            None => self.start_size.lerp(self.end_size, t),
+        }
+    }
+
+    /// The color gradient in linear space, from the start and end color when
+    /// there are no stops
+    pub fn linear_gradient(&self) -> Vec<(f32, LinearRgba)> {
+        if self.color_gradient.is_empty() {
+            return vec![(0.0, self.start_color.to_linear()), (1.0, self.end_color.to_linear())];
+        }
+        self.color_gradient.iter().map(|stop| (stop.time, stop.color.to_linear())).collect()
+    }
+
+    /// Put curve keys and gradient stops back in time order after an edit
+    pub fn sort_keys(&mut self) {
+        self.size_curve.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.color_gradient.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+}
+
+/// Color of a particle `t` of the way through its life
+pub fn sample_gradient(gradient: &[(f32, LinearRgba)], t: f32) -> LinearRgba {
+    match key_span(gradient, t, |stop| stop.0) {
+        Some((from, to, t)) => from.1.mix(&to.1, t),
+        None => LinearRgba::WHITE,
+    }
+}
+
+/// A `.particle` file: the settings of an emitter, to reuse on other entities
+#[derive(Asset, TypePath, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ParticleEffect(pub ParticleEmitter);
+
+impl ParticleEffect {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ParticleEffectLoader;
+
+impl AssetLoader for ParticleEffectLoader {
+    type Asset = ParticleEffect;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<ParticleEffect, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[PARTICLE_EXTENSION]
+    }
+}
+
+/// Starting points for common effects
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticlePreset {
+    Fire,
+    Smoke,
+    Sparkles,
+}
+
+impl ParticlePreset {
+    pub const ALL: [ParticlePreset; 3] = [ParticlePreset::Fire, ParticlePreset::Smoke, ParticlePreset::Sparkles];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ParticlePreset::Fire => "Fire",
+            ParticlePreset::Smoke => "Smoke",
+            ParticlePreset::Sparkles => "Sparkles",
+        }
+    }
+
+    pub fn emitter(self) -> ParticleEmitter {
+        match self {
+            ParticlePreset::Fire => ParticleEmitter {
+                rate: 60.0,
+                lifetime: 0.8,
+                variation: 0.3,
+                speed: 1.2,
+                cone_angle: 12.0,
+                spawn_radius: 0.15,
+                gravity: Vec3::new(0.0, 1.5, 0.0),
+                drag: 0.5,
+                start_size: 0.35,
+                end_size: 0.05,
+                start_color: Color::srgba(1.0, 0.6, 0.15, 0.9),
+                end_color: Color::srgba(0.8, 0.1, 0.0, 0.0),
+                additive: true,
+                ..default()
+            },
+            ParticlePreset::Smoke => ParticleEmitter {
+                rate: 12.0,
+                lifetime: 4.0,
+                variation: 0.3,
+                speed: 0.6,
+                cone_angle: 15.0,
+                spawn_radius: 0.2,
+                gravity: Vec3::new(0.0, 0.3, 0.0),
+                drag: 0.3,
+                start_size: 0.4,
+                end_size: 1.6,
+                start_color: Color::srgba(0.35, 0.35, 0.35, 0.5),
+                end_color: Color::srgba(0.6, 0.6, 0.6, 0.0),
+                additive: false,
+                ..default()
+            },
+            ParticlePreset::Sparkles => ParticleEmitter {
+                rate: 30.0,
+                lifetime: 1.2,
+                variation: 0.5,
+                speed: 2.5,
+                cone_angle: 180.0,
+                spawn_radius: 0.1,
+                gravity: Vec3::new(0.0, -2.0, 0.0),
+                drag: 1.0,
+                start_size: 0.08,
+                end_size: 0.0,
+                start_color: Color::srgb(1.0, 0.95, 0.6),
+                end_color: Color::srgba(1.0, 0.7, 0.3, 0.0),
+                additive: true,
+                ..default()
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+}
+
+/// The particles of an emitter and the entity that draws them
+#[derive(Component)]
+pub struct ParticleSystemState {
+    particles: Vec<Particle>,
+    /// Fraction of a particle owed from the last frame
+    spawn_debt: f32,
+    rng: WaffleRng,
+    visual: Entity,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    additive: bool,
+}
+
+impl ParticleSystemState {
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Drop every living particle, e.g. to see an effect start again
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_debt = 0.0;
+    }
+}
+
+/// Draws the particles of `emitter`; kept apart from it so the quads stay
+/// in world space
+#[derive(Component)]
+pub struct ParticleVisual {
+    emitter: Entity,
+}
+
+#[derive(Resource)]
+pub struct ParticleTexture(pub Handle<Image>);
+
+/// A soft round dot every particle is drawn with
+pub fn setup_particle_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    let half = TEXTURE_SIZE as f32 * 0.5;
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let offset = Vec2::new(x as f32 + 0.5 - half, y as f32 + 0.5 - half);
+            let alpha = (1.0 - offset.length() / half).clamp(0.0, 1.0).powi(2);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    commands.insert_resource(ParticleTexture(images.add(Image::new(
+        Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))));
+}
+
+fn particle_material(texture: &ParticleTexture, additive: bool) -> StandardMaterial {
+    StandardMaterial {
+        base_color_texture: Some(texture.0.clone()),
+        unlit: true,
+        cull_mode: None,
+        alpha_mode: if additive { AlphaMode::Add } else { AlphaMode::Blend },
+        ..default()
+    }
+}
+
+/// Give each new emitter its particle list and mesh
+pub fn spawn_particle_visuals(
+    mut commands: Commands,
+    texture: Option<Res<ParticleTexture>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    emitters: Query<(Entity, &ParticleEmitter), Without<ParticleSystemState>>,
+) {
+    let Some(texture) = texture else {
+        return;
+    };
+    for (entity, emitter) in emitters.iter() {
+        let mesh = meshes.add(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default()));
+        let material = materials.add(particle_material(&texture, emitter.additive));
+        let visual = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                ParticleVisual { emitter: entity },
+                // The quads move every frame, past any bounds computed once
+                NoFrustumCulling,
+                NotShadowCaster,
+                EditorHidden,
+                Name::new("Particles"),
+            ))
+            .id();
+        commands.entity(entity).insert(ParticleSystemState {
+            particles: Vec::new(),
+            spawn_debt: 0.0,
+            rng: WaffleRng::new(entity.to_bits()),
+            visual,
+            mesh,
+            material,
+            additive: emitter.additive,
+        });
+    }
+}
+
+/// Despawn the quads of emitters that were removed
+pub fn despawn_orphaned_particle_visuals(
+    mut commands: Commands,
+    visuals: Query<(Entity, &ParticleVisual)>,
+    emitters: Query<(), With<ParticleEmitter>>,
+) {
+    for (entity, visual) in visuals.iter() {
+        if !emitters.contains(visual.emitter) {
+            commands.entity(entity).despawn();
+            if let Some(mut emitter) = commands.get_entity(visual.emitter) {
+                emitter.remove::<ParticleSystemState>();
+            }
+        }
+    }
+}
+
+/// A direction within `angle` degrees of up
+fn cone_direction(rng: &mut WaffleRng, angle: f32) -> Vec3 {
+    let min_cos = angle.clamp(0.0, 180.0).to_radians().cos();
+    let cos = rng.range_f32(min_cos, 1.0);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let around = rng.range_f32(0.0, std::f32::consts::TAU);
+    Vec3::new(sin * around.cos(), cos, sin * around.sin())
+}
+
+/// Age, move and spawn the particles of each emitter
+pub fn simulate_particles(
+    time: Res<Time>,
+    mut emitters: Query<(&ParticleEmitter, &GlobalTransform, &mut ParticleSystemState)>,
+) {
+    let delta = time.delta_seconds();
+    for (emitter, transform, mut state) in emitters.iter_mut() {
+        let state = &mut *state;
+        let damping = (1.0 - emitter.drag * delta).clamp(0.0, 1.0);
+        state.particles.retain_mut(|particle| {
+            particle.age += delta;
+            particle.velocity = (particle.velocity + emitter.gravity * delta) * damping;
+            particle.position += particle.velocity * delta;
+            particle.age < particle.lifetime
+        });
+
+        if !emitter.enabled {
+            state.spawn_debt = 0.0;
+            continue;
+        }
+        state.spawn_debt += emitter.rate.max(0.0) * delta;
+        let count = (state.spawn_debt.floor() as u32).min(MAX_SPAWN_PER_FRAME);
+        state.spawn_debt = state.spawn_debt.fract();
+
+        let (_, rotation, origin) = transform.to_scale_rotation_translation();
+        let variation = emitter.variation.clamp(0.0, 1.0);
+        for _ in 0..count {
+            if state.particles.len() >= emitter.max_particles as usize {
+                break;
+            }
+            let rng = &mut state.rng;
+            let direction = rotation * cone_direction(rng, emitter.cone_angle);
+            let speed = emitter.speed * (1.0 + rng.range_f32(-variation, variation));
+            let lifetime = emitter.lifetime * (1.0 + rng.range_f32(-variation, variation));
+            let offset = rng.point_in_sphere(emitter.spawn_radius);
+            state.particles.push(Particle {
+                position: origin + offset,
+                velocity: direction * speed,
+                age: 0.0,
+                lifetime: lifetime.max(0.01),
+            });
+        }
+    }
+}
+
+/// Rebuild each emitter's quads facing the main camera
+pub fn build_particle_meshes(
+    texture: Option<Res<ParticleTexture>>,
+    camera_settings: Res<CameraSettings>,
+    cameras: Query<&GlobalTransform>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut emitters: Query<(&ParticleEmitter, &mut ParticleSystemState)>,
+    mut visuals: Query<&mut Visibility, With<ParticleVisual>>,
+) {
+    let Some(camera) = camera_settings
+        .main_camera_entity
+        .and_then(|camera| cameras.get(camera).ok())
+    else {
+        return;
+    };
+    let camera_position = camera.translation();
+    let right = camera.right();
+    let up = camera.up();
+
+    for (emitter, mut state) in emitters.iter_mut() {
+        let Ok(mut visibility) = visuals.get_mut(state.visual) else {
+            continue;
+        };
+        let wanted = if state.particles.is_empty() { Visibility::Hidden } else { Visibility::Inherited };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        if state.additive != emitter.additive {
+            if let (Some(texture), Some(material)) = (texture.as_ref(), materials.get_mut(&state.material)) {
+                *material = particle_material(texture, emitter.additive);
+            }
+            state.additive = emitter.additive;
+        }
+        if state.particles.is_empty() {
+            continue;
+        }
+
+        // Blended particles are drawn far to near so nearer ones cover the rest
+        if !emitter.additive {
+            state.particles.sort_by(|a, b| {
+                let a = a.position.distance_squared(camera_position);
+                let b = b.position.distance_squared(camera_position);
+                b.total_cmp(&a)
+            });
+        }
+
+        let gradient = emitter.linear_gradient();
+        let count = state.particles.len();
+        let mut positions = Vec::with_capacity(count * 4);
+        let mut uvs = Vec::with_capacity(count * 4);
+        let mut colors = Vec::with_capacity(count * 4);
+        let mut normals = Vec::with_capacity(count * 4);
+        let mut indices = Vec::with_capacity(count * 6);
+        for particle in &state.particles {
+            let t = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            let half = emitter.size_at(t).max(0.0) * 0.5;
+            let color = sample_gradient(&gradient, t).to_f32_array();
+            let side = *right * half;
+            let rise = *up * half;
+            let base = positions.len() as u32;
+            positions.extend([
+                (particle.position - side - rise).to_array(),
+                (particle.position + side - rise).to_array(),
+                (particle.position + side + rise).to_array(),
+                (particle.position - side + rise).to_array(),
+            ]);
+            uvs.extend([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+            colors.extend([color; 4]);
+            normals.extend([(camera_position - particle.position).normalize_or_zero().to_array(); 4]);
+            indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        if let Some(mesh) = meshes.get_mut(&state.mesh) {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+            mesh.insert_indices(Indices::U32(indices));
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Missing Asset Placeholders
+//! When a file the scene uses no longer exists, the objects using it get a
+//! magenta stand-in instead of silently rendering nothing: missing models
+//! become a cube named after the missing path and missing textures a checker
+//! pattern. `LocateMissingAssetEvent` points everything that used the missing
+//! path at a replacement file.
+
+use bevy::asset::{AssetLoadError, AssetPath, UntypedAssetLoadFailedEvent};
+use bevy::asset::io::AssetReaderError;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use std::path::Path;
+
+use super::materials::{material_textures, material_textures_mut};
+
+const PLACEHOLDER_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingAssetKind {
+    /// glTF scene
+    Scene,
+    /// Mesh loaded on its own, e.g. from an OBJ file
+    Mesh,
+    Texture,
+}
+
+impl MissingAssetKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            MissingAssetKind::Scene | MissingAssetKind::Mesh => "Model",
+            MissingAssetKind::Texture => "Texture",
+        }
+    }
+}
+
+/// Marks an entity that shows a placeholder for a file that was not found
+#[derive(Component, Clone, Debug)]
+pub struct MissingAsset {
+    /// File that was not found, relative to the assets folder
+    pub path: String,
+    /// Label of the missing handle, e.g. `Scene0`
+    pub label: Option<String>,
+    pub kind: MissingAssetKind,
+    /// Name before the entity was renamed after the missing path
+    pub original_name: Option<String>,
+}
+
+/// Point everything that used `missing` at `replacement`
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct LocateMissingAssetEvent {
+    pub missing: String,
+    pub replacement: String,
+}
+
+/// A material texture slot showing the placeholder texture
+struct SwappedTexture {
+    material: AssetId<StandardMaterial>,
+    slot: usize,
+    path: AssetPath<'static>,
+}
+
+#[derive(Resource)]
+pub struct PlaceholderAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub texture: Handle<Image>,
+    swapped_textures: Vec<SwappedTexture>,
+}
+
+pub fn setup_placeholder_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut data = Vec::with_capacity(8 * 8 * 4);
+    for y in 0..8 {
+        for x in 0..8 {
+            let color = if (x + y) % 2 == 0 { PLACEHOLDER_COLOR } else { [0, 0, 0, 255] };
+            data.extend_from_slice(&color);
+        }
+    }
+    let mut texture = Image::new(
+        Extent3d {
+            width: 8,
+            height: 8,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    texture.sampler = ImageSampler::nearest();
+
+    commands.insert_resource(PlaceholderAssets {
+        mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
+        material: materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.0, 1.0),
+            unlit: true,
+            ..default()
+        }),
+        texture: images.add(texture),
+        swapped_textures: Vec::new(),
+    });
+}
+
+fn is_missing_file(error: &AssetLoadError) -> bool {
+    matches!(error, AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_)))
+}
+
+fn missing_name(path: &str) -> Name {
+    Name::new(format!("Missing: {path}"))
+}
+
+fn handle_file<A: Asset>(handle: &Handle<A>) -> Option<&Path> {
+    handle.path().map(|path| path.path())
+}
+
+/// Swap in placeholders for files that failed to load because they are gone
+pub fn replace_missing_assets(
+    mut commands: Commands,
+    mut failures: EventReader<UntypedAssetLoadFailedEvent>,
+    placeholders: Option<ResMut<PlaceholderAssets>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scene_query: Query<(Entity, &Handle<Scene>, Option<&Name>), Without<MissingAsset>>,
+    mesh_query: Query<(Entity, &Handle<Mesh>, Option<&Name>), Without<MissingAsset>>,
+    material_query: Query<(Entity, &Handle<StandardMaterial>, Option<&Name>), Without<MissingAsset>>,
+) {
+    let Some(mut placeholders) = placeholders else {
+        failures.clear();
+        return;
+    };
+    for failure in failures.read() {
+        if !is_missing_file(&failure.error) {
+            continue;
+        }
+        let file = failure.path.path();
+        let path = file.to_string_lossy().replace('\\', "/");
+        warn!("Asset {} is missing; showing a placeholder", path);
+
+        for (entity, handle, name) in &scene_query {
+            if handle_file(handle) != Some(file) {
+                continue;
+            }
+            commands
+                .entity(entity)
+                .remove::<Handle<Scene>>()
+                .insert((
+                    placeholders.mesh.clone(),
+                    placeholders.material.clone(),
+                    missing_name(&path),
+                    MissingAsset {
+                        path: path.clone(),
+                        label: handle.path().and_then(|path| path.label()).map(str::to_string),
+                        kind: MissingAssetKind::Scene,
+                        original_name: name.map(|name| name.to_string()),
+                    },
+                ));
+        }
+
+        for (entity, handle, name) in &mesh_query {
+            if handle_file(handle) != Some(file) {
+                continue;
+            }
+            commands.entity(entity).insert((
+                placeholders.mesh.clone(),
+                missing_name(&path),
+                MissingAsset {
+                    path: path.clone(),
+                    label: handle.path().and_then(|path| path.label()).map(str::to_string),
+                    kind: MissingAssetKind::Mesh,
+                    original_name: name.map(|name| name.to_string()),
+                },
+            ));
+        }
+
+        let material_ids: Vec<AssetId<StandardMaterial>> = materials
+            .iter()
+            .filter(|(_, material)| {
+                material_textures(material)
+                    .into_iter()
+                    .flatten()
+                    .any(|texture| handle_file(texture) == Some(file))
+            })
+            .map(|(id, _)| id)
+            .collect();
+        for id in &material_ids {
+            let Some(material) = materials.get_mut(*id) else {
+                continue;
+            };
+            for (slot, texture) in material_textures_mut(material).into_iter().enumerate() {
+                let Some(handle) = texture.as_ref() else {
+                    continue;
+                };
+                if handle_file(handle) != Some(file) {
+                    continue;
+                }
+                let path = handle.path().cloned().unwrap_or_else(|| failure.path.clone());
+                placeholders.swapped_textures.push(SwappedTexture {
+                    material: *id,
+                    slot,
+                    path,
+                });
+                *texture = Some(placeholders.texture.clone());
+            }
+        }
+        for (entity, handle, name) in &material_query {
+            if !material_ids.contains(&handle.id()) {
+                continue;
+            }
+            commands.entity(entity).insert((
+                missing_name(&path),
+                MissingAsset {
+                    path: path.clone(),
+                    label: None,
+                    kind: MissingAssetKind::Texture,
+                    original_name: name.map(|name| name.to_string()),
+                },
+            ));
+        }
+    }
+}
+
+pub fn relink_missing_assets(
+    mut commands: Commands,
+    mut events: EventReader<LocateMissingAssetEvent>,
+    asset_server: Res<AssetServer>,
+    placeholders: Option<ResMut<PlaceholderAssets>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    missing_query: Query<(Entity, &MissingAsset)>,
+) {
+    let Some(mut placeholders) = placeholders else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        let replacement = |label: Option<&str>| {
+            let path = AssetPath::from(event.replacement.clone());
+            match label {
+                Some(label) => path.with_label(label.to_string()),
+                None => path,
+            }
+        };
+
+        for (entity, missing) in &missing_query {
+            if missing.path != event.missing {
+                continue;
+            }
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.remove::<MissingAsset>();
+            match &missing.original_name {
+                Some(name) => entity_commands.insert(Name::new(name.clone())),
+                None => entity_commands.remove::<Name>(),
+            };
+            match missing.kind {
+                MissingAssetKind::Scene => {
+                    let scene: Handle<Scene> = asset_server.load(replacement(missing.label.as_deref()));
+                    entity_commands
+                        .remove::<(Handle<Mesh>, Handle<StandardMaterial>)>()
+                        .insert(scene);
+                }
+                MissingAssetKind::Mesh => {
+                    let mesh: Handle<Mesh> = asset_server.load(replacement(missing.label.as_deref()));
+                    entity_commands.insert(mesh);
+                }
+                MissingAssetKind::Texture => {}
+            }
+        }
+
+        let missing_file = Path::new(&event.missing);
+        let (relinked, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut placeholders.swapped_textures)
+            .into_iter()
+            .partition(|swapped| swapped.path.path() == missing_file);
+        placeholders.swapped_textures = kept;
+        for swapped in relinked {
+            let Some(material) = materials.get_mut(swapped.material) else {
+                continue;
+            };
+            if let Some(texture) = material_textures_mut(material).into_iter().nth(swapped.slot) {
+                *texture = Some(asset_server.load(replacement(swapped.path.label())));
+            }
+        }
+        info!("Relinked {} to {}", event.missing, event.replacement);
+    }
+}
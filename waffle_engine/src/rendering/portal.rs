@@ -0,0 +1,443 @@
+//! Portal Module
+//! Portals and mirrors. A `Portal` is a quad facing its local +Z that shows
+//! the view out of its linked portal, or the reflection in its own plane for
+//! a mirror. Every recursion level has an off-axis camera whose near plane is
+//! the exit quad, so the texture lines up from any viewpoint and nothing
+//! behind the exit gets in the way. Level k surfaces live on render layer
+//! `portal_layer(k)`: scene cameras see level 0, level k cameras see level
+//! k + 1, and the last level shows the portal's fallback color. Portals
+//! render while editing as well, so the viewport doubles as their preview.
+
+use bevy::math::Vec3A;
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, RenderTarget};
+use bevy::render::render_resource::Extent3d;
+use bevy::render::view::RenderLayers;
+use bevy::transform::helper::TransformHelper;
+
+use crate::core::components::EditorHidden;
+use crate::rendering::camera::{create_viewport_image, CameraSettings};
+use crate::rendering::minimap::MinimapCamera;
+use crate::rendering::split_screen::LocalPlayers;
+
+pub const MAX_PORTAL_RECURSION: u32 = 4;
+
+/// First render layer used by portal surfaces, well clear of game layers
+pub const PORTAL_LAYER_BASE: usize = 16;
+
+/// Deeper levels render first so shallower ones can show them
+const PORTAL_CAMERA_ORDER: isize = -20;
+
+/// Closest the eye may get to the exit plane before the level is skipped
+const MIN_PORTAL_NEAR: f32 = 0.01;
+
+/// Render layer of the surfaces shown at recursion `level`
+pub fn portal_layer(level: usize) -> usize {
+    PORTAL_LAYER_BASE + level
+}
+
+#[derive(Component, Reflect, Clone, Debug)]
+pub struct Portal {
+    /// Portal this one looks out of; ignored by mirrors
+    pub linked: Option<Entity>,
+    /// Reflect the scene in this portal's plane instead
+    pub mirror: bool,
+    /// Width and height of the quad
+    pub size: Vec2,
+    /// Texture size of each recursion level
+    pub resolution: UVec2,
+    /// Portals seen through this one, up to `MAX_PORTAL_RECURSION`
+    pub recursion: u32,
+    /// Shown past the last recursion level
+    pub fallback: Color,
+}
+
+impl Default for Portal {
+    fn default() -> Self {
+        Self {
+            linked: None,
+            mirror: false,
+            size: Vec2::new(2.0, 3.0),
+            resolution: UVec2::new(512, 768),
+            recursion: 2,
+            fallback: Color::srgb(0.05, 0.05, 0.08),
+        }
+    }
+}
+
+impl Portal {
+    pub fn linked_to(entity: Entity) -> Self {
+        Self {
+            linked: Some(entity),
+            ..default()
+        }
+    }
+
+    pub fn mirror() -> Self {
+        Self {
+            mirror: true,
+            ..default()
+        }
+    }
+
+    /// Recursion levels that get a camera. A mirror's reflection of itself
+    /// is always behind it, so mirrors stop after one.
+    pub fn levels(&self) -> usize {
+        if self.mirror {
+            1
+        } else {
+            self.recursion.clamp(1, MAX_PORTAL_RECURSION) as usize
+        }
+    }
+}
+
+/// Cameras, textures and surfaces built for a `Portal`
+#[derive(Component)]
+pub struct PortalView {
+    /// Texture of each recursion level, level 0 first
+    pub images: Vec<Handle<Image>>,
+    cameras: Vec<Entity>,
+    surfaces: Vec<Entity>,
+    built: PortalBuild,
+}
+
+/// Settings the view was built with; changing any of them rebuilds it
+#[derive(Clone, PartialEq)]
+struct PortalBuild {
+    mirror: bool,
+    size: Vec2,
+    resolution: UVec2,
+    levels: usize,
+    fallback: Color,
+}
+
+impl From<&Portal> for PortalBuild {
+    fn from(portal: &Portal) -> Self {
+        Self {
+            mirror: portal.mirror,
+            size: portal.size,
+            resolution: portal.resolution,
+            levels: portal.levels(),
+            fallback: portal.fallback,
+        }
+    }
+}
+
+/// Camera rendering one recursion level of a portal
+#[derive(Component)]
+pub struct PortalCamera {
+    pub portal: Entity,
+    pub level: usize,
+}
+
+/// Off-axis perspective through the exit quad of a portal
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component, Default)]
+pub struct PortalProjection {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for PortalProjection {
+    fn default() -> Self {
+        Self {
+            left: -1.0,
+            right: 1.0,
+            bottom: -1.0,
+            top: 1.0,
+            near: 1.0,
+            far: 1000.0,
+        }
+    }
+}
+
+impl CameraProjection for PortalProjection {
+    fn get_clip_from_view(&self) -> Mat4 {
+        // Infinite reverse-Z, like bevy's own perspective
+        let width = self.right - self.left;
+        let height = self.top - self.bottom;
+        Mat4::from_cols(
+            Vec4::new(2.0 * self.near / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * self.near / height, 0.0, 0.0),
+            Vec4::new((self.right + self.left) / width, (self.top + self.bottom) / height, 0.0, -1.0),
+            Vec4::new(0.0, 0.0, self.near, 0.0),
+        )
+    }
+
+    /// The window comes from the exit quad, not the target size
+    fn update(&mut self, _width: f32, _height: f32) {}
+
+    fn far(&self) -> f32 {
+        self.far
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        let near = z_near.abs() / self.near;
+        let far = z_far.abs() / self.near;
+        // Same order as bevy's perspective projection
+        [
+            Vec3A::new(self.right * near, self.bottom * near, z_near),
+            Vec3A::new(self.right * near, self.top * near, z_near),
+            Vec3A::new(self.left * near, self.top * near, z_near),
+            Vec3A::new(self.left * near, self.bottom * near, z_near),
+            Vec3A::new(self.right * far, self.bottom * far, z_far),
+            Vec3A::new(self.right * far, self.top * far, z_far),
+            Vec3A::new(self.left * far, self.top * far, z_far),
+            Vec3A::new(self.left * far, self.bottom * far, z_far),
+        ]
+    }
+}
+
+/// Create or rebuild the cameras and surfaces of changed portals
+pub fn build_portal_views(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    portals: Query<(Entity, &Portal, Option<&PortalView>), Changed<Portal>>,
+    removed: Query<(Entity, &PortalView), Without<Portal>>,
+) {
+    for (entity, view) in &removed {
+        despawn_portal_view(&mut commands, view);
+        commands.entity(entity).remove::<PortalView>();
+    }
+
+    for (entity, portal, view) in &portals {
+        let build = PortalBuild::from(portal);
+        if let Some(view) = view {
+            if view.built == build {
+                continue;
+            }
+            despawn_portal_view(&mut commands, view);
+        }
+
+        let size = Extent3d {
+            width: build.resolution.x.max(1),
+            height: build.resolution.y.max(1),
+            ..default()
+        };
+        let mesh = meshes.add(Rectangle::from_size(build.size.max(Vec2::splat(0.01))));
+        let uv_transform = if build.mirror {
+            StandardMaterial::FLIP_HORIZONTAL
+        } else {
+            default()
+        };
+
+        let mut view = PortalView {
+            images: Vec::with_capacity(build.levels),
+            cameras: Vec::with_capacity(build.levels),
+            surfaces: Vec::with_capacity(build.levels + 1),
+            built: build.clone(),
+        };
+        for level in 0..=build.levels {
+            let image = (level < build.levels).then(|| images.add(create_viewport_image(size)));
+            let material = materials.add(StandardMaterial {
+                base_color: if image.is_some() { Color::WHITE } else { build.fallback },
+                base_color_texture: image.clone(),
+                uv_transform,
+                unlit: true,
+                ..default()
+            });
+            let surface = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material,
+                        ..default()
+                    },
+                    RenderLayers::layer(portal_layer(level)),
+                    NotShadowCaster,
+                    EditorHidden,
+                    Name::new(format!("Portal Surface {}", level)),
+                ))
+                .set_parent(entity)
+                .id();
+            view.surfaces.push(surface);
+
+            let Some(image) = image else {
+                continue;
+            };
+            let camera = commands
+                .spawn((
+                    Camera3dBundle {
+                        camera: Camera {
+                            target: RenderTarget::Image(image.clone()),
+                            order: PORTAL_CAMERA_ORDER - level as isize,
+                            is_active: false,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    RenderLayers::from_layers(&[0, portal_layer(level + 1)]),
+                    PortalCamera { portal: entity, level },
+                    EditorHidden,
+                    Name::new(format!("Portal Camera {}", level)),
+                ))
+                .remove::<Projection>()
+                .insert(PortalProjection::default())
+                .id();
+            view.cameras.push(camera);
+            view.images.push(image);
+        }
+        commands.entity(entity).insert(view);
+    }
+}
+
+fn despawn_portal_view(commands: &mut Commands, view: &PortalView) {
+    for entity in view.cameras.iter().chain(&view.surfaces) {
+        if let Some(entity) = commands.get_entity(*entity) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+/// Cameras outlive despawned portals since they aren't children of them
+pub fn despawn_orphaned_portal_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &PortalCamera)>,
+    views: Query<&PortalView>,
+) {
+    for (entity, camera) in &cameras {
+        let owned = views
+            .get(camera.portal)
+            .is_ok_and(|view| view.cameras.contains(&entity));
+        if !owned {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Let scene cameras see the first level of every portal
+pub fn show_portals_to_scene_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, Option<&RenderLayers>), (With<Camera3d>, Without<PortalCamera>, Without<MinimapCamera>)>,
+) {
+    let portal_layers = RenderLayers::layer(portal_layer(0));
+    for (entity, layers) in &cameras {
+        let layers = layers.cloned().unwrap_or_default();
+        if !layers.intersects(&portal_layers) {
+            commands.entity(entity).insert(layers.union(&portal_layers));
+        }
+    }
+}
+
+/// Place each level's camera at the viewer's eye carried through the portal
+/// that many times, with its window on the exit quad
+pub fn update_portal_cameras(
+    camera_settings: Res<CameraSettings>,
+    players: Res<LocalPlayers>,
+    helper: TransformHelper,
+    portals: Query<(Entity, &Portal, &PortalView)>,
+    mut cameras: Query<(&mut Camera, &mut Transform, &mut PortalProjection), With<PortalCamera>>,
+    active_cameras: Query<&Camera, Without<PortalCamera>>,
+) {
+    // Split screen views the portals from the first player
+    let viewer = players
+        .players()
+        .first()
+        .map(|player| player.camera)
+        .filter(|camera| active_cameras.get(*camera).is_ok_and(|camera| camera.is_active))
+        .or(camera_settings.active_camera_entity)
+        .or(camera_settings.main_camera_entity);
+    let viewer_eye = viewer.and_then(|viewer| helper.compute_global_transform(viewer).ok());
+
+    for (entity, portal, view) in &portals {
+        let source = helper.compute_global_transform(entity).ok().map(|global| global.compute_transform());
+        let exit = if portal.mirror {
+            source
+        } else {
+            portal
+                .linked
+                .and_then(|linked| helper.compute_global_transform(linked).ok())
+                .map(|global| global.compute_transform())
+        };
+        // A linked portal's own quad size is the window on the far side
+        let exit_size = portal
+            .linked
+            .filter(|_| !portal.mirror)
+            .and_then(|linked| portals.get(linked).ok())
+            .map_or(portal.size, |(_, linked, _)| linked.size);
+
+        let mut eye = viewer_eye.map(|eye| eye.translation());
+        for camera_entity in &view.cameras {
+            let Ok((mut camera, mut transform, mut projection)) = cameras.get_mut(*camera_entity) else {
+                continue;
+            };
+            let placed = match (source, exit, eye) {
+                (Some(source), Some(exit), Some(current)) => {
+                    let next = if portal.mirror {
+                        reflect_through(&source, current)
+                    } else {
+                        carry_through(&source, &exit, current)
+                    };
+                    eye = Some(next);
+                    place_portal_camera(&exit, exit_size, next, &mut transform, &mut projection)
+                }
+                _ => false,
+            };
+            if camera.is_active != placed {
+                camera.is_active = placed;
+            }
+            if !placed {
+                // Deeper levels can't be seen either
+                eye = None;
+            }
+        }
+    }
+}
+
+/// Where `point` in front of `source` ends up behind `exit`
+fn carry_through(source: &Transform, exit: &Transform, point: Vec3) -> Vec3 {
+    let local = source.rotation.inverse() * (point - source.translation);
+    let turned = Quat::from_rotation_y(std::f32::consts::PI) * local;
+    exit.translation + exit.rotation * turned
+}
+
+/// `point` mirrored in the plane of `mirror`
+fn reflect_through(mirror: &Transform, point: Vec3) -> Vec3 {
+    let mut local = mirror.rotation.inverse() * (point - mirror.translation);
+    local.z = -local.z;
+    mirror.translation + mirror.rotation * local
+}
+
+/// Put the camera at `eye`, looking out of `exit` with the quad as its near
+/// plane. False when the eye isn't behind the quad.
+fn place_portal_camera(
+    exit: &Transform,
+    size: Vec2,
+    eye: Vec3,
+    transform: &mut Transform,
+    projection: &mut PortalProjection,
+) -> bool {
+    let local = exit.rotation.inverse() * (eye - exit.translation);
+    let near = -local.z;
+    if near < MIN_PORTAL_NEAR {
+        return false;
+    }
+    let half = size * exit.scale.truncate() * 0.5;
+    // The camera faces out of the quad, so its right is the quad's left
+    let window = PortalProjection {
+        left: local.x - half.x,
+        right: local.x + half.x,
+        bottom: -half.y - local.y,
+        top: half.y - local.y,
+        near,
+        far: projection.far,
+    };
+    if window.left != projection.left
+        || window.right != projection.right
+        || window.bottom != projection.bottom
+        || window.top != projection.top
+        || window.near != projection.near
+    {
+        *projection = window;
+    }
+    *transform = Transform::from_translation(eye)
+        .with_rotation(exit.rotation * Quat::from_rotation_y(std::f32::consts::PI));
+    true
+}
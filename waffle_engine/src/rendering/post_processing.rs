@@ -1,5 +1,5 @@
-/// Post Processing Module
-/// Handles post-processing effects like bloom, SSAO, depth of field, etc.
+//! Post Processing Module
+//! Handles post-processing effects like bloom, SSAO, depth of field, etc.
 
 use bevy::prelude::*;
 
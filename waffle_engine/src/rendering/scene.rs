@@ -1,6 +1,7 @@
-/// 3D Scene Module
-/// Handles 3D scene setup, management, and rendering
+//! 3D Scene Module
+//! Handles 3D scene setup, management, and rendering
 
+use bevy::core_pipeline::auto_exposure::AutoExposureSettings;
 use bevy::core_pipeline::bloom::{BloomCompositeMode, BloomPrefilterSettings, BloomSettings};
 use bevy::core_pipeline::prepass::{DeferredPrepass, DepthPrepass, NormalPrepass};
 use bevy::core_pipeline::tonemapping::Tonemapping;
@@ -13,8 +14,13 @@ use bevy::pbr::{
 use bevy::render::camera::Exposure;
 use bevy::render::mesh::VertexAttributeValues;
 use bevy::render::view::{ColorGrading, ColorGradingGlobal, ColorGradingSection};
+use serde::{Deserialize, Serialize};
 use crate::rendering::camera::WaffleMainCamera;
+use crate::rendering::environment::{ActiveEnvironment, SceneEnvironment};
+use crate::rendering::sun::{GeoSunLocation, solar_position};
 use crate::core::components::EditorHidden;
+use crate::core::project::ProjectSettings;
+use crate::rendering::color::{OutputTransform, WorkingColorSpace};
 
 #[derive(Component)]
 pub struct WaffleSceneRoot;
@@ -25,21 +31,31 @@ pub struct WaffleSceneObject;
 #[derive(Component)]
 pub struct WaffleSkyDome;
 
-#[derive(Component, Clone)]
+/// Lighting, sky and post-processing of a scene. Bound to a scene root through
+/// `SceneEnvironment`; on a camera it overrides that camera's post-processing.
+#[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EnvironmentSettings {
     pub ambient_color: Color,
     pub ambient_intensity: f32,
     pub sun_color: Color,
     pub sun_intensity: f32,
+    /// Ignored when `geo_sun` is set
     pub sun_azimuth: f32,
+    /// Local clock time in hours
     pub time_of_day: f32,
+    /// Astronomical sun for a real place and date instead of the simple
+    /// sunrise-at-6 sun path
+    pub geo_sun: Option<GeoSunLocation>,
     pub sky_top_day: Color,
     pub sky_horizon_day: Color,
     pub sky_top_night: Color,
     pub sky_horizon_night: Color,
     pub sun_disk_intensity: f32,
     pub sun_disk_size: f32,
+    /// Ignored while `auto_exposure` is enabled
     pub exposure_ev100: f32,
+    pub auto_exposure: EnvironmentAutoExposureSettings,
     pub tonemapping: EnvironmentTonemapping,
     pub color_grading: EnvironmentColorGrading,
     pub bloom: EnvironmentBloomSettings,
@@ -48,7 +64,142 @@ pub struct EnvironmentSettings {
     pub ssr: EnvironmentSsrSettings,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            ambient_color: Color::srgb(0.85, 0.9, 1.0),
+            ambient_intensity: 200.0,
+            sun_color: Color::srgb(1.0, 0.98, 0.92),
+            sun_intensity: 12000.0,
+            sun_azimuth: 35.0,
+            time_of_day: 10.5,
+            geo_sun: None,
+            sky_top_day: Color::srgb(0.26, 0.49, 0.93),
+            sky_horizon_day: Color::srgb(0.85, 0.93, 1.0),
+            sky_top_night: Color::srgb(0.02, 0.04, 0.08),
+            sky_horizon_night: Color::srgb(0.12, 0.14, 0.2),
+            sun_disk_intensity: 3.5,
+            sun_disk_size: 0.025,
+            exposure_ev100: Exposure::EV100_BLENDER,
+            auto_exposure: EnvironmentAutoExposureSettings {
+                enabled: false,
+                min_ev100: 2.0,
+                max_ev100: 16.0,
+                speed_brighten: 3.0,
+                speed_darken: 1.0,
+            },
+            tonemapping: EnvironmentTonemapping::AcesFitted,
+            color_grading: EnvironmentColorGrading {
+                gamma: 1.0,
+                pre_saturation: 1.0,
+                post_saturation: 1.0,
+            },
+            bloom: EnvironmentBloomSettings {
+                enabled: true,
+                intensity: 0.2,
+                low_frequency_boost: 0.7,
+                threshold: 0.8,
+            },
+            fog: EnvironmentFogSettings {
+                enabled: false,
+                color: Color::srgb(0.6, 0.7, 0.8),
+                mode: EnvironmentFogMode::Linear,
+                start: 20.0,
+                end: 60.0,
+                density: 0.02,
+            },
+            ssao: EnvironmentSsaoSettings {
+                enabled: true,
+                quality: EnvironmentSsaoQuality::High,
+            },
+            ssr: EnvironmentSsrSettings {
+                enabled: false,
+                roughness_threshold: 0.8,
+                thickness: 0.2,
+                linear_steps: 32,
+                bisection_steps: 8,
+                use_secant: true,
+            },
+        }
+    }
+}
+
+impl EnvironmentSettings {
+    /// World-space direction towards the sun at `time_of_day`
+    pub fn sun_direction(&self) -> Vec3 {
+        if let Some(location) = self.geo_sun.as_ref() {
+            return solar_position(location, self.time_of_day).direction(location.north_heading);
+        }
+        let time = self.time_of_day.rem_euclid(24.0);
+        let elevation = (time / 24.0) * std::f32::consts::TAU - std::f32::consts::FRAC_PI_2;
+        let azimuth = self.sun_azimuth.to_radians();
+        let (sin_e, cos_e) = elevation.sin_cos();
+        let (sin_a, cos_a) = azimuth.sin_cos();
+        Vec3::new(cos_a * cos_e, sin_e, sin_a * cos_e).normalize_or_zero()
+    }
+
+    /// Blend towards `other`; options that cannot blend switch halfway
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let mix_color = |a: Color, b: Color| Color::from(a.to_linear().mix(&b.to_linear(), t));
+        // Go the short way around midnight
+        let hours = (other.time_of_day - self.time_of_day + 12.0).rem_euclid(24.0) - 12.0;
+        Self {
+            ambient_color: mix_color(self.ambient_color, other.ambient_color),
+            ambient_intensity: mix(self.ambient_intensity, other.ambient_intensity),
+            sun_color: mix_color(self.sun_color, other.sun_color),
+            sun_intensity: mix(self.sun_intensity, other.sun_intensity),
+            sun_azimuth: mix(self.sun_azimuth, other.sun_azimuth),
+            time_of_day: (self.time_of_day + hours * t).rem_euclid(24.0),
+            geo_sun: switch_halfway(self.geo_sun, other.geo_sun, t),
+            sky_top_day: mix_color(self.sky_top_day, other.sky_top_day),
+            sky_horizon_day: mix_color(self.sky_horizon_day, other.sky_horizon_day),
+            sky_top_night: mix_color(self.sky_top_night, other.sky_top_night),
+            sky_horizon_night: mix_color(self.sky_horizon_night, other.sky_horizon_night),
+            sun_disk_intensity: mix(self.sun_disk_intensity, other.sun_disk_intensity),
+            sun_disk_size: mix(self.sun_disk_size, other.sun_disk_size),
+            exposure_ev100: mix(self.exposure_ev100, other.exposure_ev100),
+            auto_exposure: EnvironmentAutoExposureSettings {
+                enabled: switch_halfway(self.auto_exposure.enabled, other.auto_exposure.enabled, t),
+                min_ev100: mix(self.auto_exposure.min_ev100, other.auto_exposure.min_ev100),
+                max_ev100: mix(self.auto_exposure.max_ev100, other.auto_exposure.max_ev100),
+                speed_brighten: mix(self.auto_exposure.speed_brighten, other.auto_exposure.speed_brighten),
+                speed_darken: mix(self.auto_exposure.speed_darken, other.auto_exposure.speed_darken),
+            },
+            tonemapping: switch_halfway(self.tonemapping, other.tonemapping, t),
+            color_grading: EnvironmentColorGrading {
+                gamma: mix(self.color_grading.gamma, other.color_grading.gamma),
+                pre_saturation: mix(self.color_grading.pre_saturation, other.color_grading.pre_saturation),
+                post_saturation: mix(self.color_grading.post_saturation, other.color_grading.post_saturation),
+            },
+            bloom: EnvironmentBloomSettings {
+                enabled: self.bloom.enabled || other.bloom.enabled,
+                intensity: mix(
+                    if self.bloom.enabled { self.bloom.intensity } else { 0.0 },
+                    if other.bloom.enabled { other.bloom.intensity } else { 0.0 },
+                ),
+                low_frequency_boost: mix(self.bloom.low_frequency_boost, other.bloom.low_frequency_boost),
+                threshold: mix(self.bloom.threshold, other.bloom.threshold),
+            },
+            fog: EnvironmentFogSettings {
+                enabled: switch_halfway(self.fog.enabled, other.fog.enabled, t),
+                color: mix_color(self.fog.color, other.fog.color),
+                mode: switch_halfway(self.fog.mode, other.fog.mode, t),
+                start: mix(self.fog.start, other.fog.start),
+                end: mix(self.fog.end, other.fog.end),
+                density: mix(self.fog.density, other.fog.density),
+            },
+            ssao: switch_halfway(self.ssao, other.ssao, t),
+            ssr: switch_halfway(self.ssr, other.ssr, t),
+        }
+    }
+}
+
+fn switch_halfway<T>(a: T, b: T, t: f32) -> T {
+    if t < 0.5 { a } else { b }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum EnvironmentTonemapping {
     None,
     Reinhard,
@@ -59,14 +210,14 @@ pub enum EnvironmentTonemapping {
     BlenderFilmic,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentColorGrading {
     pub gamma: f32,
     pub pre_saturation: f32,
     pub post_saturation: f32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentBloomSettings {
     pub enabled: bool,
     pub intensity: f32,
@@ -74,13 +225,26 @@ pub struct EnvironmentBloomSettings {
     pub threshold: f32,
 }
 
-#[derive(Clone, Copy)]
+/// Eye adaptation: the camera meters the average scene luminance and eases
+/// its exposure towards it, within the EV100 bounds
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentAutoExposureSettings {
+    pub enabled: bool,
+    pub min_ev100: f32,
+    pub max_ev100: f32,
+    /// Stops per second when adapting to a brighter scene
+    pub speed_brighten: f32,
+    /// Stops per second when adapting to a darker scene
+    pub speed_darken: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentSsaoSettings {
     pub enabled: bool,
     pub quality: EnvironmentSsaoQuality,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum EnvironmentSsaoQuality {
     Low,
     Medium,
@@ -88,7 +252,7 @@ pub enum EnvironmentSsaoQuality {
     Ultra,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentSsrSettings {
     pub enabled: bool,
     pub roughness_threshold: f32,
@@ -98,7 +262,7 @@ pub struct EnvironmentSsrSettings {
     pub use_secant: bool,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum EnvironmentFogMode {
     Linear,
     Exponential,
@@ -106,7 +270,7 @@ pub enum EnvironmentFogMode {
     Atmospheric,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentFogSettings {
     pub enabled: bool,
     pub color: Color,
@@ -119,7 +283,8 @@ pub struct EnvironmentFogSettings {
 #[derive(Resource, Clone, Copy)]
 pub struct SceneRootEntity(pub Entity);
 
-#[derive(Resource, Default)]
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
 pub struct SceneSettings {
     pub ambient_light_color: Color,
     pub ambient_light_intensity: f32,
@@ -176,59 +341,14 @@ pub fn setup_3d_scene(
         },
     )).set_parent(root);
 
-    commands.spawn((
+    let environment = commands.spawn((
         WaffleSceneObject,
         Name::new("Environment"),
-        EnvironmentSettings {
-            ambient_color: Color::srgb(0.85, 0.9, 1.0),
-            ambient_intensity: 200.0,
-            sun_color: Color::srgb(1.0, 0.98, 0.92),
-            sun_intensity: 12000.0,
-            sun_azimuth: 35.0,
-            time_of_day: 10.5,
-            sky_top_day: Color::srgb(0.26, 0.49, 0.93),
-            sky_horizon_day: Color::srgb(0.85, 0.93, 1.0),
-            sky_top_night: Color::srgb(0.02, 0.04, 0.08),
-            sky_horizon_night: Color::srgb(0.12, 0.14, 0.2),
-            sun_disk_intensity: 3.5,
-            sun_disk_size: 0.025,
-            exposure_ev100: Exposure::EV100_BLENDER,
-            tonemapping: EnvironmentTonemapping::AcesFitted,
-            color_grading: EnvironmentColorGrading {
-                gamma: 1.0,
-                pre_saturation: 1.0,
-                post_saturation: 1.0,
-            },
-            bloom: EnvironmentBloomSettings {
-                enabled: true,
-                intensity: 0.2,
-                low_frequency_boost: 0.7,
-                threshold: 0.8,
-            },
-            fog: EnvironmentFogSettings {
-                enabled: false,
-                color: Color::srgb(0.6, 0.7, 0.8),
-                mode: EnvironmentFogMode::Linear,
-                start: 20.0,
-                end: 60.0,
-                density: 0.02,
-            },
-            ssao: EnvironmentSsaoSettings {
-                enabled: true,
-                quality: EnvironmentSsaoQuality::High,
-            },
-            ssr: EnvironmentSsrSettings {
-                enabled: false,
-                roughness_threshold: 0.8,
-                thickness: 0.2,
-                linear_steps: 32,
-                bisection_steps: 8,
-                use_secant: true,
-            },
-        },
+        EnvironmentSettings::default(),
         Transform::default(),
         GlobalTransform::default(),
-    )).set_parent(root);
+    )).set_parent(root).id();
+    commands.entity(root).insert(SceneEnvironment(environment));
 
     let sky_mesh = Mesh::from(Sphere::new(1.0).mesh().uv(48, 24));
     let sky_mesh_handle = meshes.add(sky_mesh);
@@ -257,23 +377,70 @@ pub fn setup_3d_scene(
 pub fn update_3d_scene() {
 }
 
+/// Apply the active scene environment, or a camera's own `EnvironmentSettings`
+/// override, to the cameras. Ambient light always follows the scene.
 pub fn apply_environment_settings(
     mut commands: Commands,
-    env_query: Query<&EnvironmentSettings, Or<(Added<EnvironmentSettings>, Changed<EnvironmentSettings>)>>,
-    camera_query: Query<Entity, With<WaffleMainCamera>>,
+    active: Res<ActiveEnvironment>,
+    main_camera_query: Query<(Entity, Option<Ref<EnvironmentSettings>>), With<WaffleMainCamera>>,
+    override_query: Query<(Entity, Ref<EnvironmentSettings>), (With<Camera>, Without<WaffleMainCamera>)>,
+    mut removed_overrides: RemovedComponents<EnvironmentSettings>,
+    project_settings: Res<ProjectSettings>,
+    mut last_transform: Local<Option<OutputTransform>>,
     mut ambient_light: ResMut<AmbientLight>,
     mut default_opaque_method: ResMut<DefaultOpaqueRendererMethod>,
     mut msaa: ResMut<Msaa>,
 ) {
-    let Ok(camera_entity) = camera_query.get_single() else {
-        return;
-    };
+    let removed: Vec<Entity> = removed_overrides.read().collect();
+    let output_transform = project_settings.color_management.output_transform;
+    let transform_changed = *last_transform != Some(output_transform);
+    *last_transform = Some(output_transform);
+
+    if active.is_changed() {
+        if let Some(env) = active.settings.as_ref() {
+            ambient_light.color = env.ambient_color;
+            ambient_light.brightness = env.ambient_intensity;
+        }
+    }
 
-    let Some(env) = env_query.iter().next() else {
-        return;
-    };
+    for (camera, camera_env) in &main_camera_query {
+        let changed = transform_changed
+            || match camera_env.as_ref() {
+                Some(camera_env) => camera_env.is_changed(),
+                None => active.is_changed() || removed.contains(&camera),
+            };
+        if !changed {
+            continue;
+        }
+        let Some(env) = camera_env.as_deref().or(active.settings.as_ref()) else {
+            continue;
+        };
+        apply_camera_environment(&mut commands, camera, env, output_transform);
+
+        // The deferred renderer and MSAA are global, so the main camera decides
+        if env.ssr.enabled {
+            default_opaque_method.set_to_deferred();
+            *msaa = Msaa::Off;
+        } else {
+            default_opaque_method.set_to_forward();
+            *msaa = Msaa::default();
+        }
+    }
 
-    let tonemapping = match env.tonemapping {
+    for (camera, camera_env) in &override_query {
+        if camera_env.is_changed() || transform_changed {
+            apply_camera_environment(&mut commands, camera, &camera_env, output_transform);
+        }
+    }
+}
+
+fn apply_camera_environment(
+    commands: &mut Commands,
+    camera: Entity,
+    env: &EnvironmentSettings,
+    output_transform: OutputTransform,
+) {
+    let environment_tonemapping = match env.tonemapping {
         EnvironmentTonemapping::None => Tonemapping::None,
         EnvironmentTonemapping::Reinhard => Tonemapping::Reinhard,
         EnvironmentTonemapping::AcesFitted => Tonemapping::AcesFitted,
@@ -284,6 +451,7 @@ pub fn apply_environment_settings(
         EnvironmentTonemapping::TonyMcMapface => Tonemapping::TonyMcMapface,
         EnvironmentTonemapping::BlenderFilmic => Tonemapping::BlenderFilmic,
     };
+    let tonemapping = output_transform.tonemapping().unwrap_or(environment_tonemapping);
     let global = ColorGradingGlobal {
         post_saturation: env.color_grading.post_saturation,
         ..Default::default()
@@ -294,18 +462,33 @@ pub fn apply_environment_settings(
         ..Default::default()
     };
     let color_grading = ColorGrading::with_identical_sections(global, section);
+    // Metering corrects the exposure on top of the camera's, so with auto
+    // exposure the camera sits at EV100 0 and the metered value is the EV100
     let exposure = Exposure {
-        ev100: env.exposure_ev100,
+        ev100: if env.auto_exposure.enabled { 0.0 } else { env.exposure_ev100 },
     };
 
-    commands.entity(camera_entity).insert((
+    commands.entity(camera).insert((
         tonemapping,
         color_grading,
         exposure,
     ));
 
+    if env.auto_exposure.enabled {
+        let min = env.auto_exposure.min_ev100.min(env.auto_exposure.max_ev100);
+        let max = env.auto_exposure.max_ev100.max(min + 0.1);
+        commands.entity(camera).insert(AutoExposureSettings {
+            range: min..=max,
+            speed_brighten: env.auto_exposure.speed_brighten,
+            speed_darken: env.auto_exposure.speed_darken,
+            ..default()
+        });
+    } else {
+        commands.entity(camera).remove::<AutoExposureSettings>();
+    }
+
     if env.bloom.enabled {
-        commands.entity(camera_entity).insert(BloomSettings {
+        commands.entity(camera).insert(BloomSettings {
             intensity: env.bloom.intensity,
             low_frequency_boost: env.bloom.low_frequency_boost,
             low_frequency_boost_curvature: 0.95,
@@ -317,34 +500,13 @@ pub fn apply_environment_settings(
             composite_mode: BloomCompositeMode::EnergyConserving,
         });
     } else {
-        commands.entity(camera_entity).remove::<BloomSettings>();
+        commands.entity(camera).remove::<BloomSettings>();
     }
 
     if env.fog.enabled {
-        let falloff = match env.fog.mode {
-            EnvironmentFogMode::Linear => FogFalloff::Linear {
-                start: env.fog.start,
-                end: env.fog.end,
-            },
-            EnvironmentFogMode::Exponential => FogFalloff::Exponential {
-                density: env.fog.density,
-            },
-            EnvironmentFogMode::ExponentialSquared => FogFalloff::ExponentialSquared {
-                density: env.fog.density,
-            },
-            EnvironmentFogMode::Atmospheric => FogFalloff::Atmospheric {
-                extinction: Vec3::splat(env.fog.density),
-                inscattering: Vec3::splat(env.fog.density * 0.4),
-            },
-        };
-        commands.entity(camera_entity).insert(FogSettings {
-            color: env.fog.color,
-            directional_light_color: Color::NONE,
-            directional_light_exponent: 0.0,
-            falloff,
-        });
+        commands.entity(camera).insert(camera_fog(&env.fog));
     } else {
-        commands.entity(camera_entity).remove::<FogSettings>();
+        commands.entity(camera).remove::<FogSettings>();
     }
 
     if env.ssao.enabled {
@@ -354,42 +516,63 @@ pub fn apply_environment_settings(
             EnvironmentSsaoQuality::High => ScreenSpaceAmbientOcclusionQualityLevel::High,
             EnvironmentSsaoQuality::Ultra => ScreenSpaceAmbientOcclusionQualityLevel::Ultra,
         };
-        commands.entity(camera_entity).insert(ScreenSpaceAmbientOcclusionSettings {
+        commands.entity(camera).insert(ScreenSpaceAmbientOcclusionSettings {
             quality_level: quality,
         });
     } else {
         commands
-            .entity(camera_entity)
+            .entity(camera)
             .remove::<ScreenSpaceAmbientOcclusionSettings>();
     }
 
     if env.ssr.enabled {
-        default_opaque_method.set_to_deferred();
-        *msaa = Msaa::Off;
-        commands.entity(camera_entity).insert(ScreenSpaceReflectionsSettings {
-            perceptual_roughness_threshold: env.ssr.roughness_threshold,
-            thickness: env.ssr.thickness,
-            linear_steps: env.ssr.linear_steps.max(1),
-            linear_march_exponent: 1.0,
-            bisection_steps: env.ssr.bisection_steps,
-            use_secant: env.ssr.use_secant,
-        });
-        commands.entity(camera_entity).insert(DepthPrepass);
-        commands.entity(camera_entity).insert(NormalPrepass);
-        commands.entity(camera_entity).insert(DeferredPrepass);
+        commands.entity(camera).insert((
+            ScreenSpaceReflectionsSettings {
+                perceptual_roughness_threshold: env.ssr.roughness_threshold,
+                thickness: env.ssr.thickness,
+                linear_steps: env.ssr.linear_steps.max(1),
+                linear_march_exponent: 1.0,
+                bisection_steps: env.ssr.bisection_steps,
+                use_secant: env.ssr.use_secant,
+            },
+            DepthPrepass,
+            NormalPrepass,
+            DeferredPrepass,
+        ));
     } else {
-        default_opaque_method.set_to_forward();
-        commands
-            .entity(camera_entity)
-            .remove::<ScreenSpaceReflectionsSettings>();
-        *msaa = Msaa::default();
-        commands.entity(camera_entity).remove::<DepthPrepass>();
-        commands.entity(camera_entity).remove::<NormalPrepass>();
-        commands.entity(camera_entity).remove::<DeferredPrepass>();
+        commands.entity(camera).remove::<(
+            ScreenSpaceReflectionsSettings,
+            DepthPrepass,
+            NormalPrepass,
+            DeferredPrepass,
+        )>();
     }
+}
 
-    ambient_light.color = env.ambient_color;
-    ambient_light.brightness = env.ambient_intensity;
+/// The camera fog for an environment's fog settings
+pub fn camera_fog(fog: &EnvironmentFogSettings) -> FogSettings {
+    let falloff = match fog.mode {
+        EnvironmentFogMode::Linear => FogFalloff::Linear {
+            start: fog.start,
+            end: fog.end,
+        },
+        EnvironmentFogMode::Exponential => FogFalloff::Exponential {
+            density: fog.density,
+        },
+        EnvironmentFogMode::ExponentialSquared => FogFalloff::ExponentialSquared {
+            density: fog.density,
+        },
+        EnvironmentFogMode::Atmospheric => FogFalloff::Atmospheric {
+            extinction: Vec3::splat(fog.density),
+            inscattering: Vec3::splat(fog.density * 0.4),
+        },
+    };
+    FogSettings {
+        color: fog.color,
+        directional_light_color: Color::NONE,
+        directional_light_exponent: 0.0,
+        falloff,
+    }
 }
 
 pub fn ensure_scene_root_parenting(
@@ -407,12 +590,27 @@ pub fn ensure_scene_root_parenting(
     }
 }
 
+/// Auto exposure meters the HDR target, so cameras using it render in HDR
+pub fn sync_auto_exposure_hdr(mut camera_query: Query<(&mut Camera, Has<AutoExposureSettings>)>) {
+    for (mut camera, auto_exposure) in &mut camera_query {
+        if camera.hdr != auto_exposure {
+            camera.hdr = auto_exposure;
+        }
+    }
+}
+
 pub fn update_sky_dome(
-    env_query: Query<&EnvironmentSettings, Or<(Added<EnvironmentSettings>, Changed<EnvironmentSettings>)>>,
+    active: Res<ActiveEnvironment>,
+    project_settings: Res<ProjectSettings>,
+    mut last_space: Local<Option<WorkingColorSpace>>,
     mut meshes: ResMut<Assets<Mesh>>,
     sky_query: Query<&Handle<Mesh>, With<WaffleSkyDome>>,
 ) {
-    let Some(env) = env_query.iter().next() else {
+    let space = project_settings.color_management.working_space;
+    if !active.is_changed() && *last_space == Some(space) {
+        return;
+    }
+    let Some(env) = active.settings.as_ref() else {
         return;
     };
     let Ok(mesh_handle) = sky_query.get_single() else {
@@ -421,7 +619,8 @@ pub fn update_sky_dome(
     let Some(mesh) = meshes.get_mut(mesh_handle) else {
         return;
     };
-    apply_sky_gradient(mesh, env);
+    apply_sky_gradient(mesh, env, space);
+    *last_space = Some(space);
 }
 
 pub fn sync_sky_dome_to_camera(
@@ -436,74 +635,39 @@ pub fn sync_sky_dome_to_camera(
     }
 }
 
-fn apply_sky_gradient(mesh: &mut Mesh, env: &EnvironmentSettings) {
+/// Blends in the project's working space
+fn apply_sky_gradient(mesh: &mut Mesh, env: &EnvironmentSettings, space: WorkingColorSpace) {
     let Some(VertexAttributeValues::Float32x3(positions)) =
         mesh.attribute(Mesh::ATTRIBUTE_POSITION)
     else {
         return;
     };
 
-    let sun_dir = sun_direction(env);
-    let day_factor = sun_day_factor(env.time_of_day);
+    let sun_dir = env.sun_direction();
+    let day_factor = (sun_dir.y * 0.5 + 0.5).clamp(0.0, 1.0);
 
-    let sky_top = lerp_color(env.sky_top_night, env.sky_top_day, day_factor);
-    let sky_horizon = lerp_color(env.sky_horizon_night, env.sky_horizon_day, day_factor);
-    let sun_color = env.sun_color.to_linear().to_f32_array();
+    let sky_top = space.mix(env.sky_top_night, env.sky_top_day, day_factor);
+    let sky_horizon = space.mix(env.sky_horizon_night, env.sky_horizon_day, day_factor);
+    let sun_color = space.to_working(env.sun_color);
 
     let mut colors = Vec::with_capacity(positions.len());
     for position in positions {
         let dir = Vec3::from(*position).normalize_or_zero();
         let height = dir.y.clamp(-1.0, 1.0);
         let horizon_t = smoothstep(0.0, 0.9, (height + 1.0) * 0.5);
-        let mut color = lerp_linear(sky_horizon, sky_top, horizon_t);
+        let mut color = sky_horizon.lerp(sky_top, horizon_t);
 
         let sun_dot = dir.dot(sun_dir).max(0.0);
         let sharpness = (1.0 / env.sun_disk_size.max(0.001)).clamp(1.0, 256.0);
         let sun = sun_dot.powf(sharpness) * env.sun_disk_intensity;
-        color[0] += sun_color[0] * sun;
-        color[1] += sun_color[1] * sun;
-        color[2] += sun_color[2] * sun;
-        color[3] = 1.0;
+        color += sun_color * sun;
 
-        colors.push(color);
+        colors.push(space.to_render(color, 1.0).to_linear().to_f32_array());
     }
 
     mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
 }
 
-fn sun_direction(env: &EnvironmentSettings) -> Vec3 {
-    let elevation = time_to_elevation(env.time_of_day);
-    let azimuth = env.sun_azimuth.to_radians();
-    let (sin_e, cos_e) = elevation.sin_cos();
-    let (sin_a, cos_a) = azimuth.sin_cos();
-    Vec3::new(cos_a * cos_e, sin_e, sin_a * cos_e).normalize_or_zero()
-}
-
-fn time_to_elevation(time_of_day: f32) -> f32 {
-    let clamped = time_of_day.rem_euclid(24.0);
-    (clamped / 24.0) * std::f32::consts::TAU - std::f32::consts::FRAC_PI_2
-}
-
-fn sun_day_factor(time_of_day: f32) -> f32 {
-    let elevation = time_to_elevation(time_of_day);
-    (elevation.sin().clamp(-1.0, 1.0) * 0.5 + 0.5).clamp(0.0, 1.0)
-}
-
-fn lerp_color(a: Color, b: Color, t: f32) -> [f32; 4] {
-    let a = a.to_linear().to_f32_array();
-    let b = b.to_linear().to_f32_array();
-    lerp_linear(a, b, t)
-}
-
-fn lerp_linear(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
-    [
-        a[0] + (b[0] - a[0]) * t,
-        a[1] + (b[1] - a[1]) * t,
-        a[2] + (b[2] - a[2]) * t,
-        a[3] + (b[3] - a[3]) * t,
-    ]
-}
-
 fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
     t * t * (3.0 - 2.0 * t)
@@ -1,9 +1,10 @@
-/// Shadows Module
-/// Handles shadow rendering and configuration
+//! Shadows Module
+//! Handles shadow rendering and configuration
 
 use bevy::prelude::*;
 
-#[derive(Resource)]
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct ShadowSettings {
     pub shadows_enabled: bool,
     pub shadow_quality: ShadowQuality,
@@ -26,7 +27,7 @@ impl Default for ShadowSettings {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Reflect)]
 pub enum ShadowQuality {
     Low,
     Medium,
@@ -0,0 +1,267 @@
+//! Split Screen Module
+//! Local multiplayer for two to four players sharing one screen. Each player
+//! in `LocalPlayers` owns a gameplay camera, which renders into its own
+//! region of the main camera's target, and an input device. Spatial sounds
+//! are heard from the nearest player's camera, since bevy mixes a single
+//! listener. Games add and remove players at runtime through `LocalPlayers`.
+
+use bevy::audio::{DefaultSpatialScale, SpatialAudioSink, SpatialListener};
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+
+use crate::core::play::PlayState;
+use crate::rendering::camera::CameraSettings;
+
+pub const MAX_LOCAL_PLAYERS: usize = 4;
+
+/// Player cameras draw after the main camera, in player order
+const PLAYER_CAMERA_ORDER: isize = 1;
+
+/// Where a player's input comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerInput {
+    KeyboardMouse,
+    Gamepad(Gamepad),
+}
+
+/// How the screen divides between two players
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Left and right halves
+    #[default]
+    Vertical,
+    /// Top and bottom halves
+    Horizontal,
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalPlayer {
+    /// Camera that renders this player's view
+    pub camera: Entity,
+    pub input: PlayerInput,
+}
+
+/// The players sharing the screen; player 0 is the first region
+#[derive(Resource, Default, Debug)]
+pub struct LocalPlayers {
+    players: Vec<LocalPlayer>,
+    pub split: SplitDirection,
+    /// Pixels left between regions
+    pub gap: u32,
+}
+
+impl LocalPlayers {
+    pub fn players(&self) -> &[LocalPlayer] {
+        &self.players
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn get(&self, player: usize) -> Option<&LocalPlayer> {
+        self.players.get(player)
+    }
+
+    /// Add a player viewing through `camera`; `None` once the screen is full
+    /// or the camera or device already belongs to someone
+    pub fn add_player(&mut self, camera: Entity, input: PlayerInput) -> Option<usize> {
+        if self.players.len() >= MAX_LOCAL_PLAYERS
+            || self.player_for_camera(camera).is_some()
+            || self.player_for_input(input).is_some()
+        {
+            return None;
+        }
+        self.players.push(LocalPlayer { camera, input });
+        Some(self.players.len() - 1)
+    }
+
+    /// Remove a player; the ones after it move up a slot
+    pub fn remove_player(&mut self, player: usize) -> Option<LocalPlayer> {
+        (player < self.players.len()).then(|| self.players.remove(player))
+    }
+
+    /// Hand `input` to `player`, swapping devices if another player had it
+    pub fn set_input(&mut self, player: usize, input: PlayerInput) {
+        if player >= self.players.len() {
+            return;
+        }
+        if let Some(other) = self.player_for_input(input) {
+            self.players[other].input = self.players[player].input;
+        }
+        self.players[player].input = input;
+    }
+
+    pub fn set_camera(&mut self, player: usize, camera: Entity) {
+        if let Some(local) = self.players.get_mut(player) {
+            local.camera = camera;
+        }
+    }
+
+    pub fn player_for_input(&self, input: PlayerInput) -> Option<usize> {
+        self.players.iter().position(|player| player.input == input)
+    }
+
+    pub fn player_for_gamepad(&self, gamepad: Gamepad) -> Option<usize> {
+        self.player_for_input(PlayerInput::Gamepad(gamepad))
+    }
+
+    pub fn player_for_camera(&self, camera: Entity) -> Option<usize> {
+        self.players.iter().position(|player| player.camera == camera)
+    }
+
+    /// Gamepads that are connected but not assigned to a player
+    pub fn free_gamepads<'a>(&'a self, gamepads: &'a Gamepads) -> impl Iterator<Item = Gamepad> + 'a {
+        gamepads
+            .iter()
+            .filter(|gamepad| self.player_for_gamepad(*gamepad).is_none())
+    }
+
+    /// Screen region of `player` as fractions of the target, from the top left
+    pub fn region(&self, player: usize) -> Option<Rect> {
+        let half = 0.5;
+        let rect = match (self.players.len(), player) {
+            (count, index) if index >= count => return None,
+            (1, _) => Rect::new(0.0, 0.0, 1.0, 1.0),
+            (2, index) => match self.split {
+                SplitDirection::Vertical => Rect::new(half * index as f32, 0.0, half * (index + 1) as f32, 1.0),
+                SplitDirection::Horizontal => Rect::new(0.0, half * index as f32, 1.0, half * (index + 1) as f32),
+            },
+            // Player 0 gets the top half, the others share the bottom
+            (3, 0) => Rect::new(0.0, 0.0, 1.0, half),
+            (3, index) => Rect::new(half * (index - 1) as f32, half, half * index as f32, 1.0),
+            (_, index) => {
+                let (column, row) = ((index % 2) as f32, (index / 2) as f32);
+                Rect::new(half * column, half * row, half * (column + 1.0), half * (row + 1.0))
+            }
+        };
+        Some(rect)
+    }
+}
+
+/// Point player cameras at their screen regions while playing. Players need
+/// cameras of their own; the main camera steps aside so it doesn't draw
+/// underneath them, and takes over again when play stops.
+pub fn layout_split_screen(
+    players: Res<LocalPlayers>,
+    camera_settings: Res<CameraSettings>,
+    play_state: Res<State<PlayState>>,
+    mut cameras: Query<&mut Camera>,
+) {
+    // Paused games keep their split
+    let split = *play_state.get() == PlayState::Playing && !players.is_empty();
+
+    let Some(main_entity) = camera_settings.main_camera_entity else {
+        return;
+    };
+    let Ok(mut main_camera) = cameras.get_mut(main_entity) else {
+        return;
+    };
+    if main_camera.is_active == split {
+        main_camera.is_active = !split;
+    }
+    let target = main_camera.target.clone();
+    let Some(size) = main_camera.physical_target_size() else {
+        return;
+    };
+
+    for (index, player) in players.players().iter().enumerate() {
+        if player.camera == main_entity {
+            continue;
+        }
+        let Ok(mut camera) = cameras.get_mut(player.camera) else {
+            continue;
+        };
+        if camera.is_active != split {
+            camera.is_active = split;
+        }
+        if !split {
+            continue;
+        }
+        let Some(region) = players.region(index) else {
+            continue;
+        };
+        let viewport = region_viewport(region, size, players.gap);
+        let same_viewport = camera.viewport.as_ref().is_some_and(|current| {
+            current.physical_position == viewport.physical_position && current.physical_size == viewport.physical_size
+        });
+        if !same_viewport {
+            camera.viewport = Some(viewport);
+        }
+        if camera.target.normalize(None) != target.normalize(None) {
+            camera.target = target.clone();
+        }
+        let order = PLAYER_CAMERA_ORDER + index as isize;
+        if camera.order != order {
+            camera.order = order;
+        }
+    }
+}
+
+fn region_viewport(region: Rect, size: UVec2, gap: u32) -> Viewport {
+    let size_f = size.as_vec2();
+    let min = (region.min * size_f).round().as_uvec2();
+    let max = (region.max * size_f).round().as_uvec2();
+    // Only inner edges give up half the gap
+    let inset_min = UVec2::new(
+        if min.x > 0 { gap / 2 } else { 0 },
+        if min.y > 0 { gap / 2 } else { 0 },
+    );
+    let inset_max = UVec2::new(
+        if max.x < size.x { gap - gap / 2 } else { 0 },
+        if max.y < size.y { gap - gap / 2 } else { 0 },
+    );
+    let position = min + inset_min;
+    let end = max.saturating_sub(inset_max).max(position + UVec2::ONE);
+    Viewport {
+        physical_position: position,
+        physical_size: end - position,
+        ..default()
+    }
+}
+
+/// Hear each spatial sound from the nearest player. Runs in `Last`, after
+/// bevy has placed the ears of its single `SpatialListener`, and only
+/// overrides them while more than one player is on screen.
+pub fn route_split_screen_audio(
+    players: Res<LocalPlayers>,
+    default_scale: Res<DefaultSpatialScale>,
+    cameras: Query<&GlobalTransform>,
+    emitters: Query<(&GlobalTransform, &SpatialAudioSink, Option<&PlaybackSettings>)>,
+) {
+    if players.len() < 2 {
+        return;
+    }
+    let listeners: Vec<GlobalTransform> = players
+        .players()
+        .iter()
+        .filter_map(|player| cameras.get(player.camera).ok().copied())
+        .collect();
+    if listeners.is_empty() {
+        return;
+    }
+
+    let ears = SpatialListener::default();
+    for (emitter, sink, settings) in &emitters {
+        let position = emitter.translation();
+        let Some(nearest) = listeners.iter().min_by(|a, b| {
+            a.translation()
+                .distance_squared(position)
+                .total_cmp(&b.translation().distance_squared(position))
+        }) else {
+            continue;
+        };
+        let scale = settings
+            .and_then(|settings| settings.spatial_scale)
+            .unwrap_or(default_scale.0)
+            .0;
+        sink.set_ears_position(
+            nearest.transform_point(ears.left_ear_offset) * scale,
+            nearest.transform_point(ears.right_ear_offset) * scale,
+        );
+    }
+}
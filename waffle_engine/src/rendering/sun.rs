@@ -0,0 +1,117 @@
+//! Sun Position Module
+//! Astronomical sun model for environments that need the real sun path of a
+//! place and date, e.g. architectural visualization. Uses the NOAA solar
+//! position approximation, good to a fraction of a degree.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Where and when the scene is, for the astronomical sun model
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoSunLocation {
+    /// Degrees, north positive
+    pub latitude: f32,
+    /// Degrees, east positive
+    pub longitude: f32,
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    /// Hours the local clock used by `time_of_day` is ahead of UTC
+    pub utc_offset: f32,
+    /// Degrees clockwise (seen from above) from world -Z to true north
+    pub north_heading: f32,
+}
+
+impl Default for GeoSunLocation {
+    fn default() -> Self {
+        Self {
+            latitude: 48.86,
+            longitude: 2.35,
+            year: 2024,
+            month: 6,
+            day: 21,
+            utc_offset: 2.0,
+            north_heading: 0.0,
+        }
+    }
+}
+
+impl GeoSunLocation {
+    pub fn day_of_year(&self) -> u32 {
+        const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let month = self.month.clamp(1, 12);
+        let day = self.day.clamp(1, days_in_month(self.year, month));
+        let leap_day = u32::from(month > 2 && is_leap_year(self.year));
+        DAYS_BEFORE_MONTH[month as usize - 1] + day + leap_day
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolarPosition {
+    /// Degrees clockwise from true north
+    pub azimuth: f32,
+    /// Degrees above the horizon
+    pub elevation: f32,
+}
+
+impl SolarPosition {
+    /// World-space direction towards the sun
+    pub fn direction(&self, north_heading: f32) -> Vec3 {
+        let heading = (self.azimuth + north_heading).to_radians();
+        let elevation = self.elevation.to_radians();
+        let (sin_e, cos_e) = elevation.sin_cos();
+        Vec3::new(heading.sin() * cos_e, sin_e, -heading.cos() * cos_e).normalize_or_zero()
+    }
+}
+
+/// Sun position at `time_of_day` local hours on the location's date
+pub fn solar_position(location: &GeoSunLocation, time_of_day: f32) -> SolarPosition {
+    let local_hours = time_of_day.rem_euclid(24.0) as f64;
+    let utc_hours = local_hours - location.utc_offset as f64;
+    let days_in_year = if is_leap_year(location.year) { 366.0 } else { 365.0 };
+
+    // Fractional year in radians
+    let gamma = std::f64::consts::TAU / days_in_year
+        * (location.day_of_year() as f64 - 1.0 + (utc_hours - 12.0) / 24.0);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    // True solar time in minutes and the hour angle from it
+    let time_offset = equation_of_time + 4.0 * location.longitude as f64 - 60.0 * location.utc_offset as f64;
+    let solar_minutes = local_hours * 60.0 + time_offset;
+    let hour_angle = (solar_minutes / 4.0 - 180.0).to_radians();
+
+    let latitude = (location.latitude as f64).clamp(-90.0, 90.0).to_radians();
+    let sin_elevation = latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos();
+    let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+    let azimuth_from_south = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos());
+
+    SolarPosition {
+        azimuth: (azimuth_from_south.to_degrees() + 180.0).rem_euclid(360.0) as f32,
+        elevation: elevation.to_degrees() as f32,
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
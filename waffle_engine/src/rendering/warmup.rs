@@ -0,0 +1,262 @@
+//! Shader Warm-up
+//! Draws every known material once as a tiny proxy so its pipeline compiles before play
+
+use bevy::prelude::*;
+use bevy::render::render_resource::PipelineCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use crate::core::components::EditorHidden;
+use crate::core::project::ProjectSettings;
+use super::camera::CameraSettings;
+
+pub const SHADER_WARMUP_MANIFEST: &str = "shader_warmup.ron";
+/// Frames to wait before trusting that nothing new was queued
+const MIN_WARMUP_FRAMES: u32 = 3;
+/// Give up on pipelines that never finish
+const MAX_WARMUP_FRAMES: u32 = 1200;
+const PROXY_SCALE: f32 = 0.001;
+
+/// Pipeline counts from the render world
+#[derive(Resource, Clone, Default)]
+pub struct PipelineProgress {
+    total: Arc<AtomicUsize>,
+    waiting: Arc<AtomicUsize>,
+}
+
+impl PipelineProgress {
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs in the render app
+pub fn report_pipeline_progress(cache: Res<PipelineCache>, progress: Res<PipelineProgress>) {
+    progress.total.store(cache.pipelines().count(), Ordering::Relaxed);
+    progress.waiting.store(cache.waiting_pipelines().count(), Ordering::Relaxed);
+}
+
+/// Materials earlier warm-ups saw, warmed on start when the project enables
+/// it. This wgpu version cannot save compiled pipelines, so this list is
+/// what persists; GPU drivers keep their own shader caches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShaderWarmupManifest {
+    /// Asset paths of materials, e.g. `models/crate.glb#Material0`
+    pub materials: Vec<String>,
+}
+
+impl ShaderWarmupManifest {
+    pub fn load() -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(SHADER_WARMUP_MANIFEST)?;
+        Ok(ron::de::from_str(&data)?)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(SHADER_WARMUP_MANIFEST, data)?;
+        Ok(())
+    }
+}
+
+#[derive(Component)]
+pub struct ShaderWarmupProxy;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShaderWarmupStatus {
+    pub materials: usize,
+    /// Pipelines created since the warm-up started
+    pub compiled: usize,
+    pub waiting: usize,
+}
+
+impl ShaderWarmupStatus {
+    pub fn fraction(&self) -> f32 {
+        let total = self.compiled + self.waiting;
+        if total == 0 { 0.0 } else { self.compiled as f32 / total as f32 }
+    }
+}
+
+struct RunningWarmup {
+    started: Instant,
+    frames: u32,
+    pipelines_before: usize,
+    proxies: Vec<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub struct ShaderWarmup {
+    requested: bool,
+    running: Option<RunningWarmup>,
+    /// Materials from the manifest, held until they are warmed
+    manifest_materials: Vec<Handle<StandardMaterial>>,
+    pub status: Option<ShaderWarmupStatus>,
+}
+
+impl ShaderWarmup {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.requested || self.running.is_some()
+    }
+}
+
+/// Load the materials earlier warm-ups saw and warm them, when the project
+/// asks for it
+pub fn start_boot_shader_warmup(
+    project_settings: Res<ProjectSettings>,
+    asset_server: Res<AssetServer>,
+    mut warmup: ResMut<ShaderWarmup>,
+) {
+    if !project_settings.warm_up_shaders_on_start {
+        return;
+    }
+    if let Ok(manifest) = ShaderWarmupManifest::load() {
+        warmup.manifest_materials = manifest
+            .materials
+            .into_iter()
+            .map(|path| asset_server.load(path))
+            .collect();
+    }
+    warmup.request();
+}
+
+pub fn run_shader_warmup(
+    mut commands: Commands,
+    mut warmup: ResMut<ShaderWarmup>,
+    progress: Res<PipelineProgress>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    camera_settings: Res<CameraSettings>,
+    camera_query: Query<&GlobalTransform>,
+    drawn_query: Query<(&Handle<Mesh>, &Handle<StandardMaterial>), Without<ShaderWarmupProxy>>,
+) {
+    if let Some(running) = warmup.running.as_mut() {
+        running.frames += 1;
+        let compiled = progress.total().saturating_sub(running.pipelines_before + progress.waiting());
+        let waiting = progress.waiting();
+        let finished = running.frames >= MIN_WARMUP_FRAMES && waiting == 0;
+        let timed_out = running.frames >= MAX_WARMUP_FRAMES;
+        if let Some(status) = warmup.status.as_mut() {
+            status.compiled = compiled;
+            status.waiting = waiting;
+        }
+        if finished || timed_out {
+            let Some(running) = warmup.running.take() else {
+                return;
+            };
+            for proxy in running.proxies {
+                commands.entity(proxy).despawn_recursive();
+            }
+            if timed_out {
+                warn!("Shader warm-up stopped with {} pipelines still compiling", waiting);
+            } else {
+                info!(
+                    "Shader warm-up compiled {} pipelines in {:.1}s",
+                    compiled,
+                    running.started.elapsed().as_secs_f32()
+                );
+            }
+            warmup.manifest_materials.clear();
+            warmup.status = None;
+        }
+        return;
+    }
+
+    if !warmup.requested {
+        return;
+    }
+    // Wait for the manifest's materials and their textures
+    let loading = warmup.manifest_materials.iter().any(|handle| {
+        !asset_server.is_loaded_with_dependencies(handle)
+            && !matches!(
+                asset_server.get_load_state(handle),
+                Some(bevy::asset::LoadState::Failed(_)) | None
+            )
+    });
+    if loading {
+        return;
+    }
+    let Some(camera) = camera_settings
+        .active_camera_entity
+        .or(camera_settings.main_camera_entity)
+        .and_then(|camera| camera_query.get(camera).ok())
+    else {
+        return;
+    };
+    warmup.requested = false;
+
+    // One proxy per material, with a mesh it is drawn with so the vertex
+    // layout matches; materials nothing draws yet get a cube
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for (mesh, material) in &drawn_query {
+        if seen.insert(material.id()) {
+            pairs.push((mesh.clone(), material.clone()));
+        }
+    }
+    let cube = meshes.add(Cuboid::new(1.0, 1.0, 1.0));
+    let ids: Vec<AssetId<StandardMaterial>> = materials.ids().collect();
+    for id in ids {
+        if seen.insert(id) {
+            if let Some(material) = materials.get_strong_handle(id) {
+                pairs.push((cube.clone(), material));
+            }
+        }
+    }
+
+    let position = camera.translation() + camera.forward() * 1.0;
+    let proxies: Vec<Entity> = pairs
+        .iter()
+        .map(|(mesh, material)| {
+            commands
+                .spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(position).with_scale(Vec3::splat(PROXY_SCALE)),
+                        ..default()
+                    },
+                    ShaderWarmupProxy,
+                    EditorHidden,
+                    Name::new("Shader Warm-up Proxy"),
+                ))
+                .id()
+        })
+        .collect();
+
+    let mut manifest = ShaderWarmupManifest::load().unwrap_or_default();
+    for (_, material) in &pairs {
+        if let Some(path) = material.path() {
+            let path = path.to_string();
+            if !manifest.materials.contains(&path) {
+                manifest.materials.push(path);
+            }
+        }
+    }
+    if let Err(err) = manifest.save() {
+        warn!("Failed to save {}: {}", SHADER_WARMUP_MANIFEST, err);
+    }
+
+    info!("Warming up shaders for {} materials", pairs.len());
+    warmup.status = Some(ShaderWarmupStatus {
+        materials: pairs.len(),
+        compiled: 0,
+        waiting: 0,
+    });
+    warmup.running = Some(RunningWarmup {
+        started: Instant::now(),
+        frames: 0,
+        pipelines_before: progress.total(),
+        proxies,
+    });
+}
@@ -0,0 +1,584 @@
+//! Water Module
+//! Flat water surfaces with scrolling waves, a depth-tinted see-through body and reflections
+
+use bevy::core_pipeline::prepass::DepthPrepass;
+use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::math::Vec3A;
+use bevy::pbr::{ExtendedMaterial, MaterialExtension, NotShadowCaster, OpaqueRendererMethod};
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, RenderTarget};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{
+    AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor};
+use bevy::transform::helper::TransformHelper;
+use serde::{Deserialize, Serialize};
+
+use crate::core::components::EditorHidden;
+use crate::rendering::camera::CameraSettings;
+
+pub const WATER_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x2c8e_47a1_b936_4f0d_8e15_d7a4_61c9_0b52);
+
+/// Reflection cameras render before the scene cameras that show them
+const WATER_CAMERA_ORDER: isize = -30;
+
+/// How far above the surface the reflection camera's clip plane sits, which
+/// keeps the surface out of its own reflection
+const REFLECTION_CLIP_OFFSET: f32 = 0.05;
+
+/// Width and height of the generated ripple normal map
+const DEFAULT_NORMALS_SIZE: u32 = 128;
+
+/// Where the reflections on a water surface come from
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaterReflections {
+    None,
+    /// Marched over the depth prepass; only what is on screen reflects
+    #[default]
+    ScreenSpace,
+    /// Rendered by a camera mirrored in the water plane, a second scene pass
+    /// per water surface
+    Planar,
+}
+
+impl WaterReflections {
+    pub const ALL: [WaterReflections; 3] =
+        [WaterReflections::None, WaterReflections::ScreenSpace, WaterReflections::Planar];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WaterReflections::None => "None",
+            WaterReflections::ScreenSpace => "Screen Space",
+            WaterReflections::Planar => "Planar",
+        }
+    }
+
+    /// Reflection mode the water shader branches on
+    fn shader_mode(self) -> f32 {
+        match self {
+            WaterReflections::None => 0.0,
+            WaterReflections::ScreenSpace => 1.0,
+            WaterReflections::Planar => 2.0,
+        }
+    }
+}
+
+/// A water surface centered on its entity, facing its local +Y
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaffleWater {
+    /// Width along X and length along Z
+    pub size: Vec2,
+    /// Tint of what shows through shallow water
+    pub shallow_color: Color,
+    /// Color of water too deep to see through
+    pub deep_color: Color,
+    /// Depth of water that hides most of what is behind it
+    pub absorption_depth: f32,
+    /// Asset path of a tangent-space normal map; generated ripples when empty
+    pub normal_map: String,
+    /// World units one repeat of the normal map covers
+    pub wave_scale: f32,
+    /// World units per second the waves travel
+    pub wave_speed: f32,
+    /// Heading the waves travel in, in degrees around +Y from +X
+    pub wave_direction: f32,
+    /// How far the waves bend what shows through and what reflects
+    pub wave_strength: f32,
+    pub reflections: WaterReflections,
+    /// 0 hides reflections; 1 shows them as strongly as the viewing angle allows
+    pub reflection_strength: f32,
+    /// Texture size of planar reflections
+    pub reflection_resolution: u32,
+}
+
+impl Default for WaffleWater {
+    fn default() -> Self {
+        Self {
+            size: Vec2::splat(20.0),
+            shallow_color: Color::srgb(0.55, 0.85, 0.8),
+            deep_color: Color::srgb(0.02, 0.1, 0.16),
+            absorption_depth: 3.0,
+            normal_map: String::new(),
+            wave_scale: 4.0,
+            wave_speed: 0.3,
+            wave_direction: 30.0,
+            wave_strength: 0.5,
+            reflections: WaterReflections::ScreenSpace,
+            reflection_strength: 1.0,
+            reflection_resolution: 1024,
+        }
+    }
+}
+
+impl WaffleWater {
+    fn uniform(&self) -> WaterUniform {
+        let direction = Vec2::from_angle(self.wave_direction.to_radians());
+        WaterUniform {
+            shallow_color: self.shallow_color.to_linear().to_vec4(),
+            deep_color: self.deep_color.to_linear().to_vec4(),
+            waves: Vec4::new(direction.x, direction.y, self.wave_speed, self.wave_scale.max(0.01)),
+            params: Vec4::new(
+                self.absorption_depth.max(0.001),
+                self.wave_strength,
+                self.reflection_strength,
+                self.reflections.shader_mode(),
+            ),
+            reflection_clip_from_world: Mat4::IDENTITY,
+        }
+    }
+}
+
+pub type WaterMaterial = ExtendedMaterial<StandardMaterial, WaterShading>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, ShaderType)]
+pub struct WaterUniform {
+    /// Linear colors
+    pub shallow_color: Vec4,
+    pub deep_color: Vec4,
+    /// Heading of the waves in x and y, their speed in z and the size of one
+    /// normal map repeat in w
+    pub waves: Vec4,
+    /// Absorption depth, wave strength, reflection strength and reflection mode
+    pub params: Vec4,
+    /// Clip space of the planar reflection camera
+    pub reflection_clip_from_world: Mat4,
+}
+
+/// Waves, absorption and reflections drawn over a standard material
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug, Default, PartialEq)]
+pub struct WaterShading {
+    #[uniform(100)]
+    pub settings: WaterUniform,
+    #[texture(101)]
+    #[sampler(102)]
+    pub normal_map: Handle<Image>,
+    #[texture(103)]
+    #[sampler(104)]
+    pub reflection: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for WaterShading {
+    fn fragment_shader() -> ShaderRef {
+        WATER_SHADER_HANDLE.into()
+    }
+}
+
+/// Normal map of water without one
+#[derive(Resource)]
+pub struct DefaultWaterNormals(pub Handle<Image>);
+
+/// Surface, material and reflection camera built for a `WaffleWater`
+#[derive(Component)]
+pub struct WaterView {
+    pub material: Handle<WaterMaterial>,
+    surface: Entity,
+    size: Vec2,
+    reflection: Option<WaterReflectionTarget>,
+}
+
+struct WaterReflectionTarget {
+    camera: Entity,
+    image: Handle<Image>,
+    resolution: u32,
+}
+
+/// Camera rendering the planar reflection of a water surface
+#[derive(Component)]
+pub struct WaterReflectionCamera {
+    pub water: Entity,
+}
+
+/// The viewer's perspective with its near plane swapped for the water plane,
+/// so nothing under the surface gets into the reflection
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component, Default)]
+pub struct WaterReflectionProjection {
+    pub fov: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    /// The clip plane in view space; what is on its positive side is drawn
+    pub clip_plane: Vec4,
+}
+
+impl Default for WaterReflectionProjection {
+    fn default() -> Self {
+        Self {
+            fov: std::f32::consts::FRAC_PI_4,
+            aspect_ratio: 1.0,
+            near: 0.1,
+            far: 1000.0,
+            clip_plane: Vec4::new(0.0, 0.0, -1.0, -0.1),
+        }
+    }
+}
+
+impl CameraProjection for WaterReflectionProjection {
+    fn get_clip_from_view(&self) -> Mat4 {
+        let mut clip_from_view = Mat4::perspective_infinite_reverse_rh(self.fov, self.aspect_ratio, self.near);
+        // Depth is `near / -z`. Rebuilding the depth row from the clip plane
+        // puts depth 1 on the plane, scaled so the farthest corner of the
+        // view still ends at depth 0.
+        let tan_y = (self.fov * 0.5).tan();
+        let tan_x = tan_y * self.aspect_ratio;
+        let plane = self.clip_plane;
+        let reach = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| plane.x * x * tan_x + plane.y * y * tan_y - plane.z)
+            .into_iter()
+            .fold(f32::MIN, f32::max);
+        if reach <= 0.0 {
+            return clip_from_view;
+        }
+        let depth_row = Vec4::new(0.0, 0.0, -1.0, 0.0) - plane / reach;
+        clip_from_view.x_axis.z = depth_row.x;
+        clip_from_view.y_axis.z = depth_row.y;
+        clip_from_view.z_axis.z = depth_row.z;
+        clip_from_view.w_axis.z = depth_row.w;
+        clip_from_view
+    }
+
+    /// The window comes from the viewer, not the target size
+    fn update(&mut self, _width: f32, _height: f32) {}
+
+    fn far(&self) -> f32 {
+        self.far
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        let tan_half_fov = (self.fov * 0.5).tan();
+        let near = z_near.abs() * tan_half_fov;
+        let far = z_far.abs() * tan_half_fov;
+        let aspect = self.aspect_ratio;
+        // Same order as bevy's perspective projection
+        [
+            Vec3A::new(near * aspect, -near, z_near),
+            Vec3A::new(near * aspect, near, z_near),
+            Vec3A::new(-near * aspect, near, z_near),
+            Vec3A::new(-near * aspect, -near, z_near),
+            Vec3A::new(far * aspect, -far, z_far),
+            Vec3A::new(far * aspect, far, z_far),
+            Vec3A::new(-far * aspect, far, z_far),
+            Vec3A::new(-far * aspect, -far, z_far),
+        ]
+    }
+}
+
+/// Tileable ripples made of a few crossing sine waves, each a whole number of
+/// times across the texture
+pub fn setup_default_water_normals(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    // Repeats across the texture in x and y, height and phase
+    const WAVES: [(f32, f32, f32, f32); 5] = [
+        (1.0, 2.0, 0.5, 0.0),
+        (3.0, -1.0, 0.3, 1.7),
+        (-2.0, 5.0, 0.2, 4.1),
+        (7.0, 3.0, 0.1, 2.3),
+        (-5.0, -8.0, 0.06, 5.6),
+    ];
+    let size = DEFAULT_NORMALS_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new(x as f32, y as f32) / size as f32;
+            let mut slope = Vec2::ZERO;
+            for (kx, ky, height, phase) in WAVES {
+                let angle = std::f32::consts::TAU * (kx * uv.x + ky * uv.y) + phase;
+                slope += Vec2::new(kx, ky) * (height * angle.cos());
+            }
+            let normal = Vec3::new(-slope.x, -slope.y, 4.0).normalize();
+            let encoded = (normal * 0.5 + 0.5) * 255.0;
+            data.extend([encoded.x as u8, encoded.y as u8, encoded.z as u8, 255]);
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = repeating_sampler();
+    commands.insert_resource(DefaultWaterNormals(images.add(image)));
+}
+
+fn repeating_sampler() -> ImageSampler {
+    ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    })
+}
+
+/// Normal maps are linear and repeat across the surface
+fn load_water_normals(asset_server: &AssetServer, path: &str) -> Handle<Image> {
+    asset_server.load_with_settings(path.to_string(), |settings: &mut ImageLoaderSettings| {
+        settings.is_srgb = false;
+        settings.sampler = repeating_sampler();
+    })
+}
+
+/// Reflections are rendered without tonemapping into a float texture, so the
+/// water can light them like the rest of the scene
+fn create_reflection_image(resolution: u32) -> Image {
+    let size = Extent3d {
+        width: resolution,
+        height: resolution,
+        ..default()
+    };
+    let mut image = Image::default();
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Rgba16Float;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image.resize(size);
+    image
+}
+
+fn water_base_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.05,
+        // Puts the water in the transmissive pass, after the opaque scene it
+        // shows through, and keeps it out of the depth prepass
+        specular_transmission: 1.0,
+        opaque_render_method: OpaqueRendererMethod::Forward,
+        ..default()
+    }
+}
+
+/// Create the surfaces of new water, and rebuild or restyle changed water
+#[allow(clippy::too_many_arguments)]
+pub fn build_water_views(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    default_normals: Option<Res<DefaultWaterNormals>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+    mut waters: Query<(Entity, &WaffleWater, Option<&mut WaterView>), Changed<WaffleWater>>,
+    removed: Query<(Entity, &WaterView), Without<WaffleWater>>,
+) {
+    for (entity, view) in &removed {
+        despawn_water_view(&mut commands, view);
+        commands.entity(entity).remove::<WaterView>();
+    }
+    let Some(default_normals) = default_normals else {
+        return;
+    };
+
+    for (entity, water, view) in waters.iter_mut() {
+        let mut created = None;
+        let view = match view {
+            Some(view) => view.into_inner(),
+            None => created.insert(WaterView {
+                material: materials.add(WaterMaterial {
+                    base: water_base_material(),
+                    extension: WaterShading::default(),
+                }),
+                surface: Entity::PLACEHOLDER,
+                size: Vec2::ZERO,
+                reflection: None,
+            }),
+        };
+
+        let size = water.size.max(Vec2::splat(0.01));
+        if view.size != size {
+            if let Some(surface) = commands.get_entity(view.surface) {
+                surface.despawn_recursive();
+            }
+            view.surface = commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.add(Plane3d::default().mesh().size(size.x, size.y)),
+                        material: view.material.clone(),
+                        ..default()
+                    },
+                    NotShadowCaster,
+                    EditorHidden,
+                    Name::new("Water Surface"),
+                ))
+                .set_parent(entity)
+                .id();
+            view.size = size;
+        }
+
+        let planar = water.reflections == WaterReflections::Planar;
+        let resolution = water.reflection_resolution.clamp(64, 4096);
+        if view.reflection.as_ref().is_some_and(|target| !planar || target.resolution != resolution) {
+            if let Some(target) = view.reflection.take() {
+                if let Some(camera) = commands.get_entity(target.camera) {
+                    camera.despawn_recursive();
+                }
+            }
+        }
+        if planar && view.reflection.is_none() {
+            let image = images.add(create_reflection_image(resolution));
+            let camera = commands
+                .spawn((
+                    Camera3dBundle {
+                        camera: Camera {
+                            target: RenderTarget::Image(image.clone()),
+                            order: WATER_CAMERA_ORDER,
+                            is_active: false,
+                            hdr: true,
+                            ..default()
+                        },
+                        tonemapping: Tonemapping::None,
+                        deband_dither: DebandDither::Disabled,
+                        ..default()
+                    },
+                    WaterReflectionCamera { water: entity },
+                    EditorHidden,
+                    Name::new("Water Reflection Camera"),
+                ))
+                .remove::<Projection>()
+                .insert(WaterReflectionProjection::default())
+                .id();
+            view.reflection = Some(WaterReflectionTarget {
+                camera,
+                image,
+                resolution,
+            });
+        }
+
+        if let Some(material) = materials.get_mut(&view.material) {
+            let mut settings = water.uniform();
+            if planar {
+                // Placed every frame by `update_water_reflections`
+                settings.params.w = material.extension.settings.params.w;
+                settings.reflection_clip_from_world = material.extension.settings.reflection_clip_from_world;
+            }
+            material.extension = WaterShading {
+                settings,
+                normal_map: if water.normal_map.is_empty() {
+                    default_normals.0.clone()
+                } else {
+                    load_water_normals(&asset_server, &water.normal_map)
+                },
+                reflection: view.reflection.as_ref().map(|target| target.image.clone()),
+            };
+        }
+
+        if let Some(created) = created {
+            commands.entity(entity).insert(created);
+        }
+    }
+}
+
+fn despawn_water_view(commands: &mut Commands, view: &WaterView) {
+    let camera = view.reflection.as_ref().map(|target| target.camera);
+    for entity in std::iter::once(view.surface).chain(camera) {
+        if let Some(entity) = commands.get_entity(entity) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+/// Cameras outlive despawned water since they aren't children of it
+pub fn despawn_orphaned_water_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &WaterReflectionCamera)>,
+    views: Query<&WaterView>,
+) {
+    for (entity, camera) in &cameras {
+        let owned = views
+            .get(camera.water)
+            .is_ok_and(|view| view.reflection.as_ref().is_some_and(|target| target.camera == entity));
+        if !owned {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Water reads the depth of the scene behind it from the depth prepass
+pub fn add_water_depth_prepass(
+    mut commands: Commands,
+    waters: Query<(), With<WaffleWater>>,
+    cameras: Query<Entity, (With<Camera3d>, Without<DepthPrepass>, Without<WaterReflectionCamera>)>,
+) {
+    if waters.is_empty() {
+        return;
+    }
+    for camera in &cameras {
+        commands.entity(camera).insert(DepthPrepass);
+    }
+}
+
+/// Put each planar reflection camera at the viewer's eye mirrored in its
+/// water plane, and hand the water its clip space to sample the reflection by
+pub fn update_water_reflections(
+    camera_settings: Res<CameraSettings>,
+    helper: TransformHelper,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+    waters: Query<(Entity, &WaterView)>,
+    viewers: Query<&Projection, Without<WaterReflectionCamera>>,
+    mut cameras: Query<(&mut Camera, &mut Transform, &mut WaterReflectionProjection), With<WaterReflectionCamera>>,
+) {
+    let viewer = camera_settings.active_camera_entity.or(camera_settings.main_camera_entity);
+    let eye = viewer.and_then(|viewer| helper.compute_global_transform(viewer).ok());
+    let perspective = match viewer.and_then(|viewer| viewers.get(viewer).ok()) {
+        Some(Projection::Perspective(perspective)) => perspective.clone(),
+        _ => PerspectiveProjection::default(),
+    };
+
+    for (entity, view) in &waters {
+        let Some(target) = view.reflection.as_ref() else {
+            continue;
+        };
+        let Ok((mut camera, mut transform, mut projection)) = cameras.get_mut(target.camera) else {
+            continue;
+        };
+        let plane = helper.compute_global_transform(entity).ok().map(|global| (global.translation(), *global.up()));
+        // Seen from under the water there is nothing to reflect
+        let placed = match (eye, plane) {
+            (Some(eye), Some((point, normal))) if normal.dot(eye.translation() - point) > 0.0 => {
+                let mirror = |vector: Vec3| vector - 2.0 * normal * normal.dot(vector);
+                let position = eye.translation() - 2.0 * normal * normal.dot(eye.translation() - point);
+                let placed =
+                    Transform::from_translation(position).looking_to(mirror(*eye.forward()), mirror(*eye.up()));
+                let clip_point = point + normal * REFLECTION_CLIP_OFFSET;
+                let world_plane = normal.extend(-normal.dot(clip_point));
+                let window = WaterReflectionProjection {
+                    fov: perspective.fov,
+                    aspect_ratio: perspective.aspect_ratio,
+                    near: perspective.near,
+                    far: perspective.far,
+                    clip_plane: placed.compute_matrix().transpose() * world_plane,
+                };
+                Some((placed, window))
+            }
+            _ => None,
+        };
+
+        if camera.is_active != placed.is_some() {
+            camera.is_active = placed.is_some();
+        }
+        let (mode, clip_from_world) = match placed {
+            Some((placed, window)) => {
+                let clip_from_world = window.get_clip_from_view() * placed.compute_matrix().inverse();
+                *transform = placed;
+                *projection = window;
+                (WaterReflections::Planar.shader_mode(), clip_from_world)
+            }
+            None => (WaterReflections::None.shader_mode(), Mat4::IDENTITY),
+        };
+        // Only touch the material when the view moved, so it isn't prepared
+        // again every frame
+        let moved = materials.get(&view.material).is_some_and(|material| {
+            let settings = &material.extension.settings;
+            settings.params.w != mode || settings.reflection_clip_from_world != clip_from_world
+        });
+        if moved {
+            if let Some(material) = materials.get_mut(&view.material) {
+                material.extension.settings.params.w = mode;
+                material.extension.settings.reflection_clip_from_world = clip_from_world;
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Waffle Engine water
+// Two scrolling copies of a normal map make the waves. The scene behind the
+// surface, read from the view transmission texture, is tinted toward the
+// deep color by the depth of water in front of it, taken from the depth
+// prepass. Reflections are marched in screen space over the same depth, or
+// read from the texture of a planar reflection camera, and mixed in by the
+// Fresnel term.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+    mesh_view_bindings::{view, globals, view_transmission_texture, view_transmission_sampler},
+    view_transformations::depth_ndc_to_view_z,
+}
+
+#ifdef DEPTH_PREPASS
+#import bevy_pbr::prepass_utils::prepass_depth
+#endif
+
+struct WaterSettings {
+    shallow_color: vec4<f32>,
+    deep_color: vec4<f32>,
+    // Heading in xy, speed in z, world size of one normal map repeat in w
+    waves: vec4<f32>,
+    // Absorption depth, wave strength, reflection strength, reflection mode
+    params: vec4<f32>,
+    reflection_clip_from_world: mat4x4<f32>,
+}
+
+@group(2) @binding(100) var<uniform> water: WaterSettings;
+@group(2) @binding(101) var normal_texture: texture_2d<f32>;
+@group(2) @binding(102) var normal_sampler: sampler;
+@group(2) @binding(103) var reflection_texture: texture_2d<f32>;
+@group(2) @binding(104) var reflection_sampler: sampler;
+
+const REFLECTION_SCREEN_SPACE: u32 = 1u;
+const REFLECTION_PLANAR: u32 = 2u;
+
+const SSR_STEPS: i32 = 32;
+const SSR_FIRST_STEP: f32 = 0.2;
+const SSR_STEP_GROWTH: f32 = 1.15;
+
+// How far across the screen the waves bend what shows through and reflects
+const DISTORTION: f32 = 0.03;
+
+fn wave_normal(world_position: vec3<f32>, surface_normal: vec3<f32>) -> vec3<f32> {
+    let heading = water.waves.xy;
+    let across = vec2(-heading.y, heading.x);
+    let scale = water.waves.w;
+    let travel = water.waves.z * globals.time / scale;
+    let uv = world_position.xz / scale;
+    // The second copy is larger, turned and slower, so the pattern never
+    // lines up with itself
+    let first = textureSample(normal_texture, normal_sampler, uv + heading * travel).xy * 2.0 - 1.0;
+    let second_uv = vec2(dot(uv, across), dot(uv, heading)) * 0.63 + across * travel * 0.7;
+    let second = textureSample(normal_texture, normal_sampler, second_uv).xy * 2.0 - 1.0;
+    let slope = (first + second) * water.params.y;
+    return normalize(surface_normal + vec3(slope.x, 0.0, slope.y));
+}
+
+fn clip_to_uv(clip: vec4<f32>) -> vec2<f32> {
+    let ndc = clip.xy / clip.w;
+    return vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+}
+
+// Reflected color and how much of it to trust, zero where the march found
+// nothing on screen
+fn screen_space_reflection(world_position: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+#ifdef DEPTH_PREPASS
+    var step_length = SSR_FIRST_STEP;
+    var position = world_position;
+    for (var i = 0; i < SSR_STEPS; i += 1) {
+        position += direction * step_length;
+        step_length *= SSR_STEP_GROWTH;
+        let clip = view.clip_from_world * vec4(position, 1.0);
+        if clip.w <= 0.0 {
+            break;
+        }
+        let uv = clip_to_uv(clip);
+        if any(uv < vec2(0.0)) || any(uv > vec2(1.0)) {
+            break;
+        }
+        let frag_coord = vec4(uv * view.viewport.zw + view.viewport.xy, 0.0, 0.0);
+        let scene_z = depth_ndc_to_view_z(prepass_depth(frag_coord, 0u));
+        let ray_z = depth_ndc_to_view_z(clip.z / clip.w);
+        // Passed behind the scene, but not so far that it went under something
+        if ray_z < scene_z && scene_z - ray_z < step_length * 2.0 {
+            let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+            let color = textureSampleLevel(view_transmission_texture, view_transmission_sampler, uv, 0.0).rgb;
+            return vec4(color, clamp(edge * 10.0, 0.0, 1.0));
+        }
+    }
+#endif
+    return vec4(0.0);
+}
+
+fn planar_reflection(world_position: vec3<f32>, normal: vec3<f32>) -> vec4<f32> {
+    let clip = water.reflection_clip_from_world * vec4(world_position, 1.0);
+    if clip.w <= 0.0 {
+        return vec4(0.0);
+    }
+    let uv = clamp(clip_to_uv(clip) + normal.xz * DISTORTION * water.params.y, vec2(0.0), vec2(1.0));
+    return vec4(textureSampleLevel(reflection_texture, reflection_sampler, uv, 0.0).rgb, 1.0);
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    let normal = wave_normal(in.world_position.xyz, normalize(in.world_normal));
+    pbr_input.N = normal;
+    pbr_input.material.base_color = vec4(water.deep_color.rgb, 1.0);
+    // What shows through is worked out below rather than by transmission
+    pbr_input.material.specular_transmission = 0.0;
+    let lit = apply_pbr_lighting(pbr_input).rgb;
+
+    let screen_uv = (in.position.xy - view.viewport.xy) / view.viewport.zw;
+    let refracted_uv = clamp(screen_uv + normal.xz * DISTORTION * water.params.y, vec2(0.0), vec2(1.0));
+    let behind = textureSampleLevel(view_transmission_texture, view_transmission_sampler, refracted_uv, 0.0).rgb;
+
+    // Without the depth prepass the water is taken to be deep
+    var thickness = 1000.0;
+#ifdef DEPTH_PREPASS
+    let scene_z = depth_ndc_to_view_z(prepass_depth(in.position, 0u));
+    let surface_z = depth_ndc_to_view_z(in.position.z);
+    thickness = max(surface_z - scene_z, 0.0);
+#endif
+    let transmittance = exp(-thickness / water.params.x);
+    let body = mix(lit, behind * water.shallow_color.rgb, transmittance);
+
+    var reflection = vec4(0.0);
+    let mode = u32(water.params.w + 0.5);
+    if mode == REFLECTION_SCREEN_SPACE {
+        reflection = screen_space_reflection(in.world_position.xyz, reflect(-pbr_input.V, normal));
+    } else if mode == REFLECTION_PLANAR {
+        reflection = planar_reflection(in.world_position.xyz, normal);
+    }
+    let facing = clamp(dot(normal, pbr_input.V), 0.0, 1.0);
+    let fresnel = 0.02 + 0.98 * pow(1.0 - facing, 5.0);
+    let color = mix(body, reflection.rgb, fresnel * water.params.z * reflection.a);
+
+    var out: FragmentOutput;
+    out.color = main_pass_post_lighting_processing(pbr_input, vec4(color, 1.0));
+    return out;
+}
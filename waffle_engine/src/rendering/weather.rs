@@ -0,0 +1,581 @@
+//! Weather Module
+//! Rain, snow and storms. The `Weather` resource holds the current weather
+//! and eases from one kind to another over time, e.g. when a script asks it
+//! to. It drives falling drops around the camera, how wet surfaces look, the
+//! extra fog on top of the environment's, and a looping ambience per kind.
+//! Drops are plain meshes moved on the CPU inside a box that follows the
+//! camera, so the world looks like it is raining everywhere.
+
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::audio::{PlayAudioEvent, StopAudioEvent, WaffleAudioSource};
+use crate::core::components::EditorHidden;
+use crate::core::play::PlayState;
+use crate::core::project::ProjectSettings;
+use crate::rendering::camera::{CameraSettings, WaffleMainCamera};
+use crate::rendering::environment::ActiveEnvironment;
+use crate::rendering::scene::{camera_fog, EnvironmentFogMode, EnvironmentFogSettings, EnvironmentSettings};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+    /// Rain with wind
+    Storm,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 4] = [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Storm];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+            WeatherKind::Storm => "storm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// How much of each kind of weather there is, from 0 to 1. Transitions blend
+/// these, so rain can fade out while snow fades in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeatherMix {
+    pub rain: f32,
+    pub snow: f32,
+    pub storm: f32,
+}
+
+impl WeatherMix {
+    fn of(kind: WeatherKind, intensity: f32) -> Self {
+        let intensity = intensity.clamp(0.0, 1.0);
+        match kind {
+            WeatherKind::Clear => Self::default(),
+            WeatherKind::Rain => Self { rain: intensity, ..default() },
+            WeatherKind::Snow => Self { snow: intensity, ..default() },
+            WeatherKind::Storm => Self {
+                rain: intensity,
+                storm: intensity,
+                ..default()
+            },
+        }
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            rain: self.rain.lerp(other.rain, t),
+            snow: self.snow.lerp(other.snow, t),
+            storm: self.storm.lerp(other.storm, t),
+        }
+    }
+
+    pub fn is_clear(&self) -> bool {
+        self.rain <= 0.0 && self.snow <= 0.0 && self.storm <= 0.0
+    }
+}
+
+/// The current weather
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Weather {
+    kind: WeatherKind,
+    intensity: f32,
+    from: WeatherMix,
+    mix: WeatherMix,
+    duration: f32,
+    elapsed: f32,
+    /// How wet surfaces are, from 0 to 1. Soaks up while it rains and dries
+    /// off slowly afterwards.
+    pub wetness: f32,
+}
+
+impl Weather {
+    /// The weather being transitioned to, or the current one
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// What is falling right now, partway through any transition
+    pub fn mix(&self) -> WeatherMix {
+        self.mix
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.elapsed < self.duration
+    }
+
+    /// Switch to `kind` at once
+    pub fn set(&mut self, kind: WeatherKind, intensity: f32) {
+        self.transition_to(kind, intensity, 0.0);
+    }
+
+    /// Ease from the current weather to `kind` over `seconds`
+    pub fn transition_to(&mut self, kind: WeatherKind, intensity: f32, seconds: f32) {
+        self.kind = kind;
+        self.intensity = intensity.clamp(0.0, 1.0);
+        self.from = self.mix;
+        self.duration = seconds.max(0.0);
+        self.elapsed = 0.0;
+        if self.duration <= 0.0 {
+            self.mix = WeatherMix::of(kind, self.intensity);
+        }
+    }
+
+    fn advance(&mut self, delta: f32) {
+        let target = WeatherMix::of(self.kind, self.intensity);
+        if !self.is_transitioning() {
+            self.mix = target;
+            return;
+        }
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+        let t = self.elapsed / self.duration;
+        self.mix = self.from.lerp(target, t * t * (3.0 - 2.0 * t));
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct WeatherSettings {
+    /// Drops of each kind at full intensity
+    pub max_drops: usize,
+    /// Half the width of the box drops fall in around the camera
+    pub area_radius: f32,
+    pub area_height: f32,
+    pub rain_speed: f32,
+    pub snow_speed: f32,
+    /// Sideways push on drops at full storm
+    pub storm_wind: Vec3,
+    /// Fog density each kind adds at full intensity
+    pub rain_fog: f32,
+    pub snow_fog: f32,
+    pub storm_fog: f32,
+    /// Fog color when the environment has no fog of its own
+    pub fog_color: Color,
+    /// Roughness of fully wet surfaces
+    pub wet_roughness: f32,
+    /// How much darker fully wet surfaces get
+    pub wet_darkening: f32,
+    /// Seconds to soak through in full rain, and to dry off again
+    pub soak_seconds: f32,
+    pub dry_seconds: f32,
+    /// Looping ambience for each kind; empty plays nothing
+    pub rain_sound: String,
+    pub snow_sound: String,
+    /// Wind and thunder, on top of the rain sound
+    pub storm_sound: String,
+    pub ambience_volume: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            max_drops: 1500,
+            area_radius: 18.0,
+            area_height: 14.0,
+            rain_speed: 14.0,
+            snow_speed: 1.2,
+            storm_wind: Vec3::new(5.0, 0.0, 2.0),
+            rain_fog: 0.015,
+            snow_fog: 0.035,
+            storm_fog: 0.02,
+            fog_color: Color::srgb(0.55, 0.58, 0.62),
+            wet_roughness: 0.15,
+            wet_darkening: 0.35,
+            soak_seconds: 20.0,
+            dry_seconds: 60.0,
+            rain_sound: String::new(),
+            snow_sound: String::new(),
+            storm_sound: String::new(),
+            ambience_volume: 0.8,
+        }
+    }
+}
+
+/// A raindrop or snowflake; `index` picks where in the box it falls
+#[derive(Component)]
+pub struct Precipitation {
+    snow: bool,
+    index: u32,
+}
+
+/// The looping sound for one kind of weather
+#[derive(Component)]
+pub struct WeatherAmbience(WeatherKind);
+
+#[derive(Resource)]
+pub struct PrecipitationAssets {
+    rain_mesh: Handle<Mesh>,
+    snow_mesh: Handle<Mesh>,
+    rain_material: Handle<StandardMaterial>,
+    snow_material: Handle<StandardMaterial>,
+}
+
+/// Each wet material's dry color and roughness, so drying puts them back.
+/// Edits made to a material while it is wet are lost when it dries.
+#[derive(Resource, Default)]
+pub struct WetMaterials {
+    dry: HashMap<AssetId<StandardMaterial>, (Color, f32)>,
+    applied: f32,
+}
+
+/// The weather from before play began, put back when it stops
+#[derive(Resource, Default)]
+pub struct WeatherBeforePlay(Option<Weather>);
+
+pub fn setup_weather(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(PrecipitationAssets {
+        rain_mesh: meshes.add(Cuboid::new(0.012, 0.4, 0.012)),
+        snow_mesh: meshes.add(Sphere::new(0.035).mesh().uv(6, 4)),
+        rain_material: materials.add(StandardMaterial {
+            base_color: Color::srgba(0.7, 0.75, 0.85, 0.35),
+            unlit: true,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        }),
+        snow_material: materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 1.0, 1.0, 0.9),
+            unlit: true,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        }),
+    });
+
+    for kind in [WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Storm] {
+        commands.spawn((
+            WeatherAmbience(kind),
+            TransformBundle::default(),
+            EditorHidden,
+            Name::new(format!("Weather Ambience ({})", kind.name())),
+        ));
+    }
+}
+
+pub fn update_weather(time: Res<Time>, settings: Res<WeatherSettings>, mut weather: ResMut<Weather>) {
+    let delta = time.delta_seconds();
+    let mut next = weather.clone();
+    next.advance(delta);
+
+    let target = next.mix.rain;
+    next.wetness = if next.wetness < target {
+        (next.wetness + delta / settings.soak_seconds.max(0.01)).min(target)
+    } else {
+        (next.wetness - delta / settings.dry_seconds.max(0.01)).max(target)
+    };
+
+    if *weather != next {
+        *weather = next;
+    }
+}
+
+#[derive(Default)]
+pub struct PrecipitationState {
+    /// Seconds of falling so far
+    fall: f32,
+    /// How far the wind has pushed everything so far
+    drift: Vec3,
+    spawned: usize,
+    visible: bool,
+}
+
+/// Show as many drops as the weather calls for and move them through the box
+/// around the main camera
+pub fn update_precipitation(
+    mut commands: Commands,
+    time: Res<Time>,
+    weather: Res<Weather>,
+    settings: Res<WeatherSettings>,
+    assets: Option<Res<PrecipitationAssets>>,
+    camera_settings: Res<CameraSettings>,
+    camera_query: Query<&GlobalTransform>,
+    mut drops: Query<(&Precipitation, &mut Transform, &mut Visibility)>,
+    mut state: Local<PrecipitationState>,
+) {
+    let mix = weather.mix();
+    if mix.is_clear() && !state.visible {
+        return;
+    }
+    let Some(assets) = assets else {
+        return;
+    };
+
+    // Spawn the pool the first time anything falls; it is reused afterwards
+    while state.spawned < settings.max_drops {
+        let index = state.spawned as u32;
+        for snow in [false, true] {
+            let (mesh, material) = if snow {
+                (&assets.snow_mesh, &assets.snow_material)
+            } else {
+                (&assets.rain_mesh, &assets.rain_material)
+            };
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                Precipitation { snow, index },
+                NotShadowCaster,
+                EditorHidden,
+                Name::new(if snow { "Snowflake" } else { "Raindrop" }),
+            ));
+        }
+        state.spawned += 1;
+    }
+
+    let delta = time.delta_seconds();
+    state.fall += delta;
+    state.drift += settings.storm_wind * mix.storm * delta;
+    let fall = state.fall;
+    let drift = state.drift;
+
+    let center = camera_settings
+        .main_camera_entity
+        .and_then(|camera| camera_query.get(camera).ok())
+        .map(GlobalTransform::translation)
+        .unwrap_or_default();
+    let half = Vec3::new(settings.area_radius, settings.area_height * 0.5, settings.area_radius).max(Vec3::splat(0.5));
+    let rain_count = (settings.max_drops as f32 * mix.rain).round() as u32;
+    let snow_count = (settings.max_drops as f32 * mix.snow).round() as u32;
+    let wind = settings.storm_wind * mix.storm;
+
+    let mut any_visible = false;
+    for (drop, mut transform, mut visibility) in &mut drops {
+        let count = if drop.snow { snow_count } else { rain_count };
+        let shown = drop.index < count;
+        let wanted = if shown { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        if !shown {
+            continue;
+        }
+        any_visible = true;
+
+        let seed = Vec3::new(scatter(drop.index, 1), scatter(drop.index, 2), scatter(drop.index, 3)) * half * 2.0;
+        let jitter = 0.85 + 0.3 * scatter(drop.index, 4);
+        let position = if drop.snow {
+            let phase = scatter(drop.index, 5) * std::f32::consts::TAU;
+            let sway = Vec3::new((fall * 0.9 + phase).sin(), 0.0, (fall * 0.7 + phase).cos()) * 0.3;
+            seed + Vec3::NEG_Y * settings.snow_speed * jitter * fall + drift * 0.5 + sway
+        } else {
+            transform.rotation = Quat::from_rotation_arc(
+                Vec3::Y,
+                (Vec3::Y * settings.rain_speed * jitter - wind).try_normalize().unwrap_or(Vec3::Y),
+            );
+            seed + Vec3::NEG_Y * settings.rain_speed * jitter * fall + drift
+        };
+        transform.translation = center + (position - center + half).rem_euclid(half * 2.0) - half;
+    }
+    state.visible = any_visible;
+}
+
+/// Darken wet surfaces and make them shinier, and put them back as they dry
+pub fn apply_surface_wetness(
+    weather: Res<Weather>,
+    settings: Res<WeatherSettings>,
+    mut wet: ResMut<WetMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    surfaces: Query<&Handle<StandardMaterial>, (With<Handle<Mesh>>, Without<EditorHidden>)>,
+) {
+    let wetness = weather.wetness;
+    if wetness <= 0.0 {
+        if wet.dry.is_empty() {
+            wet.applied = 0.0;
+            return;
+        }
+        for (id, (color, roughness)) in wet.dry.drain() {
+            if let Some(material) = materials.get_mut(id) {
+                material.base_color = color;
+                material.perceptual_roughness = roughness;
+            }
+        }
+        wet.applied = 0.0;
+        return;
+    }
+
+    let rewrite = wet.applied.is_nan() || (wetness - wet.applied).abs() >= 0.005;
+    let WetMaterials { dry, applied } = &mut *wet;
+    for handle in &surfaces {
+        let id = handle.id();
+        if !rewrite && dry.contains_key(&id) {
+            continue;
+        }
+        let Some(material) = materials.get_mut(id) else {
+            continue;
+        };
+        if material.unlit {
+            continue;
+        }
+        let (color, roughness) = *dry
+            .entry(id)
+            .or_insert((material.base_color, material.perceptual_roughness));
+        let darkening = 1.0 - settings.wet_darkening.clamp(0.0, 1.0) * wetness;
+        let linear = color.to_linear();
+        material.base_color = LinearRgba::new(
+            linear.red * darkening,
+            linear.green * darkening,
+            linear.blue * darkening,
+            linear.alpha,
+        )
+        .into();
+        material.perceptual_roughness = roughness.lerp(roughness.min(settings.wet_roughness), wetness);
+    }
+    if rewrite {
+        *applied = wetness;
+    }
+}
+
+/// Fade each kind's ambience with how much of it there is. Like other
+/// sounds it only plays while the game does.
+pub fn update_weather_ambience(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    settings: Res<WeatherSettings>,
+    play_state: Res<State<PlayState>>,
+    mut ambience: Query<(Entity, &WeatherAmbience, Option<&mut WaffleAudioSource>, Has<Handle<AudioSource>>)>,
+    mut play_events: EventWriter<PlayAudioEvent>,
+    mut stop_events: EventWriter<StopAudioEvent>,
+) {
+    let mix = weather.mix();
+    for (entity, ambience, source, playing) in &mut ambience {
+        let (clip, amount) = match ambience.0 {
+            WeatherKind::Rain => (&settings.rain_sound, mix.rain),
+            WeatherKind::Snow => (&settings.snow_sound, mix.snow),
+            WeatherKind::Storm => (&settings.storm_sound, mix.storm),
+            WeatherKind::Clear => continue,
+        };
+        let volume = amount * settings.ambience_volume;
+        let Some(mut source) = source else {
+            let mut source = WaffleAudioSource::new(clip.clone()).looping().non_spatial().with_volume(volume);
+            source.play_on_start = false;
+            commands.entity(entity).insert(source);
+            continue;
+        };
+        if source.clip != *clip {
+            source.clip = clip.clone();
+            if playing {
+                // Restarted with the new clip next frame
+                stop_events.send(StopAudioEvent::new(entity));
+                continue;
+            }
+        }
+        if source.volume != volume {
+            source.volume = volume;
+        }
+
+        let audible = *play_state.get() == PlayState::Playing && volume > 0.0 && !clip.is_empty();
+        if audible && !playing {
+            play_events.send(PlayAudioEvent::new(entity));
+        } else if !audible && playing {
+            stop_events.send(StopAudioEvent::new(entity));
+        }
+    }
+}
+
+/// Thicken the main cameras' fog with the weather, on top of whatever fog
+/// their environment has
+pub fn apply_weather_fog(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    settings: Res<WeatherSettings>,
+    active: Res<ActiveEnvironment>,
+    project_settings: Res<ProjectSettings>,
+    cameras: Query<(Entity, Option<Ref<EnvironmentSettings>>), With<WaffleMainCamera>>,
+    mut applied: Local<HashMap<Entity, Option<EnvironmentFogSettings>>>,
+) {
+    let mix = weather.mix();
+    let density = mix.rain * settings.rain_fog + mix.snow * settings.snow_fog + mix.storm * settings.storm_fog;
+    for (camera, camera_env) in &cameras {
+        // A changed environment has just had its plain fog put back
+        let env_changed = active.is_changed()
+            || project_settings.is_changed()
+            || camera_env.as_ref().is_some_and(|env| env.is_changed());
+        let base = camera_env.as_deref().or(active.settings.as_ref()).map(|env| env.fog);
+        let fog = weathered_fog(base, density, &settings);
+        if !env_changed && applied.get(&camera) == Some(&fog) {
+            continue;
+        }
+        match &fog {
+            Some(fog) => {
+                commands.entity(camera).insert(camera_fog(fog));
+            }
+            None => {
+                commands.entity(camera).remove::<bevy::pbr::FogSettings>();
+            }
+        }
+        applied.insert(camera, fog);
+    }
+    applied.retain(|camera, _| cameras.contains(*camera));
+}
+
+fn weathered_fog(
+    base: Option<EnvironmentFogSettings>,
+    density: f32,
+    settings: &WeatherSettings,
+) -> Option<EnvironmentFogSettings> {
+    let base = base.filter(|fog| fog.enabled);
+    if density <= 0.0 {
+        return base;
+    }
+    let Some(mut fog) = base else {
+        return Some(EnvironmentFogSettings {
+            enabled: true,
+            color: settings.fog_color,
+            mode: EnvironmentFogMode::Exponential,
+            start: 0.0,
+            end: 100.0,
+            density,
+        });
+    };
+    match fog.mode {
+        // Pull the far end in to about where exponential fog that dense
+        // would hide things
+        EnvironmentFogMode::Linear => fog.end = fog.end.min(fog.start + 3.0 / density),
+        _ => fog.density += density,
+    }
+    Some(fog)
+}
+
+pub fn save_weather_before_play(weather: Res<Weather>, mut before: ResMut<WeatherBeforePlay>) {
+    before.0 = Some(weather.clone());
+}
+
+/// Weather changed while playing doesn't stick, like the rest of the scene
+pub fn restore_weather_after_play(
+    mut weather: ResMut<Weather>,
+    mut before: ResMut<WeatherBeforePlay>,
+    mut wet: ResMut<WetMaterials>,
+) {
+    if let Some(saved) = before.0.take() {
+        *weather = saved;
+    }
+    // Play mode puts materials back as they were; rewrite them from the dry
+    // copies so they match the restored wetness
+    wet.applied = f32::NAN;
+}
+
+/// A stable pseudo-random number in `0..1` for a drop
+fn scatter(index: u32, salt: u32) -> f32 {
+    let mut x = index.wrapping_mul(0x9E37_79B9) ^ salt.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1u32 << 24) as f32
+}
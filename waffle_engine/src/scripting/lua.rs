@@ -0,0 +1,1220 @@
+// Waffle Engine Lua Runtime
+// One Lua state shared by every script; each script gets its own environment
+// so globals and callbacks never clash. Scripts see these bindings:
+//
+//   entity                              the script's entity; every id the
+//                                       bindings return or take is one of
+//                                       these, compared with ==
+//   entity:id()                         -> the id as an integer, e.g. for
+//                                       table keys
+//   entity:set_highlight(r, g, b)       outlines the entity in a color
+//   entity:set_glow(r, g, b)            surrounds the entity with a glow
+//   entity:clear_highlight()
+//   transform.get_position(id)          -> x, y, z
+//   transform.set_position(id, x, y, z)
+//   transform.get_rotation(id)          -> pitch, yaw, roll in degrees
+//   transform.set_rotation(id, pitch, yaw, roll)
+//   transform.get_scale(id)             -> x, y, z
+//   transform.set_scale(id, x, y, z)
+//   world.spawn(name)                   -> id of a new empty scene object
+//   world.despawn(id)
+//   world.find(name)                    -> id or nil
+//   camera.shake(trauma)                shakes the active camera
+//   highlight.set(id, r, g, b)          same as the entity methods
+//   highlight.glow(id, r, g, b)
+//   highlight.clear(id)
+//   weather.set(kind, intensity, seconds)  eases to "clear", "rain", "snow"
+//                                       or "storm" over `seconds`
+//   weather.get()                       -> kind, intensity
+//   tween.position(id, x, y, z, seconds, easing, options)  -> tween id;
+//                                       eases the position there, e.g. easing
+//                                       "ease_out_quad"; linear if omitted.
+//                                       options is an optional table of delay
+//                                       and repeat ("once", "loop" or
+//                                       "ping_pong")
+//   tween.rotation(id, pitch, yaw, roll, seconds, easing, options)  -> tween id
+//   tween.scale(id, x, y, z, seconds, easing, options)  -> tween id
+//   tween.shake(id, amplitude, seconds, options)  -> tween id; jitters the
+//                                       position, settling over `seconds`
+//   tween.cancel(tween_id)
+//   tween.cancel_all(id)                cancels every tween of the entity
+//   tween.is_active(tween_id)           -> true until it finishes or is cancelled
+//   timer.start(name, seconds, repeating)  restarts the named timer
+//   timer.cancel(name)
+//   timer.remaining(name)               -> seconds left, or nil if not running
+//   health.damage(id, amount, source)   damages the entity, source an id or nil
+//   health.heal(id, amount)
+//   health.get(id)                      -> current, max or nil without health
+//   projectile.spawn(x, y, z, dx, dy, dz, speed, options)  -> id of a
+//                                       projectile fired from x, y, z along
+//                                       dx, dy, dz; options is an optional
+//                                       table of gravity, lifetime, damage
+//                                       and source (the shooter's id)
+//   debug.line(x1, y1, z1, x2, y2, z2, r, g, b)  draws a line this frame;
+//                                       white if the color is omitted
+//   debug.sphere(x, y, z, radius, r, g, b)
+//   debug.text(x, y, z, text, r, g, b)  e.g. debug.text(x, y, z, "hp 34")
+//   variables.set_flag(name, on)        game variables that quests and
+//   variables.get_flag(name)            dialogue conditions test; unset flags
+//   variables.set_value(name, value)    are false and unset values zero
+//   variables.get_value(name)
+//   variables.add_value(name, amount)   -> the new value
+//   stats.increment(name, amount)       -> the stat's new value; amount
+//                                       defaults to 1
+//   stats.set(name, value)
+//   stats.record_max(name, value)       keeps the higher of the two
+//   stats.get(name)                     -> value, zero if never set
+//   achievements.unlock(id)
+//   achievements.is_unlocked(id)        -> true or false
+//   vehicle.set_input(id, throttle, brake, steer)  drives a raycast
+//                                       vehicle; throttle and steer from -1
+//                                       to 1, brake from 0 to 1. Vehicles
+//                                       with key bindings read the keys instead
+//   pool.get(name)                      -> id of an instance taken from the
+//                                       named entity pool, or nil when it is
+//                                       unknown or full
+//   pool.release(id)                    parks the instance for reuse
+//   pool.stats(name)                    -> table of total, active,
+//                                       peak_active and misses, or nil
+//   destruction.destroy(id, impulse, x, y, z)  breaks a destructible apart;
+//                                       chunks fly away from x, y, z, or the
+//                                       center when the point is omitted
+//   animation.set_float(id, name, value)  sets a state machine parameter
+//   animation.set_bool(id, name, value)
+//   animation.trigger(id, name)         fires the next transition waiting on it
+//   animation.state(id)                 -> name of the playing state or nil
+//   log.info(...), log.warn(...), log.error(...)
+//   random.float()                      -> value in [0, 1) from the engine's
+//                                       seeded random source
+//   random.range(min, max)              -> value in [min, max)
+//   random.range_int(min, max)          -> integer in [min, max]
+//   random.chance(probability)          -> true or false
+//   random.choice(list)                 -> random item of a list, nil if empty
+//   random.weighted_choice(items, weights)  -> item picked in proportion to
+//                                       the weight at the same index
+//   random.new(seed)                    -> generator with the same methods,
+//                                       e.g. rng:range(1, 6)
+//   random.fork(name)                   -> generator seeded from the engine's
+//                                       seed and a name, so reseeding the
+//                                       engine replays it too
+//   noise.new(seed)                     -> noise with :perlin2(x, y),
+//                                       :perlin3(x, y, z), :simplex2(x, y) and
+//                                       :fbm2(x, y, octaves, lacunarity, gain)
+//   analytics.event(name, properties)   records an analytics event if the
+//                                       player opted in, e.g.
+//                                       analytics.event("level_complete", {time=123})
+//   ai.register_action(name, fn)        behavior tree action; fn(id, dt)
+//                                       returns "success", "failure" or
+//                                       "running", nil counting as success
+//   ai.register_condition(name, fn)     behavior tree condition; fn(id, dt)
+//                                       returns true or false
+//
+// Besides `on_start()` and `on_update(dt)`, a script may define callbacks for
+// engine events; each runs once per event, before the next `on_update`:
+//
+//   on_dialogue_start(graph, id)        a dialogue began; id of the entity it
+//                                       was started on, or nil
+//   on_dialogue_line(node, speaker, text)  the dialogue entered a node
+//   on_dialogue_choice(node, choice)    the player picked a choice, counting
+//                                       from 1
+//   on_dialogue_end(graph, id)
+//   on_death(killer)                    this script's entity died; killer is
+//                                       an id or nil
+//   on_interact(x, y, z)                the player used this script's entity,
+//                                       aiming at the point x, y, z
+//   on_tween_complete(tween_id)         a tween of this script's entity
+//                                       finished
+//   on_timer(name)                      a named timer ran out
+//
+// Log output goes to the editor console. A script that errors or panics stops
+// until its file changes, which reloads it and runs `on_start` again.
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use mlua::{
+    FromLua, FromLuaMulti, Function, IntoLua, IntoLuaMulti, Lua, MetaMethod, MultiValue, RegistryKey, Table, UserData,
+    UserDataMethods, Value, Variadic,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::{LuaScript, LuaScriptAsset};
+use crate::core::achievements::Achievements;
+use crate::core::ai::{BehaviorContext, BehaviorRegistry, LeafKind, NodeStatus};
+use crate::core::analytics::Analytics;
+use crate::core::components::PlaySpawned;
+use crate::core::debug_draw::{DebugDrawQueue, DebugShape, DebugText};
+use crate::core::destruction::DestroyEvent;
+use crate::core::dialogue::DialogueEvent;
+use crate::core::guard::catch_panic;
+use crate::core::health::{DamageEvent, DeathEvent, HealEvent, Health};
+use crate::core::interaction::InteractEvent;
+use crate::core::pool::Pool;
+use crate::core::projectile::{projectile_bundle, Projectile};
+use crate::core::random::{GlobalRng, Noise, WaffleRng};
+use crate::core::state_machine::AnimationStateMachine;
+use crate::core::tween::{
+    Easing, GameTimers, TimerFinishedEvent, Tween, TweenCompletedEvent, TweenId, TweenRepeat, Tweens,
+};
+use crate::core::variables::GameVariables;
+use crate::core::vehicle::VehicleInput;
+use crate::rendering::camera_shake::CameraShakeEvent;
+use crate::rendering::highlight::Highlight;
+use crate::rendering::scene::WaffleSceneObject;
+use crate::rendering::weather::{Weather, WeatherKind};
+
+/// Log target the editor console shows
+const LUA_LOG_TARGET: &str = "lua";
+
+pub struct LuaRuntime {
+    lua: Lua,
+    instances: HashMap<Entity, ScriptInstance>,
+}
+
+struct ScriptInstance {
+    path: String,
+    handle: Handle<LuaScriptAsset>,
+    /// The script's environment table once its chunk has run
+    environment: Option<RegistryKey>,
+    started: bool,
+    failed: bool,
+}
+
+/// A script callback to run for an engine event
+struct ScriptCallback {
+    /// Only this entity's script, or every script for `None`
+    target: Option<Entity>,
+    name: &'static str,
+    args: Vec<CallbackArg>,
+}
+
+#[derive(Clone)]
+enum CallbackArg {
+    Nil,
+    Id(Entity),
+    Int(i64),
+    Number(f32),
+    Text(String),
+}
+
+impl<'lua> IntoLua<'lua> for CallbackArg {
+    fn into_lua(self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+        match self {
+            CallbackArg::Nil => Ok(Value::Nil),
+            CallbackArg::Id(entity) => ScriptEntity(entity).into_lua(lua),
+            CallbackArg::Int(value) => Ok(Value::Integer(value)),
+            CallbackArg::Number(value) => Ok(Value::Number(value as f64)),
+            CallbackArg::Text(text) => text.into_lua(lua),
+        }
+    }
+}
+
+impl From<Option<Entity>> for CallbackArg {
+    fn from(entity: Option<Entity>) -> Self {
+        entity.map_or(CallbackArg::Nil, CallbackArg::Id)
+    }
+}
+
+/// Engine events scripts have callbacks for, read since the last frame
+#[derive(Default)]
+pub struct ScriptEventReaders {
+    dialogue: ManualEventReader<DialogueEvent>,
+    deaths: ManualEventReader<DeathEvent>,
+    interactions: ManualEventReader<InteractEvent>,
+    tweens: ManualEventReader<TweenCompletedEvent>,
+    timers: ManualEventReader<TimerFinishedEvent>,
+}
+
+impl ScriptEventReaders {
+    fn read(&mut self, world: &World) -> Vec<ScriptCallback> {
+        let mut callbacks = Vec::new();
+        for event in self.dialogue.read(world.resource::<Events<DialogueEvent>>()) {
+            let (name, args) = match event {
+                DialogueEvent::Started { graph, entity } => {
+                    ("on_dialogue_start", vec![CallbackArg::Text(graph.clone()), (*entity).into()])
+                }
+                DialogueEvent::Line { node, speaker, text } => (
+                    "on_dialogue_line",
+                    vec![
+                        CallbackArg::Text(node.clone()),
+                        CallbackArg::Text(speaker.clone()),
+                        CallbackArg::Text(text.clone()),
+                    ],
+                ),
+                DialogueEvent::Chose { node, choice } => (
+                    "on_dialogue_choice",
+                    vec![CallbackArg::Text(node.clone()), CallbackArg::Int(*choice as i64 + 1)],
+                ),
+                DialogueEvent::Ended { graph, entity } => {
+                    ("on_dialogue_end", vec![CallbackArg::Text(graph.clone()), (*entity).into()])
+                }
+            };
+            callbacks.push(ScriptCallback { target: None, name, args });
+        }
+        for death in self.deaths.read(world.resource::<Events<DeathEvent>>()) {
+            callbacks.push(ScriptCallback {
+                target: Some(death.entity),
+                name: "on_death",
+                args: vec![death.killer.into()],
+            });
+        }
+        for interaction in self.interactions.read(world.resource::<Events<InteractEvent>>()) {
+            let point = interaction.point;
+            callbacks.push(ScriptCallback {
+                target: Some(interaction.entity),
+                name: "on_interact",
+                args: vec![CallbackArg::Number(point.x), CallbackArg::Number(point.y), CallbackArg::Number(point.z)],
+            });
+        }
+        for completed in self.tweens.read(world.resource::<Events<TweenCompletedEvent>>()) {
+            callbacks.push(ScriptCallback {
+                target: Some(completed.entity),
+                name: "on_tween_complete",
+                args: vec![CallbackArg::Int(completed.id.0 as i64)],
+            });
+        }
+        for finished in self.timers.read(world.resource::<Events<TimerFinishedEvent>>()) {
+            callbacks.push(ScriptCallback {
+                target: None,
+                name: "on_timer",
+                args: vec![CallbackArg::Text(finished.name.clone())],
+            });
+        }
+        callbacks
+    }
+}
+
+impl Default for LuaRuntime {
+    fn default() -> Self {
+        let lua = Lua::new();
+        if let Err(err) = register_log_bindings(&lua) {
+            error!("Failed to set up Lua logging: {}", err);
+        }
+        if let Err(err) = register_random_bindings(&lua) {
+            error!("Failed to set up Lua random numbers: {}", err);
+        }
+        if let Err(err) = register_ai_bindings(&lua) {
+            error!("Failed to set up Lua behavior tree leaves: {}", err);
+        }
+        Self {
+            lua,
+            instances: HashMap::new(),
+        }
+    }
+}
+
+fn register_log_bindings(lua: &Lua) -> mlua::Result<()> {
+    let log = lua.create_table()?;
+    log.set("info", lua.create_function(|lua, args: Variadic<mlua::Value>| {
+        info!(target: LUA_LOG_TARGET, "{}", join_args(lua, args)?);
+        Ok(())
+    })?)?;
+    log.set("warn", lua.create_function(|lua, args: Variadic<mlua::Value>| {
+        warn!(target: LUA_LOG_TARGET, "{}", join_args(lua, args)?);
+        Ok(())
+    })?)?;
+    log.set("error", lua.create_function(|lua, args: Variadic<mlua::Value>| {
+        error!(target: LUA_LOG_TARGET, "{}", join_args(lua, args)?);
+        Ok(())
+    })?)?;
+    lua.globals().set("log", log)
+}
+
+/// `random.new` and `noise.new`; the engine-wide `random.*` functions need the
+/// world and are added each frame
+fn register_random_bindings(lua: &Lua) -> mlua::Result<()> {
+    let random = lua.create_table()?;
+    random.set("new", lua.create_function(|_, seed: u64| Ok(WaffleRng::new(seed)))?)?;
+    lua.globals().set("random", random)?;
+
+    let noise = lua.create_table()?;
+    noise.set("new", lua.create_function(|_, seed: u64| Ok(Noise::new(seed)))?)?;
+    lua.globals().set("noise", noise)
+}
+
+impl UserData for WaffleRng {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut("float", |_, rng, ()| Ok(rng.next_f32()));
+        methods.add_method_mut("range", |_, rng, (min, max): (f32, f32)| Ok(rng.range_f32(min, max)));
+        methods.add_method_mut("range_int", |_, rng, (min, max): (i32, i32)| Ok(rng.range_i32(min, max)));
+        methods.add_method_mut("chance", |_, rng, probability: f32| Ok(rng.chance(probability)));
+        methods.add_method_mut("choice", |_, rng, list: Table| choose_item(rng, list));
+        methods.add_method_mut("weighted_choice", |_, rng, (items, weights): (Table, Table)| {
+            choose_weighted_item(rng, items, weights)
+        });
+    }
+}
+
+impl UserData for Noise {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("perlin2", |_, noise, (x, y): (f32, f32)| Ok(noise.perlin2(x, y)));
+        methods.add_method("perlin3", |_, noise, (x, y, z): (f32, f32, f32)| Ok(noise.perlin3(x, y, z)));
+        methods.add_method("simplex2", |_, noise, (x, y): (f32, f32)| Ok(noise.simplex2(x, y)));
+        methods.add_method(
+            "fbm2",
+            |_, noise, (x, y, octaves, lacunarity, gain): (f32, f32, Option<u32>, Option<f32>, Option<f32>)| {
+                Ok(noise.fbm2(x, y, octaves.unwrap_or(4), lacunarity.unwrap_or(2.0), gain.unwrap_or(0.5)))
+            },
+        );
+    }
+}
+
+/// An entity as scripts see it: `entity`, ids returned by the bindings and
+/// ids passed to callbacks. Bindings that take an id also accept a plain
+/// integer from `entity:id()`. Not `Clone`, which would swap this `FromLua`
+/// for mlua's userdata-only one
+#[derive(PartialEq, Eq)]
+struct ScriptEntity(Entity);
+
+impl std::fmt::Display for ScriptEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_bits())
+    }
+}
+
+impl UserData for ScriptEntity {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("id", |_, entity, ()| Ok(entity.0.to_bits()));
+        // The world is only reachable through this frame's bindings
+        methods.add_method("set_highlight", |lua, entity, (r, g, b): (f32, f32, f32)| {
+            call_binding(lua, "highlight", "set", (ScriptEntity(entity.0), r, g, b))
+        });
+        methods.add_method("set_glow", |lua, entity, (r, g, b): (f32, f32, f32)| {
+            call_binding(lua, "highlight", "glow", (ScriptEntity(entity.0), r, g, b))
+        });
+        methods.add_method("clear_highlight", |lua, entity, ()| call_binding(lua, "highlight", "clear", ScriptEntity(entity.0)));
+        methods.add_meta_method(MetaMethod::Eq, |_, entity, other: ScriptEntity| Ok(*entity == other));
+        methods.add_meta_method(MetaMethod::ToString, |_, entity, ()| Ok(entity.to_string()));
+    }
+}
+
+impl<'lua> FromLua<'lua> for ScriptEntity {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        match value {
+            Value::UserData(data) => Ok(ScriptEntity(data.borrow::<ScriptEntity>()?.0)),
+            value => entity_from_id(u64::from_lua(value, lua)?).map(ScriptEntity),
+        }
+    }
+}
+
+fn call_binding<'lua>(lua: &'lua Lua, table: &str, name: &str, args: impl IntoLuaMulti<'lua>) -> mlua::Result<()> {
+    let table: Table = lua.globals().get(table)?;
+    table.get::<_, Function>(name)?.call(args)
+}
+
+/// A random item of a Lua list, or nil for an empty one
+fn choose_item<'lua>(rng: &mut WaffleRng, list: Table<'lua>) -> mlua::Result<Value<'lua>> {
+    let len = list.raw_len();
+    if len == 0 {
+        return Ok(Value::Nil);
+    }
+    list.raw_get(rng.range_i32(1, len as i32))
+}
+
+/// An item of `items` picked in proportion to the weight at the same index
+fn choose_weighted_item<'lua>(
+    rng: &mut WaffleRng,
+    items: Table<'lua>,
+    weights: Table<'lua>,
+) -> mlua::Result<Value<'lua>> {
+    let weighted = items
+        .sequence_values::<Value>()
+        .zip(weights.sequence_values::<f32>())
+        .map(|(item, weight)| Ok((item?, weight?)))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    Ok(rng.weighted_choice(&weighted).cloned().unwrap_or(Value::Nil))
+}
+
+/// Registry table holding the leaves scripts registered of each kind
+fn leaf_table_key(kind: LeafKind) -> &'static str {
+    match kind {
+        LeafKind::Action => "waffle_ai_actions",
+        LeafKind::Condition => "waffle_ai_conditions",
+    }
+}
+
+/// `ai.register_action` and `ai.register_condition`; leaves are kept by name
+/// and run when a behavior tree reaches one no Rust callback provides
+fn register_ai_bindings(lua: &Lua) -> mlua::Result<()> {
+    let ai = lua.create_table()?;
+    for kind in [LeafKind::Action, LeafKind::Condition] {
+        lua.set_named_registry_value(leaf_table_key(kind), lua.create_table()?)?;
+        ai.set(format!("register_{}", kind.name()), lua.create_function(
+            move |lua, (name, leaf): (String, Function)| {
+                lua.named_registry_value::<Table>(leaf_table_key(kind))?.set(name, leaf)
+            },
+        )?)?;
+    }
+    lua.globals().set("ai", ai)
+}
+
+/// Let behavior trees fall back to leaves registered from scripts
+pub fn install_behavior_leaves(mut registry: ResMut<BehaviorRegistry>) {
+    registry.script_leaves = Some(run_behavior_leaf);
+}
+
+/// Run the script leaf with this name, if one is registered. A leaf that
+/// errors is dropped until its script registers it again.
+fn run_behavior_leaf(kind: LeafKind, name: &str, context: &mut BehaviorContext) -> Option<NodeStatus> {
+    let runtime = context.world.remove_non_send_resource::<LuaRuntime>()?;
+    let status = match call_behavior_leaf(&runtime.lua, kind, name, context) {
+        Ok(status) => status,
+        Err(err) => {
+            error!(target: LUA_LOG_TARGET, "{} '{}': {}", kind.name(), name, err);
+            if let Ok(leaves) = runtime.lua.named_registry_value::<Table>(leaf_table_key(kind)) {
+                let _ = leaves.set(name, Value::Nil);
+            }
+            Some(NodeStatus::Failure)
+        }
+    };
+    context.world.insert_non_send_resource(runtime);
+    status
+}
+
+fn call_behavior_leaf(
+    lua: &Lua,
+    kind: LeafKind,
+    name: &str,
+    context: &mut BehaviorContext,
+) -> mlua::Result<Option<NodeStatus>> {
+    let leaves: Table = lua.named_registry_value(leaf_table_key(kind))?;
+    let Some(leaf) = leaves.get::<_, Option<Function>>(name)? else {
+        return Ok(None);
+    };
+    let args = (ScriptEntity(context.entity), context.delta);
+    let world = RefCell::new(&mut *context.world);
+    let status = lua.scope(|scope| {
+        register_world_bindings(lua, scope, &world)?;
+        leaf_status(kind, leaf.call(args)?)
+    })?;
+    Ok(Some(status))
+}
+
+fn leaf_status(kind: LeafKind, value: Value) -> mlua::Result<NodeStatus> {
+    Ok(match value {
+        Value::String(status) if kind == LeafKind::Action => match status.to_str()? {
+            "success" => NodeStatus::Success,
+            "failure" => NodeStatus::Failure,
+            "running" => NodeStatus::Running,
+            other => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "'{}' is not a status; return \"success\", \"failure\" or \"running\"",
+                    other
+                )))
+            }
+        },
+        Value::Nil if kind == LeafKind::Action => NodeStatus::Success,
+        Value::Nil | Value::Boolean(false) => NodeStatus::Failure,
+        _ => NodeStatus::Success,
+    })
+}
+
+/// Arguments joined with spaces, the way `print` does
+fn join_args(lua: &Lua, args: Variadic<mlua::Value>) -> mlua::Result<String> {
+    let tostring: Function = lua.globals().get("tostring")?;
+    let parts = args
+        .into_iter()
+        .map(|value| tostring.call::<_, String>(value))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    Ok(parts.join(" "))
+}
+
+/// Restart scripts whose file changed on disk
+pub fn reload_modified_lua_scripts(
+    mut events: EventReader<AssetEvent<LuaScriptAsset>>,
+    mut runtime: NonSendMut<LuaRuntime>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let runtime = &mut *runtime;
+        for instance in runtime.instances.values_mut() {
+            if instance.handle.id() == *id {
+                if let Some(key) = instance.environment.take() {
+                    let _ = runtime.lua.remove_registry_value(key);
+                }
+                instance.started = false;
+                instance.failed = false;
+            }
+        }
+    }
+}
+
+/// Scripts start over the next time play begins, and register their leaves again
+pub fn stop_lua_scripts(mut runtime: NonSendMut<LuaRuntime>) {
+    let runtime = &mut *runtime;
+    for (_, instance) in runtime.instances.drain() {
+        if let Some(key) = instance.environment {
+            let _ = runtime.lua.remove_registry_value(key);
+        }
+    }
+    for kind in [LeafKind::Action, LeafKind::Condition] {
+        if let Ok(leaves) = runtime.lua.create_table() {
+            let _ = runtime.lua.set_named_registry_value(leaf_table_key(kind), leaves);
+        }
+    }
+    runtime.lua.expire_registry_values();
+}
+
+pub fn run_lua_scripts(world: &mut World, mut event_readers: Local<ScriptEventReaders>) {
+    let Some(mut runtime) = world.remove_non_send_resource::<LuaRuntime>() else {
+        return;
+    };
+    let delta = world.resource::<Time>().delta_seconds();
+    let callbacks = event_readers.read(world);
+
+    let mut script_query = world.query::<(Entity, &LuaScript)>();
+    let scripts: Vec<(Entity, LuaScript)> = script_query
+        .iter(world)
+        .map(|(entity, script)| (entity, script.clone()))
+        .collect();
+
+    // Drop instances whose script was removed or pointed elsewhere
+    runtime.instances.retain(|entity, instance| {
+        scripts
+            .iter()
+            .any(|(script_entity, script)| script_entity == entity && script.path == instance.path)
+    });
+    for (entity, script) in &scripts {
+        if script.path.is_empty() || runtime.instances.contains_key(entity) {
+            continue;
+        }
+        let handle = world.resource::<AssetServer>().load(script.path.clone());
+        runtime.instances.insert(*entity, ScriptInstance {
+            path: script.path.clone(),
+            handle,
+            environment: None,
+            started: false,
+            failed: false,
+        });
+    }
+
+    let lua = &runtime.lua;
+    let instances = &mut runtime.instances;
+    let world_cell = RefCell::new(&mut *world);
+    let result = lua.scope(|scope| {
+        register_world_bindings(lua, scope, &world_cell)?;
+
+        for (entity, script) in &scripts {
+            let Some(instance) = instances.get_mut(entity) else {
+                continue;
+            };
+            if !script.enabled || instance.failed {
+                continue;
+            }
+            // A panic in an engine binding stops the script like a Lua error
+            // instead of losing the runtime with it
+            let message = match catch_panic(|| run_instance(lua, *entity, instance, delta, &callbacks, &world_cell)) {
+                Ok(Ok(())) => continue,
+                Ok(Err(err)) => err.to_string(),
+                Err(panic) => format!("panicked: {}", panic),
+            };
+            error!(target: LUA_LOG_TARGET, "{}: {}", instance.path, message);
+            instance.failed = true;
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        error!("Failed to set up Lua bindings: {}", err);
+    }
+
+    world.insert_non_send_resource(runtime);
+}
+
+fn run_instance(
+    lua: &Lua,
+    entity: Entity,
+    instance: &mut ScriptInstance,
+    delta: f32,
+    callbacks: &[ScriptCallback],
+    world: &RefCell<&mut World>,
+) -> mlua::Result<()> {
+    if instance.environment.is_none() {
+        let source = {
+            let world = world.borrow();
+            let assets = world.resource::<Assets<LuaScriptAsset>>();
+            match assets.get(&instance.handle) {
+                Some(asset) => asset.source.clone(),
+                // Still loading
+                None => return Ok(()),
+            }
+        };
+        let environment = lua.create_table()?;
+        let fallback = lua.create_table()?;
+        fallback.set("__index", lua.globals())?;
+        environment.set_metatable(Some(fallback));
+        environment.set("entity", ScriptEntity(entity))?;
+        lua.load(source.as_str())
+            .set_name(instance.path.clone())
+            .set_environment(environment.clone())
+            .exec()?;
+        instance.environment = Some(lua.create_registry_value(environment)?);
+    }
+
+    let Some(key) = &instance.environment else {
+        return Ok(());
+    };
+    let environment: Table = lua.registry_value(key)?;
+    if !instance.started {
+        instance.started = true;
+        if let Some(on_start) = environment.raw_get::<_, Option<Function>>("on_start")? {
+            on_start.call::<_, ()>(())?;
+        }
+    }
+    for callback in callbacks {
+        if callback.target.is_some_and(|target| target != entity) {
+            continue;
+        }
+        if let Some(function) = environment.raw_get::<_, Option<Function>>(callback.name)? {
+            function.call::<_, ()>(callback.args.iter().cloned().collect::<Variadic<_>>())?;
+        }
+    }
+    if let Some(on_update) = environment.raw_get::<_, Option<Function>>("on_update")? {
+        on_update.call::<_, ()>(delta)?;
+    }
+    Ok(())
+}
+
+/// Bindings that touch the world; they only live for this frame's scope
+fn register_world_bindings<'lua, 'scope>(
+    lua: &'lua Lua,
+    scope: &mlua::Scope<'lua, 'scope>,
+    world: &'scope RefCell<&mut World>,
+) -> mlua::Result<()>
+where
+    'lua: 'scope,
+{
+    let transform = lua.create_table()?;
+    transform.set("get_position", scope.create_function(move |_, id: ScriptEntity| {
+        let translation = read_transform(world, id)?.translation;
+        Ok((translation.x, translation.y, translation.z))
+    })?)?;
+    transform.set("set_position", scope.create_function(move |_, (id, x, y, z): (ScriptEntity, f32, f32, f32)| {
+        write_transform(world, id, |transform| transform.translation = Vec3::new(x, y, z))
+    })?)?;
+    transform.set("get_rotation", scope.create_function(move |_, id: ScriptEntity| {
+        let (yaw, pitch, roll) = read_transform(world, id)?.rotation.to_euler(EulerRot::YXZ);
+        Ok((pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees()))
+    })?)?;
+    transform.set("set_rotation", scope.create_function(move |_, (id, pitch, yaw, roll): (ScriptEntity, f32, f32, f32)| {
+        write_transform(world, id, |transform| {
+            transform.rotation =
+                Quat::from_euler(EulerRot::YXZ, yaw.to_radians(), pitch.to_radians(), roll.to_radians());
+        })
+    })?)?;
+    transform.set("get_scale", scope.create_function(move |_, id: ScriptEntity| {
+        let scale = read_transform(world, id)?.scale;
+        Ok((scale.x, scale.y, scale.z))
+    })?)?;
+    transform.set("set_scale", scope.create_function(move |_, (id, x, y, z): (ScriptEntity, f32, f32, f32)| {
+        write_transform(world, id, |transform| transform.scale = Vec3::new(x, y, z))
+    })?)?;
+    lua.globals().set("transform", transform)?;
+
+    let world_table = lua.create_table()?;
+    world_table.set("spawn", scope.create_function(move |_, name: Option<String>| {
+        let mut world = world.borrow_mut();
+        let mut entity = world.spawn((WaffleSceneObject, PlaySpawned, SpatialBundle::default()));
+        if let Some(name) = name {
+            entity.insert(Name::new(name));
+        }
+        Ok(ScriptEntity(entity.id()))
+    })?)?;
+    world_table.set("despawn", scope.create_function(move |_, id: ScriptEntity| {
+        let entity = id.0;
+        let mut world = world.borrow_mut();
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+        Ok(())
+    })?)?;
+    world_table.set("find", scope.create_function(move |_, name: String| {
+        let mut world = world.borrow_mut();
+        let mut names = world.query::<(Entity, &Name)>();
+        Ok(names
+            .iter(&world)
+            .find(|(_, entity_name)| entity_name.as_str() == name)
+            .map(|(entity, _)| ScriptEntity(entity)))
+    })?)?;
+    lua.globals().set("world", world_table)?;
+
+    let camera = lua.create_table()?;
+    camera.set("shake", scope.create_function(move |_, trauma: f32| {
+        world.borrow_mut().send_event(CameraShakeEvent { camera: None, trauma });
+        Ok(())
+    })?)?;
+    lua.globals().set("camera", camera)?;
+
+    let highlight = lua.create_table()?;
+    highlight.set("set", scope.create_function(move |_, (id, r, g, b): (ScriptEntity, f32, f32, f32)| {
+        set_highlight(world, id, Some(Highlight::outline(Color::srgb(r, g, b))))
+    })?)?;
+    highlight.set("glow", scope.create_function(move |_, (id, r, g, b): (ScriptEntity, f32, f32, f32)| {
+        set_highlight(world, id, Some(Highlight::glow(Color::srgb(r, g, b))))
+    })?)?;
+    highlight.set("clear", scope.create_function(move |_, id: ScriptEntity| set_highlight(world, id, None))?)?;
+    lua.globals().set("highlight", highlight)?;
+
+    let weather = lua.create_table()?;
+    weather.set("set", scope.create_function(
+        move |_, (kind, intensity, seconds): (String, Option<f32>, Option<f32>)| {
+            let kind = WeatherKind::from_name(&kind)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("{} is not a kind of weather", kind)))?;
+            world
+                .borrow_mut()
+                .resource_mut::<Weather>()
+                .transition_to(kind, intensity.unwrap_or(1.0), seconds.unwrap_or(0.0));
+            Ok(())
+        },
+    )?)?;
+    weather.set("get", scope.create_function(move |_, ()| {
+        let world = world.borrow();
+        let weather = world.resource::<Weather>();
+        Ok((weather.kind().name(), weather.intensity()))
+    })?)?;
+    lua.globals().set("weather", weather)?;
+
+    let tween = lua.create_table()?;
+    tween.set("position", scope.create_function(
+        move |_, (id, x, y, z, seconds, easing, options): (ScriptEntity, f32, f32, f32, f32, Option<String>, Option<Table>)| {
+            let tween = Tween::position(id.0, Vec3::new(x, y, z), seconds, easing_from_name(easing)?);
+            let tween = tween_with_options(tween, options)?;
+            Ok(world.borrow_mut().resource_mut::<Tweens>().add(tween).0)
+        },
+    )?)?;
+    tween.set("rotation", scope.create_function(
+        move |_, (id, pitch, yaw, roll, seconds, easing, options): (ScriptEntity, f32, f32, f32, f32, Option<String>, Option<Table>)| {
+            let target = Quat::from_euler(EulerRot::YXZ, yaw.to_radians(), pitch.to_radians(), roll.to_radians());
+            let tween = Tween::rotation(id.0, target, seconds, easing_from_name(easing)?);
+            let tween = tween_with_options(tween, options)?;
+            Ok(world.borrow_mut().resource_mut::<Tweens>().add(tween).0)
+        },
+    )?)?;
+    tween.set("scale", scope.create_function(
+        move |_, (id, x, y, z, seconds, easing, options): (ScriptEntity, f32, f32, f32, f32, Option<String>, Option<Table>)| {
+            let tween = Tween::scale(id.0, Vec3::new(x, y, z), seconds, easing_from_name(easing)?);
+            let tween = tween_with_options(tween, options)?;
+            Ok(world.borrow_mut().resource_mut::<Tweens>().add(tween).0)
+        },
+    )?)?;
+    tween.set("shake", scope.create_function(
+        move |_, (id, amplitude, seconds, options): (ScriptEntity, f32, f32, Option<Table>)| {
+            let tween = tween_with_options(Tween::shake(id.0, amplitude, seconds), options)?;
+            Ok(world.borrow_mut().resource_mut::<Tweens>().add(tween).0)
+        },
+    )?)?;
+    tween.set("cancel", scope.create_function(move |_, tween_id: u64| {
+        world.borrow_mut().resource_mut::<Tweens>().cancel(TweenId(tween_id));
+        Ok(())
+    })?)?;
+    tween.set("cancel_all", scope.create_function(move |_, id: ScriptEntity| {
+        world.borrow_mut().resource_mut::<Tweens>().cancel_entity(id.0);
+        Ok(())
+    })?)?;
+    tween.set("is_active", scope.create_function(move |_, tween_id: u64| {
+        Ok(world.borrow().resource::<Tweens>().is_active(TweenId(tween_id)))
+    })?)?;
+    lua.globals().set("tween", tween)?;
+
+    let timer = lua.create_table()?;
+    timer.set("start", scope.create_function(move |_, (name, seconds, repeating): (String, f32, Option<bool>)| {
+        world.borrow_mut().resource_mut::<GameTimers>().start(name, seconds, repeating.unwrap_or(false));
+        Ok(())
+    })?)?;
+    timer.set("cancel", scope.create_function(move |_, name: String| {
+        world.borrow_mut().resource_mut::<GameTimers>().cancel(&name);
+        Ok(())
+    })?)?;
+    timer.set("remaining", scope.create_function(move |_, name: String| {
+        Ok(world.borrow().resource::<GameTimers>().remaining(&name))
+    })?)?;
+    lua.globals().set("timer", timer)?;
+
+    let health = lua.create_table()?;
+    health.set("damage", scope.create_function(move |_, (id, amount, source): (ScriptEntity, f32, Option<ScriptEntity>)| {
+        let target = id.0;
+        let source = source.map(|source| source.0);
+        world.borrow_mut().send_event(DamageEvent { target, amount, source });
+        Ok(())
+    })?)?;
+    health.set("heal", scope.create_function(move |_, (id, amount): (ScriptEntity, f32)| {
+        world.borrow_mut().send_event(HealEvent { target: id.0, amount });
+        Ok(())
+    })?)?;
+    health.set("get", scope.create_function(move |_, id: ScriptEntity| {
+        let entity = id.0;
+        Ok(world
+            .borrow()
+            .get::<Health>(entity)
+            .map_or((None, None), |health| (Some(health.current), Some(health.max))))
+    })?)?;
+    lua.globals().set("health", health)?;
+
+    let projectile = lua.create_table()?;
+    projectile.set("spawn", scope.create_function(
+        move |_, (x, y, z, dx, dy, dz, speed, options): (f32, f32, f32, f32, f32, f32, f32, Option<Table>)| {
+            let mut projectile = Projectile::new(Vec3::new(dx, dy, dz), speed);
+            if let Some(options) = options {
+                if let Some(gravity) = options.get::<_, Option<f32>>("gravity")? {
+                    projectile = projectile.with_gravity(gravity);
+                }
+                if let Some(lifetime) = options.get::<_, Option<f32>>("lifetime")? {
+                    projectile = projectile.with_lifetime(lifetime);
+                }
+                let source = options.get::<_, Option<ScriptEntity>>("source")?.map(|source| source.0);
+                let damage = options.get::<_, Option<f32>>("damage")?.unwrap_or(0.0);
+                projectile = projectile.with_damage(damage, source);
+            }
+            let bundle = projectile_bundle(Vec3::new(x, y, z), projectile);
+            Ok(ScriptEntity(world.borrow_mut().spawn(bundle).id()))
+        },
+    )?)?;
+    lua.globals().set("projectile", projectile)?;
+
+    // Added to Lua's own debug library rather than replacing it
+    let debug = match lua.globals().get::<_, Option<Table>>("debug")? {
+        Some(debug) => debug,
+        None => lua.create_table()?,
+    };
+    debug.set("line", scope.create_function(
+        move |_, (x1, y1, z1, x2, y2, z2, color): (f32, f32, f32, f32, f32, f32, DebugColor)| {
+            queue_debug_shape(world, DebugShape::Line {
+                start: Vec3::new(x1, y1, z1),
+                end: Vec3::new(x2, y2, z2),
+                color: color.0,
+            });
+            Ok(())
+        },
+    )?)?;
+    debug.set("sphere", scope.create_function(move |_, (x, y, z, radius, color): (f32, f32, f32, f32, DebugColor)| {
+        queue_debug_shape(world, DebugShape::Sphere {
+            position: Vec3::new(x, y, z),
+            radius,
+            color: color.0,
+        });
+        Ok(())
+    })?)?;
+    debug.set("text", scope.create_function(move |_, (x, y, z, text, color): (f32, f32, f32, String, DebugColor)| {
+        queue_debug_shape(world, DebugShape::Text(DebugText {
+            position: Vec3::new(x, y, z),
+            text,
+            color: color.0,
+        }));
+        Ok(())
+    })?)?;
+    lua.globals().set("debug", debug)?;
+
+    let variables = lua.create_table()?;
+    variables.set("set_flag", scope.create_function(move |_, (name, on): (String, bool)| {
+        world.borrow_mut().resource_mut::<GameVariables>().set_flag(name, on);
+        Ok(())
+    })?)?;
+    variables.set("get_flag", scope.create_function(move |_, name: String| {
+        Ok(world.borrow().resource::<GameVariables>().flag(&name))
+    })?)?;
+    variables.set("set_value", scope.create_function(move |_, (name, value): (String, f32)| {
+        world.borrow_mut().resource_mut::<GameVariables>().set_value(name, value);
+        Ok(())
+    })?)?;
+    variables.set("get_value", scope.create_function(move |_, name: String| {
+        Ok(world.borrow().resource::<GameVariables>().value(&name))
+    })?)?;
+    variables.set("add_value", scope.create_function(move |_, (name, amount): (String, f32)| {
+        Ok(world.borrow_mut().resource_mut::<GameVariables>().add_value(name, amount))
+    })?)?;
+    lua.globals().set("variables", variables)?;
+
+    let stats = lua.create_table()?;
+    stats.set("increment", scope.create_function(move |_, (name, amount): (String, Option<f32>)| {
+        Ok(world.borrow_mut().resource_mut::<Achievements>().increment(&name, amount.unwrap_or(1.0)))
+    })?)?;
+    stats.set("set", scope.create_function(move |_, (name, value): (String, f32)| {
+        world.borrow_mut().resource_mut::<Achievements>().set_stat(&name, value);
+        Ok(())
+    })?)?;
+    stats.set("record_max", scope.create_function(move |_, (name, value): (String, f32)| {
+        world.borrow_mut().resource_mut::<Achievements>().record_max(&name, value);
+        Ok(())
+    })?)?;
+    stats.set("get", scope.create_function(move |_, name: String| {
+        Ok(world.borrow().resource::<Achievements>().stat(&name))
+    })?)?;
+    lua.globals().set("stats", stats)?;
+
+    let achievements = lua.create_table()?;
+    achievements.set("unlock", scope.create_function(move |_, id: String| {
+        world.borrow_mut().resource_mut::<Achievements>().unlock(&id);
+        Ok(())
+    })?)?;
+    achievements.set("is_unlocked", scope.create_function(move |_, id: String| {
+        Ok(world.borrow().resource::<Achievements>().is_unlocked(&id))
+    })?)?;
+    lua.globals().set("achievements", achievements)?;
+
+    let vehicle = lua.create_table()?;
+    vehicle.set("set_input", scope.create_function(
+        move |_, (id, throttle, brake, steer): (ScriptEntity, f32, f32, f32)| {
+            let entity = id.0;
+            let mut world = world.borrow_mut();
+            let mut entity = world
+                .get_entity_mut(entity)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("entity {} does not exist", id)))?;
+            entity.insert(VehicleInput {
+                throttle: throttle.clamp(-1.0, 1.0),
+                brake: brake.clamp(0.0, 1.0),
+                steer: steer.clamp(-1.0, 1.0),
+            });
+            Ok(())
+        },
+    )?)?;
+    lua.globals().set("vehicle", vehicle)?;
+
+    let pool = lua.create_table()?;
+    pool.set("get", scope.create_function(move |_, name: String| {
+        Ok(with_pool(world, |pool| pool.get(&name)).map(ScriptEntity))
+    })?)?;
+    pool.set("release", scope.create_function(move |_, id: ScriptEntity| {
+        let entity = id.0;
+        with_pool(world, |pool| pool.release(entity));
+        Ok(())
+    })?)?;
+    pool.set("stats", scope.create_function(move |lua, name: String| {
+        let Some(stats) = with_pool(world, |pool| pool.stats(&name)) else {
+            return Ok(None);
+        };
+        let table = lua.create_table()?;
+        table.set("total", stats.total)?;
+        table.set("active", stats.active)?;
+        table.set("peak_active", stats.peak_active)?;
+        table.set("misses", stats.misses)?;
+        Ok(Some(table))
+    })?)?;
+    lua.globals().set("pool", pool)?;
+
+    let destruction = lua.create_table()?;
+    destruction.set("destroy", scope.create_function(
+        move |_, (id, impulse, x, y, z): (ScriptEntity, Option<f32>, Option<f32>, Option<f32>, Option<f32>)| {
+            let entity = id.0;
+            let impulse = impulse.unwrap_or(0.0);
+            let event = match (x, y, z) {
+                (Some(x), Some(y), Some(z)) => DestroyEvent::at(entity, Vec3::new(x, y, z), impulse),
+                _ => DestroyEvent {
+                    force: impulse,
+                    ..DestroyEvent::new(entity)
+                },
+            };
+            world.borrow_mut().send_event(event);
+            Ok(())
+        },
+    )?)?;
+    lua.globals().set("destruction", destruction)?;
+
+    let animation = lua.create_table()?;
+    animation.set("set_float", scope.create_function(move |_, (id, name, value): (ScriptEntity, String, f32)| {
+        write_state_machine(world, id, |machine| machine.set_float(name, value))
+    })?)?;
+    animation.set("set_bool", scope.create_function(move |_, (id, name, value): (ScriptEntity, String, bool)| {
+        write_state_machine(world, id, |machine| machine.set_bool(name, value))
+    })?)?;
+    animation.set("trigger", scope.create_function(move |_, (id, name): (ScriptEntity, String)| {
+        write_state_machine(world, id, |machine| machine.trigger(name))
+    })?)?;
+    animation.set("state", scope.create_function(move |_, id: ScriptEntity| {
+        let entity = id.0;
+        Ok(world
+            .borrow()
+            .get::<AnimationStateMachine>(entity)
+            .and_then(|machine| machine.state.clone()))
+    })?)?;
+    lua.globals().set("animation", animation)?;
+
+    let analytics = lua.create_table()?;
+    analytics.set("event", scope.create_function(move |_, (name, properties): (String, Option<Value>)| {
+        let properties = match properties {
+            Some(properties) => to_json(properties, 0)?,
+            None => serde_json::Value::Object(serde_json::Map::new()),
+        };
+        world.borrow_mut().resource_mut::<Analytics>().event(name, properties);
+        Ok(())
+    })?)?;
+    lua.globals().set("analytics", analytics)?;
+
+    let random: Table = lua.globals().get("random")?;
+    random.set("float", scope.create_function(move |_, ()| Ok(with_global_rng(world, WaffleRng::next_f32)))?)?;
+    random.set("range", scope.create_function(move |_, (min, max): (f32, f32)| {
+        Ok(with_global_rng(world, |rng| rng.range_f32(min, max)))
+    })?)?;
+    random.set("range_int", scope.create_function(move |_, (min, max): (i32, i32)| {
+        Ok(with_global_rng(world, |rng| rng.range_i32(min, max)))
+    })?)?;
+    random.set("chance", scope.create_function(move |_, probability: f32| {
+        Ok(with_global_rng(world, |rng| rng.chance(probability)))
+    })?)?;
+    random.set("choice", scope.create_function(move |_, list: Table| {
+        with_global_rng(world, |rng| choose_item(rng, list))
+    })?)?;
+    random.set("weighted_choice", scope.create_function(move |_, (items, weights): (Table, Table)| {
+        with_global_rng(world, |rng| choose_weighted_item(rng, items, weights))
+    })?)?;
+    random.set("fork", scope.create_function(move |_, name: String| {
+        Ok(world.borrow().resource::<GlobalRng>().fork(&name))
+    })?)?;
+    Ok(())
+}
+
+fn with_global_rng<R>(world: &RefCell<&mut World>, draw: impl FnOnce(&mut WaffleRng) -> R) -> R {
+    draw(&mut world.borrow_mut().resource_mut::<GlobalRng>().rng)
+}
+
+/// Use the entity pools, applying the spawns and parking they queue
+fn with_pool<R>(world: &RefCell<&mut World>, use_pool: impl FnOnce(&mut Pool) -> R) -> R {
+    let mut world = world.borrow_mut();
+    let mut state = SystemState::<Pool>::new(&mut world);
+    let result = use_pool(&mut state.get_mut(&mut world));
+    state.apply(&mut world);
+    result
+}
+
+/// Tables nested deeper than this are most likely cycles
+const MAX_JSON_DEPTH: usize = 32;
+
+/// A Lua value as JSON; tables with keys 1..n become arrays, other tables
+/// objects with their keys as strings
+fn to_json(value: Value, depth: usize) -> mlua::Result<serde_json::Value> {
+    Ok(match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Boolean(value) => serde_json::Value::Bool(value),
+        Value::Integer(value) => serde_json::Value::from(value),
+        Value::Number(value) => serde_json::Value::from(value),
+        Value::String(value) => serde_json::Value::String(value.to_str()?.to_string()),
+        Value::Table(table) => {
+            if depth >= MAX_JSON_DEPTH {
+                return Err(mlua::Error::RuntimeError("table is nested too deeply".to_string()));
+            }
+            let len = table.raw_len();
+            let pairs = table.clone().pairs::<Value, Value>().collect::<mlua::Result<Vec<_>>>()?;
+            if len > 0 && pairs.len() == len {
+                let items = table.sequence_values::<Value>().collect::<mlua::Result<Vec<_>>>()?;
+                serde_json::Value::Array(
+                    items.into_iter().map(|item| to_json(item, depth + 1)).collect::<mlua::Result<_>>()?,
+                )
+            } else {
+                let mut object = serde_json::Map::new();
+                for (key, value) in pairs {
+                    let key = match key {
+                        Value::String(key) => key.to_str()?.to_string(),
+                        Value::Integer(key) => key.to_string(),
+                        Value::Number(key) => key.to_string(),
+                        Value::Boolean(key) => key.to_string(),
+                        other => {
+                            return Err(mlua::Error::RuntimeError(format!(
+                                "{} keys can't be sent as analytics properties",
+                                other.type_name()
+                            )))
+                        }
+                    };
+                    object.insert(key, to_json(value, depth + 1)?);
+                }
+                serde_json::Value::Object(object)
+            }
+        }
+        other => {
+            return Err(mlua::Error::RuntimeError(format!(
+                "{} values can't be sent as analytics properties",
+                other.type_name()
+            )))
+        }
+    })
+}
+
+/// Trailing `r, g, b` arguments, white when left out
+struct DebugColor(Color);
+
+impl<'lua> FromLuaMulti<'lua> for DebugColor {
+    fn from_lua_multi(values: MultiValue<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let (r, g, b) = <(Option<f32>, Option<f32>, Option<f32>)>::from_lua_multi(values, lua)?;
+        Ok(DebugColor(Color::srgb(r.unwrap_or(1.0), g.unwrap_or(1.0), b.unwrap_or(1.0))))
+    }
+}
+
+fn queue_debug_shape(world: &RefCell<&mut World>, shape: DebugShape) {
+    world.borrow_mut().resource_mut::<DebugDrawQueue>().shapes.push(shape);
+}
+
+fn easing_from_name(name: Option<String>) -> mlua::Result<Easing> {
+    match name {
+        Some(name) => Easing::from_name(&name)
+            .ok_or_else(|| mlua::Error::RuntimeError(format!("{} is not an easing", name))),
+        None => Ok(Easing::Linear),
+    }
+}
+
+/// Delay and repeat from a tween's optional options table
+fn tween_with_options(tween: Tween, options: Option<Table>) -> mlua::Result<Tween> {
+    let Some(options) = options else {
+        return Ok(tween);
+    };
+    let mut tween = tween.with_delay(options.get::<_, Option<f32>>("delay")?.unwrap_or(0.0));
+    if let Some(name) = options.get::<_, Option<String>>("repeat")? {
+        let repeat = TweenRepeat::from_name(&name)
+            .ok_or_else(|| mlua::Error::RuntimeError(format!("{} is not a tween repeat", name)))?;
+        tween = tween.with_repeat(repeat);
+    }
+    Ok(tween)
+}
+
+fn entity_from_id(id: u64) -> mlua::Result<Entity> {
+    Entity::try_from_bits(id).map_err(|_| mlua::Error::RuntimeError(format!("{} is not an entity id", id)))
+}
+
+fn set_highlight(world: &RefCell<&mut World>, id: ScriptEntity, highlight: Option<Highlight>) -> mlua::Result<()> {
+    let entity = id.0;
+    let mut world = world.borrow_mut();
+    let mut entity = world
+        .get_entity_mut(entity)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("entity {} does not exist", id)))?;
+    match highlight {
+        Some(highlight) => {
+            entity.insert(highlight);
+        }
+        None => {
+            entity.remove::<Highlight>();
+        }
+    }
+    Ok(())
+}
+
+fn read_transform(world: &RefCell<&mut World>, id: ScriptEntity) -> mlua::Result<Transform> {
+    let entity = id.0;
+    world
+        .borrow()
+        .get::<Transform>(entity)
+        .copied()
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("entity {} has no transform", id)))
+}
+
+fn write_transform(world: &RefCell<&mut World>, id: ScriptEntity, edit: impl FnOnce(&mut Transform)) -> mlua::Result<()> {
+    let entity = id.0;
+    let mut world = world.borrow_mut();
+    let mut transform = world
+        .get_mut::<Transform>(entity)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("entity {} has no transform", id)))?;
+    edit(&mut transform);
+    Ok(())
+}
+
+fn write_state_machine(
+    world: &RefCell<&mut World>,
+    id: ScriptEntity,
+    edit: impl FnOnce(&mut AnimationStateMachine),
+) -> mlua::Result<()> {
+    let entity = id.0;
+    let mut world = world.borrow_mut();
+    let mut machine = world
+        .get_mut::<AnimationStateMachine>(entity)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("entity {} has no animation state machine", id)))?;
+    edit(&mut machine);
+    Ok(())
+}
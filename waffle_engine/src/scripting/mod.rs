@@ -0,0 +1,103 @@
+// Waffle Engine Scripting Module
+// Per-entity Lua scripts. A `LuaScript` points at a `.lua` asset; while the
+// game is playing its `on_start()` runs once and `on_update(dt)` every frame.
+// The Lua runtime is only built with the `lua` cargo feature; without it
+// scripts stay attached to their entities but never run.
+
+#[cfg(feature = "lua")]
+pub mod lua;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::play::is_playing;
+
+/// Run a Lua script on this entity while the game is playing
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LuaScript {
+    /// Asset path, e.g. `scripts/door.lua`
+    pub path: String,
+    pub enabled: bool,
+}
+
+impl Default for LuaScript {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            enabled: true,
+        }
+    }
+}
+
+impl LuaScript {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..default()
+        }
+    }
+}
+
+/// Source of a `.lua` file
+#[derive(Asset, TypePath, Debug)]
+pub struct LuaScriptAsset {
+    pub source: String,
+}
+
+#[derive(Default)]
+pub struct LuaScriptLoader;
+
+impl AssetLoader for LuaScriptLoader {
+    type Asset = LuaScriptAsset;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LuaScriptAsset, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = String::from_utf8(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Ok(LuaScriptAsset { source })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lua"]
+    }
+}
+
+pub struct WaffleScriptingPlugin;
+
+impl Plugin for WaffleScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LuaScriptAsset>()
+            .init_asset_loader::<LuaScriptLoader>()
+            .register_type::<LuaScript>();
+
+        #[cfg(feature = "lua")]
+        app.insert_non_send_resource(lua::LuaRuntime::default())
+            .add_systems(Startup, lua::install_behavior_leaves)
+            .add_systems(Update, lua::reload_modified_lua_scripts)
+            .add_systems(Update, lua::run_lua_scripts.after(lua::reload_modified_lua_scripts).run_if(is_playing))
+            .add_systems(OnExit(crate::core::play::PlayState::Playing), lua::stop_lua_scripts);
+
+        #[cfg(not(feature = "lua"))]
+        app.add_systems(Update, warn_scripts_without_runtime.run_if(is_playing));
+    }
+}
+
+/// Say once why attached scripts do nothing
+#[cfg(not(feature = "lua"))]
+fn warn_scripts_without_runtime(scripts: Query<(), With<LuaScript>>, mut warned: Local<bool>) {
+    if !*warned && !scripts.is_empty() {
+        warn!("Lua scripts are attached but the engine was built without the `lua` feature");
+        *warned = true;
+    }
+}
@@ -0,0 +1,629 @@
+// Waffle Engine Terrain Module
+// Heightmapped landscapes. A `WaffleTerrain` reads the heights of a grayscale
+// image asset, black at the bottom and white at `height_scale`, and builds a
+// square grid of chunk meshes from them as hidden children of its entity.
+// Every chunk carries its own bounds, so chunks out of view are culled and
+// ray casts only test the chunks they pass through.
+// Four texture layers are blended over the ground by the channels of a splat
+// map, red for the first layer through alpha for the fourth; without a splat
+// map the first layer covers everything.
+// The editor sculpts the heights in place; only the chunks a change touches
+// are meshed again.
+
+use bevy::asset::{load_internal_asset, LoadState};
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::primitives::Aabb;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat};
+use bevy::render::texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::core::components::EditorHidden;
+
+pub const TERRAIN_LAYER_COUNT: usize = 4;
+
+const TERRAIN_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x5f3a_91c2_7d4e_4b8a_a2f6_0c1d_9e7b_3a14);
+
+/// A texture blended over the terrain where the splat map asks for it
+#[derive(Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainLayer {
+    /// Asset path of a color texture; without one the layer is its tint
+    pub texture: String,
+    pub tint: Color,
+    /// Times the texture repeats across the whole terrain
+    pub tiling: f32,
+}
+
+impl Default for TerrainLayer {
+    fn default() -> Self {
+        Self {
+            texture: String::new(),
+            tint: Color::WHITE,
+            tiling: 32.0,
+        }
+    }
+}
+
+/// A landscape built from a heightmap, centered on its entity
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaffleTerrain {
+    /// Asset path of a grayscale image; the terrain is flat without one
+    pub heightmap: String,
+    /// Width along X and depth along Z
+    pub size: Vec2,
+    /// Height of white in the heightmap
+    pub height_scale: f32,
+    /// Chunks along each side
+    pub chunks: u32,
+    /// Quads along each side of a chunk
+    pub chunk_resolution: u32,
+    /// Asset path of an RGBA image weighting the four layers
+    pub splatmap: String,
+    pub layers: [TerrainLayer; TERRAIN_LAYER_COUNT],
+}
+
+impl Default for WaffleTerrain {
+    fn default() -> Self {
+        let layer = |r, g, b| TerrainLayer {
+            tint: Color::srgb(r, g, b),
+            ..default()
+        };
+        Self {
+            heightmap: String::new(),
+            size: Vec2::splat(64.0),
+            height_scale: 8.0,
+            chunks: 4,
+            chunk_resolution: 32,
+            splatmap: String::new(),
+            layers: [
+                layer(0.36, 0.5, 0.22),
+                layer(0.45, 0.34, 0.24),
+                layer(0.5, 0.5, 0.5),
+                layer(0.95, 0.95, 0.97),
+            ],
+        }
+    }
+}
+
+impl WaffleTerrain {
+    /// Vertices along each side of the whole terrain
+    pub fn grid_resolution(&self) -> usize {
+        (self.chunks.max(1) * self.chunk_resolution.max(1)) as usize + 1
+    }
+}
+
+/// What the chunk meshes depend on besides the heights
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TerrainShape {
+    size: Vec2,
+    height_scale: f32,
+    chunks: u32,
+    chunk_resolution: u32,
+}
+
+impl TerrainShape {
+    fn of(terrain: &WaffleTerrain) -> Self {
+        Self {
+            size: terrain.size,
+            height_scale: terrain.height_scale,
+            chunks: terrain.chunks.max(1),
+            chunk_resolution: terrain.chunk_resolution.max(1),
+        }
+    }
+}
+
+/// Heights of the terrain's vertex grid from 0 to 1, row by row from -Z to
+/// +Z; the top row of the heightmap is the far edge
+#[derive(Clone, Debug, Default)]
+pub struct TerrainHeights {
+    /// Vertices along each side
+    pub resolution: usize,
+    pub values: Vec<f32>,
+}
+
+impl TerrainHeights {
+    pub fn flat(resolution: usize) -> Self {
+        Self {
+            resolution,
+            values: vec![0.0; resolution * resolution],
+        }
+    }
+
+    /// Sample the brightness of an image onto the grid. `None` for formats
+    /// that can't be read back on the CPU.
+    pub fn from_image(image: &Image, resolution: usize) -> Option<Self> {
+        let luma = image.clone().try_into_dynamic().ok()?.to_luma32f();
+        let (width, height) = (luma.width() as usize, luma.height() as usize);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let pixels = luma.as_raw();
+        let texel = |x: usize, y: usize| pixels[y.min(height - 1) * width + x.min(width - 1)];
+        let last = (resolution.max(2) - 1) as f32;
+        let mut values = Vec::with_capacity(resolution * resolution);
+        for z in 0..resolution {
+            for x in 0..resolution {
+                let u = x as f32 / last * (width - 1) as f32;
+                let v = z as f32 / last * (height - 1) as f32;
+                let (x0, y0) = (u as usize, v as usize);
+                let (fx, fy) = (u.fract(), v.fract());
+                let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1, y0) * fx;
+                let bottom = texel(x0, y0 + 1) * (1.0 - fx) + texel(x0 + 1, y0 + 1) * fx;
+                values.push((top * (1.0 - fy) + bottom * fy).clamp(0.0, 1.0));
+            }
+        }
+        Some(Self { resolution, values })
+    }
+
+    /// The height at a vertex, clamped to the edges of the grid
+    pub fn get(&self, x: usize, z: usize) -> f32 {
+        let last = self.resolution.saturating_sub(1);
+        self.values.get(z.min(last) * self.resolution + x.min(last)).copied().unwrap_or(0.0)
+    }
+
+    /// Write the heights as a 16-bit grayscale PNG that reads back as the
+    /// same grid
+    pub fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        let size = self.resolution as u32;
+        let pixels = self.values.iter().map(|value| (value.clamp(0.0, 1.0) * 65535.0).round() as u16).collect();
+        let image = image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(size, size, pixels)
+            .ok_or_else(|| anyhow::anyhow!("terrain heights don't fill a {size}x{size} image"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        image.save(path)?;
+        Ok(())
+    }
+}
+
+/// The heights and chunks built for a terrain
+#[derive(Component)]
+pub struct TerrainState {
+    heightmap: Option<Handle<Image>>,
+    heightmap_path: String,
+    pub heights: Option<TerrainHeights>,
+    /// Shape the chunks were last built with
+    built: Option<TerrainShape>,
+    material: Handle<TerrainMaterial>,
+    chunks: Vec<Entity>,
+    /// Chunks whose heights were edited since they were meshed
+    dirty: HashSet<UVec2>,
+}
+
+impl TerrainState {
+    /// Mesh again the chunks touching the vertices from `min` to `max`,
+    /// and their neighbors whose normals lean on them
+    pub fn mark_dirty(&mut self, min: UVec2, max: UVec2) {
+        let Some(shape) = self.built else {
+            return;
+        };
+        let quads = shape.chunk_resolution;
+        let last = shape.chunks - 1;
+        let first = min.saturating_sub(UVec2::splat(2)) / quads;
+        let end = ((max + 1) / quads).min(UVec2::splat(last));
+        for z in first.y..=end.y {
+            for x in first.x..=end.x {
+                self.dirty.insert(UVec2::new(x, z));
+            }
+        }
+    }
+
+    /// The heights now match a heightmap saved from them, so the terrain
+    /// can point at it without reading it back
+    pub fn adopt_heightmap(&mut self, path: &str, asset_server: &AssetServer) {
+        self.heightmap = load_heightmap(asset_server, path);
+        self.heightmap_path = path.to_string();
+    }
+
+    /// Where a ray in the terrain's space first meets the ground, as a
+    /// distance along the ray
+    pub fn raycast(&self, terrain: &WaffleTerrain, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let heights = self.heights.as_ref()?;
+        let half = terrain.size * 0.5;
+        let min = Vec3::new(-half.x, 0.0, -half.y);
+        let max = Vec3::new(half.x, terrain.height_scale.max(0.0), half.y);
+        let (enter, exit) = ray_box(origin, direction, min, max)?;
+
+        let last = (heights.resolution.max(2) - 1) as f32;
+        let step = (terrain.size / last).min_element().max(0.001) * 0.5;
+        let above = |t: f32| {
+            let point = origin + direction * t;
+            point.y - sample_height(heights, terrain, point.xz())
+        };
+        let mut previous = enter;
+        let mut t = enter;
+        while t <= exit {
+            if above(t) <= 0.0 {
+                if t == enter {
+                    return Some(t);
+                }
+                // Narrow down the crossing between the last two samples
+                let (mut low, mut high) = (previous, t);
+                for _ in 0..8 {
+                    let mid = (low + high) * 0.5;
+                    if above(mid) > 0.0 {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                return Some(high);
+            }
+            previous = t;
+            t += step;
+        }
+        None
+    }
+}
+
+/// Height of the ground at a point in the terrain's space, between vertices
+fn sample_height(heights: &TerrainHeights, terrain: &WaffleTerrain, point: Vec2) -> f32 {
+    let last = (heights.resolution.max(2) - 1) as f32;
+    let grid = ((point / terrain.size + 0.5) * last).clamp(Vec2::ZERO, Vec2::splat(last));
+    let (x, z) = (grid.x as usize, grid.y as usize);
+    let fraction = grid - grid.floor();
+    let near = heights.get(x, z) * (1.0 - fraction.x) + heights.get(x + 1, z) * fraction.x;
+    let far = heights.get(x, z + 1) * (1.0 - fraction.x) + heights.get(x + 1, z + 1) * fraction.x;
+    (near * (1.0 - fraction.y) + far * fraction.y) * terrain.height_scale
+}
+
+/// Distances along a ray where it enters and leaves a box
+fn ray_box(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<(f32, f32)> {
+    let inverse = direction.recip();
+    let near = (min - origin) * inverse;
+    let far = (max - origin) * inverse;
+    let enter = near.min(far).max_element().max(0.0);
+    let exit = near.max(far).min_element();
+    (enter <= exit).then_some((enter, exit))
+}
+
+/// One mesh of a terrain, with its bounds in the terrain's space
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TerrainChunk {
+    pub terrain: Entity,
+    /// Column and row of the chunk, from the -X, -Z corner
+    pub coord: UVec2,
+    pub aabb: Aabb,
+}
+
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainSplat>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, ShaderType)]
+pub struct TerrainSplatUniform {
+    /// Linear tint of each layer
+    pub tints: [Vec4; TERRAIN_LAYER_COUNT],
+    pub tiling: Vec4,
+}
+
+/// The layers of a terrain, blended on top of a standard material
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug, Default, PartialEq)]
+pub struct TerrainSplat {
+    #[uniform(100)]
+    pub layers: TerrainSplatUniform,
+    #[texture(101)]
+    #[sampler(102)]
+    pub splatmap: Handle<Image>,
+    #[texture(103)]
+    #[sampler(104)]
+    pub layer_0: Option<Handle<Image>>,
+    #[texture(105)]
+    #[sampler(106)]
+    pub layer_1: Option<Handle<Image>>,
+    #[texture(107)]
+    #[sampler(108)]
+    pub layer_2: Option<Handle<Image>>,
+    #[texture(109)]
+    #[sampler(110)]
+    pub layer_3: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for TerrainSplat {
+    fn fragment_shader() -> ShaderRef {
+        TERRAIN_SHADER_HANDLE.into()
+    }
+}
+
+/// Splat map of terrains without one: all first layer
+#[derive(Resource)]
+pub struct DefaultTerrainSplat(pub Handle<Image>);
+
+pub struct WaffleTerrainPlugin;
+
+impl Plugin for WaffleTerrainPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, TERRAIN_SHADER_HANDLE, "terrain.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<TerrainMaterial>::default())
+            .register_type::<WaffleTerrain>()
+            .add_systems(Startup, setup_default_terrain_splat)
+            .add_systems(
+                Update,
+                (
+                    despawn_orphaned_terrain_chunks,
+                    spawn_terrain_state,
+                    update_terrain_materials,
+                    build_terrain_chunks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_default_terrain_splat(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = Image::new(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        vec![255, 0, 0, 0],
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    commands.insert_resource(DefaultTerrainSplat(images.add(image)));
+}
+
+fn load_heightmap(asset_server: &AssetServer, path: &str) -> Option<Handle<Image>> {
+    (!path.is_empty()).then(|| {
+        asset_server.load_with_settings(path.to_string(), |settings: &mut ImageLoaderSettings| {
+            settings.is_srgb = false;
+        })
+    })
+}
+
+/// Layer textures repeat across the terrain
+fn load_layer_texture(asset_server: &AssetServer, path: &str) -> Option<Handle<Image>> {
+    (!path.is_empty()).then(|| {
+        asset_server.load_with_settings(path.to_string(), |settings: &mut ImageLoaderSettings| {
+            settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                address_mode_u: ImageAddressMode::Repeat,
+                address_mode_v: ImageAddressMode::Repeat,
+                ..ImageSamplerDescriptor::linear()
+            });
+        })
+    })
+}
+
+/// Give each new terrain its material and start loading its heightmap
+pub fn spawn_terrain_state(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    terrains: Query<(Entity, &WaffleTerrain), Without<TerrainState>>,
+) {
+    for (entity, terrain) in terrains.iter() {
+        let material = materials.add(TerrainMaterial {
+            base: StandardMaterial {
+                perceptual_roughness: 0.9,
+                ..default()
+            },
+            extension: TerrainSplat::default(),
+        });
+        commands.entity(entity).insert(TerrainState {
+            heightmap: load_heightmap(&asset_server, &terrain.heightmap),
+            heightmap_path: terrain.heightmap.clone(),
+            heights: None,
+            built: None,
+            material,
+            chunks: Vec::new(),
+            dirty: HashSet::new(),
+        });
+    }
+}
+
+/// Point each terrain's material at its splat map and layers
+pub fn update_terrain_materials(
+    asset_server: Res<AssetServer>,
+    default_splat: Option<Res<DefaultTerrainSplat>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    terrains: Query<(&WaffleTerrain, &TerrainState), Or<(Changed<WaffleTerrain>, Added<TerrainState>)>>,
+) {
+    let Some(default_splat) = default_splat else {
+        return;
+    };
+    for (terrain, state) in terrains.iter() {
+        let splatmap = if terrain.splatmap.is_empty() {
+            default_splat.0.clone()
+        } else {
+            asset_server.load_with_settings(terrain.splatmap.clone(), |settings: &mut ImageLoaderSettings| {
+                settings.is_srgb = false;
+            })
+        };
+        let [layer_0, layer_1, layer_2, layer_3] =
+            terrain.layers.each_ref().map(|layer| load_layer_texture(&asset_server, &layer.texture));
+        let splat = TerrainSplat {
+            layers: TerrainSplatUniform {
+                tints: terrain.layers.each_ref().map(|layer| layer.tint.to_linear().to_vec4()),
+                tiling: Vec4::from_array(terrain.layers.each_ref().map(|layer| layer.tiling)),
+            },
+            splatmap,
+            layer_0,
+            layer_1,
+            layer_2,
+            layer_3,
+        };
+        // Only touch the material when something changed, so it isn't
+        // prepared again every frame the inspector shows the terrain
+        if materials.get(&state.material).is_some_and(|material| material.extension != splat) {
+            if let Some(material) = materials.get_mut(&state.material) {
+                material.extension = splat;
+            }
+        }
+    }
+}
+
+/// Read the heights of terrains whose heightmap or resolution changed and
+/// rebuild the chunks of those whose heights or shape changed. Chunks marked
+/// dirty by an edit are meshed again in place.
+pub fn build_terrain_chunks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut terrains: Query<(Entity, &WaffleTerrain, &mut TerrainState)>,
+    mut chunk_query: Query<(&Handle<Mesh>, &mut TerrainChunk, &mut Aabb)>,
+) {
+    for (entity, terrain, mut state) in terrains.iter_mut() {
+        let resolution = terrain.grid_resolution();
+        let shape = TerrainShape::of(terrain);
+        let stale_heights = state.heightmap_path != terrain.heightmap
+            || state.heights.as_ref().is_none_or(|heights| heights.resolution != resolution);
+        let state = &mut *state;
+        if !stale_heights && state.built == Some(shape) {
+            let Some(heights) = &state.heights else {
+                continue;
+            };
+            for coord in state.dirty.drain() {
+                let index = (coord.y * shape.chunks + coord.x) as usize;
+                let Some(Ok((handle, mut chunk, mut bounds))) =
+                    state.chunks.get(index).map(|chunk| chunk_query.get_mut(*chunk))
+                else {
+                    continue;
+                };
+                let (mesh, aabb) = build_chunk_mesh(&shape, heights, coord);
+                if let Some(chunk_mesh) = meshes.get_mut(handle) {
+                    *chunk_mesh = mesh;
+                }
+                chunk.aabb = aabb;
+                *bounds = aabb;
+            }
+            continue;
+        }
+        state.dirty.clear();
+
+        if state.heightmap_path != terrain.heightmap {
+            state.heightmap = load_heightmap(&asset_server, &terrain.heightmap);
+            state.heightmap_path = terrain.heightmap.clone();
+            state.heights = None;
+        }
+        if stale_heights {
+            let heights = match &state.heightmap {
+                None => TerrainHeights::flat(resolution),
+                Some(handle) => match images.get(handle) {
+                    Some(image) => TerrainHeights::from_image(image, resolution).unwrap_or_else(|| {
+                        warn!("Heightmap {} is in a format terrain can't read", terrain.heightmap);
+                        TerrainHeights::flat(resolution)
+                    }),
+                    None if matches!(asset_server.load_state(handle.id()), LoadState::Failed(_)) => {
+                        warn!("Failed to load heightmap {}", terrain.heightmap);
+                        TerrainHeights::flat(resolution)
+                    }
+                    // Still loading
+                    None => continue,
+                },
+            };
+            state.heights = Some(heights);
+            state.built = None;
+        }
+        let Some(heights) = &state.heights else {
+            continue;
+        };
+
+        for chunk in state.chunks.drain(..) {
+            if let Some(chunk) = commands.get_entity(chunk) {
+                chunk.despawn_recursive();
+            }
+        }
+        for z in 0..shape.chunks {
+            for x in 0..shape.chunks {
+                let coord = UVec2::new(x, z);
+                let (mesh, aabb) = build_chunk_mesh(&shape, heights, coord);
+                let chunk = commands
+                    .spawn((
+                        MaterialMeshBundle::<TerrainMaterial> {
+                            mesh: meshes.add(mesh),
+                            material: state.material.clone(),
+                            ..default()
+                        },
+                        aabb,
+                        TerrainChunk {
+                            terrain: entity,
+                            coord,
+                            aabb,
+                        },
+                        EditorHidden,
+                        Name::new(format!("Terrain Chunk {x},{z}")),
+                    ))
+                    .set_parent(entity)
+                    .id();
+                state.chunks.push(chunk);
+            }
+        }
+        state.built = Some(shape);
+    }
+}
+
+/// The mesh of one chunk and its bounds. Normals come from the neighboring
+/// heights on the whole grid, so lighting matches across chunk edges.
+fn build_chunk_mesh(shape: &TerrainShape, heights: &TerrainHeights, coord: UVec2) -> (Mesh, Aabb) {
+    let quads = shape.chunk_resolution as usize;
+    let last = (heights.resolution.max(2) - 1) as f32;
+    let step = shape.size / last;
+    let origin = -shape.size * 0.5;
+    let (first_x, first_z) = (coord.x as usize * quads, coord.y as usize * quads);
+    let height_at = |x: usize, z: usize| heights.get(x, z) * shape.height_scale;
+
+    let count = (quads + 1) * (quads + 1);
+    let mut positions = Vec::with_capacity(count);
+    let mut normals = Vec::with_capacity(count);
+    let mut uvs = Vec::with_capacity(count);
+    let (mut min_y, mut max_y) = (f32::MAX, f32::MIN);
+    for z in first_z..=first_z + quads {
+        for x in first_x..=first_x + quads {
+            let y = height_at(x, z);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+            positions.push([origin.x + x as f32 * step.x, y, origin.y + z as f32 * step.y]);
+            uvs.push([x as f32 / last, z as f32 / last]);
+            let slope_x = (height_at(x + 1, z) - height_at(x.saturating_sub(1), z)) / (2.0 * step.x);
+            let slope_z = (height_at(x, z + 1) - height_at(x, z.saturating_sub(1))) / (2.0 * step.y);
+            normals.push(Vec3::new(-slope_x, 1.0, -slope_z).normalize().to_array());
+        }
+    }
+
+    let row = (quads + 1) as u32;
+    let mut indices = Vec::with_capacity(quads * quads * 6);
+    for z in 0..quads as u32 {
+        for x in 0..quads as u32 {
+            let a = z * row + x;
+            let (b, c) = (a + 1, a + row);
+            let d = c + 1;
+            indices.extend([a, c, b, b, c, d]);
+        }
+    }
+
+    let min = Vec3::new(origin.x + first_x as f32 * step.x, min_y, origin.y + first_z as f32 * step.y);
+    let max = Vec3::new(
+        origin.x + (first_x + quads) as f32 * step.x,
+        max_y,
+        origin.y + (first_z + quads) as f32 * step.y,
+    );
+    let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices));
+    (mesh, Aabb::from_min_max(min, max))
+}
+
+/// Despawn the chunks of terrains that were removed
+pub fn despawn_orphaned_terrain_chunks(
+    mut commands: Commands,
+    chunks: Query<(Entity, &TerrainChunk)>,
+    terrains: Query<(), With<WaffleTerrain>>,
+) {
+    for (entity, chunk) in chunks.iter() {
+        if !terrains.contains(chunk.terrain) {
+            commands.entity(entity).despawn_recursive();
+            if let Some(mut terrain) = commands.get_entity(chunk.terrain) {
+                terrain.remove::<TerrainState>();
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Waffle Engine terrain splatting
+// Blends four tiled texture layers by the channels of the splat map, red for
+// the first layer through alpha for the fourth, then lights the result like
+// any standard material.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct TerrainSplat {
+    tints: array<vec4<f32>, 4>,
+    // Repeats of each layer's texture across the terrain
+    tiling: vec4<f32>,
+}
+
+@group(2) @binding(100) var<uniform> terrain: TerrainSplat;
+@group(2) @binding(101) var splat_texture: texture_2d<f32>;
+@group(2) @binding(102) var splat_sampler: sampler;
+@group(2) @binding(103) var layer_0_texture: texture_2d<f32>;
+@group(2) @binding(104) var layer_0_sampler: sampler;
+@group(2) @binding(105) var layer_1_texture: texture_2d<f32>;
+@group(2) @binding(106) var layer_1_sampler: sampler;
+@group(2) @binding(107) var layer_2_texture: texture_2d<f32>;
+@group(2) @binding(108) var layer_2_sampler: sampler;
+@group(2) @binding(109) var layer_3_texture: texture_2d<f32>;
+@group(2) @binding(110) var layer_3_sampler: sampler;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    let weights = textureSample(splat_texture, splat_sampler, in.uv);
+    let total = max(dot(weights, vec4(1.0)), 0.0001);
+    var color = textureSample(layer_0_texture, layer_0_sampler, in.uv * terrain.tiling.x) * terrain.tints[0] * weights.r;
+    color += textureSample(layer_1_texture, layer_1_sampler, in.uv * terrain.tiling.y) * terrain.tints[1] * weights.g;
+    color += textureSample(layer_2_texture, layer_2_sampler, in.uv * terrain.tiling.z) * terrain.tints[2] * weights.b;
+    color += textureSample(layer_3_texture, layer_3_sampler, in.uv * terrain.tiling.w) * terrain.tints[3] * weights.a;
+    pbr_input.material.base_color *= color / total;
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
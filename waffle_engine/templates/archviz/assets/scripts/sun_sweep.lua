@@ -0,0 +1,13 @@
+-- Sweeps the sun across the sky to preview the scene's light through the day.
+-- Remove the script to keep the sun where it is placed.
+local day_seconds = 60
+local time = 0
+
+function on_update(dt)
+    time = (time + dt) % day_seconds
+    local progress = time / day_seconds
+    -- Rises in the east, highest at noon, sets in the west
+    local height = 10 + math.sin(progress * math.pi) * 60
+    local heading = 90 - progress * 180
+    transform.set_rotation(entity, -height, heading, 0)
+end
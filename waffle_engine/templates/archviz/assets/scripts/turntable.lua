@@ -0,0 +1,8 @@
+-- Turns a showpiece slowly on the spot
+local degrees_per_second = 20
+local yaw = 0
+
+function on_update(dt)
+    yaw = (yaw + degrees_per_second * dt) % 360
+    transform.set_rotation(entity, 0, yaw, 0)
+end
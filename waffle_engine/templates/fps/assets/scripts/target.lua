@@ -0,0 +1,16 @@
+-- Slides a target from side to side; each target starts at its own point of
+-- the swing so they don't move in step
+local x, y, z
+local time = 0
+local width = 3
+local speed = 1.5
+
+function on_start()
+    x, y, z = transform.get_position(entity)
+    time = x
+end
+
+function on_update(dt)
+    time = time + dt
+    transform.set_position(entity, x + math.sin(time * speed) * width, y, z)
+end
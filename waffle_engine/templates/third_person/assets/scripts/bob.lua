@@ -0,0 +1,13 @@
+-- Bobs a pickup up and down and spins it, so it catches the eye
+local x, y, z
+local time = 0
+
+function on_start()
+    x, y, z = transform.get_position(entity)
+end
+
+function on_update(dt)
+    time = time + dt
+    transform.set_position(entity, x, y + math.sin(time * 2.0) * 0.2, z)
+    transform.set_rotation(entity, 0, (time * 90) % 360, 0)
+end
@@ -0,0 +1,23 @@
+-- Walks back and forth between where the guard starts and a point ahead of it
+local distance = 8
+local speed = 2
+local start_x, start_y, start_z
+local travelled = 0
+local direction = 1
+
+function on_start()
+    start_x, start_y, start_z = transform.get_position(entity)
+end
+
+function on_update(dt)
+    travelled = travelled + speed * dt * direction
+    if travelled >= distance then
+        travelled = distance
+        direction = -1
+    elseif travelled <= 0 then
+        travelled = 0
+        direction = 1
+    end
+    transform.set_position(entity, start_x + travelled, start_y, start_z)
+    transform.set_rotation(entity, 0, direction > 0 and 90 or -90, 0)
+end